@@ -0,0 +1,63 @@
+//! Cost of moving streamed events into the session and out to clients.
+//!
+//! Compares sharing `Arc<Event>` between the stream, the session and the
+//! serializer with the previous approach of cloning the event at each hop.
+//!
+//! Run with `cargo bench --bench event_sharing`.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use google_adk::{sessions::Session, Event};
+use std::{io::sink, sync::Arc};
+
+const EVENTS_PER_TURN: usize = 64;
+
+fn streamed_events() -> Vec<Event> {
+    let text = "The quick brown fox jumps over the lazy dog. ".repeat(200);
+    (0..EVENTS_PER_TURN)
+        .map(|i| Event::text_response("bench_agent", format!("{} #{}", text, i)))
+        .collect()
+}
+
+fn new_session() -> Session {
+    Session::new("bench".to_string(), "user".to_string(), "session".to_string())
+}
+
+fn bench_event_sharing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stream_to_session_and_client");
+    group.throughput(Throughput::Elements(EVENTS_PER_TURN as u64));
+
+    group.bench_function("arc_shared", |b| {
+        b.iter_batched(
+            || (streamed_events(), new_session()),
+            |(events, mut session)| {
+                for event in events {
+                    let event = Arc::new(event);
+                    session.add_event(event.clone());
+                    serde_json::to_writer(sink(), event.as_ref()).unwrap();
+                }
+                black_box(session)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("cloned", |b| {
+        b.iter_batched(
+            || (streamed_events(), new_session()),
+            |(events, mut session)| {
+                for event in events {
+                    session.add_event(event.clone());
+                    let outgoing = event.clone();
+                    serde_json::to_writer(sink(), &outgoing).unwrap();
+                }
+                black_box(session)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_event_sharing);
+criterion_main!(benches);
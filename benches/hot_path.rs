@@ -0,0 +1,170 @@
+//! Baseline benchmarks for the agent hot path: building model requests,
+//! serializing events, appending to sessions and fanning out to WebSockets.
+//!
+//! Run with `cargo bench --bench hot_path`; see "Benchmarks" in
+//! CONTRIBUTING-rust.md for comparing against a saved baseline.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use google_adk::{
+    sessions::{BufferedSessionConfig, BufferedSessionService, InMemorySessionService, SessionService},
+    types::{FunctionCall, ToolConfig},
+    web::websocket::WebSocketMessage,
+    Content, Event, LlmRequest,
+};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::{runtime::Runtime, sync::broadcast};
+
+fn conversation(turns: usize) -> Vec<Content> {
+    (0..turns)
+        .flat_map(|i| {
+            [
+                Content::user_text(format!("Question {} about the weather in Paris this week?", i)),
+                Content::model_text("It will be mostly sunny with a light breeze. ".repeat(8)),
+            ]
+        })
+        .collect()
+}
+
+fn sample_events() -> Vec<Event> {
+    vec![
+        Event::user_input("What's the weather in Paris?", uuid::Uuid::new_v4()),
+        Event::function_call(
+            "weather_agent",
+            FunctionCall {
+                name: "get_weather".to_string(),
+                args: serde_json::json!({"city": "Paris", "days": 3}),
+            },
+        ),
+        Event::function_response("weather_agent", "get_weather", serde_json::json!({"forecast": ["sun", "sun", "cloudy"]})),
+        Event::text_response("weather_agent", "Sunny for the next three days. ".repeat(20)),
+    ]
+}
+
+fn bench_request_building(c: &mut Criterion) {
+    let mut group = c.benchmark_group("request_building");
+    for turns in [1, 10, 50] {
+        let history = conversation(turns);
+        group.bench_with_input(BenchmarkId::from_parameter(turns), &history, |b, history| {
+            b.iter(|| {
+                let request = LlmRequest::new("gemini-2.0-flash")
+                    .add_contents(history.clone())
+                    .add_user_message("And next week?")
+                    .with_temperature(0.2)
+                    .with_tool_config(ToolConfig::auto());
+                black_box(serde_json::to_vec(&request).unwrap())
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_event_serialization(c: &mut Criterion) {
+    let events = sample_events();
+    let mut group = c.benchmark_group("event_serialization");
+    group.throughput(Throughput::Elements(events.len() as u64));
+    group.bench_function("to_json", |b| {
+        b.iter(|| {
+            for event in &events {
+                black_box(serde_json::to_vec(event).unwrap());
+            }
+        })
+    });
+    let encoded: Vec<Vec<u8>> = events.iter().map(|e| serde_json::to_vec(e).unwrap()).collect();
+    group.bench_function("from_json", |b| {
+        b.iter(|| {
+            for bytes in &encoded {
+                black_box(serde_json::from_slice::<Event>(bytes).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn session_service(name: &str) -> Arc<dyn SessionService> {
+    let in_memory = Arc::new(InMemorySessionService::new());
+    match name {
+        "buffered_in_memory" => {
+            let config = BufferedSessionConfig {
+                flush_interval: Duration::from_secs(3600),
+                ..Default::default()
+            };
+            Arc::new(BufferedSessionService::new(in_memory, config))
+        }
+        _ => in_memory,
+    }
+}
+
+fn bench_session_append(c: &mut Criterion) {
+    const EVENTS: usize = 100;
+    let runtime = Runtime::new().unwrap();
+    let event = Arc::new(Event::text_response("agent", "partial chunk of a streamed answer"));
+    let mut group = c.benchmark_group("session_append");
+    group.throughput(Throughput::Elements(EVENTS as u64));
+
+    for name in ["in_memory", "buffered_in_memory"] {
+        group.bench_function(name, |b| {
+            b.to_async(&runtime).iter_batched(
+                || {
+                    let _guard = runtime.enter();
+                    session_service(name)
+                },
+                |service| {
+                    let event = event.clone();
+                    async move {
+                        let (user, session) = ("user".to_string(), "session".to_string());
+                        service.get_or_create_session("bench", &user, &session).await.unwrap();
+                        for _ in 0..EVENTS {
+                            service.append_event(&session, event.clone()).await.unwrap();
+                        }
+                        service.flush().await.unwrap();
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_ws_fanout(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let message = WebSocketMessage::AgentResponse {
+        message: "Sunny for the next three days. ".repeat(20),
+        session_id: "session".to_string(),
+        author: "weather_agent".to_string(),
+        timestamp: chrono::Utc::now(),
+        is_partial: true,
+        metadata: HashMap::new(),
+        parts: Vec::new(),
+        citations: None,
+    };
+    let mut group = c.benchmark_group("ws_fanout");
+    for connections in [1, 16, 128] {
+        group.throughput(Throughput::Elements(connections as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(connections), &connections, |b, &connections| {
+            b.to_async(&runtime).iter(|| {
+                let message = message.clone();
+                async move {
+                    // Each connection serializes the broadcast message, as in the WebSocket handler
+                    let (tx, _) = broadcast::channel(16);
+                    let mut receivers: Vec<_> = (0..connections).map(|_| tx.subscribe()).collect();
+                    tx.send(message).unwrap();
+                    for rx in &mut receivers {
+                        let received = rx.recv().await.unwrap();
+                        black_box(serde_json::to_string(&received).unwrap());
+                    }
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_request_building,
+    bench_event_serialization,
+    bench_session_append,
+    bench_ws_fanout
+);
+criterion_main!(benches);
@@ -41,7 +41,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 3. Create a model instance
     println!("🤖 Creating Model Instance:");
-    let model = match create_model(model_name).await {
+    let _model = match create_model(model_name).await {
         Ok(model) => {
             println!("  ✅ Successfully created model: {}", model.model_name());
             model
@@ -110,7 +110,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         context.invocation_id,
     );
     
-    session_service.append_event(&context.session_id, user_message).await?;
+    session_service.append_event(&context.session_id, user_message.into()).await?;
 
     // Run the agent
     match agent.run_async(context.clone()).await {
@@ -4,11 +4,11 @@ use google_adk::{
     agents::{LlmAgent, base_agent::AgentBuilder},
     sessions::{SessionService, InMemorySessionService},
     tools::google_search,
-    web::{WebServer, ServerConfig, WebServerBuilder},
+    web::{ServerConfig, WebServerBuilder},
 };
 use std::sync::Arc;
 use tokio::signal;
-use tracing::{info, warn};
+use tracing::warn;
 
 #[tokio::main]
 async fn main() -> google_adk::error::Result<()> {
@@ -58,7 +58,7 @@ async fn main() -> google_adk::error::Result<()> {
 
     println!("  📡 Host: {}", config.host);
     println!("  🔌 Port: {}", config.port);
-    println!("  🌐 CORS: {:?}", config.cors_origins);
+    println!("  🌐 CORS: {:?}", config.cors.default.allowed_origins);
     println!("  ⏱️  Timeout: {}s", config.timeout_seconds);
     println!("  📁 Static files: {:?}", config.static_dir);
     println!("  🔌 WebSocket: {}", config.enable_websockets);
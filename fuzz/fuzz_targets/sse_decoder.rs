@@ -0,0 +1,30 @@
+//! Feeds arbitrary provider output to the streaming SSE decoder.
+//!
+//! The first byte picks where the stream is split into two network chunks;
+//! decoding must not panic and must not depend on chunk boundaries.
+
+#![no_main]
+
+use google_adk::models::sse::SseDecoder;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&split, body)) = data.split_first() else {
+        return;
+    };
+    let split = split as usize % (body.len() + 1);
+
+    let mut whole = SseDecoder::new();
+    let mut expected = whole.feed(body);
+    expected.extend(whole.finish());
+
+    let mut chunked = SseDecoder::new();
+    let mut events = chunked.feed(&body[..split]);
+    events.extend(chunked.feed(&body[split..]));
+    events.extend(chunked.finish());
+    assert_eq!(events, expected);
+
+    for event in events {
+        let _ = serde_json::from_str::<serde_json::Value>(&event.data);
+    }
+});
@@ -0,0 +1,48 @@
+/* C API for embedding an ADK runner. Build the library with
+ * `cargo rustc --release --features ffi --lib --crate-type cdylib`.
+ * Generated from src/ffi.rs; regenerate with `cbindgen --config cbindgen.toml`. */
+
+#ifndef ADK_H
+#define ADK_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque runner handle */
+typedef struct AdkRunner AdkRunner;
+
+/* Create a runner from a JSON config:
+ * {"app_name": ..., "agent": {"name": ..., "model": ..., "instruction": ...}}.
+ * Returns NULL on failure; see adk_last_error. */
+AdkRunner *adk_runner_create(const char *config_json);
+
+/* Start a turn; poll its events with adk_runner_poll_event.
+ * Returns 0 on success and -1 on invalid arguments. */
+int adk_runner_run_turn(const AdkRunner *runner,
+                        const char *user_id,
+                        const char *session_id,
+                        const char *message);
+
+/* Wait up to timeout_ms for the next queued message as JSON.
+ * Returns NULL if nothing arrived in time. Each turn ends with
+ * {"done": true} or {"error": "..."}. Free the result with adk_string_free. */
+char *adk_runner_poll_event(const AdkRunner *runner, uint64_t timeout_ms);
+
+/* Message of the last error on the calling thread, or NULL.
+ * Owned by the library and valid until the next failing call. */
+const char *adk_last_error(void);
+
+/* Free a string returned by the library. */
+void adk_string_free(char *value);
+
+/* Free a runner, cancelling any turn still in flight. */
+void adk_runner_free(AdkRunner *runner);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ADK_H */
@@ -1,7 +1,7 @@
 //! Base agent trait and implementations
 
 use crate::{
-    error::{AdkError, Result},
+    error::Result,
     events::Event,
     types::{AgentId, Metadata},
 };
@@ -9,13 +9,19 @@ use async_stream::stream;
 use async_trait::async_trait;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, pin::Pin};
-use tracing::{info, instrument};
+use std::{collections::HashMap, pin::Pin, sync::Arc};
 
-use super::invocation_context::InvocationContext;
+use super::{capabilities::AgentCapabilities, invocation_context::InvocationContext};
 
-/// Stream of events from agent execution
-pub type EventStream = Pin<Box<dyn Stream<Item = Result<Event>> + Send>>;
+/// Event metadata key recording the version of the agent that produced the event
+pub const AGENT_VERSION_METADATA_KEY: &str = "agent_version";
+
+/// Event metadata key recording the configuration hash of the producing agent
+pub const AGENT_CONFIG_HASH_METADATA_KEY: &str = "agent_config_hash";
+
+/// Stream of events from agent execution; events are shared with the session
+/// and serializers rather than cloned
+pub type EventStream = Pin<Box<dyn Stream<Item = Result<Arc<Event>>> + Send>>;
 
 /// Base trait for all agents in the ADK
 #[async_trait]
@@ -38,6 +44,21 @@ pub trait BaseAgent: Send + Sync {
     /// Get the agent's sub-agents
     fn sub_agents(&self) -> &[Box<dyn BaseAgent>];
 
+    /// Get the agent's version, if it is versioned
+    fn version(&self) -> Option<&str> {
+        None
+    }
+
+    /// Get a stable hash of the agent's configuration, if it computes one
+    fn config_hash(&self) -> Option<&str> {
+        None
+    }
+
+    /// Describe what the agent can do: tools, schemas, modalities and sub-agents
+    fn capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities::of(self)
+    }
+
     /// Run the agent asynchronously with text-based conversation
     async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream>;
 
@@ -57,6 +78,77 @@ pub trait BaseAgent: Send + Sync {
     }
 }
 
+/// Shared agents are agents too, so workflow agents can hold their steps
+/// behind an `Arc` and still start them from inside a `'static` event stream
+#[async_trait]
+impl<A: BaseAgent + ?Sized> BaseAgent for Arc<A> {
+    fn id(&self) -> &AgentId {
+        (**self).id()
+    }
+
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn description(&self) -> &str {
+        (**self).description()
+    }
+
+    fn metadata(&self) -> &Metadata {
+        (**self).metadata()
+    }
+
+    fn parent(&self) -> Option<&dyn BaseAgent> {
+        (**self).parent()
+    }
+
+    fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+        (**self).sub_agents()
+    }
+
+    fn version(&self) -> Option<&str> {
+        (**self).version()
+    }
+
+    fn config_hash(&self) -> Option<&str> {
+        (**self).config_hash()
+    }
+
+    fn capabilities(&self) -> AgentCapabilities {
+        (**self).capabilities()
+    }
+
+    async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+        (**self).run_async(ctx).await
+    }
+
+    async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+        (**self).run_live(ctx).await
+    }
+
+    fn validate(&self) -> Result<()> {
+        (**self).validate()
+    }
+
+    fn can_handle(&self, input: &str) -> bool {
+        (**self).can_handle(input)
+    }
+}
+
+/// Record the agent's version and configuration hash in an event's metadata
+pub fn stamp_agent_version(agent: &dyn BaseAgent, event: &mut Event) {
+    if let Some(version) = agent.version() {
+        event
+            .metadata
+            .insert(AGENT_VERSION_METADATA_KEY.to_string(), version.into());
+    }
+    if let Some(hash) = agent.config_hash() {
+        event
+            .metadata
+            .insert(AGENT_CONFIG_HASH_METADATA_KEY.to_string(), hash.into());
+    }
+}
+
 /// Common agent properties
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentProperties {
@@ -71,7 +163,7 @@ pub struct AgentProperties {
 impl AgentProperties {
     pub fn new(name: impl Into<String>) -> Self {
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::utils::new_id(),
             name: name.into(),
             description: String::new(),
             metadata: HashMap::new(),
@@ -95,7 +187,7 @@ impl AgentProperties {
 pub fn events_to_stream(events: Vec<Event>) -> EventStream {
     Box::pin(stream! {
         for event in events {
-            yield Ok(event);
+            yield Ok(Arc::new(event));
         }
     })
 }
@@ -0,0 +1,108 @@
+//! What an agent can do, for clients and other agents to discover
+//!
+//! [`AgentCapabilities`] are generated from the agent object itself (see
+//! [`BaseAgent::capabilities`]), so they never drift from what the agent
+//! actually runs with. The server returns them from
+//! `GET /api/agents/{name}/capabilities`.
+
+use crate::{models::Modality, types::FunctionDeclaration};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::base_agent::BaseAgent;
+
+/// Tools, schemas, modalities and sub-agents of an agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentCapabilities {
+    pub name: String,
+    pub description: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// Declarations of the tools the agent's model may call
+    #[serde(default)]
+    pub tools: Vec<FunctionDeclaration>,
+
+    /// JSON schema of the expected input; free text when unset
+    #[serde(default)]
+    pub input_schema: Option<Value>,
+
+    /// JSON schema the agent's answers conform to; free text when unset
+    #[serde(default)]
+    pub output_schema: Option<Value>,
+
+    pub input_modalities: Vec<Modality>,
+    pub output_modalities: Vec<Modality>,
+
+    /// The agent runs live (bidirectional streaming) sessions
+    pub streaming: bool,
+
+    #[serde(default)]
+    pub sub_agents: Vec<AgentCapabilities>,
+}
+
+impl AgentCapabilities {
+    /// Text in, text out, no tools; sub-agents are described by their own capabilities
+    pub fn of(agent: &(impl BaseAgent + ?Sized)) -> Self {
+        Self {
+            name: agent.name().to_string(),
+            description: agent.description().to_string(),
+            version: agent.version().map(str::to_string),
+            tools: Vec::new(),
+            input_schema: None,
+            output_schema: None,
+            input_modalities: vec![Modality::Text],
+            output_modalities: vec![Modality::Text],
+            streaming: false,
+            sub_agents: agent.sub_agents().iter().map(|sub_agent| sub_agent.capabilities()).collect(),
+        }
+    }
+
+    /// Find an agent in the tree by name, depth first
+    pub fn find(&self, name: &str) -> Option<&AgentCapabilities> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.sub_agents.iter().find_map(|sub_agent| sub_agent.find(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::AgentBuilder, LlmAgent, OutputSchema, SequentialAgent},
+        tools::FunctionTool,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_capabilities_describe_the_agent_tree() {
+        let lookup = FunctionTool::new("lookup_order", "Look up an order", |_| async { Ok(json!({})) });
+        let support = LlmAgent::builder()
+            .name("support")
+            .description("Answers order questions")
+            .model("gemini-2.0-flash")
+            .tool(Arc::new(lookup))
+            .output_schema(
+                OutputSchema::new(json!({ "type": "object", "properties": { "answer": { "type": "string" } } }))
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let pipeline = SequentialAgent::new("pipeline").with_sub_agent(Box::new(support));
+
+        let capabilities = pipeline.capabilities();
+        assert_eq!(capabilities.name, "pipeline");
+        assert!(capabilities.tools.is_empty() && !capabilities.streaming);
+
+        let support = capabilities.find("support").unwrap();
+        assert_eq!(support.description, "Answers order questions");
+        assert_eq!(support.tools[0].name, "lookup_order");
+        assert_eq!(support.output_schema.as_ref().unwrap()["properties"]["answer"]["type"], "string");
+        assert!(support.input_modalities.contains(&Modality::Image));
+        assert!(support.streaming);
+    }
+}
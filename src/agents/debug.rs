@@ -0,0 +1,203 @@
+//! Step-through debugging of agent runs
+//!
+//! With breakpoints enabled in the [`RunConfig`](super::RunConfig), an agent
+//! stops before each model call and/or tool execution, emits a partial
+//! "paused" event describing the pending request and waits until a
+//! [`DebugCommand`] resumes, modifies or aborts it.
+
+use crate::{
+    error::Result,
+    events::{Event, EventBuilder},
+    types::InvocationId,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::oneshot;
+
+/// Metadata key of paused events, holding the [`PausePoint`]
+pub const DEBUG_PAUSE_METADATA_KEY: &str = "debug_pause";
+
+/// Where agents stop during a debug run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Breakpoints {
+    /// Pause before each model call
+    pub before_model: bool,
+
+    /// Pause before each tool execution
+    pub before_tool: bool,
+}
+
+impl Breakpoints {
+    /// Pause before every model call and tool execution
+    pub fn all() -> Self {
+        Self {
+            before_model: true,
+            before_tool: true,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.before_model || self.before_tool
+    }
+}
+
+/// The pending step an agent is paused at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PausePoint {
+    /// Serialized `LlmRequest` about to be sent
+    ModelCall { request: serde_json::Value },
+
+    /// Tool about to run with `args`
+    ToolCall { tool: String, args: serde_json::Value },
+}
+
+/// How a paused agent proceeds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DebugCommand {
+    /// Run the pending step unchanged
+    Continue,
+
+    /// Replace the pending request (model call) or arguments (tool call), then run it
+    Modify { value: serde_json::Value },
+
+    /// End the invocation with an error
+    Abort,
+}
+
+/// A step waiting for the debugger
+#[derive(Debug, Clone, Serialize)]
+pub struct PausedStep {
+    /// Id of the paused event, used to resume the step
+    pub pause_id: String,
+    pub invocation_id: InvocationId,
+    pub agent: String,
+    pub point: PausePoint,
+}
+
+type PausedSteps = HashMap<String, (PausedStep, oneshot::Sender<DebugCommand>)>;
+
+/// Registry of paused steps
+#[derive(Debug, Clone, Default)]
+pub struct Debugger {
+    paused: Arc<Mutex<PausedSteps>>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a pause; emit [`Pause::event`] and then wait on it
+    pub fn pause(&self, agent: &str, invocation_id: InvocationId, point: PausePoint) -> Pause {
+        let mut event = EventBuilder::new(agent, invocation_id).build();
+        // Partial so the pause is streamed to the client but never persisted into the history
+        event.is_partial = true;
+        event
+            .metadata
+            .insert(DEBUG_PAUSE_METADATA_KEY.to_string(), serde_json::to_value(&point).unwrap_or_default());
+
+        let step = PausedStep {
+            pause_id: event.id.clone(),
+            invocation_id,
+            agent: agent.to_string(),
+            point,
+        };
+        let (tx, rx) = oneshot::channel();
+        self.paused.lock().unwrap().insert(event.id.clone(), (step, tx));
+        Pause {
+            debugger: self.clone(),
+            pause_id: event.id.clone(),
+            event: Some(event),
+            commands: rx,
+        }
+    }
+
+    /// Resume the step paused at `pause_id`
+    pub fn resume(&self, pause_id: &str, command: DebugCommand) -> Result<()> {
+        let (_, tx) = self
+            .paused
+            .lock()
+            .unwrap()
+            .remove(pause_id)
+            .ok_or_else(|| crate::adk_error!(ValidationError, "No step is paused at '{}'", pause_id))?;
+        tx.send(command)
+            .map_err(|_| crate::adk_error!(ValidationError, "The run paused at '{}' has ended", pause_id))
+    }
+
+    /// Steps currently waiting for a command
+    pub fn paused(&self) -> Vec<PausedStep> {
+        self.paused.lock().unwrap().values().map(|(step, _)| step.clone()).collect()
+    }
+}
+
+/// A registered pause; dropping it (e.g. when the client disconnects) unregisters it
+pub struct Pause {
+    debugger: Debugger,
+    pause_id: String,
+    event: Option<Event>,
+    commands: oneshot::Receiver<DebugCommand>,
+}
+
+impl Pause {
+    /// The paused event to emit before waiting
+    pub fn event(&mut self) -> Event {
+        self.event.take().expect("paused event already taken")
+    }
+
+    /// Wait for the command resuming this step
+    pub async fn wait(mut self) -> DebugCommand {
+        (&mut self.commands).await.unwrap_or(DebugCommand::Abort)
+    }
+}
+
+impl Drop for Pause {
+    fn drop(&mut self) {
+        self.debugger.paused.lock().unwrap().remove(&self.pause_id);
+    }
+}
+
+static GLOBAL_DEBUGGER: once_cell::sync::Lazy<Debugger> = once_cell::sync::Lazy::new(Debugger::new);
+
+/// Get the global debugger
+pub fn global_debugger() -> &'static Debugger {
+    &GLOBAL_DEBUGGER
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resume_delivers_command_and_unregisters_pause() {
+        let debugger = Debugger::new();
+        let mut pause = debugger.pause(
+            "agent",
+            uuid::Uuid::new_v4(),
+            PausePoint::ToolCall { tool: "search".into(), args: serde_json::json!({"q": "rust"}) },
+        );
+        let event = pause.event();
+        assert!(event.is_partial);
+        assert_eq!(event.metadata[DEBUG_PAUSE_METADATA_KEY]["type"], "tool_call");
+        assert_eq!(debugger.paused().len(), 1);
+
+        let command: DebugCommand = serde_json::from_str(r#"{"action":"modify","value":{"q":"tokio"}}"#).unwrap();
+        debugger.resume(&event.id, command.clone()).unwrap();
+        assert_eq!(pause.wait().await, command);
+        assert!(debugger.paused().is_empty());
+        assert!(debugger.resume(&event.id, DebugCommand::Continue).is_err());
+    }
+
+    #[test]
+    fn test_dropped_pause_is_unregistered() {
+        let debugger = Debugger::new();
+        let pause = debugger.pause("agent", uuid::Uuid::new_v4(), PausePoint::ModelCall { request: serde_json::json!({}) });
+        drop(pause);
+        assert!(debugger.paused().is_empty());
+    }
+}
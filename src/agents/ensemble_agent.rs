@@ -0,0 +1,385 @@
+//! Ensemble of agents with an aggregation step
+//!
+//! Every member answers the user's question independently, then the
+//! aggregator turns their answers into one: by majority vote, by a judge
+//! model picking the best answer, or by a model merging them. Only the final
+//! answer joins the conversation; the members' answers are recorded in its
+//! metadata.
+
+use crate::{
+    agents::{BaseAgent, InvocationContext},
+    error::Result,
+    events::Event,
+    models::{create_model, LlmRequest},
+    types::{AgentId, Metadata},
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{future::join_all, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use tracing::warn;
+
+use super::base_agent::EventStream;
+
+/// Metadata key of the members' answers and the aggregation outcome
+pub const ENSEMBLE_METADATA_KEY: &str = "ensemble";
+
+/// Default instruction of the merge aggregator
+pub const DEFAULT_MERGE_INSTRUCTION: &str = "Combine these answers into a single best answer to the question. \
+Keep what they agree on, resolve disagreements in favor of the best-supported claim, and reply with the answer only.";
+
+/// How an ensemble combines its members' answers
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnsembleAggregator {
+    /// The most common answer, ignoring case and surrounding whitespace; ties go to the earlier member
+    Vote,
+
+    /// A model picks the best answer, which is returned verbatim
+    Judge { model: String },
+
+    /// A model writes a new answer from all of them
+    Merge { model: String, instruction: String },
+}
+
+impl EnsembleAggregator {
+    pub fn judge(model: impl Into<String>) -> Self {
+        Self::Judge { model: model.into() }
+    }
+
+    pub fn merge(model: impl Into<String>) -> Self {
+        Self::Merge {
+            model: model.into(),
+            instruction: DEFAULT_MERGE_INSTRUCTION.to_string(),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Vote => "vote",
+            Self::Judge { .. } => "judge",
+            Self::Merge { .. } => "merge",
+        }
+    }
+}
+
+/// One member's contribution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemberAnswer {
+    pub agent: String,
+
+    #[serde(default)]
+    pub answer: Option<String>,
+
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Agent answering with the aggregate of several agents' answers
+pub struct EnsembleAgent {
+    id: AgentId,
+    name: String,
+    description: String,
+    sub_agents: Vec<Box<dyn BaseAgent>>,
+    metadata: Metadata,
+    aggregator: EnsembleAggregator,
+}
+
+impl EnsembleAgent {
+    pub fn new(name: impl Into<String>, aggregator: EnsembleAggregator) -> Self {
+        Self {
+            id: crate::utils::new_id(),
+            name: name.into(),
+            description: String::new(),
+            sub_agents: Vec::new(),
+            metadata: HashMap::new(),
+            aggregator,
+        }
+    }
+
+    /// Add a member, e.g. the same agent on a different model
+    pub fn with_member(mut self, agent: Box<dyn BaseAgent>) -> Self {
+        self.sub_agents.push(agent);
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+}
+
+/// Final text of a member's run
+async fn final_answer(mut events: EventStream) -> Result<Option<String>> {
+    let mut answer = None;
+    while let Some(event) = events.next().await {
+        let event = event?;
+        if !event.is_partial {
+            if let Some(text) = event.get_text().filter(|text| !text.is_empty()) {
+                answer = Some(text);
+            }
+        }
+    }
+    Ok(answer)
+}
+
+fn normalize(answer: &str) -> String {
+    answer.trim().trim_end_matches('.').trim().to_lowercase()
+}
+
+/// Index of the most common answer in `answers`
+fn vote(answers: &[&str]) -> usize {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for answer in answers {
+        *counts.entry(normalize(answer)).or_default() += 1;
+    }
+    let mut best = 0;
+    for (index, answer) in answers.iter().enumerate() {
+        if counts[&normalize(answer)] > counts[&normalize(answers[best])] {
+            best = index;
+        }
+    }
+    best
+}
+
+fn numbered(answers: &[&str]) -> String {
+    answers
+        .iter()
+        .enumerate()
+        .map(|(index, answer)| format!("Answer {}:\n{}", index + 1, answer))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+async fn generate(model: &str, prompt: String) -> Result<String> {
+    let llm = create_model(model).await?;
+    let response = llm.generate_content(LlmRequest::new(model).add_user_message(prompt)).await?;
+    Ok(response.get_text().unwrap_or_default())
+}
+
+/// Index of the answer a judge model prefers
+async fn judge(model: &str, question: &str, answers: &[&str]) -> Result<usize> {
+    #[derive(Deserialize)]
+    struct Verdict {
+        best: usize,
+    }
+
+    let prompt = format!(
+        "Question:\n{}\n\n{}\n\nWhich answer is the most accurate and complete? \
+         Reply with only a JSON object: {{\"best\": <answer number>, \"reason\": \"<one sentence>\"}}.",
+        question,
+        numbered(answers)
+    );
+    let text = generate(model, prompt).await?;
+    let json = text
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    let verdict: Verdict = serde_json::from_str(json)
+        .map_err(|e| crate::adk_error!(ModelError, "Judge verdict is not the expected JSON object: {}", e))?;
+    if verdict.best == 0 || verdict.best > answers.len() {
+        return Err(crate::adk_error!(ModelError, "Judge picked answer {} of {}", verdict.best, answers.len()));
+    }
+    Ok(verdict.best - 1)
+}
+
+#[async_trait]
+impl BaseAgent for EnsembleAgent {
+    fn id(&self) -> &AgentId {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn parent(&self) -> Option<&dyn BaseAgent> {
+        None
+    }
+
+    fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+        &self.sub_agents
+    }
+
+    async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+        let question = ctx
+            .session_service
+            .get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id)
+            .await?
+            .and_then(|session| {
+                session
+                    .events
+                    .iter()
+                    .rev()
+                    .find(|event| event.author == "user")
+                    .and_then(|event| event.get_text())
+            })
+            .unwrap_or_default();
+
+        // Members run concurrently and their events stay out of the session,
+        // so no member sees another's answer
+        let mut runs = Vec::with_capacity(self.sub_agents.len());
+        for member in &self.sub_agents {
+            let name = member.name().to_string();
+            let run = member.run_async(ctx.clone()).await;
+            runs.push(async move {
+                let answer = match run {
+                    Ok(events) => final_answer(events).await,
+                    Err(e) => Err(e),
+                };
+                match answer {
+                    Ok(answer) => MemberAnswer { agent: name, answer, error: None },
+                    Err(e) => MemberAnswer { agent: name, answer: None, error: Some(e.to_string()) },
+                }
+            });
+        }
+
+        let name = self.name.clone();
+        let aggregator = self.aggregator.clone();
+        Ok(Box::pin(stream! {
+            let members = join_all(runs).await;
+            let answered: Vec<&MemberAnswer> = members.iter().filter(|member| member.answer.is_some()).collect();
+            if answered.is_empty() {
+                yield Err(crate::adk_error!(AgentError, "No ensemble member of '{}' produced an answer", name));
+                return;
+            }
+            let answers: Vec<&str> = answered.iter().filter_map(|member| member.answer.as_deref()).collect();
+
+            let mut selected = None;
+            let answer = match &aggregator {
+                EnsembleAggregator::Vote => {
+                    let best = vote(&answers);
+                    selected = Some(best);
+                    Ok(answers[best].to_string())
+                }
+                EnsembleAggregator::Judge { model } => {
+                    let best = judge(model, &question, &answers).await.unwrap_or_else(|e| {
+                        warn!("Ensemble judge failed, falling back to a vote: {}", e);
+                        vote(&answers)
+                    });
+                    selected = Some(best);
+                    Ok(answers[best].to_string())
+                }
+                EnsembleAggregator::Merge { model, instruction } => {
+                    let prompt = format!("{}\n\nQuestion:\n{}\n\n{}", instruction, question, numbered(&answers));
+                    generate(model, prompt).await
+                }
+            };
+            let answer = match answer {
+                Ok(answer) => answer,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+
+            let mut event = Event::text_response(name.clone(), answer);
+            event.invocation_id = ctx.invocation_id;
+            event.metadata.insert(
+                ENSEMBLE_METADATA_KEY.to_string(),
+                serde_json::json!({
+                    "aggregator": aggregator.kind(),
+                    "members": members,
+                    "selected": selected.map(|index| answered[index].agent.clone()),
+                }),
+            );
+            yield Ok(Arc::new(event));
+        }))
+    }
+
+    async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+        self.run_async(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::base_agent::events_to_stream,
+        sessions::{InMemorySessionService, SessionService},
+        types::SessionState,
+    };
+
+    /// Answers with a fixed text, or fails when it has none
+    struct FixedAgent {
+        id: AgentId,
+        answer: Option<String>,
+        metadata: Metadata,
+    }
+
+    impl FixedAgent {
+        fn boxed(name: &str, answer: Option<&str>) -> Box<dyn BaseAgent> {
+            Box::new(Self { id: name.into(), answer: answer.map(str::to_string), metadata: Metadata::new() })
+        }
+    }
+
+    #[async_trait]
+    impl BaseAgent for FixedAgent {
+        fn id(&self) -> &AgentId {
+            &self.id
+        }
+        fn name(&self) -> &str {
+            &self.id
+        }
+        fn description(&self) -> &str {
+            ""
+        }
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+        fn parent(&self) -> Option<&dyn BaseAgent> {
+            None
+        }
+        fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+            &[]
+        }
+        async fn run_async(&self, _ctx: InvocationContext) -> Result<EventStream> {
+            match &self.answer {
+                Some(answer) => Ok(events_to_stream(vec![Event::text_response(self.id.clone(), answer.clone())])),
+                None => Err(crate::adk_error!(ModelError, "quota exceeded")),
+            }
+        }
+        async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+            self.run_async(ctx).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vote_records_member_answers() {
+        let sessions = Arc::new(InMemorySessionService::new());
+        let session = sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        sessions
+            .append_event(&session.id, Arc::new(Event::user_input("Capital of Australia?", uuid::Uuid::new_v4())))
+            .await
+            .unwrap();
+
+        let agent = EnsembleAgent::new("panel", EnsembleAggregator::Vote)
+            .with_member(FixedAgent::boxed("flash", Some("Sydney")))
+            .with_member(FixedAgent::boxed("pro", Some("Canberra.")))
+            .with_member(FixedAgent::boxed("ultra", Some("canberra")))
+            .with_member(FixedAgent::boxed("offline", None));
+        let ctx = InvocationContext::new("s1".into(), "u1".into(), "app".into(), SessionState::new(), sessions);
+        let events: Vec<_> = agent.run_async(ctx).await.unwrap().collect().await;
+
+        assert_eq!(events.len(), 1);
+        let event = events[0].as_ref().unwrap();
+        assert_eq!(event.author, "panel");
+        assert_eq!(event.get_text().unwrap(), "Canberra.");
+        let ensemble = &event.metadata[ENSEMBLE_METADATA_KEY];
+        assert_eq!(ensemble["aggregator"], "vote");
+        assert_eq!(ensemble["selected"], "pro");
+        assert_eq!(ensemble["members"][0]["answer"], "Sydney");
+        assert!(ensemble["members"][3]["error"].as_str().unwrap().contains("quota exceeded"));
+    }
+}
@@ -0,0 +1,142 @@
+//! Few-shot examples for LLM agents
+//!
+//! Examples are sent to the model as user/model turns between the instruction
+//! and the conversation history, instead of being pasted into the instruction.
+
+use crate::{
+    error::Result,
+    models::embedding::{cosine_similarity, Embedder},
+    types::Content,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// An input and the answer the model should give to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Example {
+    pub input: String,
+    pub output: String,
+}
+
+impl Example {
+    pub fn new(input: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+            output: output.into(),
+        }
+    }
+}
+
+/// Chooses the examples sent with a request
+#[async_trait]
+pub trait ExampleProvider: Send + Sync {
+    /// Examples for the current user message
+    async fn examples(&self, query: &str) -> Result<Vec<Example>>;
+}
+
+/// Picks the examples whose input is most similar to the user message
+pub struct SimilarExamples {
+    embedder: Arc<dyn Embedder>,
+    examples: Vec<Example>,
+    top_k: usize,
+    vectors: OnceCell<Vec<Vec<f32>>>,
+}
+
+impl SimilarExamples {
+    /// Select up to 3 of `examples` per request
+    pub fn new(embedder: Arc<dyn Embedder>, examples: Vec<Example>) -> Self {
+        Self {
+            embedder,
+            examples,
+            top_k: 3,
+            vectors: OnceCell::new(),
+        }
+    }
+
+    /// Maximum number of examples per request
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+}
+
+#[async_trait]
+impl ExampleProvider for SimilarExamples {
+    async fn examples(&self, query: &str) -> Result<Vec<Example>> {
+        if self.examples.len() <= self.top_k {
+            return Ok(self.examples.clone());
+        }
+
+        // Example inputs are embedded once, on first use
+        let vectors = self
+            .vectors
+            .get_or_try_init(|| async {
+                let inputs: Vec<String> = self.examples.iter().map(|e| e.input.clone()).collect();
+                self.embedder.embed(&inputs).await
+            })
+            .await?;
+        let query_vector = self.embedder.embed(&[query.to_string()]).await?.pop().unwrap_or_default();
+
+        let mut scored: Vec<(usize, f32)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(index, vector)| (index, cosine_similarity(vector, &query_vector)))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(scored
+            .into_iter()
+            .take(self.top_k)
+            .map(|(index, _)| self.examples[index].clone())
+            .collect())
+    }
+}
+
+/// Contents sending `examples` as alternating user and model turns
+pub fn example_contents(examples: &[Example]) -> Vec<Content> {
+    examples
+        .iter()
+        .flat_map(|example| [Content::user_text(&example.input), Content::model_text(&example.output)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Embeds texts as keyword indicator vectors
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|text| ["refund", "shipping"].iter().map(|word| text.contains(word) as u8 as f32).collect())
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_similar_examples_pick_closest_inputs() {
+        let provider = SimilarExamples::new(
+            Arc::new(KeywordEmbedder),
+            vec![
+                Example::new("how do I get a refund", "Open Orders > Refund."),
+                Example::new("what are shipping times", "2-5 business days."),
+                Example::new("is shipping free", "Above $50, yes."),
+            ],
+        )
+        .with_top_k(2);
+
+        let selected = provider.examples("shipping to Norway?").await.unwrap();
+        assert_eq!(selected.len(), 2);
+        assert!(selected.iter().all(|example| example.input.contains("shipping")));
+
+        let contents = example_contents(&selected);
+        assert_eq!(contents.len(), 4);
+        assert_eq!(contents[0].role, "user");
+        assert_eq!(contents[1].role, "model");
+    }
+}
@@ -0,0 +1,280 @@
+//! Runtime kill switches for agents and tools
+//!
+//! Operators can disable a tool or an agent, or force an agent onto a
+//! fallback instruction, without redeploying. Flags come from the `[flags]`
+//! section of a config file plus environment overrides, and are re-read
+//! whenever the file changes:
+//!
+//! ```toml
+//! [flags]
+//! disabled_tools = ["send_email"]
+//! disabled_agents = ["refund_agent"]
+//!
+//! [flags.fallback_instructions]
+//! support = "Apologize: account changes are paused. Offer to open a ticket."
+//! ```
+//!
+//! The runner refuses to run disabled agents, and LLM agents hide disabled
+//! tools from the model and refuse to execute them.
+
+use crate::{
+    error::Result,
+    models::profiles::{CONFIG_PATH_ENV_VAR, DEFAULT_CONFIG_FILE},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Environment variable with a comma-separated list of tools to disable
+pub const DISABLED_TOOLS_ENV_VAR: &str = "ADK_DISABLED_TOOLS";
+
+/// Environment variable with a comma-separated list of agents to disable
+pub const DISABLED_AGENTS_ENV_VAR: &str = "ADK_DISABLED_AGENTS";
+
+/// The flags in effect
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlagSet {
+    pub disabled_tools: BTreeSet<String>,
+    pub disabled_agents: BTreeSet<String>,
+    /// Instructions replacing an agent's own, by agent name
+    pub fallback_instructions: BTreeMap<String, String>,
+}
+
+impl FlagSet {
+    /// Add the tools and agents disabled through the environment
+    fn with_env_overrides(mut self) -> Self {
+        let list = |var: &str| {
+            std::env::var(var)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        self.disabled_tools.extend(list(DISABLED_TOOLS_ENV_VAR));
+        self.disabled_agents.extend(list(DISABLED_AGENTS_ENV_VAR));
+        self
+    }
+}
+
+/// On-disk layout of the flags section of `adk.toml`
+#[derive(Debug, Default, Deserialize)]
+struct FlagsFile {
+    #[serde(default)]
+    flags: FlagSet,
+}
+
+/// Shared, reloadable feature flags
+#[derive(Debug, Clone, Default)]
+pub struct FeatureFlags {
+    flags: Arc<RwLock<FlagSet>>,
+    path: Option<PathBuf>,
+}
+
+impl FeatureFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load flags from a config file (plus environment overrides); `reload` re-reads it
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self> {
+        let flags = Self {
+            flags: Arc::default(),
+            path: Some(path.into()),
+        };
+        flags.reload()?;
+        Ok(flags)
+    }
+
+    /// Load flags from `$ADK_CONFIG` or `./adk.toml` if present, else from the environment only
+    pub fn load_default() -> Result<Self> {
+        let path = std::env::var(CONFIG_PATH_ENV_VAR).unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+        if Path::new(&path).exists() {
+            Self::from_file(path)
+        } else {
+            let flags = Self::new();
+            flags.set(FlagSet::default());
+            Ok(flags)
+        }
+    }
+
+    /// Re-read the config file and environment; on error the current flags stay in effect
+    pub fn reload(&self) -> Result<()> {
+        let file = match &self.path {
+            Some(path) => {
+                let file: FlagsFile = config::Config::builder()
+                    .add_source(config::File::from(path.as_path()))
+                    .build()?
+                    .try_deserialize()?;
+                file.flags
+            }
+            None => FlagSet::default(),
+        };
+        self.set(file);
+        Ok(())
+    }
+
+    /// Replace all flags; environment overrides still apply
+    pub fn set(&self, flags: FlagSet) {
+        let flags = flags.with_env_overrides();
+        let mut current = self.flags.write().expect("flags lock poisoned");
+        if *current != flags {
+            info!(
+                "Feature flags updated: {} tools and {} agents disabled, {} fallback instructions",
+                flags.disabled_tools.len(),
+                flags.disabled_agents.len(),
+                flags.fallback_instructions.len()
+            );
+            *current = flags;
+        }
+    }
+
+    pub fn snapshot(&self) -> FlagSet {
+        self.flags.read().expect("flags lock poisoned").clone()
+    }
+
+    fn update(&self, change: impl FnOnce(&mut FlagSet)) {
+        change(&mut self.flags.write().expect("flags lock poisoned"));
+    }
+
+    pub fn disable_tool(&self, name: impl Into<String>) {
+        self.update(|flags| {
+            flags.disabled_tools.insert(name.into());
+        });
+    }
+
+    pub fn enable_tool(&self, name: &str) {
+        self.update(|flags| {
+            flags.disabled_tools.remove(name);
+        });
+    }
+
+    pub fn disable_agent(&self, name: impl Into<String>) {
+        self.update(|flags| {
+            flags.disabled_agents.insert(name.into());
+        });
+    }
+
+    pub fn enable_agent(&self, name: &str) {
+        self.update(|flags| {
+            flags.disabled_agents.remove(name);
+        });
+    }
+
+    /// Make an agent answer with `instruction` instead of its own
+    pub fn set_fallback_instruction(&self, agent: impl Into<String>, instruction: impl Into<String>) {
+        self.update(|flags| {
+            flags.fallback_instructions.insert(agent.into(), instruction.into());
+        });
+    }
+
+    pub fn clear_fallback_instruction(&self, agent: &str) {
+        self.update(|flags| {
+            flags.fallback_instructions.remove(agent);
+        });
+    }
+
+    pub fn is_tool_disabled(&self, name: &str) -> bool {
+        self.flags.read().expect("flags lock poisoned").disabled_tools.contains(name)
+    }
+
+    pub fn is_agent_disabled(&self, name: &str) -> bool {
+        self.flags.read().expect("flags lock poisoned").disabled_agents.contains(name)
+    }
+
+    pub fn fallback_instruction(&self, agent: &str) -> Option<String> {
+        self.flags.read().expect("flags lock poisoned").fallback_instructions.get(agent).cloned()
+    }
+
+    /// Reload whenever the config file changes, checking every `interval` until `cancel` fires
+    pub fn watch(&self, interval: Duration, cancel: CancellationToken) -> Option<JoinHandle<()>> {
+        let path = self.path.clone()?;
+        let flags = self.clone();
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        Some(tokio::spawn(async move {
+            let mut last: Option<SystemTime> = modified(&path);
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                let current = modified(&path);
+                if current == last {
+                    continue;
+                }
+                last = current;
+                if let Err(e) = flags.reload() {
+                    warn!("Failed to reload feature flags from {}: {}", path.display(), e);
+                }
+            }
+        }))
+    }
+}
+
+/// Global flags, loaded from `adk.toml` and the environment on first use
+static GLOBAL_FLAGS: once_cell::sync::Lazy<FeatureFlags> = once_cell::sync::Lazy::new(|| {
+    FeatureFlags::load_default().unwrap_or_else(|e| {
+        warn!("Failed to load feature flags: {}", e);
+        FeatureFlags::new()
+    })
+});
+
+/// Get the global feature flags
+pub fn global_flags() -> &'static FeatureFlags {
+    &GLOBAL_FLAGS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_flags_reload_when_the_file_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("adk.toml");
+        std::fs::write(&path, "[flags]\ndisabled_tools = [\"send_email\"]\n").unwrap();
+
+        let flags = FeatureFlags::from_file(&path).unwrap();
+        assert!(flags.is_tool_disabled("send_email"));
+        assert!(!flags.is_agent_disabled("support"));
+
+        let cancel = CancellationToken::new();
+        let watcher = flags.watch(Duration::from_millis(10), cancel.clone()).unwrap();
+        // Ensure the modification time differs on coarse-grained filesystems
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        std::fs::write(
+            &path,
+            "[flags]\ndisabled_agents = [\"support\"]\n[flags.fallback_instructions]\nhelper = \"Say sorry.\"\n",
+        )
+        .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while !flags.is_agent_disabled("support") && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(flags.is_agent_disabled("support"));
+        assert!(!flags.is_tool_disabled("send_email"));
+        assert_eq!(flags.fallback_instruction("helper").as_deref(), Some("Say sorry."));
+
+        // A broken file keeps the last good flags
+        std::fs::write(&path, "[flags\n").unwrap();
+        assert!(flags.reload().is_err());
+        assert!(flags.is_agent_disabled("support"));
+
+        cancel.cancel();
+        watcher.await.unwrap();
+    }
+}
@@ -0,0 +1,497 @@
+//! Slot-filling form agent
+//!
+//! The agent is configured with typed fields. Each turn it extracts values
+//! for them from the user's message, validates them, and asks for the first
+//! required field still missing or invalid. Progress is recorded in session
+//! state under the form's state key, so the form survives across turns and
+//! restarts. Once every required field is valid the agent emits a result
+//! event carrying the values.
+
+use crate::{
+    agents::{BaseAgent, InvocationContext},
+    error::Result,
+    events::Event,
+    models::{create_model, LlmRequest},
+    types::{AgentId, Metadata},
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, sync::Arc};
+use tracing::warn;
+
+use super::base_agent::EventStream;
+
+/// Metadata key of the filled-in values on the result event
+pub const FORM_RESULT_METADATA_KEY: &str = "form_result";
+
+/// Default message of the result event
+pub const DEFAULT_FORM_COMPLETION_MESSAGE: &str = "Thanks, I have everything I need.";
+
+/// Type of a form field's value
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    Text,
+    Integer { min: Option<i64>, max: Option<i64> },
+    Number,
+    Boolean,
+    /// A `YYYY-MM-DD` date
+    Date,
+    Email,
+    /// One of the given options, matched case-insensitively
+    Choice(Vec<String>),
+}
+
+impl FieldType {
+    fn describe(&self) -> String {
+        match self {
+            Self::Text => "text".to_string(),
+            Self::Integer { .. } => "integer".to_string(),
+            Self::Number => "number".to_string(),
+            Self::Boolean => "true or false".to_string(),
+            Self::Date => "date as YYYY-MM-DD".to_string(),
+            Self::Email => "email address".to_string(),
+            Self::Choice(options) => format!("one of: {}", options.join(", ")),
+        }
+    }
+
+    /// Normalize `value` to this type, or explain why it does not fit
+    fn coerce(&self, value: &Value) -> std::result::Result<Value, String> {
+        let text = match value {
+            Value::String(text) => text.trim().to_string(),
+            other => other.to_string(),
+        };
+        match self {
+            Self::Text if !text.is_empty() => Ok(Value::String(text)),
+            Self::Text => Err("it is empty".to_string()),
+            Self::Integer { min, max } => {
+                let number: i64 = text.parse().map_err(|_| format!("'{}' is not a whole number", text))?;
+                if min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max) {
+                    let range = match (min, max) {
+                        (Some(min), Some(max)) => format!("between {} and {}", min, max),
+                        (Some(min), None) => format!("at least {}", min),
+                        (None, _) => format!("at most {}", max.unwrap_or_default()),
+                    };
+                    return Err(format!("it must be {}", range));
+                }
+                Ok(number.into())
+            }
+            Self::Number => text
+                .parse::<f64>()
+                .map(Value::from)
+                .map_err(|_| format!("'{}' is not a number", text)),
+            Self::Boolean => match text.to_lowercase().as_str() {
+                "true" | "yes" | "y" => Ok(true.into()),
+                "false" | "no" | "n" => Ok(false.into()),
+                _ => Err(format!("'{}' is not yes or no", text)),
+            },
+            Self::Date => chrono::NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+                .map(|date| Value::String(date.to_string()))
+                .map_err(|_| format!("'{}' is not a date like 2024-12-31", text)),
+            Self::Email => {
+                let valid = text
+                    .split_once('@')
+                    .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.') && !text.contains(' '));
+                if valid {
+                    Ok(Value::String(text))
+                } else {
+                    Err(format!("'{}' is not an email address", text))
+                }
+            }
+            Self::Choice(options) => options
+                .iter()
+                .find(|option| option.eq_ignore_ascii_case(&text))
+                .map(|option| Value::String(option.clone()))
+                .ok_or_else(|| format!("it must be one of: {}", options.join(", "))),
+        }
+    }
+}
+
+/// A slot of the form
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormField {
+    pub name: String,
+    pub field_type: FieldType,
+    pub description: String,
+    pub required: bool,
+
+    /// Question asking for the field; derived from the description if unset
+    pub prompt: Option<String>,
+
+    /// Regular expression the value's text must match
+    pub pattern: Option<String>,
+}
+
+impl FormField {
+    pub fn new(name: impl Into<String>, field_type: FieldType) -> Self {
+        let name = name.into();
+        Self {
+            description: name.replace('_', " "),
+            name,
+            field_type,
+            required: true,
+            prompt: None,
+            pattern: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.pattern = Some(pattern.into());
+        self
+    }
+
+    /// Fill the field when the user offers it, but never ask for it
+    pub fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+
+    fn question(&self) -> String {
+        let choices = match &self.field_type {
+            FieldType::Choice(options) => format!(" ({})", options.join(", ")),
+            _ => String::new(),
+        };
+        self.prompt
+            .clone()
+            .unwrap_or_else(|| format!("What is your {}{}?", self.description, choices))
+    }
+
+    /// Normalized value, or why it is invalid
+    pub fn validate(&self, value: &Value) -> std::result::Result<Value, String> {
+        let value = self.field_type.coerce(value)?;
+        if let Some(pattern) = &self.pattern {
+            let text = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+            let regex = regex::Regex::new(pattern).map_err(|e| format!("invalid pattern: {}", e))?;
+            if !regex.is_match(&text) {
+                return Err(format!("'{}' is not in the expected format", text));
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// Finds values for form fields in a user's message
+#[async_trait]
+pub trait SlotExtractor: Send + Sync {
+    /// Values mentioned in `message`, keyed by field name; `pending` is the field the user was asked for
+    async fn extract(&self, fields: &[FormField], pending: Option<&str>, message: &str) -> Result<Map<String, Value>>;
+}
+
+/// Slot extractor backed by a model
+#[derive(Debug, Clone)]
+pub struct LlmSlotExtractor {
+    model: String,
+}
+
+impl LlmSlotExtractor {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self { model: model.into() }
+    }
+}
+
+#[async_trait]
+impl SlotExtractor for LlmSlotExtractor {
+    async fn extract(&self, fields: &[FormField], pending: Option<&str>, message: &str) -> Result<Map<String, Value>> {
+        let described: Vec<String> = fields
+            .iter()
+            .map(|field| format!("- {}: {} ({})", field.name, field.description, field.field_type.describe()))
+            .collect();
+        let pending = pending
+            .map(|field| format!("The user was just asked for {}.\n", field))
+            .unwrap_or_default();
+        let prompt = format!(
+            "Extract form fields from the user's message.\nFields:\n{}\n{}\
+             Reply with only a JSON object mapping the names of the fields the message gives a value for \
+             to that value. Reply with {{}} if it gives none.\n\nMessage:\n{}",
+            described.join("\n"),
+            pending,
+            message
+        );
+
+        let model = create_model(&self.model).await?;
+        let response = model.generate_content(LlmRequest::new(&self.model).add_user_message(prompt)).await?;
+        let text = response.get_text().unwrap_or_default();
+        let json = text
+            .trim()
+            .trim_start_matches("```json")
+            .trim_start_matches("```")
+            .trim_end_matches("```")
+            .trim();
+        serde_json::from_str(json)
+            .map_err(|e| crate::adk_error!(ModelError, "Extracted fields are not a JSON object: {}", e))
+    }
+}
+
+/// Progress of a form, stored in session state
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FormProgress {
+    #[serde(default)]
+    pub values: Map<String, Value>,
+
+    /// Field the user was last asked for
+    #[serde(default)]
+    pub pending: Option<String>,
+
+    #[serde(default)]
+    pub completed: bool,
+}
+
+/// Agent collecting a set of typed fields from the user
+pub struct FormAgent {
+    id: AgentId,
+    name: String,
+    description: String,
+    sub_agents: Vec<Box<dyn BaseAgent>>,
+    metadata: Metadata,
+    fields: Vec<FormField>,
+    extractor: Arc<dyn SlotExtractor>,
+    state_key: String,
+    completion_message: String,
+}
+
+impl FormAgent {
+    pub fn new(name: impl Into<String>, extractor: Arc<dyn SlotExtractor>) -> Self {
+        let name = name.into();
+        Self {
+            id: crate::utils::new_id(),
+            state_key: format!("form:{}", name),
+            name,
+            description: String::new(),
+            sub_agents: Vec::new(),
+            metadata: HashMap::new(),
+            fields: Vec::new(),
+            extractor,
+            completion_message: DEFAULT_FORM_COMPLETION_MESSAGE.to_string(),
+        }
+    }
+
+    pub fn with_field(mut self, field: FormField) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Session state key of the form's progress; `form:<name>` by default
+    pub fn with_state_key(mut self, key: impl Into<String>) -> Self {
+        self.state_key = key.into();
+        self
+    }
+
+    pub fn with_completion_message(mut self, message: impl Into<String>) -> Self {
+        self.completion_message = message.into();
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    pub fn fields(&self) -> &[FormField] {
+        &self.fields
+    }
+}
+
+#[async_trait]
+impl BaseAgent for FormAgent {
+    fn id(&self) -> &AgentId {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn parent(&self) -> Option<&dyn BaseAgent> {
+        None
+    }
+
+    fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+        &self.sub_agents
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !self.fields.iter().any(|field| field.required) {
+            return Err(crate::adk_error!(ValidationError, "Form '{}' has no required fields", self.name));
+        }
+        Ok(())
+    }
+
+    async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+        let session = ctx
+            .session_service
+            .get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id)
+            .await?;
+
+        // The latest progress is the last recorded delta, or the state the session started with
+        let mut progress: FormProgress = session
+            .as_ref()
+            .and_then(|session| {
+                session
+                    .events
+                    .iter()
+                    .rev()
+                    .find_map(|event| event.actions.state_delta.get(&self.state_key))
+                    .or_else(|| session.state.get(&self.state_key))
+            })
+            .or_else(|| ctx.state.get(&self.state_key))
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default();
+        if progress.completed {
+            progress = FormProgress::default();
+        }
+
+        let message = session
+            .as_ref()
+            .and_then(|session| session.events.last())
+            .filter(|event| event.author == "user")
+            .and_then(|event| event.get_text())
+            .filter(|text| !text.trim().is_empty());
+
+        let mut problems = Vec::new();
+        if let Some(message) = message {
+            let extracted = self
+                .extractor
+                .extract(&self.fields, progress.pending.as_deref(), &message)
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("Slot extraction failed for form '{}': {}", self.name, e);
+                    Map::new()
+                });
+            for field in &self.fields {
+                let Some(value) = extracted.get(&field.name).filter(|value| !value.is_null()) else {
+                    continue;
+                };
+                match field.validate(value) {
+                    Ok(value) => {
+                        progress.values.insert(field.name.clone(), value);
+                    }
+                    Err(reason) => {
+                        progress.values.remove(&field.name);
+                        problems.push((field, reason));
+                    }
+                }
+            }
+        }
+
+        let next = problems
+            .first()
+            .map(|(field, _)| *field)
+            .or_else(|| {
+                self.fields
+                    .iter()
+                    .find(|field| field.required && !progress.values.contains_key(&field.name))
+            });
+
+        let mut event = match next {
+            Some(field) => {
+                let question = match problems.first() {
+                    Some((_, reason)) => format!("That {} doesn't work: {}. {}", field.description, reason, field.question()),
+                    None => field.question(),
+                };
+                progress.pending = Some(field.name.clone());
+                Event::text_response(self.name.clone(), question)
+            }
+            None => {
+                progress.pending = None;
+                progress.completed = true;
+                let mut event = Event::text_response(self.name.clone(), self.completion_message.clone());
+                event
+                    .metadata
+                    .insert(FORM_RESULT_METADATA_KEY.to_string(), Value::Object(progress.values.clone()));
+                event
+            }
+        };
+        event.invocation_id = ctx.invocation_id;
+        event
+            .actions
+            .state_delta
+            .insert(self.state_key.clone(), serde_json::to_value(&progress)?);
+
+        Ok(Box::pin(futures::stream::once(async move { Ok(Arc::new(event)) })))
+    }
+
+    async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+        self.run_async(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{runners::Runner, sessions::InMemorySessionService, types::Content};
+    use futures::StreamExt;
+
+    /// Reads `field=value` pairs; a bare message answers the pending field
+    struct PairExtractor;
+
+    #[async_trait]
+    impl SlotExtractor for PairExtractor {
+        async fn extract(&self, _fields: &[FormField], pending: Option<&str>, message: &str) -> Result<Map<String, Value>> {
+            let mut values = Map::new();
+            for word in message.split_whitespace() {
+                match (word.split_once('='), pending) {
+                    (Some((name, value)), _) => values.insert(name.to_string(), value.into()),
+                    (None, Some(pending)) => values.insert(pending.to_string(), word.into()),
+                    (None, None) => None,
+                };
+            }
+            Ok(values)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_asks_until_slots_are_filled_and_valid() {
+        let form = FormAgent::new("booking", Arc::new(PairExtractor))
+            .with_field(FormField::new("name", FieldType::Text))
+            .with_field(FormField::new("party_size", FieldType::Integer { min: Some(1), max: Some(8) }))
+            .with_field(FormField::new("seating", FieldType::Choice(vec!["Indoor".into(), "Outdoor".into()])).optional());
+        let runner = Runner::new("app", Arc::new(form), Arc::new(InMemorySessionService::new()));
+
+        let turn = |message: &'static str| {
+            let runner = &runner;
+            async move {
+                let events: Vec<_> = runner
+                    .run_async("u1".into(), "s1".into(), Content::user_text(message))
+                    .await
+                    .unwrap()
+                    .collect()
+                    .await;
+                events.into_iter().next().unwrap().unwrap()
+            }
+        };
+
+        assert_eq!(turn("hello").await.get_text().unwrap(), "What is your name?");
+        let event = turn("name=Ada party_size=12 seating=outdoor").await;
+        assert_eq!(
+            event.get_text().unwrap(),
+            "That party size doesn't work: it must be between 1 and 8. What is your party size?"
+        );
+        let progress: FormProgress = serde_json::from_value(event.actions.state_delta["form:booking"].clone()).unwrap();
+        assert_eq!(progress.values["name"], "Ada");
+        assert_eq!(progress.pending.as_deref(), Some("party_size"));
+
+        let event = turn("4").await;
+        assert_eq!(event.get_text().unwrap(), DEFAULT_FORM_COMPLETION_MESSAGE);
+        let result = &event.metadata[FORM_RESULT_METADATA_KEY];
+        assert_eq!(result["party_size"], 4);
+        assert_eq!(result["seating"], "Outdoor");
+    }
+}
@@ -0,0 +1,220 @@
+//! Strategies for selecting which conversation history to send to the model
+
+use crate::{
+    error::Result,
+    models::embedding::{cosine_similarity, Embedder},
+    types::{Content, ContentPart},
+};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tracing::debug;
+
+/// Maximum number of cached turn embeddings before the cache is reset
+const EMBEDDING_CACHE_CAPACITY: usize = 10_000;
+
+/// Selects the history contents included in a model request
+#[async_trait]
+pub trait HistoryStrategy: Send + Sync {
+    /// Reduce the session history (oldest first, ending with the new user message)
+    async fn select(&self, history: Vec<Content>) -> Result<Vec<Content>>;
+}
+
+/// Send the full history (the default)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FullHistory;
+
+#[async_trait]
+impl HistoryStrategy for FullHistory {
+    async fn select(&self, history: Vec<Content>) -> Result<Vec<Content>> {
+        Ok(history)
+    }
+}
+
+/// Send the last N turns plus older turns semantically relevant to the new message.
+///
+/// A turn starts at a user text message and includes the model replies and
+/// function call/response pairs that follow it, so tool exchanges are never split.
+pub struct RelevantHistory {
+    embedder: Arc<dyn Embedder>,
+    recent_turns: usize,
+    max_relevant_turns: usize,
+    min_similarity: f32,
+    cache: Mutex<HashMap<String, Vec<f32>>>,
+}
+
+impl RelevantHistory {
+    /// Keep the last 4 turns plus up to 4 relevant older turns
+    pub fn new(embedder: Arc<dyn Embedder>) -> Self {
+        Self {
+            embedder,
+            recent_turns: 4,
+            max_relevant_turns: 4,
+            min_similarity: 0.5,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Number of most recent turns always included
+    pub fn with_recent_turns(mut self, turns: usize) -> Self {
+        self.recent_turns = turns;
+        self
+    }
+
+    /// Maximum number of older turns selected by relevance
+    pub fn with_max_relevant_turns(mut self, turns: usize) -> Self {
+        self.max_relevant_turns = turns;
+        self
+    }
+
+    /// Minimum cosine similarity for an older turn to be included
+    pub fn with_min_similarity(mut self, similarity: f32) -> Self {
+        self.min_similarity = similarity;
+        self
+    }
+
+    /// Embed texts, reusing cached vectors for turns seen in earlier requests
+    async fn embed_cached(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let missing: Vec<String> = {
+            let cache = self.cache.lock().expect("history cache poisoned");
+            texts.iter().filter(|t| !cache.contains_key(*t)).cloned().collect()
+        };
+
+        if !missing.is_empty() {
+            let vectors = self.embedder.embed(&missing).await?;
+            let mut cache = self.cache.lock().expect("history cache poisoned");
+            if cache.len() + missing.len() > EMBEDDING_CACHE_CAPACITY {
+                cache.clear();
+            }
+            cache.extend(missing.into_iter().zip(vectors));
+        }
+
+        let cache = self.cache.lock().expect("history cache poisoned");
+        Ok(texts.iter().map(|t| cache.get(t).cloned().unwrap_or_default()).collect())
+    }
+}
+
+fn is_user_text(content: &Content) -> bool {
+    content.role == "user" && content.parts.iter().any(|part| matches!(part, ContentPart::Text { .. }))
+}
+
+/// Group contents into turns, each starting at a user text message
+fn split_turns(history: Vec<Content>) -> Vec<Vec<Content>> {
+    let mut turns: Vec<Vec<Content>> = Vec::new();
+    for content in history {
+        match turns.last_mut() {
+            Some(turn) if !is_user_text(&content) => turn.push(content),
+            _ => turns.push(vec![content]),
+        }
+    }
+    turns
+}
+
+fn turn_text(turn: &[Content]) -> String {
+    turn.iter()
+        .map(|content| content.get_text())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[async_trait]
+impl HistoryStrategy for RelevantHistory {
+    async fn select(&self, history: Vec<Content>) -> Result<Vec<Content>> {
+        let mut turns = split_turns(history);
+        // The final turn holds the new message and is always kept
+        let keep_recent = self.recent_turns.max(1);
+        if turns.len() <= keep_recent {
+            return Ok(turns.into_iter().flatten().collect());
+        }
+
+        let recent = turns.split_off(turns.len() - keep_recent);
+        let query = recent
+            .last()
+            .and_then(|turn| turn.first())
+            .map(Content::get_text)
+            .unwrap_or_default();
+
+        let mut texts: Vec<String> = turns.iter().map(|turn| turn_text(turn)).collect();
+        texts.push(query);
+        let mut vectors = self.embed_cached(&texts).await?;
+        let query_vector = vectors.pop().unwrap_or_default();
+
+        let mut scored: Vec<(usize, f32)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(index, vector)| (index, cosine_similarity(vector, &query_vector)))
+            .filter(|(_, score)| *score >= self.min_similarity)
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(self.max_relevant_turns);
+
+        // Restore chronological order of the selected turns
+        let mut selected: Vec<usize> = scored.into_iter().map(|(index, _)| index).collect();
+        selected.sort_unstable();
+        debug!("Selected {} of {} older turns by relevance", selected.len(), turns.len());
+
+        let mut older: Vec<Option<Vec<Content>>> = turns.into_iter().map(Some).collect();
+        Ok(selected
+            .into_iter()
+            .filter_map(|index| older[index].take())
+            .chain(recent)
+            .flatten()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Embeds texts as keyword indicator vectors
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    ["weather", "recipe", "football"]
+                        .iter()
+                        .map(|word| text.contains(word) as u8 as f32)
+                        .collect()
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relevant_history_keeps_matching_and_recent_turns() {
+        let history = vec![
+            Content::user_text("what's the weather in Oslo"),
+            Content::model_text("the weather is rainy"),
+            Content::user_text("give me a recipe for soup"),
+            Content::model_text("here is a soup recipe"),
+            Content::user_text("who won the football match"),
+            Content::model_text("the home team"),
+            Content::user_text("and the weather tomorrow?"),
+        ];
+
+        let strategy = RelevantHistory::new(Arc::new(KeywordEmbedder))
+            .with_recent_turns(2)
+            .with_max_relevant_turns(1);
+        let selected = strategy.select(history).await.unwrap();
+        let texts: Vec<String> = selected.iter().map(Content::get_text).collect();
+
+        assert_eq!(
+            texts,
+            vec![
+                "what's the weather in Oslo",
+                "the weather is rainy",
+                "who won the football match",
+                "the home team",
+                "and the weather tomorrow?",
+            ]
+        );
+    }
+}
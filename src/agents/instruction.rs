@@ -0,0 +1,141 @@
+//! Instructions assembled at run time
+
+use crate::{
+    agents::{InvocationContext, RunConfig},
+    error::Result,
+    types::{InvocationId, SessionId, SessionState, UserId},
+};
+use async_trait::async_trait;
+use std::{future::Future, sync::Arc, time::Duration};
+use tracing::warn;
+
+/// How long an instruction provider may take before the static instruction is used
+pub const DEFAULT_INSTRUCTION_PROVIDER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Read-only view of an invocation handed to instruction providers
+#[derive(Debug, Clone)]
+pub struct ReadonlyContext {
+    invocation_id: InvocationId,
+    session_id: SessionId,
+    user_id: UserId,
+    app_name: String,
+    state: SessionState,
+    run_config: RunConfig,
+}
+
+impl ReadonlyContext {
+    pub fn invocation_id(&self) -> InvocationId {
+        self.invocation_id
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    pub fn app_name(&self) -> &str {
+        &self.app_name
+    }
+
+    /// Session state at the start of the invocation
+    pub fn state(&self) -> &SessionState {
+        &self.state
+    }
+
+    pub fn get_state_value(&self, key: &str) -> Option<&serde_json::Value> {
+        self.state.get(key)
+    }
+
+    pub fn run_config(&self) -> &RunConfig {
+        &self.run_config
+    }
+}
+
+impl From<&InvocationContext> for ReadonlyContext {
+    fn from(ctx: &InvocationContext) -> Self {
+        Self {
+            invocation_id: ctx.invocation_id,
+            session_id: ctx.session_id.clone(),
+            user_id: ctx.user_id.clone(),
+            app_name: ctx.app_name.clone(),
+            state: ctx.state.clone(),
+            run_config: ctx.run_config.clone(),
+        }
+    }
+}
+
+/// Builds an agent's instruction for each invocation (from a database, feature flags, user profiles...)
+#[async_trait]
+pub trait InstructionProvider: Send + Sync {
+    async fn instruction(&self, ctx: ReadonlyContext) -> Result<String>;
+}
+
+#[async_trait]
+impl<F, Fut> InstructionProvider for F
+where
+    F: Fn(ReadonlyContext) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String>> + Send,
+{
+    async fn instruction(&self, ctx: ReadonlyContext) -> Result<String> {
+        self(ctx).await
+    }
+}
+
+/// Ask `provider` for the instruction, falling back to `fallback` on error or timeout
+pub(crate) async fn resolve_instruction(
+    provider: &Arc<dyn InstructionProvider>,
+    ctx: &InvocationContext,
+    timeout: Duration,
+    fallback: &str,
+) -> String {
+    match tokio::time::timeout(timeout, provider.instruction(ReadonlyContext::from(ctx))).await {
+        Ok(Ok(instruction)) => instruction,
+        Ok(Err(e)) => {
+            warn!("Instruction provider failed, using the static instruction: {}", e);
+            fallback.to_string()
+        }
+        Err(_) => {
+            warn!("Instruction provider timed out after {:?}, using the static instruction", timeout);
+            fallback.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sessions::InMemorySessionService;
+
+    fn context() -> InvocationContext {
+        let mut state = SessionState::new();
+        state.insert("tier".into(), serde_json::json!("gold"));
+        InvocationContext::new("s1".into(), "u1".into(), "app".into(), state, Arc::new(InMemorySessionService::new()))
+    }
+
+    #[tokio::test]
+    async fn test_provider_reads_context_and_falls_back() {
+        let ctx = context();
+        let timeout = Duration::from_millis(50);
+
+        let personalized: Arc<dyn InstructionProvider> = Arc::new(|ctx: ReadonlyContext| async move {
+            Ok(format!("Help {} ({} tier).", ctx.user_id(), ctx.get_state_value("tier").unwrap()))
+        });
+        assert_eq!(
+            resolve_instruction(&personalized, &ctx, timeout, "static").await,
+            "Help u1 (\"gold\" tier)."
+        );
+
+        let failing: Arc<dyn InstructionProvider> =
+            Arc::new(|_| async { Err(crate::adk_error!(NetworkError, "profile service down")) });
+        assert_eq!(resolve_instruction(&failing, &ctx, timeout, "static").await, "static");
+
+        let slow: Arc<dyn InstructionProvider> = Arc::new(|_| async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok("late".to_string())
+        });
+        assert_eq!(resolve_instruction(&slow, &ctx, timeout, "static").await, "static");
+    }
+}
@@ -1,14 +1,22 @@
 //! Invocation context for agent execution
 
 use crate::{
+    agents::{base_agent::EventStream, LiveRequestQueue, ResponseValidation, RunConfig},
     error::Result,
+    experiments::ExperimentAssignment,
+    models::CostTracker,
     sessions::SessionService,
     types::{InvocationId, SessionId, SessionState, StateDelta, UserId},
+    utils::TraceCollector,
 };
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use uuid::Uuid;
+use futures::Stream;
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tracing::Span;
 
 /// Context for agent invocation containing session and execution state
 #[derive(Clone)]
@@ -42,6 +50,28 @@ pub struct InvocationContext {
     
     /// Whether this is a live (audio/video) session
     pub is_live: bool,
+
+    /// Input of a live run
+    pub live_request_queue: Option<LiveRequestQueue>,
+
+    /// Run configuration (seed, deterministic mode)
+    pub run_config: RunConfig,
+
+    /// Spans of the invocation, shared with sub-agent contexts
+    pub trace: TraceCollector,
+
+    /// Tracing span carrying the invocation and session ids; child of the
+    /// span current at creation (e.g. the web request span with its request id)
+    pub span: Span,
+
+    /// Experiment variant the session was assigned, shared with sub-agent contexts
+    pub experiment: Option<ExperimentAssignment>,
+
+    /// Checks of final model answers, shared with sub-agent contexts
+    pub response_validation: Option<ResponseValidation>,
+
+    /// Running cost of the invocation's model calls, shared with sub-agent contexts
+    pub cost: CostTracker,
 }
 
 impl InvocationContext {
@@ -53,17 +83,31 @@ impl InvocationContext {
         state: SessionState,
         session_service: Arc<dyn SessionService>,
     ) -> Self {
+        let invocation_id = crate::utils::new_uuid();
+        let span = tracing::info_span!(
+            "invocation",
+            invocation_id = %invocation_id,
+            session_id = %session_id,
+            app_name = %app_name,
+        );
         Self {
-            invocation_id: Uuid::new_v4(),
+            invocation_id,
+            span,
             session_id,
             user_id,
             app_name,
             state,
             session_service,
             end_invocation: false,
-            started_at: Utc::now(),
+            started_at: crate::types::now(),
             timeout_seconds: None,
             is_live: false,
+            live_request_queue: None,
+            run_config: RunConfig::default(),
+            trace: TraceCollector::new(),
+            experiment: None,
+            response_validation: None,
+            cost: CostTracker::new(),
         }
     }
 
@@ -87,7 +131,7 @@ impl InvocationContext {
     /// Check if the invocation has timed out
     pub fn is_timed_out(&self) -> bool {
         if let Some(timeout) = self.timeout_seconds {
-            let elapsed = Utc::now().signed_duration_since(self.started_at);
+            let elapsed = crate::types::now().signed_duration_since(self.started_at);
             elapsed.num_seconds() as u64 >= timeout
         } else {
             false
@@ -101,8 +145,17 @@ impl InvocationContext {
 
     /// Create a child context for sub-agent execution
     pub fn create_child_context(&self, child_app_name: String) -> Self {
+        let invocation_id = crate::utils::new_uuid();
+        let span = tracing::info_span!(
+            parent: &self.span,
+            "invocation",
+            invocation_id = %invocation_id,
+            session_id = %self.session_id,
+            app_name = %child_app_name,
+        );
         Self {
-            invocation_id: Uuid::new_v4(),
+            invocation_id,
+            span,
             session_id: self.session_id.clone(),
             user_id: self.user_id.clone(),
             app_name: child_app_name,
@@ -112,6 +165,12 @@ impl InvocationContext {
             started_at: self.started_at,
             timeout_seconds: self.timeout_seconds,
             is_live: self.is_live,
+            live_request_queue: self.live_request_queue.clone(),
+            run_config: self.run_config.clone(),
+            trace: self.trace.clone(),
+            experiment: self.experiment.clone(),
+            response_validation: self.response_validation.clone(),
+            cost: self.cost.clone(),
         }
     }
 
@@ -123,6 +182,26 @@ impl InvocationContext {
     }
 }
 
+/// Enter `span` whenever `events` is polled, so agent, model and tool logs carry the invocation fields
+pub fn instrument_events(events: EventStream, span: Span) -> EventStream {
+    Box::pin(InstrumentedEvents { events, span })
+}
+
+struct InstrumentedEvents {
+    events: EventStream,
+    span: Span,
+}
+
+impl Stream for InstrumentedEvents {
+    type Item = <EventStream as Stream>::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let _entered = this.span.enter();
+        this.events.as_mut().poll_next(cx)
+    }
+}
+
 /// Builder for creating invocation contexts
 pub struct InvocationContextBuilder {
     session_id: Option<SessionId>,
@@ -132,6 +211,8 @@ pub struct InvocationContextBuilder {
     session_service: Option<Arc<dyn SessionService>>,
     timeout_seconds: Option<u64>,
     is_live: bool,
+    live_request_queue: Option<LiveRequestQueue>,
+    run_config: RunConfig,
 }
 
 impl InvocationContextBuilder {
@@ -144,6 +225,8 @@ impl InvocationContextBuilder {
             session_service: None,
             timeout_seconds: None,
             is_live: false,
+            live_request_queue: None,
+            run_config: RunConfig::default(),
         }
     }
 
@@ -182,6 +265,16 @@ impl InvocationContextBuilder {
         self
     }
 
+    pub fn live_request_queue(mut self, queue: LiveRequestQueue) -> Self {
+        self.live_request_queue = Some(queue);
+        self
+    }
+
+    pub fn run_config(mut self, run_config: RunConfig) -> Self {
+        self.run_config = run_config;
+        self
+    }
+
     pub fn build(self) -> Result<InvocationContext> {
         let session_id = self.session_id.ok_or_else(|| {
             crate::adk_error!(ValidationError, "session_id is required")
@@ -205,6 +298,8 @@ impl InvocationContextBuilder {
         );
         ctx.timeout_seconds = self.timeout_seconds;
         ctx.is_live = self.is_live;
+        ctx.live_request_queue = self.live_request_queue;
+        ctx.run_config = self.run_config;
 
         Ok(ctx)
     }
@@ -215,3 +310,60 @@ impl Default for InvocationContextBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::Event, sessions::InMemorySessionService};
+    use futures::StreamExt;
+    use std::{io::Write, sync::Mutex};
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_logs_inside_agent_streams_carry_invocation_fields() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_writer(move || writer.clone())
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let request = tracing::info_span!("request", request_id = "req-1");
+            let ctx = request.in_scope(|| {
+                InvocationContext::new(
+                    "s1".into(),
+                    "u1".into(),
+                    "app".into(),
+                    SessionState::new(),
+                    Arc::new(InMemorySessionService::new()),
+                )
+            });
+            let events: EventStream = Box::pin(futures::stream::once(async {
+                tracing::info!("calling model");
+                Ok(Arc::new(Event::text_response("agent", "hi")))
+            }));
+            let mut events = instrument_events(events, ctx.span.clone());
+            futures::executor::block_on(events.next());
+        });
+
+        let logs = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(logs.lines().next().unwrap()).unwrap();
+        assert_eq!(line["span"]["session_id"], "s1");
+        assert!(line["span"]["invocation_id"].is_string());
+        assert_eq!(line["spans"][0]["request_id"], "req-1");
+    }
+}
@@ -0,0 +1,73 @@
+//! Response-language policy for LLM agents
+
+use serde::{Deserialize, Serialize};
+
+/// Metadata key recording the language detected in the user's message (ISO 639-3 code)
+pub const DETECTED_LANGUAGE_METADATA_KEY: &str = "detected_language";
+
+/// Detections below this confidence are ignored
+pub const MIN_LANGUAGE_CONFIDENCE: f64 = 0.5;
+
+/// Which language an agent answers in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "language", rename_all = "snake_case")]
+pub enum LanguagePolicy {
+    /// Answer in the language the user wrote in, when it can be detected reliably
+    MatchUser,
+
+    /// Always answer in this language, e.g. `French` or `pt-BR`
+    Fixed(String),
+}
+
+impl LanguagePolicy {
+    /// Instruction appended to the agent's instruction, if the policy applies
+    pub fn instruction(&self, detected: Option<&DetectedLanguage>) -> Option<String> {
+        let language = match self {
+            Self::MatchUser => detected?.name.clone(),
+            Self::Fixed(language) => language.clone(),
+        };
+        Some(format!(
+            "Always respond in {}, regardless of the language of the instructions or examples.",
+            language
+        ))
+    }
+}
+
+/// Language of a piece of text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectedLanguage {
+    /// ISO 639-3 code, e.g. `fra`
+    pub code: String,
+
+    /// English name, e.g. `French`
+    pub name: String,
+    pub confidence: f64,
+}
+
+/// Detect the language of `text`, if the detection is confident enough
+pub fn detect_language(text: &str) -> Option<DetectedLanguage> {
+    let info = whatlang::detect(text).filter(|info| info.confidence() >= MIN_LANGUAGE_CONFIDENCE)?;
+    Some(DetectedLanguage {
+        code: info.lang().code().to_string(),
+        name: info.lang().eng_name().to_string(),
+        confidence: info.confidence(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_instructions_follow_detected_language() {
+        let detected = detect_language("Bonjour, pourriez-vous m'aider à réserver une chambre pour ce soir ?").unwrap();
+        assert_eq!(detected.code, "fra");
+        assert_eq!(
+            LanguagePolicy::MatchUser.instruction(Some(&detected)).unwrap(),
+            "Always respond in French, regardless of the language of the instructions or examples."
+        );
+        assert!(LanguagePolicy::MatchUser.instruction(None).is_none());
+        assert!(LanguagePolicy::Fixed("German".into()).instruction(None).unwrap().contains("German"));
+        assert!(detect_language("ok").is_none());
+    }
+}
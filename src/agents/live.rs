@@ -0,0 +1,355 @@
+//! Live (realtime audio/video) runs
+//!
+//! Clients push user input into a [`LiveRequestQueue`] while the agent
+//! streams the model's responses back as events. The loop in
+//! [`run_live_connection`] forwards queued input to the model's live
+//! connection and turns its responses, including transcriptions of both
+//! sides' audio, into events. With a [`VadConfig`] the loop also detects the
+//! user's speech in raw PCM audio and lets it interrupt the model (barge-in).
+
+use crate::{
+    agents::{
+        base_agent::EventStream,
+        vad::{pcm_sample_rate, ActivityDetection, VadConfig, VoiceActivity, VoiceActivityDetector},
+    },
+    error::Result,
+    events::{Event, TranscriptAssembler, TranscriptionSource},
+    models::{LlmConnection, LlmResponse},
+    types::{Blob, Content},
+};
+use async_stream::stream;
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Input sent to a live run
+#[derive(Debug, Clone)]
+pub enum LiveRequest {
+    /// A complete message, e.g. typed text
+    Content(Content),
+
+    /// A chunk of streamed audio or video
+    Realtime(Blob),
+
+    /// The user started speaking (push-to-talk)
+    ActivityStart,
+
+    /// The user stopped speaking (push-to-talk)
+    ActivityEnd,
+
+    /// End the live run
+    Close,
+}
+
+/// Queue of input for a live run; clones feed the same run
+#[derive(Debug, Clone)]
+pub struct LiveRequestQueue {
+    sender: UnboundedSender<LiveRequest>,
+    receiver: Arc<Mutex<Option<UnboundedReceiver<LiveRequest>>>>,
+}
+
+impl Default for LiveRequestQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveRequestQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            sender,
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+        }
+    }
+
+    /// Queue a request; ignored once the run has ended
+    pub fn send(&self, request: LiveRequest) {
+        let _ = self.sender.send(request);
+    }
+
+    pub fn send_content(&self, content: Content) {
+        self.send(LiveRequest::Content(content));
+    }
+
+    pub fn send_realtime(&self, blob: Blob) {
+        self.send(LiveRequest::Realtime(blob));
+    }
+
+    /// Signal the start of user speech when detection is push-to-talk
+    pub fn send_activity_start(&self) {
+        self.send(LiveRequest::ActivityStart);
+    }
+
+    /// Signal the end of user speech when detection is push-to-talk
+    pub fn send_activity_end(&self) {
+        self.send(LiveRequest::ActivityEnd);
+    }
+
+    pub fn close(&self) {
+        self.send(LiveRequest::Close);
+    }
+
+    /// Receiving end of the queue; only one run may consume it
+    pub fn take_receiver(&self) -> Result<UnboundedReceiver<LiveRequest>> {
+        self.receiver
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| crate::adk_error!(AgentError, "Live request queue is already consumed by another run"))
+    }
+}
+
+/// Events of one live response: transcriptions, then content, then the
+/// final transcriptions of utterances the response's turn completed
+pub fn live_events(author: &str, response: LlmResponse, transcripts: &mut TranscriptAssembler) -> Vec<Event> {
+    let mut events: Vec<Event> = response
+        .transcriptions
+        .into_iter()
+        .map(|transcription| Event::transcription(author, transcripts.observe(transcription)))
+        .collect();
+    if let Some(content) = response.content.filter(|content| !content.parts.is_empty()) {
+        let mut event = Event::content_response(author, content).with_citations(response.citations);
+        event.is_partial = response.is_partial;
+        events.push(event);
+    }
+    if !response.is_partial {
+        events.extend(
+            transcripts
+                .finish_turn()
+                .into_iter()
+                .map(|transcription| Event::transcription(author, transcription)),
+        );
+    }
+    events
+}
+
+/// Step of the live loop; short-lived, so its size does not matter
+#[allow(clippy::large_enum_variant)]
+enum LiveStep {
+    Request(Option<LiveRequest>),
+    Response(Result<Option<LlmResponse>>),
+}
+
+/// Forward `requests` to `connection` and stream its responses as events of `author`
+/// until the queue closes or the connection ends.
+///
+/// When the user starts speaking while the model responds and `vad` allows
+/// barge-in (the default without a config), the loop emits an interruption
+/// event and drops the rest of the interrupted turn's output.
+pub fn run_live_connection(
+    author: String,
+    mut connection: Box<dyn LlmConnection>,
+    mut requests: UnboundedReceiver<LiveRequest>,
+    vad: Option<VadConfig>,
+) -> EventStream {
+    Box::pin(stream! {
+        let mut transcripts = TranscriptAssembler::new(Instant::now());
+        let barge_in = vad.as_ref().is_none_or(|vad| vad.barge_in);
+        let mut detector = vad
+            .filter(|vad| vad.mode == ActivityDetection::Automatic)
+            .map(VoiceActivityDetector::new);
+        // Whether the model is streaming a turn, and whether the user cut that turn off
+        let mut responding = false;
+        let mut interrupted = false;
+        loop {
+            let step = tokio::select! {
+                request = requests.recv() => LiveStep::Request(request),
+                response = connection.receive() => LiveStep::Response(response),
+            };
+            let (activity_start, audio, activity_end) = match step {
+                LiveStep::Request(Some(LiveRequest::Content(content))) => {
+                    if let Err(e) = connection.send_message(content).await {
+                        yield Err(e);
+                        break;
+                    }
+                    continue;
+                }
+                LiveStep::Request(Some(LiveRequest::Realtime(blob))) => {
+                    let activity = match (&mut detector, pcm_sample_rate(&blob.mime_type)) {
+                        (Some(detector), Some(sample_rate)) => detector.process(&blob.data, sample_rate),
+                        _ => None,
+                    };
+                    (
+                        activity == Some(VoiceActivity::SpeechStarted),
+                        Some(blob),
+                        activity == Some(VoiceActivity::SpeechEnded),
+                    )
+                }
+                LiveStep::Request(Some(LiveRequest::ActivityStart)) => (true, None, false),
+                LiveStep::Request(Some(LiveRequest::ActivityEnd)) => (false, None, true),
+                LiveStep::Request(Some(LiveRequest::Close) | None) => break,
+                LiveStep::Response(Ok(Some(mut response))) => {
+                    if interrupted {
+                        response.content = None;
+                        response.transcriptions.retain(|transcription| transcription.source == TranscriptionSource::User);
+                        interrupted = response.is_partial;
+                    } else {
+                        responding = response.is_partial;
+                    }
+                    for event in live_events(&author, response, &mut transcripts) {
+                        yield Ok(Arc::new(event));
+                    }
+                    continue;
+                }
+                LiveStep::Response(Ok(None)) => break,
+                LiveStep::Response(Err(e)) => {
+                    yield Err(e);
+                    break;
+                }
+            };
+
+            if activity_start {
+                if let Err(e) = connection.send_activity_start().await {
+                    yield Err(e);
+                    break;
+                }
+                if barge_in && responding {
+                    responding = false;
+                    interrupted = true;
+                    yield Ok(Arc::new(Event::interruption(&author)));
+                }
+            }
+            if let Some(blob) = audio {
+                if let Err(e) = connection.send_realtime(blob).await {
+                    yield Err(e);
+                    break;
+                }
+            }
+            if activity_end {
+                if let Err(e) = connection.send_activity_end().await {
+                    yield Err(e);
+                    break;
+                }
+            }
+        }
+        if let Err(e) = connection.close().await {
+            tracing::warn!("Failed to close live connection: {}", e);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Transcription;
+    use async_trait::async_trait;
+    use futures::StreamExt;
+    use std::collections::VecDeque;
+
+    /// Replies to every audio chunk with the next scripted response
+    struct ScriptedConnection {
+        replies: VecDeque<LlmResponse>,
+        pending: usize,
+    }
+
+    #[async_trait]
+    impl LlmConnection for ScriptedConnection {
+        async fn send_message(&mut self, _content: Content) -> Result<()> {
+            Ok(())
+        }
+
+        async fn send_realtime(&mut self, _blob: Blob) -> Result<()> {
+            self.pending += 1;
+            Ok(())
+        }
+
+        async fn receive(&mut self) -> Result<Option<LlmResponse>> {
+            // Waits for the live loop to cancel this receive and forward more audio
+            if self.pending == 0 {
+                std::future::pending::<()>().await;
+            }
+            self.pending -= 1;
+            Ok(self.replies.pop_front())
+        }
+
+        async fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_active(&self) -> bool {
+            true
+        }
+    }
+
+    fn reply(transcriptions: Vec<Transcription>, text: Option<&str>, is_partial: bool) -> LlmResponse {
+        let mut response = LlmResponse::new();
+        response.transcriptions = transcriptions;
+        response.content = text.map(Content::model_text);
+        response.is_partial = is_partial;
+        response
+    }
+
+    #[tokio::test]
+    async fn test_live_run_emits_interim_and_final_transcriptions() {
+        let connection = ScriptedConnection {
+            replies: VecDeque::from([
+                reply(vec![Transcription::interim(TranscriptionSource::User, "Book a ")], None, true),
+                reply(vec![Transcription::interim(TranscriptionSource::User, "table.")], None, true),
+                reply(vec![Transcription::final_text(TranscriptionSource::Model, "For how many?")], Some("For how many?"), false),
+            ]),
+            pending: 0,
+        };
+        let queue = LiveRequestQueue::new();
+        let mut stream = run_live_connection("host".into(), Box::new(connection), queue.take_receiver().unwrap(), None);
+        for _ in 0..3 {
+            queue.send_realtime(Blob::new("audio/pcm;rate=16000", vec![0u8; 320]));
+        }
+        let mut events = Vec::new();
+        for _ in 0..5 {
+            events.push(stream.next().await.unwrap().unwrap());
+        }
+        queue.close();
+        assert!(stream.next().await.is_none());
+
+        let transcripts: Vec<_> = events
+            .iter()
+            .filter_map(|event| event.transcription.as_ref().map(|t| (event.author.as_str(), t.text.as_str(), event.is_partial)))
+            .collect();
+        assert_eq!(
+            transcripts,
+            [
+                ("user", "Book a ", true),
+                ("user", "table.", true),
+                ("host", "For how many?", false),
+                ("user", "Book a table.", false),
+            ]
+        );
+        assert!(events.iter().any(|event| event.get_text().as_deref() == Some("For how many?")));
+        assert!(queue.take_receiver().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_user_speech_interrupts_model_response() {
+        let connection = ScriptedConnection {
+            replies: VecDeque::from([
+                reply(vec![], Some("Our opening hours"), true),
+                reply(vec![], Some(" are nine to five"), true),
+                reply(vec![Transcription::final_text(TranscriptionSource::User, "Just Sunday.")], Some(" on weekdays."), false),
+                reply(vec![], Some("Sunday we open at ten."), false),
+            ]),
+            pending: 0,
+        };
+        let queue = LiveRequestQueue::new();
+        let mut stream = run_live_connection("host".into(), Box::new(connection), queue.take_receiver().unwrap(), None);
+        let audio = || Blob::new("audio/pcm;rate=16000", vec![0u8; 320]);
+
+        queue.send_realtime(audio());
+        assert_eq!(stream.next().await.unwrap().unwrap().get_text().as_deref(), Some("Our opening hours"));
+        queue.send_activity_start();
+        assert!(stream.next().await.unwrap().unwrap().actions.interrupted);
+
+        // The rest of the interrupted turn is dropped, except the user's own transcription
+        queue.send_realtime(audio());
+        queue.send_realtime(audio());
+        let transcript = stream.next().await.unwrap().unwrap();
+        assert_eq!(transcript.transcription.as_ref().map(|t| t.text.as_str()), Some("Just Sunday."));
+        queue.send_realtime(audio());
+        let answer = stream.next().await.unwrap().unwrap();
+        assert_eq!(answer.get_text().as_deref(), Some("Sunday we open at ten."));
+        queue.close();
+        assert!(stream.next().await.is_none());
+    }
+}
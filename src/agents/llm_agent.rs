@@ -1,19 +1,63 @@
 //! LLM-based agent implementation
 
 use crate::{
-    agents::{BaseAgent, InvocationContext},
+    agents::{
+        detect_language, example_contents, global_debugger, AgentCapabilities, global_flags, instruction::resolve_instruction,
+        run_live_connection, Example, ExampleProvider,
+        LanguagePolicy, DETECTED_LANGUAGE_METADATA_KEY, with_determinism, BaseAgent, DebugCommand,
+        HistoryStrategy, InstructionProvider, InvocationContext, OutputSchema, PausePoint, ResponseValidation, ValidationFailure,
+        DEFAULT_INSTRUCTION_PROVIDER_TIMEOUT, STRUCTURED_OUTPUT_METADATA_KEY,
+    },
     error::Result,
-    events::Event,
-    models::{create_model, LlmRequest},
-    tools::BaseTool,
-    types::{AgentId, Content, Metadata},
+    events::{apply_output_processors, Citations, Event, EventBuilder, OutputProcessor},
+    models::{
+        create_model, global_catalog, global_live_pool, global_profiles, BaseLlm, FinishReason, LlmRequest, LlmResponse,
+        COST_METADATA_KEY,
+    },
+    tools::{global_tool_defaults, BaseTool, EffectiveToolPolicy, StateAccessPolicy, ToolContext, ToolPolicy},
+    types::{AgentId, Content, FunctionCallingMode, Metadata, StateDelta, ToolConfig},
+    utils::{global_usage_tracker, trace::json_size, ModelCallRecord, SpanKind, TraceSpan},
 };
 use async_stream::stream;
 use async_trait::async_trait;
-use std::{collections::HashMap, sync::Arc};
+use futures::TryStreamExt;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
 
 use super::base_agent::{AgentBuilder, EventStream};
 
+/// Metadata key carrying the structured answer submitted through a final-answer tool
+pub const FINAL_ANSWER_METADATA_KEY: &str = "final_answer";
+
+/// Metadata key carrying the serialized `LlmRequest` of a dry run
+pub const DRY_RUN_REQUEST_METADATA_KEY: &str = "dry_run_request";
+
+/// Metadata key carrying the model's `FinishReason` on answer events
+pub const FINISH_REASON_METADATA_KEY: &str = "finish_reason";
+
+/// Treatment of a designated tool as the agent's structured answer channel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinalAnswerMode {
+    /// Finish the turn as soon as the model calls the tool; plain text is still accepted
+    StopOnTool { tool_name: String },
+    /// The model must answer by calling the tool; plain text replies are re-prompted
+    RequiredTool { tool_name: String, max_reprompts: u32 },
+}
+
+impl FinalAnswerMode {
+    /// Get the designated tool name
+    pub fn tool_name(&self) -> &str {
+        match self {
+            Self::StopOnTool { tool_name } | Self::RequiredTool { tool_name, .. } => tool_name,
+        }
+    }
+}
+
 /// LLM-based agent
 // Note: Debug not derived due to trait objects
 pub struct LlmAgent {
@@ -21,10 +65,27 @@ pub struct LlmAgent {
     name: String,
     description: String,
     model: String,
+    profile: Option<String>,
     instruction: String,
+    instruction_provider: Option<Arc<dyn InstructionProvider>>,
+    instruction_timeout: Duration,
+    examples: Vec<Example>,
+    example_provider: Option<Arc<dyn ExampleProvider>>,
+    language_policy: Option<LanguagePolicy>,
     tools: Vec<Arc<dyn BaseTool>>,
+    tool_config: Option<ToolConfig>,
+    single_tool_round: bool,
+    tool_state_policies: HashMap<String, StateAccessPolicy>,
+    default_tool_state_policy: StateAccessPolicy,
+    tool_policies: HashMap<String, EffectiveToolPolicy>,
+    final_answer: Option<FinalAnswerMode>,
+    output_schema: Option<OutputSchema>,
+    output_processors: Vec<Arc<dyn OutputProcessor>>,
+    history_strategy: Option<Arc<dyn HistoryStrategy>>,
     sub_agents: Vec<Box<dyn BaseAgent>>,
     metadata: Metadata,
+    version: Option<String>,
+    config_hash: String,
 }
 
 impl LlmAgent {
@@ -32,6 +93,11 @@ impl LlmAgent {
     pub fn builder() -> LlmAgentBuilder {
         LlmAgentBuilder::new()
     }
+
+    /// Effective execution policy of each tool, by tool name
+    pub fn tool_policies(&self) -> &HashMap<String, EffectiveToolPolicy> {
+        &self.tool_policies
+    }
 }
 
 #[async_trait]
@@ -60,115 +126,670 @@ impl BaseAgent for LlmAgent {
         &self.sub_agents
     }
 
+    fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    fn config_hash(&self) -> Option<&str> {
+        Some(&self.config_hash)
+    }
+
+    fn capabilities(&self) -> AgentCapabilities {
+        let mut capabilities = AgentCapabilities::of(self);
+        capabilities.tools = self.tools.iter().filter_map(|tool| tool.get_declaration()).collect();
+        // A final-answer tool's arguments are the answer
+        capabilities.output_schema = match &self.final_answer {
+            Some(mode) => capabilities
+                .tools
+                .iter()
+                .find(|declaration| declaration.name == mode.tool_name())
+                .map(|declaration| declaration.parameters.clone()),
+            None => self.output_schema.as_ref().map(|schema| schema.schema().clone()),
+        };
+        // Profiles are resolved now, so the modalities are those of the model a run would use
+        let model = match &self.profile {
+            Some(name) => global_profiles().resolve(name).map(|profile| profile.model).unwrap_or_default(),
+            None => self.model.clone(),
+        };
+        if let Some(metadata) = global_catalog().lookup(&model) {
+            if !metadata.input_modalities.is_empty() {
+                capabilities.input_modalities = metadata.input_modalities;
+            }
+            if !metadata.output_modalities.is_empty() {
+                capabilities.output_modalities = metadata.output_modalities;
+            }
+        }
+        capabilities.streaming = true;
+        capabilities
+    }
+
     async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
         let agent_name = self.name.clone();
         let model_name = self.model.clone();
+        let profile_name = self.profile.clone();
         let instruction = self.instruction.clone();
+        let instruction_provider = self.instruction_provider.clone();
+        let instruction_timeout = self.instruction_timeout;
+        let examples = self.examples.clone();
+        let example_provider = self.example_provider.clone();
+        let language_policy = self.language_policy.clone();
+        // Set once the user's language is detected; stamped on every event of the turn
+        let detected = Arc::new(OnceLock::new());
+        let detected_in_stream = detected.clone();
         let tools = self.tools.clone();
+        let tool_config = self.tool_config.clone();
+        let single_tool_round = self.single_tool_round;
+        let tool_state_policies = self.tool_state_policies.clone();
+        let default_tool_state_policy = self.default_tool_state_policy.clone();
+        let tool_policies = self.tool_policies.clone();
+        let final_answer = self.final_answer.clone();
+        let output_schema = self.output_schema.clone();
+        let output_processors = self.output_processors.clone();
+        let history_strategy = self.history_strategy.clone();
+        let cost = ctx.cost.clone();
 
         Ok(Box::pin(stream! {
-            // Create the LLM model
-            let model = match create_model(&model_name).await {
-                Ok(model) => model,
-                Err(e) => {
-                    yield Err(e);
-                    return;
-                }
+            // Resolve the model profile, if the agent references one
+            let profile = match &profile_name {
+                Some(name) => match global_profiles().resolve(name) {
+                    Ok(profile) => Some(profile),
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                },
+                None => None,
             };
+            // A variant of a running experiment overrides the model and instruction
+            let variant = ctx.experiment.as_ref().filter(|a| a.applies_to(&agent_name)).map(|a| a.variant.clone());
+            let model_name = variant
+                .as_ref()
+                .and_then(|v| v.model.clone())
+                .or_else(|| profile.as_ref().map(|p| p.model.clone()))
+                .unwrap_or(model_name);
 
             // Build the conversation history from session
             let mut conversation_history = Vec::new();
 
-            // Add system instruction if provided
-            if !instruction.is_empty() {
-                conversation_history.push(Content::user_text(format!("System: {}", instruction)));
-            }
+            // Session state as tools see it, including their writes earlier in the turn
+            let mut tool_state = ctx.state.clone();
+
+            // Assemble the instruction at run time if a provider is configured; an
+            // operator's fallback instruction or the experiment variant's replaces both
+            let fallback = global_flags().fallback_instruction(&agent_name);
+            let variant_instruction = variant.and_then(|v| v.instruction);
+            let mut instruction = match (fallback.or(variant_instruction), &instruction_provider) {
+                (Some(replacement), _) => replacement,
+                (None, Some(provider)) => resolve_instruction(provider, &ctx, instruction_timeout, &instruction).await,
+                (None, None) => instruction,
+            };
 
             // Add conversation history from session events
+            let mut session_history = Vec::new();
             for event in &ctx.session_service.get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id)
                 .await
                 .unwrap_or_default()
                 .unwrap_or_else(|| crate::sessions::Session::new(ctx.app_name.clone(), ctx.user_id.clone(), ctx.session_id.clone()))
                 .events {
                 if let Some(content) = &event.content {
-                    conversation_history.push(content.clone());
+                    session_history.push(content.clone());
                 }
             }
 
+            // Trim the history with the configured strategy
+            if let Some(strategy) = &history_strategy {
+                session_history = match strategy.select(session_history).await {
+                    Ok(history) => history,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+            }
+
+            let query = session_history
+                .iter()
+                .rev()
+                .find(|content| content.role == "user")
+                .map(Content::get_text)
+                .unwrap_or_default();
+
+            // Answer in the user's (or a fixed) language
+            if let Some(policy) = &language_policy {
+                let language = detect_language(&query);
+                if let Some(policy_instruction) = policy.instruction(language.as_ref()) {
+                    if !instruction.is_empty() {
+                        instruction.push('\n');
+                    }
+                    instruction.push_str(&policy_instruction);
+                }
+                if let Some(language) = language {
+                    let _ = detected_in_stream.set(language.code);
+                }
+            }
+
+            // Few-shot examples go before the conversation
+            let mut examples = examples;
+            if let Some(provider) = &example_provider {
+                match provider.examples(&query).await {
+                    Ok(selected) => examples.extend(selected),
+                    Err(e) => tracing::warn!("Example provider failed, sending no dynamic examples: {}", e),
+                }
+            }
+            conversation_history.extend(example_contents(&examples));
+            conversation_history.extend(session_history);
+
             // Create LLM request
             let mut request = LlmRequest::new(&model_name);
+            if !instruction.is_empty() {
+                request = request.with_system_instruction(Content::system_text(instruction));
+            }
+            if let Some(profile) = &profile {
+                profile.apply_to(&mut request.config);
+            }
+            ctx.run_config.apply_to(&mut request.config);
+            if let Some(schema) = &output_schema {
+                schema.apply_to(&mut request.config);
+            }
             for content in conversation_history {
                 request = request.add_content(content);
             }
 
-            // Add tools if available
+            // Add tools if available, hiding those switched off by an operator
+            let tools: Vec<_> = tools.into_iter().filter(|tool| !global_flags().is_tool_disabled(tool.name())).collect();
             if !tools.is_empty() {
                 request = request.add_tools(tools.clone());
+                if let Some(tool_config) = tool_config {
+                    request = request.with_tool_config(tool_config);
+                }
             }
 
-            // Generate response
-            match model.generate_content(request.clone()).await {
-                Ok(response) => {
-                    // Handle function calls
-                    if response.has_function_calls() {
-                        for function_call in &response.function_calls {
-                            yield Ok(Event::text_response(&agent_name, &format!("Calling function: {}", function_call.name)));
-
-                            // Execute the function call
-                            if let Some(tool) = request.get_tool(&function_call.name) {
-                                let args: HashMap<String, serde_json::Value> = match serde_json::from_value(function_call.args.clone()) {
-                                    Ok(args) => args,
-                                    Err(e) => {
-                                        yield Ok(Event::text_response(&agent_name, &format!("Error parsing function arguments: {}", e)));
-                                        continue;
-                                    }
-                                };
+            // A dry run stops here, before any model is created or called
+            if ctx.run_config.dry_run {
+                let mut event = EventBuilder::new(&agent_name, ctx.invocation_id).build();
+                event.metadata.insert(
+                    DRY_RUN_REQUEST_METADATA_KEY.to_string(),
+                    serde_json::to_value(&request).unwrap_or_default(),
+                );
+                yield Ok(event);
+                return;
+            }
 
-                                match tool.run_async(args).await {
-                                    Ok(result) => {
-                                        yield Ok(Event::text_response(&agent_name, &format!("Function result: {}", result)));
+            // Create the LLM model
+            let model = match &profile {
+                Some(profile) => profile.create_model().await,
+                None => create_model(&model_name).await,
+            };
+            let model = match model {
+                Ok(model) => model,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
 
-                                        // Add function result to conversation and continue
-                                        let mut follow_up_request = request.clone();
-                                        follow_up_request = follow_up_request.add_model_message(format!("Function {} returned: {}", function_call.name, result));
+            // The other tools stay available; only re-prompts and the turn after a
+            // tool result are restricted to the required final-answer tool
+            let required_tool = match &final_answer {
+                Some(FinalAnswerMode::RequiredTool { tool_name, .. }) => Some(tool_name.clone()),
+                _ => None,
+            };
 
-                                        match model.generate_content(follow_up_request).await {
-                                            Ok(final_response) => {
-                                                if let Some(text) = final_response.get_text() {
-                                                    yield Ok(Event::text_response(&agent_name, text));
-                                                }
-                                            }
+            // Generate response, re-prompting if the model answered in plain text
+            // instead of calling a required final-answer tool, or the answer failed
+            // the runner's validation
+            let mut reprompts = 0;
+            let mut validation_retries = 0;
+            let response = loop {
+                if ctx.run_config.breakpoints.before_model {
+                    let mut pause = global_debugger().pause(&agent_name, ctx.invocation_id, PausePoint::ModelCall {
+                        request: serde_json::to_value(&request).unwrap_or_default(),
+                    });
+                    yield Ok(pause.event());
+                    match pause.wait().await {
+                        DebugCommand::Continue => {}
+                        DebugCommand::Modify { value } => match modified_request(&request, value) {
+                            Ok(modified) => request = modified,
+                            Err(e) => {
+                                yield Err(e);
+                                return;
+                            }
+                        },
+                        DebugCommand::Abort => {
+                            yield Err(aborted_by_debugger(&agent_name));
+                            return;
+                        }
+                    }
+                }
+
+                let started = Instant::now();
+                let request_bytes = json_size(&request);
+                let response = model.generate_content(request.clone()).await;
+                record_model_call(&ctx, &agent_name, &model_name, request_bytes, started, &response);
+                let response = match response {
+                    Ok(response) => response,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+
+                if let Some(FinalAnswerMode::RequiredTool { tool_name, max_reprompts }) = &final_answer {
+                    if !response.has_function_calls() {
+                        if reprompts >= *max_reprompts {
+                            yield Err(crate::adk_error!(
+                                AgentError,
+                                "Model did not call required tool '{}' after {} re-prompts",
+                                tool_name,
+                                reprompts
+                            ));
+                            return;
+                        }
+                        reprompts += 1;
+                        if let Some(text) = response.get_text() {
+                            request = request.add_model_message(text);
+                        }
+                        request = request
+                            .add_user_message(format!(
+                                "You must submit your answer by calling the `{}` function. Do not reply with plain text.",
+                                tool_name
+                            ))
+                            .with_tool_config(ToolConfig::any().with_allowed_function_names(vec![tool_name.clone()]));
+                        continue;
+                    }
+                }
+
+                if let Some((text, failure)) = failed_validation(&ctx, &response, validation_retries + 1).await {
+                    let validation = ctx.response_validation.as_ref().expect("validation failed without validators");
+                    if validation_retries >= validation.max_retries() {
+                        yield Ok(failure.event(&agent_name));
+                        return;
+                    }
+                    validation_retries += 1;
+                    request = request
+                        .add_model_message(text)
+                        .add_user_message(ResponseValidation::retry_prompt(&failure));
+                    continue;
+                }
+
+                break response;
+            };
+
+            // Handle function calls
+            if response.has_function_calls() {
+                for function_call in &response.function_calls {
+                    yield Ok(Event::function_call(&agent_name, function_call.clone()));
+
+                    // Execute the function call
+                    if global_flags().is_tool_disabled(&function_call.name) {
+                        yield Ok(Event::text_response(&agent_name, format!("Tool '{}' is disabled", function_call.name)));
+                    } else if let Some(tool) = request.get_tool(&function_call.name) {
+                        let tool_policy = tool_policies.get(&function_call.name).cloned().unwrap_or_default();
+                        let mut args_value = function_call.args.clone();
+                        // Tools requiring confirmation pause like a breakpoint until approved
+                        if ctx.run_config.breakpoints.before_tool || tool_policy.require_confirmation {
+                            let mut pause = global_debugger().pause(&agent_name, ctx.invocation_id, PausePoint::ToolCall {
+                                tool: function_call.name.clone(),
+                                args: args_value.clone(),
+                            });
+                            yield Ok(pause.event());
+                            match pause.wait().await {
+                                DebugCommand::Continue => {}
+                                DebugCommand::Modify { value } => args_value = value,
+                                DebugCommand::Abort => {
+                                    yield Err(aborted_by_debugger(&agent_name));
+                                    return;
+                                }
+                            }
+                        }
+                        let args: HashMap<String, serde_json::Value> = match serde_json::from_value(args_value) {
+                            Ok(args) => args,
+                            Err(e) => {
+                                yield Ok(Event::text_response(&agent_name, format!("Error parsing function arguments: {}", e)));
+                                continue;
+                            }
+                        };
+
+                        let is_final_answer = final_answer
+                            .as_ref()
+                            .is_some_and(|mode| mode.tool_name() == function_call.name);
+
+                        // Tools see session state only as far as their policy allows
+                        let tool_ctx = ToolContext::new(
+                            &function_call.name,
+                            &agent_name,
+                            ctx.invocation_id,
+                            ctx.session_id.clone(),
+                            ctx.user_id.clone(),
+                            tool_state.clone(),
+                            tool_state_policies.get(&function_call.name).unwrap_or(&default_tool_state_policy).clone(),
+                        );
+                        let started = Instant::now();
+                        let args_bytes = json_size(&args);
+                        let result =
+                            with_determinism(ctx.run_config.deterministic, tool_policy.run(tool.as_ref(), args, &tool_ctx)).await;
+                        // Writes of a failed call are discarded
+                        let state_delta = match &result {
+                            Ok(_) => tool_ctx.state_delta(),
+                            Err(_) => Default::default(),
+                        };
+                        tool_state.extend(state_delta.clone());
+                        let span = TraceSpan::finished(
+                            &agent_name,
+                            SpanKind::ToolCall {
+                                tool: function_call.name.clone(),
+                                args_bytes,
+                                result_bytes: result.as_ref().map(json_size).unwrap_or(0),
+                            },
+                            started,
+                        );
+                        ctx.trace.record(match &result {
+                            Ok(_) => span,
+                            Err(e) => span.with_error(e),
+                        });
+
+                        match result {
+                            Ok(result) if is_final_answer => {
+                                yield Ok(final_answer_event(&agent_name, result, state_delta));
+                                return;
+                            }
+                            Ok(result) => {
+                                let retrieval_citations = Citations::from_retrieval_results(&result);
+                                let result = if tool_policy.should_summarize(&result) {
+                                    summarize_tool_output(&ctx, &agent_name, model.as_ref(), &model_name, &function_call.name, result).await
+                                } else {
+                                    result
+                                };
+                                let mut event = Event::function_response(&agent_name, &function_call.name, result.clone());
+                                event.actions.state_delta = state_delta;
+                                event.redact = tool_ctx.redacts_response();
+                                yield Ok(event);
+
+                                // Add function result to conversation and continue
+                                let mut follow_up_request = request
+                                    .clone()
+                                    .add_content(Content::function_call(function_call.clone()))
+                                    .add_content(Content::function_response(&function_call.name, result));
+                                // A forced call here would be dropped, leaving the turn without an answer
+                                let forces_call = follow_up_request
+                                    .config
+                                    .tool_config
+                                    .as_ref()
+                                    .is_some_and(|config| config.function_calling_mode == FunctionCallingMode::Any);
+                                if let Some(tool_name) = &required_tool {
+                                    // The answer must still come through the required tool
+                                    follow_up_request = follow_up_request.with_tool_config(
+                                        ToolConfig::any().with_allowed_function_names(vec![tool_name.clone()]),
+                                    );
+                                } else if single_tool_round || forces_call {
+                                    follow_up_request = follow_up_request.with_tool_config(ToolConfig::none());
+                                }
+
+                                if ctx.run_config.breakpoints.before_model {
+                                    let mut pause = global_debugger().pause(&agent_name, ctx.invocation_id, PausePoint::ModelCall {
+                                        request: serde_json::to_value(&follow_up_request).unwrap_or_default(),
+                                    });
+                                    yield Ok(pause.event());
+                                    match pause.wait().await {
+                                        DebugCommand::Continue => {}
+                                        DebugCommand::Modify { value } => match modified_request(&follow_up_request, value) {
+                                            Ok(modified) => follow_up_request = modified,
                                             Err(e) => {
                                                 yield Err(e);
+                                                return;
                                             }
+                                        },
+                                        DebugCommand::Abort => {
+                                            yield Err(aborted_by_debugger(&agent_name));
+                                            return;
+                                        }
+                                    }
+                                }
+
+                                let mut validation_retries = 0;
+                                let final_response = loop {
+                                    let started = Instant::now();
+                                    let request_bytes = json_size(&follow_up_request);
+                                    let final_response = model.generate_content(follow_up_request.clone()).await;
+                                    record_model_call(&ctx, &agent_name, &model_name, request_bytes, started, &final_response);
+                                    let Ok(response) = &final_response else {
+                                        break final_response;
+                                    };
+                                    let Some((text, failure)) = failed_validation(&ctx, response, validation_retries + 1).await else {
+                                        break final_response;
+                                    };
+                                    let validation = ctx.response_validation.as_ref().expect("validation failed without validators");
+                                    if validation_retries >= validation.max_retries() {
+                                        yield Ok(failure.event(&agent_name));
+                                        return;
+                                    }
+                                    validation_retries += 1;
+                                    follow_up_request = follow_up_request
+                                        .add_model_message(text)
+                                        .add_user_message(ResponseValidation::retry_prompt(&failure));
+                                };
+                                match final_response {
+                                    Ok(final_response) => {
+                                        let answer_call = final_response
+                                            .function_calls
+                                            .iter()
+                                            .find(|call| required_tool.as_ref() == Some(&call.name));
+                                        if let (Some(call), Some(tool)) = (answer_call, answer_call.and_then(|call| request.get_tool(&call.name))) {
+                                            yield Ok(Event::function_call(&agent_name, call.clone()));
+                                            let args: HashMap<String, serde_json::Value> = match serde_json::from_value(call.args.clone()) {
+                                                Ok(args) => args,
+                                                Err(e) => {
+                                                    yield Ok(Event::text_response(&agent_name, format!("Error parsing function arguments: {}", e)));
+                                                    return;
+                                                }
+                                            };
+                                            let tool_ctx = ToolContext::new(
+                                                &call.name,
+                                                &agent_name,
+                                                ctx.invocation_id,
+                                                ctx.session_id.clone(),
+                                                ctx.user_id.clone(),
+                                                tool_state.clone(),
+                                                tool_state_policies.get(&call.name).unwrap_or(&default_tool_state_policy).clone(),
+                                            );
+                                            let tool_policy = tool_policies.get(&call.name).cloned().unwrap_or_default();
+                                            match with_determinism(ctx.run_config.deterministic, tool_policy.run(tool.as_ref(), args, &tool_ctx)).await {
+                                                Ok(result) => yield Ok(final_answer_event(&agent_name, result, tool_ctx.state_delta())),
+                                                Err(e) => yield Ok(Event::text_response(&agent_name, format!("Function execution error: {}", e))),
+                                            }
+                                            return;
+                                        }
+                                        // Prefer provider grounding, else cite the retrieval tool's results
+                                        let citations = final_response
+                                            .citations
+                                            .or(retrieval_citations);
+                                        if let Some(content) = final_response.content {
+                                            yield answer_event(&agent_name, content, citations, final_response.finish_reason, output_schema.as_ref());
                                         }
                                     }
                                     Err(e) => {
-                                        yield Ok(Event::text_response(&agent_name, &format!("Function execution error: {}", e)));
+                                        yield Err(e);
                                     }
                                 }
-                            } else {
-                                yield Ok(Event::text_response(&agent_name, &format!("Unknown function: {}", function_call.name)));
+                            }
+                            Err(e) => {
+                                yield Ok(Event::text_response(&agent_name, format!("Function execution error: {}", e)));
                             }
                         }
-                    } else if let Some(text) = response.get_text() {
-                        // Regular text response
-                        yield Ok(Event::text_response(&agent_name, text));
                     } else {
-                        yield Ok(Event::text_response(&agent_name, "No response generated"));
+                        yield Ok(Event::text_response(&agent_name, format!("Unknown function: {}", function_call.name)));
                     }
                 }
-                Err(e) => {
-                    yield Err(e);
-                }
+            } else if let Some(content) = response.content {
+                // Regular response, possibly with code execution parts
+                yield answer_event(&agent_name, content, response.citations, response.finish_reason, output_schema.as_ref());
+            } else {
+                yield Ok(Event::text_response(&agent_name, "No response generated"));
+            }
+        }.map_ok(move |mut event: Event| {
+            apply_output_processors(&mut event, &output_processors);
+            if let Some(language) = detected.get() {
+                event.metadata.insert(DETECTED_LANGUAGE_METADATA_KEY.to_string(), language.clone().into());
             }
-        }))
+            let total = cost.total();
+            if total.calls > 0 {
+                event.metadata.insert(COST_METADATA_KEY.to_string(), serde_json::to_value(total).unwrap_or_default());
+            }
+            Arc::new(event)
+        })))
     }
 
     async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
-        // TODO: Implement live mode
-        self.run_async(ctx).await
+        // Without live input, or with a model that can't stream, answer the session like a normal run
+        let Some(queue) = ctx.live_request_queue.clone() else {
+            return self.run_async(ctx).await;
+        };
+        let model = match &self.profile {
+            Some(name) => global_profiles().resolve(name)?.create_model().await?,
+            None => create_model(&self.model).await?,
+        };
+        if !model.supports_live() {
+            return self.run_async(ctx).await;
+        }
+        // A pre-established connection, if the pool has one, saves the handshake
+        let connection = global_live_pool().acquire(Arc::from(model)).await?;
+        Ok(run_live_connection(
+            self.name.clone(),
+            connection,
+            queue.take_receiver()?,
+            ctx.run_config.voice_activity.clone(),
+        ))
+    }
+}
+
+/// Condense a long tool output with the agent's model; the output is kept as is if that fails
+async fn summarize_tool_output(
+    ctx: &InvocationContext,
+    agent_name: &str,
+    model: &dyn BaseLlm,
+    model_name: &str,
+    tool_name: &str,
+    output: serde_json::Value,
+) -> serde_json::Value {
+    let request = LlmRequest::new(model_name).add_user_message(format!(
+        "Summarize this output of the `{}` tool. Keep every fact needed to answer the user, including \
+         identifiers, numbers and dates.\n\n{}",
+        tool_name, output
+    ));
+    let started = Instant::now();
+    let request_bytes = json_size(&request);
+    let response = model.generate_content(request).await;
+    record_model_call(ctx, agent_name, model_name, request_bytes, started, &response);
+    match response.map(|response| response.get_text()) {
+        Ok(Some(summary)) => serde_json::json!({ "summary": summary }),
+        Ok(None) => output,
+        Err(e) => {
+            tracing::warn!("Failed to summarize output of tool '{}': {}", tool_name, e);
+            output
+        }
+    }
+}
+
+/// The text of a final answer failing the runner's response validation, and the failure
+async fn failed_validation(
+    ctx: &InvocationContext,
+    response: &LlmResponse,
+    attempts: u32,
+) -> Option<(String, ValidationFailure)> {
+    let validation = ctx.response_validation.as_ref()?;
+    if response.has_function_calls() {
+        return None;
+    }
+    let text = response.get_text()?;
+    let failure = validation.validate(&text, attempts).await?;
+    Some((text, failure))
+}
+
+/// The final-answer tool ends the turn with its structured result
+fn final_answer_event(agent_name: &str, result: serde_json::Value, state_delta: StateDelta) -> Event {
+    let mut event = Event::text_response(agent_name, result.to_string());
+    event.metadata.insert(FINAL_ANSWER_METADATA_KEY.to_string(), result);
+    event.actions.end_conversation = true;
+    event.actions.state_delta = state_delta;
+    event
+}
+
+/// Event with the model's answer, repaired and validated if the agent has an output schema
+fn answer_event(
+    agent_name: &str,
+    content: Content,
+    citations: Option<Citations>,
+    finish_reason: Option<FinishReason>,
+    output_schema: Option<&OutputSchema>,
+) -> Result<Event> {
+    let mut event = match output_schema {
+        Some(schema) => {
+            let output = schema.parse(&content.get_text())?;
+            let mut event = Event::text_response(agent_name, output.value.to_string()).with_citations(citations);
+            event.metadata.insert(STRUCTURED_OUTPUT_METADATA_KEY.to_string(), output.metadata());
+            event
+        }
+        None => Event::content_response(agent_name, content).with_citations(citations),
+    };
+    if let Some(reason) = finish_reason {
+        event
+            .metadata
+            .insert(FINISH_REASON_METADATA_KEY.to_string(), serde_json::to_value(reason).unwrap_or_default());
+    }
+    Ok(event)
+}
+
+/// Replace a paused request with the debugger's edited version, keeping its tools
+fn modified_request(request: &LlmRequest, value: serde_json::Value) -> Result<LlmRequest> {
+    let mut modified: LlmRequest = serde_json::from_value(value)
+        .map_err(|e| crate::adk_error!(ValidationError, "Invalid modified model request: {}", e))?;
+    modified.tools_dict = request.tools_dict.clone();
+    Ok(modified)
+}
+
+fn aborted_by_debugger(agent_name: &str) -> crate::error::AdkError {
+    crate::adk_error!(AgentError, "Run of agent '{}' aborted by the debugger", agent_name)
+}
+
+/// Record a completed model call in the global usage tracker, the invocation's cost and its trace
+fn record_model_call(
+    ctx: &InvocationContext,
+    agent_name: &str,
+    model_name: &str,
+    request_bytes: usize,
+    started: Instant,
+    response: &Result<LlmResponse>,
+) {
+    let usage = response.as_ref().ok().and_then(|r| r.usage.as_ref());
+    let mut record = ModelCallRecord::new(
+        &ctx.app_name,
+        agent_name,
+        model_name,
+        started.elapsed(),
+        usage,
+        response.is_err(),
+    )
+    .with_experiment(ctx.experiment.as_ref());
+    if response.is_ok() {
+        record.cost_usd = ctx.cost.record(model_name, usage);
     }
+    global_usage_tracker().record_model_call(record);
+
+    let span = TraceSpan::finished(
+        agent_name,
+        SpanKind::ModelCall {
+            model: model_name.to_string(),
+            request_bytes,
+            response_bytes: response.as_ref().map(|r| json_size(&r.content)).unwrap_or(0),
+            prompt_tokens: usage.and_then(|u| u.prompt_tokens),
+            completion_tokens: usage.and_then(|u| u.completion_tokens),
+        },
+        started,
+    );
+    ctx.trace.record(match response {
+        Ok(_) => span,
+        Err(e) => span.with_error(e),
+    });
 }
 
 /// Builder for LlmAgent
@@ -176,10 +797,27 @@ pub struct LlmAgentBuilder {
     name: Option<String>,
     description: String,
     model: Option<String>,
+    profile: Option<String>,
     instruction: String,
+    instruction_provider: Option<Arc<dyn InstructionProvider>>,
+    instruction_timeout: Duration,
+    examples: Vec<Example>,
+    example_provider: Option<Arc<dyn ExampleProvider>>,
+    language_policy: Option<LanguagePolicy>,
     tools: Vec<Arc<dyn BaseTool>>,
+    tool_config: Option<ToolConfig>,
+    single_tool_round: bool,
+    tool_state_policies: HashMap<String, StateAccessPolicy>,
+    default_tool_state_policy: StateAccessPolicy,
+    tool_defaults: ToolPolicy,
+    tool_policy_overrides: HashMap<String, ToolPolicy>,
+    final_answer: Option<FinalAnswerMode>,
+    output_schema: Option<OutputSchema>,
+    output_processors: Vec<Arc<dyn OutputProcessor>>,
+    history_strategy: Option<Arc<dyn HistoryStrategy>>,
     sub_agents: Vec<Box<dyn BaseAgent>>,
     metadata: Metadata,
+    version: Option<String>,
 }
 
 impl LlmAgentBuilder {
@@ -188,10 +826,27 @@ impl LlmAgentBuilder {
             name: None,
             description: String::new(),
             model: None,
+            profile: None,
             instruction: String::new(),
+            instruction_provider: None,
+            instruction_timeout: DEFAULT_INSTRUCTION_PROVIDER_TIMEOUT,
+            examples: Vec::new(),
+            example_provider: None,
+            language_policy: None,
             tools: Vec::new(),
+            tool_config: None,
+            single_tool_round: false,
+            tool_state_policies: HashMap::new(),
+            default_tool_state_policy: StateAccessPolicy::default(),
+            tool_defaults: ToolPolicy::default(),
+            tool_policy_overrides: HashMap::new(),
+            final_answer: None,
+            output_schema: None,
+            output_processors: Vec::new(),
+            history_strategy: None,
             sub_agents: Vec::new(),
             metadata: HashMap::new(),
+            version: None,
         }
     }
 
@@ -200,22 +855,195 @@ impl LlmAgentBuilder {
         self
     }
 
+    /// Use a named model profile (resolved at run time, so it can be repointed)
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
     pub fn instruction(mut self, instruction: impl Into<String>) -> Self {
         self.instruction = instruction.into();
         self
     }
 
+    /// Build the instruction at run time; the static instruction is used if the provider fails or times out
+    pub fn instruction_provider<F, Fut>(mut self, provider: F) -> Self
+    where
+        F: Fn(crate::agents::ReadonlyContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        self.instruction_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// How long the instruction provider may take (defaults to 5 seconds)
+    pub fn instruction_timeout(mut self, timeout: Duration) -> Self {
+        self.instruction_timeout = timeout;
+        self
+    }
+
+    /// Few-shot examples sent with every request
+    pub fn examples(mut self, examples: impl IntoIterator<Item = Example>) -> Self {
+        self.examples.extend(examples);
+        self
+    }
+
+    /// Choose additional examples per request, e.g. by similarity to the user message
+    pub fn example_provider(mut self, provider: Arc<dyn ExampleProvider>) -> Self {
+        self.example_provider = Some(provider);
+        self
+    }
+
+    /// Answer in the user's detected language or a fixed one; the detected
+    /// language is recorded in event metadata
+    pub fn language_policy(mut self, policy: LanguagePolicy) -> Self {
+        self.language_policy = Some(policy);
+        self
+    }
+
     pub fn tool(mut self, tool: Arc<dyn BaseTool>) -> Self {
         self.tools.push(tool);
         self
     }
 
+    /// Set the function calling configuration for the agent's model requests
+    pub fn tool_config(mut self, tool_config: ToolConfig) -> Self {
+        self.tool_config = Some(tool_config);
+        self
+    }
+
+    /// Forbid function calls in the model turn after a tool result, so it
+    /// answers from that result; otherwise the follow-up keeps the tool config,
+    /// except that a forced (`ANY`) config is always lifted there
+    pub fn single_tool_round(mut self, single_tool_round: bool) -> Self {
+        self.single_tool_round = single_tool_round;
+        self
+    }
+
+    /// Limit the session state a tool can read and write, e.g. for third-party tools
+    pub fn tool_state_access(mut self, tool_name: impl Into<String>, policy: StateAccessPolicy) -> Self {
+        self.tool_state_policies.insert(tool_name.into(), policy);
+        self
+    }
+
+    /// Timeouts, retries, confirmation and summarization of all tools of this agent,
+    /// overriding the app's `[tool_defaults]`
+    pub fn tool_defaults(mut self, policy: ToolPolicy) -> Self {
+        self.tool_defaults = policy;
+        self
+    }
+
+    /// Override the execution policy of one tool
+    pub fn tool_policy(mut self, tool_name: impl Into<String>, policy: ToolPolicy) -> Self {
+        self.tool_policy_overrides.insert(tool_name.into(), policy);
+        self
+    }
+
+    /// State access of tools without their own policy (defaults to full access)
+    pub fn default_tool_state_access(mut self, policy: StateAccessPolicy) -> Self {
+        self.default_tool_state_policy = policy;
+        self
+    }
+
+    /// Finish the turn as soon as the model calls the given tool
+    pub fn stop_on_tool(mut self, tool_name: impl Into<String>) -> Self {
+        self.final_answer = Some(FinalAnswerMode::StopOnTool {
+            tool_name: tool_name.into(),
+        });
+        self
+    }
+
+    /// Require the model to answer through the given tool, re-prompting plain text replies;
+    /// the turn fails with an `AgentError` once `max_reprompts` re-prompts are used up.
+    /// The agent's other tools stay callable before the answer
+    pub fn require_tool(mut self, tool_name: impl Into<String>, max_reprompts: u32) -> Self {
+        self.final_answer = Some(FinalAnswerMode::RequiredTool {
+            tool_name: tool_name.into(),
+            max_reprompts,
+        });
+        self
+    }
+
+    /// Answer with JSON matching a schema; near-valid output is repaired per the schema's strictness
+    pub fn output_schema(mut self, schema: OutputSchema) -> Self {
+        self.output_schema = Some(schema);
+        self
+    }
+
+    /// Rewrite the agent's final answers, e.g. to format them for a channel
+    pub fn output_processor(mut self, processor: Arc<dyn OutputProcessor>) -> Self {
+        self.output_processors.push(processor);
+        self
+    }
+
+    /// Add several output processors, such as [`OutputFormat::processors`](crate::events::OutputFormat::processors)
+    pub fn output_processors(mut self, processors: impl IntoIterator<Item = Arc<dyn OutputProcessor>>) -> Self {
+        self.output_processors.extend(processors);
+        self
+    }
+
+    /// Select which session history is sent to the model (defaults to the full history)
+    pub fn history_strategy(mut self, strategy: Arc<dyn HistoryStrategy>) -> Self {
+        self.history_strategy = Some(strategy);
+        self
+    }
+
     pub fn sub_agent(mut self, agent: Box<dyn BaseAgent>) -> Self {
         self.sub_agents.push(agent);
         self
     }
+
+    /// Set the agent version recorded on its events and used for registry lookups
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Hash the behavior-defining configuration (sha256, first 16 hex chars)
+    fn config_hash(&self, name: &str, model: &str, tool_policies: &HashMap<String, EffectiveToolPolicy>) -> String {
+        let tools: Vec<serde_json::Value> = self
+            .tools
+            .iter()
+            .map(|tool| {
+                serde_json::json!({
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "declaration": tool.get_declaration(),
+                })
+            })
+            .collect();
+        let sub_agents: Vec<serde_json::Value> = self
+            .sub_agents
+            .iter()
+            .map(|agent| serde_json::json!([agent.name(), agent.config_hash()]))
+            .collect();
+        let config = serde_json::json!({
+            "name": name,
+            "model": model,
+            "profile": self.profile,
+            "instruction": self.instruction,
+            "instruction_provider": self.instruction_provider.is_some(),
+            "examples": self.examples,
+            "example_provider": self.example_provider.is_some(),
+            "language_policy": self.language_policy,
+            "tools": tools,
+            "tool_config": self.tool_config,
+            "single_tool_round": self.single_tool_round,
+            "tool_state_policies": self.tool_state_policies.iter().collect::<std::collections::BTreeMap<_, _>>(),
+            "default_tool_state_policy": self.default_tool_state_policy,
+            "tool_policies": tool_policies.iter().collect::<std::collections::BTreeMap<_, _>>(),
+            "final_answer": self.final_answer.as_ref().map(|mode| format!("{:?}", mode)),
+            "output_schema": self.output_schema.as_ref().map(|schema| (schema.schema(), schema.strictness())),
+            "output_processors": self.output_processors.iter().map(|processor| processor.name()).collect::<Vec<_>>(),
+            "sub_agents": sub_agents,
+        });
+
+        let digest = Sha256::digest(config.to_string().as_bytes());
+        digest.iter().take(8).map(|byte| format!("{:02x}", byte)).collect()
+    }
 }
 
+
 impl AgentBuilder<LlmAgent> for LlmAgentBuilder {
     fn name(mut self, name: impl Into<String>) -> Self {
         self.name = Some(name.into());
@@ -233,23 +1061,65 @@ impl AgentBuilder<LlmAgent> for LlmAgentBuilder {
     }
 
     fn build(self) -> Result<LlmAgent> {
-        let name = self.name.ok_or_else(|| {
+        let name = self.name.clone().ok_or_else(|| {
             crate::adk_error!(ValidationError, "Agent name is required")
         })?;
         
-        let model = self.model.ok_or_else(|| {
-            crate::adk_error!(ValidationError, "Model is required")
-        })?;
+        if self.model.is_none() && self.profile.is_none() {
+            return Err(crate::adk_error!(ValidationError, "Model or profile is required"));
+        }
+        let model = self.model.clone().unwrap_or_default();
+
+        if let Some(mode) = &self.final_answer {
+            if !self.tools.iter().any(|tool| tool.name() == mode.tool_name()) {
+                return Err(crate::adk_error!(
+                    ValidationError,
+                    "Final-answer tool '{}' is not registered on agent '{}'",
+                    mode.tool_name(),
+                    name
+                ));
+            }
+        }
+
+        // Resolve app, agent and tool level policies once, at build time
+        let no_override = ToolPolicy::default();
+        let tool_policies: HashMap<String, EffectiveToolPolicy> = self
+            .tools
+            .iter()
+            .map(|tool| {
+                let tool_policy = self.tool_policy_overrides.get(tool.name()).unwrap_or(&no_override);
+                let policy = global_tool_defaults().resolve(&name, &self.tool_defaults, tool.name(), tool_policy);
+                (tool.name().to_string(), policy)
+            })
+            .collect();
+        let config_hash = self.config_hash(&name, &model, &tool_policies);
 
         Ok(LlmAgent {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::utils::new_id(),
             name,
             description: self.description,
             model,
+            profile: self.profile,
             instruction: self.instruction,
+            instruction_provider: self.instruction_provider,
+            instruction_timeout: self.instruction_timeout,
+            examples: self.examples,
+            example_provider: self.example_provider,
+            language_policy: self.language_policy,
             tools: self.tools,
+            tool_config: self.tool_config,
+            single_tool_round: self.single_tool_round,
+            tool_state_policies: self.tool_state_policies,
+            default_tool_state_policy: self.default_tool_state_policy,
+            tool_policies,
+            final_answer: self.final_answer,
+            output_schema: self.output_schema,
+            output_processors: self.output_processors,
+            history_strategy: self.history_strategy,
             sub_agents: self.sub_agents,
             metadata: self.metadata,
+            version: self.version,
+            config_hash,
         })
     }
 }
@@ -262,3 +1132,361 @@ impl Default for LlmAgentBuilder {
 
 /// Type alias for backward compatibility
 pub type Agent = LlmAgent;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::RunConfig,
+        sessions::{InMemorySessionService, SessionService},
+        tools::FunctionTool,
+        types::SessionState,
+    };
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_dry_run_emits_request_without_calling_model() {
+        let agent = LlmAgent::builder()
+            .name("helper")
+            .model("no-such-model")
+            .instruction("Be brief.")
+            .examples([Example::new("2+2?", "4")])
+            .tool(Arc::new(FunctionTool::new("lookup", "Look something up", |_| async {
+                Ok(serde_json::json!(null))
+            })))
+            .build()
+            .unwrap();
+        let mut ctx = InvocationContext::new(
+            "s1".into(),
+            "u1".into(),
+            "app".into(),
+            SessionState::new(),
+            Arc::new(InMemorySessionService::new()),
+        );
+        ctx.run_config = RunConfig::dry_run();
+
+        let events: Vec<_> = agent.run_async(ctx).await.unwrap().collect().await;
+        assert_eq!(events.len(), 1);
+        let request = &events[0].as_ref().unwrap().metadata[DRY_RUN_REQUEST_METADATA_KEY];
+        assert_eq!(request["model"], "no-such-model");
+        assert!(request["system_instruction"].to_string().contains("Be brief."));
+        assert!(request["contents"][0].to_string().contains("2+2?"));
+        assert_eq!(request["contents"][1]["role"], "model");
+        assert!(request.to_string().contains("lookup"));
+    }
+
+    #[tokio::test]
+    async fn test_language_policy_follows_user_language() {
+        let sessions = Arc::new(InMemorySessionService::new());
+        let session = sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        let message = "¿Podrías ayudarme a reservar una mesa para cuatro personas esta noche?";
+        sessions
+            .append_event(&session.id, Arc::new(Event::user_input(message, uuid::Uuid::new_v4())))
+            .await
+            .unwrap();
+
+        let agent = LlmAgent::builder()
+            .name("helper")
+            .model("no-such-model")
+            .language_policy(crate::agents::LanguagePolicy::MatchUser)
+            .build()
+            .unwrap();
+        let mut ctx = InvocationContext::new("s1".into(), "u1".into(), "app".into(), SessionState::new(), sessions);
+        ctx.run_config = RunConfig::dry_run();
+
+        let events: Vec<_> = agent.run_async(ctx).await.unwrap().collect().await;
+        let event = events[0].as_ref().unwrap();
+        assert_eq!(event.metadata[DETECTED_LANGUAGE_METADATA_KEY], "spa");
+        let request = &event.metadata[DRY_RUN_REQUEST_METADATA_KEY];
+        assert!(request["system_instruction"].to_string().contains("Always respond in Spanish"));
+    }
+
+    #[tokio::test]
+    async fn test_feature_flags_hide_tools_and_replace_instruction() {
+        let agent = LlmAgent::builder()
+            .name("flagged_helper")
+            .model("no-such-model")
+            .instruction("Issue refunds.")
+            .tool(Arc::new(FunctionTool::new("flagged_refund", "Issue a refund", |_| async {
+                Ok(serde_json::json!(null))
+            })))
+            .build()
+            .unwrap();
+        global_flags().disable_tool("flagged_refund");
+        global_flags().set_fallback_instruction("flagged_helper", "Refunds are paused.");
+
+        let mut ctx = InvocationContext::new(
+            "s1".into(),
+            "u1".into(),
+            "app".into(),
+            SessionState::new(),
+            Arc::new(InMemorySessionService::new()),
+        );
+        ctx.run_config = RunConfig::dry_run();
+        let events: Vec<_> = agent.run_async(ctx).await.unwrap().collect().await;
+        let request = &events[0].as_ref().unwrap().metadata[DRY_RUN_REQUEST_METADATA_KEY];
+        assert!(request["system_instruction"].to_string().contains("Refunds are paused."));
+        assert!(!request.to_string().contains("Issue refunds."));
+        assert!(!request.to_string().contains("flagged_refund"));
+
+        // The runner refuses disabled agents outright
+        global_flags().disable_agent("flagged_helper");
+        let runner = crate::runners::Runner::new("app", Arc::new(agent), Arc::new(InMemorySessionService::new()));
+        let result = runner.run_async("u1".into(), "s1".into(), Content::user_text("Refund me")).await;
+        assert!(result.is_err());
+
+        global_flags().enable_agent("flagged_helper");
+        global_flags().enable_tool("flagged_refund");
+        global_flags().clear_fallback_instruction("flagged_helper");
+    }
+
+    #[tokio::test]
+    async fn test_tools_access_state_within_their_policy() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-tool-state").await;
+        mock.push_function_call("track_order", serde_json::json!({})).push_text("It shipped.");
+
+        let tool = FunctionTool::with_context("track_order", "Track the order", |_, ctx| async move {
+            let order = ctx.get_state("order:id")?;
+            let secret_denied = ctx.get_state("secret:api_key").is_err();
+            ctx.set_state("order:status", serde_json::json!("shipped"))?;
+            Ok(serde_json::json!({ "order": order, "secret_denied": secret_denied }))
+        });
+        let agent = LlmAgent::builder()
+            .name("helper")
+            .model("mock-gemini-tool-state")
+            .tool(Arc::new(tool))
+            .tool_state_access("track_order", StateAccessPolicy::full().with_prefix("order:"))
+            .build()
+            .unwrap();
+
+        let sessions = Arc::new(InMemorySessionService::new());
+        sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        let state = SessionState::from([
+            ("order:id".to_string(), serde_json::json!("A-1")),
+            ("secret:api_key".to_string(), serde_json::json!("sk-123")),
+        ]);
+        sessions.update_session_state(&"s1".to_string(), &state).await.unwrap();
+
+        let runner = crate::runners::Runner::new("app", Arc::new(agent), sessions);
+        let events: Vec<_> = runner
+            .run_async("u1".into(), "s1".into(), Content::user_text("Where is my order?"))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let response = events
+            .iter()
+            .map(|event| event.as_ref().unwrap())
+            .find(|event| !event.actions.state_delta.is_empty())
+            .unwrap();
+        assert_eq!(response.actions.state_delta["order:status"], "shipped");
+
+        let (_, result) = mock.requests()[1].function_responses().remove(0);
+        assert_eq!(result["order"], "A-1");
+        assert_eq!(result["secret_denied"], true);
+        assert!(!mock.requests()[1].body.to_string().contains("sk-123"));
+    }
+
+    #[tokio::test]
+    async fn test_events_carry_the_running_cost() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-cost").await;
+        mock.push_function_call("lookup", serde_json::json!({})).push_text("Found it.");
+
+        let agent = LlmAgent::builder()
+            .name("helper")
+            .model("mock-gemini-cost")
+            .tool(Arc::new(FunctionTool::new("lookup", "Look something up", |_| async {
+                Ok(serde_json::json!({ "found": true }))
+            })))
+            .build()
+            .unwrap();
+        let sessions = Arc::new(InMemorySessionService::new());
+        sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        sessions
+            .append_event(&"s1".to_string(), Arc::new(Event::user_input("Find it", uuid::Uuid::new_v4())))
+            .await
+            .unwrap();
+        let mut ctx = InvocationContext::new("s1".into(), "u1".into(), "app".into(), SessionState::new(), sessions);
+        let pricing = crate::models::PricingTable::new()
+            .with_price("mock-gemini-cost", crate::models::ModelPricing::new(1_000_000.0, 1_000_000.0));
+        ctx.cost = crate::models::CostTracker::with_pricing(Arc::new(pricing));
+        let cost = ctx.cost.clone();
+
+        let events: Vec<_> = agent.run_async(ctx).await.unwrap().map(|event| event.unwrap()).collect().await;
+        assert_eq!(events.first().unwrap().metadata[COST_METADATA_KEY]["calls"], 1);
+        let last = &events.last().unwrap().metadata[COST_METADATA_KEY];
+        assert_eq!(last["calls"], 2);
+        // One dollar per token
+        let tokens = cost.total().prompt_tokens + cost.total().completion_tokens;
+        assert_eq!(last["cost_usd"], tokens as f64);
+    }
+
+    #[tokio::test]
+    async fn test_instruction_is_sent_as_system_instruction() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-instruction").await;
+        mock.push_text("Bonjour.");
+
+        let agent = LlmAgent::builder()
+            .name("greeter")
+            .model("mock-gemini-instruction")
+            .instruction("Answer in French.")
+            .build()
+            .unwrap();
+        let sessions = Arc::new(InMemorySessionService::new());
+        sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        sessions
+            .append_event(&"s1".to_string(), Arc::new(Event::user_input("Hello", uuid::Uuid::new_v4())))
+            .await
+            .unwrap();
+        let ctx = InvocationContext::new("s1".into(), "u1".into(), "app".into(), SessionState::new(), sessions);
+
+        let events: Vec<_> = agent.run_async(ctx).await.unwrap().map(|event| event.unwrap()).collect().await;
+        assert!(!events.is_empty());
+        let request = &mock.requests()[0];
+        assert_eq!(request.system_instruction().as_deref(), Some("Answer in French."));
+        assert!(request.body["system_instruction"].get("role").is_none());
+        assert_eq!(request.body["contents"].as_array().unwrap().len(), 1);
+        assert_eq!(request.last_user_text().as_deref(), Some("Hello"));
+    }
+
+    #[tokio::test]
+    async fn test_follow_up_keeps_the_tool_config_unless_single_tool_round() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-tool-round").await;
+
+        let cases = [(ToolConfig::auto(), false), (ToolConfig::auto(), true), (ToolConfig::any(), false)];
+        for (tool_config, single_tool_round) in cases {
+            mock.push_function_call("lookup", serde_json::json!({})).push_text("Found it.");
+            let agent = LlmAgent::builder()
+                .name("helper")
+                .model("mock-gemini-tool-round")
+                .tool(Arc::new(FunctionTool::new("lookup", "Look something up", |_| async {
+                    Ok(serde_json::json!({ "found": true }))
+                })))
+                .tool_config(tool_config)
+                .single_tool_round(single_tool_round)
+                .build()
+                .unwrap();
+            let sessions = Arc::new(InMemorySessionService::new());
+            sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+            sessions
+                .append_event(&"s1".to_string(), Arc::new(Event::user_input("Find it", uuid::Uuid::new_v4())))
+                .await
+                .unwrap();
+            let ctx = InvocationContext::new("s1".into(), "u1".into(), "app".into(), SessionState::new(), sessions);
+            let events: Vec<_> = agent.run_async(ctx).await.unwrap().map(|event| event.unwrap()).collect().await;
+            let answer = events.last().and_then(|event| event.content.as_ref()).map(Content::get_text);
+            assert_eq!(answer.as_deref(), Some("Found it."));
+        }
+
+        let modes: Vec<_> = mock
+            .requests()
+            .iter()
+            .map(|request| request.body["tool_config"]["function_calling_config"]["mode"].clone())
+            .collect();
+        // A forced call is lifted on the follow-up so the model can answer
+        assert_eq!(modes, ["AUTO", "AUTO", "AUTO", "NONE", "ANY", "NONE"]);
+    }
+
+    fn answer_agent(model: &str) -> LlmAgentBuilder {
+        LlmAgent::builder()
+            .name("helper")
+            .model(model)
+            .tool(Arc::new(FunctionTool::new("lookup", "Look something up", |_| async {
+                Ok(serde_json::json!({ "found": true }))
+            })))
+            .tool(crate::tools::submit_answer(serde_json::json!({ "type": "object" })))
+    }
+
+    async fn run_agent(agent: LlmAgent) -> Vec<Result<Arc<Event>>> {
+        let sessions = Arc::new(InMemorySessionService::new());
+        sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        sessions
+            .append_event(&"s1".to_string(), Arc::new(Event::user_input("What is it?", uuid::Uuid::new_v4())))
+            .await
+            .unwrap();
+        let ctx = InvocationContext::new("s1".into(), "u1".into(), "app".into(), SessionState::new(), sessions);
+        agent.run_async(ctx).await.unwrap().collect().await
+    }
+
+    #[tokio::test]
+    async fn test_stop_on_tool_ends_the_run_with_the_tool_result() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-stop-on-tool").await;
+        mock.push_function_call("submit_answer", serde_json::json!({ "answer": 42 }))
+            .push_text("Never requested.");
+        let agent = answer_agent("mock-gemini-stop-on-tool").stop_on_tool("submit_answer").build().unwrap();
+
+        let events: Vec<_> = run_agent(agent).await.into_iter().map(|event| event.unwrap()).collect();
+        let last = events.last().unwrap();
+        assert!(last.actions.end_conversation);
+        assert_eq!(last.metadata[FINAL_ANSWER_METADATA_KEY], serde_json::json!({ "answer": 42 }));
+        // No follow-up turn after the final answer
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_require_tool_reprompts_a_text_answer() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-require-tool").await;
+        mock.push_text("It is 42.")
+            .push_function_call("submit_answer", serde_json::json!({ "answer": 42 }));
+        let agent = answer_agent("mock-gemini-require-tool").require_tool("submit_answer", 2).build().unwrap();
+
+        let events: Vec<_> = run_agent(agent).await.into_iter().map(|event| event.unwrap()).collect();
+        let last = events.last().unwrap();
+        assert!(last.actions.end_conversation);
+        assert_eq!(last.metadata[FINAL_ANSWER_METADATA_KEY], serde_json::json!({ "answer": 42 }));
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        // Only the re-prompt is restricted to the answer tool
+        assert!(requests[0].body["tool_config"].is_null());
+        let config = &requests[1].body["tool_config"]["function_calling_config"];
+        assert_eq!(config["mode"], "ANY");
+        assert_eq!(config["allowed_function_names"], serde_json::json!(["submit_answer"]));
+        assert!(requests[1].last_user_text().unwrap().contains("submit_answer"));
+    }
+
+    #[tokio::test]
+    async fn test_require_tool_keeps_the_other_tools_callable() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-require-tool-lookup").await;
+        mock.push_function_call("lookup", serde_json::json!({}))
+            .push_function_call("submit_answer", serde_json::json!({ "answer": 42 }));
+        let agent = answer_agent("mock-gemini-require-tool-lookup").require_tool("submit_answer", 2).build().unwrap();
+
+        let events: Vec<_> = run_agent(agent).await.into_iter().map(|event| event.unwrap()).collect();
+        let looked_up = events.iter().filter_map(|event| event.content.as_ref()).any(|content| {
+            matches!(content.parts.as_slice(), [crate::types::ContentPart::FunctionResponse { name, .. }] if name == "lookup")
+        });
+        assert!(looked_up);
+        let last = events.last().unwrap();
+        assert!(last.actions.end_conversation);
+        assert_eq!(last.metadata[FINAL_ANSWER_METADATA_KEY], serde_json::json!({ "answer": 42 }));
+
+        // The turn after the lookup result must answer through the tool
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        let config = &requests[1].body["tool_config"]["function_calling_config"];
+        assert_eq!(config["mode"], "ANY");
+        assert_eq!(config["allowed_function_names"], serde_json::json!(["submit_answer"]));
+    }
+
+    #[tokio::test]
+    async fn test_require_tool_fails_after_max_reprompts() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-require-tool-fail").await;
+        mock.push_text("It is 42.").push_text("Still 42.");
+        let agent = answer_agent("mock-gemini-require-tool-fail").require_tool("submit_answer", 1).build().unwrap();
+
+        let results = run_agent(agent).await;
+        let error = results.last().unwrap().as_ref().unwrap_err();
+        assert!(matches!(error, crate::error::AdkError::AgentError(_)));
+        assert!(error.to_string().contains("Model did not call required tool 'submit_answer' after 1 re-prompts"));
+        assert_eq!(mock.requests().len(), 2);
+    }
+}
@@ -23,7 +23,7 @@ pub struct LoopAgent {
 impl LoopAgent {
     pub fn new(name: impl Into<String>) -> Self {
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::utils::new_id(),
             name: name.into(),
             description: String::new(),
             sub_agents: Vec::new(),
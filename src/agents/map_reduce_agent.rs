@@ -0,0 +1,468 @@
+//! Map-reduce over inputs too large for one model call
+//!
+//! The input (the user's message, or documents listed in session state) is
+//! split into chunks. The map agent processes each chunk in a scratch session
+//! of its own, a bounded number at a time, and the reduce agent combines the
+//! results in another scratch session. Only the reduce agent's answer joins
+//! the conversation.
+
+use crate::{
+    agents::{BaseAgent, InvocationContext},
+    error::Result,
+    events::Event,
+    sessions::{InMemorySessionService, SessionService},
+    types::{AgentId, Metadata},
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use tracing::{debug, warn};
+
+use super::base_agent::EventStream;
+
+/// Metadata key of the chunk count and map failures on the final answer
+pub const MAP_REDUCE_METADATA_KEY: &str = "map_reduce";
+
+/// Default number of chunks mapped at once
+pub const DEFAULT_MAP_CONCURRENCY: usize = 4;
+
+/// Default message given to the map agent; `{index}`, `{total}` and `{chunk}` are filled in
+pub const DEFAULT_MAP_PROMPT: &str = "Part {index} of {total}:\n\n{chunk}";
+
+/// Default message given to the reduce agent; `{results}` is filled in
+pub const DEFAULT_REDUCE_PROMPT: &str = "Combine the results for each part of the input:\n\n{results}";
+
+/// Where the input comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapReduceSource {
+    /// The user's latest message
+    UserMessage,
+
+    /// A session state value: one document, or an array of documents
+    StateKey(String),
+}
+
+/// How documents are split into chunks
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChunkStrategy {
+    /// Each document is one chunk
+    Whole,
+
+    /// Windows of `size` characters, each repeating the last `overlap` of the previous one
+    Characters { size: usize, overlap: usize },
+
+    /// Consecutive paragraphs grouped up to `max_chars`; longer paragraphs stand alone
+    Paragraphs { max_chars: usize },
+
+    /// Pieces between occurrences of a separator
+    Separator(String),
+}
+
+impl ChunkStrategy {
+    pub fn split(&self, document: &str) -> Vec<String> {
+        let chunks = match self {
+            Self::Whole => vec![document.to_string()],
+            Self::Characters { size, overlap } => {
+                let chars: Vec<char> = document.chars().collect();
+                let size = (*size).max(1);
+                let step = size.saturating_sub(*overlap).max(1);
+                let mut chunks = Vec::new();
+                let mut start = 0;
+                while start < chars.len() {
+                    let end = (start + size).min(chars.len());
+                    chunks.push(chars[start..end].iter().collect());
+                    if end == chars.len() {
+                        break;
+                    }
+                    start += step;
+                }
+                chunks
+            }
+            Self::Paragraphs { max_chars } => {
+                let mut chunks = Vec::new();
+                let mut current = String::new();
+                for paragraph in document.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+                    if !current.is_empty() && current.chars().count() + paragraph.chars().count() + 2 > *max_chars {
+                        chunks.push(std::mem::take(&mut current));
+                    }
+                    if !current.is_empty() {
+                        current.push_str("\n\n");
+                    }
+                    current.push_str(paragraph);
+                }
+                if !current.is_empty() {
+                    chunks.push(current);
+                }
+                chunks
+            }
+            Self::Separator(separator) => document.split(separator.as_str()).map(str::to_string).collect(),
+        };
+        chunks.into_iter().filter(|chunk| !chunk.trim().is_empty()).collect()
+    }
+}
+
+/// A chunk the map agent could not process
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkFailure {
+    /// 1-based position of the chunk
+    pub chunk: usize,
+    pub error: String,
+}
+
+/// Agent mapping chunks of a large input and reducing the results
+pub struct MapReduceAgent {
+    id: AgentId,
+    name: String,
+    description: String,
+    sub_agents: Vec<Box<dyn BaseAgent>>,
+    metadata: Metadata,
+    map_agent: Arc<dyn BaseAgent>,
+    reduce_agent: Arc<dyn BaseAgent>,
+    source: MapReduceSource,
+    chunking: ChunkStrategy,
+    max_concurrency: usize,
+    max_retries: usize,
+    allow_partial: bool,
+    map_prompt: String,
+    reduce_prompt: String,
+}
+
+impl MapReduceAgent {
+    pub fn new(name: impl Into<String>, map_agent: Box<dyn BaseAgent>, reduce_agent: Box<dyn BaseAgent>) -> Self {
+        let map_agent: Arc<dyn BaseAgent> = Arc::from(map_agent);
+        let reduce_agent: Arc<dyn BaseAgent> = Arc::from(reduce_agent);
+        Self {
+            id: crate::utils::new_id(),
+            name: name.into(),
+            description: String::new(),
+            sub_agents: vec![Box::new(map_agent.clone()), Box::new(reduce_agent.clone())],
+            metadata: HashMap::new(),
+            map_agent,
+            reduce_agent,
+            source: MapReduceSource::UserMessage,
+            chunking: ChunkStrategy::Paragraphs { max_chars: 8000 },
+            max_concurrency: DEFAULT_MAP_CONCURRENCY,
+            max_retries: 0,
+            allow_partial: true,
+            map_prompt: DEFAULT_MAP_PROMPT.to_string(),
+            reduce_prompt: DEFAULT_REDUCE_PROMPT.to_string(),
+        }
+    }
+
+    pub fn with_source(mut self, source: MapReduceSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn with_chunking(mut self, chunking: ChunkStrategy) -> Self {
+        self.chunking = chunking;
+        self
+    }
+
+    /// Chunks mapped at once
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Extra attempts for a chunk whose map run fails
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Whether to reduce the chunks that succeeded when others failed; otherwise any failure fails the run
+    pub fn with_partial_results(mut self, allow_partial: bool) -> Self {
+        self.allow_partial = allow_partial;
+        self
+    }
+
+    pub fn with_map_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.map_prompt = prompt.into();
+        self
+    }
+
+    pub fn with_reduce_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.reduce_prompt = prompt.into();
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    async fn documents(&self, ctx: &InvocationContext) -> Result<Vec<String>> {
+        match &self.source {
+            MapReduceSource::UserMessage => Ok(ctx
+                .session_service
+                .get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id)
+                .await?
+                .and_then(|session| {
+                    session
+                        .events
+                        .iter()
+                        .rev()
+                        .find(|event| event.author == "user")
+                        .and_then(|event| event.get_text())
+                })
+                .into_iter()
+                .collect()),
+            MapReduceSource::StateKey(key) => match ctx.state.get(key) {
+                Some(serde_json::Value::String(document)) => Ok(vec![document.clone()]),
+                Some(serde_json::Value::Array(documents)) => Ok(documents
+                    .iter()
+                    .map(|document| match document {
+                        serde_json::Value::String(document) => document.clone(),
+                        other => other.to_string(),
+                    })
+                    .collect()),
+                Some(other) => Ok(vec![other.to_string()]),
+                None => Err(crate::adk_error!(AgentError, "Session state has no '{}' to map over", key)),
+            },
+        }
+    }
+}
+
+/// Run `agent` on `message` in a fresh scratch session, returning its events
+async fn run_scratch(agent: &dyn BaseAgent, ctx: &InvocationContext, message: String) -> Result<EventStream> {
+    let sessions: Arc<dyn SessionService> = Arc::new(InMemorySessionService::new());
+    let session_id = crate::utils::new_id();
+    sessions.get_or_create_session(&ctx.app_name, &ctx.user_id, &session_id).await?;
+    sessions
+        .append_event(&session_id, Arc::new(Event::user_input(message, ctx.invocation_id)))
+        .await?;
+
+    let mut scratch = ctx.clone();
+    scratch.session_id = session_id;
+    scratch.session_service = sessions;
+    agent.run_async(scratch).await
+}
+
+/// Final text of a map run
+async fn map_chunk(agent: &dyn BaseAgent, ctx: &InvocationContext, message: String) -> Result<String> {
+    let mut events = run_scratch(agent, ctx, message).await?;
+    let mut answer = None;
+    while let Some(event) = events.next().await {
+        let event = event?;
+        if !event.is_partial {
+            if let Some(text) = event.get_text().filter(|text| !text.is_empty()) {
+                answer = Some(text);
+            }
+        }
+    }
+    answer.ok_or_else(|| crate::adk_error!(AgentError, "Map agent '{}' produced no answer", agent.name()))
+}
+
+#[async_trait]
+impl BaseAgent for MapReduceAgent {
+    fn id(&self) -> &AgentId {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn parent(&self) -> Option<&dyn BaseAgent> {
+        None
+    }
+
+    fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+        &self.sub_agents
+    }
+
+    async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+        let chunks: Vec<String> = self
+            .documents(&ctx)
+            .await?
+            .iter()
+            .flat_map(|document| self.chunking.split(document))
+            .collect();
+        if chunks.is_empty() {
+            return Err(crate::adk_error!(AgentError, "Nothing to map for '{}'", self.name));
+        }
+        debug!("Mapping {} chunks with '{}'", chunks.len(), self.map_agent.name());
+
+        let total = chunks.len();
+        let map_agent = self.map_agent.clone();
+        let reduce_agent = self.reduce_agent.clone();
+        let max_concurrency = self.max_concurrency;
+        let max_retries = self.max_retries;
+        let allow_partial = self.allow_partial;
+        let map_prompt = self.map_prompt.clone();
+        let reduce_prompt = self.reduce_prompt.clone();
+
+        Ok(Box::pin(stream! {
+            let mapped: Vec<Result<String>> = futures::stream::iter(chunks.into_iter().enumerate())
+                .map(|(index, chunk)| {
+                    let message = map_prompt
+                        .replace("{index}", &(index + 1).to_string())
+                        .replace("{total}", &total.to_string())
+                        .replace("{chunk}", &chunk);
+                    let map_agent = map_agent.clone();
+                    let ctx = ctx.clone();
+                    async move {
+                        let mut attempt = 0;
+                        loop {
+                            match map_chunk(map_agent.as_ref(), &ctx, message.clone()).await {
+                                Ok(result) => return Ok(result),
+                                Err(e) if attempt < max_retries => {
+                                    warn!("Map of chunk {} failed, retrying: {}", index + 1, e);
+                                    attempt += 1;
+                                }
+                                Err(e) => return Err(e),
+                            }
+                        }
+                    }
+                })
+                .buffered(max_concurrency)
+                .collect()
+                .await;
+
+            let mut results = Vec::new();
+            let mut failures = Vec::new();
+            for (index, result) in mapped.into_iter().enumerate() {
+                match result {
+                    Ok(result) => results.push(format!("Part {}:\n{}", index + 1, result)),
+                    Err(e) => failures.push(ChunkFailure { chunk: index + 1, error: e.to_string() }),
+                }
+            }
+            if results.is_empty() || (!failures.is_empty() && !allow_partial) {
+                yield Err(crate::adk_error!(
+                    AgentError,
+                    "Map failed for {} of {} chunks: {}",
+                    failures.len(),
+                    total,
+                    failures.iter().map(|failure| failure.error.as_str()).collect::<Vec<_>>().join("; ")
+                ));
+                return;
+            }
+
+            let summary = serde_json::json!({"chunks": total, "failed": failures});
+            let message = reduce_prompt.replace("{results}", &results.join("\n\n"));
+            let mut events = match run_scratch(reduce_agent.as_ref(), &ctx, message).await {
+                Ok(events) => events,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            while let Some(result) = events.next().await {
+                let mut event = match result {
+                    Ok(event) => event,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                if !event.is_partial {
+                    Arc::make_mut(&mut event)
+                        .metadata
+                        .insert(MAP_REDUCE_METADATA_KEY.to_string(), summary.clone());
+                }
+                yield Ok(event);
+            }
+        }))
+    }
+
+    async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+        self.run_async(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{agents::base_agent::events_to_stream, types::SessionState};
+
+    /// Answers with the transformed latest user message; fails on messages containing "corrupt"
+    struct TextAgent {
+        id: AgentId,
+        metadata: Metadata,
+        transform: fn(&str) -> String,
+    }
+
+    #[async_trait]
+    impl BaseAgent for TextAgent {
+        fn id(&self) -> &AgentId {
+            &self.id
+        }
+        fn name(&self) -> &str {
+            &self.id
+        }
+        fn description(&self) -> &str {
+            ""
+        }
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+        fn parent(&self) -> Option<&dyn BaseAgent> {
+            None
+        }
+        fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+            &[]
+        }
+        async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+            let session = ctx.session_service.get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id).await?;
+            let message = session.and_then(|session| session.events.last().and_then(|event| event.get_text()));
+            let message = message.unwrap_or_default();
+            if message.contains("corrupt") {
+                return Err(crate::adk_error!(ModelError, "unreadable chunk"));
+            }
+            Ok(events_to_stream(vec![Event::text_response(self.id.clone(), (self.transform)(&message))]))
+        }
+        async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+            self.run_async(ctx).await
+        }
+    }
+
+    #[test]
+    fn test_chunk_strategies() {
+        let characters = ChunkStrategy::Characters { size: 4, overlap: 1 };
+        assert_eq!(characters.split("abcdefghij"), ["abcd", "defg", "ghij"]);
+        let paragraphs = ChunkStrategy::Paragraphs { max_chars: 12 };
+        assert_eq!(paragraphs.split("one\n\ntwo\n\nthree four five"), ["one\n\ntwo", "three four five"]);
+        assert_eq!(ChunkStrategy::Separator("---".into()).split("a---b--- "), ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_maps_chunks_and_reduces_partial_results() {
+        let sessions = Arc::new(InMemorySessionService::new());
+        sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        let mut state = SessionState::new();
+        state.insert("documents".into(), serde_json::json!(["alpha", "corrupt", "gamma"]));
+
+        let map = Box::new(TextAgent { id: "map".into(), metadata: Metadata::new(), transform: |m| m.to_uppercase() });
+        let reduce = Box::new(TextAgent { id: "reduce".into(), metadata: Metadata::new(), transform: |m| m.to_string() });
+        let agent = MapReduceAgent::new("digest", map, reduce)
+            .with_source(MapReduceSource::StateKey("documents".into()))
+            .with_chunking(ChunkStrategy::Whole)
+            .with_map_prompt("{chunk}")
+            .with_reduce_prompt("{results}")
+            .with_max_retries(1);
+
+        let ctx = InvocationContext::new("s1".into(), "u1".into(), "app".into(), state.clone(), sessions.clone());
+        let events: Vec<_> = agent.run_async(ctx).await.unwrap().collect().await;
+        let event = events[0].as_ref().unwrap();
+        assert_eq!(event.author, "reduce");
+        assert_eq!(event.get_text().unwrap(), "Part 1:\nALPHA\n\nPart 3:\nGAMMA");
+        assert_eq!(event.metadata[MAP_REDUCE_METADATA_KEY]["chunks"], 3);
+        assert_eq!(event.metadata[MAP_REDUCE_METADATA_KEY]["failed"][0]["chunk"], 2);
+
+        let strict = agent.with_partial_results(false);
+        let ctx = InvocationContext::new("s1".into(), "u1".into(), "app".into(), state, sessions);
+        let events: Vec<_> = strict.run_async(ctx).await.unwrap().collect().await;
+        assert!(events[0].is_err());
+    }
+}
@@ -1,17 +1,76 @@
 //! Agent system for the ADK library
 
 pub mod base_agent;
+pub mod capabilities;
+pub mod debug;
+pub mod ensemble_agent;
+pub mod examples;
+pub mod flags;
+pub mod form_agent;
+pub mod history;
+pub mod instruction;
 pub mod invocation_context;
+pub mod language;
+pub mod live;
 pub mod llm_agent;
 pub mod loop_agent;
+pub mod map_reduce_agent;
+pub mod moderated_agent;
 pub mod parallel_agent;
+pub mod pipeline;
+pub mod reflexion_agent;
+pub mod registry;
 pub mod run_config;
 pub mod sequential_agent;
+pub mod structured_output;
+pub mod translation_agent;
+pub mod vad;
+pub mod validation;
 
-pub use base_agent::BaseAgent;
-pub use invocation_context::{InvocationContext, InvocationContextBuilder};
-pub use llm_agent::{Agent, LlmAgent, LlmAgentBuilder};
+pub use base_agent::{
+    stamp_agent_version, BaseAgent, AGENT_CONFIG_HASH_METADATA_KEY, AGENT_VERSION_METADATA_KEY,
+};
+pub use capabilities::AgentCapabilities;
+pub use debug::{global_debugger, Breakpoints, DebugCommand, Debugger, PausePoint, PausedStep, DEBUG_PAUSE_METADATA_KEY};
+pub use ensemble_agent::{
+    EnsembleAgent, EnsembleAggregator, MemberAnswer, DEFAULT_MERGE_INSTRUCTION, ENSEMBLE_METADATA_KEY,
+};
+pub use examples::{example_contents, Example, ExampleProvider, SimilarExamples};
+pub use flags::{global_flags, FeatureFlags, FlagSet, DISABLED_AGENTS_ENV_VAR, DISABLED_TOOLS_ENV_VAR};
+pub use form_agent::{
+    FieldType, FormAgent, FormField, FormProgress, LlmSlotExtractor, SlotExtractor, DEFAULT_FORM_COMPLETION_MESSAGE,
+    FORM_RESULT_METADATA_KEY,
+};
+pub use history::{FullHistory, HistoryStrategy, RelevantHistory};
+pub use instruction::{InstructionProvider, ReadonlyContext, DEFAULT_INSTRUCTION_PROVIDER_TIMEOUT};
+pub use invocation_context::{instrument_events, InvocationContext, InvocationContextBuilder};
+pub use language::{detect_language, DetectedLanguage, LanguagePolicy, DETECTED_LANGUAGE_METADATA_KEY};
+pub use live::{live_events, run_live_connection, LiveRequest, LiveRequestQueue};
+pub use llm_agent::{
+    Agent, FinalAnswerMode, DRY_RUN_REQUEST_METADATA_KEY, FINISH_REASON_METADATA_KEY, LlmAgent, LlmAgentBuilder,
+};
 pub use loop_agent::LoopAgent;
+pub use map_reduce_agent::{
+    ChunkFailure, ChunkStrategy, MapReduceAgent, MapReduceSource, DEFAULT_MAP_CONCURRENCY, DEFAULT_MAP_PROMPT,
+    DEFAULT_REDUCE_PROMPT, MAP_REDUCE_METADATA_KEY,
+};
+pub use moderated_agent::{ModeratedAgent, MODERATION_METADATA_KEY};
 pub use parallel_agent::ParallelAgent;
-pub use run_config::RunConfig;
+pub use pipeline::{global_pipelines, PipelineAgentStep, PipelineRegistry, PipelineStep, PipelineTemplate};
+pub use reflexion_agent::{
+    AcceptanceCriteria, Critique, ReflexionAgent, CRITIQUE_FORMAT_INSTRUCTION, DEFAULT_MAX_REVISIONS, REFLEXION_METADATA_KEY,
+};
+pub use registry::{AgentRegistry, DEFAULT_AGENT_VERSION};
+pub use run_config::{is_deterministic, with_determinism, RunConfig};
 pub use sequential_agent::SequentialAgent;
+pub use structured_output::{OutputSchema, StructuredOutput, STRUCTURED_OUTPUT_METADATA_KEY};
+pub use translation_agent::{
+    LlmTranslator, TranslationAgent, Translator, TRANSLATED_TO_METADATA_KEY, TRANSLATION_ORIGINAL_METADATA_KEY,
+};
+pub use validation::{
+    FnValidator, JsonSchemaValidator, RegexValidator, ResponseValidation, ResponseValidator, ValidationFailure,
+    DEFAULT_VALIDATION_RETRIES, VALIDATION_FAILURE_METADATA_KEY,
+};
+pub use vad::{
+    pcm_sample_rate, ActivityDetection, VadConfig, VoiceActivity, VoiceActivityDetector, DEFAULT_PCM_SAMPLE_RATE,
+};
@@ -0,0 +1,283 @@
+//! Moderation guardrail agent
+//!
+//! Checks the user's message before the wrapped agent runs and the wrapped
+//! agent's answers before they are sent, replacing flagged content with the
+//! policy's refusal.
+
+use crate::{
+    agents::{BaseAgent, InvocationContext},
+    error::Result,
+    events::{Event, Handoff},
+    models::moderation::{ModerationPolicy, ModerationViolation, Moderator},
+    types::{AgentId, Content, Metadata},
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::{collections::HashMap, sync::Arc};
+use tracing::warn;
+
+use super::base_agent::EventStream;
+
+/// Metadata key holding the violations that caused a refusal
+pub const MODERATION_METADATA_KEY: &str = "moderation";
+
+/// Agent that moderates the input and output of a wrapped agent
+pub struct ModeratedAgent {
+    id: AgentId,
+    name: String,
+    description: String,
+    sub_agents: Vec<Box<dyn BaseAgent>>,
+    metadata: Metadata,
+    moderator: Arc<dyn Moderator>,
+    policy: Arc<ModerationPolicy>,
+    check_output: bool,
+    handoff: bool,
+}
+
+impl ModeratedAgent {
+    /// Moderate both the user's messages and `agent`'s answers
+    pub fn new(
+        name: impl Into<String>,
+        agent: Box<dyn BaseAgent>,
+        moderator: Arc<dyn Moderator>,
+        policy: ModerationPolicy,
+    ) -> Self {
+        Self {
+            id: crate::utils::new_id(),
+            name: name.into(),
+            description: String::new(),
+            sub_agents: vec![agent],
+            metadata: HashMap::new(),
+            moderator,
+            policy: Arc::new(policy),
+            check_output: true,
+            handoff: false,
+        }
+    }
+
+    /// Whether answers are moderated too. Output moderation holds back
+    /// streamed deltas, since they cannot be retracted once sent.
+    pub fn with_output_checks(mut self, enabled: bool) -> Self {
+        self.check_output = enabled;
+        self
+    }
+
+    /// Whether refusals hand the conversation over to a human operator
+    pub fn with_handoff(mut self, enabled: bool) -> Self {
+        self.handoff = enabled;
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    fn agent(&self) -> &dyn BaseAgent {
+        self.sub_agents[0].as_ref()
+    }
+}
+
+/// Violations of `policy` in `text`; moderation failures are logged and let the text through
+async fn check(moderator: &dyn Moderator, policy: &ModerationPolicy, text: &str) -> Vec<ModerationViolation> {
+    match moderator.moderate(text).await {
+        Ok(result) => policy.violations(&result),
+        Err(e) => {
+            warn!("Content moderation failed, letting the text through: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Replace `event`'s content with the refusal for `violations`, optionally requesting a handoff
+fn refuse(event: &mut Event, policy: &ModerationPolicy, violations: Vec<ModerationViolation>, handoff: bool) {
+    event.content = Some(Content::model_text(policy.refusal(&violations[0])));
+    event.citations = None;
+    if handoff {
+        event.actions.handoff = Some(Handoff::new(format!("Moderation flagged {}", violations[0].category)));
+    }
+    event.metadata.insert(
+        MODERATION_METADATA_KEY.to_string(),
+        serde_json::to_value(violations).unwrap_or_default(),
+    );
+}
+
+#[async_trait]
+impl BaseAgent for ModeratedAgent {
+    fn id(&self) -> &AgentId {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn parent(&self) -> Option<&dyn BaseAgent> {
+        None
+    }
+
+    fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+        &self.sub_agents
+    }
+
+    async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+        let input = ctx
+            .session_service
+            .get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id)
+            .await?
+            .and_then(|session| {
+                session
+                    .events
+                    .iter()
+                    .rev()
+                    .find(|event| event.author == "user")
+                    .and_then(|event| event.get_text())
+            })
+            .filter(|text| !text.is_empty());
+        if let Some(input) = input {
+            let violations = check(self.moderator.as_ref(), &self.policy, &input).await;
+            if !violations.is_empty() {
+                let mut refusal = Event::text_response(self.name.clone(), "");
+                refusal.invocation_id = ctx.invocation_id;
+                refuse(&mut refusal, &self.policy, violations, self.handoff);
+                return Ok(Box::pin(futures::stream::once(async move { Ok(Arc::new(refusal)) })));
+            }
+        }
+
+        let mut events = self.agent().run_async(ctx).await?;
+        if !self.check_output {
+            return Ok(events);
+        }
+
+        let moderator = self.moderator.clone();
+        let policy = self.policy.clone();
+        let handoff = self.handoff;
+        Ok(Box::pin(stream! {
+            while let Some(result) = events.next().await {
+                let mut event = match result {
+                    Ok(event) => event,
+                    Err(e) => {
+                        yield Err(e);
+                        continue;
+                    }
+                };
+                let Some(text) = event.get_text().filter(|text| !text.is_empty()) else {
+                    yield Ok(event);
+                    continue;
+                };
+                if event.is_partial {
+                    continue;
+                }
+                let violations = check(moderator.as_ref(), &policy, &text).await;
+                if !violations.is_empty() {
+                    refuse(Arc::make_mut(&mut event), &policy, violations, handoff);
+                }
+                yield Ok(event);
+            }
+        }))
+    }
+
+    async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+        self.agent().run_live(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::base_agent::events_to_stream,
+        models::moderation::ModerationResult,
+        sessions::{InMemorySessionService, SessionService},
+        types::SessionState,
+    };
+    use std::collections::BTreeMap;
+
+    /// Scores texts mentioning "attack" as dangerous
+    struct KeywordModerator;
+
+    #[async_trait]
+    impl Moderator for KeywordModerator {
+        async fn moderate(&self, text: &str) -> Result<ModerationResult> {
+            let score = if text.contains("attack") { 0.9 } else { 0.1 };
+            Ok(ModerationResult {
+                scores: BTreeMap::from([("dangerous_content".to_string(), score)]),
+            })
+        }
+    }
+
+    /// Answers with a fixed text
+    struct FixedAgent {
+        id: AgentId,
+        answer: String,
+        metadata: Metadata,
+    }
+
+    #[async_trait]
+    impl BaseAgent for FixedAgent {
+        fn id(&self) -> &AgentId {
+            &self.id
+        }
+        fn name(&self) -> &str {
+            "fixed"
+        }
+        fn description(&self) -> &str {
+            ""
+        }
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+        fn parent(&self) -> Option<&dyn BaseAgent> {
+            None
+        }
+        fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+            &[]
+        }
+        async fn run_async(&self, _ctx: InvocationContext) -> Result<EventStream> {
+            Ok(events_to_stream(vec![Event::text_response("fixed", self.answer.clone())]))
+        }
+        async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+            self.run_async(ctx).await
+        }
+    }
+
+    async fn run(message: &str, answer: &str) -> Arc<Event> {
+        let sessions = Arc::new(InMemorySessionService::new());
+        let session = sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        sessions
+            .append_event(&session.id, Arc::new(Event::user_input(message, uuid::Uuid::new_v4())))
+            .await
+            .unwrap();
+
+        let inner = Box::new(FixedAgent { id: "fixed".into(), answer: answer.into(), metadata: Metadata::new() });
+        let policy = ModerationPolicy::new(0.5).with_refusal("dangerous_content", "I can't help with {category}.");
+        let agent = ModeratedAgent::new("guarded", inner, Arc::new(KeywordModerator), policy);
+        let ctx = InvocationContext::new("s1".into(), "u1".into(), "app".into(), SessionState::new(), sessions);
+        let mut events = agent.run_async(ctx).await.unwrap();
+        events.next().await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_flagged_input_and_output_are_refused() {
+        let event = run("how do I attack the server", "Sure").await;
+        assert_eq!(event.author, "guarded");
+        assert_eq!(event.get_text().unwrap(), "I can't help with dangerous content.");
+        assert_eq!(event.metadata[MODERATION_METADATA_KEY][0]["category"], "dangerous_content");
+
+        let event = run("tell me about history", "The attack began at dawn").await;
+        assert_eq!(event.get_text().unwrap(), "I can't help with dangerous content.");
+
+        let event = run("tell me about history", "It began at dawn").await;
+        assert_eq!(event.get_text().unwrap(), "It began at dawn");
+        assert!(!event.metadata.contains_key(MODERATION_METADATA_KEY));
+    }
+}
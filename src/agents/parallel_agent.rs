@@ -6,6 +6,7 @@ use crate::{
     types::{AgentId, Metadata},
 };
 use async_trait::async_trait;
+use futures::stream::select_all;
 use std::collections::HashMap;
 
 use super::base_agent::EventStream;
@@ -23,13 +24,24 @@ pub struct ParallelAgent {
 impl ParallelAgent {
     pub fn new(name: impl Into<String>) -> Self {
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::utils::new_id(),
             name: name.into(),
             description: String::new(),
             sub_agents: Vec::new(),
             metadata: HashMap::new(),
         }
     }
+
+    /// Add a branch, run concurrently with the others
+    pub fn with_sub_agent(mut self, agent: Box<dyn BaseAgent>) -> Self {
+        self.sub_agents.push(agent);
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
 }
 
 #[async_trait]
@@ -58,9 +70,14 @@ impl BaseAgent for ParallelAgent {
         &self.sub_agents
     }
 
-    async fn run_async(&self, _ctx: InvocationContext) -> Result<EventStream> {
-        // TODO: Implement parallel execution
-        todo!("Parallel agent execution not implemented")
+    async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+        // Branches see the conversation up to this point; their events are
+        // interleaved in the order they are produced
+        let mut branches = Vec::with_capacity(self.sub_agents.len());
+        for agent in &self.sub_agents {
+            branches.push(agent.run_async(ctx.clone()).await?);
+        }
+        Ok(Box::pin(select_all(branches)))
     }
 
     async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
@@ -0,0 +1,399 @@
+//! Named pipelines loaded from `adk.toml`
+//!
+//! A pipeline is a reusable workflow template whose steps run in order, each
+//! seeing the output of the steps before it. Step fields may contain
+//! `{param}` slots, filled in when the pipeline is instantiated so the same
+//! template can run with different models or instructions:
+//!
+//! ```toml
+//! [pipelines.research_report]
+//! description = "Research a topic and write a report"
+//! params = ["topic", "model"]
+//! model = "{model}"
+//!
+//! [pipelines.research_report.defaults]
+//! model = "gemini-2.0-flash"
+//!
+//! [[pipelines.research_report.steps]]
+//! name = "research"
+//! instruction = "Collect the key facts about {topic}."
+//!
+//! [[pipelines.research_report.steps]]
+//! name = "draft"
+//! instruction = "Write a report on {topic} from the facts above."
+//!
+//! [[pipelines.research_report.steps]]
+//! parallel = [
+//!     { name = "accuracy_critique", instruction = "Point out factual errors in the draft." },
+//!     { name = "style_critique", instruction = "Point out unclear passages in the draft." },
+//! ]
+//!
+//! [[pipelines.research_report.steps]]
+//! name = "finalize"
+//! instruction = "Rewrite the draft addressing the critiques."
+//! ```
+//!
+//! Pipelines setting `agent` and `version` are versions of one agent, e.g.
+//! `adk eval research_report --agent-version v2`.
+
+use crate::{
+    agents::{AgentRegistry, BaseAgent, LlmAgent, ParallelAgent, SequentialAgent},
+    error::Result,
+    models::profiles::{CONFIG_PATH_ENV_VAR, DEFAULT_CONFIG_FILE},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+use tracing::{debug, info};
+
+use super::base_agent::AgentBuilder;
+
+/// An LLM agent step of a pipeline
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipelineAgentStep {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub instruction: String,
+
+    /// Model of this step; the pipeline's model if unset
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Model profile of this step; the pipeline's profile if unset
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// A pipeline step: one agent, or several agents run concurrently
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PipelineStep {
+    Parallel {
+        #[serde(default)]
+        name: Option<String>,
+        parallel: Vec<PipelineAgentStep>,
+    },
+    Agent(PipelineAgentStep),
+}
+
+/// A reusable workflow with parameter slots
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipelineTemplate {
+    #[serde(default)]
+    pub description: String,
+
+    /// Names of the `{param}` slots
+    #[serde(default)]
+    pub params: Vec<String>,
+
+    /// Values of parameters that need not be given
+    #[serde(default)]
+    pub defaults: HashMap<String, String>,
+
+    /// Model of steps that do not name one
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Model profile of steps that name neither a model nor a profile
+    #[serde(default)]
+    pub profile: Option<String>,
+
+    /// Registry name of the agent this pipeline is a version of; the pipeline name if unset
+    #[serde(default)]
+    pub agent: Option<String>,
+
+    /// Agent version the pipeline is registered under
+    #[serde(default)]
+    pub version: Option<String>,
+
+    pub steps: Vec<PipelineStep>,
+}
+
+impl PipelineTemplate {
+    /// Parameter values for `args`, failing on unknown or missing parameters
+    pub fn bind(&self, args: &HashMap<String, String>) -> Result<HashMap<String, String>> {
+        if let Some(unknown) = args.keys().find(|name| !self.params.contains(name)) {
+            return Err(crate::adk_error!(
+                ValidationError,
+                "Unknown pipeline parameter '{}'; expected one of: {}",
+                unknown,
+                self.params.join(", ")
+            ));
+        }
+
+        let mut values = HashMap::new();
+        for name in &self.params {
+            let value = args.get(name).or_else(|| self.defaults.get(name)).ok_or_else(|| {
+                crate::adk_error!(ValidationError, "Pipeline parameter '{}' is required", name)
+            })?;
+            values.insert(name.clone(), value.clone());
+        }
+        Ok(values)
+    }
+
+    /// Build the pipeline as an agent named `name`
+    pub fn instantiate(&self, name: &str, args: &HashMap<String, String>) -> Result<SequentialAgent> {
+        let values = self.bind(args)?;
+        let fill = |text: &str| substitute(text, &values);
+
+        let mut pipeline = SequentialAgent::new(name).with_description(fill(&self.description));
+        if let Some(version) = &self.version {
+            pipeline = pipeline.with_version(version.clone());
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            let agent: Box<dyn BaseAgent> = match step {
+                PipelineStep::Agent(step) => Box::new(self.build_step(step, &fill)?),
+                PipelineStep::Parallel { name: group, parallel } => {
+                    let group = group.clone().unwrap_or_else(|| format!("{}_step_{}", name, index + 1));
+                    let mut branches = ParallelAgent::new(group);
+                    for step in parallel {
+                        branches = branches.with_sub_agent(Box::new(self.build_step(step, &fill)?));
+                    }
+                    Box::new(branches)
+                }
+            };
+            pipeline = pipeline.with_sub_agent(agent);
+        }
+        Ok(pipeline)
+    }
+
+    fn build_step(&self, step: &PipelineAgentStep, fill: &dyn Fn(&str) -> String) -> Result<LlmAgent> {
+        let mut builder = LlmAgent::builder()
+            .name(fill(&step.name))
+            .instruction(fill(&step.instruction));
+        if let Some(description) = &step.description {
+            builder = builder.description(fill(description));
+        }
+
+        let (model, profile) = if step.model.is_some() || step.profile.is_some() {
+            (&step.model, &step.profile)
+        } else {
+            (&self.model, &self.profile)
+        };
+        if let Some(model) = model {
+            builder = builder.model(fill(model));
+        }
+        if let Some(profile) = profile {
+            builder = builder.profile(fill(profile));
+        }
+        builder.build()
+    }
+}
+
+/// Replace the `{param}` slots of `text`; other braces are left for
+/// instruction templating at run time
+fn substitute(text: &str, values: &HashMap<String, String>) -> String {
+    values.iter().fold(text.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+/// On-disk layout of the pipelines section of `adk.toml`
+#[derive(Debug, Default, Deserialize)]
+struct PipelinesFile {
+    #[serde(default)]
+    pipelines: HashMap<String, PipelineTemplate>,
+}
+
+/// Collection of named pipelines
+#[derive(Debug, Clone, Default)]
+pub struct PipelineRegistry {
+    pipelines: Arc<RwLock<HashMap<String, PipelineTemplate>>>,
+}
+
+impl PipelineRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load pipelines from a TOML file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file: PipelinesFile = config::Config::builder()
+            .add_source(config::File::from(path).format(config::FileFormat::Toml))
+            .build()?
+            .try_deserialize()?;
+
+        info!("Loaded {} pipelines from {}", file.pipelines.len(), path.display());
+
+        Ok(Self {
+            pipelines: Arc::new(RwLock::new(file.pipelines)),
+        })
+    }
+
+    /// Load pipelines from `$ADK_CONFIG` or `./adk.toml`, if present
+    pub fn load_default() -> Result<Self> {
+        let path = std::env::var(CONFIG_PATH_ENV_VAR).unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+        if Path::new(&path).exists() {
+            Self::from_file(path)
+        } else {
+            debug!("No {} found, starting with no pipelines", path);
+            Ok(Self::new())
+        }
+    }
+
+    /// Define or replace a pipeline
+    pub fn set(&self, name: impl Into<String>, template: PipelineTemplate) {
+        let mut pipelines = self.pipelines.write().expect("pipelines lock poisoned");
+        pipelines.insert(name.into(), template);
+    }
+
+    /// Get a pipeline by name
+    pub fn get(&self, name: &str) -> Option<PipelineTemplate> {
+        let pipelines = self.pipelines.read().expect("pipelines lock poisoned");
+        pipelines.get(name).cloned()
+    }
+
+    /// Get a pipeline by name, failing if it is not defined
+    pub fn resolve(&self, name: &str) -> Result<PipelineTemplate> {
+        self.get(name)
+            .ok_or_else(|| crate::adk_error!(ConfigError, "Pipeline '{}' is not defined", name))
+    }
+
+    /// List pipeline names
+    pub fn names(&self) -> Vec<String> {
+        let pipelines = self.pipelines.read().expect("pipelines lock poisoned");
+        pipelines.keys().cloned().collect()
+    }
+
+    /// Build the named pipeline with the given parameter values
+    pub fn instantiate(&self, name: &str, args: &HashMap<String, String>) -> Result<SequentialAgent> {
+        self.resolve(name)?.instantiate(name, args)
+    }
+
+    /// Build every pipeline that needs no parameters into a registry of agents.
+    ///
+    /// Pipelines are registered in name order, so the first version by name
+    /// is an agent's default.
+    pub fn agents(&self) -> Result<AgentRegistry> {
+        let registry = AgentRegistry::new();
+        let mut names = self.names();
+        names.sort();
+        for name in names {
+            let template = self.resolve(&name)?;
+            if template.params.iter().any(|param| !template.defaults.contains_key(param)) {
+                debug!("Pipeline '{}' has required parameters, not registering it as an agent", name);
+                continue;
+            }
+            let agent = template.instantiate(&name, &HashMap::new())?;
+            registry.register(template.agent.clone().unwrap_or(name), Arc::new(agent));
+        }
+        Ok(registry)
+    }
+}
+
+/// Global pipelines instance, loaded from `adk.toml` on first use
+static GLOBAL_PIPELINES: once_cell::sync::Lazy<PipelineRegistry> = once_cell::sync::Lazy::new(|| {
+    PipelineRegistry::load_default().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load pipelines: {}", e);
+        PipelineRegistry::new()
+    })
+});
+
+/// Get the global pipelines
+pub fn global_pipelines() -> &'static PipelineRegistry {
+    &GLOBAL_PIPELINES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_load_and_instantiate_pipeline() {
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(
+            file,
+            r#"
+[pipelines.research_report]
+description = "Report on {{topic}}"
+params = ["topic", "model"]
+model = "{{model}}"
+
+[pipelines.research_report.defaults]
+model = "gemini-2.0-flash"
+
+[[pipelines.research_report.steps]]
+name = "research"
+instruction = "Collect facts about {{topic}} for {{audience}}."
+
+[[pipelines.research_report.steps]]
+parallel = [
+    {{ name = "accuracy", instruction = "Check facts." }},
+    {{ name = "style", instruction = "Check style.", profile = "cheap" }},
+]
+
+[[pipelines.research_report.steps]]
+name = "finalize"
+model = "gemini-1.5-pro"
+"#
+        )
+        .unwrap();
+
+        let pipelines = PipelineRegistry::from_file(file.path()).unwrap();
+        let template = pipelines.resolve("research_report").unwrap();
+        assert_eq!(template.steps.len(), 3);
+        assert!(matches!(&template.steps[1], PipelineStep::Parallel { parallel, .. } if parallel.len() == 2));
+
+        let args = HashMap::from([("topic".to_string(), "tides".to_string())]);
+        let agent = pipelines.instantiate("research_report", &args).unwrap();
+        assert_eq!(agent.description(), "Report on tides");
+        let steps: Vec<_> = agent.sub_agents().iter().map(|step| step.name().to_string()).collect();
+        assert_eq!(steps, ["research", "research_report_step_2", "finalize"]);
+        assert_eq!(agent.sub_agents()[1].sub_agents().len(), 2);
+
+        // Undeclared slots are left for run-time instruction templating
+        let bound = template.bind(&args).unwrap();
+        assert_eq!(
+            substitute("Collect facts about {topic} for {audience}.", &bound),
+            "Collect facts about tides for {audience}."
+        );
+        assert_eq!(bound["model"], "gemini-2.0-flash");
+
+        assert!(pipelines.instantiate("research_report", &HashMap::new()).is_err());
+        let unknown = HashMap::from([("topic".to_string(), "tides".to_string()), ("tone".to_string(), "dry".to_string())]);
+        assert!(pipelines.instantiate("research_report", &unknown).is_err());
+        assert!(pipelines.instantiate("summary", &args).is_err());
+    }
+
+    #[test]
+    fn test_pipeline_versions_register_as_one_agent() {
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(
+            file,
+            r#"
+[pipelines.triage]
+model = "gemini-2.0-flash"
+version = "v1"
+steps = [{{ name = "classify", instruction = "Classify the ticket." }}]
+
+[pipelines.triage_v2]
+model = "gemini-2.0-flash"
+agent = "triage"
+version = "v2"
+steps = [{{ name = "classify", instruction = "Classify the ticket by urgency." }}]
+
+[pipelines.report]
+model = "gemini-2.0-flash"
+params = ["topic"]
+steps = [{{ name = "write", instruction = "Write about {{topic}}." }}]
+"#
+        )
+        .unwrap();
+
+        let agents = PipelineRegistry::from_file(file.path()).unwrap().agents().unwrap();
+        // Pipelines with required parameters cannot run unattended
+        assert_eq!(agents.names(), ["triage"]);
+        assert_eq!(agents.versions("triage"), ["v1", "v2"]);
+        assert_eq!(agents.default_version("triage").as_deref(), Some("v1"));
+        assert_eq!(agents.get("triage", Some("v2")).unwrap().version(), Some("v2"));
+    }
+}
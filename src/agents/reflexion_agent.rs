@@ -0,0 +1,339 @@
+//! Generate → critique → revise loop
+//!
+//! The generator drafts an answer, the critic reviews it and replies with a
+//! structured critique, and the generator revises until the critique meets
+//! the acceptance criteria or the revision budget runs out. Both agents see
+//! the whole exchange in the session, so revisions can address the feedback.
+
+use crate::{
+    agents::{BaseAgent, InvocationContext},
+    error::Result,
+    types::{AgentId, Metadata},
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use tracing::{debug, warn};
+
+use super::base_agent::EventStream;
+
+/// Metadata key of the parsed critique on the critic's events
+pub const REFLEXION_METADATA_KEY: &str = "reflexion";
+
+/// Default number of revisions after the first draft
+pub const DEFAULT_MAX_REVISIONS: usize = 3;
+
+/// Instruction for critics describing the structured output the loop expects
+pub const CRITIQUE_FORMAT_INSTRUCTION: &str = "Review the latest draft. Reply with only a JSON object: \
+{\"accepted\": true or false, \"score\": a number from 0 to 1, \"feedback\": \"what to change\"}.";
+
+/// A critic's structured verdict on a draft
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Critique {
+    #[serde(default)]
+    pub accepted: Option<bool>,
+
+    #[serde(default)]
+    pub score: Option<f32>,
+
+    #[serde(default)]
+    pub feedback: String,
+}
+
+impl Critique {
+    /// Parse a critic's reply, allowing a fenced JSON block
+    pub fn parse(text: &str) -> Result<Self> {
+        let json = text
+            .trim()
+            .trim_start_matches("```json")
+            .trim_start_matches("```")
+            .trim_end_matches("```")
+            .trim();
+        serde_json::from_str(json)
+            .map_err(|e| crate::adk_error!(AgentError, "Critique is not the expected JSON object: {}", e))
+    }
+}
+
+/// When a critique ends the loop
+#[derive(Debug, Clone, PartialEq)]
+pub enum AcceptanceCriteria {
+    /// The critic set `accepted` to true
+    Accepted,
+
+    /// The critic scored the draft at least this high
+    MinScore(f32),
+
+    /// The critic accepted the draft and scored it at least this high
+    AcceptedWithMinScore(f32),
+}
+
+impl AcceptanceCriteria {
+    pub fn is_met(&self, critique: &Critique) -> bool {
+        let accepted = critique.accepted == Some(true);
+        let scored = |min: f32| critique.score.is_some_and(|score| score >= min);
+        match self {
+            Self::Accepted => accepted,
+            Self::MinScore(min) => scored(*min),
+            Self::AcceptedWithMinScore(min) => accepted && scored(*min),
+        }
+    }
+}
+
+/// Agent alternating a generator and a critic until the critic is satisfied
+pub struct ReflexionAgent {
+    id: AgentId,
+    name: String,
+    description: String,
+    sub_agents: Vec<Box<dyn BaseAgent>>,
+    metadata: Metadata,
+    generator: Arc<dyn BaseAgent>,
+    critic: Arc<dyn BaseAgent>,
+    max_revisions: usize,
+    acceptance: AcceptanceCriteria,
+}
+
+impl ReflexionAgent {
+    pub fn new(name: impl Into<String>, generator: Box<dyn BaseAgent>, critic: Box<dyn BaseAgent>) -> Self {
+        let generator: Arc<dyn BaseAgent> = Arc::from(generator);
+        let critic: Arc<dyn BaseAgent> = Arc::from(critic);
+        Self {
+            id: crate::utils::new_id(),
+            name: name.into(),
+            description: String::new(),
+            sub_agents: vec![Box::new(generator.clone()), Box::new(critic.clone())],
+            metadata: HashMap::new(),
+            generator,
+            critic,
+            max_revisions: DEFAULT_MAX_REVISIONS,
+            acceptance: AcceptanceCriteria::Accepted,
+        }
+    }
+
+    /// Revisions allowed after the first draft; 0 drafts and critiques once
+    pub fn with_max_revisions(mut self, max_revisions: usize) -> Self {
+        self.max_revisions = max_revisions;
+        self
+    }
+
+    pub fn with_acceptance(mut self, acceptance: AcceptanceCriteria) -> Self {
+        self.acceptance = acceptance;
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+}
+
+#[async_trait]
+impl BaseAgent for ReflexionAgent {
+    fn id(&self) -> &AgentId {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn parent(&self) -> Option<&dyn BaseAgent> {
+        None
+    }
+
+    fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+        &self.sub_agents
+    }
+
+    async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+        let generator = self.generator.clone();
+        let critic = self.critic.clone();
+        let max_revisions = self.max_revisions;
+        let acceptance = self.acceptance.clone();
+
+        // Like a sequential agent, each turn starts once the runner has
+        // persisted the previous one, so it sees the latest draft or critique
+        Ok(Box::pin(stream! {
+            for revision in 0..=max_revisions {
+                let mut events = match generator.run_async(ctx.clone()).await {
+                    Ok(events) => events,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                while let Some(result) = events.next().await {
+                    let failed = result.is_err();
+                    yield result;
+                    if failed {
+                        return;
+                    }
+                }
+
+                let mut events = match critic.run_async(ctx.clone()).await {
+                    Ok(events) => events,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                let mut critique = None;
+                while let Some(result) = events.next().await {
+                    let mut event = match result {
+                        Ok(event) => event,
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    };
+                    if !event.is_partial {
+                        if let Some(text) = event.get_text().filter(|text| !text.is_empty()) {
+                            match Critique::parse(&text) {
+                                Ok(parsed) => {
+                                    Arc::make_mut(&mut event).metadata.insert(
+                                        REFLEXION_METADATA_KEY.to_string(),
+                                        serde_json::json!({
+                                            "revision": revision,
+                                            "critique": parsed,
+                                            "accepted": acceptance.is_met(&parsed),
+                                        }),
+                                    );
+                                    critique = Some(parsed);
+                                }
+                                Err(e) => warn!("Ignoring unstructured critique: {}", e),
+                            }
+                        }
+                    }
+                    yield Ok(event);
+                }
+
+                if critique.as_ref().is_some_and(|critique| acceptance.is_met(critique)) {
+                    debug!("Draft accepted after {} revisions", revision);
+                    return;
+                }
+            }
+            debug!("Revision budget of {} exhausted", max_revisions);
+        }))
+    }
+
+    async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+        self.run_async(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::base_agent::events_to_stream, events::Event, runners::Runner, sessions::InMemorySessionService,
+        types::Content,
+    };
+
+    /// Replies with the next of its scripted answers, keyed by how many it gave before
+    struct ScriptedAgent {
+        id: AgentId,
+        name: String,
+        metadata: Metadata,
+        replies: Vec<String>,
+    }
+
+    impl ScriptedAgent {
+        fn boxed(name: &str, replies: &[&str]) -> Box<dyn BaseAgent> {
+            Box::new(Self {
+                id: name.into(),
+                name: name.into(),
+                metadata: Metadata::new(),
+                replies: replies.iter().map(|reply| reply.to_string()).collect(),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl BaseAgent for ScriptedAgent {
+        fn id(&self) -> &AgentId {
+            &self.id
+        }
+        fn name(&self) -> &str {
+            &self.name
+        }
+        fn description(&self) -> &str {
+            ""
+        }
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+        fn parent(&self) -> Option<&dyn BaseAgent> {
+            None
+        }
+        fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+            &[]
+        }
+        async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+            let session = ctx.session_service.get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id).await?;
+            let turn = session
+                .map(|session| session.events.iter().filter(|event| event.author == self.name).count())
+                .unwrap_or_default();
+            Ok(events_to_stream(vec![Event::text_response(self.name.clone(), self.replies[turn].clone())]))
+        }
+        async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+            self.run_async(ctx).await
+        }
+    }
+
+    async fn run(agent: ReflexionAgent) -> Vec<Arc<Event>> {
+        let runner = Runner::new("app", Arc::new(agent), Arc::new(InMemorySessionService::new()));
+        runner
+            .run_async("u1".into(), "s1".into(), Content::user_text("write a haiku"))
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_revises_until_critic_accepts() {
+        let writer = ScriptedAgent::boxed("writer", &["draft 1", "draft 2", "draft 3"]);
+        let critic = ScriptedAgent::boxed(
+            "critic",
+            &[
+                r#"{"accepted": false, "score": 0.4, "feedback": "too long"}"#,
+                "```json\n{\"accepted\": true, \"score\": 0.7, \"feedback\": \"ok\"}\n```",
+                r#"{"accepted": true, "score": 0.9, "feedback": "great"}"#,
+            ],
+        );
+        let events = run(ReflexionAgent::new("haiku", writer, critic)).await;
+        let texts: Vec<_> = events.iter().map(|event| event.get_text().unwrap()).collect();
+        assert_eq!(texts.len(), 4);
+        assert_eq!(texts[2], "draft 2");
+        assert_eq!(events[1].metadata[REFLEXION_METADATA_KEY]["critique"]["feedback"], "too long");
+        assert_eq!(events[3].metadata[REFLEXION_METADATA_KEY]["accepted"], true);
+
+        // A stricter score threshold needs the third draft; a budget of one revision stops before it
+        let writer = ScriptedAgent::boxed("writer", &["draft 1", "draft 2", "draft 3"]);
+        let critic = ScriptedAgent::boxed(
+            "critic",
+            &[
+                r#"{"accepted": false, "score": 0.4}"#,
+                r#"{"accepted": true, "score": 0.7}"#,
+                r#"{"accepted": true, "score": 0.9}"#,
+            ],
+        );
+        let agent = ReflexionAgent::new("haiku", writer, critic)
+            .with_acceptance(AcceptanceCriteria::MinScore(0.8))
+            .with_max_revisions(1);
+        let events = run(agent).await;
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[2].get_text().unwrap(), "draft 2");
+        assert_eq!(events[3].metadata[REFLEXION_METADATA_KEY]["accepted"], false);
+    }
+}
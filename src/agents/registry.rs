@@ -0,0 +1,194 @@
+//! Registry of named agents hosting multiple versions side by side
+
+use crate::{agents::BaseAgent, error::Result};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+};
+
+/// Version key used for agents registered without a version
+pub const DEFAULT_AGENT_VERSION: &str = "default";
+
+struct AgentVersions {
+    versions: BTreeMap<String, Arc<dyn BaseAgent>>,
+    default_version: String,
+}
+
+/// Named agents, each with one or more concurrently served versions.
+///
+/// The first version registered under a name serves requests that do not ask
+/// for a specific version until `set_default_version` promotes another one,
+/// so registering a new version never shifts traffic on its own.
+#[derive(Clone, Default)]
+pub struct AgentRegistry {
+    agents: Arc<RwLock<HashMap<String, AgentVersions>>>,
+}
+
+impl AgentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an agent under `name`, keyed by its own version.
+    ///
+    /// Re-registering an existing version replaces it.
+    pub fn register(&self, name: impl Into<String>, agent: Arc<dyn BaseAgent>) {
+        let version = agent.version().unwrap_or(DEFAULT_AGENT_VERSION).to_string();
+        let mut agents = self.agents.write().expect("agent registry lock poisoned");
+        agents
+            .entry(name.into())
+            .or_insert_with(|| AgentVersions {
+                versions: BTreeMap::new(),
+                default_version: version.clone(),
+            })
+            .versions
+            .insert(version, agent);
+    }
+
+    /// Serve `version` for requests that do not ask for a specific version
+    pub fn set_default_version(&self, name: &str, version: &str) -> Result<()> {
+        let mut agents = self.agents.write().expect("agent registry lock poisoned");
+        let entry = agents
+            .get_mut(name)
+            .ok_or_else(|| crate::adk_error!(AgentError, "Agent '{}' is not registered", name))?;
+        if !entry.versions.contains_key(version) {
+            return Err(crate::adk_error!(
+                AgentError,
+                "Agent '{}' has no version '{}'",
+                name,
+                version
+            ));
+        }
+        entry.default_version = version.to_string();
+        Ok(())
+    }
+
+    /// Remove one version of an agent, or the whole agent when `version` is `None`
+    pub fn unregister(&self, name: &str, version: Option<&str>) -> bool {
+        let mut agents = self.agents.write().expect("agent registry lock poisoned");
+        let Some(version) = version else {
+            return agents.remove(name).is_some();
+        };
+        let Some(entry) = agents.get_mut(name) else {
+            return false;
+        };
+        if entry.versions.remove(version).is_none() {
+            return false;
+        }
+        match entry.versions.keys().next().cloned() {
+            None => {
+                agents.remove(name);
+            }
+            Some(first) if entry.default_version == version => entry.default_version = first,
+            Some(_) => {}
+        }
+        true
+    }
+
+    /// Look up an agent by name, using the default version when `version` is `None`
+    pub fn get(&self, name: &str, version: Option<&str>) -> Option<Arc<dyn BaseAgent>> {
+        let agents = self.agents.read().expect("agent registry lock poisoned");
+        let entry = agents.get(name)?;
+        let version = version.unwrap_or(&entry.default_version);
+        entry.versions.get(version).cloned()
+    }
+
+    /// Look up an agent by name and version, failing if either is not registered
+    pub fn resolve(&self, name: &str, version: Option<&str>) -> Result<Arc<dyn BaseAgent>> {
+        self.get(name, version).ok_or_else(|| match version {
+            Some(version) if !self.versions(name).is_empty() => crate::adk_error!(
+                AgentError,
+                "Agent '{}' has no version '{}'; registered: {}",
+                name,
+                version,
+                self.versions(name).join(", ")
+            ),
+            _ => crate::adk_error!(AgentError, "Agent '{}' is not registered", name),
+        })
+    }
+
+    /// The version served by default for an agent
+    pub fn default_version(&self, name: &str) -> Option<String> {
+        let agents = self.agents.read().expect("agent registry lock poisoned");
+        agents.get(name).map(|entry| entry.default_version.clone())
+    }
+
+    /// All registered versions of an agent, sorted
+    pub fn versions(&self, name: &str) -> Vec<String> {
+        let agents = self.agents.read().expect("agent registry lock poisoned");
+        agents
+            .get(name)
+            .map(|entry| entry.versions.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// All registered agent names, sorted
+    pub fn names(&self) -> Vec<String> {
+        let agents = self.agents.read().expect("agent registry lock poisoned");
+        let mut names: Vec<String> = agents.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.agents.read().expect("agent registry lock poisoned").is_empty()
+    }
+}
+
+impl std::fmt::Debug for AgentRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let agents = self.agents.read().expect("agent registry lock poisoned");
+        let mut map = f.debug_map();
+        for (name, entry) in agents.iter() {
+            map.entry(name, &entry.versions.keys().collect::<Vec<_>>());
+        }
+        map.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{base_agent::AgentBuilder, LlmAgent};
+
+    fn agent(version: &str, instruction: &str) -> Arc<dyn BaseAgent> {
+        Arc::new(
+            LlmAgent::builder()
+                .name("helper")
+                .model("gemini-2.0-flash")
+                .instruction(instruction)
+                .version(version)
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_registry_serves_versions_side_by_side() {
+        let registry = AgentRegistry::new();
+        registry.register("helper", agent("v1", "Be brief."));
+        registry.register("helper", agent("v2", "Be thorough."));
+
+        assert_eq!(registry.versions("helper"), vec!["v1", "v2"]);
+        assert_eq!(registry.get("helper", None).unwrap().version(), Some("v1"));
+        assert_eq!(registry.get("helper", Some("v2")).unwrap().version(), Some("v2"));
+        assert!(registry.get("helper", Some("v3")).is_none());
+
+        let v1_hash = registry.get("helper", Some("v1")).unwrap().config_hash().map(str::to_string);
+        let v2_hash = registry.get("helper", Some("v2")).unwrap().config_hash().map(str::to_string);
+        assert_ne!(v1_hash, v2_hash);
+        assert_eq!(agent("v9", "Be brief.").config_hash().map(str::to_string), v1_hash);
+
+        registry.set_default_version("helper", "v2").unwrap();
+        assert_eq!(registry.get("helper", None).unwrap().version(), Some("v2"));
+        assert!(registry.set_default_version("helper", "v3").is_err());
+
+        assert_eq!(registry.resolve("helper", Some("v2")).unwrap().version(), Some("v2"));
+        let error = registry.resolve("helper", Some("v3")).err().unwrap();
+        assert!(error.to_string().contains("has no version 'v3'; registered: v1, v2"));
+        assert!(registry.resolve("other", None).is_err());
+
+        assert!(registry.unregister("helper", Some("v2")));
+        assert_eq!(registry.default_version("helper").as_deref(), Some("v1"));
+    }
+}
@@ -1,10 +1,21 @@
 //! Run configuration for agents
 
-use crate::types::StreamingMode;
+use crate::{
+    agents::{Breakpoints, VadConfig},
+    types::{GenerateContentConfig, StreamingMode},
+};
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// Seed used by deterministic runs that do not specify one
+pub const DEFAULT_DETERMINISTIC_SEED: i32 = 0;
+
+tokio::task_local! {
+    static DETERMINISTIC: bool;
+}
 
 /// Configuration for running agents
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RunConfig {
     /// Streaming mode for responses
     pub streaming_mode: StreamingMode,
@@ -14,14 +25,107 @@ pub struct RunConfig {
     
     /// Timeout in seconds
     pub timeout_seconds: Option<u64>,
+
+    /// Sampling seed passed to models that support it
+    #[serde(default)]
+    pub seed: Option<i32>,
+
+    /// Replayable mode: pins the seed, zeroes temperature, and makes tools use their mocks
+    #[serde(default)]
+    pub deterministic: bool,
+
+    /// Steps at which agents pause for the debugger
+    #[serde(default)]
+    pub breakpoints: Breakpoints,
+
+    /// Build the model request and emit it as an event instead of calling the model
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// Voice activity detection and barge-in of live runs
+    #[serde(default)]
+    pub voice_activity: Option<VadConfig>,
 }
 
-impl Default for RunConfig {
-    fn default() -> Self {
+impl RunConfig {
+    /// Create a deterministic run configuration with the given seed
+    pub fn deterministic(seed: i32) -> Self {
         Self {
-            streaming_mode: StreamingMode::default(),
-            max_iterations: None,
-            timeout_seconds: None,
+            seed: Some(seed),
+            deterministic: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_seed(mut self, seed: i32) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Emit the composed model request without calling the model
+    pub fn dry_run() -> Self {
+        Self {
+            dry_run: true,
+            ..Default::default()
+        }
+    }
+
+    /// Pause at `breakpoints` and wait for commands from the global debugger
+    pub fn with_breakpoints(mut self, breakpoints: Breakpoints) -> Self {
+        self.breakpoints = breakpoints;
+        self
+    }
+
+    pub fn with_voice_activity(mut self, voice_activity: VadConfig) -> Self {
+        self.voice_activity = Some(voice_activity);
+        self
+    }
+
+    /// Apply the seed and, in deterministic mode, greedy sampling to a model request
+    pub fn apply_to(&self, config: &mut GenerateContentConfig) {
+        if self.deterministic {
+            config.seed = Some(self.seed.unwrap_or(DEFAULT_DETERMINISTIC_SEED));
+            config.temperature = Some(0.0);
+        } else if let Some(seed) = self.seed {
+            config.seed = Some(seed);
         }
     }
 }
+
+/// Run a future (typically a tool call) with the deterministic flag set for its duration
+pub async fn with_determinism<F: Future>(deterministic: bool, future: F) -> F::Output {
+    DETERMINISTIC.scope(deterministic, future).await
+}
+
+/// Whether the current tool call is part of a deterministic run.
+///
+/// Tools with mock behavior should return their mock result when this is set.
+pub fn is_deterministic() -> bool {
+    DETERMINISTIC.try_with(|deterministic| *deterministic).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{BaseTool, FunctionTool};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_deterministic_mode_pins_sampling_and_tool_mocks() {
+        let mut config = GenerateContentConfig {
+            temperature: Some(0.9),
+            ..Default::default()
+        };
+        RunConfig::deterministic(42).apply_to(&mut config);
+        assert_eq!(config.seed, Some(42));
+        assert_eq!(config.temperature, Some(0.0));
+
+        let tool = FunctionTool::new("now", "Current time", |_| async { Ok(json!(crate::types::now())) })
+            .with_mock_response(json!("2024-01-01T00:00:00Z"));
+        let live = tool.run_async(HashMap::new()).await.unwrap();
+        assert_ne!(live, json!("2024-01-01T00:00:00Z"));
+        let replayed = with_determinism(true, tool.run_async(HashMap::new())).await.unwrap();
+        assert_eq!(replayed, json!("2024-01-01T00:00:00Z"));
+    }
+}
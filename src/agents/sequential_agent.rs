@@ -5,31 +5,56 @@ use crate::{
     error::Result,
     types::{AgentId, Metadata},
 };
+use async_stream::stream;
 use async_trait::async_trait;
-use std::collections::HashMap;
+use futures::StreamExt;
+use std::{collections::HashMap, sync::Arc};
 
 use super::base_agent::EventStream;
 
-/// Agent that runs sub-agents in sequence
+/// Agent that runs sub-agents one after another
 // Note: Debug not derived due to trait objects
 pub struct SequentialAgent {
     id: AgentId,
     name: String,
     description: String,
     sub_agents: Vec<Box<dyn BaseAgent>>,
+    steps: Vec<Arc<dyn BaseAgent>>,
     metadata: Metadata,
+    version: Option<String>,
 }
 
 impl SequentialAgent {
     pub fn new(name: impl Into<String>) -> Self {
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id: crate::utils::new_id(),
             name: name.into(),
             description: String::new(),
             sub_agents: Vec::new(),
+            steps: Vec::new(),
             metadata: HashMap::new(),
+            version: None,
         }
     }
+
+    /// Append a step, run after the steps added before it
+    pub fn with_sub_agent(mut self, agent: Box<dyn BaseAgent>) -> Self {
+        let step: Arc<dyn BaseAgent> = Arc::from(agent);
+        self.sub_agents.push(Box::new(step.clone()));
+        self.steps.push(step);
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Set the agent version recorded on its events and used for registry lookups
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
 }
 
 #[async_trait]
@@ -58,12 +83,118 @@ impl BaseAgent for SequentialAgent {
         &self.sub_agents
     }
 
-    async fn run_async(&self, _ctx: InvocationContext) -> Result<EventStream> {
-        // TODO: Implement sequential execution
-        todo!("Sequential agent execution not implemented")
+    fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+        // Each step starts only once the runner has persisted the previous
+        // step's last event, so it sees everything said before it
+        let steps = self.steps.clone();
+        Ok(Box::pin(stream! {
+            for step in steps {
+                let mut events = match step.run_async(ctx.clone()).await {
+                    Ok(events) => events,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                while let Some(result) = events.next().await {
+                    let failed = result.is_err();
+                    yield result;
+                    if failed {
+                        return;
+                    }
+                }
+            }
+        }))
     }
 
     async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
         self.run_async(ctx).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::events_to_stream, ParallelAgent},
+        events::Event,
+        runners::Runner,
+        sessions::InMemorySessionService,
+        types::Content,
+    };
+
+    /// Answers with its name and the number of events it could see
+    struct CountingAgent {
+        id: AgentId,
+        name: String,
+        metadata: Metadata,
+    }
+
+    impl CountingAgent {
+        fn boxed(name: &str) -> Box<dyn BaseAgent> {
+            Box::new(Self { id: name.into(), name: name.into(), metadata: Metadata::new() })
+        }
+    }
+
+    #[async_trait]
+    impl BaseAgent for CountingAgent {
+        fn id(&self) -> &AgentId {
+            &self.id
+        }
+        fn name(&self) -> &str {
+            &self.name
+        }
+        fn description(&self) -> &str {
+            ""
+        }
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+        fn parent(&self) -> Option<&dyn BaseAgent> {
+            None
+        }
+        fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+            &[]
+        }
+        async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+            let session = ctx.session_service.get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id).await?;
+            let seen = session.map(|session| session.events.len()).unwrap_or_default();
+            Ok(events_to_stream(vec![Event::text_response(self.name.clone(), format!("{}:{}", self.name, seen))]))
+        }
+        async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+            self.run_async(ctx).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_steps_see_earlier_output() {
+        let critiques = ParallelAgent::new("critiques")
+            .with_sub_agent(CountingAgent::boxed("accuracy"))
+            .with_sub_agent(CountingAgent::boxed("style"));
+        let pipeline = SequentialAgent::new("report")
+            .with_sub_agent(CountingAgent::boxed("draft"))
+            .with_sub_agent(Box::new(critiques))
+            .with_sub_agent(CountingAgent::boxed("finalize"));
+        assert_eq!(pipeline.sub_agents().len(), 3);
+
+        let runner = Runner::new("app", Arc::new(pipeline), Arc::new(InMemorySessionService::new()));
+        let events: Vec<_> = runner
+            .run_async("u1".into(), "s1".into(), Content::user_text("tides"))
+            .await
+            .unwrap()
+            .map(|event| event.unwrap().get_text().unwrap())
+            .collect()
+            .await;
+
+        // Parallel branches start together, after the draft
+        let mut critiques = events[1..3].to_vec();
+        critiques.sort();
+        assert_eq!(events[0], "draft:1");
+        assert_eq!(critiques, ["accuracy:2", "style:2"]);
+        assert_eq!(events[3], "finalize:4");
+    }
+}
@@ -0,0 +1,175 @@
+//! Structured (JSON) output of LLM agents
+//!
+//! An agent with an [`OutputSchema`] asks the model for JSON matching the
+//! schema, repairs near-misses according to its [`RepairStrictness`], and
+//! validates the result before emitting it. Whether repair was needed is
+//! recorded under [`STRUCTURED_OUTPUT_METADATA_KEY`], so prompts and models
+//! that rely on it can be spotted.
+
+use crate::{
+    error::Result,
+    types::GenerateContentConfig,
+    utils::json_repair::{repair_json, JsonRepair, RepairStrictness},
+};
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Metadata key of the `{repaired, repairs}` record on structured output events
+pub const STRUCTURED_OUTPUT_METADATA_KEY: &str = "structured_output";
+
+/// JSON schema an agent's final answer must satisfy
+#[derive(Clone)]
+pub struct OutputSchema {
+    schema: Value,
+    validator: Arc<JSONSchema>,
+    strictness: RepairStrictness,
+}
+
+impl std::fmt::Debug for OutputSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutputSchema")
+            .field("schema", &self.schema)
+            .field("strictness", &self.strictness)
+            .finish()
+    }
+}
+
+impl OutputSchema {
+    /// Compile `schema`; repairs leniently by default
+    pub fn new(schema: Value) -> Result<Self> {
+        let validator = JSONSchema::compile(&schema)
+            .map_err(|e| crate::adk_error!(ConfigError, "Invalid output schema: {}", e))?;
+        Ok(Self {
+            schema,
+            validator: Arc::new(validator),
+            strictness: RepairStrictness::default(),
+        })
+    }
+
+    pub fn with_repair(mut self, strictness: RepairStrictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    pub fn strictness(&self) -> RepairStrictness {
+        self.strictness
+    }
+
+    /// Ask the model for JSON matching the schema
+    pub fn apply_to(&self, config: &mut GenerateContentConfig) {
+        config.response_mime_type = Some("application/json".to_string());
+        config.response_schema = Some(self.schema.clone());
+    }
+
+    /// Repair and validate a model's answer
+    pub fn parse(&self, text: &str) -> Result<StructuredOutput> {
+        let repaired = repair_json(text, self.strictness)?;
+        if let Err(errors) = self.validator.validate(&repaired.value) {
+            let errors: Vec<String> = errors
+                .map(|error| match error.instance_path.to_string() {
+                    path if path.is_empty() => error.to_string(),
+                    path => format!("{}: {}", path, error),
+                })
+                .collect();
+            return Err(crate::adk_error!(
+                ValidationError,
+                "Model output does not match the output schema: {}",
+                errors.join("; ")
+            ));
+        }
+        Ok(StructuredOutput {
+            value: repaired.value,
+            repairs: repaired.repairs,
+        })
+    }
+}
+
+/// A validated answer and the repairs it needed
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructuredOutput {
+    pub value: Value,
+    pub repairs: Vec<JsonRepair>,
+}
+
+impl StructuredOutput {
+    pub fn was_repaired(&self) -> bool {
+        !self.repairs.is_empty()
+    }
+
+    /// Value recorded under [`STRUCTURED_OUTPUT_METADATA_KEY`]
+    pub fn metadata(&self) -> Value {
+        json!({ "repaired": self.was_repaired(), "repairs": self.repairs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::AgentBuilder, LlmAgent},
+        runners::Runner,
+        sessions::InMemorySessionService,
+        testing::MockGeminiServer,
+        types::Content,
+    };
+    use futures::StreamExt;
+
+    fn weather_schema() -> OutputSchema {
+        OutputSchema::new(json!({
+            "type": "object",
+            "properties": { "city": { "type": "string" }, "celsius": { "type": "number" } },
+            "required": ["city", "celsius"],
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_agent_repairs_and_validates_structured_answers() {
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-structured-output").await;
+        mock.push_text("```json\n{\"city\": \"Oslo\", \"celsius\": 4,}\n```")
+            .push_text("{\"city\": \"Oslo\"}");
+
+        let agent = LlmAgent::builder()
+            .name("forecaster")
+            .model("mock-gemini-structured-output")
+            .output_schema(weather_schema())
+            .build()
+            .unwrap();
+        let runner = Runner::new("app", Arc::new(agent), Arc::new(InMemorySessionService::new()));
+        let run = |message: &str| {
+            let runner = &runner;
+            let message = Content::user_text(message);
+            async move {
+                let events: Vec<_> = runner.run_async("u1".into(), "s1".into(), message).await.unwrap().collect().await;
+                events.into_iter().last().unwrap()
+            }
+        };
+
+        let event = run("Weather in Oslo?").await.unwrap();
+        assert_eq!(event.get_text().as_deref(), Some(r#"{"celsius":4,"city":"Oslo"}"#));
+        assert_eq!(
+            event.metadata[STRUCTURED_OUTPUT_METADATA_KEY],
+            json!({ "repaired": true, "repairs": ["code_fence", "trailing_commas"] })
+        );
+        let request = &mock.requests()[0].body["generation_config"];
+        assert_eq!(request["response_mime_type"], "application/json");
+        assert_eq!(request["response_schema"]["required"], json!(["city", "celsius"]));
+
+        let error = run("And tomorrow?").await.unwrap_err().to_string();
+        assert!(error.contains("celsius"), "{}", error);
+    }
+
+    #[test]
+    fn test_strict_schema_rejects_fenced_output() {
+        let schema = weather_schema().with_repair(RepairStrictness::Strict);
+        assert!(schema.parse("```json\n{\"city\": \"Oslo\", \"celsius\": 4}\n```").is_err());
+        assert!(!schema.parse(r#"{"city": "Oslo", "celsius": 4}"#).unwrap().was_repaired());
+        assert!(OutputSchema::new(json!({ "type": 12 })).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+//! Translation wrapper agent
+//!
+//! Lets a domain agent built for one working language serve users writing in
+//! other languages: the conversation is shown to the wrapped agent in the
+//! working language and its answers are translated back into the language of
+//! the user's latest message.
+
+use crate::{
+    agents::{detect_language, BaseAgent, InvocationContext},
+    error::Result,
+    events::Event,
+    models::{create_model, LlmRequest},
+    sessions::{Session, SessionFilter, SessionService},
+    types::{AgentId, ContentPart, Metadata, SessionId, SessionState, UserId},
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+use tracing::warn;
+
+use super::base_agent::EventStream;
+
+/// Metadata key holding an answer's text before it was translated for the user
+pub const TRANSLATION_ORIGINAL_METADATA_KEY: &str = "translation_original";
+
+/// Metadata key holding the language an answer was translated into
+pub const TRANSLATED_TO_METADATA_KEY: &str = "translated_to";
+
+/// Maximum number of cached message translations before the cache is reset
+const TRANSLATION_CACHE_CAPACITY: usize = 10_000;
+
+/// Translates text between languages
+#[async_trait]
+pub trait Translator: Send + Sync {
+    /// Translate `text` into `target_language` (an English language name such as `French`)
+    async fn translate(&self, text: &str, target_language: &str) -> Result<String>;
+}
+
+/// Translator backed by a model
+#[derive(Debug, Clone)]
+pub struct LlmTranslator {
+    model: String,
+}
+
+impl LlmTranslator {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self { model: model.into() }
+    }
+}
+
+#[async_trait]
+impl Translator for LlmTranslator {
+    async fn translate(&self, text: &str, target_language: &str) -> Result<String> {
+        let model = create_model(&self.model).await?;
+        let request = LlmRequest::new(&self.model).add_user_message(format!(
+            "Translate the following text into {}. Preserve formatting, names and numbers. \
+             Reply with the translation only.\n\n{}",
+            target_language, text
+        ));
+        model
+            .generate_content(request)
+            .await?
+            .get_text()
+            .filter(|text| !text.trim().is_empty())
+            .ok_or_else(|| crate::adk_error!(ModelError, "Translation model returned no text"))
+    }
+}
+
+/// Agent that runs a wrapped agent in a working language and translates its answers for the user
+pub struct TranslationAgent {
+    id: AgentId,
+    name: String,
+    description: String,
+    sub_agents: Vec<Box<dyn BaseAgent>>,
+    metadata: Metadata,
+    translator: Arc<dyn Translator>,
+    working_language: String,
+    cache: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl TranslationAgent {
+    /// Wrap `agent`, which works in English
+    pub fn new(name: impl Into<String>, agent: Box<dyn BaseAgent>, translator: Arc<dyn Translator>) -> Self {
+        Self {
+            id: crate::utils::new_id(),
+            name: name.into(),
+            description: String::new(),
+            sub_agents: vec![agent],
+            metadata: HashMap::new(),
+            translator,
+            working_language: "English".to_string(),
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Language the wrapped agent works in, as an English name such as `German`
+    pub fn with_working_language(mut self, language: impl Into<String>) -> Self {
+        self.working_language = language.into();
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    fn agent(&self) -> &dyn BaseAgent {
+        self.sub_agents[0].as_ref()
+    }
+
+    /// The session as the wrapped agent should see it: user messages in the
+    /// working language and its own answers untranslated
+    async fn working_session(&self, mut session: Session) -> Result<Session> {
+        for event in &mut session.events {
+            if let Some(original) = event.metadata.get(TRANSLATION_ORIGINAL_METADATA_KEY).and_then(|v| v.as_str()) {
+                let original = original.to_string();
+                replace_text(Arc::make_mut(event), |_| Some(original.clone()));
+                continue;
+            }
+            if event.author != "user" {
+                continue;
+            }
+            let Some(text) = event.get_text().filter(|text| !text.is_empty()) else {
+                continue;
+            };
+
+            let cached = self.cache.lock().expect("translation cache poisoned").get(&event.id).cloned();
+            let translated = match cached {
+                Some(translated) => translated,
+                None => {
+                    let translated = match detect_language(&text) {
+                        Some(language) if !self.is_working_language(&language.name) => {
+                            self.translator.translate(&text, &self.working_language).await?
+                        }
+                        _ => text,
+                    };
+                    let mut cache = self.cache.lock().expect("translation cache poisoned");
+                    if cache.len() >= TRANSLATION_CACHE_CAPACITY {
+                        cache.clear();
+                    }
+                    cache.insert(event.id.clone(), translated.clone());
+                    translated
+                }
+            };
+            replace_text(Arc::make_mut(event), |_| Some(translated.clone()));
+        }
+        Ok(session)
+    }
+
+    fn is_working_language(&self, language: &str) -> bool {
+        language.eq_ignore_ascii_case(&self.working_language)
+    }
+}
+
+/// Replace each text part with `f(text)`, leaving it unchanged when `f` returns `None`
+fn replace_text(event: &mut Event, mut f: impl FnMut(&str) -> Option<String>) {
+    if let Some(content) = event.content.as_mut() {
+        for part in &mut content.parts {
+            if let ContentPart::Text { text } = part {
+                if let Some(replaced) = f(text) {
+                    *text = replaced;
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl BaseAgent for TranslationAgent {
+    fn id(&self) -> &AgentId {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn parent(&self) -> Option<&dyn BaseAgent> {
+        None
+    }
+
+    fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+        &self.sub_agents
+    }
+
+    async fn run_async(&self, mut ctx: InvocationContext) -> Result<EventStream> {
+        let Some(session) = ctx
+            .session_service
+            .get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id)
+            .await?
+        else {
+            return self.agent().run_async(ctx).await;
+        };
+        let user_language = session
+            .events
+            .iter()
+            .rev()
+            .find(|event| event.author == "user")
+            .and_then(|event| event.get_text())
+            .and_then(|text| detect_language(&text))
+            .filter(|language| !self.is_working_language(&language.name));
+        let Some(user_language) = user_language else {
+            return self.agent().run_async(ctx).await;
+        };
+
+        let working_session = self.working_session(session).await?;
+        ctx.session_service = Arc::new(WorkingLanguageView {
+            inner: ctx.session_service.clone(),
+            session: working_session,
+        });
+        let mut events = self.agent().run_async(ctx).await?;
+
+        let translator = self.translator.clone();
+        let target = user_language.name;
+        Ok(Box::pin(stream! {
+            while let Some(result) = events.next().await {
+                let mut event = match result {
+                    Ok(event) => event,
+                    Err(e) => {
+                        yield Err(e);
+                        continue;
+                    }
+                };
+                let Some(text) = event.get_text().filter(|text| !text.is_empty()) else {
+                    yield Ok(event);
+                    continue;
+                };
+                // Deltas are in the working language; the translated final event replaces them
+                if event.is_partial {
+                    continue;
+                }
+
+                let mut parts = Vec::new();
+                if let Some(content) = &event.content {
+                    for part in &content.parts {
+                        if let ContentPart::Text { text } = part {
+                            parts.push(translator.translate(text, &target).await);
+                        }
+                    }
+                }
+                if let Some(e) = parts.iter().find_map(|part| part.as_ref().err()) {
+                    warn!("Failed to translate answer into {}, sending it untranslated: {}", target, e);
+                    yield Ok(event);
+                    continue;
+                }
+
+                let translated = Arc::make_mut(&mut event);
+                let mut parts = parts.into_iter().flatten();
+                replace_text(translated, |_| parts.next());
+                translated.metadata.insert(TRANSLATION_ORIGINAL_METADATA_KEY.to_string(), text.into());
+                translated.metadata.insert(TRANSLATED_TO_METADATA_KEY.to_string(), target.clone().into());
+                if let Some(citations) = translated.citations.as_mut() {
+                    // Spans index into the untranslated text
+                    citations.spans.clear();
+                }
+                yield Ok(event);
+            }
+        }))
+    }
+
+    async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+        self.agent().run_live(ctx).await
+    }
+}
+
+/// Session service presenting the current session in the working language; writes go to `inner`
+struct WorkingLanguageView {
+    inner: Arc<dyn SessionService>,
+    session: Session,
+}
+
+#[async_trait]
+impl SessionService for WorkingLanguageView {
+    async fn create_session(&self, session: Session) -> Result<()> {
+        self.inner.create_session(session).await
+    }
+
+    async fn get_session(&self, app_name: &str, user_id: &UserId, session_id: &SessionId) -> Result<Option<Session>> {
+        if session_id == &self.session.id && user_id == &self.session.user_id && app_name == self.session.app_name {
+            return Ok(Some(self.session.clone()));
+        }
+        self.inner.get_session(app_name, user_id, session_id).await
+    }
+
+    async fn list_sessions(&self, filter: &SessionFilter) -> Result<Vec<Session>> {
+        self.inner.list_sessions(filter).await
+    }
+
+    async fn update_session_state(&self, session_id: &SessionId, state: &SessionState) -> Result<()> {
+        self.inner.update_session_state(session_id, state).await
+    }
+
+    async fn append_event(&self, session_id: &SessionId, event: Arc<Event>) -> Result<()> {
+        self.inner.append_event(session_id, event).await
+    }
+
+    async fn update_event_metadata(&self, session_id: &SessionId, event_id: &str, metadata: Metadata) -> Result<()> {
+        self.inner.update_event_metadata(session_id, event_id, metadata).await
+    }
+
+    async fn set_session_tags(&self, session_id: &SessionId, tags: BTreeSet<String>) -> Result<()> {
+        self.inner.set_session_tags(session_id, tags).await
+    }
+
+    async fn delete_session(&self, session_id: &SessionId) -> Result<bool> {
+        self.inner.delete_session(session_id).await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.inner.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{agents::base_agent::events_to_stream, sessions::InMemorySessionService};
+
+    /// Tags text with the target language
+    struct TaggingTranslator;
+
+    #[async_trait]
+    impl Translator for TaggingTranslator {
+        async fn translate(&self, text: &str, target_language: &str) -> Result<String> {
+            Ok(format!("[{}] {}", target_language, text))
+        }
+    }
+
+    /// Echoes the latest message it sees
+    struct EchoAgent {
+        id: AgentId,
+        metadata: Metadata,
+    }
+
+    #[async_trait]
+    impl BaseAgent for EchoAgent {
+        fn id(&self) -> &AgentId {
+            &self.id
+        }
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn description(&self) -> &str {
+            ""
+        }
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+        fn parent(&self) -> Option<&dyn BaseAgent> {
+            None
+        }
+        fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+            &[]
+        }
+        async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+            let session = ctx.session_service.get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id).await?.unwrap();
+            let last = session.events.last().and_then(|event| event.get_text()).unwrap_or_default();
+            Ok(events_to_stream(vec![Event::text_response("echo", format!("echo: {}", last))]))
+        }
+        async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+            self.run_async(ctx).await
+        }
+    }
+
+    async fn run(message: &str) -> Arc<Event> {
+        let sessions = Arc::new(InMemorySessionService::new());
+        let session = sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        sessions
+            .append_event(&session.id, Arc::new(Event::user_input(message, uuid::Uuid::new_v4())))
+            .await
+            .unwrap();
+
+        let echo = Box::new(EchoAgent { id: "echo".into(), metadata: Metadata::new() });
+        let agent = TranslationAgent::new("translated", echo, Arc::new(TaggingTranslator));
+        let ctx = InvocationContext::new("s1".into(), "u1".into(), "app".into(), SessionState::new(), sessions);
+        let mut events = agent.run_async(ctx).await.unwrap();
+        events.next().await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_translates_in_and_out_of_working_language() {
+        let event = run("Bonjour, pourriez-vous m'aider à réserver une chambre pour ce soir ?").await;
+        let inbound = "[English] Bonjour, pourriez-vous m'aider à réserver une chambre pour ce soir ?";
+        assert_eq!(event.get_text().unwrap(), format!("[French] echo: {}", inbound));
+        assert_eq!(event.metadata[TRANSLATED_TO_METADATA_KEY], "French");
+        assert_eq!(event.metadata[TRANSLATION_ORIGINAL_METADATA_KEY], format!("echo: {}", inbound));
+
+        // Messages already in the working language are passed straight through
+        let event = run("Could you help me book a room for tonight, please?").await;
+        assert_eq!(event.get_text().unwrap(), "echo: Could you help me book a room for tonight, please?");
+        assert!(!event.metadata.contains_key(TRANSLATED_TO_METADATA_KEY));
+    }
+}
@@ -0,0 +1,225 @@
+//! Voice activity detection for live runs
+//!
+//! Connectors that deliver raw PCM audio (telephony bridges, browsers
+//! streaming microphone input) rely on the server to tell the model when the
+//! user starts and stops speaking. The detector here is energy based: speech
+//! starts once enough consecutive audio is louder than a threshold derived
+//! from the sensitivity, and ends after the configured silence.
+
+use serde::{Deserialize, Serialize};
+
+/// Sample rate assumed for PCM audio whose MIME type doesn't declare one
+pub const DEFAULT_PCM_SAMPLE_RATE: u32 = 16_000;
+
+/// How the start and end of user speech are determined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityDetection {
+    /// The server detects speech in the incoming audio
+    #[default]
+    Automatic,
+
+    /// The client sends activity start and end requests, e.g. while a button is held
+    PushToTalk,
+}
+
+/// Voice activity detection and barge-in settings of live runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VadConfig {
+    #[serde(default)]
+    pub mode: ActivityDetection,
+
+    /// From 0 (only loud speech counts) to 1 (quiet speech counts too)
+    #[serde(default = "default_sensitivity")]
+    pub sensitivity: f32,
+
+    /// Silence that ends an utterance
+    #[serde(default = "default_silence_duration_ms")]
+    pub silence_duration_ms: u64,
+
+    /// Speech needed before an utterance starts; shorter noises are ignored
+    #[serde(default = "default_min_speech_ms")]
+    pub min_speech_ms: u64,
+
+    /// Whether user speech interrupts the model while it is responding
+    #[serde(default = "default_barge_in")]
+    pub barge_in: bool,
+}
+
+fn default_sensitivity() -> f32 {
+    0.5
+}
+
+fn default_silence_duration_ms() -> u64 {
+    800
+}
+
+fn default_min_speech_ms() -> u64 {
+    100
+}
+
+fn default_barge_in() -> bool {
+    true
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            mode: ActivityDetection::default(),
+            sensitivity: default_sensitivity(),
+            silence_duration_ms: default_silence_duration_ms(),
+            min_speech_ms: default_min_speech_ms(),
+            barge_in: default_barge_in(),
+        }
+    }
+}
+
+impl VadConfig {
+    /// Client-driven activity; audio is forwarded without detection
+    pub fn push_to_talk() -> Self {
+        Self {
+            mode: ActivityDetection::PushToTalk,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_sensitivity(mut self, sensitivity: f32) -> Self {
+        self.sensitivity = sensitivity.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_silence_duration_ms(mut self, silence_duration_ms: u64) -> Self {
+        self.silence_duration_ms = silence_duration_ms;
+        self
+    }
+
+    pub fn with_min_speech_ms(mut self, min_speech_ms: u64) -> Self {
+        self.min_speech_ms = min_speech_ms;
+        self
+    }
+
+    pub fn with_barge_in(mut self, barge_in: bool) -> Self {
+        self.barge_in = barge_in;
+        self
+    }
+
+    /// RMS level, as a fraction of full scale, above which audio counts as speech
+    pub fn threshold(&self) -> f32 {
+        // Sensitivity 0 needs about -14 dBFS, 1 about -46 dBFS
+        0.2 * (1.0 - self.sensitivity.clamp(0.0, 1.0)).powi(2) + 0.005
+    }
+}
+
+/// Change in the user's speech
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceActivity {
+    SpeechStarted,
+    SpeechEnded,
+}
+
+/// Sample rate of a raw PCM MIME type such as `audio/pcm;rate=24000`; `None` for other audio
+pub fn pcm_sample_rate(mime_type: &str) -> Option<u32> {
+    let mut params = mime_type.split(';').map(str::trim);
+    let essence = params.next()?.to_ascii_lowercase();
+    if essence != "audio/pcm" && essence != "audio/l16" {
+        return None;
+    }
+    Some(
+        params
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.eq_ignore_ascii_case("rate"))
+            .and_then(|(_, rate)| rate.parse().ok())
+            .unwrap_or(DEFAULT_PCM_SAMPLE_RATE),
+    )
+}
+
+/// Energy-based detector over 16-bit little-endian mono PCM
+#[derive(Debug, Clone)]
+pub struct VoiceActivityDetector {
+    config: VadConfig,
+    speaking: bool,
+    /// Consecutive loud audio while silent, or quiet audio while speaking
+    run_ms: f64,
+}
+
+impl VoiceActivityDetector {
+    pub fn new(config: VadConfig) -> Self {
+        Self {
+            config,
+            speaking: false,
+            run_ms: 0.0,
+        }
+    }
+
+    pub fn is_speaking(&self) -> bool {
+        self.speaking
+    }
+
+    /// Feed a chunk of audio; returns the transition it completes, if any
+    pub fn process(&mut self, pcm: &[u8], sample_rate: u32) -> Option<VoiceActivity> {
+        let samples: Vec<f32> = pcm
+            .chunks_exact(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as f32 / i16::MAX as f32)
+            .collect();
+        if samples.is_empty() || sample_rate == 0 {
+            return None;
+        }
+        let rms = (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt();
+        let duration_ms = samples.len() as f64 * 1000.0 / sample_rate as f64;
+
+        let loud = rms >= self.config.threshold();
+        if loud == self.speaking {
+            self.run_ms = 0.0;
+            return None;
+        }
+        self.run_ms += duration_ms;
+        let needed = if self.speaking { self.config.silence_duration_ms } else { self.config.min_speech_ms };
+        if self.run_ms < needed as f64 {
+            return None;
+        }
+        self.run_ms = 0.0;
+        self.speaking = loud;
+        Some(if loud { VoiceActivity::SpeechStarted } else { VoiceActivity::SpeechEnded })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 20 ms of a square wave at `amplitude` (fraction of full scale), 16 kHz
+    fn frame(amplitude: f32) -> Vec<u8> {
+        (0..320)
+            .flat_map(|i| {
+                let sample = if i % 2 == 0 { amplitude } else { -amplitude };
+                ((sample * i16::MAX as f32) as i16).to_le_bytes()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_speech_after_min_duration_and_end_after_silence() {
+        let mut vad = VoiceActivityDetector::new(VadConfig::default().with_silence_duration_ms(100).with_min_speech_ms(40));
+        assert_eq!(vad.process(&frame(0.3), 16_000), None);
+        assert_eq!(vad.process(&frame(0.001), 16_000), None);
+        assert_eq!(vad.process(&frame(0.3), 16_000), None);
+        assert_eq!(vad.process(&frame(0.3), 16_000), Some(VoiceActivity::SpeechStarted));
+        for _ in 0..4 {
+            assert_eq!(vad.process(&frame(0.0), 16_000), None);
+        }
+        assert_eq!(vad.process(&frame(0.0), 16_000), Some(VoiceActivity::SpeechEnded));
+    }
+
+    #[test]
+    fn test_sensitivity_and_sample_rates() {
+        let quiet = frame(0.02);
+        let mut sensitive = VoiceActivityDetector::new(VadConfig::default().with_sensitivity(1.0).with_min_speech_ms(0));
+        let mut strict = VoiceActivityDetector::new(VadConfig::default().with_sensitivity(0.0).with_min_speech_ms(0));
+        assert_eq!(sensitive.process(&quiet, 16_000), Some(VoiceActivity::SpeechStarted));
+        assert_eq!(strict.process(&quiet, 16_000), None);
+
+        assert_eq!(pcm_sample_rate("audio/pcm;rate=24000"), Some(24_000));
+        assert_eq!(pcm_sample_rate("audio/pcm"), Some(DEFAULT_PCM_SAMPLE_RATE));
+        assert_eq!(pcm_sample_rate("audio/ogg"), None);
+    }
+}
@@ -0,0 +1,319 @@
+//! Post-validation of model answers
+//!
+//! A [`ResponseValidation`] registered on a runner checks every final text
+//! answer of its LLM agents against JSON schemas, regular expressions or
+//! custom functions. When a check fails the agent re-prompts the model with
+//! the validation error; once the retries are used up the turn ends with a
+//! failure event carrying a [`ValidationFailure`] under
+//! [`VALIDATION_FAILURE_METADATA_KEY`] instead of the invalid answer.
+
+use crate::{agents::OutputSchema, error::Result, events::Event};
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Metadata key of the [`ValidationFailure`] on failure events
+pub const VALIDATION_FAILURE_METADATA_KEY: &str = "validation_failure";
+
+/// Default number of re-prompts after a failed validation
+pub const DEFAULT_VALIDATION_RETRIES: u32 = 2;
+
+/// Check of a model's final text answer
+#[async_trait]
+pub trait ResponseValidator: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Check an answer; the error explains the problem to the model
+    async fn validate(&self, text: &str) -> std::result::Result<(), String>;
+}
+
+/// Answers must be JSON matching a schema (code fences and trailing commas are tolerated)
+pub struct JsonSchemaValidator {
+    schema: OutputSchema,
+}
+
+impl JsonSchemaValidator {
+    pub fn new(schema: Value) -> Result<Self> {
+        Ok(Self {
+            schema: OutputSchema::new(schema)?,
+        })
+    }
+}
+
+#[async_trait]
+impl ResponseValidator for JsonSchemaValidator {
+    fn name(&self) -> &str {
+        "json_schema"
+    }
+
+    async fn validate(&self, text: &str) -> std::result::Result<(), String> {
+        self.schema.parse(text).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+/// Answers must match a regular expression
+pub struct RegexValidator {
+    regex: Regex,
+}
+
+impl RegexValidator {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| crate::adk_error!(ConfigError, "Invalid validation pattern '{}': {}", pattern, e))?;
+        Ok(Self { regex })
+    }
+}
+
+#[async_trait]
+impl ResponseValidator for RegexValidator {
+    fn name(&self) -> &str {
+        "regex"
+    }
+
+    async fn validate(&self, text: &str) -> std::result::Result<(), String> {
+        if self.regex.is_match(text) {
+            Ok(())
+        } else {
+            Err(format!("The answer must match the pattern `{}`", self.regex.as_str()))
+        }
+    }
+}
+
+/// Validator backed by a function
+pub struct FnValidator<F> {
+    name: String,
+    check: F,
+}
+
+impl<F> FnValidator<F>
+where
+    F: Fn(&str) -> std::result::Result<(), String> + Send + Sync,
+{
+    pub fn new(name: impl Into<String>, check: F) -> Self {
+        Self {
+            name: name.into(),
+            check,
+        }
+    }
+}
+
+#[async_trait]
+impl<F> ResponseValidator for FnValidator<F>
+where
+    F: Fn(&str) -> std::result::Result<(), String> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn validate(&self, text: &str) -> std::result::Result<(), String> {
+        (self.check)(text)
+    }
+}
+
+/// A failed validation, recorded on the event ending the turn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationFailure {
+    /// Name of the failing validator
+    pub validator: String,
+    pub error: String,
+
+    /// Answers the model gave, including re-prompted ones
+    pub attempts: u32,
+}
+
+impl ValidationFailure {
+    /// Event ending a turn whose answer never passed validation
+    pub fn event(&self, agent_name: &str) -> Event {
+        let mut event = Event::text_response(
+            agent_name,
+            format!("The response failed validation by '{}': {}", self.validator, self.error),
+        );
+        event
+            .metadata
+            .insert(VALIDATION_FAILURE_METADATA_KEY.to_string(), serde_json::to_value(self).unwrap_or_default());
+        event
+    }
+
+    /// The failure recorded on an event, if any
+    pub fn from_event(event: &Event) -> Option<Self> {
+        serde_json::from_value(event.metadata.get(VALIDATION_FAILURE_METADATA_KEY)?.clone()).ok()
+    }
+}
+
+/// Validators run on final answers and how often a failing answer is re-prompted
+#[derive(Clone)]
+pub struct ResponseValidation {
+    validators: Vec<Arc<dyn ResponseValidator>>,
+    max_retries: u32,
+}
+
+impl std::fmt::Debug for ResponseValidation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseValidation")
+            .field("validators", &self.validators.iter().map(|v| v.name()).collect::<Vec<_>>())
+            .field("max_retries", &self.max_retries)
+            .finish()
+    }
+}
+
+impl Default for ResponseValidation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseValidation {
+    pub fn new() -> Self {
+        Self {
+            validators: Vec::new(),
+            max_retries: DEFAULT_VALIDATION_RETRIES,
+        }
+    }
+
+    /// Re-prompts after a failed validation before the failure event; 0 fails immediately
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn validator(mut self, validator: Arc<dyn ResponseValidator>) -> Self {
+        self.validators.push(validator);
+        self
+    }
+
+    /// Require answers to be JSON matching `schema`
+    pub fn json_schema(self, schema: Value) -> Result<Self> {
+        Ok(self.validator(Arc::new(JsonSchemaValidator::new(schema)?)))
+    }
+
+    /// Require answers to match `pattern`
+    pub fn regex(self, pattern: &str) -> Result<Self> {
+        Ok(self.validator(Arc::new(RegexValidator::new(pattern)?)))
+    }
+
+    /// Check answers with a function returning the problem to report
+    pub fn check<F>(self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn(&str) -> std::result::Result<(), String> + Send + Sync + 'static,
+    {
+        self.validator(Arc::new(FnValidator::new(name, check)))
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// The first failing check of an answer, validators in registration order
+    pub async fn validate(&self, text: &str, attempts: u32) -> Option<ValidationFailure> {
+        for validator in &self.validators {
+            if let Err(error) = validator.validate(text).await {
+                return Some(ValidationFailure {
+                    validator: validator.name().to_string(),
+                    error,
+                    attempts,
+                });
+            }
+        }
+        None
+    }
+
+    /// Message re-prompting the model after a failed validation
+    pub fn retry_prompt(failure: &ValidationFailure) -> String {
+        format!(
+            "Your answer failed validation: {}. Reply again with a corrected answer only.",
+            failure.error
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::AgentBuilder, LlmAgent},
+        runners::RunnerBuilder,
+        sessions::InMemorySessionService,
+        testing::MockGeminiServer,
+        types::Content,
+    };
+    use futures::StreamExt;
+    use serde_json::json;
+
+    fn ticket_validation() -> ResponseValidation {
+        ResponseValidation::new()
+            .json_schema(json!({
+                "type": "object",
+                "properties": { "ticket": { "type": "string" } },
+                "required": ["ticket"],
+            }))
+            .unwrap()
+            .regex("TCK-[0-9]+")
+            .unwrap()
+            .with_max_retries(1)
+    }
+
+    #[tokio::test]
+    async fn test_invalid_answers_are_reprompted_then_fail() {
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-validation").await;
+        mock.push_text("{\"ticket\": \"none\"}")
+            .push_text("{\"ticket\": \"TCK-42\"}")
+            .push_text("Sorry, I can't.")
+            .push_text("Still no.");
+
+        let agent = LlmAgent::builder()
+            .name("support")
+            .model("mock-gemini-validation")
+            .build()
+            .unwrap();
+        let runner = RunnerBuilder::new()
+            .app_name("app")
+            .agent(Arc::new(agent))
+            .session_service(Arc::new(InMemorySessionService::new()))
+            .response_validation(ticket_validation())
+            .build()
+            .unwrap();
+
+        let events: Vec<_> = runner
+            .run_async("u1".into(), "s1".into(), Content::user_text("Open a ticket"))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let answer = events.last().unwrap().as_ref().unwrap();
+        assert_eq!(answer.get_text().as_deref(), Some("{\"ticket\": \"TCK-42\"}"));
+        let retry = mock.requests()[1].body.to_string();
+        assert!(retry.contains("failed validation") && retry.contains("TCK-[0-9]+"), "{}", retry);
+
+        let events: Vec<_> = runner
+            .run_async("u1".into(), "s2".into(), Content::user_text("Open a ticket"))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let failure = ValidationFailure::from_event(events.last().unwrap().as_ref().unwrap()).unwrap();
+        assert_eq!(failure.validator, "json_schema");
+        assert_eq!(failure.attempts, 2);
+        assert_eq!(mock.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_custom_checks_run_in_order() {
+        let validation = ResponseValidation::new()
+            .check("polite", |text| {
+                if text.contains("please") {
+                    Ok(())
+                } else {
+                    Err("say please".to_string())
+                }
+            })
+            .regex("^[a-z ]+$")
+            .unwrap();
+        assert!(validation.validate("yes please", 1).await.is_none());
+        assert_eq!(validation.validate("No", 1).await.unwrap().validator, "polite");
+        assert_eq!(validation.validate("Yes please", 1).await.unwrap().validator, "regex");
+    }
+}
@@ -0,0 +1,579 @@
+//! Conversation analytics
+//!
+//! A background job periodically aggregates stored sessions into per-app
+//! metrics (turns per session, tool usage, abandonment, top intents) and
+//! persists them, so product questions can be answered from the admin API
+//! without exporting conversations. Sessions of an
+//! [experiment](crate::experiments) are also broken down by variant.
+
+use crate::{
+    error::Result,
+    experiments::event_variant,
+    models::{create_model, LlmRequest},
+    sessions::{Feedback, Rating, Session, SessionFilter, SessionService},
+    types::{ContentPart, Timestamp},
+    utils::{global_usage_tracker, ModelCallRecord},
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "sql")]
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{info, warn};
+
+/// Schedule and parameters of the analytics job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalyticsConfig {
+    /// How often metrics are recomputed; 0 disables the job
+    pub interval_seconds: u64,
+
+    /// Sessions active within this many hours are aggregated
+    pub window_hours: i64,
+
+    /// Sessions idle this long whose last message is the user's count as abandoned
+    pub abandonment_timeout_seconds: i64,
+
+    /// Model labelling intents; intent clustering is skipped when unset
+    pub intent_model: Option<String>,
+
+    /// Number of intents reported per app
+    pub top_intents: usize,
+
+    /// Opening messages sent for clustering per app
+    pub intent_sample_size: usize,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 3600,
+            window_hours: 24 * 7,
+            abandonment_timeout_seconds: 30 * 60,
+            intent_model: None,
+            top_intents: 10,
+            intent_sample_size: 200,
+        }
+    }
+}
+
+impl AnalyticsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_interval(mut self, seconds: u64) -> Self {
+        self.interval_seconds = seconds;
+        self
+    }
+
+    pub fn with_window_hours(mut self, hours: i64) -> Self {
+        self.window_hours = hours;
+        self
+    }
+
+    pub fn with_abandonment_timeout(mut self, seconds: i64) -> Self {
+        self.abandonment_timeout_seconds = seconds;
+        self
+    }
+
+    pub fn with_intent_model(mut self, model: impl Into<String>) -> Self {
+        self.intent_model = Some(model.into());
+        self
+    }
+}
+
+/// Sessions whose opening message was given an intent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntentCount {
+    pub intent: String,
+    pub sessions: usize,
+    pub share: f64,
+}
+
+/// Aggregated metrics of one app's conversations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationMetrics {
+    pub app_name: String,
+    pub computed_at: Timestamp,
+    pub window_start: Timestamp,
+    pub sessions: usize,
+
+    /// Average number of user messages per session
+    pub turns_per_session: f64,
+    pub max_turns: usize,
+
+    /// Tool calls keyed by tool name
+    pub tool_usage: BTreeMap<String, usize>,
+    pub abandoned_sessions: usize,
+    pub abandonment_rate: f64,
+
+    /// Most common intents, most frequent first; empty without an intent model
+    #[serde(default)]
+    pub top_intents: Vec<IntentCount>,
+
+    /// Metrics of each experiment variant with sessions in the window
+    #[serde(default)]
+    pub variants: Vec<VariantMetrics>,
+}
+
+/// Quality, feedback and cost of the sessions assigned to one experiment variant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariantMetrics {
+    pub experiment: String,
+    pub variant: String,
+    pub sessions: usize,
+    pub turns_per_session: f64,
+    pub abandonment_rate: f64,
+    pub thumbs_up: usize,
+    pub thumbs_down: usize,
+
+    /// Share of rated events rated thumbs up; `None` without feedback
+    pub satisfaction: Option<f64>,
+    pub model_calls: usize,
+
+    /// Cost of the model calls with known pricing
+    pub cost_usd: f64,
+}
+
+/// Aggregate `sessions` of one app (intents are filled in separately)
+pub fn aggregate_sessions(
+    app_name: &str,
+    sessions: &[Session],
+    window_start: Timestamp,
+    abandonment_timeout: chrono::Duration,
+) -> ConversationMetrics {
+    let now = crate::types::now();
+    let mut total_turns = 0;
+    let mut max_turns = 0;
+    let mut tool_usage = BTreeMap::new();
+    let mut abandoned_sessions = 0;
+
+    for session in sessions {
+        let mut events = session.events.iter().filter(|event| !event.is_partial);
+        let turns = events.clone().filter(|event| event.author == "user").count();
+        total_turns += turns;
+        max_turns = max_turns.max(turns);
+
+        for part in events.clone().filter_map(|event| event.content.as_ref()).flat_map(|c| &c.parts) {
+            if let ContentPart::FunctionCall { name, .. } = part {
+                *tool_usage.entry(name.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let idle = now - session.updated_at >= abandonment_timeout;
+        if idle && events.next_back().is_some_and(|event| event.author == "user") {
+            abandoned_sessions += 1;
+        }
+    }
+
+    let ratio = |count: usize| match sessions.len() {
+        0 => 0.0,
+        total => count as f64 / total as f64,
+    };
+    ConversationMetrics {
+        app_name: app_name.to_string(),
+        computed_at: now,
+        window_start,
+        sessions: sessions.len(),
+        turns_per_session: ratio(total_turns),
+        max_turns,
+        tool_usage,
+        abandoned_sessions,
+        abandonment_rate: ratio(abandoned_sessions),
+        top_intents: Vec::new(),
+        variants: Vec::new(),
+    }
+}
+
+/// Aggregate the sessions of one app by the experiment variant their events are tagged with
+pub fn aggregate_variants(
+    app_name: &str,
+    sessions: &[Session],
+    model_calls: &[ModelCallRecord],
+    window_start: Timestamp,
+    abandonment_timeout: chrono::Duration,
+) -> Vec<VariantMetrics> {
+    let mut per_variant: BTreeMap<(String, String), Vec<Session>> = BTreeMap::new();
+    for session in sessions {
+        if let Some((experiment, variant)) = session.events.iter().find_map(|event| event_variant(event)) {
+            let key = (experiment.to_string(), variant.to_string());
+            per_variant.entry(key).or_default().push(session.clone());
+        }
+    }
+
+    per_variant
+        .into_iter()
+        .map(|((experiment, variant), sessions)| {
+            let metrics = aggregate_sessions(app_name, &sessions, window_start, abandonment_timeout);
+            let ratings: Vec<Rating> = sessions
+                .iter()
+                .flat_map(|session| &session.events)
+                .filter_map(|event| Feedback::from_event(event))
+                .map(|feedback| feedback.rating)
+                .collect();
+            let thumbs_up = ratings.iter().filter(|rating| **rating == Rating::ThumbsUp).count();
+            let calls: Vec<&ModelCallRecord> = model_calls
+                .iter()
+                .filter(|call| call.app_name == app_name)
+                .filter(|call| call.experiment.as_deref() == Some(&experiment) && call.variant.as_deref() == Some(&variant))
+                .collect();
+            VariantMetrics {
+                sessions: metrics.sessions,
+                turns_per_session: metrics.turns_per_session,
+                abandonment_rate: metrics.abandonment_rate,
+                thumbs_up,
+                thumbs_down: ratings.len() - thumbs_up,
+                satisfaction: (!ratings.is_empty()).then(|| thumbs_up as f64 / ratings.len() as f64),
+                model_calls: calls.len(),
+                cost_usd: calls.iter().filter_map(|call| call.cost_usd).sum(),
+                experiment,
+                variant,
+            }
+        })
+        .collect()
+}
+
+/// Labels user messages with intents
+#[async_trait]
+pub trait IntentClusterer: Send + Sync {
+    /// One intent label per message, in order
+    async fn label(&self, messages: &[String], max_intents: usize) -> Result<Vec<String>>;
+}
+
+/// Clusters messages into intents with a model
+#[derive(Debug, Clone)]
+pub struct LlmIntentClusterer {
+    model: String,
+}
+
+impl LlmIntentClusterer {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self { model: model.into() }
+    }
+}
+
+#[async_trait]
+impl IntentClusterer for LlmIntentClusterer {
+    async fn label(&self, messages: &[String], max_intents: usize) -> Result<Vec<String>> {
+        let numbered: Vec<String> = messages
+            .iter()
+            .enumerate()
+            .map(|(index, message)| format!("{}. {}", index + 1, message.replace('\n', " ")))
+            .collect();
+        let prompt = format!(
+            "Group these user messages into at most {} intents with short snake_case labels. \
+             Reply with only a JSON array of {} strings: the intent of each message, in order.\n\n{}",
+            max_intents,
+            messages.len(),
+            numbered.join("\n")
+        );
+
+        let model = create_model(&self.model).await?;
+        let response = model.generate_content(LlmRequest::new(&self.model).add_user_message(prompt)).await?;
+        let text = response.get_text().unwrap_or_default();
+        let json = text
+            .trim()
+            .trim_start_matches("```json")
+            .trim_start_matches("```")
+            .trim_end_matches("```")
+            .trim();
+        let labels: Vec<String> = serde_json::from_str(json)
+            .map_err(|e| crate::adk_error!(ModelError, "Intent labels are not a JSON array of strings: {}", e))?;
+        if labels.len() != messages.len() {
+            return Err(crate::adk_error!(
+                ModelError,
+                "Got {} intent labels for {} messages",
+                labels.len(),
+                messages.len()
+            ));
+        }
+        Ok(labels)
+    }
+}
+
+/// Most frequent intents of the sessions' opening messages
+pub async fn top_intents(
+    clusterer: &dyn IntentClusterer,
+    sessions: &[Session],
+    sample_size: usize,
+    top: usize,
+) -> Result<Vec<IntentCount>> {
+    let openings: Vec<String> = sessions
+        .iter()
+        .filter_map(|session| session.events.iter().find(|event| event.author == "user"))
+        .filter_map(|event| event.get_text())
+        .filter(|text| !text.trim().is_empty())
+        .take(sample_size)
+        .collect();
+    if openings.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for label in clusterer.label(&openings, top).await? {
+        *counts.entry(label.trim().to_lowercase()).or_insert(0) += 1;
+    }
+    let mut intents: Vec<IntentCount> = counts
+        .into_iter()
+        .map(|(intent, sessions)| IntentCount {
+            intent,
+            sessions,
+            share: sessions as f64 / openings.len() as f64,
+        })
+        .collect();
+    intents.sort_by(|a, b| b.sessions.cmp(&a.sessions).then_with(|| a.intent.cmp(&b.intent)));
+    intents.truncate(top);
+    Ok(intents)
+}
+
+/// Where computed metrics are kept
+#[async_trait]
+pub trait AnalyticsStore: Send + Sync {
+    async fn save(&self, metrics: &ConversationMetrics) -> Result<()>;
+
+    /// Most recent metrics of every app, or of one app
+    async fn latest(&self, app_name: Option<&str>) -> Result<Vec<ConversationMetrics>>;
+}
+
+/// Keeps the latest metrics of each app in memory
+#[derive(Default)]
+pub struct InMemoryAnalyticsStore {
+    metrics: RwLock<BTreeMap<String, ConversationMetrics>>,
+}
+
+impl InMemoryAnalyticsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AnalyticsStore for InMemoryAnalyticsStore {
+    async fn save(&self, metrics: &ConversationMetrics) -> Result<()> {
+        self.metrics.write().unwrap().insert(metrics.app_name.clone(), metrics.clone());
+        Ok(())
+    }
+
+    async fn latest(&self, app_name: Option<&str>) -> Result<Vec<ConversationMetrics>> {
+        let metrics = self.metrics.read().unwrap();
+        Ok(metrics
+            .values()
+            .filter(|m| app_name.is_none_or(|app| m.app_name == app))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Persists every computed snapshot to a `conversation_analytics` SQLite table
+#[cfg(feature = "sql")]
+pub struct SqliteAnalyticsStore {
+    pool: SqlitePool,
+}
+
+#[cfg(feature = "sql")]
+impl SqliteAnalyticsStore {
+    /// Connect to `url` (e.g. `sqlite://analytics.db?mode=rwc`) and create the table if needed
+    pub async fn connect(url: &str) -> Result<Self> {
+        let pool = SqlitePoolOptions::new().max_connections(1).connect(url).await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS conversation_analytics (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                app_name TEXT NOT NULL,
+                computed_at TEXT NOT NULL,
+                metrics TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS conversation_analytics_app
+             ON conversation_analytics (app_name, computed_at)",
+        )
+        .execute(&pool)
+        .await?;
+        Ok(Self { pool })
+    }
+}
+
+#[cfg(feature = "sql")]
+#[async_trait]
+impl AnalyticsStore for SqliteAnalyticsStore {
+    async fn save(&self, metrics: &ConversationMetrics) -> Result<()> {
+        sqlx::query("INSERT INTO conversation_analytics (app_name, computed_at, metrics) VALUES (?, ?, ?)")
+            .bind(&metrics.app_name)
+            .bind(metrics.computed_at.to_rfc3339())
+            .bind(serde_json::to_string(metrics)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn latest(&self, app_name: Option<&str>) -> Result<Vec<ConversationMetrics>> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT metrics FROM conversation_analytics AS a
+             WHERE (?1 IS NULL OR app_name = ?1)
+               AND id = (SELECT MAX(id) FROM conversation_analytics WHERE app_name = a.app_name)
+             ORDER BY app_name",
+        )
+        .bind(app_name)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|(metrics,)| serde_json::from_str(&metrics).map_err(Into::into))
+            .collect()
+    }
+}
+
+/// Periodically aggregates stored sessions into the analytics store
+pub struct AnalyticsJob {
+    config: AnalyticsConfig,
+    session_service: Arc<dyn SessionService>,
+    store: Arc<dyn AnalyticsStore>,
+    clusterer: Option<Arc<dyn IntentClusterer>>,
+}
+
+impl AnalyticsJob {
+    /// Clusters intents with `config.intent_model` when set
+    pub fn new(config: AnalyticsConfig, session_service: Arc<dyn SessionService>, store: Arc<dyn AnalyticsStore>) -> Self {
+        let clusterer = config
+            .intent_model
+            .clone()
+            .map(|model| Arc::new(LlmIntentClusterer::new(model)) as Arc<dyn IntentClusterer>);
+        Self {
+            config,
+            session_service,
+            store,
+            clusterer,
+        }
+    }
+
+    pub fn with_clusterer(mut self, clusterer: Arc<dyn IntentClusterer>) -> Self {
+        self.clusterer = Some(clusterer);
+        self
+    }
+
+    /// Compute and store metrics for every app with sessions in the window
+    pub async fn run_once(&self) -> Result<Vec<ConversationMetrics>> {
+        let window_start = crate::types::now() - chrono::Duration::hours(self.config.window_hours);
+        let mut per_app: BTreeMap<String, Vec<Session>> = BTreeMap::new();
+        for session in self.session_service.list_sessions(&SessionFilter::new()).await? {
+            if session.updated_at >= window_start {
+                per_app.entry(session.app_name.clone()).or_default().push(session);
+            }
+        }
+
+        let abandonment_timeout = chrono::Duration::seconds(self.config.abandonment_timeout_seconds);
+        let model_calls = global_usage_tracker().model_calls_since(window_start);
+        let mut computed = Vec::new();
+        for (app_name, sessions) in per_app {
+            let mut metrics = aggregate_sessions(&app_name, &sessions, window_start, abandonment_timeout);
+            metrics.variants = aggregate_variants(&app_name, &sessions, &model_calls, window_start, abandonment_timeout);
+            if let Some(clusterer) = &self.clusterer {
+                let sample = self.config.intent_sample_size;
+                match top_intents(clusterer.as_ref(), &sessions, sample, self.config.top_intents).await {
+                    Ok(intents) => metrics.top_intents = intents,
+                    Err(e) => warn!("Intent clustering failed for {}: {}", app_name, e),
+                }
+            }
+            self.store.save(&metrics).await?;
+            computed.push(metrics);
+        }
+        Ok(computed)
+    }
+
+    /// Run `run_once` on `tasks` every interval until `cancel` fires
+    pub fn spawn(self: Arc<Self>, tasks: &TaskTracker, cancel: CancellationToken) -> JoinHandle<()> {
+        let interval = Duration::from_secs(self.config.interval_seconds.max(1));
+        tasks.spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                match self.run_once().await {
+                    Ok(metrics) => info!("Computed conversation analytics for {} apps", metrics.len()),
+                    Err(e) => warn!("Conversation analytics failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::Event,
+        sessions::InMemorySessionService,
+        types::FunctionCall,
+    };
+
+    /// Labels messages by keyword
+    struct KeywordClusterer;
+
+    #[async_trait]
+    impl IntentClusterer for KeywordClusterer {
+        async fn label(&self, messages: &[String], _max_intents: usize) -> Result<Vec<String>> {
+            Ok(messages
+                .iter()
+                .map(|m| if m.contains("refund") { "refund" } else { "shipping" }.to_string())
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_aggregates_and_persists_metrics() {
+        let sessions = Arc::new(InMemorySessionService::new());
+        let stale = crate::types::now() - chrono::Duration::hours(2);
+
+        let mut answered = Session::new("shop".into(), "u1".into(), "s1".into());
+        answered.add_event(Event::user_input("I want a refund", uuid::Uuid::new_v4()));
+        let call = FunctionCall { name: "lookup_order".into(), args: serde_json::json!({}) };
+        answered.add_event(Event::function_call("helper", call));
+        answered.add_event(Event::text_response("helper", "Refund issued."));
+        answered.add_event(Event::user_input("thanks, another refund please", uuid::Uuid::new_v4()));
+        answered.add_event(Event::text_response("helper", "Done."));
+
+        let mut abandoned = Session::new("shop".into(), "u2".into(), "s2".into());
+        abandoned.add_event(Event::user_input("where is my refund", uuid::Uuid::new_v4()));
+        abandoned.updated_at = stale;
+
+        let mut other = Session::new("shop".into(), "u3".into(), "s3".into());
+        other.add_event(Event::user_input("shipping to Oslo?", uuid::Uuid::new_v4()));
+        other.add_event(Event::text_response("helper", "3 days."));
+        for session in [answered, abandoned, other] {
+            sessions.create_session(session).await.unwrap();
+        }
+
+        #[cfg(feature = "sql")]
+        let store = Arc::new(SqliteAnalyticsStore::connect("sqlite::memory:").await.unwrap());
+        #[cfg(not(feature = "sql"))]
+        let store = Arc::new(InMemoryAnalyticsStore::new());
+        let job = AnalyticsJob::new(AnalyticsConfig::new(), sessions, store.clone())
+            .with_clusterer(Arc::new(KeywordClusterer));
+        job.run_once().await.unwrap();
+        let metrics = job.run_once().await.unwrap().remove(0);
+
+        assert_eq!(metrics.sessions, 3);
+        assert!((metrics.turns_per_session - 4.0 / 3.0).abs() < 1e-9);
+        assert_eq!(metrics.max_turns, 2);
+        assert_eq!(metrics.tool_usage["lookup_order"], 1);
+        assert_eq!(metrics.abandoned_sessions, 1);
+        assert_eq!(metrics.top_intents[0].intent, "refund");
+        assert_eq!(metrics.top_intents[0].sessions, 2);
+
+        // Only the latest snapshot per app is returned
+        let latest = store.latest(Some("shop")).await.unwrap();
+        assert_eq!(latest, vec![metrics]);
+        assert!(store.latest(Some("other")).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+//! Artifact service trait and its shared types
+
+use crate::{
+    artifacts::{metadata::SNIFF_LEN, policy::quota_exceeded, ArtifactMetadata},
+    error::Result,
+    types::{InvocationId, SessionId, Timestamp, UserId},
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, ops::Range, pin::Pin};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Location of an artifact; every save under the same key adds a version
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ArtifactKey {
+    pub app_name: String,
+    pub user_id: UserId,
+    pub session_id: SessionId,
+    pub filename: String,
+}
+
+impl ArtifactKey {
+    pub fn new(
+        app_name: impl Into<String>,
+        user_id: impl Into<UserId>,
+        session_id: impl Into<SessionId>,
+        filename: impl Into<String>,
+    ) -> Self {
+        Self {
+            app_name: app_name.into(),
+            user_id: user_id.into(),
+            session_id: session_id.into(),
+            filename: filename.into(),
+        }
+    }
+
+    /// Reject components that are empty or could escape a storage directory
+    pub fn validate(&self) -> Result<()> {
+        for component in [&self.app_name, &self.user_id, &self.session_id, &self.filename] {
+            if component.is_empty()
+                || component == "."
+                || component == ".."
+                || component.contains(['/', '\\', '\0'])
+            {
+                return Err(crate::adk_error!(ArtifactError, "Invalid artifact key component '{}'", component));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One stored version of an artifact
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactVersion {
+    /// Version number, starting at 1
+    pub version: u64,
+
+    /// Size in bytes
+    pub size: u64,
+
+    pub mime_type: String,
+
+    pub created_at: Timestamp,
+
+    /// Agent that produced the version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+
+    /// Invocation that produced the version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invocation_id: Option<InvocationId>,
+
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+
+    /// When the version stops being served and becomes eligible for cleanup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<Timestamp>,
+}
+
+impl ArtifactVersion {
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Byte stream of an artifact
+pub type ArtifactRead = Pin<Box<dyn AsyncRead + Send>>;
+
+/// An open artifact read
+pub struct ArtifactReader {
+    /// Version being read
+    pub version: ArtifactVersion,
+
+    /// Bytes the reader yields, the requested range clamped to the artifact size
+    pub range: Range<u64>,
+
+    pub reader: ArtifactRead,
+}
+
+/// Byte sink of a new artifact version
+#[async_trait]
+pub trait ArtifactWrite: AsyncWrite + Send + Unpin {
+    /// Publish the written bytes as the key's next version; dropping the writer discards them
+    async fn commit(self: Box<Self>) -> Result<ArtifactVersion>;
+}
+
+/// An open artifact write
+pub type ArtifactWriter = Box<dyn ArtifactWrite>;
+
+/// Bytes written so far to a new version, checked against the size quota
+#[derive(Debug, Default)]
+pub(crate) struct WriteProgress {
+    pub written: u64,
+    pub head: Vec<u8>,
+    pub max_size_bytes: Option<u64>,
+}
+
+impl WriteProgress {
+    pub fn new(max_size_bytes: Option<u64>) -> Self {
+        Self { max_size_bytes, ..Self::default() }
+    }
+
+    /// Fail before writing `len` more bytes would exceed the quota
+    pub fn check(&self, len: usize) -> std::io::Result<()> {
+        match self.max_size_bytes {
+            Some(max) if self.written + len as u64 > max => Err(quota_exceeded(max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record(&mut self, written: &[u8]) {
+        self.written += written.len() as u64;
+        let keep = SNIFF_LEN.saturating_sub(self.head.len()).min(written.len());
+        self.head.extend_from_slice(&written[..keep]);
+    }
+}
+
+/// Clamp `range` to an artifact of `size` bytes
+pub fn clamp_range(range: Range<u64>, size: u64) -> Range<u64> {
+    let start = range.start.min(size);
+    start..range.end.clamp(start, size)
+}
+
+/// Storage of versioned binary artifacts.
+///
+/// Large artifacts are written and read as streams so they never have to
+/// fit in memory; `save_artifact` and `load_artifact` are conveniences for
+/// small ones. A version is invisible to readers until its writer commits
+/// and once it expires. Backends enforce their apps' [`ArtifactPolicy`] size
+/// quota while a version is written, failing the write with
+/// `std::io::ErrorKind::FileTooLarge`.
+///
+/// [`ArtifactPolicy`]: crate::artifacts::ArtifactPolicy
+#[async_trait]
+pub trait BaseArtifactService: Send + Sync {
+    /// Start writing a new version of the artifact
+    async fn open_write(&self, key: &ArtifactKey, metadata: ArtifactMetadata) -> Result<ArtifactWriter>;
+
+    /// Read `range` of a version (the latest if `None`); `None` if it does not exist
+    async fn open_read_range(
+        &self,
+        key: &ArtifactKey,
+        version: Option<u64>,
+        range: Range<u64>,
+    ) -> Result<Option<ArtifactReader>>;
+
+    /// Unexpired versions of the artifact, oldest first
+    async fn list_versions(&self, key: &ArtifactKey) -> Result<Vec<ArtifactVersion>>;
+
+    /// Filenames of the session's artifacts, sorted
+    async fn list_artifact_keys(&self, app_name: &str, user_id: &UserId, session_id: &SessionId) -> Result<Vec<String>>;
+
+    /// Delete every version of the artifact
+    async fn delete_artifact(&self, key: &ArtifactKey) -> Result<()>;
+
+    /// Delete every artifact of a user in every app and session, expired
+    /// versions included; returns how many artifacts were deleted
+    async fn delete_user_artifacts(&self, user_id: &UserId) -> Result<usize>;
+
+    /// Delete expired versions; returns how many were deleted
+    async fn purge_expired(&self) -> Result<usize>;
+
+    /// Delete every artifact of a session; returns how many were deleted
+    async fn delete_session_artifacts(&self, app_name: &str, user_id: &UserId, session_id: &SessionId) -> Result<usize> {
+        let filenames = self.list_artifact_keys(app_name, user_id, session_id).await?;
+        for filename in &filenames {
+            let key = ArtifactKey::new(app_name, user_id.as_str(), session_id.as_str(), filename.as_str());
+            self.delete_artifact(&key).await?;
+        }
+        Ok(filenames.len())
+    }
+
+    /// Read a whole version (the latest if `None`)
+    async fn open_read(&self, key: &ArtifactKey, version: Option<u64>) -> Result<Option<ArtifactReader>> {
+        self.open_read_range(key, version, 0..u64::MAX).await
+    }
+
+    /// Metadata of a version (the latest if `None`)
+    async fn artifact_version(&self, key: &ArtifactKey, version: Option<u64>) -> Result<Option<ArtifactVersion>> {
+        let versions = self.list_versions(key).await?;
+        Ok(match version {
+            Some(version) => versions.into_iter().find(|stored| stored.version == version),
+            None => versions.into_iter().last(),
+        })
+    }
+
+    /// Store `data` as the artifact's next version
+    async fn save_artifact(&self, key: &ArtifactKey, data: Bytes, metadata: ArtifactMetadata) -> Result<ArtifactVersion> {
+        let mut writer = self.open_write(key, metadata).await?;
+        writer
+            .write_all(&data)
+            .await
+            .map_err(|e| crate::adk_error!(ArtifactError, "Failed to write artifact '{}': {}", key.filename, e))?;
+        writer.commit().await
+    }
+
+    /// Load a whole version (the latest if `None`) into memory
+    async fn load_artifact(&self, key: &ArtifactKey, version: Option<u64>) -> Result<Option<(ArtifactVersion, Bytes)>> {
+        let Some(mut read) = self.open_read(key, version).await? else {
+            return Ok(None);
+        };
+        let mut data = Vec::with_capacity(read.version.size as usize);
+        read.reader
+            .read_to_end(&mut data)
+            .await
+            .map_err(|e| crate::adk_error!(ArtifactError, "Failed to read artifact '{}': {}", key.filename, e))?;
+        Ok(Some((read.version, data.into())))
+    }
+}
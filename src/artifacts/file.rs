@@ -0,0 +1,332 @@
+//! Artifact storage on the local filesystem
+//!
+//! Versions live under `<root>/<app>/<user>/<session>/<filename>/` as a data
+//! file named after the version number next to a `<version>.json` metadata
+//! file. Writers stream into a hidden partial file that is renamed into place
+//! on commit, and a version becomes visible once its metadata file exists.
+//! Expired versions are hidden from readers and deleted by `purge_expired`.
+
+use crate::{
+    artifacts::{
+        artifact_service::WriteProgress, clamp_range, ArtifactKey, ArtifactMetadata, ArtifactPolicies, ArtifactReader,
+        ArtifactVersion, ArtifactWrite, ArtifactWriter, BaseArtifactService,
+    },
+    error::Result,
+    types::{SessionId, UserId},
+};
+use async_trait::async_trait;
+use std::{
+    io::SeekFrom,
+    ops::Range,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::{
+    fs::{self, File},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWrite},
+    sync::Mutex,
+};
+
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> crate::error::AdkError {
+    crate::adk_error!(ArtifactError, "Failed to {} '{}': {}", action, path.display(), e)
+}
+
+/// Artifact service storing versions as files under a root directory
+#[derive(Debug, Clone)]
+pub struct FileArtifactService {
+    root: PathBuf,
+
+    /// Serializes version numbering between commits
+    commit_lock: Arc<Mutex<()>>,
+
+    policies: ArtifactPolicies,
+}
+
+impl FileArtifactService {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            commit_lock: Arc::new(Mutex::new(())),
+            policies: ArtifactPolicies::default(),
+        }
+    }
+
+    /// Enforce per-app size quotas and TTLs
+    pub fn with_policies(mut self, policies: ArtifactPolicies) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    fn session_dir(&self, app_name: &str, user_id: &str, session_id: &str) -> PathBuf {
+        self.root.join(app_name).join(user_id).join(session_id)
+    }
+
+    fn artifact_dir(&self, key: &ArtifactKey) -> PathBuf {
+        self.session_dir(&key.app_name, &key.user_id, &key.session_id).join(&key.filename)
+    }
+}
+
+/// Subdirectories of `dir`; none if it doesn't exist
+async fn subdirectories(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_error("list", dir, e)),
+    };
+    let mut dirs = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(|e| io_error("list", dir, e))? {
+        if entry.file_type().await.is_ok_and(|file_type| file_type.is_dir()) {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+/// Unexpired versions in `dir`, oldest first
+async fn live_versions(dir: &Path) -> Result<Vec<ArtifactVersion>> {
+    let now = crate::types::now();
+    let mut versions = read_versions(dir).await?;
+    versions.retain(|version| !version.is_expired(now));
+    Ok(versions)
+}
+
+/// Committed versions in `dir`, including expired ones, oldest first
+async fn read_versions(dir: &Path) -> Result<Vec<ArtifactVersion>> {
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_error("list", dir, e)),
+    };
+    let mut versions = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(|e| io_error("list", dir, e))? {
+        let path = entry.path();
+        if path.extension().is_some_and(|extension| extension == "json") {
+            let json = fs::read(&path).await.map_err(|e| io_error("read", &path, e))?;
+            let version: ArtifactVersion = serde_json::from_slice(&json)
+                .map_err(|e| crate::adk_error!(ArtifactError, "Corrupt artifact metadata '{}': {}", path.display(), e))?;
+            versions.push(version);
+        }
+    }
+    versions.sort_by_key(|version| version.version);
+    Ok(versions)
+}
+
+/// Streams a version into a partial file until it is committed
+struct FileWriter {
+    file: File,
+    partial: PathBuf,
+    dir: PathBuf,
+    filename: String,
+    metadata: ArtifactMetadata,
+    policies: ArtifactPolicies,
+    app_name: String,
+    progress: WriteProgress,
+    commit_lock: Arc<Mutex<()>>,
+    committed: bool,
+}
+
+impl AsyncWrite for FileWriter {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        self.progress.check(buf.len())?;
+        let written = std::task::ready!(Pin::new(&mut self.file).poll_write(cx, buf))?;
+        self.progress.record(&buf[..written]);
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.file).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.file).poll_shutdown(cx)
+    }
+}
+
+#[async_trait]
+impl ArtifactWrite for FileWriter {
+    async fn commit(mut self: Box<Self>) -> Result<ArtifactVersion> {
+        self.file.sync_all().await.map_err(|e| io_error("write", &self.partial, e))?;
+        let size = self.file.metadata().await.map_err(|e| io_error("write", &self.partial, e))?.len();
+
+        let _guard = self.commit_lock.clone().lock_owned().await;
+        let versions = read_versions(&self.dir).await?;
+        let policy = self.policies.for_app(&self.app_name);
+        let version = self.metadata.clone().into_version(
+            versions.last().map_or(1, |latest| latest.version + 1),
+            size,
+            &self.filename,
+            &self.progress.head,
+            &policy,
+            crate::types::now(),
+        );
+        let data = self.dir.join(version.version.to_string());
+        fs::rename(&self.partial, &data).await.map_err(|e| io_error("commit", &data, e))?;
+        self.committed = true;
+
+        // Metadata is renamed into place too, so readers never see half of it
+        let metadata = self.dir.join(format!("{}.json", version.version));
+        let staged = self.dir.join(format!(".{}.json.partial", version.version));
+        let json = serde_json::to_vec(&version)?;
+        fs::write(&staged, json).await.map_err(|e| io_error("write", &staged, e))?;
+        fs::rename(&staged, &metadata).await.map_err(|e| io_error("commit", &metadata, e))?;
+        Ok(version)
+    }
+}
+
+impl Drop for FileWriter {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_file(&self.partial);
+        }
+    }
+}
+
+#[async_trait]
+impl BaseArtifactService for FileArtifactService {
+    async fn open_write(&self, key: &ArtifactKey, metadata: ArtifactMetadata) -> Result<ArtifactWriter> {
+        key.validate()?;
+        let dir = self.artifact_dir(key);
+        fs::create_dir_all(&dir).await.map_err(|e| io_error("create", &dir, e))?;
+        let partial = dir.join(format!(".{}.partial", uuid::Uuid::new_v4()));
+        let file = File::create(&partial).await.map_err(|e| io_error("create", &partial, e))?;
+        Ok(Box::new(FileWriter {
+            file,
+            partial,
+            dir,
+            filename: key.filename.clone(),
+            metadata,
+            policies: self.policies.clone(),
+            app_name: key.app_name.clone(),
+            progress: WriteProgress::new(self.policies.for_app(&key.app_name).max_size_bytes),
+            commit_lock: self.commit_lock.clone(),
+            committed: false,
+        }))
+    }
+
+    async fn open_read_range(
+        &self,
+        key: &ArtifactKey,
+        version: Option<u64>,
+        range: Range<u64>,
+    ) -> Result<Option<ArtifactReader>> {
+        key.validate()?;
+        let Some(version) = self.artifact_version(key, version).await? else {
+            return Ok(None);
+        };
+        let path = self.artifact_dir(key).join(version.version.to_string());
+        let mut file = File::open(&path).await.map_err(|e| io_error("open", &path, e))?;
+        let range = clamp_range(range, version.size);
+        file.seek(SeekFrom::Start(range.start)).await.map_err(|e| io_error("read", &path, e))?;
+        Ok(Some(ArtifactReader {
+            version,
+            reader: Box::pin(file.take(range.end - range.start)),
+            range,
+        }))
+    }
+
+    async fn list_versions(&self, key: &ArtifactKey) -> Result<Vec<ArtifactVersion>> {
+        key.validate()?;
+        live_versions(&self.artifact_dir(key)).await
+    }
+
+    async fn list_artifact_keys(&self, app_name: &str, user_id: &UserId, session_id: &SessionId) -> Result<Vec<String>> {
+        ArtifactKey::new(app_name, user_id.as_str(), session_id.as_str(), "_").validate()?;
+        let mut filenames = Vec::new();
+        for dir in subdirectories(&self.session_dir(app_name, user_id, session_id)).await? {
+            if !live_versions(&dir).await?.is_empty() {
+                if let Some(filename) = dir.file_name() {
+                    filenames.push(filename.to_string_lossy().into_owned());
+                }
+            }
+        }
+        filenames.sort();
+        Ok(filenames)
+    }
+
+    async fn delete_artifact(&self, key: &ArtifactKey) -> Result<()> {
+        key.validate()?;
+        let dir = self.artifact_dir(key);
+        match fs::remove_dir_all(&dir).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io_error("delete", &dir, e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn delete_user_artifacts(&self, user_id: &UserId) -> Result<usize> {
+        ArtifactKey::new("_", user_id.as_str(), "_", "_").validate()?;
+        let mut deleted = 0;
+        for app in subdirectories(&self.root).await? {
+            let dir = app.join(user_id);
+            for session in subdirectories(&dir).await? {
+                deleted += subdirectories(&session).await?.len();
+            }
+            match fs::remove_dir_all(&dir).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(io_error("delete", &dir, e)),
+                _ => {}
+            }
+        }
+        Ok(deleted)
+    }
+
+    async fn purge_expired(&self) -> Result<usize> {
+        let now = crate::types::now();
+        let mut purged = 0;
+        for app in subdirectories(&self.root).await? {
+            for user in subdirectories(&app).await? {
+                for session in subdirectories(&user).await? {
+                    for dir in subdirectories(&session).await? {
+                        for version in read_versions(&dir).await? {
+                            if version.is_expired(now) {
+                                // Metadata first, so a failure never leaves a visible version without data
+                                let metadata = dir.join(format!("{}.json", version.version));
+                                fs::remove_file(&metadata).await.map_err(|e| io_error("delete", &metadata, e))?;
+                                let _ = fs::remove_file(dir.join(version.version.to_string())).await;
+                                purged += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_file_versions_range_reads_and_expiry() {
+        let root = std::env::temp_dir().join(format!("adk-artifacts-{}", uuid::Uuid::new_v4()));
+        let service = FileArtifactService::new(&root);
+        let key = ArtifactKey::new("app", "u1", "s1", "data.csv");
+
+        let mut writer = service.open_write(&key, ArtifactMetadata::new()).await.unwrap();
+        writer.write_all(b"a,b\n1,2\n").await.unwrap();
+        drop(writer);
+        assert!(service.list_versions(&key).await.unwrap().is_empty());
+
+        service.save_artifact(&key, Bytes::from_static(b"a,b\n1,2\n"), ArtifactMetadata::new()).await.unwrap();
+        let metadata = ArtifactMetadata::new().with_ttl(std::time::Duration::ZERO);
+        service.save_artifact(&key, Bytes::from_static(b"a,b\n3,4\n"), metadata).await.unwrap();
+        assert_eq!(service.list_versions(&key).await.unwrap()[0].mime_type, "text/csv");
+        let mut read = service.open_read_range(&key, Some(1), 4..100).await.unwrap().unwrap();
+        let mut tail = String::new();
+        read.reader.read_to_string(&mut tail).await.unwrap();
+        assert_eq!((read.range, tail.as_str()), (4..8, "1,2\n"));
+        assert_eq!(service.load_artifact(&key, None).await.unwrap().unwrap().0.version, 1);
+        assert_eq!(service.purge_expired().await.unwrap(), 1);
+        assert!(!root.join("app/u1/s1/data.csv/2").exists());
+        assert_eq!(service.list_artifact_keys("app", &"u1".into(), &"s1".into()).await.unwrap(), ["data.csv"]);
+
+        assert!(service.open_write(&ArtifactKey::new("app", "u1", "s1", ".."), ArtifactMetadata::new()).await.is_err());
+        service.delete_artifact(&key).await.unwrap();
+        assert!(service.load_artifact(&key, None).await.unwrap().is_none());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,240 @@
+//! In-memory artifact storage
+
+use crate::{
+    artifacts::{
+        artifact_service::WriteProgress, clamp_range, ArtifactKey, ArtifactMetadata, ArtifactPolicies, ArtifactReader,
+        ArtifactVersion, ArtifactWrite, ArtifactWriter, BaseArtifactService,
+    },
+    error::Result,
+    types::{SessionId, UserId},
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    ops::Range,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+};
+use tokio::io::AsyncWrite;
+
+type Store = Arc<RwLock<HashMap<ArtifactKey, Vec<(ArtifactVersion, Bytes)>>>>;
+
+/// Artifact service keeping every version in memory, for tests and development
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryArtifactService {
+    artifacts: Store,
+    policies: ArtifactPolicies,
+}
+
+impl InMemoryArtifactService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enforce per-app size quotas and TTLs
+    pub fn with_policies(mut self, policies: ArtifactPolicies) -> Self {
+        self.policies = policies;
+        self
+    }
+}
+
+/// Buffers a version until it is committed
+struct InMemoryWriter {
+    artifacts: Store,
+    key: ArtifactKey,
+    metadata: ArtifactMetadata,
+    policies: ArtifactPolicies,
+    progress: WriteProgress,
+    buffer: Vec<u8>,
+}
+
+impl AsyncWrite for InMemoryWriter {
+    fn poll_write(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        self.progress.check(buf.len())?;
+        self.progress.record(buf);
+        self.buffer.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[async_trait]
+impl ArtifactWrite for InMemoryWriter {
+    async fn commit(self: Box<Self>) -> Result<ArtifactVersion> {
+        let policy = self.policies.for_app(&self.key.app_name);
+        let mut artifacts = self.artifacts.write().unwrap();
+        let versions = artifacts.entry(self.key.clone()).or_default();
+        let version = self.metadata.into_version(
+            versions.last().map_or(1, |(latest, _)| latest.version + 1),
+            self.buffer.len() as u64,
+            &self.key.filename,
+            &self.progress.head,
+            &policy,
+            crate::types::now(),
+        );
+        versions.push((version.clone(), self.buffer.into()));
+        Ok(version)
+    }
+}
+
+#[async_trait]
+impl BaseArtifactService for InMemoryArtifactService {
+    async fn open_write(&self, key: &ArtifactKey, metadata: ArtifactMetadata) -> Result<ArtifactWriter> {
+        key.validate()?;
+        let max_size_bytes = self.policies.for_app(&key.app_name).max_size_bytes;
+        Ok(Box::new(InMemoryWriter {
+            artifacts: self.artifacts.clone(),
+            key: key.clone(),
+            metadata,
+            policies: self.policies.clone(),
+            progress: WriteProgress::new(max_size_bytes),
+            buffer: Vec::new(),
+        }))
+    }
+
+    async fn open_read_range(
+        &self,
+        key: &ArtifactKey,
+        version: Option<u64>,
+        range: Range<u64>,
+    ) -> Result<Option<ArtifactReader>> {
+        let now = crate::types::now();
+        let artifacts = self.artifacts.read().unwrap();
+        let Some(versions) = artifacts.get(key) else {
+            return Ok(None);
+        };
+        let mut live = versions.iter().filter(|(stored, _)| !stored.is_expired(now));
+        let stored = match version {
+            Some(version) => live.find(|(stored, _)| stored.version == version),
+            None => live.last(),
+        };
+        Ok(stored.map(|(version, data)| {
+            let range = clamp_range(range, version.size);
+            ArtifactReader {
+                version: version.clone(),
+                reader: Box::pin(Cursor::new(data.slice(range.start as usize..range.end as usize))),
+                range,
+            }
+        }))
+    }
+
+    async fn list_versions(&self, key: &ArtifactKey) -> Result<Vec<ArtifactVersion>> {
+        let now = crate::types::now();
+        let artifacts = self.artifacts.read().unwrap();
+        Ok(artifacts
+            .get(key)
+            .map(|versions| {
+                versions
+                    .iter()
+                    .filter(|(version, _)| !version.is_expired(now))
+                    .map(|(version, _)| version.clone())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn list_artifact_keys(&self, app_name: &str, user_id: &UserId, session_id: &SessionId) -> Result<Vec<String>> {
+        let now = crate::types::now();
+        let artifacts = self.artifacts.read().unwrap();
+        let mut filenames: Vec<String> = artifacts
+            .iter()
+            .filter(|(key, _)| key.app_name == app_name && &key.user_id == user_id && &key.session_id == session_id)
+            .filter(|(_, versions)| versions.iter().any(|(version, _)| !version.is_expired(now)))
+            .map(|(key, _)| key.filename.clone())
+            .collect();
+        filenames.sort();
+        Ok(filenames)
+    }
+
+    async fn delete_artifact(&self, key: &ArtifactKey) -> Result<()> {
+        self.artifacts.write().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn delete_user_artifacts(&self, user_id: &UserId) -> Result<usize> {
+        let mut artifacts = self.artifacts.write().unwrap();
+        let before = artifacts.len();
+        artifacts.retain(|key, _| &key.user_id != user_id);
+        Ok(before - artifacts.len())
+    }
+
+    async fn purge_expired(&self) -> Result<usize> {
+        let now = crate::types::now();
+        let mut artifacts = self.artifacts.write().unwrap();
+        let mut purged = 0;
+        artifacts.retain(|_, versions| {
+            let before = versions.len();
+            versions.retain(|(version, _)| !version.is_expired(now));
+            purged += before - versions.len();
+            !versions.is_empty()
+        });
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifacts::ArtifactPolicy;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_streamed_versions_and_range_reads() {
+        let service = InMemoryArtifactService::new();
+        let key = ArtifactKey::new("app", "u1", "s1", "clip.mp4");
+
+        let mut writer = service.open_write(&key, ArtifactMetadata::new()).await.unwrap();
+        writer.write_all(b"0123").await.unwrap();
+        writer.write_all(b"456789").await.unwrap();
+        assert!(service.open_read(&key, None).await.unwrap().is_none());
+        assert_eq!(writer.commit().await.unwrap().version, 1);
+        service.save_artifact(&key, Bytes::from_static(b"second"), ArtifactMetadata::new()).await.unwrap();
+
+        let mut read = service.open_read_range(&key, Some(1), 3..7).await.unwrap().unwrap();
+        let mut data = String::new();
+        read.reader.read_to_string(&mut data).await.unwrap();
+        assert_eq!(data, "3456");
+        assert_eq!(read.version.size, 10);
+
+        let read = service.open_read_range(&key, None, 4..100).await.unwrap().unwrap();
+        assert_eq!(read.range, 4..6);
+        let (latest, data) = service.load_artifact(&key, None).await.unwrap().unwrap();
+        assert_eq!((latest.version, &data[..]), (2, &b"second"[..]));
+        assert_eq!(service.list_artifact_keys("app", &"u1".into(), &"s1".into()).await.unwrap(), ["clip.mp4"]);
+    }
+
+    #[tokio::test]
+    async fn test_quota_and_expiry() {
+        let policies = ArtifactPolicies::default()
+            .with_app("app", ArtifactPolicy::default().with_max_size_bytes(8).with_ttl(Duration::from_secs(3600)));
+        let service = InMemoryArtifactService::new().with_policies(policies);
+        let key = ArtifactKey::new("app", "u1", "s1", "report");
+
+        let mut writer = service.open_write(&key, ArtifactMetadata::new()).await.unwrap();
+        writer.write_all(b"12345").await.unwrap();
+        let error = writer.write_all(b"6789").await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::FileTooLarge);
+
+        let metadata = ArtifactMetadata::new().with_created_by("writer").with_tag("draft");
+        let kept = service.save_artifact(&key, Bytes::from_static(b"%PDF-1.7"), metadata).await.unwrap();
+        assert_eq!((kept.mime_type.as_str(), kept.created_by.as_deref()), ("application/pdf", Some("writer")));
+        assert!(kept.expires_at.is_some());
+
+        let expired = ArtifactMetadata::new().with_ttl(Duration::ZERO);
+        service.save_artifact(&ArtifactKey::new("app", "u1", "s1", "tmp"), Bytes::from_static(b"x"), expired).await.unwrap();
+        assert_eq!(service.list_artifact_keys("app", &"u1".into(), &"s1".into()).await.unwrap(), ["report"]);
+        assert_eq!(service.purge_expired().await.unwrap(), 1);
+        assert_eq!(service.list_versions(&key).await.unwrap(), [kept]);
+    }
+}
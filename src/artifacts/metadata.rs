@@ -0,0 +1,160 @@
+//! Metadata attached to artifact versions and content-type detection
+
+use crate::{
+    artifacts::{ArtifactPolicy, ArtifactVersion},
+    types::{InvocationId, Timestamp},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, time::Duration};
+
+/// Bytes of a new version kept for content-type detection
+pub const SNIFF_LEN: usize = 512;
+
+/// Metadata supplied when writing an artifact version
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactMetadata {
+    /// Detected from the content and filename when unset
+    #[serde(default)]
+    pub mime_type: Option<String>,
+
+    /// Agent that produced the artifact
+    #[serde(default)]
+    pub created_by: Option<String>,
+
+    /// Invocation that produced the artifact
+    #[serde(default)]
+    pub invocation_id: Option<InvocationId>,
+
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+
+    /// Lifetime of the version; overrides the app's TTL policy
+    #[serde(default)]
+    pub ttl_seconds: Option<u64>,
+}
+
+impl ArtifactMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+
+    pub fn with_created_by(mut self, agent: impl Into<String>) -> Self {
+        self.created_by = Some(agent.into());
+        self
+    }
+
+    pub fn with_invocation_id(mut self, invocation_id: InvocationId) -> Self {
+        self.invocation_id = Some(invocation_id);
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.insert(tag.into());
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl_seconds = Some(ttl.as_secs());
+        self
+    }
+
+    /// Describe a committed version; `head` is its first bytes
+    pub(crate) fn into_version(
+        self,
+        version: u64,
+        size: u64,
+        filename: &str,
+        head: &[u8],
+        policy: &ArtifactPolicy,
+        created_at: Timestamp,
+    ) -> ArtifactVersion {
+        let ttl = self.ttl_seconds.or(policy.ttl_seconds);
+        ArtifactVersion {
+            version,
+            size,
+            mime_type: self.mime_type.unwrap_or_else(|| sniff_mime_type(head, filename).to_string()),
+            created_at,
+            created_by: self.created_by,
+            invocation_id: self.invocation_id,
+            tags: self.tags,
+            expires_at: ttl.map(|ttl| created_at + chrono::Duration::seconds(ttl as i64)),
+        }
+    }
+}
+
+/// Detect a MIME type from leading magic bytes, then the file extension,
+/// falling back to `text/plain` for UTF-8 and `application/octet-stream`
+pub fn sniff_mime_type(head: &[u8], filename: &str) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"\x1a\x45\xdf\xa3", "video/webm"),
+    ];
+    if let Some((_, mime_type)) = SIGNATURES.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return mime_type;
+    }
+    if head.len() >= 12 && &head[..4] == b"RIFF" {
+        match &head[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            _ => {}
+        }
+    }
+    if head.len() >= 8 && &head[4..8] == b"ftyp" {
+        return "video/mp4";
+    }
+
+    let extension = filename.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+    let by_extension = match extension.as_deref() {
+        Some("txt" | "log") => Some("text/plain"),
+        Some("md") => Some("text/markdown"),
+        Some("csv") => Some("text/csv"),
+        Some("html" | "htm") => Some("text/html"),
+        Some("json") => Some("application/json"),
+        Some("jsonl") => Some("application/jsonl"),
+        Some("xml") => Some("application/xml"),
+        Some("yaml" | "yml") => Some("application/yaml"),
+        Some("svg") => Some("image/svg+xml"),
+        Some("mp3") => Some("audio/mpeg"),
+        Some("mp4") => Some("video/mp4"),
+        _ => None,
+    };
+    if let Some(mime_type) = by_extension {
+        return mime_type;
+    }
+
+    // A multi-byte character cut off at the end of `head` still counts as text
+    match std::str::from_utf8(head) {
+        Ok(_) => "text/plain",
+        Err(e) if e.error_len().is_none() => "text/plain",
+        Err(_) => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniffing_prefers_content_over_extension() {
+        assert_eq!(sniff_mime_type(b"\x89PNG\r\n\x1a\n....", "chart.txt"), "image/png");
+        assert_eq!(sniff_mime_type(b"\0\0\0\x18ftypmp42", "clip"), "video/mp4");
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WAVEfmt ", "a.bin"), "audio/wav");
+        assert_eq!(sniff_mime_type(b"a,b\n1,2\n", "data.csv"), "text/csv");
+        assert_eq!(sniff_mime_type("caf\u{e9}".as_bytes()[..4].as_ref(), "notes"), "text/plain");
+        assert_eq!(sniff_mime_type(b"\x00\xfe\xff\x10", "blob"), "application/octet-stream");
+    }
+}
@@ -1,3 +1,20 @@
 //! Artifact management system
+//!
+//! Artifacts are versioned binary files (reports, images, datasets) attached
+//! to a session. Services expose them as byte streams so large artifacts are
+//! never loaded whole into memory.
 
-// Stub implementation
+pub mod artifact_service;
+pub mod file;
+pub mod in_memory;
+pub mod metadata;
+pub mod policy;
+
+pub use artifact_service::{
+    clamp_range, ArtifactKey, ArtifactRead, ArtifactReader, ArtifactVersion, ArtifactWrite, ArtifactWriter,
+    BaseArtifactService,
+};
+pub use file::FileArtifactService;
+pub use in_memory::InMemoryArtifactService;
+pub use metadata::{sniff_mime_type, ArtifactMetadata};
+pub use policy::{ArtifactCleanupJob, ArtifactPolicies, ArtifactPolicy};
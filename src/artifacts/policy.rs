@@ -0,0 +1,126 @@
+//! Per-app artifact quotas and expiry
+
+use crate::artifacts::BaseArtifactService;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{info, warn};
+
+/// Limits applied to an app's artifacts
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactPolicy {
+    /// Largest version that may be written, in bytes
+    #[serde(default)]
+    pub max_size_bytes: Option<u64>,
+
+    /// Lifetime of new versions that don't set their own
+    #[serde(default)]
+    pub ttl_seconds: Option<u64>,
+}
+
+impl ArtifactPolicy {
+    pub fn with_max_size_bytes(mut self, max_size_bytes: u64) -> Self {
+        self.max_size_bytes = Some(max_size_bytes);
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl_seconds = Some(ttl.as_secs());
+        self
+    }
+
+    /// Whether writing `size` bytes stays within the quota
+    pub fn allows_size(&self, size: u64) -> bool {
+        self.max_size_bytes.is_none_or(|max| size <= max)
+    }
+}
+
+/// Artifact policies per app, with a default for apps not listed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactPolicies {
+    #[serde(default)]
+    pub default: ArtifactPolicy,
+
+    #[serde(default)]
+    pub apps: HashMap<String, ArtifactPolicy>,
+}
+
+impl ArtifactPolicies {
+    pub fn new(default: ArtifactPolicy) -> Self {
+        Self {
+            default,
+            apps: HashMap::new(),
+        }
+    }
+
+    pub fn with_app(mut self, app_name: impl Into<String>, policy: ArtifactPolicy) -> Self {
+        self.apps.insert(app_name.into(), policy);
+        self
+    }
+
+    /// Effective policy of an app; limits it leaves unset fall back to the default
+    pub fn for_app(&self, app_name: &str) -> ArtifactPolicy {
+        match self.apps.get(app_name) {
+            Some(policy) => ArtifactPolicy {
+                max_size_bytes: policy.max_size_bytes.or(self.default.max_size_bytes),
+                ttl_seconds: policy.ttl_seconds.or(self.default.ttl_seconds),
+            },
+            None => self.default.clone(),
+        }
+    }
+}
+
+/// Error of writes exceeding an app's size quota
+pub(crate) fn quota_exceeded(max_size_bytes: u64) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::FileTooLarge,
+        format!("artifact exceeds the {} byte size quota", max_size_bytes),
+    )
+}
+
+/// Periodically deletes expired artifact versions
+pub struct ArtifactCleanupJob {
+    service: Arc<dyn BaseArtifactService>,
+    interval: Duration,
+}
+
+impl ArtifactCleanupJob {
+    pub fn new(service: Arc<dyn BaseArtifactService>, interval: Duration) -> Self {
+        Self { service, interval }
+    }
+
+    /// Run `purge_expired` on `tasks` every interval until `cancel` fires
+    pub fn spawn(self, tasks: &TaskTracker, cancel: CancellationToken) -> JoinHandle<()> {
+        let interval = self.interval.max(Duration::from_secs(1));
+        tasks.spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                match self.service.purge_expired().await {
+                    Ok(0) => {}
+                    Ok(purged) => info!("Deleted {} expired artifact versions", purged),
+                    Err(e) => warn!("Artifact cleanup failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_policy_falls_back_to_default() {
+        let policies = ArtifactPolicies::new(ArtifactPolicy::default().with_max_size_bytes(1024).with_ttl(Duration::from_secs(60)))
+            .with_app("media", ArtifactPolicy::default().with_max_size_bytes(1 << 30));
+        let media = policies.for_app("media");
+        assert_eq!((media.max_size_bytes, media.ttl_seconds), (Some(1 << 30), Some(60)));
+        assert!(!policies.for_app("chat").allows_size(2048));
+    }
+}
@@ -0,0 +1,161 @@
+//! Synchronous facade over the async runtime
+//!
+//! [`BlockingRunner`] owns a Tokio runtime and blocks on each turn, so CLI
+//! utilities and codebases without async Rust can call agents directly:
+//!
+//! ```no_run
+//! use google_adk::{
+//!     agents::{base_agent::AgentBuilder, LlmAgent},
+//!     blocking::BlockingRunner,
+//!     sessions::InMemorySessionService,
+//! };
+//! use std::sync::Arc;
+//!
+//! # fn main() -> google_adk::Result<()> {
+//! let agent = LlmAgent::builder().name("helper").model("gemini-2.0-flash").build()?;
+//! let runner = BlockingRunner::new("app", Arc::new(agent), Arc::new(InMemorySessionService::new()))?;
+//! let turn = runner.run_turn("user", "session", "What's the capital of Norway?")?;
+//! println!("{}", turn.text().unwrap_or_default());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Blocking calls must not be made from async code: constructing a
+//! [`BlockingRunner`] inside a Tokio runtime fails.
+
+use crate::{
+    agents::BaseAgent,
+    error::Result,
+    events::Event,
+    runners::Runner,
+    sessions::SessionService,
+    types::Content,
+};
+use futures::StreamExt;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// Runs agents from synchronous code on an internal runtime
+pub struct BlockingRunner {
+    runner: Runner,
+    // Declared last so it is dropped after the runner's streams and tasks
+    runtime: Runtime,
+}
+
+impl BlockingRunner {
+    pub fn new(
+        app_name: impl Into<String>,
+        agent: Arc<dyn BaseAgent>,
+        session_service: Arc<dyn SessionService>,
+    ) -> Result<Self> {
+        Self::from_runner(Runner::new(app_name, agent, session_service))
+    }
+
+    /// Wrap a configured runner, e.g. one with output processors or a run config
+    pub fn from_runner(runner: Runner) -> Result<Self> {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(crate::adk_error!(
+                InitializationError,
+                "BlockingRunner cannot be used inside an async runtime; use Runner instead"
+            ));
+        }
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .thread_name("adk-blocking")
+            .enable_all()
+            .build()?;
+        Ok(Self { runner, runtime })
+    }
+
+    pub fn runner(&self) -> &Runner {
+        &self.runner
+    }
+
+    /// Send a user message and wait for the agent's complete reply
+    pub fn run_turn(
+        &self,
+        user_id: impl Into<String>,
+        session_id: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Result<BlockingTurn> {
+        self.run_content(user_id, session_id, Content::user_text(message.into()))
+    }
+
+    /// Like [`run_turn`](Self::run_turn), with a message of any content
+    pub fn run_content(
+        &self,
+        user_id: impl Into<String>,
+        session_id: impl Into<String>,
+        message: Content,
+    ) -> Result<BlockingTurn> {
+        self.runtime.block_on(async {
+            let mut stream = self.runner.run_async(user_id.into(), session_id.into(), message).await?;
+            let mut events = Vec::new();
+            while let Some(event) = stream.next().await {
+                let event = event?;
+                if !event.is_partial {
+                    events.push(event);
+                }
+            }
+            Ok(BlockingTurn { events })
+        })
+    }
+
+    /// Stop background tasks and flush the session service
+    pub fn close(self) -> Result<()> {
+        self.runtime.block_on(self.runner.close())
+    }
+}
+
+/// The complete events of one turn
+#[derive(Debug, Clone)]
+pub struct BlockingTurn {
+    pub events: Vec<Arc<Event>>,
+}
+
+impl BlockingTurn {
+    /// The agent's last text answer
+    pub fn text(&self) -> Option<String> {
+        self.events
+            .iter()
+            .rev()
+            .filter(|event| event.author != "user")
+            .find_map(|event| event.get_text().filter(|text| !text.is_empty()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::AgentBuilder, LlmAgent},
+        sessions::InMemorySessionService,
+        testing::MockGeminiServer,
+    };
+
+    fn agent() -> Arc<dyn BaseAgent> {
+        Arc::new(LlmAgent::builder().name("helper").model("mock-gemini-blocking").build().unwrap())
+    }
+
+    #[test]
+    fn test_turns_run_without_an_async_caller() {
+        // The mock server runs on its own runtime, as a remote model would
+        let server_runtime = Runtime::new().unwrap();
+        let mock = server_runtime.block_on(MockGeminiServer::start()).unwrap();
+        server_runtime.block_on(mock.register_model("mock-gemini-blocking"));
+        mock.push_text("Oslo.").push_text("About 700,000.");
+
+        let runner = BlockingRunner::new("app", agent(), Arc::new(InMemorySessionService::new())).unwrap();
+        assert_eq!(runner.run_turn("u1", "s1", "Capital of Norway?").unwrap().text().as_deref(), Some("Oslo."));
+        let turn = runner.run_turn("u1", "s1", "Population?").unwrap();
+        assert_eq!(turn.text().as_deref(), Some("About 700,000."));
+        assert_eq!(mock.requests()[1].body["contents"].as_array().unwrap().len(), 3);
+        runner.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_refuses_to_block_inside_a_runtime() {
+        let runner = BlockingRunner::new("app", agent(), Arc::new(InMemorySessionService::new()));
+        assert!(runner.is_err());
+    }
+}
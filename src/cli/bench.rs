@@ -0,0 +1,388 @@
+//! Load generation against a running ADK server (`adk bench serve`)
+//!
+//! Fires synthetic multi-turn conversations at an agent's run or stream
+//! endpoint at a fixed request rate and reports latency percentiles and error
+//! rates, for capacity planning before deploying.
+
+use crate::error::Result;
+use clap::{Args, Subcommand};
+use futures::StreamExt;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::Mutex, task::JoinSet};
+use tracing::{debug, info};
+
+/// Benchmarking tools
+#[derive(Args)]
+pub struct BenchCommand {
+    #[command(subcommand)]
+    pub command: BenchSubcommand,
+}
+
+#[derive(Subcommand)]
+pub enum BenchSubcommand {
+    /// Load test a running ADK server with synthetic conversations
+    Serve(BenchServeCommand),
+}
+
+impl BenchCommand {
+    pub async fn execute(self) -> Result<()> {
+        match self.command {
+            BenchSubcommand::Serve(cmd) => cmd.execute().await,
+        }
+    }
+}
+
+/// Load test a running ADK server
+#[derive(Args, Debug, Clone)]
+pub struct BenchServeCommand {
+    /// Agent to send conversations to
+    pub agent: String,
+
+    /// Base URL of the server
+    #[arg(long, default_value = "http://localhost:8000")]
+    pub url: String,
+
+    /// Target requests (turns) per second across all conversations
+    #[arg(long, default_value_t = 10.0)]
+    pub rps: f64,
+
+    /// How long to generate load, in seconds
+    #[arg(long, default_value_t = 30)]
+    pub duration: u64,
+
+    /// Turns per synthetic conversation
+    #[arg(long, default_value_t = 3)]
+    pub turns: usize,
+
+    /// Use the SSE streaming endpoint and also report time to first event
+    #[arg(long)]
+    pub stream: bool,
+
+    /// Message sent on every turn; `{turn}` is replaced with the turn number
+    #[arg(long, default_value = "Hello! This is load test turn {turn}.")]
+    pub message: String,
+
+    /// Upper bound on concurrent requests; ticks beyond it are counted as skipped
+    #[arg(long, default_value_t = 256)]
+    pub max_in_flight: usize,
+
+    /// Per-request timeout in seconds
+    #[arg(long, default_value_t = 120)]
+    pub timeout: u64,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl BenchServeCommand {
+    pub async fn execute(self) -> Result<()> {
+        if self.rps <= 0.0 || self.turns == 0 {
+            return Err(crate::adk_error!(ValidationError, "--rps and --turns must be positive"));
+        }
+        info!(
+            "Load testing {} at {}: {} req/s for {}s, {} turns per conversation",
+            self.agent, self.url, self.rps, self.duration, self.turns
+        );
+        let report = run_load_test(&self).await?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            print!("{}", report);
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one request
+#[derive(Debug, Clone)]
+struct Sample {
+    latency: Duration,
+    first_event: Option<Duration>,
+    status: Option<u16>,
+    error: bool,
+}
+
+/// Latency distribution in milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    pub mean_ms: f64,
+}
+
+impl LatencySummary {
+    fn from_durations(mut durations: Vec<Duration>) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        // Nearest-rank percentile
+        let percentile = |p: f64| {
+            let rank = ((p / 100.0) * durations.len() as f64).ceil() as usize;
+            ms(durations[rank.clamp(1, durations.len()) - 1])
+        };
+        Some(Self {
+            p50_ms: percentile(50.0),
+            p90_ms: percentile(90.0),
+            p99_ms: percentile(99.0),
+            max_ms: ms(*durations.last().expect("non-empty")),
+            mean_ms: durations.iter().copied().map(ms).sum::<f64>() / durations.len() as f64,
+        })
+    }
+}
+
+/// Results of a load test
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LoadReport {
+    pub requests: usize,
+    pub errors: usize,
+    pub error_rate: f64,
+    /// Ticks dropped because `max_in_flight` requests were already running
+    pub skipped: usize,
+    pub conversations: usize,
+    pub elapsed_secs: f64,
+    pub achieved_rps: f64,
+    pub latency: Option<LatencySummary>,
+    /// Time to the first streamed event (streaming mode only)
+    pub first_event: Option<LatencySummary>,
+    /// Responses by HTTP status; transport failures are counted under `error`
+    pub status_codes: BTreeMap<String, usize>,
+}
+
+impl LoadReport {
+    fn new(samples: Vec<Sample>, skipped: usize, conversations: usize, elapsed: Duration) -> Self {
+        let requests = samples.len();
+        let errors = samples.iter().filter(|sample| sample.error).count();
+        let mut status_codes = BTreeMap::new();
+        for sample in &samples {
+            let key = sample.status.map_or_else(|| "error".to_string(), |code| code.to_string());
+            *status_codes.entry(key).or_insert(0) += 1;
+        }
+        Self {
+            requests,
+            errors,
+            error_rate: if requests == 0 { 0.0 } else { errors as f64 / requests as f64 },
+            skipped,
+            conversations,
+            elapsed_secs: elapsed.as_secs_f64(),
+            achieved_rps: requests as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            latency: LatencySummary::from_durations(samples.iter().map(|s| s.latency).collect()),
+            first_event: LatencySummary::from_durations(samples.iter().filter_map(|s| s.first_event).collect()),
+            status_codes,
+        }
+    }
+}
+
+impl std::fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Requests: {} in {:.1}s ({:.2} req/s), {} conversations",
+            self.requests, self.elapsed_secs, self.achieved_rps, self.conversations
+        )?;
+        writeln!(f, "Errors:   {} ({:.2}%)", self.errors, self.error_rate * 100.0)?;
+        if self.skipped > 0 {
+            writeln!(f, "Skipped:  {} (max in-flight reached)", self.skipped)?;
+        }
+        for (label, summary) in [("Latency", &self.latency), ("First event", &self.first_event)] {
+            if let Some(s) = summary {
+                writeln!(
+                    f,
+                    "{}: p50 {:.1}ms  p90 {:.1}ms  p99 {:.1}ms  max {:.1}ms  mean {:.1}ms",
+                    label, s.p50_ms, s.p90_ms, s.p99_ms, s.max_ms, s.mean_ms
+                )?;
+            }
+        }
+        let codes: Vec<String> = self.status_codes.iter().map(|(code, n)| format!("{}: {}", code, n)).collect();
+        writeln!(f, "Status:   {}", codes.join(", "))
+    }
+}
+
+/// A conversation waiting for its next turn
+struct Conversation {
+    session_id: String,
+    user_id: String,
+    turn: usize,
+}
+
+/// Run the load test described by `config` and collect a report
+pub async fn run_load_test(config: &BenchServeCommand) -> Result<LoadReport> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.timeout))
+        .build()
+        .map_err(|e| crate::adk_error!(ConfigError, "Failed to build HTTP client: {}", e))?;
+    let endpoint = format!(
+        "{}/api/agents/{}/{}",
+        config.url.trim_end_matches('/'),
+        config.agent,
+        if config.stream { "stream" } else { "run" }
+    );
+
+    // Conversations whose previous turn finished and that have turns left
+    let ready: Arc<Mutex<VecDeque<Conversation>>> = Arc::new(Mutex::new(VecDeque::new()));
+    let mut requests = JoinSet::new();
+    let mut samples = Vec::new();
+    let mut skipped = 0;
+    let mut conversations = 0;
+
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(config.duration);
+    let mut ticks = tokio::time::interval(Duration::from_secs_f64(1.0 / config.rps));
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Burst);
+
+    while Instant::now() < deadline {
+        tokio::select! {
+            _ = ticks.tick() => {
+                if requests.len() >= config.max_in_flight {
+                    skipped += 1;
+                    continue;
+                }
+                let conversation = match ready.lock().await.pop_front() {
+                    Some(conversation) => conversation,
+                    None => {
+                        conversations += 1;
+                        Conversation {
+                            session_id: uuid::Uuid::new_v4().to_string(),
+                            user_id: format!("bench-user-{}", conversations),
+                            turn: 0,
+                        }
+                    }
+                };
+                requests.spawn(send_turn(
+                    client.clone(),
+                    endpoint.clone(),
+                    config.clone(),
+                    conversation,
+                    ready.clone(),
+                ));
+            }
+            Some(result) = requests.join_next(), if !requests.is_empty() => {
+                if let Ok(sample) = result {
+                    samples.push(sample);
+                }
+            }
+        }
+    }
+
+    // Let requests already in flight finish
+    while let Some(result) = requests.join_next().await {
+        if let Ok(sample) = result {
+            samples.push(sample);
+        }
+    }
+    Ok(LoadReport::new(samples, skipped, conversations, started.elapsed()))
+}
+
+async fn send_turn(
+    client: reqwest::Client,
+    endpoint: String,
+    config: BenchServeCommand,
+    mut conversation: Conversation,
+    ready: Arc<Mutex<VecDeque<Conversation>>>,
+) -> Sample {
+    conversation.turn += 1;
+    let body = serde_json::json!({
+        "message": config.message.replace("{turn}", &conversation.turn.to_string()),
+        "session_id": conversation.session_id,
+        "user_id": conversation.user_id,
+        "stream": config.stream,
+    });
+
+    let started = Instant::now();
+    let mut sample = Sample {
+        latency: Duration::ZERO,
+        first_event: None,
+        status: None,
+        error: true,
+    };
+    match client.post(&endpoint).json(&body).send().await {
+        Ok(response) => {
+            sample.status = Some(response.status().as_u16());
+            let success = response.status().is_success();
+            let mut body = response.bytes_stream();
+            let mut body_ok = true;
+            while let Some(chunk) = body.next().await {
+                if chunk.is_err() {
+                    body_ok = false;
+                    break;
+                }
+                sample.first_event.get_or_insert_with(|| started.elapsed());
+            }
+            if !config.stream {
+                sample.first_event = None;
+            }
+            sample.error = !(success && body_ok);
+        }
+        Err(e) => debug!("Load test request failed: {}", e),
+    }
+    sample.latency = started.elapsed();
+
+    if conversation.turn < config.turns {
+        ready.lock().await.push_back(conversation);
+    }
+    sample
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Path, routing::post, Json, Router};
+
+    #[test]
+    fn test_latency_percentiles() {
+        let durations = (1..=100).map(Duration::from_millis).collect();
+        let summary = LatencySummary::from_durations(durations).unwrap();
+        assert_eq!(summary.p50_ms, 50.0);
+        assert_eq!(summary.p99_ms, 99.0);
+        assert_eq!(summary.max_ms, 100.0);
+        assert!(LatencySummary::from_durations(Vec::new()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_load_test_against_local_server() {
+        let app = Router::new().route(
+            "/api/agents/:agent/run",
+            post(|Path(agent): Path<String>, Json(body): Json<serde_json::Value>| async move {
+                if agent == "broken" {
+                    return Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+                }
+                Ok(Json(serde_json::json!({"response": body["message"]})))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut config = BenchServeCommand {
+            agent: "echo".to_string(),
+            url: format!("http://{}", addr),
+            rps: 50.0,
+            duration: 1,
+            turns: 2,
+            stream: false,
+            message: "turn {turn}".to_string(),
+            max_in_flight: 8,
+            timeout: 5,
+            json: false,
+        };
+        let report = run_load_test(&config).await.unwrap();
+        assert!(report.requests >= 10, "{:?}", report);
+        assert_eq!(report.errors, 0);
+        assert!(report.conversations < report.requests);
+        assert!(report.latency.is_some());
+
+        config.agent = "broken".to_string();
+        let report = run_load_test(&config).await.unwrap();
+        assert_eq!(report.error_rate, 1.0);
+        assert_eq!(report.status_codes["500"], report.requests);
+    }
+}
@@ -1,8 +1,11 @@
 //! CLI command implementations
 
-use crate::error::Result;
+use crate::{
+    agents::{AgentRegistry, BaseAgent},
+    error::Result,
+};
 use clap::Args;
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
 /// Create a new agent project
 #[derive(Args)]
@@ -22,12 +25,101 @@ impl CreateCommand {
 #[derive(Args)]
 pub struct RunCommand {
     /// Path to the agent source code folder
-    pub agent: PathBuf,
+    #[arg(required_unless_present = "pipeline")]
+    pub agent: Option<PathBuf>,
+
+    /// Run a pipeline defined in adk.toml instead of an agent folder
+    #[arg(long, conflicts_with = "agent")]
+    pub pipeline: Option<String>,
+
+    /// Pipeline parameter as `name=value`; repeatable
+    #[arg(long = "param", value_parser = parse_pipeline_param, requires = "pipeline")]
+    pub params: Vec<(String, String)>,
+
+    /// Message starting the pipeline
+    #[arg(short, long, default_value = "Begin.", requires = "pipeline")]
+    pub message: String,
+
+    /// Check the final response against assertions in this YAML file
+    /// (`assertions.yaml` next to the agent when no file is given)
+    #[arg(long, num_args = 0..=1, default_missing_value = "")]
+    pub assert: Option<PathBuf>,
+}
+
+fn parse_pipeline_param(param: &str) -> std::result::Result<(String, String), String> {
+    param
+        .split_once('=')
+        .map(|(name, value)| (name.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected name=value, got '{}'", param))
 }
 
 impl RunCommand {
     pub async fn execute(self) -> Result<()> {
-        println!("Running agent from: {}", self.agent.display());
+        let assertions = self.load_assertions()?;
+        match &self.pipeline {
+            Some(pipeline) => self.run_pipeline(pipeline, assertions.as_ref()).await,
+            None => {
+                if let Some(agent) = &self.agent {
+                    println!("Running agent from: {}", agent.display());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn load_assertions(&self) -> Result<Option<crate::testing::AssertionSet>> {
+        use crate::testing::AssertionSet;
+
+        let path = match &self.assert {
+            None => return Ok(None),
+            Some(path) if path.as_os_str().is_empty() => AssertionSet::sidecar_path(self.agent.as_deref()),
+            Some(path) => path.clone(),
+        };
+        let assertions = AssertionSet::from_file(&path)?;
+        println!("Checking responses against {} assertions from {}\n", assertions.assertions.len(), path.display());
+        Ok(Some(assertions))
+    }
+
+    async fn run_pipeline(&self, name: &str, assertions: Option<&crate::testing::AssertionSet>) -> Result<()> {
+        use crate::{
+            agents::global_pipelines, runners::Runner, sessions::InMemorySessionService, types::Content,
+        };
+        use futures::StreamExt;
+        use std::collections::HashMap;
+
+        let args: HashMap<String, String> = self.params.iter().cloned().collect();
+        let agent = global_pipelines().instantiate(name, &args)?;
+        let runner = Runner::new(name, Arc::new(agent), Arc::new(InMemorySessionService::new()));
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let mut events = runner
+            .run_async("cli_user".to_string(), session_id, Content::user_text(self.message.clone()))
+            .await?;
+        let mut final_response = None;
+        while let Some(event) = events.next().await {
+            let event = event?;
+            if event.is_partial {
+                continue;
+            }
+            if let Some(text) = event.get_text().filter(|text| !text.is_empty()) {
+                println!("[{}]\n{}\n", event.author, text);
+                final_response = Some(text);
+            }
+        }
+        runner.close().await?;
+
+        let Some(assertions) = assertions else {
+            return Ok(());
+        };
+        let results = assertions.check(&self.message, final_response.as_deref().unwrap_or_default()).await;
+        for result in &results {
+            println!("  {}", result);
+        }
+        let failed = results.iter().filter(|result| !result.passed).count();
+        println!("{} of {} assertions passed", results.len() - failed, results.len());
+        if failed > 0 {
+            return Err(crate::adk_error!(ValidationError, "{} assertions failed", failed));
+        }
         Ok(())
     }
 }
@@ -35,15 +127,93 @@ impl RunCommand {
 /// Evaluate an agent
 #[derive(Args)]
 pub struct EvalCommand {
-    /// Path to the agent module
+    /// Path to the agent module; its file stem names the agent among the
+    /// pipelines in adk.toml
     pub agent_module_file_path: PathBuf,
+
+    /// Evaluate a specific registered agent version instead of the default
+    #[arg(long)]
+    pub agent_version: Option<String>,
+
+    /// Converse with the agent through a simulated user described by this persona
+    #[arg(long)]
+    pub simulate: Option<String>,
+
+    /// Goal-script steps for the simulated user, pursued in order
+    #[arg(long = "goal", requires = "simulate")]
+    pub goals: Vec<String>,
+
+    /// Maximum simulated user turns
+    #[arg(long, default_value_t = crate::testing::DEFAULT_MAX_TURNS)]
+    pub max_turns: usize,
+
+    /// Model playing the simulated user
+    #[arg(long, default_value = "gemini-2.0-flash")]
+    pub simulator_model: String,
 }
 
 impl EvalCommand {
     pub async fn execute(self) -> Result<()> {
-        println!("Evaluating agent: {}", self.agent_module_file_path.display());
+        use crate::agents::{global_pipelines, DEFAULT_AGENT_VERSION};
+
+        let agents = global_pipelines().agents()?;
+        let agent = self.resolve_agent(&agents)?;
+        println!(
+            "Evaluating agent: {} (version {})",
+            agent.name(),
+            agent.version().unwrap_or(DEFAULT_AGENT_VERSION)
+        );
+
+        if self.simulate.is_none() {
+            return Ok(());
+        }
+        let model = crate::models::create_model(&self.simulator_model).await?;
+        let Some(simulation) = self.simulate(agent, Arc::from(model)).await? else {
+            return Ok(());
+        };
+        println!("{}\n", simulation.transcript());
+        println!("Simulation ended after {} turns: {:?}", simulation.turns.len(), simulation.outcome);
+        if simulation.outcome != crate::testing::SimulationOutcome::GoalReached {
+            return Err(crate::adk_error!(
+                ValidationError,
+                "The simulated user did not reach its goal within {} turns",
+                self.max_turns
+            ));
+        }
         Ok(())
     }
+
+    /// Converse with `agent` through the `--simulate` persona, played by `model`
+    pub async fn simulate(
+        &self,
+        agent: Arc<dyn BaseAgent>,
+        model: Arc<dyn crate::models::BaseLlm>,
+    ) -> Result<Option<crate::testing::Simulation>> {
+        use crate::{runners::Runner, sessions::InMemorySessionService, testing::UserSimulator};
+
+        let Some(persona) = &self.simulate else {
+            return Ok(None);
+        };
+        let simulator = self
+            .goals
+            .iter()
+            .fold(UserSimulator::new(model, persona.clone()), |simulator, goal| simulator.goal(goal.clone()))
+            .max_turns(self.max_turns);
+        let runner = Runner::new(agent.name().to_string(), agent, Arc::new(InMemorySessionService::new()));
+        let simulation = simulator.run(&runner).await;
+        runner.close().await?;
+        simulation.map(Some)
+    }
+
+    /// The agent version to evaluate, the default one unless `--agent-version` is given
+    pub fn resolve_agent(&self, agents: &AgentRegistry) -> Result<Arc<dyn BaseAgent>> {
+        let name = self
+            .agent_module_file_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        agents.resolve(&name, self.agent_version.as_deref())
+    }
 }
 
 /// Start a web server with UI for agents
@@ -65,6 +235,10 @@ pub struct WebCommand {
     #[arg(long)]
     pub no_docs: bool,
 
+    /// Disable gzip/brotli response compression
+    #[arg(long)]
+    pub no_compression: bool,
+
     /// Static files directory
     #[arg(long)]
     pub static_dir: Option<String>,
@@ -73,10 +247,38 @@ pub struct WebCommand {
     #[arg(long, default_value = "30")]
     pub timeout: u64,
 
+    /// Seconds between WS ping / SSE heartbeat frames (0 disables)
+    #[arg(long, default_value = "15")]
+    pub heartbeat_interval: u64,
+
+    /// Seconds of inactivity before WS/SSE connections are closed (0 disables)
+    #[arg(long, default_value = "300")]
+    pub idle_timeout: u64,
+
     /// CORS allowed origins (comma-separated)
     #[arg(long, default_value = "*")]
     pub cors_origins: String,
 
+    /// Allow credentialed CORS requests (requires explicit origins)
+    #[arg(long)]
+    pub cors_allow_credentials: bool,
+
+    /// Listen on this Unix domain socket instead of host:port
+    #[arg(long, conflicts_with = "systemd_socket")]
+    pub unix_socket: Option<String>,
+
+    /// Accept connections on the socket passed by systemd socket activation
+    #[arg(long)]
+    pub systemd_socket: bool,
+
+    /// PEM certificate chain for serving HTTPS/WSS (requires the `tls` feature)
+    #[arg(long, requires = "tls_key")]
+    pub tls_cert: Option<String>,
+
+    /// PEM private key matching --tls-cert
+    #[arg(long, requires = "tls_cert")]
+    pub tls_key: Option<String>,
+
     /// API key for authentication
     #[arg(long, env = "ADK_API_KEY")]
     pub api_key: Option<String>,
@@ -88,9 +290,8 @@ impl WebCommand {
             agents::{LlmAgent, base_agent::AgentBuilder},
             sessions::{SessionService, InMemorySessionService},
             tools::google_search,
-            web::{ServerConfig, WebServerBuilder},
+            web::{ServerConfig, TlsConfig, WebServerBuilder},
         };
-        use std::sync::Arc;
         use tokio::signal;
         use tracing::{info, warn};
 
@@ -107,7 +308,23 @@ impl WebCommand {
             .with_host(self.host)
             .with_port(self.port)
             .with_cors_origins(cors_origins)
-            .with_timeout(self.timeout);
+            .with_timeout(self.timeout)
+            .with_heartbeat_interval(self.heartbeat_interval)
+            .with_idle_timeout(self.idle_timeout);
+
+        if self.cors_allow_credentials {
+            config.cors.default = config.cors.default.with_credentials();
+        }
+
+        if let Some(path) = self.unix_socket {
+            config = config.with_unix_socket(path);
+        } else if self.systemd_socket {
+            config = config.with_systemd_socket();
+        }
+
+        if let (Some(cert), Some(key)) = (self.tls_cert, self.tls_key) {
+            config = config.with_tls(TlsConfig::new(cert, key));
+        }
 
         if self.no_websockets {
             config = config.disable_websockets();
@@ -117,6 +334,10 @@ impl WebCommand {
             config = config.disable_docs();
         }
 
+        if self.no_compression {
+            config = config.disable_compression();
+        }
+
         if let Some(static_dir) = self.static_dir {
             config = config.with_static_dir(static_dir);
         }
@@ -168,6 +389,7 @@ impl WebCommand {
         println!("  WebSocket: {}", config.enable_websockets);
         println!("  API Docs: {}", config.enable_docs);
         println!("  Timeout: {}s", config.timeout_seconds);
+        println!("  Heartbeat: {}s, idle timeout: {}s", config.heartbeat_interval_seconds, config.idle_timeout_seconds);
         if let Some(static_dir) = &config.static_dir {
             println!("  Static files: {}", static_dir);
         }
@@ -247,6 +469,208 @@ impl WebCommand {
     }
 }
 
+/// Export stored sessions as a fine-tuning dataset
+#[derive(Args)]
+pub struct ExportCommand {
+    /// Output format (gemini-tuning or openai-chat)
+    #[arg(long, default_value = "gemini-tuning")]
+    pub format: crate::sessions::ExportFormat,
+
+    /// Sessions file (JSON array or JSON lines of sessions)
+    #[arg(long)]
+    pub input: PathBuf,
+
+    /// Output file (defaults to stdout)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Only export sessions of this application
+    #[arg(long)]
+    pub app_name: Option<String>,
+
+    /// Only export sessions involving this agent
+    #[arg(long)]
+    pub agent: Option<String>,
+
+    /// Only export sessions created at or after this RFC 3339 timestamp
+    #[arg(long)]
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Only export sessions created before this RFC 3339 timestamp
+    #[arg(long)]
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Only export sessions whose state has this flag set to true (repeatable)
+    #[arg(long = "quality-flag")]
+    pub quality_flags: Vec<String>,
+}
+
+impl ExportCommand {
+    pub async fn execute(self) -> Result<()> {
+        use crate::sessions::{export, ExportFilter};
+
+        let sessions = export::read_sessions(&self.input)?;
+        let filter = ExportFilter {
+            app_name: self.app_name,
+            agent: self.agent,
+            since: self.since,
+            until: self.until,
+            quality_flags: self.quality_flags,
+        };
+        let examples = export::export_sessions(&sessions, &filter, self.format);
+
+        let written = match &self.output {
+            Some(path) => export::write_jsonl(&examples, std::fs::File::create(path)?)?,
+            None => export::write_jsonl(&examples, std::io::stdout().lock())?,
+        };
+
+        eprintln!(
+            "Exported {} of {} sessions as {}",
+            written,
+            sessions.len(),
+            self.format
+        );
+        Ok(())
+    }
+}
+
+/// Import conversations from other frameworks as sessions
+#[derive(Args)]
+pub struct ImportCommand {
+    /// Source format (adk-session, adk-evalset or dialogflow-cx)
+    #[arg(long)]
+    pub format: crate::sessions::ImportFormat,
+
+    /// Exported conversations (JSON document or JSON lines)
+    #[arg(long)]
+    pub input: PathBuf,
+
+    /// Sessions file to write as JSON lines (defaults to stdout)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Application the sessions belong to
+    #[arg(long)]
+    pub app_name: Option<String>,
+
+    /// User of the sessions when the source does not record one
+    #[arg(long, default_value = "imported_user")]
+    pub user_id: String,
+
+    /// Author of agent turns when the source does not record one
+    #[arg(long, default_value = "imported_agent")]
+    pub agent_name: String,
+}
+
+impl ImportCommand {
+    pub async fn execute(self) -> Result<()> {
+        use crate::sessions::{export, import_sessions, ImportOptions};
+
+        let data = std::fs::read_to_string(&self.input)?;
+        let options = ImportOptions {
+            app_name: self.app_name,
+            user_id: self.user_id,
+            agent_name: self.agent_name,
+        };
+        let sessions = import_sessions(&data, self.format, &options)?;
+        let sessions = sessions
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let written = match &self.output {
+            Some(path) => export::write_jsonl(&sessions, std::fs::File::create(path)?)?,
+            None => export::write_jsonl(&sessions, std::io::stdout().lock())?,
+        };
+
+        eprintln!("Imported {} sessions from {}", written, self.format);
+        Ok(())
+    }
+}
+
+/// Check the configuration and show the policies tools run under
+#[derive(Args)]
+pub struct DoctorCommand {
+    /// Agent to resolve tool policies for (defaults to every agent in `[tool_defaults.agents]`)
+    #[arg(long = "agent")]
+    pub agents: Vec<String>,
+
+    /// Tool to resolve policies for (defaults to every tool in `[tool_defaults.tools]`)
+    #[arg(long = "tool")]
+    pub tools: Vec<String>,
+}
+
+impl DoctorCommand {
+    pub async fn execute(self) -> Result<()> {
+        use crate::{
+            agents::{FeatureFlags, PipelineRegistry},
+            models::{profiles::{CONFIG_PATH_ENV_VAR, DEFAULT_CONFIG_FILE}, ModelProfiles},
+            tools::{ToolDefaults, ToolPolicy},
+        };
+
+        let path = std::env::var(CONFIG_PATH_ENV_VAR).unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+        if std::path::Path::new(&path).exists() {
+            println!("Config file: {}", path);
+        } else {
+            println!("Config file: {} (not found, using built-in defaults)", path);
+        }
+
+        let mut problems = 0;
+        let mut check = |section: &str, loaded: std::result::Result<String, String>| match loaded {
+            Ok(summary) => println!("  {}: {}", section, summary),
+            Err(e) => {
+                problems += 1;
+                println!("  {}: ERROR {}", section, e);
+            }
+        };
+        let defined = |names: Vec<String>| format!("{} defined", names.len());
+        check("profiles", ModelProfiles::load_default().map(|p| defined(p.names())).map_err(|e| e.to_string()));
+        check("pipelines", PipelineRegistry::load_default().map(|p| defined(p.names())).map_err(|e| e.to_string()));
+        check(
+            "flags",
+            FeatureFlags::load_default()
+                .map(|flags| {
+                    let flags = flags.snapshot();
+                    format!(
+                        "{} tools and {} agents disabled",
+                        flags.disabled_tools.len(),
+                        flags.disabled_agents.len()
+                    )
+                })
+                .map_err(|e| e.to_string()),
+        );
+        let defaults = ToolDefaults::load_default();
+        check("tool_defaults", defaults.as_ref().map(|_| "ok".to_string()).map_err(|e| e.to_string()));
+
+        let config = defaults.map(|defaults| defaults.snapshot()).unwrap_or_default();
+        let agents = match self.agents.is_empty() {
+            true => config.agents.keys().cloned().collect(),
+            false => self.agents,
+        };
+        let tools = match self.tools.is_empty() {
+            true => config.tools.keys().cloned().collect(),
+            false => self.tools,
+        };
+        let none = ToolPolicy::default();
+        println!("\nEffective tool policies (before overrides set in code):");
+        println!("  all tools: {}", config.app.resolve());
+        for agent in &agents {
+            println!("  {} / other tools: {}", agent, config.resolve(agent, &none, "", &none));
+        }
+        for tool in &tools {
+            println!("  {}: {}", tool, config.resolve("", &none, tool, &none));
+            for agent in &agents {
+                println!("  {} / {}: {}", agent, tool, config.resolve(agent, &none, tool, &none));
+            }
+        }
+
+        if problems > 0 {
+            return Err(crate::adk_error!(ConfigError, "{} configuration sections failed to load", problems));
+        }
+        Ok(())
+    }
+}
+
 /// Start a FastAPI server for agents
 #[derive(Args)]
 pub struct ApiServerCommand {
@@ -274,3 +698,70 @@ impl DeployCommand {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{base_agent::AgentBuilder, LlmAgent};
+
+    fn eval_command(path: &str) -> EvalCommand {
+        EvalCommand {
+            agent_module_file_path: PathBuf::from(path),
+            agent_version: None,
+            simulate: None,
+            goals: Vec::new(),
+            max_turns: crate::testing::DEFAULT_MAX_TURNS,
+            simulator_model: "gemini-2.0-flash".to_string(),
+        }
+    }
+
+    fn agent(version: &str) -> Arc<dyn BaseAgent> {
+        Arc::new(LlmAgent::builder().name("triage").model("gemini-2.0-flash").version(version).build().unwrap())
+    }
+
+    #[test]
+    fn test_eval_resolves_the_requested_agent_version() {
+        let agents = AgentRegistry::new();
+        agents.register("triage", agent("v1"));
+        agents.register("triage", agent("v2"));
+        let eval = |path: &str, version: Option<&str>| EvalCommand {
+            agent_version: version.map(str::to_string),
+            ..eval_command(path)
+        };
+
+        assert_eq!(eval("agents/triage.py", None).resolve_agent(&agents).unwrap().version(), Some("v1"));
+        assert_eq!(eval("triage", Some("v2")).resolve_agent(&agents).unwrap().version(), Some("v2"));
+        assert!(eval("triage", Some("v3")).resolve_agent(&agents).is_err());
+        assert!(eval("billing", None).resolve_agent(&agents).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_eval_simulates_a_user_against_the_agent() {
+        use crate::testing::{MockGeminiServer, SimulationOutcome, GOAL_COMPLETE_TOKEN};
+
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-eval").await;
+        // Simulated user, agent, then the simulated user again
+        mock.push_text("My order 42 arrived broken, I want a refund")
+            .push_text("Refund issued for order 42.")
+            .push_text(GOAL_COMPLETE_TOKEN);
+        let agent: Arc<dyn BaseAgent> =
+            Arc::new(LlmAgent::builder().name("support").model("mock-gemini-eval").build().unwrap());
+        let model = crate::models::create_model("mock-gemini-eval").await.unwrap();
+        let eval = EvalCommand {
+            simulate: Some("A customer with a broken order".to_string()),
+            goals: vec!["Get a refund for order 42".to_string()],
+            max_turns: 3,
+            ..eval_command("support")
+        };
+
+        let simulation = eval.simulate(agent.clone(), Arc::from(model)).await.unwrap().unwrap();
+        assert_eq!(simulation.outcome, SimulationOutcome::GoalReached);
+        assert_eq!(simulation.turns.len(), 1);
+        assert_eq!(simulation.turns[0].agent_reply, "Refund issued for order 42.");
+        assert!(mock.requests()[0].body.to_string().contains("Get a refund for order 42"));
+
+        let model = crate::models::create_model("mock-gemini-eval").await.unwrap();
+        assert!(eval_command("support").simulate(agent, Arc::from(model)).await.unwrap().is_none());
+    }
+}
@@ -1,5 +1,7 @@
 //! CLI system for the ADK
 
+pub mod bench;
 pub mod commands;
 
+pub use bench::{BenchCommand, BenchServeCommand, LoadReport};
 pub use commands::*;
@@ -1,7 +1,5 @@
 //! Error types for the ADK library
 
-use std::fmt;
-
 /// Result type alias for ADK operations
 pub type Result<T> = std::result::Result<T, AdkError>;
 
@@ -95,6 +93,7 @@ impl From<reqwest::Error> for AdkError {
     }
 }
 
+#[cfg(feature = "sql")]
 impl From<sqlx::Error> for AdkError {
     fn from(err: sqlx::Error) -> Self {
         AdkError::DatabaseError(err.to_string())
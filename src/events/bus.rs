@@ -0,0 +1,188 @@
+//! In-process event bus
+//!
+//! Runners publish every event they stream; subscribers such as telemetry
+//! sinks, WebSocket broadcast, memory ingestion or usage tracking consume them
+//! from their own bounded channel. Publishing never waits: a subscriber that
+//! falls behind loses events (counted in its stats) rather than slowing down
+//! agent execution.
+
+use crate::events::Event;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    task::{Context, Poll},
+};
+use tokio::{
+    sync::mpsc::{self, error::TrySendError},
+    task::JoinHandle,
+};
+
+/// Default channel capacity of a subscriber
+pub const DEFAULT_SUBSCRIBER_CAPACITY: usize = 1024;
+
+/// An event together with the session it belongs to
+#[derive(Debug, Clone)]
+pub struct PublishedEvent {
+    pub app_name: String,
+    pub user_id: String,
+    pub session_id: String,
+    pub event: Arc<Event>,
+}
+
+/// Delivery statistics for one subscriber
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriberStats {
+    pub name: String,
+    pub delivered: u64,
+    pub dropped: u64,
+    pub queued: usize,
+}
+
+struct Subscriber {
+    name: String,
+    sender: mpsc::Sender<Arc<PublishedEvent>>,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Fan-out of published events to independent, bounded subscribers
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<RwLock<Vec<Arc<Subscriber>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribe to events published from now on, buffering at most `capacity` of them
+    pub fn subscribe(&self, name: impl Into<String>, capacity: usize) -> EventSubscription {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        self.subscribers.write().unwrap().push(Arc::new(Subscriber {
+            name: name.into(),
+            sender,
+            delivered: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }));
+        EventSubscription { receiver }
+    }
+
+    /// Run `handler` on a background task for every published event
+    pub fn spawn_subscriber<F, Fut>(&self, name: impl Into<String>, capacity: usize, mut handler: F) -> JoinHandle<()>
+    where
+        F: FnMut(Arc<PublishedEvent>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let mut subscription = self.subscribe(name, capacity);
+        tokio::spawn(async move {
+            while let Some(event) = subscription.recv().await {
+                handler(event).await;
+            }
+        })
+    }
+
+    /// Deliver an event to every subscriber without waiting on any of them
+    pub fn publish(&self, event: PublishedEvent) {
+        let event = Arc::new(event);
+        let mut closed = false;
+        for subscriber in self.subscribers.read().unwrap().iter() {
+            match subscriber.sender.try_send(event.clone()) {
+                Ok(()) => {
+                    subscriber.delivered.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(TrySendError::Full(_)) => {
+                    subscriber.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(TrySendError::Closed(_)) => closed = true,
+            }
+        }
+        if closed {
+            self.subscribers
+                .write()
+                .unwrap()
+                .retain(|subscriber| !subscriber.sender.is_closed());
+        }
+    }
+
+    /// Whether anyone is listening; lets publishers skip building events
+    pub fn has_subscribers(&self) -> bool {
+        !self.subscribers.read().unwrap().is_empty()
+    }
+
+    pub fn subscriber_stats(&self) -> Vec<SubscriberStats> {
+        self.subscribers
+            .read()
+            .unwrap()
+            .iter()
+            .map(|subscriber| SubscriberStats {
+                name: subscriber.name.clone(),
+                delivered: subscriber.delivered.load(Ordering::Relaxed),
+                dropped: subscriber.dropped.load(Ordering::Relaxed),
+                queued: subscriber.sender.max_capacity() - subscriber.sender.capacity(),
+            })
+            .collect()
+    }
+}
+
+/// Receiving end of an [`EventBus`] subscription; dropping it unsubscribes
+pub struct EventSubscription {
+    receiver: mpsc::Receiver<Arc<PublishedEvent>>,
+}
+
+impl EventSubscription {
+    pub async fn recv(&mut self) -> Option<Arc<PublishedEvent>> {
+        self.receiver.recv().await
+    }
+}
+
+impl Stream for EventSubscription {
+    type Item = Arc<PublishedEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn published(text: &str) -> PublishedEvent {
+        PublishedEvent {
+            app_name: "app".to_string(),
+            user_id: "user".to_string(),
+            session_id: "session".to_string(),
+            event: Arc::new(Event::text_response("agent", text)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_drops_without_blocking_others() {
+        let bus = EventBus::new();
+        let mut fast = bus.subscribe("fast", 8);
+        let slow = bus.subscribe("slow", 1);
+
+        for text in ["one", "two", "three"] {
+            bus.publish(published(text));
+        }
+
+        for expected in ["one", "two", "three"] {
+            let event = fast.recv().await.unwrap();
+            assert_eq!(event.event.get_text().as_deref(), Some(expected));
+        }
+        let stats = bus.subscriber_stats();
+        assert_eq!((stats[0].delivered, stats[0].dropped), (3, 0));
+        assert_eq!((stats[1].delivered, stats[1].dropped, stats[1].queued), (1, 2, 1));
+
+        drop(slow);
+        bus.publish(published("four"));
+        assert_eq!(bus.subscriber_stats().len(), 1);
+    }
+}
@@ -0,0 +1,163 @@
+//! Source citations attached to model answers
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A source backing part of an answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CitationSource {
+    pub uri: String,
+
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/// A span of the answer text supported by one or more sources
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CitationSpan {
+    /// Byte offset where the supported text starts
+    pub start_index: usize,
+
+    /// Byte offset where the supported text ends (exclusive)
+    pub end_index: usize,
+
+    /// The supported text, if provided
+    #[serde(default)]
+    pub text: Option<String>,
+
+    /// Indices into `Citations::sources`
+    pub source_indices: Vec<usize>,
+
+    /// Confidence per source index, in the same order
+    #[serde(default)]
+    pub confidence: Vec<f32>,
+}
+
+/// Sources and supported spans for an answer
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Citations {
+    pub sources: Vec<CitationSource>,
+
+    #[serde(default)]
+    pub spans: Vec<CitationSpan>,
+}
+
+impl Citations {
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Build source-only citations from a retrieval tool result.
+    ///
+    /// Accepts the `{"results": [{"url" | "uri": ..., "title": ...}]}` shape
+    /// returned by the search tools.
+    pub fn from_retrieval_results(result: &Value) -> Option<Self> {
+        let sources: Vec<CitationSource> = result
+            .get("results")?
+            .as_array()?
+            .iter()
+            .filter_map(|item| {
+                let uri = item.get("url").or_else(|| item.get("uri"))?.as_str()?;
+                Some(CitationSource {
+                    uri: uri.to_string(),
+                    title: item.get("title").and_then(Value::as_str).map(str::to_string),
+                })
+            })
+            .collect();
+
+        (!sources.is_empty()).then(|| Self {
+            sources,
+            spans: Vec::new(),
+        })
+    }
+
+    /// Insert numbered markers such as `[1][3]` after each supported span.
+    ///
+    /// Markers are numbered from 1 by source index. Spans whose offsets do not
+    /// fall on character boundaries of `text` are skipped.
+    pub fn annotate(&self, text: &str) -> String {
+        let mut insertions: Vec<(usize, String)> = self
+            .spans
+            .iter()
+            .filter(|span| span.end_index <= text.len() && text.is_char_boundary(span.end_index))
+            .map(|span| {
+                let markers: String = span
+                    .source_indices
+                    .iter()
+                    .filter(|&&index| index < self.sources.len())
+                    .map(|index| format!("[{}]", index + 1))
+                    .collect();
+                (span.end_index, markers)
+            })
+            .filter(|(_, markers)| !markers.is_empty())
+            .collect();
+        insertions.sort_by_key(|(index, _)| *index);
+
+        let mut annotated = String::with_capacity(text.len() + insertions.len() * 4);
+        let mut cursor = 0;
+        for (index, markers) in insertions {
+            annotated.push_str(&text[cursor..index]);
+            annotated.push_str(&markers);
+            cursor = index;
+        }
+        annotated.push_str(&text[cursor..]);
+        annotated
+    }
+
+    /// Render a numbered reference list matching the markers from `annotate`
+    pub fn reference_list(&self) -> String {
+        self.sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| match &source.title {
+                Some(title) => format!("[{}] {} - {}", i + 1, title, source.uri),
+                None => format!("[{}] {}", i + 1, source.uri),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_inserts_markers_after_spans() {
+        let citations = Citations {
+            sources: vec![
+                CitationSource {
+                    uri: "https://a.example".to_string(),
+                    title: Some("A".to_string()),
+                },
+                CitationSource {
+                    uri: "https://b.example".to_string(),
+                    title: None,
+                },
+            ],
+            spans: vec![
+                CitationSpan {
+                    start_index: 13,
+                    end_index: 24,
+                    text: None,
+                    source_indices: vec![1],
+                    confidence: vec![0.7],
+                },
+                CitationSpan {
+                    start_index: 0,
+                    end_index: 12,
+                    text: None,
+                    source_indices: vec![0, 1, 5],
+                    confidence: vec![0.9, 0.8, 0.1],
+                },
+            ],
+        };
+
+        let text = "Rust is fast. It is safe.";
+        assert_eq!(citations.annotate(text), "Rust is fast[1][2]. It is safe[2].");
+        assert_eq!(
+            citations.reference_list(),
+            "[1] A - https://a.example\n[2] https://b.example"
+        );
+    }
+}
@@ -1,10 +1,11 @@
 //! Event types for agent communication
 
-use crate::types::{Content, InvocationId, StateDelta, Timestamp};
-use chrono::Utc;
+use crate::{
+    events::{Citations, Transcription, TranscriptionSource},
+    types::{Content, FunctionCall, InvocationId, StateDelta, Timestamp},
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use uuid::Uuid;
 
 /// Event generated during agent execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +33,19 @@ pub struct Event {
     
     /// Metadata associated with the event
     pub metadata: HashMap<String, serde_json::Value>,
+
+    /// Sources grounding the event's text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub citations: Option<Citations>,
+
+    /// Transcription of live audio; such events carry no content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcription: Option<Transcription>,
+
+    /// The event holds secrets: it is stored and streamed with its content
+    /// replaced by a placeholder (see [`Event::redacted`])
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub redact: bool,
 }
 
 /// Actions that can be performed as a result of an event
@@ -51,6 +65,27 @@ pub struct EventAction {
     
     /// Custom actions
     pub custom: HashMap<String, serde_json::Value>,
+
+    /// Hand the conversation over to a human operator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<Handoff>,
+
+    /// The user interrupted the agent's response; clients should stop playing its audio
+    #[serde(default)]
+    pub interrupted: bool,
+}
+
+/// Request to hand a conversation over to a human operator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handoff {
+    /// Why the agent is handing over, shown to operators
+    pub reason: String,
+}
+
+impl Handoff {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self { reason: reason.into() }
+    }
 }
 
 impl Event {
@@ -60,31 +95,88 @@ impl Event {
         text: impl Into<String>,
     ) -> Self {
         Self {
-            id: Uuid::new_v4().to_string(),
+            id: crate::utils::new_id(),
             author: author.into(),
             content: Some(Content::model_text(text)),
             actions: EventAction::default(),
-            timestamp: Utc::now(),
-            invocation_id: Uuid::new_v4(),
+            timestamp: crate::types::now(),
+            invocation_id: crate::utils::new_uuid(),
             is_partial: false,
             metadata: HashMap::new(),
+            citations: None,
+            transcription: None,
+            redact: false,
+        }
+    }
+
+    /// Create an event carrying a full model content (text, code, and execution results)
+    pub fn content_response(author: impl Into<String>, content: Content) -> Self {
+        Self {
+            content: Some(content),
+            ..Self::text_response(author, "")
+        }
+    }
+
+    /// Create an event recording a function call requested by the model
+    pub fn function_call(author: impl Into<String>, call: FunctionCall) -> Self {
+        Self {
+            content: Some(Content::function_call(call)),
+            ..Self::text_response(author, "")
+        }
+    }
+
+    /// Create an event recording a function result
+    pub fn function_response(
+        author: impl Into<String>,
+        name: impl Into<String>,
+        response: serde_json::Value,
+    ) -> Self {
+        Self {
+            content: Some(Content::function_response(name, response)),
+            ..Self::text_response(author, "")
         }
     }
 
+    /// Create a transcription event of live audio; interim transcriptions are partial.
+    /// User speech is authored by "user", the model's by `agent`
+    pub fn transcription(agent: impl Into<String>, transcription: Transcription) -> Self {
+        let author = match transcription.source {
+            TranscriptionSource::User => "user".to_string(),
+            TranscriptionSource::Model => agent.into(),
+        };
+        Self {
+            content: None,
+            is_partial: !transcription.is_final,
+            transcription: Some(transcription),
+            ..Self::text_response(author, "")
+        }
+    }
+
+    /// Create an event announcing that the user barged in on `agent`'s live response
+    pub fn interruption(agent: impl Into<String>) -> Self {
+        let mut event = Self::text_response(agent, "");
+        event.content = None;
+        event.actions.interrupted = true;
+        event
+    }
+
     /// Create a user input event
     pub fn user_input(
         text: impl Into<String>,
         invocation_id: InvocationId,
     ) -> Self {
         Self {
-            id: Uuid::new_v4().to_string(),
+            id: crate::utils::new_id(),
             author: "user".to_string(),
             content: Some(Content::user_text(text)),
             actions: EventAction::default(),
-            timestamp: Utc::now(),
+            timestamp: crate::types::now(),
             invocation_id,
             is_partial: false,
             metadata: HashMap::new(),
+            citations: None,
+            transcription: None,
+            redact: false,
         }
     }
 
@@ -92,6 +184,27 @@ impl Event {
     pub fn get_text(&self) -> Option<String> {
         self.content.as_ref().map(|c| c.get_text())
     }
+
+    /// Get the text with numbered citation markers inserted, if the event has citations
+    pub fn get_cited_text(&self) -> Option<String> {
+        let text = self.get_text()?;
+        Some(match &self.citations {
+            Some(citations) => citations.annotate(&text),
+            None => text,
+        })
+    }
+
+    /// Mark the event as holding secrets, to be redacted before it is stored or streamed
+    pub fn with_redaction(mut self) -> Self {
+        self.redact = true;
+        self
+    }
+
+    /// Attach citations to this event
+    pub fn with_citations(mut self, citations: Option<Citations>) -> Self {
+        self.citations = citations.filter(|c| !c.is_empty());
+        self
+    }
 }
 
 /// Builder for creating events
@@ -103,14 +216,17 @@ impl EventBuilder {
     pub fn new(author: impl Into<String>, invocation_id: InvocationId) -> Self {
         Self {
             event: Event {
-                id: Uuid::new_v4().to_string(),
+                id: crate::utils::new_id(),
                 author: author.into(),
                 content: None,
                 actions: EventAction::default(),
-                timestamp: Utc::now(),
+                timestamp: crate::types::now(),
                 invocation_id,
                 is_partial: false,
                 metadata: HashMap::new(),
+                citations: None,
+                transcription: None,
+                redact: false,
             },
         }
     }
@@ -119,3 +235,31 @@ impl EventBuilder {
         self.event
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::strategies::{arb_content, arb_json};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_event_serde_round_trip_is_lossless(
+            author in "[a-z_]{1,12}",
+            content in arb_content(),
+            is_partial in any::<bool>(),
+            state_delta in prop::collection::hash_map("[a-z:_]{1,10}", arb_json(), 0..4),
+            metadata in prop::collection::hash_map("[a-z_]{1,10}", arb_json(), 0..4),
+        ) {
+            let mut event = Event::content_response(author, content);
+            event.is_partial = is_partial;
+            event.actions.state_delta = state_delta;
+            event.metadata = metadata;
+
+            let json = serde_json::to_string(&event).unwrap();
+            let decoded: Event = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::to_value(&event).unwrap());
+            prop_assert_eq!(decoded.timestamp, event.timestamp);
+        }
+    }
+}
@@ -0,0 +1,471 @@
+//! Output processors that format agent answers for a delivery channel
+//!
+//! Models answer in Markdown. Chat widgets render it, but SMS and plain-text
+//! email need it stripped, and web pages need safe HTML. [`OutputFormat`]
+//! bundles the processors for each kind of channel so connectors don't have
+//! to post-process answers themselves.
+
+use crate::events::output::OutputProcessor;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::sync::Arc;
+
+static CODE_SPAN: Lazy<Regex> = Lazy::new(|| Regex::new(r"`([^`\n]+)`").expect("valid code span pattern"));
+static IMAGE: Lazy<Regex> = Lazy::new(|| Regex::new(r"!\[([^\]\n]*)\]\(([^)\s]*)\)").expect("valid image pattern"));
+static LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]\n]+)\]\(([^)\s]+)\)").expect("valid link pattern"));
+static BOLD: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*\*(\S(?:.*?\S)?)\*\*|__(\S(?:.*?\S)?)__").expect("valid bold pattern"));
+static ITALIC: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\*(\S(?:[^*]*?\S)?)\*|(?:^|\b)_(\S(?:[^_]*?\S)?)_(?:\b|$)").expect("valid italic pattern"));
+static STRIKETHROUGH: Lazy<Regex> = Lazy::new(|| Regex::new(r"~~(\S(?:.*?\S)?)~~").expect("valid strikethrough pattern"));
+static HEADING: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(#{1,6})\s+(.*?)\s*#*\s*$").expect("valid heading pattern"));
+static BULLET: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*[-*+]\s+(.*)$").expect("valid bullet pattern"));
+static NUMBERED: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*\d+[.)]\s+(.*)$").expect("valid numbered item pattern"));
+static RULE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*(?:-{3,}|\*{3,}|_{3,})\s*$").expect("valid rule pattern"));
+
+/// Apply `inline` to the text outside code spans and `code` to the content of each span
+fn map_inline(line: &str, inline: impl Fn(&str) -> String, code: impl Fn(&str) -> String) -> String {
+    let mut mapped = String::with_capacity(line.len());
+    let mut last = 0;
+    for span in CODE_SPAN.captures_iter(line) {
+        let whole = span.get(0).expect("match has a whole group");
+        mapped.push_str(&inline(&line[last..whole.start()]));
+        mapped.push_str(&code(&span[1]));
+        last = whole.end();
+    }
+    mapped.push_str(&inline(&line[last..]));
+    mapped
+}
+
+/// The first captured group that participated in the match
+fn group<'t>(captures: &Captures<'t>) -> &'t str {
+    captures.iter().skip(1).flatten().next().map_or("", |m| m.as_str())
+}
+
+fn strip_emphasis(text: &str) -> String {
+    let text = BOLD.replace_all(text, |c: &Captures| group(c).to_string());
+    let text = STRIKETHROUGH.replace_all(&text, "$1");
+    ITALIC.replace_all(&text, |c: &Captures| group(c).to_string()).into_owned()
+}
+
+/// Converts Markdown to plain text, keeping link targets and code
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripMarkdown;
+
+impl StripMarkdown {
+    fn inline(text: &str) -> String {
+        let text = IMAGE.replace_all(text, "$1");
+        let text = LINK.replace_all(&text, |c: &Captures| {
+            if c[1] == c[2] {
+                c[2].to_string()
+            } else {
+                format!("{} ({})", &c[1], &c[2])
+            }
+        });
+        strip_emphasis(&text)
+    }
+}
+
+impl OutputProcessor for StripMarkdown {
+    fn name(&self) -> &str {
+        "strip_markdown"
+    }
+
+    fn process(&self, text: &str) -> String {
+        let mut lines = Vec::new();
+        let mut in_code_block = false;
+        for line in text.lines() {
+            if line.trim_start().starts_with("```") {
+                in_code_block = !in_code_block;
+                continue;
+            }
+            if in_code_block {
+                lines.push(line.to_string());
+            } else if RULE.is_match(line) {
+                lines.push(String::new());
+            } else {
+                let line = match HEADING.captures(line) {
+                    Some(heading) => heading[2].to_string(),
+                    None => line.trim_start_matches('>').trim_start_matches(' ').to_string(),
+                };
+                let line = match BULLET.captures(&line) {
+                    Some(item) => format!("• {}", &item[1]),
+                    None => line,
+                };
+                lines.push(map_inline(&line, Self::inline, str::to_string));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Paragraph,
+    Quote,
+    List,
+    OrderedList,
+}
+
+/// Converts Markdown to HTML safely
+///
+/// All text is escaped before the supported subset (headings, emphasis,
+/// code, lists, quotes and links) is rendered, so raw HTML in the answer is
+/// shown rather than interpreted. Links are only rendered for `http`,
+/// `https` and `mailto` targets, and images are replaced by their alt text.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownToHtml;
+
+impl MarkdownToHtml {
+    fn inline(text: &str) -> String {
+        let escaped = escape_html(text);
+        let text = IMAGE.replace_all(&escaped, "$1");
+        let text = LINK.replace_all(&text, |c: &Captures| {
+            let target = &c[2];
+            if ["http://", "https://", "mailto:"].iter().any(|scheme| target.starts_with(scheme)) {
+                format!("<a href=\"{}\">{}</a>", target, &c[1])
+            } else {
+                c[1].to_string()
+            }
+        });
+        let text = BOLD.replace_all(&text, |c: &Captures| format!("<strong>{}</strong>", group(c)));
+        let text = STRIKETHROUGH.replace_all(&text, "<del>$1</del>");
+        ITALIC
+            .replace_all(&text, |c: &Captures| format!("<em>{}</em>", group(c)))
+            .into_owned()
+    }
+
+    fn render_inline(line: &str) -> String {
+        map_inline(line, Self::inline, |code| format!("<code>{}</code>", escape_html(code)))
+    }
+}
+
+impl OutputProcessor for MarkdownToHtml {
+    fn name(&self) -> &str {
+        "markdown_to_html"
+    }
+
+    fn process(&self, text: &str) -> String {
+        let mut html = Vec::new();
+        let mut open: Option<(Block, Vec<String>)> = None;
+        let mut code: Option<Vec<String>> = None;
+
+        fn close(html: &mut Vec<String>, block: Option<(Block, Vec<String>)>) {
+            let Some((block, lines)) = block else {
+                return;
+            };
+            html.push(match block {
+                Block::Paragraph => format!("<p>{}</p>", lines.join("<br>\n")),
+                Block::Quote => format!("<blockquote>{}</blockquote>", lines.join("<br>\n")),
+                Block::List => format!("<ul>\n<li>{}</li>\n</ul>", lines.join("</li>\n<li>")),
+                Block::OrderedList => format!("<ol>\n<li>{}</li>\n</ol>", lines.join("</li>\n<li>")),
+            });
+        }
+
+        for line in text.lines() {
+            if line.trim_start().starts_with("```") {
+                match code.take() {
+                    Some(lines) => html.push(format!("<pre><code>{}</code></pre>", escape_html(&lines.join("\n")))),
+                    None => {
+                        close(&mut html, open.take());
+                        code = Some(Vec::new());
+                    }
+                }
+                continue;
+            }
+            if let Some(lines) = code.as_mut() {
+                lines.push(line.to_string());
+                continue;
+            }
+
+            if line.trim().is_empty() || RULE.is_match(line) {
+                close(&mut html, open.take());
+                if !line.trim().is_empty() {
+                    html.push("<hr>".to_string());
+                }
+                continue;
+            }
+            if let Some(heading) = HEADING.captures(line) {
+                close(&mut html, open.take());
+                let level = heading[1].len();
+                html.push(format!("<h{0}>{1}</h{0}>", level, Self::render_inline(&heading[2])));
+                continue;
+            }
+
+            let (block, content) = if let Some(quote) = line.trim_start().strip_prefix('>') {
+                (Block::Quote, quote.trim_start().to_string())
+            } else if let Some(item) = BULLET.captures(line) {
+                (Block::List, item[1].to_string())
+            } else if let Some(item) = NUMBERED.captures(line) {
+                (Block::OrderedList, item[1].to_string())
+            } else {
+                (Block::Paragraph, line.trim().to_string())
+            };
+            if open.as_ref().map(|(current, _)| *current) != Some(block) {
+                close(&mut html, open.take());
+            }
+            open.get_or_insert_with(|| (block, Vec::new()))
+                .1
+                .push(Self::render_inline(&content));
+        }
+
+        // An unterminated code block runs to the end of the answer
+        if let Some(lines) = code {
+            html.push(format!("<pre><code>{}</code></pre>", escape_html(&lines.join("\n"))));
+        }
+        close(&mut html, open);
+        html.join("\n")
+    }
+}
+
+/// Truncates long answers, preferring a word boundary, and marks the cut
+#[derive(Debug, Clone)]
+pub struct MaxLength {
+    max_chars: usize,
+    ellipsis: String,
+}
+
+impl MaxLength {
+    /// At most `max_chars` characters per text part, ellipsis included
+    pub fn new(max_chars: usize) -> Self {
+        Self {
+            max_chars,
+            ellipsis: "…".to_string(),
+        }
+    }
+
+    pub fn with_ellipsis(mut self, ellipsis: impl Into<String>) -> Self {
+        self.ellipsis = ellipsis.into();
+        self
+    }
+}
+
+impl OutputProcessor for MaxLength {
+    fn name(&self) -> &str {
+        "max_length"
+    }
+
+    fn process(&self, text: &str) -> String {
+        if text.chars().count() <= self.max_chars {
+            return text.to_string();
+        }
+        let budget = self.max_chars.saturating_sub(self.ellipsis.chars().count());
+        let end = text.char_indices().nth(budget).map_or(text.len(), |(index, _)| index);
+        let mut kept = &text[..end];
+        // Cut at the last word boundary unless that loses more than half the text
+        let cut_mid_word = text[end..].starts_with(|c: char| !c.is_whitespace());
+        if let Some(space) = kept.rfind(char::is_whitespace).filter(|space| cut_mid_word && *space >= end / 2) {
+            kept = &kept[..space];
+        }
+        let kept = kept.trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':' | '-'));
+        format!("{}{}", kept, self.ellipsis)
+    }
+}
+
+static SELF_REFERENCES: Lazy<Vec<Regex>> = Lazy::new(|| {
+    const MODEL: &str = r"(?:an? )?(?:AI|artificial intelligence|(?:large )?language model|LLM|virtual assistant|chatbot)(?: (?:language )?model| assistant)?";
+    [
+        // "As an AI language model, I can't..." -> "I can't..."
+        format!(r"(?i)\bas {},\s*", MODEL),
+        // "I'm just an AI, but..." -> "But..."
+        format!(r"(?i)\bI(?:'m| am) (?:just |only )?{},\s*(?:(?:but|and|so)\s+)?", MODEL),
+        // "I am an AI language model." -> ""
+        format!(r"(?i)\bI(?:'m| am) (?:just |only )?{}\.\s*", MODEL),
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).expect("valid self-reference pattern"))
+    .collect()
+});
+
+/// Removes the model talking about itself ("As an AI language model, ...")
+#[derive(Debug, Clone, Default)]
+pub struct RemoveSelfReferences {
+    extra: Vec<Regex>,
+}
+
+impl RemoveSelfReferences {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also remove a phrase (any casing), e.g. the name of the underlying model
+    pub fn phrase(mut self, phrase: &str) -> Self {
+        let pattern = format!(r"(?i)\b{}\b[,.]?\s*", regex::escape(phrase));
+        self.extra.push(Regex::new(&pattern).expect("escaped phrase is a valid pattern"));
+        self
+    }
+}
+
+impl OutputProcessor for RemoveSelfReferences {
+    fn name(&self) -> &str {
+        "remove_self_references"
+    }
+
+    fn process(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for pattern in SELF_REFERENCES.iter().chain(&self.extra) {
+            let mut processed = String::with_capacity(text.len());
+            let mut last = 0;
+            for found in pattern.find_iter(&text) {
+                processed.push_str(&text[last..found.start()]);
+                last = found.end();
+                // Capitalize what is now the start of the sentence
+                let sentence_start = processed.trim_end().is_empty()
+                    || processed.trim_end().ends_with(['.', '!', '?', '\n'])
+                    || processed.ends_with('\n');
+                if sentence_start {
+                    if let Some(first) = text[last..].chars().next() {
+                        processed.extend(first.to_uppercase());
+                        last += first.len_utf8();
+                    }
+                }
+            }
+            processed.push_str(&text[last..]);
+            text = processed;
+        }
+        text
+    }
+}
+
+/// How a channel renders agent answers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Chat UIs that render Markdown themselves
+    Markdown,
+
+    /// SMS, voice and plain-text email
+    PlainText,
+
+    /// Web pages and HTML email
+    Html,
+}
+
+impl OutputFormat {
+    /// Processors for the channel: self-references are removed, answers
+    /// truncated to `max_chars` (before HTML conversion, so tags stay intact)
+    /// and then converted to the format
+    pub fn processors(self, max_chars: Option<usize>) -> Vec<Arc<dyn OutputProcessor>> {
+        let mut processors: Vec<Arc<dyn OutputProcessor>> = vec![Arc::new(RemoveSelfReferences::new())];
+        if self == Self::PlainText {
+            processors.push(Arc::new(StripMarkdown));
+        }
+        if let Some(max_chars) = max_chars {
+            processors.push(Arc::new(MaxLength::new(max_chars)));
+        }
+        if self == Self::Html {
+            processors.push(Arc::new(MarkdownToHtml));
+        }
+        processors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::AgentBuilder, LlmAgent},
+        runners::Runner,
+        sessions::InMemorySessionService,
+        testing::MockGeminiServer,
+        types::Content,
+    };
+    use futures::StreamExt;
+
+    const ANSWER: &str = "## Your *trip*\n\nBook **early** via [the portal](https://example.com/a?b=1&c=2) or `run <book>`:\n\n- flights\n- hotels\n\n```\nif a < b && c {}\n```";
+
+    #[test]
+    fn test_strip_markdown_keeps_text_links_and_code() {
+        assert_eq!(
+            StripMarkdown.process(ANSWER),
+            "Your trip\n\nBook early via the portal (https://example.com/a?b=1&c=2) or run <book>:\n\n• flights\n• hotels\n\nif a < b && c {}"
+        );
+        assert_eq!(StripMarkdown.process("snake_case_name and `**raw**`"), "snake_case_name and **raw**");
+    }
+
+    #[test]
+    fn test_markdown_to_html_escapes_before_rendering() {
+        assert_eq!(
+            MarkdownToHtml.process(ANSWER),
+            "<h2>Your <em>trip</em></h2>\n\
+             <p>Book <strong>early</strong> via <a href=\"https://example.com/a?b=1&amp;c=2\">the portal</a> or <code>run &lt;book&gt;</code>:</p>\n\
+             <ul>\n<li>flights</li>\n<li>hotels</li>\n</ul>\n\
+             <pre><code>if a &lt; b &amp;&amp; c {}</code></pre>"
+        );
+        assert_eq!(
+            MarkdownToHtml.process("<script>alert(1)</script> [x](javascript:steal) ![i](https://t.co/p.png)"),
+            "<p>&lt;script&gt;alert(1)&lt;/script&gt; x i</p>"
+        );
+        assert_eq!(
+            MarkdownToHtml.process("[x](https://a.com/\"onmouseover=\"alert(1))"),
+            "<p><a href=\"https://a.com/&quot;onmouseover=&quot;alert(1\">x</a>)</p>"
+        );
+    }
+
+    #[test]
+    fn test_max_length_cuts_at_word_boundary() {
+        let max = MaxLength::new(20);
+        assert_eq!(max.process("short enough"), "short enough");
+        assert_eq!(max.process("The quick brown fox jumps over the lazy dog"), "The quick brown fox…");
+        assert_eq!(max.process("Supercalifragilisticexpialidocious"), "Supercalifragilisti…");
+        assert_eq!(MaxLength::new(16).with_ellipsis("...").process("Hello, wonderful world"), "Hello...");
+    }
+
+    #[test]
+    fn test_self_references_are_removed() {
+        let processor = RemoveSelfReferences::new().phrase("Gemini");
+        assert_eq!(
+            processor.process("As an AI language model, I can't book flights. I'm just an AI, but here are tips."),
+            "I can't book flights. Here are tips."
+        );
+        assert_eq!(processor.process("I am a large language model. Paris is lovely."), "Paris is lovely.");
+        assert_eq!(processor.process("Gemini, the assistant, says hi"), "The assistant, says hi");
+        assert_eq!(processor.process("An AI designed this bridge"), "An AI designed this bridge");
+    }
+
+    #[test]
+    fn test_format_presets_order_processors() {
+        let names = |format: OutputFormat| {
+            format.processors(Some(160)).iter().map(|p| p.name().to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(names(OutputFormat::PlainText), ["remove_self_references", "strip_markdown", "max_length"]);
+        assert_eq!(names(OutputFormat::Html), ["remove_self_references", "max_length", "markdown_to_html"]);
+        assert_eq!(OutputFormat::Markdown.processors(None).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_agent_formats_final_answers_for_its_channel() {
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-output-format").await;
+        mock.push_text("As an AI, I suggest **Oslo** <3");
+
+        let agent = LlmAgent::builder()
+            .name("web_agent")
+            .model("mock-gemini-output-format")
+            .output_processors(OutputFormat::Html.processors(None))
+            .build()
+            .unwrap();
+        let runner = Runner::new("app", Arc::new(agent), Arc::new(InMemorySessionService::new()));
+        let events: Vec<_> = runner
+            .run_async("u1".into(), "s1".into(), Content::user_text("Where to?"))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        let answer = events.last().unwrap().as_ref().unwrap();
+        assert_eq!(answer.get_text().as_deref(), Some("<p>I suggest <strong>Oslo</strong> &lt;3</p>"));
+    }
+}
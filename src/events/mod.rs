@@ -1,5 +1,22 @@
 //! Event system for agent communication
 
+pub mod bus;
+pub mod citations;
 pub mod event;
+pub mod formatting;
+pub mod output;
+pub mod redaction;
+pub mod transcription;
+pub mod webhooks;
 
-pub use event::{Event, EventAction, EventBuilder};
+pub use bus::{EventBus, EventSubscription, PublishedEvent, SubscriberStats, DEFAULT_SUBSCRIBER_CAPACITY};
+pub use citations::{CitationSource, CitationSpan, Citations};
+pub use event::{Event, EventAction, EventBuilder, Handoff};
+pub use formatting::{MarkdownToHtml, MaxLength, OutputFormat, RemoveSelfReferences, StripMarkdown};
+pub use output::{apply_output_processors, Glossary, NumberFormat, OutputProcessor};
+pub use redaction::{InMemoryRedactionVault, RedactionVault, REDACTED_PLACEHOLDER};
+pub use transcription::{TranscriptAssembler, Transcription, TranscriptionSource};
+pub use webhooks::{
+    sign_webhook_body, LifecycleEvent, LifecycleEventType, WebhookConfig, WebhookDispatcher, WEBHOOK_DEAD_LETTER_KIND,
+    WEBHOOK_DELIVERY_HEADER, WEBHOOK_EVENT_HEADER, WEBHOOK_SIGNATURE_HEADER,
+};
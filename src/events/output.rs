@@ -0,0 +1,225 @@
+//! Post-processing of agent output
+//!
+//! Output processors rewrite the text of final (non-partial) agent events
+//! before the runner persists and streams them. Partial streaming deltas are
+//! passed through unchanged.
+
+use crate::{events::Event, types::ContentPart};
+use regex::{NoExpand, Regex};
+use std::sync::Arc;
+
+/// Rewrites the text of final agent events
+pub trait OutputProcessor: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    fn process(&self, text: &str) -> String;
+}
+
+/// Run `processors` over the text parts of a final agent event.
+///
+/// Citation spans hold byte offsets into the original text, so they are
+/// dropped (keeping the sources) when the text changes.
+pub fn apply_output_processors(event: &mut Event, processors: &[Arc<dyn OutputProcessor>]) {
+    if processors.is_empty() || event.is_partial || event.author == "user" {
+        return;
+    }
+    let Some(content) = event.content.as_mut() else {
+        return;
+    };
+
+    let mut changed = false;
+    for part in &mut content.parts {
+        if let ContentPart::Text { text } = part {
+            let processed = processors
+                .iter()
+                .fold(text.clone(), |text, processor| processor.process(&text));
+            if processed != *text {
+                *text = processed;
+                changed = true;
+            }
+        }
+    }
+    if changed {
+        if let Some(citations) = event.citations.as_mut() {
+            citations.spans.clear();
+        }
+    }
+}
+
+/// Separators used when formatting numbers for a locale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub thousands_separator: char,
+    pub decimal_separator: char,
+}
+
+impl NumberFormat {
+    pub fn new(thousands_separator: char, decimal_separator: char) -> Self {
+        Self {
+            thousands_separator,
+            decimal_separator,
+        }
+    }
+
+    /// `1.234,5` (German, Spanish, Italian...)
+    pub fn european() -> Self {
+        Self::new('.', ',')
+    }
+
+    /// `1 234,5` with a narrow no-break space (French)
+    pub fn french() -> Self {
+        Self::new('\u{202f}', ',')
+    }
+}
+
+struct GlossaryRule {
+    pattern: Regex,
+    replacement: String,
+}
+
+/// Enforces terminology: replaces banned terms, fixes the spelling of
+/// product names and formats numbers for a locale
+#[derive(Default)]
+pub struct Glossary {
+    rules: Vec<GlossaryRule>,
+    number_format: Option<NumberFormat>,
+}
+
+impl Glossary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace a banned term (whole words, any casing)
+    pub fn replace(mut self, banned: &str, replacement: impl Into<String>) -> Self {
+        self.rules.push(GlossaryRule {
+            pattern: whole_word(banned),
+            replacement: replacement.into(),
+        });
+        self
+    }
+
+    /// Enforce the canonical spelling of a term, e.g. `GitHub` for `github` or `Github`
+    pub fn term(self, canonical: &str) -> Self {
+        self.replace(canonical, canonical)
+    }
+
+    /// Enforce the canonical spelling of a term, also replacing known misspellings
+    pub fn term_with_variants<'a>(mut self, canonical: &str, variants: impl IntoIterator<Item = &'a str>) -> Self {
+        for variant in variants {
+            self = self.replace(variant, canonical);
+        }
+        self.term(canonical)
+    }
+
+    /// Format numbers such as `1,234.5` with the locale's separators
+    pub fn with_number_format(mut self, format: NumberFormat) -> Self {
+        self.number_format = Some(format);
+        self
+    }
+
+    fn format_numbers(&self, text: &str, format: NumberFormat) -> String {
+        static NUMBER: once_cell::sync::Lazy<Regex> = once_cell::sync::Lazy::new(|| {
+            Regex::new(r"\d{1,3}(?:,\d{3})+(?:\.\d+)?|\d+\.\d+").expect("valid number pattern")
+        });
+
+        let mut formatted = String::with_capacity(text.len());
+        let mut last = 0;
+        for number in NUMBER.find_iter(text) {
+            // Leave versions, IP addresses and identifiers such as `v1.2.3` alone
+            let before = text[..number.start()].chars().next_back();
+            let after = text[number.end()..].chars().next();
+            let embedded = before.is_some_and(|c| c.is_alphanumeric() || matches!(c, '.' | ',' | '_'))
+                || after.is_some_and(|c| c.is_alphanumeric() || c == '_')
+                || (matches!(after, Some('.' | ',')) && text[number.end() + 1..].starts_with(|c: char| c.is_ascii_digit()));
+            if embedded {
+                continue;
+            }
+            formatted.push_str(&text[last..number.start()]);
+            formatted.extend(number.as_str().chars().map(|c| match c {
+                ',' => format.thousands_separator,
+                '.' => format.decimal_separator,
+                c => c,
+            }));
+            last = number.end();
+        }
+        formatted.push_str(&text[last..]);
+        formatted
+    }
+}
+
+fn whole_word(term: &str) -> Regex {
+    Regex::new(&format!(r"(?i)\b{}\b", regex::escape(term))).expect("escaped term is a valid pattern")
+}
+
+impl OutputProcessor for Glossary {
+    fn name(&self) -> &str {
+        "glossary"
+    }
+
+    fn process(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for rule in &self.rules {
+            if let std::borrow::Cow::Owned(replaced) = rule.pattern.replace_all(&text, NoExpand(&rule.replacement)) {
+                text = replaced;
+            }
+        }
+        match self.number_format {
+            Some(format) => self.format_numbers(&text, format),
+            None => text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Citations;
+
+    #[test]
+    fn test_glossary_enforces_terms_and_number_format() {
+        let glossary = Glossary::new()
+            .replace("cheap", "affordable")
+            .term("GitHub")
+            .term_with_variants("Wi-Fi", ["wifi", "wi fi"])
+            .with_number_format(NumberFormat::european());
+
+        assert_eq!(
+            glossary.process("A CHEAP github plan with WIFI costs 1,299.50 euros, or 3.5 a day"),
+            "A affordable GitHub plan with Wi-Fi costs 1.299,50 euros, or 3,5 a day"
+        );
+        // Identifiers, versions and addresses are left alone
+        assert_eq!(glossary.process("cheapest v1.2 on 10.0.0.1 build 2.0.1"), "cheapest v1.2 on 10.0.0.1 build 2.0.1");
+    }
+
+    #[test]
+    fn test_only_final_agent_text_is_processed() {
+        let processors: Vec<Arc<dyn OutputProcessor>> = vec![Arc::new(Glossary::new().term("GitHub"))];
+
+        let mut event = Event::text_response("agent", "see github").with_citations(Some(Citations {
+            sources: vec![crate::events::CitationSource { uri: "https://github.com".into(), title: None }],
+            spans: vec![crate::events::CitationSpan {
+                start_index: 4,
+                end_index: 10,
+                text: None,
+                source_indices: vec![0],
+                confidence: vec![],
+            }],
+        }));
+        apply_output_processors(&mut event, &processors);
+        assert_eq!(event.get_text().unwrap(), "see GitHub");
+        let citations = event.citations.unwrap();
+        assert_eq!(citations.sources.len(), 1);
+        assert!(citations.spans.is_empty());
+
+        let mut partial = Event::text_response("agent", "github");
+        partial.is_partial = true;
+        apply_output_processors(&mut partial, &processors);
+        assert_eq!(partial.get_text().unwrap(), "github");
+
+        let mut user = Event::user_input("github", uuid::Uuid::new_v4());
+        apply_output_processors(&mut user, &processors);
+        assert_eq!(user.get_text().unwrap(), "github");
+    }
+}
@@ -0,0 +1,176 @@
+//! Redaction of events holding secrets
+//!
+//! Tools and guardrails handling secrets mark their events with
+//! [`Event::redact`]. The runner then stores and streams
+//! [`Event::redacted`] instead, so neither the session store nor clients
+//! ever see the secret. Originals are kept only if a [`RedactionVault`] is
+//! configured, where an admin endpoint with elevated auth can read them.
+
+use crate::{
+    error::Result,
+    events::Event,
+    types::{Content, ContentPart, SessionId},
+};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::{collections::HashMap, sync::RwLock};
+
+/// Text replacing redacted content
+pub const REDACTED_PLACEHOLDER: &str = "[redacted]";
+
+impl Event {
+    /// The event with its content replaced by placeholders. Part kinds and
+    /// function names are kept so the conversation stays well-formed;
+    /// media, arguments, results and transcribed text are not.
+    pub fn redacted(&self) -> Self {
+        let mut event = self.clone();
+        if let Some(content) = event.content.as_mut() {
+            redact_content(content);
+        }
+        if let Some(transcription) = event.transcription.as_mut() {
+            transcription.text = REDACTED_PLACEHOLDER.to_string();
+        }
+        event.citations = None;
+        event
+    }
+}
+
+fn redact_content(content: &mut Content) {
+    for part in &mut content.parts {
+        *part = match part {
+            ContentPart::FunctionCall { name, .. } => ContentPart::FunctionCall {
+                name: name.clone(),
+                args: Value::String(REDACTED_PLACEHOLDER.to_string()),
+            },
+            ContentPart::FunctionResponse { name, .. } => ContentPart::FunctionResponse {
+                name: name.clone(),
+                response: Value::String(REDACTED_PLACEHOLDER.to_string()),
+            },
+            ContentPart::ExecutableCode { language, .. } => ContentPart::ExecutableCode {
+                language: language.clone(),
+                code: REDACTED_PLACEHOLDER.to_string(),
+            },
+            ContentPart::CodeExecutionResult { outcome, .. } => ContentPart::CodeExecutionResult {
+                outcome: *outcome,
+                output: REDACTED_PLACEHOLDER.to_string(),
+            },
+            _ => ContentPart::Text {
+                text: REDACTED_PLACEHOLDER.to_string(),
+            },
+        };
+    }
+}
+
+/// Keeps the originals of redacted events, for admins with elevated access
+#[async_trait]
+pub trait RedactionVault: Send + Sync {
+    async fn store(&self, session_id: &SessionId, event: &Event) -> Result<()>;
+
+    /// The original of a redacted event, if it was kept
+    async fn original(&self, session_id: &SessionId, event_id: &str) -> Result<Option<Event>>;
+}
+
+/// Keeps originals in memory, for tests and development
+#[derive(Debug, Default)]
+pub struct InMemoryRedactionVault {
+    events: RwLock<HashMap<(SessionId, String), Event>>,
+}
+
+impl InMemoryRedactionVault {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RedactionVault for InMemoryRedactionVault {
+    async fn store(&self, session_id: &SessionId, event: &Event) -> Result<()> {
+        self.events
+            .write()
+            .unwrap()
+            .insert((session_id.clone(), event.id.clone()), event.clone());
+        Ok(())
+    }
+
+    async fn original(&self, session_id: &SessionId, event_id: &str) -> Result<Option<Event>> {
+        Ok(self
+            .events
+            .read()
+            .unwrap()
+            .get(&(session_id.clone(), event_id.to_string()))
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::AgentBuilder, LlmAgent},
+        runners::Runner,
+        sessions::{InMemorySessionService, SessionService},
+        testing::MockGeminiServer,
+        tools::FunctionTool,
+    };
+    use futures::StreamExt;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_redacted_events_keep_their_shape() {
+        let mut content = Content::function_response("vault_lookup", json!({ "password": "hunter2" }));
+        content.parts.push(ContentPart::Text { text: "hunter2".to_string() });
+        let event = Event::content_response("agent", content).with_redaction();
+
+        let redacted = event.redacted();
+        let text = serde_json::to_string(&redacted).unwrap();
+        assert!(!text.contains("hunter2"), "{}", text);
+        assert!(redacted.redact);
+        assert_eq!(redacted.id, event.id);
+        assert!(matches!(
+            &redacted.content.as_ref().unwrap().parts[0],
+            ContentPart::FunctionResponse { name, .. } if name == "vault_lookup"
+        ));
+        assert_eq!(redacted.get_text().as_deref(), Some(REDACTED_PLACEHOLDER));
+    }
+
+    #[tokio::test]
+    async fn test_runner_stores_and_streams_redacted_tool_responses() {
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-redaction").await;
+        mock.push_function_call("read_secret", json!({ "name": "db" }))
+            .push_text("The secret is set.");
+
+        let tool = FunctionTool::with_context("read_secret", "Read a secret", |_, ctx| async move {
+            ctx.redact_response();
+            Ok(json!({ "value": "s3cr3t" }))
+        });
+        let agent = LlmAgent::builder()
+            .name("keeper")
+            .model("mock-gemini-redaction")
+            .tool(Arc::new(tool))
+            .build()
+            .unwrap();
+        let sessions = Arc::new(InMemorySessionService::new());
+        let vault = Arc::new(InMemoryRedactionVault::new());
+        let runner =
+            Runner::new("app", Arc::new(agent), sessions.clone()).with_redaction_vault(vault.clone());
+
+        let events: Vec<_> = runner
+            .run_async("u1".into(), "s1".into(), Content::user_text("Check the db secret"))
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        let streamed = events.iter().find(|event| event.redact).unwrap();
+        assert!(!serde_json::to_string(streamed.as_ref()).unwrap().contains("s3cr3t"));
+        // The model still saw the real result within the turn
+        assert!(mock.requests()[1].body.to_string().contains("s3cr3t"));
+
+        let session = sessions.get_session("app", &"u1".into(), &"s1".into()).await.unwrap().unwrap();
+        assert!(!serde_json::to_string(&session.events).unwrap().contains("s3cr3t"));
+        let original = vault.original(&"s1".into(), &streamed.id).await.unwrap().unwrap();
+        assert!(serde_json::to_string(&original).unwrap().contains("s3cr3t"));
+    }
+}
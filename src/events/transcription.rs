@@ -0,0 +1,149 @@
+//! Transcriptions of live audio
+//!
+//! Live connections report what they recognise in the user's audio and what
+//! the model says in its own. Interim transcriptions stream to clients as
+//! partial events for captions; the final transcription of each utterance is
+//! a regular event, so session history keeps a text record of the call.
+
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Whose audio was transcribed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptionSource {
+    User,
+    Model,
+}
+
+/// Text recognised in live audio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transcription {
+    pub source: TranscriptionSource,
+
+    /// Interim transcriptions carry the text recognised since the previous
+    /// one; a final transcription carries the whole utterance
+    pub text: String,
+
+    pub is_final: bool,
+
+    /// Start of the utterance, in milliseconds since the live session started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_ms: Option<u64>,
+
+    /// End of the audio transcribed so far, in milliseconds since the live session started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_ms: Option<u64>,
+}
+
+impl Transcription {
+    pub fn interim(source: TranscriptionSource, text: impl Into<String>) -> Self {
+        Self {
+            source,
+            text: text.into(),
+            is_final: false,
+            start_ms: None,
+            end_ms: None,
+        }
+    }
+
+    pub fn final_text(source: TranscriptionSource, text: impl Into<String>) -> Self {
+        Self {
+            is_final: true,
+            ..Self::interim(source, text)
+        }
+    }
+}
+
+/// An utterance being transcribed
+#[derive(Debug, Default)]
+struct Utterance {
+    text: String,
+    start_ms: u64,
+}
+
+/// Stamps timing on a live session's transcriptions and completes utterances
+/// whose connection only reports interim text
+#[derive(Debug)]
+pub struct TranscriptAssembler {
+    started: Instant,
+    user: Option<Utterance>,
+    model: Option<Utterance>,
+}
+
+impl TranscriptAssembler {
+    pub fn new(started: Instant) -> Self {
+        Self {
+            started,
+            user: None,
+            model: None,
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn utterance(&mut self, source: TranscriptionSource) -> &mut Option<Utterance> {
+        match source {
+            TranscriptionSource::User => &mut self.user,
+            TranscriptionSource::Model => &mut self.model,
+        }
+    }
+
+    /// Fill in the timing of a reported transcription
+    pub fn observe(&mut self, mut transcription: Transcription) -> Transcription {
+        let now = self.elapsed_ms();
+        let utterance = self.utterance(transcription.source);
+        let open = utterance.get_or_insert_with(|| Utterance {
+            text: String::new(),
+            start_ms: transcription.start_ms.unwrap_or(now),
+        });
+        transcription.start_ms.get_or_insert(open.start_ms);
+        transcription.end_ms.get_or_insert(now);
+        if transcription.is_final {
+            *utterance = None;
+        } else {
+            open.text.push_str(&transcription.text);
+        }
+        transcription
+    }
+
+    /// Final transcriptions of utterances still open when the turn ends
+    pub fn finish_turn(&mut self) -> Vec<Transcription> {
+        let end_ms = self.elapsed_ms();
+        [TranscriptionSource::User, TranscriptionSource::Model]
+            .into_iter()
+            .filter_map(|source| {
+                let utterance = self.utterance(source).take()?;
+                let text = utterance.text.trim();
+                (!text.is_empty()).then(|| Transcription {
+                    start_ms: Some(utterance.start_ms),
+                    end_ms: Some(end_ms),
+                    ..Transcription::final_text(source, text)
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interim_fragments_complete_at_turn_end() {
+        let mut assembler = TranscriptAssembler::new(Instant::now());
+        let first = assembler.observe(Transcription::interim(TranscriptionSource::User, "What's the "));
+        assembler.observe(Transcription::interim(TranscriptionSource::User, "weather?"));
+        let answer = assembler.observe(Transcription::final_text(TranscriptionSource::Model, "Sunny."));
+        assert!(answer.start_ms.is_some() && answer.end_ms.is_some());
+
+        let finals = assembler.finish_turn();
+        assert_eq!(finals.len(), 1);
+        assert_eq!(finals[0].text, "What's the weather?");
+        assert_eq!(finals[0].start_ms, first.start_ms);
+        assert!(finals[0].is_final);
+        assert!(assembler.finish_turn().is_empty());
+    }
+}
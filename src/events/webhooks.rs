@@ -0,0 +1,352 @@
+//! Outbound webhooks for conversation lifecycle events
+//!
+//! Webhooks are registered per app with an optional secret and event-type
+//! filter. Runners report sessions they create and invocations that finish
+//! or fail; each matching webhook receives a signed JSON POST. Failed
+//! deliveries go to the dead-letter queue, which retries them.
+
+use crate::{
+    error::Result,
+    models::HttpClientConfig,
+    types::{InvocationId, SessionId, Timestamp, UserId},
+    utils::{DeadLetter, DeadLetterHandler, DeadLetterQueue},
+};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio_util::task::TaskTracker;
+use tracing::debug;
+
+/// Header carrying `sha256=<hex HMAC of the body>` when the webhook has a secret
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-adk-signature";
+
+/// Header carrying the lifecycle event type
+pub const WEBHOOK_EVENT_HEADER: &str = "x-adk-event";
+
+/// Header carrying the delivery id, stable across retries
+pub const WEBHOOK_DELIVERY_HEADER: &str = "x-adk-delivery";
+
+/// Dead-letter kind of failed webhook deliveries
+pub const WEBHOOK_DEAD_LETTER_KIND: &str = "webhook";
+
+/// Timeout of a single delivery
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Type of lifecycle event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleEventType {
+    SessionCreated,
+    InvocationFinished,
+    Error,
+    HandoffRequested,
+}
+
+impl LifecycleEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SessionCreated => "session_created",
+            Self::InvocationFinished => "invocation_finished",
+            Self::Error => "error",
+            Self::HandoffRequested => "handoff_requested",
+        }
+    }
+}
+
+/// A lifecycle event as posted to webhooks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: LifecycleEventType,
+    pub app_name: String,
+    pub user_id: UserId,
+    pub session_id: SessionId,
+    pub invocation_id: Option<InvocationId>,
+    pub timestamp: Timestamp,
+
+    /// Event-specific details, e.g. the error message
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+impl LifecycleEvent {
+    pub fn new(
+        event_type: LifecycleEventType,
+        app_name: impl Into<String>,
+        user_id: impl Into<UserId>,
+        session_id: impl Into<SessionId>,
+    ) -> Self {
+        Self {
+            id: crate::utils::new_id(),
+            event_type,
+            app_name: app_name.into(),
+            user_id: user_id.into(),
+            session_id: session_id.into(),
+            invocation_id: None,
+            timestamp: crate::types::now(),
+            data: serde_json::Value::Null,
+        }
+    }
+
+    pub fn with_invocation_id(mut self, invocation_id: InvocationId) -> Self {
+        self.invocation_id = Some(invocation_id);
+        self
+    }
+
+    pub fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.data = data;
+        self
+    }
+}
+
+/// An endpoint receiving an app's lifecycle events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(default = "new_webhook_id")]
+    pub id: String,
+    pub url: String,
+
+    /// Key of the HMAC-SHA256 body signature; never returned by the API
+    #[serde(default, skip_serializing)]
+    pub secret: Option<String>,
+
+    /// Event types to deliver; all when empty
+    #[serde(default)]
+    pub events: Vec<LifecycleEventType>,
+}
+
+fn new_webhook_id() -> String {
+    crate::utils::new_id()
+}
+
+impl WebhookConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            id: new_webhook_id(),
+            url: url.into(),
+            secret: None,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Only deliver events of these types
+    pub fn with_events(mut self, events: impl IntoIterator<Item = LifecycleEventType>) -> Self {
+        self.events = events.into_iter().collect();
+        self
+    }
+
+    pub fn accepts(&self, event_type: LifecycleEventType) -> bool {
+        self.events.is_empty() || self.events.contains(&event_type)
+    }
+}
+
+/// `sha256=<hex>` HMAC-SHA256 signature of `body` under `secret`
+pub fn sign_webhook_body(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let signature: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", signature)
+}
+
+/// Everything needed to redo a failed delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WebhookDelivery {
+    url: String,
+    secret: Option<String>,
+    event: LifecycleEvent,
+}
+
+/// Delivers lifecycle events to the webhooks registered for their app
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    webhooks: Arc<RwLock<HashMap<String, Vec<WebhookConfig>>>>,
+    client: Client,
+    dead_letters: Option<Arc<DeadLetterQueue>>,
+    tasks: TaskTracker,
+}
+
+impl Default for WebhookDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookDispatcher {
+    pub fn new() -> Self {
+        let client = HttpClientConfig::default()
+            .shared_client()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            webhooks: Arc::new(RwLock::new(HashMap::new())),
+            client,
+            dead_letters: None,
+            tasks: TaskTracker::new(),
+        }
+    }
+
+    /// Run deliveries on `tasks`, e.g. a server's, so shutdown waits for them
+    pub fn with_task_tracker(mut self, tasks: TaskTracker) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    /// Park failed deliveries in `queue` and register the handler retrying them
+    pub fn with_dead_letter_queue(mut self, queue: Arc<DeadLetterQueue>) -> Self {
+        queue.register_handler(WEBHOOK_DEAD_LETTER_KIND, Arc::new(self.clone()));
+        self.dead_letters = Some(queue);
+        self
+    }
+
+    pub fn register(&self, app_name: impl Into<String>, webhook: WebhookConfig) {
+        self.webhooks.write().unwrap().entry(app_name.into()).or_default().push(webhook);
+    }
+
+    /// Remove a webhook; returns whether it existed
+    pub fn remove(&self, app_name: &str, webhook_id: &str) -> bool {
+        let mut webhooks = self.webhooks.write().unwrap();
+        let Some(registered) = webhooks.get_mut(app_name) else {
+            return false;
+        };
+        let before = registered.len();
+        registered.retain(|webhook| webhook.id != webhook_id);
+        before != registered.len()
+    }
+
+    pub fn webhooks(&self, app_name: &str) -> Vec<WebhookConfig> {
+        self.webhooks.read().unwrap().get(app_name).cloned().unwrap_or_default()
+    }
+
+    /// Deliver `event` to every matching webhook in the background
+    pub fn dispatch(&self, event: LifecycleEvent) {
+        for webhook in self.webhooks(&event.app_name) {
+            if !webhook.accepts(event.event_type) {
+                continue;
+            }
+            let delivery = WebhookDelivery {
+                url: webhook.url,
+                secret: webhook.secret,
+                event: event.clone(),
+            };
+            let dispatcher = self.clone();
+            self.tasks.spawn(async move {
+                if let Err(e) = dispatcher.deliver(&delivery).await {
+                    match (&dispatcher.dead_letters, serde_json::to_value(&delivery)) {
+                        (Some(queue), Ok(payload)) => {
+                            let _ = queue.push(WEBHOOK_DEAD_LETTER_KIND, payload, e).await;
+                        }
+                        _ => tracing::warn!("Webhook delivery to {} failed: {}", delivery.url, e),
+                    }
+                }
+            });
+        }
+    }
+
+    async fn deliver(&self, delivery: &WebhookDelivery) -> Result<()> {
+        debug!("Delivering {} webhook to {}", delivery.event.event_type.as_str(), delivery.url);
+        let body = serde_json::to_vec(&delivery.event)?;
+        let mut request = self
+            .client
+            .post(&delivery.url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_EVENT_HEADER, delivery.event.event_type.as_str())
+            .header(WEBHOOK_DELIVERY_HEADER, &delivery.event.id);
+        if let Some(secret) = &delivery.secret {
+            request = request.header(WEBHOOK_SIGNATURE_HEADER, sign_webhook_body(secret, &body));
+        }
+
+        let response = request.body(body).send().await?;
+        if !response.status().is_success() {
+            return Err(crate::adk_error!(
+                NetworkError,
+                "Webhook {} responded with {}",
+                delivery.url,
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeadLetterHandler for WebhookDispatcher {
+    async fn retry(&self, letter: &DeadLetter) -> Result<()> {
+        let delivery: WebhookDelivery = serde_json::from_value(letter.payload.clone())?;
+        self.deliver(&delivery).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::DeadLetterConfig;
+    use axum::{body::Bytes, http::HeaderMap, routing::post, Router};
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_signed_delivery_filters_and_dead_letters() {
+        let (sender, mut received) = mpsc::unbounded_channel();
+        let app = Router::new().route(
+            "/hook",
+            post(move |headers: HeaderMap, body: Bytes| {
+                let sender = sender.clone();
+                async move {
+                    let signature = headers[WEBHOOK_SIGNATURE_HEADER].to_str().unwrap().to_string();
+                    sender.send((signature, body)).unwrap();
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let queue = Arc::new(DeadLetterQueue::in_memory(DeadLetterConfig::new()));
+        let tasks = TaskTracker::new();
+        let dispatcher = WebhookDispatcher::new()
+            .with_dead_letter_queue(queue.clone())
+            .with_task_tracker(tasks.clone());
+        dispatcher.register(
+            "shop",
+            WebhookConfig::new(format!("http://{}/hook", addr))
+                .with_secret("s3cret")
+                .with_events([LifecycleEventType::Error]),
+        );
+        dispatcher.register("shop", WebhookConfig::new(format!("http://{}/missing", addr)));
+
+        dispatcher.dispatch(LifecycleEvent::new(LifecycleEventType::SessionCreated, "shop", "u1", "s1"));
+        dispatcher.dispatch(
+            LifecycleEvent::new(LifecycleEventType::Error, "shop", "u1", "s1")
+                .with_data(serde_json::json!({"error": "model unavailable"})),
+        );
+
+        // Only the error reaches the filtered webhook, signed with its secret
+        let (signature, body) = received.recv().await.unwrap();
+        assert_eq!(signature, sign_webhook_body("s3cret", &body));
+        let event: LifecycleEvent = serde_json::from_slice(&body).unwrap();
+        assert_eq!(event.event_type, LifecycleEventType::Error);
+        assert_eq!(event.data["error"], "model unavailable");
+
+        // Both events fail at the unfiltered webhook's missing route and are
+        // dead-lettered by the time the deliveries' tasks are done
+        tasks.close();
+        tasks.wait().await;
+        let letters = queue.list().await;
+        assert_eq!(letters.len(), 2);
+        assert!(letters.iter().all(|letter| letter.kind == WEBHOOK_DEAD_LETTER_KIND));
+        assert!(received.try_recv().is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Prompt and model experiments
+//!
+//! An [`Experiment`] splits sessions between named variants, each of which
+//! may replace the agent's instruction or model. A runner configured with
+//! [`Runner::with_experiment`](crate::runners::Runner::with_experiment)
+//! assigns every session to a variant (the same one on every turn), applies
+//! it to the targeted agent and tags the session's events, so
+//! [`analytics`](crate::analytics) can report quality, feedback and cost per
+//! variant.
+
+use crate::{error::Result, events::Event};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Event metadata key holding the experiment name
+pub const EXPERIMENT_METADATA_KEY: &str = "experiment";
+
+/// Event metadata key holding the assigned variant
+pub const EXPERIMENT_VARIANT_METADATA_KEY: &str = "experiment_variant";
+
+/// One arm of an experiment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+
+    /// Percentage of sessions assigned to this variant
+    pub allocation: u32,
+
+    /// Instruction replacing the agent's own
+    #[serde(default)]
+    pub instruction: Option<String>,
+
+    /// Model replacing the agent's own (or its profile's)
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl Variant {
+    pub fn new(name: impl Into<String>, allocation: u32) -> Self {
+        Self {
+            name: name.into(),
+            allocation,
+            instruction: None,
+            model: None,
+        }
+    }
+
+    pub fn with_instruction(mut self, instruction: impl Into<String>) -> Self {
+        self.instruction = Some(instruction.into());
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+}
+
+/// Named variants with allocations adding up to 100%
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Experiment {
+    pub name: String,
+
+    /// Agent the variants apply to; the runner's root agent when unset
+    #[serde(default)]
+    pub agent: Option<String>,
+    pub variants: Vec<Variant>,
+}
+
+impl Experiment {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            agent: None,
+            variants: Vec::new(),
+        }
+    }
+
+    /// Apply the variants to a sub-agent instead of the root agent
+    pub fn for_agent(mut self, agent: impl Into<String>) -> Self {
+        self.agent = Some(agent.into());
+        self
+    }
+
+    pub fn variant(mut self, variant: Variant) -> Self {
+        self.variants.push(variant);
+        self
+    }
+
+    /// Check that variant names are unique and allocations add up to 100%
+    pub fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        if let Some(duplicate) = self.variants.iter().find(|variant| !names.insert(&variant.name)) {
+            return Err(crate::adk_error!(
+                ValidationError,
+                "Experiment '{}' has more than one variant named '{}'",
+                self.name,
+                duplicate.name
+            ));
+        }
+        let total: u32 = self.variants.iter().map(|variant| variant.allocation).sum();
+        if total != 100 {
+            return Err(crate::adk_error!(
+                ValidationError,
+                "Allocations of experiment '{}' add up to {}%, not 100%",
+                self.name,
+                total
+            ));
+        }
+        Ok(())
+    }
+
+    /// The variant of a session; stable across turns, processes and restarts
+    pub fn assign(&self, session_id: &str) -> Option<&Variant> {
+        let bucket = (stable_hash(&format!("{}:{}", self.name, session_id)) % 100) as u32;
+        let mut upper = 0;
+        self.variants.iter().find(|variant| {
+            upper += variant.allocation;
+            bucket < upper
+        })
+    }
+}
+
+/// FNV-1a, so assignments do not depend on the standard library's hasher
+fn stable_hash(key: &str) -> u64 {
+    key.bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// The variant a session was assigned, carried by its invocation context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExperimentAssignment {
+    pub experiment: String,
+
+    /// Agent the variant applies to
+    pub agent: String,
+    pub variant: Variant,
+}
+
+impl ExperimentAssignment {
+    /// Assign a session of `root_agent`'s runner, if the experiment covers it
+    pub fn for_session(experiment: &Experiment, root_agent: &str, session_id: &str) -> Option<Self> {
+        experiment.assign(session_id).map(|variant| Self {
+            experiment: experiment.name.clone(),
+            agent: experiment.agent.clone().unwrap_or_else(|| root_agent.to_string()),
+            variant: variant.clone(),
+        })
+    }
+
+    /// Whether the variant overrides `agent`
+    pub fn applies_to(&self, agent: &str) -> bool {
+        self.agent == agent
+    }
+
+    /// Tag an event with the experiment and variant
+    pub fn tag(&self, event: &mut Event) {
+        event.metadata.insert(EXPERIMENT_METADATA_KEY.to_string(), self.experiment.clone().into());
+        event
+            .metadata
+            .insert(EXPERIMENT_VARIANT_METADATA_KEY.to_string(), self.variant.name.clone().into());
+    }
+}
+
+/// The experiment and variant an event was tagged with
+pub fn event_variant(event: &Event) -> Option<(&str, &str)> {
+    let experiment = event.metadata.get(EXPERIMENT_METADATA_KEY)?.as_str()?;
+    let variant = event.metadata.get(EXPERIMENT_VARIANT_METADATA_KEY)?.as_str()?;
+    Some((experiment, variant))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::AgentBuilder, LlmAgent},
+        analytics::aggregate_variants,
+        runners::RunnerBuilder,
+        sessions::{feedback::record_feedback, Feedback, InMemorySessionService, Rating, SessionFilter, SessionService},
+        testing::MockGeminiServer,
+        types::Content,
+        utils::global_usage_tracker,
+    };
+    use futures::StreamExt;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_sessions_split_by_allocation_and_stay_assigned() {
+        let experiment = Experiment::new("greeting")
+            .variant(Variant::new("control", 80))
+            .variant(Variant::new("friendly", 20).with_instruction("Be warm."));
+        experiment.validate().unwrap();
+
+        let friendly = (0..1000)
+            .filter(|i| experiment.assign(&format!("session-{}", i)).unwrap().name == "friendly")
+            .count();
+        assert!((120..280).contains(&friendly), "{} of 1000 sessions were friendly", friendly);
+        assert_eq!(experiment.assign("s1"), experiment.assign("s1"));
+
+        assert!(Experiment::new("bad").variant(Variant::new("a", 60)).validate().is_err());
+        let duplicate = Experiment::new("bad").variant(Variant::new("a", 50)).variant(Variant::new("a", 50));
+        assert!(duplicate.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_runner_applies_variants_and_analytics_reports_them() {
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-experiments").await;
+        mock.set_fallback(crate::testing::MockReply::text("Hello."));
+
+        let agent = LlmAgent::builder()
+            .name("greeter")
+            .model("mock-gemini-experiments")
+            .instruction("Greet the user.")
+            .build()
+            .unwrap();
+        let sessions = Arc::new(InMemorySessionService::new());
+        let experiment = Experiment::new("greeting")
+            .variant(Variant::new("control", 50))
+            .variant(Variant::new("friendly", 50).with_instruction("Greet the user warmly."));
+        let runner = RunnerBuilder::new()
+            .app_name("experiments-app")
+            .agent(Arc::new(agent))
+            .session_service(sessions.clone())
+            .experiment(experiment.clone())
+            .build()
+            .unwrap();
+
+        let started = crate::types::now();
+        for i in 0..8 {
+            let session_id = format!("s{}", i);
+            let events: Vec<_> = runner
+                .run_async("u1".into(), session_id.clone(), Content::user_text("Hi"))
+                .await
+                .unwrap()
+                .collect()
+                .await;
+            let variant = &experiment.assign(&session_id).unwrap().name;
+            let event = events.last().unwrap().as_ref().unwrap();
+            assert_eq!(event_variant(event), Some(("greeting", variant.as_str())));
+
+            let request = mock.requests().last().unwrap().body.to_string();
+            assert_eq!(request.contains("warmly"), variant == "friendly");
+            assert!(request.contains("Greet the user"));
+
+            if i == 0 {
+                record_feedback(sessions.as_ref(), &session_id, &event.id, &Feedback::new(Rating::ThumbsUp))
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let stored = sessions.list_sessions(&SessionFilter::new()).await.unwrap();
+        assert!(stored.iter().all(|session| session.events.iter().all(|event| event_variant(event).is_some())));
+        let calls = global_usage_tracker().model_calls_since(started);
+        let variants = aggregate_variants("experiments-app", &stored, &calls, started, chrono::Duration::hours(1));
+        assert_eq!(variants.len(), 2);
+        assert_eq!(variants.iter().map(|v| v.sessions).sum::<usize>(), 8);
+        assert_eq!(variants.iter().map(|v| v.model_calls).sum::<usize>(), 8);
+        assert_eq!(variants.iter().map(|v| v.thumbs_up).sum::<usize>(), 1);
+        let rated = variants.iter().find(|v| v.variant == experiment.assign("s0").unwrap().name).unwrap();
+        assert_eq!(rated.satisfaction, Some(1.0));
+    }
+}
@@ -0,0 +1,304 @@
+//! C ABI for embedding a runner in non-Rust hosts
+//!
+//! Build a shared library with
+//! `cargo rustc --release --features ffi --lib --crate-type cdylib`;
+//! the matching header is `include/adk.h` (regenerate with `cbindgen`).
+//!
+//! Strings crossing the boundary are NUL-terminated UTF-8. Strings returned by
+//! the library must be released with `adk_string_free`.
+
+use crate::{
+    agents::{base_agent::AgentBuilder, LlmAgent},
+    error::Result,
+    runners::Runner,
+    sessions::InMemorySessionService,
+    types::Content,
+};
+use futures::StreamExt;
+use serde::Deserialize;
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    ptr,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let message = CString::new(message.into().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = Some(message));
+}
+
+/// Runner configuration accepted by `adk_runner_create`
+#[derive(Debug, Deserialize)]
+struct FfiRunnerConfig {
+    app_name: String,
+    agent: FfiAgentConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct FfiAgentConfig {
+    name: String,
+    model: String,
+    #[serde(default)]
+    instruction: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// Opaque runner handle
+pub struct AdkRunner {
+    runtime: tokio::runtime::Runtime,
+    runner: Arc<Runner>,
+    sender: Sender<String>,
+    events: Mutex<Receiver<String>>,
+}
+
+impl AdkRunner {
+    fn from_config(config: &str) -> Result<Self> {
+        let config: FfiRunnerConfig = serde_json::from_str(config)?;
+        let agent = LlmAgent::builder()
+            .name(config.agent.name)
+            .model(config.agent.model)
+            .instruction(config.agent.instruction)
+            .description(config.agent.description)
+            .build()?;
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        let runner = Runner::new(config.app_name, Arc::new(agent), Arc::new(InMemorySessionService::new()));
+        let (sender, events) = mpsc::channel();
+        Ok(Self {
+            runtime,
+            runner: Arc::new(runner),
+            sender,
+            events: Mutex::new(events),
+        })
+    }
+
+    /// Start a turn in the background, queueing each event as JSON followed by
+    /// a terminal `{"done": true}` or `{"error": "..."}` message
+    fn run_turn(&self, user_id: String, session_id: String, message: String) {
+        let runner = self.runner.clone();
+        let sender = self.sender.clone();
+        // Host threads are outside the runtime, so enter it for the runner's tracker to spawn on
+        let _runtime = self.runtime.enter();
+        self.runner.spawn(async move {
+            let outcome = async {
+                let mut events = runner
+                    .run_async(user_id, session_id, Content::user_text(message))
+                    .await?;
+                while let Some(event) = events.next().await {
+                    let _ = sender.send(serde_json::to_string(&event?)?);
+                }
+                Ok::<_, crate::error::AdkError>(())
+            }
+            .await;
+            let terminal = match outcome {
+                Ok(()) => serde_json::json!({"done": true}),
+                Err(e) => serde_json::json!({"error": e.to_string()}),
+            };
+            let _ = sender.send(terminal.to_string());
+        });
+    }
+}
+
+unsafe fn read_str<'a>(value: *const c_char, name: &str) -> Option<&'a str> {
+    if value.is_null() {
+        set_last_error(format!("{} is null", name));
+        return None;
+    }
+    match CStr::from_ptr(value).to_str() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            set_last_error(format!("{} is not valid UTF-8", name));
+            None
+        }
+    }
+}
+
+/// Create a runner from a JSON config:
+/// `{"app_name": ..., "agent": {"name": ..., "model": ..., "instruction": ...}}`.
+///
+/// Returns null on failure; see `adk_last_error`.
+///
+/// # Safety
+///
+/// `config_json` must be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn adk_runner_create(config_json: *const c_char) -> *mut AdkRunner {
+    let Some(config) = read_str(config_json, "config_json") else {
+        return ptr::null_mut();
+    };
+    match AdkRunner::from_config(config) {
+        Ok(runner) => Box::into_raw(Box::new(runner)),
+        Err(e) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Start a turn; poll its events with `adk_runner_poll_event`.
+///
+/// Returns 0 on success and -1 on invalid arguments.
+///
+/// # Safety
+///
+/// `runner` must come from `adk_runner_create` and not be freed; the strings
+/// must be null or valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn adk_runner_run_turn(
+    runner: *const AdkRunner,
+    user_id: *const c_char,
+    session_id: *const c_char,
+    message: *const c_char,
+) -> c_int {
+    let Some(runner) = runner.as_ref() else {
+        set_last_error("runner is null");
+        return -1;
+    };
+    let (Some(user_id), Some(session_id), Some(message)) = (
+        read_str(user_id, "user_id"),
+        read_str(session_id, "session_id"),
+        read_str(message, "message"),
+    ) else {
+        return -1;
+    };
+    runner.run_turn(user_id.to_string(), session_id.to_string(), message.to_string());
+    0
+}
+
+/// Wait up to `timeout_ms` for the next queued message as JSON.
+///
+/// Returns null if nothing arrived in time. Each turn ends with
+/// `{"done": true}` or `{"error": "..."}`. Free the result with `adk_string_free`.
+///
+/// # Safety
+///
+/// `runner` must come from `adk_runner_create` and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn adk_runner_poll_event(runner: *const AdkRunner, timeout_ms: u64) -> *mut c_char {
+    let Some(runner) = runner.as_ref() else {
+        set_last_error("runner is null");
+        return ptr::null_mut();
+    };
+    let events = runner.events.lock().expect("ffi event queue poisoned");
+    match events.recv_timeout(Duration::from_millis(timeout_ms)) {
+        Ok(json) => CString::new(json).map(CString::into_raw).unwrap_or(ptr::null_mut()),
+        Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => ptr::null_mut(),
+    }
+}
+
+/// Message of the last error on the calling thread, or null.
+///
+/// The pointer is owned by the library and valid until the next failing call.
+#[no_mangle]
+pub extern "C" fn adk_last_error() -> *const c_char {
+    LAST_ERROR.with(|slot| slot.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Free a string returned by the library.
+///
+/// # Safety
+///
+/// `value` must be null or a pointer returned by this library, freed once.
+#[no_mangle]
+pub unsafe extern "C" fn adk_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Free a runner, cancelling any turn still in flight.
+///
+/// # Safety
+///
+/// `runner` must be null or a pointer from `adk_runner_create`, freed once.
+#[no_mangle]
+pub unsafe extern "C" fn adk_runner_free(runner: *mut AdkRunner) {
+    if !runner.is_null() {
+        let runner = Box::from_raw(runner);
+        let _ = runner.runtime.block_on(runner.runner.close());
+        runner.runtime.shutdown_background();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runner_lifecycle_over_c_abi() {
+        unsafe {
+            let bad = CString::new(r#"{"app_name": "app"}"#).unwrap();
+            assert!(adk_runner_create(bad.as_ptr()).is_null());
+            let error = CStr::from_ptr(adk_last_error()).to_str().unwrap();
+            assert!(error.contains("agent"), "{}", error);
+
+            let config = CString::new(
+                r#"{"app_name": "app", "agent": {"name": "helper", "model": "gemini-2.0-flash"}}"#,
+            )
+            .unwrap();
+            let runner = adk_runner_create(config.as_ptr());
+            assert!(!runner.is_null());
+            assert!(adk_runner_poll_event(runner, 10).is_null());
+            assert_eq!(adk_runner_run_turn(runner, ptr::null(), ptr::null(), ptr::null()), -1);
+            adk_runner_free(runner);
+        }
+    }
+
+    #[test]
+    fn test_turn_runs_from_a_host_thread() {
+        // The mock serves from its own runtime; the turn is started from a plain thread
+        let server_runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock = server_runtime.block_on(async {
+            let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+            mock.register_model("mock-gemini-ffi").await;
+            mock
+        });
+        mock.push_text("Hello from the mock.");
+
+        let host = std::thread::spawn(|| unsafe {
+            let config = CString::new(
+                r#"{"app_name": "app", "agent": {"name": "helper", "model": "mock-gemini-ffi"}}"#,
+            )
+            .unwrap();
+            let runner = adk_runner_create(config.as_ptr());
+            assert!(!runner.is_null());
+            let (user, session, message) = (
+                CString::new("u1").unwrap(),
+                CString::new("s1").unwrap(),
+                CString::new("Hi").unwrap(),
+            );
+            assert_eq!(adk_runner_run_turn(runner, user.as_ptr(), session.as_ptr(), message.as_ptr()), 0);
+
+            let mut messages = Vec::new();
+            loop {
+                let event = adk_runner_poll_event(runner, 5000);
+                assert!(!event.is_null(), "turn produced no terminal message");
+                let json: serde_json::Value = serde_json::from_str(CStr::from_ptr(event).to_str().unwrap()).unwrap();
+                adk_string_free(event);
+                let terminal = json.get("done").is_some() || json.get("error").is_some();
+                messages.push(json);
+                if terminal {
+                    break;
+                }
+            }
+            adk_runner_free(runner);
+            messages
+        });
+        let messages = host.join().unwrap();
+        assert_eq!(messages.last().unwrap()["done"], true, "{:?}", messages);
+        assert!(messages.iter().any(|event| event.to_string().contains("Hello from the mock.")));
+        drop(mock);
+        drop(server_runtime);
+    }
+}
@@ -22,7 +22,7 @@
 //! ## Quick Start
 //!
 //! ```rust
-//! use google_adk::agents::LlmAgent;
+//! use google_adk::agents::{base_agent::AgentBuilder, LlmAgent};
 //! use google_adk::tools::google_search;
 //!
 //! #[tokio::main]
@@ -41,18 +41,30 @@
 //! ```
 
 pub mod agents;
+pub mod analytics;
 pub mod artifacts;
+pub mod blocking;
+#[cfg(feature = "cli")]
 pub mod cli;
 pub mod error;
 pub mod events;
+pub mod experiments;
+#[cfg(feature = "evaluation")]
 pub mod evaluation;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod memory;
 pub mod models;
+pub mod retention;
 pub mod runners;
+#[cfg(feature = "scripting")]
+pub mod scripting;
 pub mod sessions;
+pub mod testing;
 pub mod tools;
 pub mod types;
 pub mod utils;
+#[cfg(feature = "server")]
 pub mod web;
 
 // Re-export commonly used types
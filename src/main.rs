@@ -1,8 +1,9 @@
 //! ADK CLI binary
 
 use clap::{Parser, Subcommand};
-use google_adk::cli::commands::{ApiServerCommand, CreateCommand, EvalCommand, RunCommand, WebCommand};
-use google_adk::{init, Result};
+use google_adk::cli::BenchCommand;
+use google_adk::cli::commands::{ApiServerCommand, CreateCommand, DoctorCommand, EvalCommand, ExportCommand, ImportCommand, RunCommand, WebCommand};
+use google_adk::init;
 use std::process;
 use tracing::{error, info};
 
@@ -25,13 +26,33 @@ enum Commands {
     Eval(EvalCommand),
     /// Start a web server with UI for agents
     Web(WebCommand),
+    /// Export stored sessions as a fine-tuning dataset
+    Export(ExportCommand),
+    /// Import conversations from Python ADK or Dialogflow CX exports
+    Import(ImportCommand),
     /// Start a FastAPI server for agents
     #[command(name = "api_server")]
     ApiServer(ApiServerCommand),
+    /// Benchmark a running server
+    Bench(BenchCommand),
+    /// Check the configuration and show effective tool policies
+    Doctor(DoctorCommand),
 }
 
-#[tokio::main]
-async fn main() {
+fn main() {
+    // The environment can only be changed safely before the runtime's threads start
+    google_adk::web::capture_systemd_activation();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start the async runtime: {}", e);
+            process::exit(1);
+        }
+    };
+    runtime.block_on(run());
+}
+
+async fn run() {
     // Initialize the library
     if let Err(e) = init() {
         eprintln!("Failed to initialize ADK: {}", e);
@@ -45,7 +66,11 @@ async fn main() {
         Commands::Run(cmd) => cmd.execute().await,
         Commands::Eval(cmd) => cmd.execute().await,
         Commands::Web(cmd) => cmd.execute().await,
+        Commands::Export(cmd) => cmd.execute().await,
+        Commands::Import(cmd) => cmd.execute().await,
         Commands::ApiServer(cmd) => cmd.execute().await,
+        Commands::Bench(cmd) => cmd.execute().await,
+        Commands::Doctor(cmd) => cmd.execute().await,
     };
 
     if let Err(e) = result {
@@ -0,0 +1,710 @@
+//! Anthropic Claude LLM implementation
+//!
+//! [`AnthropicLlm`] talks to the Anthropic messages API. Tool use blocks are
+//! identified by id while ADK content matches calls and responses by
+//! function name, so ids are assigned while the request is converted.
+//! Consecutive turns of the same role are merged, as the API requires
+//! alternating roles.
+
+use crate::{
+    error::Result,
+    models::{
+        base_llm::LlmConfig, http_client::InFlightRequest, sse::json_event_stream, BaseLlm, FinishReason,
+        HttpClientConfig, LlmRequest, LlmResponse, Usage,
+    },
+    types::{Content, ContentPart, FunctionCall, FunctionCallingMode},
+    utils::base64_bytes::Base64,
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::pin::Pin;
+use tracing::{debug, error, info};
+
+/// Default Anthropic API base URL
+pub const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+
+/// Messages API version sent with every request
+pub const ANTHROPIC_API_VERSION: &str = "2023-06-01";
+
+/// Output token limit used when the request sets none; the API requires one
+pub const DEFAULT_MAX_TOKENS: i32 = 4096;
+
+/// Anthropic Claude LLM implementation
+#[derive(Debug, Clone)]
+pub struct AnthropicLlm {
+    model: String,
+    api_key: Option<String>,
+    client: Client,
+    base_url: String,
+}
+
+/// Messages API request format
+#[derive(Debug, Serialize)]
+struct AnthropicRequest {
+    model: String,
+    max_tokens: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicMessage {
+    role: &'static str,
+    content: Vec<AnthropicBlock>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicBlock {
+    Text { text: String },
+    Image { source: AnthropicSource },
+    Document { source: AnthropicSource },
+    ToolUse { id: String, name: String, input: Value },
+    ToolResult { tool_use_id: String, content: String },
+}
+
+/// Media sent inline (base64) or by URL
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: Value,
+}
+
+/// Messages API response format
+#[derive(Debug, Deserialize)]
+struct AnthropicResponse {
+    #[serde(default)]
+    content: Vec<AnthropicResponseBlock>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicResponseBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        name: String,
+        #[serde(default)]
+        input: Value,
+    },
+    /// Thinking and other blocks ADK has no counterpart for
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: Option<u32>,
+    #[serde(default)]
+    output_tokens: Option<u32>,
+}
+
+impl From<AnthropicUsage> for Usage {
+    fn from(usage: AnthropicUsage) -> Self {
+        Self {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: usage.input_tokens.zip(usage.output_tokens).map(|(input, output)| input + output),
+        }
+    }
+}
+
+/// One server-sent event of a streamed response
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicStreamEvent {
+    MessageStart {
+        message: AnthropicStreamMessage,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: AnthropicResponseBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: AnthropicDelta,
+    },
+    MessageDelta {
+        delta: AnthropicMessageDelta,
+        #[serde(default)]
+        usage: Option<AnthropicUsage>,
+    },
+    MessageStop,
+    Error {
+        error: AnthropicError,
+    },
+    /// Pings and block stops
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicStreamMessage {
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicDelta {
+    TextDelta {
+        text: String,
+    },
+    /// Fragment of a tool use block's input JSON
+    InputJsonDelta {
+        partial_json: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicMessageDelta {
+    #[serde(default)]
+    stop_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicError {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    message: String,
+}
+
+impl AnthropicLlm {
+    /// Create a new Anthropic LLM instance
+    pub fn new(model: impl Into<String>) -> Self {
+        let client = HttpClientConfig::default()
+            .shared_client()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            model: model.into(),
+            api_key: None,
+            client,
+            base_url: ANTHROPIC_BASE_URL.to_string(),
+        }
+    }
+
+    /// Create an Anthropic LLM instance from an `LlmConfig`
+    pub fn from_config(config: LlmConfig) -> Result<Self> {
+        let mut http_client = config.http_client;
+        if http_client.timeout_seconds.is_none() {
+            http_client.timeout_seconds = config.timeout_seconds;
+        }
+
+        let mut llm = Self::new(config.model).with_http_client_config(&http_client)?;
+        llm.api_key = config.api_key;
+        if let Some(endpoint) = config.endpoint {
+            llm.base_url = endpoint;
+        }
+        Ok(llm)
+    }
+
+    /// Use a caller-provided HTTP client
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Use the shared HTTP client for the given connection settings (proxy, CA bundle, timeouts, pooling)
+    pub fn with_http_client_config(mut self, config: &HttpClientConfig) -> Result<Self> {
+        self.client = config.shared_client()?;
+        Ok(self)
+    }
+
+    /// Send requests to another messages API endpoint, e.g. a proxy
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set API key
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Convert ADK request to messages API format
+    fn convert_request(&self, request: &LlmRequest, stream: bool) -> Result<AnthropicRequest> {
+        let mut system = Vec::new();
+        let mut messages: Vec<AnthropicMessage> = Vec::new();
+        // Ids of tool uses awaiting their result, in call order
+        let mut pending_calls: Vec<(String, String)> = Vec::new();
+        let mut next_call_id = 0;
+
+        for content in request.all_contents() {
+            if content.role == "system" {
+                system.extend(content.parts.iter().filter_map(part_as_text));
+                continue;
+            }
+            let role = if content.role == "model" { "assistant" } else { "user" };
+
+            let mut blocks = Vec::new();
+            for part in &content.parts {
+                let block = match part {
+                    ContentPart::FunctionCall { name, args } => {
+                        let id = format!("toolu_{}", next_call_id);
+                        next_call_id += 1;
+                        pending_calls.push((name.clone(), id.clone()));
+                        AnthropicBlock::ToolUse {
+                            id,
+                            name: name.clone(),
+                            input: args.clone(),
+                        }
+                    }
+                    ContentPart::FunctionResponse { name, response } => {
+                        let tool_use_id = match pending_calls.iter().position(|(call, _)| call == name) {
+                            Some(index) => pending_calls.remove(index).1,
+                            None => {
+                                next_call_id += 1;
+                                format!("toolu_{}", next_call_id - 1)
+                            }
+                        };
+                        AnthropicBlock::ToolResult {
+                            tool_use_id,
+                            content: response.to_string(),
+                        }
+                    }
+                    ContentPart::Image { data, mime_type } => AnthropicBlock::Image {
+                        source: AnthropicSource::Base64 {
+                            media_type: mime_type.clone(),
+                            data: Base64(data).to_string(),
+                        },
+                    },
+                    ContentPart::File { data, mime_type, .. } if mime_type == "application/pdf" => {
+                        AnthropicBlock::Document {
+                            source: AnthropicSource::Base64 {
+                                media_type: mime_type.clone(),
+                                data: Base64(data).to_string(),
+                            },
+                        }
+                    }
+                    ContentPart::FileRef { uri, mime_type } if mime_type.starts_with("image/") => {
+                        AnthropicBlock::Image {
+                            source: AnthropicSource::Url { url: uri.clone() },
+                        }
+                    }
+                    ContentPart::FileRef { uri, mime_type } if mime_type == "application/pdf" => {
+                        AnthropicBlock::Document {
+                            source: AnthropicSource::Url { url: uri.clone() },
+                        }
+                    }
+                    part => match part_as_text(part) {
+                        Some(text) => AnthropicBlock::Text { text },
+                        None => {
+                            return Err(crate::adk_error!(
+                                ModelError,
+                                "The Anthropic messages API does not support this content part: {:?}",
+                                part
+                            ))
+                        }
+                    },
+                };
+                blocks.push(block);
+            }
+            if blocks.is_empty() {
+                continue;
+            }
+
+            // Roles must alternate, e.g. a tool result followed by a user message
+            match messages.last_mut() {
+                Some(last) if last.role == role => last.content.extend(blocks),
+                _ => messages.push(AnthropicMessage { role, content: blocks }),
+            }
+        }
+
+        let tools = request
+            .config
+            .tools
+            .iter()
+            .flat_map(|tool| &tool.function_declarations)
+            .map(|decl| AnthropicTool {
+                name: decl.name.clone(),
+                description: decl.description.clone(),
+                input_schema: decl.parameters.clone(),
+            })
+            .collect();
+
+        let tool_choice = request.config.tool_config.as_ref().map(|config| {
+            match (config.function_calling_mode, config.allowed_function_names.as_slice()) {
+                (FunctionCallingMode::Any, [name]) => json!({ "type": "tool", "name": name }),
+                (FunctionCallingMode::Any, _) => json!({ "type": "any" }),
+                (FunctionCallingMode::None, _) => json!({ "type": "none" }),
+                (FunctionCallingMode::Auto, _) => json!({ "type": "auto" }),
+            }
+        });
+
+        Ok(AnthropicRequest {
+            model: self.model.clone(),
+            max_tokens: request.config.max_output_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            system: (!system.is_empty()).then(|| system.join("\n")),
+            messages,
+            tools,
+            tool_choice,
+            temperature: request.config.temperature,
+            top_p: request.config.top_p,
+            top_k: request.config.top_k,
+            stop_sequences: request.config.stop_sequences.clone(),
+            stream,
+        })
+    }
+
+    /// Convert messages API response to ADK format
+    fn convert_response(&self, response: AnthropicResponse) -> Result<LlmResponse> {
+        let mut llm_response = LlmResponse::new();
+        let mut text = String::new();
+
+        for block in response.content {
+            match block {
+                AnthropicResponseBlock::Text { text: block_text } => text.push_str(&block_text),
+                AnthropicResponseBlock::ToolUse { name, input } => llm_response.function_calls.push(FunctionCall {
+                    name,
+                    args: if input.is_null() { json!({}) } else { input },
+                }),
+                AnthropicResponseBlock::Other => {}
+            }
+        }
+        if !text.is_empty() {
+            llm_response.content = Some(Content::model_text(text));
+        }
+        llm_response.finish_reason = response.stop_reason.as_deref().map(convert_stop_reason);
+        llm_response.usage = response.usage.map(Usage::from);
+
+        Ok(llm_response)
+    }
+
+    /// Send a request, turning error statuses into `ModelError`s
+    async fn post(&self, request: &AnthropicRequest) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(format!("{}/messages", self.base_url))
+            .header("x-api-key", self.get_api_key()?)
+            .header("anthropic-version", ANTHROPIC_API_VERSION)
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!("Anthropic API error: {} - {}", status, error_text);
+            return Err(crate::adk_error!(
+                ModelError,
+                "Anthropic API error: {} - {}",
+                status,
+                error_text
+            ));
+        }
+        Ok(response)
+    }
+
+    /// Get the API key
+    fn get_api_key(&self) -> Result<String> {
+        self.api_key
+            .clone()
+            .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
+            .ok_or_else(|| {
+                crate::adk_error!(
+                    AuthError,
+                    "No API key provided. Set ANTHROPIC_API_KEY environment variable or use with_api_key()"
+                )
+            })
+    }
+}
+
+/// Text of parts the messages API has no counterpart for; `None` for media
+fn part_as_text(part: &ContentPart) -> Option<String> {
+    match part {
+        ContentPart::Text { text } => Some(text.clone()),
+        ContentPart::ExecutableCode { language, code } => {
+            Some(format!("```{}\n{}\n```", language.to_lowercase(), code))
+        }
+        ContentPart::CodeExecutionResult { output, .. } => Some(output.clone()),
+        _ => None,
+    }
+}
+
+fn convert_stop_reason(reason: &str) -> FinishReason {
+    match reason {
+        "end_turn" | "stop_sequence" => FinishReason::Stop,
+        "max_tokens" => FinishReason::MaxTokens,
+        "tool_use" => FinishReason::FunctionCall,
+        "refusal" => FinishReason::Safety,
+        _ => FinishReason::Other,
+    }
+}
+
+/// Turn a streamed message into ADK responses: a partial response per text
+/// delta, then a final one with the tool calls, finish reason and usage
+fn response_stream<S, E>(bytes: S) -> Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    let mut events = json_event_stream::<AnthropicStreamEvent, _, _>(bytes);
+    Box::pin(stream! {
+        // (block index, name, input JSON) of each tool use
+        let mut tool_uses: Vec<(usize, String, String)> = Vec::new();
+        let mut finish_reason = None;
+        let mut usage = AnthropicUsage::default();
+
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            match event {
+                AnthropicStreamEvent::MessageStart { message } => {
+                    if let Some(start_usage) = message.usage {
+                        usage = start_usage;
+                    }
+                }
+                AnthropicStreamEvent::ContentBlockStart { index, content_block } => match content_block {
+                    AnthropicResponseBlock::Text { text } if !text.is_empty() => {
+                        yield Ok(LlmResponse::partial_text(text));
+                    }
+                    AnthropicResponseBlock::ToolUse { name, .. } => tool_uses.push((index, name, String::new())),
+                    _ => {}
+                },
+                AnthropicStreamEvent::ContentBlockDelta { index, delta } => match delta {
+                    AnthropicDelta::TextDelta { text } if !text.is_empty() => {
+                        yield Ok(LlmResponse::partial_text(text));
+                    }
+                    AnthropicDelta::InputJsonDelta { partial_json } => {
+                        if let Some((_, _, input)) = tool_uses.iter_mut().find(|(block, _, _)| *block == index) {
+                            input.push_str(&partial_json);
+                        }
+                    }
+                    _ => {}
+                },
+                AnthropicStreamEvent::MessageDelta { delta, usage: delta_usage } => {
+                    if let Some(reason) = delta.stop_reason {
+                        finish_reason = Some(convert_stop_reason(&reason));
+                    }
+                    // Output tokens are reported cumulatively
+                    if let Some(output_tokens) = delta_usage.and_then(|delta_usage| delta_usage.output_tokens) {
+                        usage.output_tokens = Some(output_tokens);
+                    }
+                }
+                AnthropicStreamEvent::MessageStop => break,
+                AnthropicStreamEvent::Error { error } => {
+                    yield Err(crate::adk_error!(ModelError, "Anthropic stream error: {} - {}", error.kind, error.message));
+                    return;
+                }
+                AnthropicStreamEvent::Other => {}
+            }
+        }
+
+        let mut response = LlmResponse::new();
+        for (_, name, input) in tool_uses {
+            let args = if input.trim().is_empty() {
+                Ok(json!({}))
+            } else {
+                serde_json::from_str(&input)
+                    .map_err(|e| crate::adk_error!(ModelError, "Anthropic returned invalid input for '{}': {}", name, e))
+            };
+            match args {
+                Ok(args) => response.function_calls.push(FunctionCall { name, args }),
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+        response.finish_reason = finish_reason;
+        response.usage = Some(Usage::from(usage));
+        yield Ok(response);
+    })
+}
+
+#[async_trait]
+impl BaseLlm for AnthropicLlm {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn supported_models() -> Vec<String> {
+        vec![r"claude-.*".to_string()]
+    }
+
+    async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+        debug!("Generating content with Anthropic for model: {}", self.model);
+
+        let anthropic_request = self.convert_request(&request, false)?;
+
+        let _in_flight = InFlightRequest::start();
+        let response = self.post(&anthropic_request).await?;
+
+        let anthropic_response: AnthropicResponse = response.json().await?;
+        let llm_response = self.convert_response(anthropic_response)?;
+
+        info!("Successfully generated content with Anthropic");
+        Ok(llm_response)
+    }
+
+    async fn generate_content_stream(
+        &self,
+        request: LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send + 'static>>> {
+        debug!("Streaming content with Anthropic for model: {}", self.model);
+
+        let anthropic_request = self.convert_request(&request, true)?;
+
+        // Counted as in flight until the stream is finished or dropped
+        let in_flight = InFlightRequest::start();
+        let response = self.post(&anthropic_request).await?;
+        let mut responses = response_stream(response.bytes_stream());
+
+        Ok(Box::pin(stream! {
+            let _in_flight = in_flight;
+            while let Some(response) = responses.next().await {
+                yield response;
+            }
+        }))
+    }
+
+    fn supports_multimodal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FunctionDeclaration, Tool};
+
+    #[test]
+    fn test_tool_turns_become_paired_tool_blocks() {
+        let llm = AnthropicLlm::new("claude-sonnet-4");
+        let mut request = LlmRequest::new("claude-sonnet-4")
+            .add_content(Content {
+                role: "system".to_string(),
+                parts: vec![ContentPart::text("Be brief.")],
+            })
+            .add_user_message("Where is order 7?")
+            .add_content(Content::function_call(FunctionCall {
+                name: "lookup".to_string(),
+                args: json!({ "id": 7 }),
+            }))
+            .add_content(Content::function_response("lookup", json!({ "status": "shipped" })))
+            .add_user_message("Thanks!");
+        request.config.tools.push(Tool {
+            function_declarations: vec![FunctionDeclaration {
+                name: "lookup".to_string(),
+                description: "Look up an order".to_string(),
+                parameters: json!({ "type": "object" }),
+            }],
+        });
+
+        let body = serde_json::to_value(llm.convert_request(&request, false).unwrap()).unwrap();
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][0]["input"], json!({ "id": 7 }));
+        // The tool result and the next message are merged into one user turn
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], messages[1]["content"][0]["id"]);
+        assert_eq!(messages[2]["content"][1], json!({ "type": "text", "text": "Thanks!" }));
+        assert_eq!(body["tools"][0]["input_schema"], json!({ "type": "object" }));
+    }
+
+    #[test]
+    fn test_response_with_tool_use() {
+        let llm = AnthropicLlm::new("claude-sonnet-4");
+        let response: AnthropicResponse = serde_json::from_value(json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                { "type": "thinking", "thinking": "..." },
+                { "type": "text", "text": "Let me check." },
+                { "type": "tool_use", "id": "toolu_1", "name": "lookup", "input": { "id": 7 } }
+            ],
+            "stop_reason": "tool_use",
+            "usage": { "input_tokens": 20, "output_tokens": 8 }
+        }))
+        .unwrap();
+
+        let response = llm.convert_response(response).unwrap();
+        assert_eq!(response.get_text().as_deref(), Some("Let me check."));
+        assert_eq!(response.function_calls[0].args, json!({ "id": 7 }));
+        assert!(matches!(response.finish_reason, Some(FinishReason::FunctionCall)));
+        assert_eq!(response.usage.unwrap().total_tokens, Some(28));
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_text_deltas_then_assembled_tool_use() {
+        let body = [
+            r#"{"type":"message_start","message":{"id":"msg_1","usage":{"input_tokens":20,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"ping"}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Let me "}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"check."}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"lookup","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"id\""}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":": 7}"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":15}}"#,
+            r#"{"type":"message_stop"}"#,
+        ]
+        .iter()
+        .map(|data| Ok::<_, std::convert::Infallible>(Bytes::from(format!("event: x\ndata: {}\n\n", data))))
+        .collect::<Vec<_>>();
+
+        let responses: Vec<LlmResponse> = response_stream(futures::stream::iter(body))
+            .map(|response| response.unwrap())
+            .collect()
+            .await;
+        let texts: Vec<Option<String>> = responses.iter().map(|response| response.get_text()).collect();
+        assert_eq!(texts, [Some("Let me ".to_string()), Some("check.".to_string()), None]);
+
+        let last = &responses[2];
+        assert!(!last.is_partial);
+        assert_eq!(last.function_calls[0].args, json!({ "id": 7 }));
+        assert!(matches!(last.finish_reason, Some(FinishReason::FunctionCall)));
+        let usage = last.usage.as_ref().unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.total_tokens), (Some(20), Some(15), Some(35)));
+    }
+}
@@ -0,0 +1,274 @@
+//! Azure OpenAI LLM implementation
+//!
+//! [`AzureOpenAiLlm`] sends chat completions to an Azure OpenAI deployment
+//! (`{endpoint}/openai/deployments/{deployment}/chat/completions`) with an
+//! `api-version` query parameter. Requests and responses share the OpenAI
+//! format, so conversion is done by [`OpenAiLlm`]. Authentication is an
+//! API key or an Azure AD (Entra ID) token.
+
+use crate::{
+    error::Result,
+    models::{
+        base_llm::LlmConfig,
+        http_client::InFlightRequest,
+        openai_llm::{response_stream, OpenAiRequest, OpenAiResponse},
+        BaseLlm, HttpClientConfig, LlmRequest, LlmResponse, OpenAiLlm,
+    },
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use reqwest::Client;
+use std::{pin::Pin, sync::Arc};
+use tracing::{debug, error, info};
+
+/// API version used unless configured otherwise
+pub const AZURE_OPENAI_API_VERSION: &str = "2024-10-21";
+
+/// Prefix of registered Azure OpenAI model names, followed by the deployment name
+pub const AZURE_OPENAI_MODEL_PREFIX: &str = "azure/";
+
+/// Source of Azure AD access tokens, e.g. a managed identity client. Called
+/// for every request, so implementations should cache tokens until they expire.
+#[async_trait]
+pub trait AzureAdTokenProvider: Send + Sync {
+    /// A token for the `https://cognitiveservices.azure.com/.default` scope
+    async fn token(&self) -> Result<String>;
+}
+
+/// A fixed token, e.g. one obtained with `az account get-access-token`
+#[async_trait]
+impl AzureAdTokenProvider for String {
+    async fn token(&self) -> Result<String> {
+        Ok(self.clone())
+    }
+}
+
+#[derive(Clone)]
+enum AzureAuth {
+    ApiKey(String),
+    AdToken(Arc<dyn AzureAdTokenProvider>),
+}
+
+impl std::fmt::Debug for AzureAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ApiKey(_) => f.write_str("ApiKey(..)"),
+            Self::AdToken(_) => f.write_str("AdToken(..)"),
+        }
+    }
+}
+
+/// Azure OpenAI LLM implementation
+#[derive(Debug, Clone)]
+pub struct AzureOpenAiLlm {
+    /// Converts requests and responses; its model is the deployment name
+    inner: OpenAiLlm,
+    endpoint: String,
+    api_version: String,
+    auth: Option<AzureAuth>,
+}
+
+impl AzureOpenAiLlm {
+    /// Create an instance for a deployment of an Azure OpenAI resource,
+    /// e.g. `https://my-resource.openai.azure.com`
+    pub fn new(endpoint: impl Into<String>, deployment: impl Into<String>) -> Self {
+        Self {
+            inner: OpenAiLlm::new(deployment),
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            api_version: AZURE_OPENAI_API_VERSION.to_string(),
+            auth: None,
+        }
+    }
+
+    /// Create an Azure OpenAI instance from an `LlmConfig`; the model is the deployment name
+    pub fn from_config(config: LlmConfig) -> Result<Self> {
+        let endpoint = config.endpoint.clone().ok_or_else(|| {
+            crate::adk_error!(ConfigError, "Azure OpenAI requires the resource endpoint")
+        })?;
+        let mut http_client = config.http_client;
+        if http_client.timeout_seconds.is_none() {
+            http_client.timeout_seconds = config.timeout_seconds;
+        }
+
+        let mut llm = Self::new(endpoint, config.model).with_http_client_config(&http_client)?;
+        llm.auth = config.api_key.map(AzureAuth::ApiKey);
+        Ok(llm)
+    }
+
+    /// Use a caller-provided HTTP client
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.inner = self.inner.with_client(client);
+        self
+    }
+
+    /// Use the shared HTTP client for the given connection settings (proxy, CA bundle, timeouts, pooling)
+    pub fn with_http_client_config(mut self, config: &HttpClientConfig) -> Result<Self> {
+        self.inner = self.inner.with_http_client_config(config)?;
+        Ok(self)
+    }
+
+    /// Set the `api-version` query parameter
+    pub fn with_api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.api_version = api_version.into();
+        self
+    }
+
+    /// Authenticate with a resource API key
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.auth = Some(AzureAuth::ApiKey(api_key.into()));
+        self
+    }
+
+    /// Authenticate with a fixed Azure AD token
+    pub fn with_azure_ad_token(self, token: impl Into<String>) -> Self {
+        self.with_azure_ad_token_provider(Arc::new(token.into()))
+    }
+
+    /// Authenticate with Azure AD tokens from a provider
+    pub fn with_azure_ad_token_provider(mut self, provider: Arc<dyn AzureAdTokenProvider>) -> Self {
+        self.auth = Some(AzureAuth::AdToken(provider));
+        self
+    }
+
+    /// Chat completions URL of the deployment, without the `api-version` parameter
+    fn chat_completions_url(&self) -> String {
+        format!(
+            "{}/openai/deployments/{}/chat/completions",
+            self.endpoint,
+            self.inner.model_name()
+        )
+    }
+
+    /// Get the authentication header; falls back to `AZURE_OPENAI_API_KEY`, then `AZURE_OPENAI_AD_TOKEN`
+    async fn get_auth_header(&self) -> Result<(&'static str, String)> {
+        let auth = match &self.auth {
+            Some(auth) => auth.clone(),
+            None => match (std::env::var("AZURE_OPENAI_API_KEY"), std::env::var("AZURE_OPENAI_AD_TOKEN")) {
+                (Ok(api_key), _) => AzureAuth::ApiKey(api_key),
+                (_, Ok(token)) => AzureAuth::AdToken(Arc::new(token)),
+                _ => {
+                    return Err(crate::adk_error!(
+                        AuthError,
+                        "No credentials provided. Set AZURE_OPENAI_API_KEY or AZURE_OPENAI_AD_TOKEN, or use with_api_key() or with_azure_ad_token_provider()"
+                    ))
+                }
+            },
+        };
+        Ok(match auth {
+            AzureAuth::ApiKey(api_key) => ("api-key", api_key),
+            AzureAuth::AdToken(provider) => ("Authorization", format!("Bearer {}", provider.token().await?)),
+        })
+    }
+
+    /// Send a request, turning error statuses into `ModelError`s
+    async fn post(&self, request: &OpenAiRequest) -> Result<reqwest::Response> {
+        let (auth_header, auth_value) = self.get_auth_header().await?;
+        let response = self
+            .inner
+            .http_client()
+            .post(self.chat_completions_url())
+            .query(&[("api-version", &self.api_version)])
+            .header(auth_header, auth_value)
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!("Azure OpenAI API error: {} - {}", status, error_text);
+            return Err(crate::adk_error!(
+                ModelError,
+                "Azure OpenAI API error: {} - {}",
+                status,
+                error_text
+            ));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl BaseLlm for AzureOpenAiLlm {
+    /// The deployment name
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn supported_models() -> Vec<String> {
+        vec![r"azure/.*".to_string()]
+    }
+
+    async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+        debug!("Generating content with Azure OpenAI for deployment: {}", self.model_name());
+
+        let openai_request = self.inner.convert_request(&request, false)?;
+
+        let _in_flight = InFlightRequest::start();
+        let response = self.post(&openai_request).await?;
+
+        let openai_response: OpenAiResponse = response.json().await?;
+        let llm_response = self.inner.convert_response(openai_response)?;
+
+        info!("Successfully generated content with Azure OpenAI");
+        Ok(llm_response)
+    }
+
+    async fn generate_content_stream(
+        &self,
+        request: LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send + 'static>>> {
+        debug!("Streaming content with Azure OpenAI for deployment: {}", self.model_name());
+
+        let openai_request = self.inner.convert_request(&request, true)?;
+
+        // Counted as in flight until the stream is finished or dropped
+        let in_flight = InFlightRequest::start();
+        let response = self.post(&openai_request).await?;
+        let mut responses = response_stream(response.bytes_stream());
+
+        Ok(Box::pin(stream! {
+            let _in_flight = in_flight;
+            while let Some(response) = responses.next().await {
+                yield response;
+            }
+        }))
+    }
+
+    fn supports_multimodal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountingProvider(std::sync::atomic::AtomicUsize);
+
+    #[async_trait]
+    impl AzureAdTokenProvider for CountingProvider {
+        async fn token(&self) -> Result<String> {
+            let n = self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(format!("token-{}", n))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deployment_url_and_auth_headers() {
+        let llm = AzureOpenAiLlm::new("https://contoso.openai.azure.com/", "gpt-4o-prod").with_api_key("k1");
+        assert_eq!(llm.model_name(), "gpt-4o-prod");
+        assert_eq!(
+            llm.chat_completions_url(),
+            "https://contoso.openai.azure.com/openai/deployments/gpt-4o-prod/chat/completions"
+        );
+        assert_eq!(llm.get_auth_header().await.unwrap(), ("api-key", "k1".to_string()));
+
+        // Tokens are requested for every call, so expiring ones get refreshed
+        let llm = llm.with_azure_ad_token_provider(Arc::new(CountingProvider(Default::default())));
+        assert_eq!(llm.get_auth_header().await.unwrap().1, "Bearer token-0");
+        assert_eq!(llm.get_auth_header().await.unwrap(), ("Authorization", "Bearer token-1".to_string()));
+    }
+}
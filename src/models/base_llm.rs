@@ -9,7 +9,7 @@ use futures::Stream;
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
 
-use super::{LlmRequest, LlmResponse};
+use super::{estimate_request_tokens, HttpClientConfig, LlmRequest, LlmResponse, RetryPolicy};
 
 /// Base trait for all LLM implementations
 #[async_trait]
@@ -31,6 +31,12 @@ pub trait BaseLlm: Send + Sync {
         request: LlmRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>>;
 
+    /// Count the prompt tokens of a request before sending it; models
+    /// without a token counting API use the local estimate
+    async fn count_tokens(&self, request: &LlmRequest) -> Result<u32> {
+        Ok(estimate_request_tokens(request))
+    }
+
     /// Check if the model supports streaming
     fn supports_streaming(&self) -> bool {
         true
@@ -74,6 +80,16 @@ pub trait LlmConnection: Send + Sync {
     /// Send realtime data (audio/video) to the model
     async fn send_realtime(&mut self, blob: Blob) -> Result<()>;
 
+    /// Tell the model the user started speaking; connections doing their own detection ignore it
+    async fn send_activity_start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Tell the model the user stopped speaking and expects a response
+    async fn send_activity_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     /// Receive responses from the model
     async fn receive(&mut self) -> Result<Option<LlmResponse>>;
 
@@ -120,6 +136,14 @@ pub struct LlmConfig {
     /// Request timeout in seconds
     pub timeout_seconds: Option<u64>,
 
+    /// HTTP client settings (proxy, CA bundle, connection pool)
+    #[serde(default)]
+    pub http_client: HttpClientConfig,
+
+    /// Retries of rate-limited and failed requests
+    #[serde(default)]
+    pub retry: RetryPolicy,
+
     /// Additional model-specific parameters
     pub additional_params: serde_json::Value,
 }
@@ -138,6 +162,8 @@ impl Default for LlmConfig {
             max_output_tokens: None,
             stop_sequences: Vec::new(),
             timeout_seconds: Some(30),
+            http_client: HttpClientConfig::default(),
+            retry: RetryPolicy::default(),
             additional_params: serde_json::Value::Null,
         }
     }
@@ -180,6 +206,16 @@ impl LlmConfig {
         self.timeout_seconds = Some(timeout_seconds);
         self
     }
+
+    pub fn with_http_client_config(mut self, http_client: HttpClientConfig) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
 }
 
 /// Helper trait for model builders
@@ -0,0 +1,947 @@
+//! AWS Bedrock LLM implementation
+//!
+//! [`BedrockLlm`] uses the Bedrock Converse API, which has one request
+//! format for the Anthropic, Meta and other models hosted on Bedrock.
+//! Registered as `bedrock/<model id>`, e.g.
+//! `bedrock/anthropic.claude-3-5-sonnet-20240620-v1:0`. Requests are signed
+//! with AWS Signature Version 4; streamed responses use the AWS event
+//! stream encoding.
+
+use crate::{
+    error::Result,
+    models::{
+        base_llm::LlmConfig, http_client::InFlightRequest, BaseLlm, FinishReason, HttpClientConfig, LlmRequest,
+        LlmResponse, Usage,
+    },
+    types::{Content, ContentPart, FunctionCall, FunctionCallingMode},
+    utils::base64_bytes::Base64,
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use bytes::{Buf, Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, pin::Pin};
+use tracing::{debug, error, info};
+
+/// Prefix of registered Bedrock model names, followed by the model id
+pub const BEDROCK_MODEL_PREFIX: &str = "bedrock/";
+
+/// Service name requests are signed for
+const SIGNING_SERVICE: &str = "bedrock";
+
+/// AWS credentials requests are signed with
+#[derive(Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Set for temporary credentials, e.g. of an assumed role
+    pub session_token: Option<String>,
+}
+
+impl std::fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AwsCredentials {
+    pub fn new(access_key_id: impl Into<String>, secret_access_key: impl Into<String>) -> Self {
+        Self {
+            access_key_id: access_key_id.into(),
+            secret_access_key: secret_access_key.into(),
+            session_token: None,
+        }
+    }
+
+    pub fn with_session_token(mut self, session_token: impl Into<String>) -> Self {
+        self.session_token = Some(session_token.into());
+        self
+    }
+
+    /// Read `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+    pub fn from_env() -> Result<Self> {
+        match (std::env::var("AWS_ACCESS_KEY_ID"), std::env::var("AWS_SECRET_ACCESS_KEY")) {
+            (Ok(access_key_id), Ok(secret_access_key)) => Ok(Self {
+                access_key_id,
+                secret_access_key,
+                session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+            }),
+            _ => Err(crate::adk_error!(
+                AuthError,
+                "No AWS credentials provided. Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY or use with_credentials()"
+            )),
+        }
+    }
+}
+
+/// AWS Bedrock LLM implementation
+#[derive(Debug, Clone)]
+pub struct BedrockLlm {
+    model: String,
+    region: String,
+    credentials: Option<AwsCredentials>,
+    client: Client,
+    base_url: String,
+}
+
+/// Converse API request format
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseRequest {
+    messages: Vec<ConverseMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    system: Vec<ConverseBlock>,
+    inference_config: ConverseInferenceConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<ConverseToolConfig>,
+    /// Model-specific parameters Converse has no field for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    additional_model_request_fields: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct ConverseMessage {
+    role: &'static str,
+    content: Vec<ConverseBlock>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum ConverseBlock {
+    Text(String),
+    Image(ConverseImage),
+    ToolUse(ConverseToolUse),
+    ToolResult(ConverseToolResult),
+}
+
+#[derive(Debug, Serialize)]
+struct ConverseImage {
+    /// `png`, `jpeg`, `gif` or `webp`
+    format: String,
+    source: ConverseImageSource,
+}
+
+#[derive(Debug, Serialize)]
+struct ConverseImageSource {
+    bytes: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseToolUse {
+    tool_use_id: String,
+    name: String,
+    input: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseToolResult {
+    tool_use_id: String,
+    content: Vec<Value>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseInferenceConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseToolConfig {
+    tools: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<Value>,
+}
+
+/// Converse API response format
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseResponse {
+    output: ConverseOutput,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: Option<ConverseUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConverseOutput {
+    #[serde(default)]
+    message: Option<ConverseResponseMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConverseResponseMessage {
+    #[serde(default)]
+    content: Vec<ConverseResponseBlock>,
+}
+
+/// A response content block; reasoning and other blocks have neither field
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseResponseBlock {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    tool_use: Option<ConverseResponseToolUse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConverseResponseToolUse {
+    name: String,
+    #[serde(default)]
+    input: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseUsage {
+    #[serde(default)]
+    input_tokens: Option<u32>,
+    #[serde(default)]
+    output_tokens: Option<u32>,
+    #[serde(default)]
+    total_tokens: Option<u32>,
+}
+
+impl From<ConverseUsage> for Usage {
+    fn from(usage: ConverseUsage) -> Self {
+        Self {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: usage.total_tokens,
+        }
+    }
+}
+
+/// Payload of a stream event; which fields are set depends on the event type
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseStreamPayload {
+    #[serde(default)]
+    content_block_index: usize,
+    /// `contentBlockStart`
+    #[serde(default)]
+    start: Option<ConverseBlockStart>,
+    /// `contentBlockDelta`
+    #[serde(default)]
+    delta: Option<ConverseBlockDelta>,
+    /// `messageStop`
+    #[serde(default)]
+    stop_reason: Option<String>,
+    /// `metadata`
+    #[serde(default)]
+    usage: Option<ConverseUsage>,
+    /// Exceptions
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseBlockStart {
+    #[serde(default)]
+    tool_use: Option<ConverseToolUseStart>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConverseToolUseStart {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConverseBlockDelta {
+    #[serde(default)]
+    text: Option<String>,
+    /// Fragment of a tool use block's input JSON
+    #[serde(default)]
+    tool_use: Option<ConverseToolUseDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConverseToolUseDelta {
+    #[serde(default)]
+    input: String,
+}
+
+impl BedrockLlm {
+    /// Create a Bedrock LLM instance for a model id in a region; a `bedrock/`
+    /// prefix is removed from the model id
+    pub fn new(model: impl Into<String>, region: impl Into<String>) -> Self {
+        let client = HttpClientConfig::default()
+            .shared_client()
+            .expect("Failed to create HTTP client");
+        let model = model.into();
+        let region = region.into();
+
+        Self {
+            model: model.strip_prefix(BEDROCK_MODEL_PREFIX).unwrap_or(&model).to_string(),
+            base_url: format!("https://bedrock-runtime.{}.amazonaws.com", region),
+            region,
+            credentials: None,
+            client,
+        }
+    }
+
+    /// Create a Bedrock LLM instance from an `LlmConfig`; credentials come from the environment
+    pub fn from_config(config: LlmConfig) -> Result<Self> {
+        let region = config.region.clone().ok_or_else(|| {
+            crate::adk_error!(ConfigError, "Bedrock requires an AWS region")
+        })?;
+        let mut http_client = config.http_client;
+        if http_client.timeout_seconds.is_none() {
+            http_client.timeout_seconds = config.timeout_seconds;
+        }
+
+        let mut llm = Self::new(config.model, region).with_http_client_config(&http_client)?;
+        if let Some(endpoint) = config.endpoint {
+            llm = llm.with_base_url(endpoint);
+        }
+        Ok(llm)
+    }
+
+    /// Use a caller-provided HTTP client
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Use the shared HTTP client for the given connection settings (proxy, CA bundle, timeouts, pooling)
+    pub fn with_http_client_config(mut self, config: &HttpClientConfig) -> Result<Self> {
+        self.client = config.shared_client()?;
+        Ok(self)
+    }
+
+    /// Send requests to another Bedrock runtime endpoint, e.g. a VPC endpoint
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sign requests with these credentials instead of the environment's
+    pub fn with_credentials(mut self, credentials: AwsCredentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Convert ADK request to Converse format
+    fn convert_request(&self, request: &LlmRequest) -> Result<ConverseRequest> {
+        let mut system = Vec::new();
+        let mut messages: Vec<ConverseMessage> = Vec::new();
+        // Ids of tool uses awaiting their result, in call order
+        let mut pending_calls: Vec<(String, String)> = Vec::new();
+        let mut next_call_id = 0;
+
+        for content in request.all_contents() {
+            if content.role == "system" {
+                system.extend(content.parts.iter().filter_map(part_as_text).map(ConverseBlock::Text));
+                continue;
+            }
+            let role = if content.role == "model" { "assistant" } else { "user" };
+
+            let mut blocks = Vec::new();
+            for part in &content.parts {
+                let block = match part {
+                    ContentPart::FunctionCall { name, args } => {
+                        let id = format!("tooluse_{}", next_call_id);
+                        next_call_id += 1;
+                        pending_calls.push((name.clone(), id.clone()));
+                        ConverseBlock::ToolUse(ConverseToolUse {
+                            tool_use_id: id,
+                            name: name.clone(),
+                            input: args.clone(),
+                        })
+                    }
+                    ContentPart::FunctionResponse { name, response } => {
+                        let tool_use_id = match pending_calls.iter().position(|(call, _)| call == name) {
+                            Some(index) => pending_calls.remove(index).1,
+                            None => {
+                                next_call_id += 1;
+                                format!("tooluse_{}", next_call_id - 1)
+                            }
+                        };
+                        // JSON results must be objects
+                        let content = if response.is_object() {
+                            json!({ "json": response })
+                        } else {
+                            json!({ "text": response.to_string() })
+                        };
+                        ConverseBlock::ToolResult(ConverseToolResult {
+                            tool_use_id,
+                            content: vec![content],
+                        })
+                    }
+                    ContentPart::Image { data, mime_type } => ConverseBlock::Image(ConverseImage {
+                        format: mime_type.trim_start_matches("image/").to_string(),
+                        source: ConverseImageSource {
+                            bytes: Base64(data).to_string(),
+                        },
+                    }),
+                    part => match part_as_text(part) {
+                        Some(text) => ConverseBlock::Text(text),
+                        None => {
+                            return Err(crate::adk_error!(
+                                ModelError,
+                                "The Bedrock Converse API does not support this content part: {:?}",
+                                part
+                            ))
+                        }
+                    },
+                };
+                blocks.push(block);
+            }
+            if blocks.is_empty() {
+                continue;
+            }
+
+            // Roles must alternate, e.g. a tool result followed by a user message
+            match messages.last_mut() {
+                Some(last) if last.role == role => last.content.extend(blocks),
+                _ => messages.push(ConverseMessage { role, content: blocks }),
+            }
+        }
+
+        let tools: Vec<Value> = request
+            .config
+            .tools
+            .iter()
+            .flat_map(|tool| &tool.function_declarations)
+            .map(|decl| {
+                json!({
+                    "toolSpec": {
+                        "name": decl.name,
+                        "description": decl.description,
+                        "inputSchema": { "json": decl.parameters },
+                    }
+                })
+            })
+            .collect();
+        let tool_choice = request.config.tool_config.as_ref().and_then(|config| {
+            match (config.function_calling_mode, config.allowed_function_names.as_slice()) {
+                (FunctionCallingMode::Any, [name]) => Some(json!({ "tool": { "name": name } })),
+                (FunctionCallingMode::Any, _) => Some(json!({ "any": {} })),
+                (FunctionCallingMode::Auto, _) => Some(json!({ "auto": {} })),
+                // Converse has no choice disabling tools; they are not sent instead
+                (FunctionCallingMode::None, _) => None,
+            }
+        });
+        let tools_disabled = request
+            .config
+            .tool_config
+            .as_ref()
+            .is_some_and(|config| matches!(config.function_calling_mode, FunctionCallingMode::None));
+        let tool_config = (!tools.is_empty() && !tools_disabled).then_some(ConverseToolConfig { tools, tool_choice });
+
+        Ok(ConverseRequest {
+            messages,
+            system,
+            inference_config: ConverseInferenceConfig {
+                max_tokens: request.config.max_output_tokens,
+                temperature: request.config.temperature,
+                top_p: request.config.top_p,
+                stop_sequences: request.config.stop_sequences.clone(),
+            },
+            tool_config,
+            additional_model_request_fields: request.config.top_k.map(|top_k| json!({ "top_k": top_k })),
+        })
+    }
+
+    /// Convert Converse response to ADK format
+    fn convert_response(&self, response: ConverseResponse) -> Result<LlmResponse> {
+        let mut llm_response = LlmResponse::new();
+        let mut text = String::new();
+
+        for block in response.output.message.map(|message| message.content).unwrap_or_default() {
+            if let Some(block_text) = block.text {
+                text.push_str(&block_text);
+            }
+            if let Some(tool_use) = block.tool_use {
+                llm_response.function_calls.push(FunctionCall {
+                    name: tool_use.name,
+                    args: if tool_use.input.is_null() { json!({}) } else { tool_use.input },
+                });
+            }
+        }
+        if !text.is_empty() {
+            llm_response.content = Some(Content::model_text(text));
+        }
+        llm_response.finish_reason = response.stop_reason.as_deref().map(convert_stop_reason);
+        llm_response.usage = response.usage.map(Usage::from);
+
+        Ok(llm_response)
+    }
+
+    /// Sign and send a request to a Converse operation (`converse` or `converse-stream`)
+    async fn post(&self, operation: &str, request: &ConverseRequest) -> Result<reqwest::Response> {
+        let credentials = match &self.credentials {
+            Some(credentials) => credentials.clone(),
+            None => AwsCredentials::from_env()?,
+        };
+        let url = format!("{}/model/{}/{}", self.base_url, uri_encode(&self.model), operation);
+        let url = url::Url::parse(&url)
+            .map_err(|e| crate::adk_error!(ConfigError, "Invalid Bedrock URL '{}': {}", url, e))?;
+        let body = serde_json::to_vec(request)?;
+
+        let mut builder = self.client.post(url.clone()).header("Content-Type", "application/json");
+        for (name, value) in sign_request("POST", &url, &body, &credentials, &self.region, SIGNING_SERVICE, Utc::now()) {
+            builder = builder.header(name, value);
+        }
+        let response = builder.body(body).send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!("Bedrock API error: {} - {}", status, error_text);
+            return Err(crate::adk_error!(
+                ModelError,
+                "Bedrock API error: {} - {}",
+                status,
+                error_text
+            ));
+        }
+        Ok(response)
+    }
+}
+
+/// Text of parts the Converse API has no counterpart for; `None` for media
+fn part_as_text(part: &ContentPart) -> Option<String> {
+    match part {
+        ContentPart::Text { text } => Some(text.clone()),
+        ContentPart::ExecutableCode { language, code } => {
+            Some(format!("```{}\n{}\n```", language.to_lowercase(), code))
+        }
+        ContentPart::CodeExecutionResult { output, .. } => Some(output.clone()),
+        _ => None,
+    }
+}
+
+fn convert_stop_reason(reason: &str) -> FinishReason {
+    match reason {
+        "end_turn" | "stop_sequence" => FinishReason::Stop,
+        "max_tokens" => FinishReason::MaxTokens,
+        "tool_use" => FinishReason::FunctionCall,
+        "guardrail_intervened" | "content_filtered" => FinishReason::Safety,
+        _ => FinishReason::Other,
+    }
+}
+
+/// Percent-encode everything but unreserved characters, as SigV4 requires
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// AWS Signature Version 4 headers (`x-amz-date`, `x-amz-security-token`,
+/// `authorization`) for a request; `host` is signed as reqwest sends it
+fn sign_request(
+    method: &str,
+    url: &url::Url,
+    payload: &[u8],
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let mut headers = vec![("host", host), ("x-amz-date", amz_date.clone())];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+
+    // Services other than S3 encode the already encoded path once more
+    let canonical_uri = url.path().split('/').map(uri_encode).collect::<Vec<_>>().join("/");
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (uri_encode(&name), uri_encode(&value)))
+        .collect();
+    query.sort();
+    let canonical_query = query
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        canonical_uri,
+        canonical_query,
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(payload))
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = hmac_sha256(format!("AWS4{}", credentials.secret_access_key).as_bytes(), &date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    let key = hmac_sha256(&key, "aws4_request");
+    let signature = hex(&hmac_sha256(&key, &string_to_sign));
+
+    headers.remove(0);
+    headers.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    headers
+}
+
+/// One message of an AWS event stream
+#[derive(Debug)]
+struct EventStreamMessage {
+    /// String-valued headers, e.g. `:event-type`
+    headers: HashMap<String, String>,
+    payload: Bytes,
+}
+
+/// Decode one message from the front of `buffer`, if it holds a complete one.
+/// Layout: total length, headers length, prelude CRC, headers, payload, message CRC.
+fn decode_event_message(buffer: &mut BytesMut) -> Result<Option<EventStreamMessage>> {
+    if buffer.len() < 12 {
+        return Ok(None);
+    }
+    let total_len = u32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+    let headers_len = u32::from_be_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]) as usize;
+    if total_len < 16 + headers_len {
+        return Err(crate::adk_error!(ModelError, "Malformed event stream message"));
+    }
+    if buffer.len() < total_len {
+        return Ok(None);
+    }
+    let mut message = buffer.split_to(total_len).freeze();
+    message.advance(12);
+    let mut header_bytes = message.split_to(headers_len);
+    let payload = message.split_to(total_len - 16 - headers_len);
+
+    let malformed = || crate::adk_error!(ModelError, "Malformed event stream header");
+    let mut headers = HashMap::new();
+    while header_bytes.has_remaining() {
+        let name_len = header_bytes.get_u8() as usize;
+        if header_bytes.remaining() < name_len + 1 {
+            return Err(malformed());
+        }
+        let name = String::from_utf8_lossy(&header_bytes.split_to(name_len)).into_owned();
+        // Only strings are kept; other types are skipped by their size
+        let value_len = match header_bytes.get_u8() {
+            0 | 1 => 0,
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            9 => 16,
+            6 | 7 if header_bytes.remaining() >= 2 => header_bytes.get_u16() as usize,
+            _ => return Err(malformed()),
+        };
+        if header_bytes.remaining() < value_len {
+            return Err(malformed());
+        }
+        let value = header_bytes.split_to(value_len);
+        headers.insert(name, String::from_utf8_lossy(&value).into_owned());
+    }
+    Ok(Some(EventStreamMessage { headers, payload }))
+}
+
+/// Turn a Converse event stream into ADK responses: a partial response per
+/// text delta, then a final one with the tool calls, finish reason and usage
+fn response_stream<S, E>(bytes: S) -> Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    Box::pin(stream! {
+        let mut bytes = Box::pin(bytes);
+        let mut buffer = BytesMut::new();
+        // (block index, name, input JSON) of each tool use
+        let mut tool_uses: Vec<(usize, String, String)> = Vec::new();
+        let mut finish_reason = None;
+        let mut usage = None;
+
+        while let Some(chunk) = bytes.next().await {
+            match chunk {
+                Ok(chunk) => buffer.extend_from_slice(&chunk),
+                Err(e) => {
+                    yield Err(crate::adk_error!(NetworkError, "Stream transport error: {}", e));
+                    return;
+                }
+            }
+            loop {
+                let message = match decode_event_message(&mut buffer) {
+                    Ok(Some(message)) => message,
+                    Ok(None) => break,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                let payload: ConverseStreamPayload = match serde_json::from_slice(&message.payload) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        yield Err(crate::adk_error!(ModelError, "Malformed stream event: {}", e));
+                        return;
+                    }
+                };
+                let header = |name: &str| message.headers.get(name).map(String::as_str).unwrap_or_default();
+                if header(":message-type") == "exception" {
+                    yield Err(crate::adk_error!(
+                        ModelError,
+                        "Bedrock stream error: {} - {}",
+                        header(":exception-type"),
+                        payload.message.unwrap_or_default()
+                    ));
+                    return;
+                }
+
+                match header(":event-type") {
+                    "contentBlockStart" => {
+                        if let Some(tool_use) = payload.start.and_then(|start| start.tool_use) {
+                            tool_uses.push((payload.content_block_index, tool_use.name, String::new()));
+                        }
+                    }
+                    "contentBlockDelta" => {
+                        let Some(delta) = payload.delta else { continue };
+                        if let Some(text) = delta.text.filter(|text| !text.is_empty()) {
+                            yield Ok(LlmResponse::partial_text(text));
+                        }
+                        if let Some(tool_use) = delta.tool_use {
+                            if let Some((_, _, input)) =
+                                tool_uses.iter_mut().find(|(block, _, _)| *block == payload.content_block_index)
+                            {
+                                input.push_str(&tool_use.input);
+                            }
+                        }
+                    }
+                    "messageStop" => finish_reason = payload.stop_reason.as_deref().map(convert_stop_reason),
+                    "metadata" => usage = payload.usage.map(Usage::from),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut response = LlmResponse::new();
+        for (_, name, input) in tool_uses {
+            let args = if input.trim().is_empty() {
+                Ok(json!({}))
+            } else {
+                serde_json::from_str(&input)
+                    .map_err(|e| crate::adk_error!(ModelError, "Bedrock returned invalid input for '{}': {}", name, e))
+            };
+            match args {
+                Ok(args) => response.function_calls.push(FunctionCall { name, args }),
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+        response.finish_reason = finish_reason;
+        response.usage = usage;
+        yield Ok(response);
+    })
+}
+
+#[async_trait]
+impl BaseLlm for BedrockLlm {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn supported_models() -> Vec<String> {
+        vec![r"bedrock/.*".to_string()]
+    }
+
+    async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+        debug!("Generating content with Bedrock for model: {}", self.model);
+
+        let converse_request = self.convert_request(&request)?;
+
+        let _in_flight = InFlightRequest::start();
+        let response = self.post("converse", &converse_request).await?;
+
+        let converse_response: ConverseResponse = response.json().await?;
+        let llm_response = self.convert_response(converse_response)?;
+
+        info!("Successfully generated content with Bedrock");
+        Ok(llm_response)
+    }
+
+    async fn generate_content_stream(
+        &self,
+        request: LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send + 'static>>> {
+        debug!("Streaming content with Bedrock for model: {}", self.model);
+
+        let converse_request = self.convert_request(&request)?;
+
+        // Counted as in flight until the stream is finished or dropped
+        let in_flight = InFlightRequest::start();
+        let response = self.post("converse-stream", &converse_request).await?;
+        let mut responses = response_stream(response.bytes_stream());
+
+        Ok(Box::pin(stream! {
+            let _in_flight = in_flight;
+            while let Some(response) = responses.next().await {
+                yield response;
+            }
+        }))
+    }
+
+    fn supports_multimodal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BufMut;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sigv4_matches_the_aws_test_suite() {
+        // `get-vanilla` from the AWS Signature Version 4 test suite
+        let credentials = AwsCredentials::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY");
+        let url = url::Url::parse("https://example.amazonaws.com/").unwrap();
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+
+        let headers = sign_request("GET", &url, b"", &credentials, "us-east-1", "service", now);
+        assert_eq!(headers[0], ("x-amz-date", "20150830T123600Z".to_string()));
+        assert_eq!(
+            headers[1].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_converse_request_pairs_tool_turns() {
+        let llm = BedrockLlm::new("bedrock/anthropic.claude-3-5-sonnet-20240620-v1:0", "us-east-1");
+        assert_eq!(llm.model_name(), "anthropic.claude-3-5-sonnet-20240620-v1:0");
+
+        let request = LlmRequest::new("bedrock/anthropic.claude-3-5-sonnet-20240620-v1:0")
+            .add_user_message("Where is order 7?")
+            .add_content(Content::function_call(FunctionCall {
+                name: "lookup".to_string(),
+                args: json!({ "id": 7 }),
+            }))
+            .add_content(Content::function_response("lookup", json!({ "status": "shipped" })))
+            .with_max_tokens(256);
+
+        let body = serde_json::to_value(llm.convert_request(&request).unwrap()).unwrap();
+        let messages = &body["messages"];
+        assert_eq!(messages[0], json!({ "role": "user", "content": [{ "text": "Where is order 7?" }] }));
+        assert_eq!(messages[1]["content"][0]["toolUse"]["input"], json!({ "id": 7 }));
+        assert_eq!(
+            messages[2]["content"][0]["toolResult"],
+            json!({ "toolUseId": "tooluse_0", "content": [{ "json": { "status": "shipped" } }] })
+        );
+        assert_eq!(body["inferenceConfig"], json!({ "maxTokens": 256 }));
+    }
+
+    fn event(event_type: &str, payload: Value) -> Bytes {
+        let mut headers = BytesMut::new();
+        for (name, value) in [(":message-type", "event"), (":event-type", event_type)] {
+            headers.put_u8(name.len() as u8);
+            headers.put_slice(name.as_bytes());
+            headers.put_u8(7);
+            headers.put_u16(value.len() as u16);
+            headers.put_slice(value.as_bytes());
+        }
+        let payload = payload.to_string();
+        let mut message = BytesMut::new();
+        message.put_u32((16 + headers.len() + payload.len()) as u32);
+        message.put_u32(headers.len() as u32);
+        message.put_u32(0);
+        message.put_slice(&headers);
+        message.put_slice(payload.as_bytes());
+        message.put_u32(0);
+        message.freeze()
+    }
+
+    #[tokio::test]
+    async fn test_stream_decodes_events_split_across_chunks() {
+        let mut body = BytesMut::new();
+        for message in [
+            event("messageStart", json!({ "role": "assistant" })),
+            event("contentBlockDelta", json!({ "contentBlockIndex": 0, "delta": { "text": "Let me check." } })),
+            event(
+                "contentBlockStart",
+                json!({ "contentBlockIndex": 1, "start": { "toolUse": { "toolUseId": "t1", "name": "lookup" } } }),
+            ),
+            event("contentBlockDelta", json!({ "contentBlockIndex": 1, "delta": { "toolUse": { "input": "{\"id\"" } } })),
+            event("contentBlockDelta", json!({ "contentBlockIndex": 1, "delta": { "toolUse": { "input": ": 7}" } } })),
+            event("messageStop", json!({ "stopReason": "tool_use" })),
+            event("metadata", json!({ "usage": { "inputTokens": 20, "outputTokens": 9, "totalTokens": 29 } })),
+        ] {
+            body.extend_from_slice(&message);
+        }
+        let body = body.freeze();
+        let chunks: Vec<_> = body
+            .chunks(7)
+            .map(|chunk| Ok::<_, std::convert::Infallible>(Bytes::copy_from_slice(chunk)))
+            .collect();
+
+        let responses: Vec<LlmResponse> = response_stream(futures::stream::iter(chunks))
+            .map(|response| response.unwrap())
+            .collect()
+            .await;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].get_text().as_deref(), Some("Let me check."));
+        assert!(responses[0].is_partial);
+
+        let last = &responses[1];
+        assert_eq!(last.function_calls[0].args, json!({ "id": 7 }));
+        assert!(matches!(last.finish_reason, Some(FinishReason::FunctionCall)));
+        assert_eq!(last.usage.as_ref().unwrap().total_tokens, Some(29));
+    }
+}
@@ -0,0 +1,271 @@
+//! Model metadata: context windows, modalities, and pricing
+//!
+//! A bundled table covers the Gemini family; entries can be added or
+//! overridden from the `model_metadata` section of `adk.toml`:
+//!
+//! ```toml
+//! [model_metadata."gemini-2.0-flash"]
+//! max_input_tokens = 1048576
+//!
+//! [model_metadata."gemini-2.0-flash".pricing]
+//! input_per_million_tokens = 0.10
+//! output_per_million_tokens = 0.40
+//! ```
+
+use crate::{
+    error::Result,
+    models::profiles::{CONFIG_PATH_ENV_VAR, DEFAULT_CONFIG_FILE},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+use tracing::debug;
+
+/// Input or output modality supported by a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modality {
+    Text,
+    Image,
+    Audio,
+    Video,
+}
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Price per million prompt tokens
+    pub input_per_million_tokens: f64,
+
+    /// Price per million completion tokens
+    pub output_per_million_tokens: f64,
+}
+
+impl ModelPricing {
+    pub fn new(input_per_million_tokens: f64, output_per_million_tokens: f64) -> Self {
+        Self {
+            input_per_million_tokens,
+            output_per_million_tokens,
+        }
+    }
+
+    /// Compute the cost in USD for the given token counts
+    pub fn cost(&self, prompt_tokens: u32, completion_tokens: u32) -> f64 {
+        (prompt_tokens as f64 * self.input_per_million_tokens
+            + completion_tokens as f64 * self.output_per_million_tokens)
+            / 1_000_000.0
+    }
+}
+
+/// Static metadata describing a model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelMetadata {
+    /// Maximum prompt (context window) size in tokens
+    #[serde(default)]
+    pub max_input_tokens: Option<u32>,
+
+    /// Maximum number of tokens the model can generate
+    #[serde(default)]
+    pub max_output_tokens: Option<u32>,
+
+    /// Accepted input modalities
+    #[serde(default)]
+    pub input_modalities: Vec<Modality>,
+
+    /// Produced output modalities
+    #[serde(default)]
+    pub output_modalities: Vec<Modality>,
+
+    /// Token pricing
+    #[serde(default)]
+    pub pricing: Option<ModelPricing>,
+}
+
+impl ModelMetadata {
+    /// Overlay the fields set in `other` on top of this metadata
+    pub fn merge(mut self, other: ModelMetadata) -> Self {
+        if other.max_input_tokens.is_some() {
+            self.max_input_tokens = other.max_input_tokens;
+        }
+        if other.max_output_tokens.is_some() {
+            self.max_output_tokens = other.max_output_tokens;
+        }
+        if !other.input_modalities.is_empty() {
+            self.input_modalities = other.input_modalities;
+        }
+        if !other.output_modalities.is_empty() {
+            self.output_modalities = other.output_modalities;
+        }
+        if other.pricing.is_some() {
+            self.pricing = other.pricing;
+        }
+        self
+    }
+}
+
+fn gemini(max_input: u32, max_output: u32, input_price: f64, output_price: f64) -> ModelMetadata {
+    ModelMetadata {
+        max_input_tokens: Some(max_input),
+        max_output_tokens: Some(max_output),
+        input_modalities: vec![Modality::Text, Modality::Image, Modality::Audio, Modality::Video],
+        output_modalities: vec![Modality::Text],
+        pricing: Some(ModelPricing::new(input_price, output_price)),
+    }
+}
+
+/// Bundled metadata, keyed by model name prefix
+fn bundled_metadata() -> HashMap<String, ModelMetadata> {
+    let mut table = HashMap::new();
+    table.insert("gemini-2.5-pro".to_string(), gemini(1_048_576, 65_536, 1.25, 10.0));
+    table.insert("gemini-2.5-flash".to_string(), gemini(1_048_576, 65_536, 0.30, 2.50));
+    table.insert("gemini-2.0-flash".to_string(), gemini(1_048_576, 8_192, 0.10, 0.40));
+    table.insert("gemini-2.0-flash-lite".to_string(), gemini(1_048_576, 8_192, 0.075, 0.30));
+    table.insert("gemini-1.5-pro".to_string(), gemini(2_097_152, 8_192, 1.25, 5.0));
+    table.insert("gemini-1.5-flash".to_string(), gemini(1_048_576, 8_192, 0.075, 0.30));
+    table.insert(
+        "gemini-1.0-pro".to_string(),
+        ModelMetadata {
+            max_input_tokens: Some(30_720),
+            max_output_tokens: Some(2_048),
+            input_modalities: vec![Modality::Text],
+            output_modalities: vec![Modality::Text],
+            pricing: Some(ModelPricing::new(0.50, 1.50)),
+        },
+    );
+    table.insert("gemini-pro".to_string(), table["gemini-1.0-pro"].clone());
+    table
+}
+
+/// On-disk layout of the model metadata section of `adk.toml`
+#[derive(Debug, Default, Deserialize)]
+struct CatalogFile {
+    #[serde(default)]
+    model_metadata: HashMap<String, ModelMetadata>,
+}
+
+/// Lookup table of model metadata with configurable overrides
+#[derive(Debug, Clone)]
+pub struct ModelCatalog {
+    bundled: Arc<HashMap<String, ModelMetadata>>,
+    overrides: Arc<RwLock<HashMap<String, ModelMetadata>>>,
+}
+
+impl ModelCatalog {
+    /// Create a catalog with only the bundled table
+    pub fn new() -> Self {
+        Self {
+            bundled: Arc::new(bundled_metadata()),
+            overrides: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Load overrides from a TOML file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let file: CatalogFile = config::Config::builder()
+            .add_source(config::File::from(path.as_ref()).format(config::FileFormat::Toml))
+            .build()?
+            .try_deserialize()?;
+
+        let catalog = Self::new();
+        for (name, metadata) in file.model_metadata {
+            catalog.set_override(name, metadata);
+        }
+        Ok(catalog)
+    }
+
+    /// Load overrides from `$ADK_CONFIG` or `./adk.toml`, if present
+    pub fn load_default() -> Result<Self> {
+        let path = std::env::var(CONFIG_PATH_ENV_VAR).unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+        if Path::new(&path).exists() {
+            Self::from_file(path)
+        } else {
+            debug!("No {} found, using bundled model metadata", path);
+            Ok(Self::new())
+        }
+    }
+
+    /// Add or override metadata for a model name prefix
+    pub fn set_override(&self, model_prefix: impl Into<String>, metadata: ModelMetadata) {
+        let mut overrides = self.overrides.write().expect("catalog lock poisoned");
+        overrides.insert(model_prefix.into(), metadata);
+    }
+
+    /// Look up metadata for a model, using the longest matching name prefix.
+    ///
+    /// Bundled data and overrides are resolved independently and merged, so an
+    /// override only needs to specify the fields it changes.
+    pub fn lookup(&self, model_name: &str) -> Option<ModelMetadata> {
+        let bundled = longest_prefix_match(&self.bundled, model_name);
+        let overrides = self.overrides.read().expect("catalog lock poisoned");
+        let overridden = longest_prefix_match(&overrides, model_name);
+
+        match (bundled, overridden) {
+            (Some(base), Some(over)) => Some(base.clone().merge(over.clone())),
+            (Some(base), None) => Some(base.clone()),
+            (None, Some(over)) => Some(over.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn longest_prefix_match<'a>(
+    table: &'a HashMap<String, ModelMetadata>,
+    model_name: &str,
+) -> Option<&'a ModelMetadata> {
+    table
+        .iter()
+        .filter(|(prefix, _)| model_name.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, metadata)| metadata)
+}
+
+/// Global catalog instance, with overrides loaded from `adk.toml` on first use
+static GLOBAL_CATALOG: once_cell::sync::Lazy<ModelCatalog> = once_cell::sync::Lazy::new(|| {
+    ModelCatalog::load_default().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load model metadata overrides: {}", e);
+        ModelCatalog::new()
+    })
+});
+
+/// Get the global model catalog
+pub fn global_catalog() -> &'static ModelCatalog {
+    &GLOBAL_CATALOG
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_longest_prefix_and_override() {
+        let catalog = ModelCatalog::new();
+
+        let lite = catalog.lookup("gemini-2.0-flash-lite-001").unwrap();
+        assert_eq!(lite.pricing.unwrap().input_per_million_tokens, 0.075);
+
+        let flash = catalog.lookup("gemini-2.0-flash").unwrap();
+        assert_eq!(flash.max_input_tokens, Some(1_048_576));
+        assert!(catalog.lookup("unknown-model").is_none());
+
+        catalog.set_override(
+            "gemini-2.0-flash",
+            ModelMetadata {
+                pricing: Some(ModelPricing::new(1.0, 2.0)),
+                ..Default::default()
+            },
+        );
+        let flash = catalog.lookup("gemini-2.0-flash").unwrap();
+        assert_eq!(flash.pricing, Some(ModelPricing::new(1.0, 2.0)));
+        assert_eq!(flash.max_output_tokens, Some(8_192));
+        assert_eq!(flash.pricing.unwrap().cost(1_000_000, 500_000), 2.0);
+    }
+}
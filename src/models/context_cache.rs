@@ -0,0 +1,335 @@
+//! Gemini context caching
+//!
+//! A long static prompt prefix (system instruction, reference documents,
+//! tools) can be uploaded once as cached content and referenced by name from
+//! later requests, which are then billed the cached-token rate for it. Create
+//! caches with [`GoogleLlm::context_cache`], then reference one per model with
+//! [`GoogleLlm::with_cached_content`] or per request with
+//! [`LlmRequest::with_cached_content`](crate::models::LlmRequest::with_cached_content).
+
+use crate::{
+    error::Result,
+    models::{
+        google_llm::{convert_contents, convert_tools, GoogleAiContent, GoogleAiTool},
+        BaseLlm, GoogleLlm,
+    },
+    types::{Content, ContentPart, Tool},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+/// Content to cache and how long to keep it
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub display_name: Option<String>,
+    pub system_instruction: Option<String>,
+    pub contents: Vec<Content>,
+    pub tools: Vec<Tool>,
+
+    /// Time after which the cache is deleted, unless extended
+    pub ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            display_name: None,
+            system_instruction: None,
+            contents: Vec::new(),
+            tools: Vec::new(),
+            ttl: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_display_name(mut self, display_name: impl Into<String>) -> Self {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
+    pub fn with_system_instruction(mut self, instruction: impl Into<String>) -> Self {
+        self.system_instruction = Some(instruction.into());
+        self
+    }
+
+    pub fn with_content(mut self, content: Content) -> Self {
+        self.contents.push(content);
+        self
+    }
+
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+/// Cached content as stored by the API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedContent {
+    /// Resource name to reference, e.g. `cachedContents/abc123`
+    pub name: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default, alias = "displayName")]
+    pub display_name: Option<String>,
+    #[serde(default, alias = "createTime")]
+    pub create_time: Option<String>,
+    #[serde(default, alias = "updateTime")]
+    pub update_time: Option<String>,
+    #[serde(default, alias = "expireTime")]
+    pub expire_time: Option<String>,
+    #[serde(default, alias = "usageMetadata")]
+    pub usage_metadata: Option<serde_json::Value>,
+}
+
+/// Cached content management for a [`GoogleLlm`]'s model and endpoint
+#[derive(Debug, Clone)]
+pub struct ContextCache {
+    llm: GoogleLlm,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateCachedContentRequest {
+    model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GoogleAiContent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    contents: Vec<GoogleAiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<GoogleAiTool>>,
+    ttl: String,
+}
+
+impl GoogleLlm {
+    /// Create, inspect and delete cached content for this model
+    pub fn context_cache(&self) -> ContextCache {
+        ContextCache { llm: self.clone() }
+    }
+}
+
+impl ContextCache {
+    /// Upload content to cache; reference the returned name from requests
+    pub async fn create(&self, config: CacheConfig) -> Result<CachedContent> {
+        let system_instruction = config.system_instruction.map(|instruction| {
+            let content = Content { role: String::new(), parts: vec![ContentPart::text(instruction)] };
+            convert_contents(&[content]).remove(0)
+        });
+        let request = CreateCachedContentRequest {
+            model: self.model_resource(),
+            display_name: config.display_name,
+            system_instruction,
+            contents: convert_contents(&config.contents),
+            tools: convert_tools(&config.tools, false),
+            ttl: format_ttl(config.ttl),
+        };
+
+        let url = self.collection_url();
+        let response = self.llm.send(|| self.llm.client().post(&url).json(&request)).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Get cached content by name
+    pub async fn get(&self, name: &str) -> Result<CachedContent> {
+        let url = self.resource_url(name);
+        let response = self.llm.send(|| self.llm.client().get(&url)).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Keep cached content for `ttl` from now
+    pub async fn update_ttl(&self, name: &str, ttl: Duration) -> Result<CachedContent> {
+        let url = format!("{}?updateMask=ttl", self.resource_url(name));
+        let body = json!({ "ttl": format_ttl(ttl) });
+        let response = self.llm.send(|| self.llm.client().patch(&url).json(&body)).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Delete cached content before it expires
+    pub async fn delete(&self, name: &str) -> Result<()> {
+        let url = self.resource_url(name);
+        self.llm.send(|| self.llm.client().delete(&url)).await?;
+        Ok(())
+    }
+
+    /// API root the resource names are relative to; Vertex AI model URLs
+    /// nest under `/projects/...`
+    fn api_root(&self) -> &str {
+        let base_url = self.llm.base_url();
+        match (self.llm.vertex_location(), base_url.find("/projects/")) {
+            (Some(_), Some(index)) => &base_url[..index],
+            _ => base_url,
+        }
+    }
+
+    fn model_resource(&self) -> String {
+        match self.llm.vertex_location() {
+            Some((project, region)) => format!(
+                "projects/{}/locations/{}/publishers/google/models/{}",
+                project,
+                region,
+                self.llm.model_name()
+            ),
+            None => format!("models/{}", self.llm.model_name()),
+        }
+    }
+
+    fn collection_url(&self) -> String {
+        match self.llm.vertex_location() {
+            Some((project, region)) => {
+                format!("{}/projects/{}/locations/{}/cachedContents", self.api_root(), project, region)
+            }
+            None => format!("{}/cachedContents", self.api_root()),
+        }
+    }
+
+    fn resource_url(&self, name: &str) -> String {
+        format!("{}/{}", self.api_root(), name)
+    }
+}
+
+/// Durations are sent as seconds with an `s` suffix
+fn format_ttl(ttl: Duration) -> String {
+    format!("{}s", ttl.as_secs().max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{LlmRequest, RetryPolicy},
+        types::FunctionDeclaration,
+    };
+    use axum::{
+        extract::{Path, State},
+        routing::{patch, post},
+        Json, Router,
+    };
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+
+    type Seen = Arc<Mutex<Vec<(String, Value)>>>;
+
+    async fn create(State(seen): State<Seen>, Json(body): Json<Value>) -> Json<Value> {
+        seen.lock().unwrap().push(("create".to_string(), body.clone()));
+        Json(json!({
+            "name": "cachedContents/abc123",
+            "model": body["model"],
+            "displayName": body["display_name"],
+            "expireTime": "2026-01-01T01:00:00Z",
+            "usageMetadata": { "totalTokenCount": 40000 }
+        }))
+    }
+
+    async fn update(State(seen): State<Seen>, Path(id): Path<String>, Json(body): Json<Value>) -> Json<Value> {
+        seen.lock().unwrap().push((format!("patch {}", id), body));
+        Json(json!({ "name": format!("cachedContents/{}", id), "expireTime": "2026-01-01T02:00:00Z" }))
+    }
+
+    async fn delete(State(seen): State<Seen>, Path(id): Path<String>) -> Json<Value> {
+        seen.lock().unwrap().push((format!("delete {}", id), Value::Null));
+        Json(json!({}))
+    }
+
+    async fn generate(State(seen): State<Seen>, Json(body): Json<Value>) -> Json<Value> {
+        seen.lock().unwrap().push(("generate".to_string(), body));
+        Json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [{ "text": "Chapter 3." }] }, "finishReason": "STOP" }]
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_create_and_reference_cached_content() {
+        let seen = Seen::default();
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let base_url = format!("http://{}/v1beta", listener.local_addr().unwrap());
+        let router = Router::new()
+            .route("/v1beta/cachedContents", post(create))
+            .route("/v1beta/cachedContents/:id", patch(update).delete(delete))
+            .route("/v1beta/models/:call", post(generate))
+            .with_state(seen.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let llm = GoogleLlm::new("gemini-2.0-flash")
+            .with_api_key("test-key")
+            .with_base_url(base_url)
+            .with_retry_policy(RetryPolicy::none());
+        let cache = llm.context_cache();
+        let lookup = Tool {
+            function_declarations: vec![FunctionDeclaration {
+                name: "lookup".to_string(),
+                description: "Look up a section".to_string(),
+                parameters: json!({ "type": "object" }),
+            }],
+        };
+        let cached = cache
+            .create(
+                CacheConfig::new()
+                    .with_display_name("manual")
+                    .with_system_instruction("Answer from the manual.")
+                    .with_content(Content::user_text("<the manual>"))
+                    .with_tools(vec![lookup.clone()])
+                    .with_ttl(Duration::from_secs(600)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(cached.name, "cachedContents/abc123");
+        assert_eq!(cached.display_name.as_deref(), Some("manual"));
+        assert_eq!(cached.usage_metadata.unwrap()["totalTokenCount"], 40000);
+
+        let updated = cache.update_ttl(&cached.name, Duration::from_secs(7200)).await.unwrap();
+        assert_eq!(updated.expire_time.as_deref(), Some("2026-01-01T02:00:00Z"));
+
+        // Tools live in the cache, so requests referencing it leave them out
+        let mut request = LlmRequest::new("gemini-2.0-flash")
+            .add_user_message("Where is the reset procedure?")
+            .with_cached_content(&cached.name);
+        request.config.tools = vec![lookup];
+        let response = llm.generate_content(request).await.unwrap();
+        assert_eq!(response.get_text().as_deref(), Some("Chapter 3."));
+
+        cache.delete(&cached.name).await.unwrap();
+
+        let seen = seen.lock().unwrap();
+        let (_, create) = &seen[0];
+        assert_eq!(create["model"], "models/gemini-2.0-flash");
+        assert_eq!(create["ttl"], "600s");
+        assert_eq!(create["system_instruction"], json!({ "parts": [{ "text": "Answer from the manual." }] }));
+        assert_eq!(create["contents"][0]["role"], "user");
+        assert_eq!(create["tools"][0]["function_declarations"][0]["name"], "lookup");
+        assert_eq!(seen[1], ("patch abc123".to_string(), json!({ "ttl": "7200s" })));
+        let (_, generate) = &seen[2];
+        assert_eq!(generate["cached_content"], "cachedContents/abc123");
+        assert!(generate.get("tools").is_none());
+        assert_eq!(seen[3].0, "delete abc123");
+    }
+
+    #[test]
+    fn test_vertex_ai_resource_names() {
+        let cache = GoogleLlm::new("gemini-1.5-pro")
+            .with_project_id("acme")
+            .with_region("us-central1")
+            .use_vertex_ai()
+            .context_cache();
+        assert_eq!(
+            cache.collection_url(),
+            "https://us-central1-aiplatform.googleapis.com/v1/projects/acme/locations/us-central1/cachedContents"
+        );
+        assert_eq!(cache.model_resource(), "projects/acme/locations/us-central1/publishers/google/models/gemini-1.5-pro");
+        assert_eq!(
+            cache.resource_url("projects/123/locations/us-central1/cachedContents/abc"),
+            "https://us-central1-aiplatform.googleapis.com/v1/projects/123/locations/us-central1/cachedContents/abc"
+        );
+    }
+}
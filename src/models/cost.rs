@@ -0,0 +1,154 @@
+//! Cost accounting for model calls
+//!
+//! A [`CostTracker`] prices the token usage of every model call of an
+//! invocation and keeps running totals, per model and overall. Prices come
+//! from a [`PricingTable`]; models it does not list are priced from the model
+//! catalog. The tracker is shared by an invocation context and its sub-agent
+//! contexts, and LLM agents stamp the running total on their events under
+//! [`COST_METADATA_KEY`].
+
+use crate::models::{global_catalog, ModelPricing, Usage};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+/// Event metadata key holding the invocation's running [`CostSummary`]
+pub const COST_METADATA_KEY: &str = "cost";
+
+/// Prices by model name prefix; the longest matching prefix wins
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PricingTable {
+    prices: HashMap<String, ModelPricing>,
+}
+
+impl PricingTable {
+    /// An empty table, pricing every model from the catalog
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Price models whose name starts with `model_prefix`
+    pub fn with_price(mut self, model_prefix: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.prices.insert(model_prefix.into(), pricing);
+        self
+    }
+
+    /// Pricing of a model: the table's, else the catalog's
+    pub fn lookup(&self, model_name: &str) -> Option<ModelPricing> {
+        self.prices
+            .iter()
+            .filter(|(prefix, _)| model_name.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, pricing)| *pricing)
+            .or_else(|| global_catalog().lookup(model_name).and_then(|metadata| metadata.pricing))
+    }
+}
+
+/// Token usage and cost of a set of model calls
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostSummary {
+    pub calls: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+
+    /// Cost in USD of the calls whose model has a price
+    pub cost_usd: f64,
+
+    /// Calls not included in `cost_usd`, as their model has no price
+    pub unpriced_calls: u32,
+}
+
+impl CostSummary {
+    fn add(&mut self, other: &CostSummary) {
+        self.calls += other.calls;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost_usd += other.cost_usd;
+        self.unpriced_calls += other.unpriced_calls;
+    }
+}
+
+/// Running cost of the model calls of an invocation; clones share the totals
+#[derive(Debug, Clone, Default)]
+pub struct CostTracker {
+    pricing: Arc<PricingTable>,
+    by_model: Arc<Mutex<BTreeMap<String, CostSummary>>>,
+}
+
+impl CostTracker {
+    /// A tracker pricing models from the catalog
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A tracker pricing models from `pricing` first
+    pub fn with_pricing(pricing: Arc<PricingTable>) -> Self {
+        Self {
+            pricing,
+            by_model: Arc::default(),
+        }
+    }
+
+    /// Account for a completed call; returns its cost in USD if the model has a price
+    pub fn record(&self, model_name: &str, usage: Option<&Usage>) -> Option<f64> {
+        let prompt_tokens = usage.and_then(|u| u.prompt_tokens).unwrap_or(0);
+        let completion_tokens = usage.and_then(|u| u.completion_tokens).unwrap_or(0);
+        let cost = self
+            .pricing
+            .lookup(model_name)
+            .map(|pricing| pricing.cost(prompt_tokens, completion_tokens));
+
+        let mut by_model = self.by_model.lock().unwrap();
+        by_model.entry(model_name.to_string()).or_default().add(&CostSummary {
+            calls: 1,
+            prompt_tokens: prompt_tokens as u64,
+            completion_tokens: completion_tokens as u64,
+            cost_usd: cost.unwrap_or(0.0),
+            unpriced_calls: cost.is_none() as u32,
+        });
+        cost
+    }
+
+    /// Totals over all models
+    pub fn total(&self) -> CostSummary {
+        let mut total = CostSummary::default();
+        for summary in self.by_model.lock().unwrap().values() {
+            total.add(summary);
+        }
+        total
+    }
+
+    /// Totals per model name
+    pub fn by_model(&self) -> BTreeMap<String, CostSummary> {
+        self.by_model.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_accumulates_cost_per_model() {
+        let pricing = PricingTable::new()
+            .with_price("house-", ModelPricing::new(1.0, 2.0))
+            .with_price("house-large", ModelPricing::new(10.0, 20.0));
+        let tracker = CostTracker::with_pricing(Arc::new(pricing));
+        let usage = Usage::new().with_prompt_tokens(1_000_000).with_completion_tokens(500_000);
+
+        assert_eq!(tracker.record("house-small", Some(&usage)), Some(2.0));
+        assert_eq!(tracker.record("house-large-2", Some(&usage)), Some(20.0));
+        assert_eq!(tracker.record("unknown-model", Some(&usage)), None);
+
+        // Clones, e.g. in sub-agent contexts, add to the same totals
+        tracker.clone().record("house-small", None);
+        let total = tracker.total();
+        assert_eq!(total.calls, 4);
+        assert_eq!(total.cost_usd, 22.0);
+        assert_eq!(total.unpriced_calls, 1);
+        assert_eq!(total.prompt_tokens, 3_000_000);
+        assert_eq!(tracker.by_model()["house-small"].calls, 2);
+    }
+}
@@ -0,0 +1,172 @@
+//! Text embedding models
+
+use crate::{
+    error::Result,
+    models::{http_client::InFlightRequest, HttpClientConfig},
+};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+/// Default Gemini embedding model
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-004";
+
+/// Model that maps texts to embedding vectors
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Embed a batch of texts, returning one vector per input in order
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Cosine similarity of two vectors (0.0 if either is zero or lengths differ)
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BatchEmbedRequest {
+    requests: Vec<EmbedRequest>,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedRequest {
+    model: String,
+    content: EmbedContent,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedContent {
+    parts: Vec<EmbedPart>,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedPart {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchEmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<EmbeddingValues>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingValues {
+    values: Vec<f32>,
+}
+
+/// Gemini embedding model via the `batchEmbedContents` endpoint
+#[derive(Debug, Clone)]
+pub struct GoogleEmbedder {
+    model: String,
+    api_key: Option<String>,
+    client: Client,
+    base_url: String,
+}
+
+impl GoogleEmbedder {
+    pub fn new(model: impl Into<String>) -> Self {
+        let client = HttpClientConfig::default()
+            .shared_client()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            model: model.into(),
+            api_key: None,
+            client,
+            base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn get_auth_header(&self) -> Result<String> {
+        match self.api_key.clone().or_else(|| std::env::var("GOOGLE_API_KEY").ok()) {
+            Some(key) => Ok(format!("Bearer {}", key)),
+            None => Err(crate::adk_error!(
+                AuthError,
+                "No API key provided. Set GOOGLE_API_KEY environment variable or use with_api_key()"
+            )),
+        }
+    }
+}
+
+impl Default for GoogleEmbedder {
+    fn default() -> Self {
+        Self::new(DEFAULT_EMBEDDING_MODEL)
+    }
+}
+
+#[async_trait]
+impl Embedder for GoogleEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        debug!("Embedding {} texts with {}", texts.len(), self.model);
+
+        let request = BatchEmbedRequest {
+            requests: texts
+                .iter()
+                .map(|text| EmbedRequest {
+                    model: format!("models/{}", self.model),
+                    content: EmbedContent {
+                        parts: vec![EmbedPart { text: text.clone() }],
+                    },
+                })
+                .collect(),
+        };
+
+        let url = format!("{}/models/{}:batchEmbedContents", self.base_url, self.model);
+        let _in_flight = InFlightRequest::start();
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", self.get_auth_header()?)
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!("Embedding API error: {} - {}", status, error_text);
+            return Err(crate::adk_error!(ModelError, "Embedding API error: {} - {}", status, error_text));
+        }
+
+        let response: BatchEmbedResponse = response.json().await?;
+        if response.embeddings.len() != texts.len() {
+            return Err(crate::adk_error!(
+                ModelError,
+                "Embedding API returned {} vectors for {} inputs",
+                response.embeddings.len(),
+                texts.len()
+            ));
+        }
+        Ok(response.embeddings.into_iter().map(|e| e.values).collect())
+    }
+}
@@ -0,0 +1,153 @@
+//! Pre-flight size estimates of model requests
+//!
+//! A [`PromptEstimate`] breaks a request down into its turns and tool
+//! declarations and compares the total with the model's context window and
+//! price, without calling the model. Token counts are approximations (about
+//! four characters per token, a fixed cost per media part), good enough to
+//! find what makes a prompt too long or too expensive.
+
+use crate::{
+    models::{global_catalog, LlmRequest},
+    types::{Content, ContentPart},
+};
+use serde::Serialize;
+
+/// Characters per token assumed by the estimates
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens assumed for each image, audio, video or file part
+pub const MEDIA_PART_TOKENS: u32 = 258;
+
+/// Approximate token count of a text
+pub fn estimate_tokens(text: &str) -> u32 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u32
+}
+
+/// Approximate prompt token count of a request: turns, tool declarations and response schema
+pub fn estimate_request_tokens(request: &LlmRequest) -> u32 {
+    PromptEstimate::of(String::new(), request).prompt_tokens
+}
+
+fn content_tokens(content: &Content) -> u32 {
+    content
+        .parts
+        .iter()
+        .map(|part| match part {
+            ContentPart::Text { text } => estimate_tokens(text),
+            ContentPart::Image { .. }
+            | ContentPart::Video { .. }
+            | ContentPart::Audio { .. }
+            | ContentPart::File { .. }
+            | ContentPart::FileRef { .. } => MEDIA_PART_TOKENS,
+            other => estimate_tokens(&serde_json::to_string(other).unwrap_or_default()),
+        })
+        .sum()
+}
+
+/// Estimated size of one turn of the request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TurnEstimate {
+    pub role: String,
+    pub tokens: u32,
+}
+
+/// Estimated size of one tool declaration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ToolDeclarationEstimate {
+    pub name: String,
+    pub tokens: u32,
+}
+
+/// Estimated size and cost of a model request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PromptEstimate {
+    /// Agent that would send the request
+    pub agent: String,
+    pub model: String,
+
+    /// Estimated prompt tokens: turns plus tool declarations
+    pub prompt_tokens: u32,
+
+    /// Turns sent, in order: instruction, examples, history and the new message
+    pub turns: Vec<TurnEstimate>,
+
+    pub tools: Vec<ToolDeclarationEstimate>,
+
+    /// The model's context window, if known
+    pub max_input_tokens: Option<u32>,
+
+    /// The estimate is larger than the context window
+    pub exceeds_context: bool,
+
+    /// Estimated cost of the prompt tokens in USD, if the model's price is known
+    pub prompt_cost_usd: Option<f64>,
+}
+
+impl PromptEstimate {
+    pub fn of(agent: impl Into<String>, request: &LlmRequest) -> Self {
+        let turns: Vec<TurnEstimate> = request
+            .all_contents()
+            .map(|content| TurnEstimate {
+                role: content.role.clone(),
+                tokens: content_tokens(content),
+            })
+            .collect();
+        let tools: Vec<ToolDeclarationEstimate> = request
+            .config
+            .tools
+            .iter()
+            .flat_map(|tool| &tool.function_declarations)
+            .map(|declaration| ToolDeclarationEstimate {
+                name: declaration.name.clone(),
+                tokens: estimate_tokens(&serde_json::to_string(declaration).unwrap_or_default()),
+            })
+            .collect();
+        let prompt_tokens = turns.iter().map(|turn| turn.tokens).sum::<u32>()
+            + tools.iter().map(|tool| tool.tokens).sum::<u32>()
+            + request.config.response_schema.as_ref().map_or(0, |schema| estimate_tokens(&schema.to_string()));
+
+        let metadata = global_catalog().lookup(&request.model);
+        let max_input_tokens = metadata.as_ref().and_then(|metadata| metadata.max_input_tokens);
+        Self {
+            agent: agent.into(),
+            model: request.model.clone(),
+            prompt_tokens,
+            turns,
+            tools,
+            max_input_tokens,
+            exceeds_context: max_input_tokens.is_some_and(|max| prompt_tokens > max),
+            prompt_cost_usd: metadata.and_then(|metadata| metadata.pricing).map(|pricing| pricing.cost(prompt_tokens, 0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FunctionDeclaration, Tool};
+    use serde_json::json;
+
+    #[test]
+    fn test_estimate_breaks_down_turns_and_tools() {
+        let mut request = LlmRequest::new("gemini-2.0-flash")
+            .add_user_message("a".repeat(400))
+            .add_model_message("ok");
+        request.config.tools.push(Tool {
+            function_declarations: vec![FunctionDeclaration {
+                name: "lookup".to_string(),
+                description: "Look something up".to_string(),
+                parameters: json!({ "type": "object" }),
+            }],
+        });
+
+        let estimate = PromptEstimate::of("helper", &request);
+        assert_eq!(estimate.turns[0], TurnEstimate { role: "user".to_string(), tokens: 100 });
+        assert_eq!(estimate.turns[1].tokens, 1);
+        assert_eq!(estimate_request_tokens(&request), estimate.prompt_tokens);
+        assert_eq!(estimate.tools[0].name, "lookup");
+        assert_eq!(estimate.prompt_tokens, 101 + estimate.tools[0].tokens);
+        assert_eq!(estimate.max_input_tokens, Some(1_048_576));
+        assert!(!estimate.exceeds_context);
+        assert!(estimate.prompt_cost_usd.unwrap() > 0.0);
+    }
+}
@@ -0,0 +1,267 @@
+//! Model fallback chains
+
+use crate::{
+    error::{AdkError, Result},
+    models::{BaseLlm, LlmConnection, LlmRequest, LlmResponse},
+};
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::{pin::Pin, sync::Arc};
+use tracing::{info, warn};
+
+/// Response metadata key recording which model of a [`FallbackLlm`] served the
+/// request and the errors of the models tried before it
+pub const FALLBACK_METADATA_KEY: &str = "fallback";
+
+/// Provider errors carry the HTTP status after "error: ", e.g. "OpenAI API error: 429 Too Many Requests - ..."
+static FALLBACK_STATUS: Lazy<Regex> = Lazy::new(|| Regex::new(r"error: (429|5\d\d)\b").unwrap());
+
+/// Whether an error is worth retrying on another model: rate limits, 5xx
+/// responses, timeouts and network failures. Request and auth errors would
+/// fail the same way everywhere, so they are returned as is.
+pub fn is_fallback_error(error: &AdkError) -> bool {
+    match error {
+        AdkError::TimeoutError(_) | AdkError::NetworkError(_) => true,
+        AdkError::ModelError(message) => FALLBACK_STATUS.is_match(message),
+        _ => false,
+    }
+}
+
+/// Model wrapper that tries an ordered list of models, moving on to the next
+/// one when a model fails with a rate-limit, 5xx, timeout or network error.
+///
+/// Responses carry [`FALLBACK_METADATA_KEY`] metadata naming the model that
+/// served them. Streams fall back only while being opened; an error after
+/// chunks were yielded is passed through, as the output cannot be taken back.
+pub struct FallbackLlm {
+    models: Vec<Arc<dyn BaseLlm>>,
+}
+
+impl FallbackLlm {
+    /// Start a chain with its primary model
+    pub fn new(primary: Arc<dyn BaseLlm>) -> Self {
+        Self { models: vec![primary] }
+    }
+
+    /// Add a model to try after the ones already in the chain
+    pub fn with_fallback(mut self, model: Arc<dyn BaseLlm>) -> Self {
+        self.models.push(model);
+        self
+    }
+
+    /// Get the models in the order they are tried
+    pub fn models(&self) -> &[Arc<dyn BaseLlm>] {
+        &self.models
+    }
+
+    fn primary(&self) -> &Arc<dyn BaseLlm> {
+        &self.models[0]
+    }
+
+    /// Record a skipped model, or return the error if no model is left to try
+    fn skip(&self, index: usize, error: AdkError, skipped: &mut Vec<Value>) -> Result<()> {
+        let model = self.models[index].model_name();
+        if index + 1 == self.models.len() || !is_fallback_error(&error) {
+            return Err(error);
+        }
+        warn!(
+            model = %model,
+            next = %self.models[index + 1].model_name(),
+            "Model failed, falling back: {}",
+            error
+        );
+        skipped.push(json!({ "model": model, "error": error.to_string() }));
+        Ok(())
+    }
+
+    fn metadata(model: &dyn BaseLlm, skipped: &[Value]) -> Value {
+        json!({ "model": model.model_name(), "skipped": skipped })
+    }
+}
+
+impl std::fmt::Debug for FallbackLlm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FallbackLlm")
+            .field("models", &self.models.iter().map(|m| m.model_name()).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl BaseLlm for FallbackLlm {
+    /// The primary model's name
+    fn model_name(&self) -> &str {
+        self.primary().model_name()
+    }
+
+    fn supported_models() -> Vec<String> {
+        Vec::new()
+    }
+
+    async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let mut skipped = Vec::new();
+        for (index, model) in self.models.iter().enumerate() {
+            match model.generate_content(request.clone()).await {
+                Ok(response) => {
+                    if !skipped.is_empty() {
+                        info!(model = %model.model_name(), "Request served by fallback model");
+                    }
+                    return Ok(response.with_metadata(FALLBACK_METADATA_KEY, Self::metadata(model.as_ref(), &skipped)));
+                }
+                Err(e) => self.skip(index, e, &mut skipped)?,
+            }
+        }
+        unreachable!("the last model's error is returned by skip")
+    }
+
+    async fn generate_content_stream(
+        &self,
+        request: LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>> {
+        let mut skipped = Vec::new();
+        for (index, model) in self.models.iter().enumerate() {
+            match model.generate_content_stream(request.clone()).await {
+                Ok(stream) => {
+                    let metadata = Self::metadata(model.as_ref(), &skipped);
+                    return Ok(Box::pin(stream.map(move |chunk| {
+                        chunk.map(|response| response.with_metadata(FALLBACK_METADATA_KEY, metadata.clone()))
+                    })));
+                }
+                Err(e) => self.skip(index, e, &mut skipped)?,
+            }
+        }
+        unreachable!("the last model's error is returned by skip")
+    }
+
+    async fn count_tokens(&self, request: &LlmRequest) -> Result<u32> {
+        self.primary().count_tokens(request).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.primary().supports_streaming()
+    }
+
+    fn supports_function_calling(&self) -> bool {
+        self.primary().supports_function_calling()
+    }
+
+    fn supports_multimodal(&self) -> bool {
+        self.primary().supports_multimodal()
+    }
+
+    fn supports_live(&self) -> bool {
+        self.primary().supports_live()
+    }
+
+    async fn create_live_connection(&self) -> Result<Box<dyn LlmConnection>> {
+        self.primary().create_live_connection().await
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.models.iter().try_for_each(|model| model.validate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FailingLlm {
+        name: &'static str,
+        error: fn() -> AdkError,
+        calls: AtomicUsize,
+    }
+
+    impl FailingLlm {
+        fn new(name: &'static str, error: fn() -> AdkError) -> Arc<Self> {
+            Arc::new(Self { name, error, calls: AtomicUsize::new(0) })
+        }
+    }
+
+    #[async_trait]
+    impl BaseLlm for FailingLlm {
+        fn model_name(&self) -> &str {
+            self.name
+        }
+
+        fn supported_models() -> Vec<String> {
+            Vec::new()
+        }
+
+        async fn generate_content(&self, _request: LlmRequest) -> Result<LlmResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err((self.error)())
+        }
+
+        async fn generate_content_stream(
+            &self,
+            request: LlmRequest,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>> {
+            Err(self.generate_content(request).await.unwrap_err())
+        }
+    }
+
+    struct EchoLlm;
+
+    #[async_trait]
+    impl BaseLlm for EchoLlm {
+        fn model_name(&self) -> &str {
+            "echo"
+        }
+
+        fn supported_models() -> Vec<String> {
+            Vec::new()
+        }
+
+        async fn generate_content(&self, _request: LlmRequest) -> Result<LlmResponse> {
+            Ok(LlmResponse::text("hello"))
+        }
+
+        async fn generate_content_stream(
+            &self,
+            request: LlmRequest,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>> {
+            let response = self.generate_content(request).await?;
+            Ok(Box::pin(futures::stream::once(async move { Ok(response) })))
+        }
+    }
+
+    #[test]
+    fn test_fallback_errors() {
+        assert!(is_fallback_error(&crate::adk_error!(ModelError, "OpenAI API error: 429 Too Many Requests - slow down")));
+        assert!(is_fallback_error(&crate::adk_error!(ModelError, "Google AI API error: 503 Service Unavailable - ")));
+        assert!(is_fallback_error(&crate::adk_error!(TimeoutError, "timed out")));
+        assert!(!is_fallback_error(&crate::adk_error!(ModelError, "Anthropic API error: 400 Bad Request - ")));
+        assert!(!is_fallback_error(&crate::adk_error!(AuthError, "No API key provided")));
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_on_rate_limits_and_reports_the_serving_model() {
+        let limited = FailingLlm::new("primary", || crate::adk_error!(ModelError, "OpenAI API error: 429 Too Many Requests - "));
+        let down = FailingLlm::new("secondary", || crate::adk_error!(NetworkError, "connection refused"));
+        let llm = FallbackLlm::new(limited.clone()).with_fallback(down.clone()).with_fallback(Arc::new(EchoLlm));
+        assert_eq!(llm.model_name(), "primary");
+
+        let response = llm.generate_content(LlmRequest::new("primary")).await.unwrap();
+        assert_eq!(response.get_text().as_deref(), Some("hello"));
+        let metadata = &response.metadata[FALLBACK_METADATA_KEY];
+        assert_eq!(metadata["model"], "echo");
+        assert_eq!(metadata["skipped"][0]["model"], "primary");
+        assert_eq!(metadata["skipped"][1]["model"], "secondary");
+
+        let mut stream = llm.generate_content_stream(LlmRequest::new("primary")).await.unwrap();
+        let chunk = stream.next().await.unwrap().unwrap();
+        assert_eq!(chunk.metadata[FALLBACK_METADATA_KEY]["model"], "echo");
+        assert_eq!(limited.calls.load(Ordering::SeqCst), 2);
+
+        // Errors that would repeat on every model end the chain
+        let invalid = FailingLlm::new("primary", || crate::adk_error!(ModelError, "OpenAI API error: 400 Bad Request - "));
+        let llm = FallbackLlm::new(invalid).with_fallback(Arc::new(EchoLlm));
+        let error = llm.generate_content(LlmRequest::new("primary")).await.unwrap_err();
+        assert!(error.to_string().contains("400"));
+    }
+}
@@ -0,0 +1,518 @@
+//! LLM behind a custom REST endpoint
+//!
+//! [`GenericRestLlm`] plugs proprietary inference gateways into ADK without
+//! a new `BaseLlm` implementation. The request body is rendered from a JSON
+//! template: a string that is exactly `{{name}}` is replaced by the
+//! variable's JSON value (members whose value is unset are dropped), while
+//! `{{name}}` inside a longer string is replaced by its text. Variables:
+//!
+//! - `model`, `prompt` (text of the last user turn), `system`
+//! - `messages`: `{role, content}` objects with `user`, `assistant`,
+//!   `system` and `tool` roles; tool calls and results are JSON text
+//! - `contents`: the ADK contents as they serialize
+//! - `tools`: `{name, description, parameters}` declarations
+//! - `temperature`, `top_p`, `top_k`, `max_output_tokens`, `stop_sequences`, `seed`
+//!
+//! A [`ResponseTemplate`] locates the answer in the response with JSON
+//! pointers. Auth header values may reference environment variables as
+//! `${NAME}`, resolved for every request.
+
+use crate::{
+    error::Result,
+    models::{http_client::InFlightRequest, BaseLlm, FinishReason, HttpClientConfig, LlmRequest, LlmResponse, Usage},
+    types::{Content, ContentPart, FunctionCall},
+};
+use async_trait::async_trait;
+use futures::Stream;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::pin::Pin;
+use tracing::{debug, error, info, warn};
+
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*([A-Za-z_]+)\s*\}\}").unwrap());
+static ENV_REFERENCE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+
+/// Where the parts of an answer are found in a response, as JSON pointers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseTemplate {
+    /// Text of the answer, e.g. `/output/text`
+    pub text: String,
+
+    /// Array of function calls
+    #[serde(default)]
+    pub function_calls: Option<String>,
+
+    /// Name of a function call, relative to its array item
+    #[serde(default = "default_function_name")]
+    pub function_name: String,
+
+    /// Arguments of a function call, relative to its array item; a JSON object or a string holding one
+    #[serde(default = "default_function_args")]
+    pub function_args: String,
+
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+    #[serde(default)]
+    pub prompt_tokens: Option<String>,
+    #[serde(default)]
+    pub completion_tokens: Option<String>,
+    #[serde(default)]
+    pub total_tokens: Option<String>,
+}
+
+fn default_function_name() -> String {
+    "/name".to_string()
+}
+
+fn default_function_args() -> String {
+    "/arguments".to_string()
+}
+
+impl ResponseTemplate {
+    /// Answers whose text is at `text`
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            function_calls: None,
+            function_name: default_function_name(),
+            function_args: default_function_args(),
+            finish_reason: None,
+            prompt_tokens: None,
+            completion_tokens: None,
+            total_tokens: None,
+        }
+    }
+
+    /// Function calls are items of the array at `calls`, each with a name and arguments
+    pub fn with_function_calls(
+        mut self,
+        calls: impl Into<String>,
+        name: impl Into<String>,
+        args: impl Into<String>,
+    ) -> Self {
+        self.function_calls = Some(calls.into());
+        self.function_name = name.into();
+        self.function_args = args.into();
+        self
+    }
+
+    pub fn with_finish_reason(mut self, finish_reason: impl Into<String>) -> Self {
+        self.finish_reason = Some(finish_reason.into());
+        self
+    }
+
+    /// Token counts; the total defaults to the sum of the other two
+    pub fn with_usage(mut self, prompt_tokens: impl Into<String>, completion_tokens: impl Into<String>) -> Self {
+        self.prompt_tokens = Some(prompt_tokens.into());
+        self.completion_tokens = Some(completion_tokens.into());
+        self
+    }
+
+    pub fn with_total_tokens(mut self, total_tokens: impl Into<String>) -> Self {
+        self.total_tokens = Some(total_tokens.into());
+        self
+    }
+
+    /// Extract the answer from a response body
+    fn extract(&self, body: &Value) -> Result<LlmResponse> {
+        let mut response = LlmResponse::new();
+        match body.pointer(&self.text) {
+            Some(Value::String(text)) if !text.is_empty() => response.content = Some(Content::model_text(text.clone())),
+            Some(Value::String(_)) | Some(Value::Null) | None => {}
+            Some(other) => response.content = Some(Content::model_text(other.to_string())),
+        }
+
+        if let Some(calls) = self.function_calls.as_ref().and_then(|pointer| body.pointer(pointer)) {
+            for call in calls.as_array().into_iter().flatten() {
+                let name = call
+                    .pointer(&self.function_name)
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| crate::adk_error!(ModelError, "Function call without a name at '{}'", self.function_name))?;
+                let args = match call.pointer(&self.function_args) {
+                    Some(Value::String(args)) => serde_json::from_str(args).map_err(|e| {
+                        crate::adk_error!(ModelError, "Invalid arguments for function call '{}': {}", name, e)
+                    })?,
+                    Some(Value::Null) | None => json!({}),
+                    Some(args) => args.clone(),
+                };
+                response.function_calls.push(FunctionCall {
+                    name: name.to_string(),
+                    args,
+                });
+            }
+        }
+
+        response.finish_reason = match self.finish_reason.as_ref().and_then(|pointer| body.pointer(pointer)) {
+            Some(Value::String(reason)) => Some(convert_finish_reason(reason)),
+            _ if !response.function_calls.is_empty() => Some(FinishReason::FunctionCall),
+            _ => Some(FinishReason::Stop),
+        };
+
+        let count = |pointer: &Option<String>| {
+            pointer
+                .as_ref()
+                .and_then(|pointer| body.pointer(pointer))
+                .and_then(Value::as_u64)
+                .map(|count| count as u32)
+        };
+        let (prompt_tokens, completion_tokens) = (count(&self.prompt_tokens), count(&self.completion_tokens));
+        let total_tokens = count(&self.total_tokens).or_else(|| prompt_tokens.zip(completion_tokens).map(|(p, c)| p + c));
+        if prompt_tokens.is_some() || completion_tokens.is_some() || total_tokens.is_some() {
+            response.usage = Some(Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens,
+            });
+        }
+        Ok(response)
+    }
+}
+
+fn convert_finish_reason(reason: &str) -> FinishReason {
+    match reason.to_lowercase().as_str() {
+        "stop" | "end_turn" | "end" | "complete" | "completed" | "stop_sequence" => FinishReason::Stop,
+        "length" | "max_tokens" => FinishReason::MaxTokens,
+        "tool_calls" | "tool_use" | "function_call" => FinishReason::FunctionCall,
+        "safety" | "content_filter" => FinishReason::Safety,
+        _ => FinishReason::Other,
+    }
+}
+
+/// LLM behind a custom REST endpoint, described by templates
+#[derive(Debug, Clone)]
+pub struct GenericRestLlm {
+    model: String,
+    base_url: String,
+    client: Client,
+    /// Header name and value; the value may reference `${ENV_VARS}`
+    headers: Vec<(String, String)>,
+    request_template: Value,
+    response_template: ResponseTemplate,
+}
+
+impl GenericRestLlm {
+    /// Create a model posting to `base_url`, with an OpenAI-like default
+    /// request template and a response template reading `/text`
+    pub fn new(model: impl Into<String>, base_url: impl Into<String>) -> Self {
+        let client = HttpClientConfig::default()
+            .shared_client()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            model: model.into(),
+            base_url: base_url.into(),
+            client,
+            headers: Vec::new(),
+            request_template: json!({
+                "model": "{{model}}",
+                "messages": "{{messages}}",
+                "tools": "{{tools}}",
+                "temperature": "{{temperature}}",
+                "max_tokens": "{{max_output_tokens}}",
+            }),
+            response_template: ResponseTemplate::new("/text"),
+        }
+    }
+
+    /// Use a caller-provided HTTP client
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Use the shared HTTP client for the given connection settings (proxy, CA bundle, timeouts, pooling)
+    pub fn with_http_client_config(mut self, config: &HttpClientConfig) -> Result<Self> {
+        self.client = config.shared_client()?;
+        Ok(self)
+    }
+
+    /// Authenticate with a header, e.g. `("Authorization", "Bearer ${GATEWAY_TOKEN}")`
+    pub fn with_auth_header(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.with_header(name, value)
+    }
+
+    /// Send an extra header; the value may reference `${ENV_VARS}`
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_request_template(mut self, template: Value) -> Self {
+        self.request_template = template;
+        self
+    }
+
+    pub fn with_response_template(mut self, template: ResponseTemplate) -> Self {
+        self.response_template = template;
+        self
+    }
+
+    /// Template variables of a request
+    fn variables(&self, request: &LlmRequest) -> Map<String, Value> {
+        let mut messages = Vec::new();
+        let mut system = Vec::new();
+        for content in request.all_contents() {
+            let role = match content.role.as_str() {
+                "model" => "assistant",
+                "system" => "system",
+                _ => "user",
+            };
+            let mut text = String::new();
+            for part in &content.parts {
+                match part {
+                    ContentPart::Text { text: part_text } => text.push_str(part_text),
+                    ContentPart::FunctionCall { name, args } => {
+                        messages.push(json!({ "role": "assistant", "content": json!({ "name": name, "arguments": args }).to_string() }))
+                    }
+                    ContentPart::FunctionResponse { name, response } => {
+                        messages.push(json!({ "role": "tool", "name": name, "content": response.to_string() }))
+                    }
+                    other => debug!("Generic REST request leaves out a {:?} part", std::mem::discriminant(other)),
+                }
+            }
+            if text.is_empty() {
+                continue;
+            }
+            if role == "system" {
+                system.push(text.clone());
+            }
+            messages.push(json!({ "role": role, "content": text }));
+        }
+
+        let tools: Vec<Value> = request
+            .config
+            .tools
+            .iter()
+            .flat_map(|tool| &tool.function_declarations)
+            .map(|decl| json!({ "name": decl.name, "description": decl.description, "parameters": decl.parameters }))
+            .collect();
+        let config = &request.config;
+        let prompt = request.last_user_message().and_then(|content| {
+            let text: String = content.parts.iter().filter_map(ContentPart::as_text).collect();
+            (!text.is_empty()).then_some(text)
+        });
+
+        let mut variables = Map::new();
+        variables.insert("model".to_string(), json!(self.model));
+        variables.insert("prompt".to_string(), json!(prompt));
+        variables.insert("system".to_string(), json!((!system.is_empty()).then(|| system.join("\n"))));
+        variables.insert("messages".to_string(), Value::Array(messages));
+        variables.insert("contents".to_string(), serde_json::to_value(&request.contents).unwrap_or_default());
+        variables.insert("tools".to_string(), if tools.is_empty() { Value::Null } else { Value::Array(tools) });
+        variables.insert("temperature".to_string(), json!(config.temperature));
+        variables.insert("top_p".to_string(), json!(config.top_p));
+        variables.insert("top_k".to_string(), json!(config.top_k));
+        variables.insert("max_output_tokens".to_string(), json!(config.max_output_tokens));
+        variables.insert(
+            "stop_sequences".to_string(),
+            if config.stop_sequences.is_empty() { Value::Null } else { json!(config.stop_sequences) },
+        );
+        variables.insert("seed".to_string(), json!(config.seed));
+        variables
+    }
+
+    /// Send a request, turning error statuses into `ModelError`s
+    async fn post(&self, body: &Value) -> Result<reqwest::Response> {
+        let mut builder = self.client.post(&self.base_url).header("Content-Type", "application/json").json(body);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, expand_env(value)?);
+        }
+        let response = builder.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!("Generic REST model error: {} - {}", status, error_text);
+            return Err(crate::adk_error!(
+                ModelError,
+                "Generic REST model error: {} - {}",
+                status,
+                error_text
+            ));
+        }
+        Ok(response)
+    }
+}
+
+/// Render a request template; see the module documentation
+fn render(template: &Value, variables: &Map<String, Value>) -> Value {
+    match template {
+        Value::String(text) => match whole_placeholder(text) {
+            Some(name) => variables.get(name).cloned().unwrap_or(Value::Null),
+            None => Value::String(
+                PLACEHOLDER
+                    .replace_all(text, |captures: &regex::Captures| match variables.get(&captures[1]) {
+                        Some(Value::String(value)) => value.clone(),
+                        Some(Value::Null) | None => String::new(),
+                        Some(value) => value.to_string(),
+                    })
+                    .into_owned(),
+            ),
+        },
+        Value::Array(items) => Value::Array(items.iter().map(|item| render(item, variables)).collect()),
+        Value::Object(members) => Value::Object(
+            members
+                .iter()
+                .filter_map(|(name, value)| {
+                    let rendered = render(value, variables);
+                    let unset = rendered.is_null() && value.as_str().and_then(whole_placeholder).is_some();
+                    (!unset).then(|| (name.clone(), rendered))
+                })
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// The variable name if `text` is a single placeholder
+fn whole_placeholder(text: &str) -> Option<&str> {
+    let captures = PLACEHOLDER.captures(text.trim())?;
+    (captures.get(0)?.as_str().len() == text.trim().len()).then(|| captures.get(1).map(|name| name.as_str()))?
+}
+
+/// Replace `${NAME}` references with environment variables
+fn expand_env(value: &str) -> Result<String> {
+    let mut missing = None;
+    let expanded = ENV_REFERENCE.replace_all(value, |captures: &regex::Captures| {
+        std::env::var(&captures[1]).unwrap_or_else(|_| {
+            missing = Some(captures[1].to_string());
+            String::new()
+        })
+    });
+    match missing {
+        Some(name) => Err(crate::adk_error!(AuthError, "Environment variable {} is not set", name)),
+        None => Ok(expanded.into_owned()),
+    }
+}
+
+#[async_trait]
+impl BaseLlm for GenericRestLlm {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn supported_models() -> Vec<String> {
+        Vec::new()
+    }
+
+    async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+        debug!("Generating content with generic REST model: {}", self.model);
+
+        let body = render(&self.request_template, &self.variables(&request));
+
+        let _in_flight = InFlightRequest::start();
+        let response = self.post(&body).await?;
+
+        let response_body: Value = response.json().await?;
+        let llm_response = self.response_template.extract(&response_body)?;
+
+        info!("Successfully generated content with generic REST model");
+        Ok(llm_response)
+    }
+
+    /// Templates describe one response per request, so the answer arrives as a single item
+    async fn generate_content_stream(
+        &self,
+        request: LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send + 'static>>> {
+        warn!("Streaming not supported by generic REST models, falling back to non-streaming");
+        let response = self.generate_content(request).await?;
+        Ok(Box::pin(futures::stream::once(async move { Ok(response) })))
+    }
+
+    fn supports_streaming(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FunctionDeclaration, Tool};
+    use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_template_placeholders() {
+        let variables = Map::from_iter([
+            ("prompt".to_string(), json!("Hi")),
+            ("max_output_tokens".to_string(), json!(64)),
+            ("temperature".to_string(), Value::Null),
+        ]);
+        let template = json!({
+            "input": { "text": "{{prompt}}", "label": "user said: {{ prompt }}" },
+            "limits": ["{{max_output_tokens}}"],
+            "temperature": "{{temperature}}",
+        });
+        assert_eq!(
+            render(&template, &variables),
+            json!({ "input": { "text": "Hi", "label": "user said: Hi" }, "limits": [64] })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gateway_request_and_response_templates() {
+        type Seen = Arc<Mutex<Option<(HeaderMap, Value)>>>;
+        async fn infer(State(seen): State<Seen>, headers: HeaderMap, Json(body): Json<Value>) -> Json<Value> {
+            *seen.lock().unwrap() = Some((headers, body));
+            Json(json!({
+                "result": {
+                    "output": "Checking.",
+                    "calls": [{ "fn": "lookup", "input": "{\"id\": 7}" }],
+                    "status": "TOOL_CALLS"
+                },
+                "meter": { "in": 12, "out": 3 }
+            }))
+        }
+        let seen = Seen::default();
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("http://{}/v2/infer", listener.local_addr().unwrap());
+        let router = Router::new().route("/v2/infer", post(infer)).with_state(seen.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        std::env::set_var("ADK_TEST_GATEWAY_TOKEN", "t0k3n");
+        let llm = GenericRestLlm::new("house-model", url)
+            .with_auth_header("X-Gateway-Key", "Key ${ADK_TEST_GATEWAY_TOKEN}")
+            .with_request_template(json!({
+                "deployment": "{{model}}",
+                "conversation": "{{messages}}",
+                "functions": "{{tools}}",
+                "params": { "max_new_tokens": "{{max_output_tokens}}", "temperature": "{{temperature}}" }
+            }))
+            .with_response_template(
+                ResponseTemplate::new("/result/output")
+                    .with_function_calls("/result/calls", "/fn", "/input")
+                    .with_finish_reason("/result/status")
+                    .with_usage("/meter/in", "/meter/out"),
+            );
+        let mut request = LlmRequest::new("house-model").add_user_message("Where is order 7?").with_max_tokens(128);
+        request.config.tools.push(Tool {
+            function_declarations: vec![FunctionDeclaration {
+                name: "lookup".to_string(),
+                description: "Look up an order".to_string(),
+                parameters: json!({ "type": "object" }),
+            }],
+        });
+
+        let response = llm.generate_content(request).await.unwrap();
+        assert_eq!(response.get_text().as_deref(), Some("Checking."));
+        assert_eq!(response.function_calls[0].args, json!({ "id": 7 }));
+        assert!(matches!(response.finish_reason, Some(FinishReason::FunctionCall)));
+        assert_eq!(response.usage.unwrap().total_tokens, Some(15));
+
+        let (headers, body) = seen.lock().unwrap().take().unwrap();
+        assert_eq!(headers["x-gateway-key"], "Key t0k3n");
+        assert_eq!(
+            body,
+            json!({
+                "deployment": "house-model",
+                "conversation": [{ "role": "user", "content": "Where is order 7?" }],
+                "functions": [{ "name": "lookup", "description": "Look up an order", "parameters": { "type": "object" } }],
+                "params": { "max_new_tokens": 128 }
+            })
+        );
+    }
+}
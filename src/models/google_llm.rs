@@ -2,14 +2,20 @@
 
 use crate::{
     error::Result,
-    models::{BaseLlm, LlmRequest, LlmResponse, FinishReason, Usage},
-    types::{Content, ContentPart, FunctionCall},
+    events::{CitationSource, CitationSpan, Citations},
+    models::{
+        base_llm::LlmConfig, http_client::InFlightRequest, sse::json_event_stream, BaseLlm, HttpClientConfig, LlmRequest,
+        LlmResponse, FinishReason, RetryPolicy, SafetyRating, Usage,
+    },
+    types::{CodeExecutionOutcome, Content, ContentPart, FunctionCall, FunctionCallingMode, SafetySetting, Tool},
 };
 use async_trait::async_trait;
-use futures::Stream;
+use bytes::Bytes;
+use async_stream::stream;
+use futures::{Stream, StreamExt};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::{pin::Pin, time::Duration};
+use std::pin::Pin;
 use tracing::{debug, error, info, warn};
 
 /// Google AI/Gemini LLM implementation
@@ -21,20 +27,32 @@ pub struct GoogleLlm {
     region: Option<String>,
     client: Client,
     base_url: String,
+    retry_policy: RetryPolicy,
+    /// Context cache every request continues from, unless the request names one
+    cached_content: Option<String>,
 }
 
 /// Google AI API request format
 #[derive(Debug, Serialize)]
 struct GoogleAiRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GoogleAiContent>,
     contents: Vec<GoogleAiContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<GoogleAiTool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<GoogleAiToolConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    safety_settings: Vec<SafetySetting>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     generation_config: Option<GoogleAiGenerationConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cached_content: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
-struct GoogleAiContent {
+pub(super) struct GoogleAiContent {
+    #[serde(skip_serializing_if = "String::is_empty")]
     role: String,
     parts: Vec<GoogleAiPart>,
 }
@@ -45,6 +63,37 @@ enum GoogleAiPart {
     Text { text: String },
     FunctionCall { function_call: GoogleAiFunctionCall },
     FunctionResponse { function_response: GoogleAiFunctionResponse },
+    ExecutableCode { executable_code: GoogleAiExecutableCode },
+    CodeExecutionResult { code_execution_result: GoogleAiCodeExecutionResult },
+    InlineData { inline_data: GoogleAiInlineData },
+    FileData { file_data: GoogleAiFileData },
+}
+
+/// Inline media; the data is base64-encoded while the request body is written
+#[derive(Debug, Serialize)]
+struct GoogleAiInlineData {
+    mime_type: String,
+    #[serde(with = "crate::utils::base64_bytes")]
+    data: Bytes,
+}
+
+#[derive(Debug, Serialize)]
+struct GoogleAiFileData {
+    mime_type: String,
+    file_uri: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GoogleAiExecutableCode {
+    language: String,
+    code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GoogleAiCodeExecutionResult {
+    outcome: CodeExecutionOutcome,
+    #[serde(default)]
+    output: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -60,8 +109,11 @@ struct GoogleAiFunctionResponse {
 }
 
 #[derive(Debug, Serialize)]
-struct GoogleAiTool {
+pub(super) struct GoogleAiTool {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     function_declarations: Vec<GoogleAiFunctionDeclaration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_execution: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize)]
@@ -71,6 +123,18 @@ struct GoogleAiFunctionDeclaration {
     parameters: serde_json::Value,
 }
 
+#[derive(Debug, Serialize)]
+struct GoogleAiToolConfig {
+    function_calling_config: GoogleAiFunctionCallingConfig,
+}
+
+#[derive(Debug, Serialize)]
+struct GoogleAiFunctionCallingConfig {
+    mode: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    allowed_function_names: Vec<String>,
+}
+
 #[derive(Debug, Serialize)]
 struct GoogleAiGenerationConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -87,26 +151,128 @@ struct GoogleAiGenerationConfig {
     response_mime_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     response_schema: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i32>,
+}
+
+/// `countTokens` response format
+#[derive(Debug, Deserialize)]
+struct GoogleAiCountTokensResponse {
+    #[serde(alias = "totalTokens")]
+    total_tokens: u32,
 }
 
 /// Google AI API response format
 #[derive(Debug, Deserialize)]
 struct GoogleAiResponse {
-    candidates: Vec<GoogleAiCandidate>,
+    // Absent when the prompt is blocked
     #[serde(default)]
+    candidates: Vec<GoogleAiCandidate>,
+    #[serde(default, alias = "usageMetadata")]
     usage_metadata: Option<GoogleAiUsageMetadata>,
+    #[serde(default, alias = "promptFeedback")]
+    prompt_feedback: Option<GoogleAiPromptFeedback>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleAiPromptFeedback {
+    #[serde(default, alias = "blockReason")]
+    block_reason: Option<String>,
+    #[serde(default, alias = "safetyRatings")]
+    safety_ratings: Vec<GoogleAiSafetyRating>,
 }
 
 #[derive(Debug, Deserialize)]
 struct GoogleAiCandidate {
+    // Absent when the candidate is blocked
+    #[serde(default)]
     content: GoogleAiResponseContent,
+    #[serde(default, alias = "finishReason")]
     finish_reason: Option<String>,
-    safety_ratings: Option<Vec<GoogleAiSafetyRating>>,
+    #[serde(default, alias = "safetyRatings")]
+    safety_ratings: Vec<GoogleAiSafetyRating>,
+    #[serde(default, alias = "groundingMetadata")]
+    grounding_metadata: Option<GoogleAiGroundingMetadata>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GoogleAiGroundingMetadata {
+    #[serde(default, alias = "groundingChunks")]
+    grounding_chunks: Vec<GoogleAiGroundingChunk>,
+    #[serde(default, alias = "groundingSupports")]
+    grounding_supports: Vec<GoogleAiGroundingSupport>,
 }
 
 #[derive(Debug, Deserialize)]
+struct GoogleAiGroundingChunk {
+    #[serde(default)]
+    web: Option<GoogleAiGroundingSource>,
+    #[serde(default, alias = "retrievedContext")]
+    retrieved_context: Option<GoogleAiGroundingSource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleAiGroundingSource {
+    #[serde(default)]
+    uri: String,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleAiGroundingSupport {
+    segment: GoogleAiSegment,
+    #[serde(default, alias = "groundingChunkIndices")]
+    grounding_chunk_indices: Vec<usize>,
+    #[serde(default, alias = "confidenceScores")]
+    confidence_scores: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleAiSegment {
+    #[serde(default, alias = "startIndex")]
+    start_index: usize,
+    #[serde(default, alias = "endIndex")]
+    end_index: usize,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+impl From<&GoogleAiGroundingMetadata> for Citations {
+    fn from(metadata: &GoogleAiGroundingMetadata) -> Self {
+        Self {
+            sources: metadata
+                .grounding_chunks
+                .iter()
+                .map(|chunk| {
+                    let source = chunk.web.as_ref().or(chunk.retrieved_context.as_ref());
+                    CitationSource {
+                        uri: source.map(|s| s.uri.clone()).unwrap_or_default(),
+                        title: source.and_then(|s| s.title.clone()),
+                    }
+                })
+                .collect(),
+            spans: metadata
+                .grounding_supports
+                .iter()
+                .map(|support| CitationSpan {
+                    start_index: support.segment.start_index,
+                    end_index: support.segment.end_index,
+                    text: support.segment.text.clone(),
+                    source_indices: support.grounding_chunk_indices.clone(),
+                    confidence: support.confidence_scores.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[allow(dead_code)]
 struct GoogleAiResponseContent {
+    #[serde(default)]
     parts: Vec<GoogleAiResponsePart>,
+    #[serde(default)]
     role: String,
 }
 
@@ -114,7 +280,18 @@ struct GoogleAiResponseContent {
 #[serde(untagged)]
 enum GoogleAiResponsePart {
     Text { text: String },
-    FunctionCall { function_call: GoogleAiResponseFunctionCall },
+    FunctionCall {
+        #[serde(alias = "functionCall")]
+        function_call: GoogleAiResponseFunctionCall,
+    },
+    ExecutableCode {
+        #[serde(alias = "executableCode")]
+        executable_code: GoogleAiExecutableCode,
+    },
+    CodeExecutionResult {
+        #[serde(alias = "codeExecutionResult")]
+        code_execution_result: GoogleAiCodeExecutionResult,
+    },
 }
 
 #[derive(Debug, Deserialize)]
@@ -127,21 +304,35 @@ struct GoogleAiResponseFunctionCall {
 struct GoogleAiSafetyRating {
     category: String,
     probability: String,
+    #[serde(default)]
+    blocked: bool,
+}
+
+impl From<&GoogleAiSafetyRating> for SafetyRating {
+    fn from(rating: &GoogleAiSafetyRating) -> Self {
+        Self {
+            category: rating.category.clone(),
+            probability: rating.probability.clone(),
+            blocked: rating.blocked,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct GoogleAiUsageMetadata {
+    #[serde(default, alias = "promptTokenCount")]
     prompt_token_count: Option<u32>,
+    #[serde(default, alias = "candidatesTokenCount")]
     candidates_token_count: Option<u32>,
+    #[serde(default, alias = "totalTokenCount")]
     total_token_count: Option<u32>,
 }
 
 impl GoogleLlm {
     /// Create a new Google LLM instance
     pub fn new(model: impl Into<String>) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(60))
-            .build()
+        let client = HttpClientConfig::default()
+            .shared_client()
             .expect("Failed to create HTTP client");
 
         Self {
@@ -151,9 +342,64 @@ impl GoogleLlm {
             region: None,
             client,
             base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
+            retry_policy: RetryPolicy::default(),
+            cached_content: None,
         }
     }
 
+    /// Create a Google LLM instance from an `LlmConfig`
+    pub fn from_config(config: LlmConfig) -> Result<Self> {
+        let mut http_client = config.http_client;
+        if http_client.timeout_seconds.is_none() {
+            http_client.timeout_seconds = config.timeout_seconds;
+        }
+
+        let mut llm = Self::new(config.model).with_http_client_config(&http_client)?;
+        llm.api_key = config.api_key;
+        llm.project_id = config.project_id;
+        llm.region = config.region;
+        llm.retry_policy = config.retry;
+
+        if let Some(endpoint) = config.endpoint {
+            llm.base_url = endpoint;
+        } else if llm.project_id.is_some() && llm.region.is_some() {
+            llm = llm.use_vertex_ai();
+        }
+
+        Ok(llm)
+    }
+
+    /// Use a caller-provided HTTP client
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Use the shared HTTP client for the given connection settings (proxy, CA bundle, timeouts, pooling)
+    pub fn with_http_client_config(mut self, config: &HttpClientConfig) -> Result<Self> {
+        self.client = config.shared_client()?;
+        Ok(self)
+    }
+
+    /// Send requests to another Gemini-compatible endpoint, e.g. a proxy or a mock server
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Retry rate-limited, overloaded and failed requests with this policy
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Continue every request from a context cache, e.g. one created with
+    /// [`context_cache`](Self::context_cache); requests may name another one
+    pub fn with_cached_content(mut self, name: impl Into<String>) -> Self {
+        self.cached_content = Some(name.into());
+        self
+    }
+
     /// Set API key
     pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
         self.api_key = Some(api_key.into());
@@ -185,34 +431,32 @@ impl GoogleLlm {
 
     /// Convert ADK request to Google AI format
     fn convert_request(&self, request: &LlmRequest) -> GoogleAiRequest {
-        let contents = request.contents.iter().map(|content| {
-            let parts = content.parts.iter().map(|part| {
-                match part {
-                    ContentPart::Text { text } => GoogleAiPart::Text { text: text.clone() },
-                    _ => GoogleAiPart::Text { text: "[Unsupported content type]".to_string() },
+        let contents = convert_contents(&request.contents);
+        let tools = convert_tools(&request.config.tools, request.config.code_execution);
+
+        let tool_config = request.config.tool_config.as_ref().map(|config| GoogleAiToolConfig {
+            function_calling_config: GoogleAiFunctionCallingConfig {
+                mode: match config.function_calling_mode {
+                    FunctionCallingMode::Auto => "AUTO",
+                    FunctionCallingMode::Any => "ANY",
+                    FunctionCallingMode::None => "NONE",
                 }
-            }).collect();
+                .to_string(),
+                allowed_function_names: config.allowed_function_names.clone(),
+            },
+        });
 
-            GoogleAiContent {
-                role: content.role.clone(),
-                parts,
-            }
-        }).collect();
+        // Gemini takes the instruction without a role
+        let system_instruction = request.system_instruction.as_ref().map(|instruction| GoogleAiContent {
+            role: String::new(),
+            ..convert_contents(std::slice::from_ref(instruction)).remove(0)
+        });
 
-        let tools = if !request.config.tools.is_empty() {
-            Some(request.config.tools.iter().map(|tool| {
-                GoogleAiTool {
-                    function_declarations: tool.function_declarations.iter().map(|decl| {
-                        GoogleAiFunctionDeclaration {
-                            name: decl.name.clone(),
-                            description: decl.description.clone(),
-                            parameters: decl.parameters.clone(),
-                        }
-                    }).collect(),
-                }
-            }).collect())
-        } else {
-            None
+        // Tools and the instruction are part of the cache; Gemini rejects requests repeating them
+        let cached_content = request.config.cached_content.clone().or_else(|| self.cached_content.clone());
+        let (system_instruction, tools, tool_config) = match cached_content {
+            Some(_) => (None, None, None),
+            None => (system_instruction, tools, tool_config),
         };
 
         let generation_config = Some(GoogleAiGenerationConfig {
@@ -223,19 +467,36 @@ impl GoogleLlm {
             stop_sequences: request.config.stop_sequences.clone(),
             response_mime_type: request.config.response_mime_type.clone(),
             response_schema: request.config.response_schema.clone(),
+            seed: request.config.seed,
         });
 
         GoogleAiRequest {
+            system_instruction,
             contents,
             tools,
+            tool_config,
+            safety_settings: request.config.safety_settings.clone(),
             generation_config,
+            cached_content,
         }
     }
 
     /// Convert Google AI response to ADK format
     fn convert_response(&self, response: GoogleAiResponse) -> Result<LlmResponse> {
         if response.candidates.is_empty() {
-            return Ok(LlmResponse::new());
+            let mut llm_response = LlmResponse::new();
+            if let Some(feedback) = response.prompt_feedback {
+                llm_response.safety_ratings = feedback.safety_ratings.iter().map(SafetyRating::from).collect();
+                if let Some(block_reason) = feedback.block_reason {
+                    warn!("Gemini blocked the prompt: {}", block_reason);
+                    llm_response.finish_reason = Some(match block_reason.as_str() {
+                        "SAFETY" => FinishReason::Safety,
+                        _ => FinishReason::Other,
+                    });
+                    llm_response.block_reason = Some(block_reason);
+                }
+            }
+            return Ok(llm_response);
         }
 
         let candidate = &response.candidates[0];
@@ -243,13 +504,17 @@ impl GoogleLlm {
 
         // Convert content
         if !candidate.content.parts.is_empty() {
-            let mut text_parts = Vec::new();
+            let mut parts: Vec<ContentPart> = Vec::new();
             let mut function_calls = Vec::new();
 
             for part in &candidate.content.parts {
                 match part {
                     GoogleAiResponsePart::Text { text } => {
-                        text_parts.push(text.clone());
+                        // Merge adjacent text so code parts keep their position in the answer
+                        match parts.last_mut() {
+                            Some(ContentPart::Text { text: previous }) => previous.push_str(text),
+                            _ => parts.push(ContentPart::text(text.clone())),
+                        }
                     }
                     GoogleAiResponsePart::FunctionCall { function_call } => {
                         function_calls.push(FunctionCall {
@@ -257,11 +522,26 @@ impl GoogleLlm {
                             args: function_call.args.clone(),
                         });
                     }
+                    GoogleAiResponsePart::ExecutableCode { executable_code } => {
+                        parts.push(ContentPart::executable_code(
+                            executable_code.language.clone(),
+                            executable_code.code.clone(),
+                        ));
+                    }
+                    GoogleAiResponsePart::CodeExecutionResult { code_execution_result } => {
+                        parts.push(ContentPart::code_execution_result(
+                            code_execution_result.outcome,
+                            code_execution_result.output.clone(),
+                        ));
+                    }
                 }
             }
 
-            if !text_parts.is_empty() {
-                llm_response.content = Some(Content::model_text(text_parts.join("")));
+            if !parts.is_empty() {
+                llm_response.content = Some(Content {
+                    role: "model".to_string(),
+                    parts,
+                });
             }
 
             llm_response.function_calls = function_calls;
@@ -278,6 +558,15 @@ impl GoogleLlm {
             });
         }
 
+        llm_response.safety_ratings = candidate.safety_ratings.iter().map(SafetyRating::from).collect();
+
+        // Convert grounding metadata
+        llm_response.citations = candidate
+            .grounding_metadata
+            .as_ref()
+            .map(Citations::from)
+            .filter(|citations| !citations.is_empty());
+
         // Convert usage
         if let Some(usage_metadata) = response.usage_metadata {
             llm_response.usage = Some(Usage {
@@ -290,14 +579,83 @@ impl GoogleLlm {
         Ok(llm_response)
     }
 
-    /// Get the API endpoint URL
-    fn get_endpoint_url(&self) -> String {
-        if self.project_id.is_some() && self.region.is_some() {
+    /// Whether requests go to Vertex AI rather than the Gemini API
+    pub(super) fn is_vertex_ai(&self) -> bool {
+        self.project_id.is_some() && self.region.is_some()
+    }
+
+    /// Vertex AI project and region, if requests go to Vertex AI
+    pub(super) fn vertex_location(&self) -> Option<(&str, &str)> {
+        self.project_id.as_deref().zip(self.region.as_deref())
+    }
+
+    pub(super) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub(super) fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Get the API endpoint URL of a method, e.g. `generateContent`
+    fn get_endpoint_url(&self, method: &str) -> String {
+        if self.is_vertex_ai() {
             // Vertex AI endpoint
-            format!("{}/{}:generateContent", self.base_url, self.model)
+            format!("{}/{}:{}", self.base_url, self.model, method)
         } else {
             // Google AI endpoint
-            format!("{}/models/{}:generateContent", self.base_url, self.model)
+            format!("{}/models/{}:{}", self.base_url, self.model, method)
+        }
+    }
+
+    /// Send a JSON body with POST; see [`Self::send`]
+    async fn post(&self, url: &str, request: &impl Serialize) -> Result<reqwest::Response> {
+        self.send(|| self.client.post(url).json(request)).await
+    }
+
+    /// Send an authenticated request, rebuilding it for each attempt the retry
+    /// policy allows and turning error statuses into `ModelError`s
+    pub(super) async fn send(&self, build: impl Fn() -> reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let max_attempts = self.retry_policy.max_attempts;
+        let mut attempt = 1;
+        loop {
+            let sent = build()
+                .header("Authorization", self.get_auth_header()?)
+                .header("Content-Type", "application/json")
+                .send()
+                .await;
+            let response = match sent {
+                Ok(response) => response,
+                Err(e) if attempt < max_attempts && (e.is_timeout() || e.is_connect()) => {
+                    let delay = self.retry_policy.delay(attempt, None);
+                    warn!("Google AI request failed, retrying in {:?} (attempt {}/{}): {}", delay, attempt, max_attempts, e);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(response);
+            }
+            if attempt < max_attempts && RetryPolicy::is_retryable_status(status) {
+                let delay = self.retry_policy.delay(attempt, Some(response.headers()));
+                warn!("Google AI API returned {}, retrying in {:?} (attempt {}/{})", status, delay, attempt, max_attempts);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+
+            let error_text = response.text().await.unwrap_or_default();
+            error!("Google AI API error: {} - {}", status, error_text);
+            return Err(crate::adk_error!(
+                ModelError,
+                "Google AI API error: {} - {}",
+                status,
+                error_text
+            ));
         }
     }
 
@@ -316,6 +674,69 @@ impl GoogleLlm {
     }
 }
 
+/// Convert ADK contents to Google AI format
+pub(super) fn convert_contents(contents: &[Content]) -> Vec<GoogleAiContent> {
+    contents.iter().map(|content| {
+        let parts = content.parts.iter().map(|part| {
+            match part {
+                ContentPart::Text { text } => GoogleAiPart::Text { text: text.clone() },
+                ContentPart::FunctionCall { name, args } => GoogleAiPart::FunctionCall {
+                    function_call: GoogleAiFunctionCall { name: name.clone(), args: args.clone() },
+                },
+                ContentPart::FunctionResponse { name, response } => GoogleAiPart::FunctionResponse {
+                    function_response: GoogleAiFunctionResponse { name: name.clone(), response: response.clone() },
+                },
+                ContentPart::ExecutableCode { language, code } => GoogleAiPart::ExecutableCode {
+                    executable_code: GoogleAiExecutableCode { language: language.clone(), code: code.clone() },
+                },
+                ContentPart::CodeExecutionResult { outcome, output } => GoogleAiPart::CodeExecutionResult {
+                    code_execution_result: GoogleAiCodeExecutionResult {
+                        outcome: *outcome,
+                        output: output.clone(),
+                    },
+                },
+                ContentPart::Image { data, mime_type }
+                | ContentPart::Video { data, mime_type }
+                | ContentPart::Audio { data, mime_type }
+                | ContentPart::File { data, mime_type, .. } => GoogleAiPart::InlineData {
+                    inline_data: GoogleAiInlineData { mime_type: mime_type.clone(), data: data.clone() },
+                },
+                ContentPart::FileRef { uri, mime_type } => GoogleAiPart::FileData {
+                    file_data: GoogleAiFileData { mime_type: mime_type.clone(), file_uri: uri.clone() },
+                },
+            }
+        }).collect();
+
+        GoogleAiContent {
+            role: content.role.clone(),
+            parts,
+        }
+    }).collect()
+}
+
+/// Convert function declarations, plus the code execution tool if enabled, to Google AI format
+pub(super) fn convert_tools(tools: &[Tool], code_execution: bool) -> Option<Vec<GoogleAiTool>> {
+    let mut converted: Vec<GoogleAiTool> = tools.iter().map(|tool| {
+        GoogleAiTool {
+            function_declarations: tool.function_declarations.iter().map(|decl| {
+                GoogleAiFunctionDeclaration {
+                    name: decl.name.clone(),
+                    description: decl.description.clone(),
+                    parameters: decl.parameters.clone(),
+                }
+            }).collect(),
+            code_execution: None,
+        }
+    }).collect();
+    if code_execution {
+        converted.push(GoogleAiTool {
+            function_declarations: Vec::new(),
+            code_execution: Some(serde_json::json!({})),
+        });
+    }
+    if converted.is_empty() { None } else { Some(converted) }
+}
+
 #[async_trait]
 impl BaseLlm for GoogleLlm {
     fn model_name(&self) -> &str {
@@ -335,28 +756,10 @@ impl BaseLlm for GoogleLlm {
         debug!("Generating content with Google AI for model: {}", self.model);
 
         let google_request = self.convert_request(&request);
-        let url = self.get_endpoint_url();
-        let auth_header = self.get_auth_header()?;
-
-        let response = self.client
-            .post(&url)
-            .header("Authorization", auth_header)
-            .header("Content-Type", "application/json")
-            .json(&google_request)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            error!("Google AI API error: {} - {}", status, error_text);
-            return Err(crate::adk_error!(
-                ModelError,
-                "Google AI API error: {} - {}",
-                status,
-                error_text
-            ));
-        }
+        let url = self.get_endpoint_url("generateContent");
+
+        let _in_flight = InFlightRequest::start();
+        let response = self.post(&url, &google_request).await?;
 
         let google_response: GoogleAiResponse = response.json().await?;
         let llm_response = self.convert_response(google_response)?;
@@ -369,14 +772,56 @@ impl BaseLlm for GoogleLlm {
         &self,
         request: LlmRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send + 'static>>> {
-        // For now, just return the non-streaming response as a single item stream
-        warn!("Streaming not yet implemented for Google AI, falling back to non-streaming");
-        let response = self.generate_content(request).await?;
-        Ok(Box::pin(futures::stream::once(async move { Ok(response) })))
+        debug!("Streaming content with Google AI for model: {}", self.model);
+
+        let google_request = self.convert_request(&request);
+        let url = format!("{}?alt=sse", self.get_endpoint_url("streamGenerateContent"));
+
+        // Counted as in flight until the stream is finished or dropped
+        let in_flight = InFlightRequest::start();
+        let response = self.post(&url, &google_request).await?;
+        let mut frames = json_event_stream::<GoogleAiResponse, _, _>(response.bytes_stream());
+        let llm = self.clone();
+
+        Ok(Box::pin(stream! {
+            let _in_flight = in_flight;
+            while let Some(frame) = frames.next().await {
+                match frame.and_then(|frame| llm.convert_response(frame)) {
+                    // Each chunk carries the text generated since the previous one and
+                    // the usage so far; the chunk with a finish reason completes the answer
+                    Ok(mut chunk) => {
+                        chunk.is_partial = chunk.finish_reason.is_none();
+                        yield Ok(chunk);
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Counts with the `countTokens` method, which takes the tools and config into account
+    async fn count_tokens(&self, request: &LlmRequest) -> Result<u32> {
+        let mut body = serde_json::to_value(self.convert_request(request))?;
+        if !self.is_vertex_ai() {
+            // Google AI counts a full request only when it is wrapped with the model name
+            body["model"] = format!("models/{}", self.model).into();
+            body = serde_json::json!({ "generate_content_request": body });
+        }
+        let url = self.get_endpoint_url("countTokens");
+
+        let _in_flight = InFlightRequest::start();
+        let response = self.post(&url, &body).await?;
+
+        let count: GoogleAiCountTokensResponse = response.json().await?;
+        debug!("Google AI counted {} prompt tokens", count.total_tokens);
+        Ok(count.total_tokens)
     }
 
     fn supports_streaming(&self) -> bool {
-        true // Google AI supports streaming, but not implemented yet
+        true
     }
 
     fn supports_function_calling(&self) -> bool {
@@ -391,3 +836,297 @@ impl BaseLlm for GoogleLlm {
         self.model.contains("2.0")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::strategies::{arb_json, arb_model_part};
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_function_turns_become_gemini_parts() {
+        let call = FunctionCall { name: "get_weather".to_string(), args: json!({ "city": "Oslo" }) };
+        let request = LlmRequest::new("gemini-2.0-flash")
+            .add_content(Content::function_call(call))
+            .add_content(Content::function_response("get_weather", json!({ "temp": 12 })));
+        assert_eq!(request.contents[0].function_calls()[0].name, "get_weather");
+
+        let body = serde_json::to_value(GoogleLlm::new("gemini-2.0-flash").convert_request(&request)).unwrap();
+        assert_eq!(body["contents"][0]["role"], "model");
+        assert_eq!(
+            body["contents"][0]["parts"][0],
+            json!({ "function_call": { "name": "get_weather", "args": { "city": "Oslo" } } })
+        );
+        assert_eq!(body["contents"][1]["role"], "user");
+        assert_eq!(
+            body["contents"][1]["parts"][0],
+            json!({ "function_response": { "name": "get_weather", "response": { "temp": 12 } } })
+        );
+    }
+
+    #[test]
+    fn test_grounding_metadata_in_the_wire_format_becomes_citations() {
+        let llm = GoogleLlm::new("gemini-2.0-flash");
+        let response: GoogleAiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [{ "text": "Rust 1.0 shipped in 2015." }] },
+                "finishReason": "STOP",
+                "groundingMetadata": {
+                    "groundingChunks": [
+                        { "web": { "uri": "https://blog.rust-lang.org/2015/05/15/Rust-1.0.html", "title": "Rust 1.0" } },
+                        { "retrievedContext": { "uri": "gs://docs/history.pdf", "title": "History" } }
+                    ],
+                    "groundingSupports": [{
+                        "segment": { "startIndex": 0, "endIndex": 25, "text": "Rust 1.0 shipped in 2015." },
+                        "groundingChunkIndices": [0, 1],
+                        "confidenceScores": [0.9, 0.5]
+                    }]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let citations = llm.convert_response(response).unwrap().citations.unwrap();
+        assert_eq!(citations.sources[0].title.as_deref(), Some("Rust 1.0"));
+        assert_eq!(citations.sources[1].uri, "gs://docs/history.pdf");
+        let span = &citations.spans[0];
+        assert_eq!((span.start_index, span.end_index), (0, 25));
+        assert_eq!(span.source_indices, vec![0, 1]);
+        assert_eq!(span.confidence, vec![0.9, 0.5]);
+    }
+
+    #[test]
+    fn test_code_execution_parts_round_trip() {
+        let llm = GoogleLlm::new("gemini-2.0-flash");
+        let response: GoogleAiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        { "text": "Let me compute that." },
+                        { "executableCode": { "language": "PYTHON", "code": "print(6 * 7)" } },
+                        { "codeExecutionResult": { "outcome": "OUTCOME_OK", "output": "42\n" } },
+                        { "text": "The answer is 42." }
+                    ]
+                },
+                "finishReason": "STOP"
+            }]
+        }))
+        .unwrap();
+
+        let content = llm.convert_response(response).unwrap().content.unwrap();
+        assert_eq!(content.parts.len(), 4);
+        assert!(matches!(
+            &content.parts[2],
+            ContentPart::CodeExecutionResult { outcome: CodeExecutionOutcome::Ok, output } if output == "42\n"
+        ));
+
+        let mut request = LlmRequest::new("gemini-2.0-flash").add_content(content);
+        request.config.code_execution = true;
+        let body = serde_json::to_value(llm.convert_request(&request)).unwrap();
+        assert_eq!(body["contents"][0]["parts"][1]["executable_code"]["code"], "print(6 * 7)");
+        assert_eq!(body["contents"][0]["parts"][2]["code_execution_result"]["outcome"], "OUTCOME_OK");
+        assert_eq!(body["tools"][0]["code_execution"], serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_media_parts_inline_and_by_reference() {
+        let llm = GoogleLlm::new("gemini-2.0-flash");
+        let content = Content {
+            role: "user".to_string(),
+            parts: vec![
+                ContentPart::image(&b"\x89PNG"[..], "image/png"),
+                ContentPart::file_ref("gs://bucket/report.pdf", "application/pdf"),
+            ],
+        };
+        let request = LlmRequest::new("gemini-2.0-flash").add_content(content);
+        let body = serde_json::to_value(llm.convert_request(&request)).unwrap();
+        let parts = &body["contents"][0]["parts"];
+        assert_eq!(parts[0]["inline_data"], serde_json::json!({"mime_type": "image/png", "data": "iVBORw=="}));
+        assert_eq!(parts[1]["file_data"]["file_uri"], "gs://bucket/report.pdf");
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_partial_chunks_then_the_final_one() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        mock.push_text("one two three");
+
+        let chunks: Vec<LlmResponse> = mock
+            .llm("gemini-2.0-flash")
+            .generate_content_stream(LlmRequest::new("gemini-2.0-flash").add_user_message("Count to three"))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let texts: Vec<String> = chunks.iter().map(|chunk| chunk.get_text().unwrap()).collect();
+        assert_eq!(texts, ["one ", "two ", "three"]);
+        let partial: Vec<bool> = chunks.iter().map(|chunk| chunk.is_partial).collect();
+        assert_eq!(partial, [true, true, false]);
+
+        let last = chunks.last().unwrap();
+        assert!(matches!(last.finish_reason, Some(FinishReason::Stop)));
+        assert!(last.usage.as_ref().unwrap().completion_tokens.unwrap() > 0);
+        assert!(mock.requests()[0].streaming);
+    }
+
+    #[tokio::test]
+    async fn test_parses_stream_chunks_in_the_wire_format() {
+        // As sent by `streamGenerateContent?alt=sse`
+        let body = concat!(
+            "data: {\"candidates\": [{\"content\": {\"parts\": [{\"text\": \"Hello\"}],\"role\": \"model\"}}],",
+            "\"usageMetadata\": {\"promptTokenCount\": 5,\"totalTokenCount\": 5},\"modelVersion\": \"gemini-2.0-flash\"}\r\n\r\n",
+            "data: {\"candidates\": [{\"content\": {\"parts\": [{\"text\": \" world\"}],\"role\": \"model\"},",
+            "\"finishReason\": \"STOP\"}],\"usageMetadata\": {\"promptTokenCount\": 5,\"candidatesTokenCount\": 2,",
+            "\"totalTokenCount\": 7},\"modelVersion\": \"gemini-2.0-flash\"}\r\n\r\n",
+        );
+        let bytes = futures::stream::iter([Ok::<_, std::convert::Infallible>(Bytes::from(body))]);
+        let llm = GoogleLlm::new("gemini-2.0-flash");
+        let chunks: Vec<LlmResponse> = json_event_stream::<GoogleAiResponse, _, _>(bytes)
+            .map(|frame| llm.convert_response(frame.unwrap()).unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(chunks[0].get_text().as_deref(), Some("Hello"));
+        assert!(chunks[0].finish_reason.is_none());
+        assert_eq!(chunks[0].usage.as_ref().unwrap().prompt_tokens, Some(5));
+        assert!(matches!(chunks[1].finish_reason, Some(FinishReason::Stop)));
+        let usage = chunks[1].usage.as_ref().unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.total_tokens), (Some(5), Some(2), Some(7)));
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens_wraps_the_full_request() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        let request = LlmRequest::new("gemini-2.0-flash").add_user_message("How many tokens is this?");
+
+        let counted = mock.llm("gemini-2.0-flash").count_tokens(&request).await.unwrap();
+        assert!(counted > 0);
+        // Token counts are not model calls
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retries_rate_limits_and_overload() {
+        let mock = crate::testing::MockGeminiServer::start().await.unwrap();
+        mock.push_error(429, "Resource exhausted").push_error(503, "Overloaded").push_text("done");
+        let retry = RetryPolicy::new().with_backoff(std::time::Duration::from_millis(1), std::time::Duration::from_millis(5));
+
+        let llm = mock.llm("gemini-2.0-flash").with_retry_policy(retry.clone());
+        let response = llm.generate_content(LlmRequest::new("gemini-2.0-flash")).await.unwrap();
+        assert_eq!(response.get_text().as_deref(), Some("done"));
+        assert_eq!(mock.requests().len(), 3);
+
+        // Streams retry while opening, and errors outlast the attempts
+        mock.push_error(503, "Overloaded").push_text("streamed");
+        let mut stream = llm.generate_content_stream(LlmRequest::new("gemini-2.0-flash")).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap().get_text().as_deref(), Some("streamed"));
+
+        mock.push_error(503, "Overloaded").push_error(503, "Overloaded").push_text("too late");
+        let llm = llm.with_retry_policy(retry.with_max_attempts(2));
+        let error = llm.generate_content(LlmRequest::new("gemini-2.0-flash")).await.unwrap_err();
+        assert!(error.to_string().contains("503"));
+    }
+
+    #[test]
+    fn test_safety_settings_and_ratings() {
+        let llm = GoogleLlm::new("gemini-2.0-flash");
+        let request = LlmRequest::new("gemini-2.0-flash")
+            .with_safety_setting(SafetySetting::new("HARM_CATEGORY_HARASSMENT", "BLOCK_LOW_AND_ABOVE"))
+            .with_safety_setting(SafetySetting::new("HARM_CATEGORY_HARASSMENT", "BLOCK_ONLY_HIGH"));
+        let body = serde_json::to_value(llm.convert_request(&request)).unwrap();
+        assert_eq!(
+            body["safety_settings"],
+            serde_json::json!([{ "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH" }])
+        );
+
+        // A blocked candidate has ratings but no content
+        let response: GoogleAiResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "finish_reason": "SAFETY",
+                "safety_ratings": [
+                    { "category": "HARM_CATEGORY_HARASSMENT", "probability": "HIGH", "blocked": true },
+                    { "category": "HARM_CATEGORY_HATE_SPEECH", "probability": "NEGLIGIBLE" }
+                ]
+            }]
+        }))
+        .unwrap();
+        let converted = llm.convert_response(response).unwrap();
+        assert!(converted.is_safety_filtered());
+        assert!(converted.content.is_none());
+        assert_eq!(converted.safety_ratings.len(), 2);
+        assert!(converted.safety_ratings[0].blocked);
+
+        // A blocked prompt gets no candidates, only feedback
+        let response: GoogleAiResponse = serde_json::from_value(serde_json::json!({
+            "promptFeedback": {
+                "blockReason": "SAFETY",
+                "safetyRatings": [{ "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH", "blocked": true }]
+            }
+        }))
+        .unwrap();
+        let converted = llm.convert_response(response).unwrap();
+        assert!(converted.is_prompt_blocked());
+        assert!(converted.is_safety_filtered());
+        assert_eq!(converted.safety_ratings[0].category, "HARM_CATEGORY_DANGEROUS_CONTENT");
+    }
+
+    proptest! {
+        #[test]
+        fn test_model_content_survives_request_and_response_conversion(
+            parts in prop::collection::vec(arb_model_part(), 1..8),
+        ) {
+            let llm = GoogleLlm::new("gemini-2.0-flash");
+            let content = Content { role: "model".to_string(), parts: parts.clone() };
+            let request = LlmRequest::new("gemini-2.0-flash").add_content(content);
+            let body = serde_json::to_value(llm.convert_request(&request)).unwrap();
+            let response: GoogleAiResponse = serde_json::from_value(serde_json::json!({
+                "candidates": [{ "content": body["contents"][0], "finish_reason": "STOP" }]
+            }))
+            .unwrap();
+            let converted = llm.convert_response(response).unwrap();
+
+            // Function calls move out of the content and adjacent text merges
+            let mut expected_parts: Vec<ContentPart> = Vec::new();
+            let mut expected_calls = Vec::new();
+            for part in parts {
+                match (part, expected_parts.last_mut()) {
+                    (ContentPart::FunctionCall { name, args }, _) => expected_calls.push(FunctionCall { name, args }),
+                    (ContentPart::Text { text }, Some(ContentPart::Text { text: previous })) => previous.push_str(&text),
+                    (part, _) => expected_parts.push(part),
+                }
+            }
+            let parts_json = |parts: &[ContentPart]| serde_json::to_value(parts).unwrap();
+            prop_assert_eq!(
+                converted.content.map(|content| parts_json(&content.parts)),
+                (!expected_parts.is_empty()).then(|| parts_json(&expected_parts))
+            );
+            prop_assert_eq!(
+                serde_json::to_value(&converted.function_calls).unwrap(),
+                serde_json::to_value(&expected_calls).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_malformed_candidates_never_panic(
+            parts in prop::collection::vec(arb_json(), 0..6),
+            finish_reason in arb_json(),
+            grounding in arb_json(),
+            usage in arb_json(),
+        ) {
+            let llm = GoogleLlm::new("gemini-2.0-flash");
+            let payload = serde_json::json!({
+                "candidates": [{
+                    "content": { "role": "model", "parts": parts },
+                    "finish_reason": finish_reason,
+                    "grounding_metadata": grounding,
+                }],
+                "usage_metadata": usage,
+            });
+            if let Ok(response) = serde_json::from_value::<GoogleAiResponse>(payload) {
+                prop_assert!(llm.convert_response(response).is_ok());
+            }
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! HTTP client configuration shared by model backends
+//!
+//! Model instances built from equal configurations share one pooled
+//! `reqwest::Client` (see [`HttpClientConfig::shared_client`]), so connections
+//! and TLS sessions are reused across agents and model instances.
+
+use crate::error::Result;
+use once_cell::sync::Lazy;
+use reqwest::{Certificate, Client, Proxy};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// Environment variable for an explicit proxy URL
+pub const PROXY_ENV_VAR: &str = "ADK_HTTP_PROXY";
+
+/// Environment variable for a PEM-encoded CA bundle path
+pub const CA_BUNDLE_ENV_VAR: &str = "ADK_CA_BUNDLE";
+
+/// Connection settings used to build the HTTP client for a model backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpClientConfig {
+    /// Proxy URL applied to all requests (e.g. `http://proxy.corp:3128`)
+    pub proxy_url: Option<String>,
+
+    /// Path to a PEM-encoded CA bundle to trust in addition to the system roots
+    pub ca_bundle_path: Option<String>,
+
+    /// Overall request timeout in seconds
+    pub timeout_seconds: Option<u64>,
+
+    /// Connect timeout in seconds
+    pub connect_timeout_seconds: Option<u64>,
+
+    /// Maximum idle connections kept per host
+    pub pool_max_idle_per_host: Option<usize>,
+
+    /// How long idle pooled connections are kept open, in seconds
+    pub pool_idle_timeout_seconds: Option<u64>,
+
+    /// TCP keep-alive interval in seconds
+    pub tcp_keepalive_seconds: Option<u64>,
+
+    /// Disable Nagle's algorithm so small streamed requests are sent immediately
+    pub tcp_nodelay: bool,
+
+    /// Interval of HTTP/2 keep-alive pings on open connections, in seconds
+    pub http2_keep_alive_interval_seconds: Option<u64>,
+
+    /// Let HTTP/2 flow-control windows grow with the connection's bandwidth
+    pub http2_adaptive_window: bool,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            proxy_url: None,
+            ca_bundle_path: None,
+            timeout_seconds: Some(60),
+            connect_timeout_seconds: None,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout_seconds: Some(90),
+            tcp_keepalive_seconds: Some(60),
+            tcp_nodelay: true,
+            http2_keep_alive_interval_seconds: Some(30),
+            http2_adaptive_window: true,
+        }
+    }
+}
+
+impl HttpClientConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a configuration from `ADK_HTTP_PROXY` and `ADK_CA_BUNDLE`
+    pub fn from_env() -> Self {
+        Self {
+            proxy_url: std::env::var(PROXY_ENV_VAR).ok(),
+            ca_bundle_path: std::env::var(CA_BUNDLE_ENV_VAR).ok(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_proxy(mut self, proxy_url: impl Into<String>) -> Self {
+        self.proxy_url = Some(proxy_url.into());
+        self
+    }
+
+    pub fn with_ca_bundle(mut self, path: impl Into<String>) -> Self {
+        self.ca_bundle_path = Some(path.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.connect_timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    pub fn with_pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    pub fn with_pool_idle_timeout(mut self, timeout_seconds: u64) -> Self {
+        self.pool_idle_timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    pub fn with_tcp_keepalive(mut self, interval_seconds: u64) -> Self {
+        self.tcp_keepalive_seconds = Some(interval_seconds);
+        self
+    }
+
+    pub fn with_tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.tcp_nodelay = enabled;
+        self
+    }
+
+    pub fn with_http2_keep_alive_interval(mut self, interval_seconds: u64) -> Self {
+        self.http2_keep_alive_interval_seconds = Some(interval_seconds);
+        self
+    }
+
+    /// Pooled client for this configuration, shared with every other caller
+    /// using an equal configuration
+    pub fn shared_client(&self) -> Result<Client> {
+        let key = serde_json::to_string(self)?;
+        let mut clients = SHARED_CLIENTS.lock().expect("shared HTTP clients poisoned");
+        if let Some(client) = clients.get(&key) {
+            POOL_METRICS.shared_client_reuses.fetch_add(1, Ordering::Relaxed);
+            return Ok(client.clone());
+        }
+        let client = self.build_client()?;
+        clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    /// Build a `reqwest::Client` from this configuration
+    pub fn build_client(&self) -> Result<Client> {
+        let mut builder = Client::builder();
+
+        if let Some(timeout) = self.timeout_seconds {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+
+        if let Some(timeout) = self.connect_timeout_seconds {
+            builder = builder.connect_timeout(Duration::from_secs(timeout));
+        }
+
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+
+        builder = builder
+            .pool_idle_timeout(self.pool_idle_timeout_seconds.map(Duration::from_secs))
+            .tcp_keepalive(self.tcp_keepalive_seconds.map(Duration::from_secs))
+            .tcp_nodelay(self.tcp_nodelay)
+            .http2_adaptive_window(self.http2_adaptive_window);
+
+        if let Some(interval) = self.http2_keep_alive_interval_seconds {
+            builder = builder
+                .http2_keep_alive_interval(Duration::from_secs(interval))
+                .http2_keep_alive_while_idle(true);
+        }
+
+        if let Some(proxy_url) = &self.proxy_url {
+            let proxy = Proxy::all(proxy_url).map_err(|e| {
+                crate::adk_error!(ConfigError, "Invalid proxy URL '{}': {}", proxy_url, e)
+            })?;
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(path) = &self.ca_bundle_path {
+            let pem = std::fs::read(path).map_err(|e| {
+                crate::adk_error!(ConfigError, "Failed to read CA bundle '{}': {}", path, e)
+            })?;
+            let certificate = Certificate::from_pem(&pem).map_err(|e| {
+                crate::adk_error!(ConfigError, "Invalid CA bundle '{}': {}", path, e)
+            })?;
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| crate::adk_error!(ConfigError, "Failed to build HTTP client: {}", e))?;
+        POOL_METRICS.clients_built.fetch_add(1, Ordering::Relaxed);
+        Ok(client)
+    }
+}
+
+static SHARED_CLIENTS: Lazy<Mutex<HashMap<String, Client>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static POOL_METRICS: PoolMetrics = PoolMetrics {
+    clients_built: AtomicU64::new(0),
+    shared_client_reuses: AtomicU64::new(0),
+    requests: AtomicU64::new(0),
+    in_flight: AtomicU64::new(0),
+};
+
+struct PoolMetrics {
+    clients_built: AtomicU64,
+    shared_client_reuses: AtomicU64,
+    requests: AtomicU64,
+    in_flight: AtomicU64,
+}
+
+/// Snapshot of HTTP client pool usage by model backends
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpPoolStats {
+    /// Distinct pooled clients currently shared
+    pub shared_clients: usize,
+
+    /// Clients built (each owns its own connection pool)
+    pub clients_built: u64,
+
+    /// Model instances that reused an existing shared client
+    pub shared_client_reuses: u64,
+
+    /// Requests sent by model backends
+    pub requests: u64,
+
+    /// Requests currently awaiting a response
+    pub in_flight: u64,
+}
+
+/// Current HTTP client pool statistics
+pub fn http_pool_stats() -> HttpPoolStats {
+    HttpPoolStats {
+        shared_clients: SHARED_CLIENTS.lock().map(|clients| clients.len()).unwrap_or_default(),
+        clients_built: POOL_METRICS.clients_built.load(Ordering::Relaxed),
+        shared_client_reuses: POOL_METRICS.shared_client_reuses.load(Ordering::Relaxed),
+        requests: POOL_METRICS.requests.load(Ordering::Relaxed),
+        in_flight: POOL_METRICS.in_flight.load(Ordering::Relaxed),
+    }
+}
+
+/// Counts a model request as in flight until dropped
+pub(crate) struct InFlightRequest(());
+
+impl InFlightRequest {
+    pub(crate) fn start() -> Self {
+        POOL_METRICS.requests.fetch_add(1, Ordering::Relaxed);
+        POOL_METRICS.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(())
+    }
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        POOL_METRICS.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_client_with_proxy() {
+        let config = HttpClientConfig::new()
+            .with_proxy("http://proxy.example.com:3128")
+            .with_connect_timeout(5)
+            .with_pool_max_idle_per_host(4);
+        assert!(config.build_client().is_ok());
+    }
+
+    #[test]
+    fn test_equal_configs_share_a_client() {
+        let config = HttpClientConfig::new().with_pool_idle_timeout(17).with_tcp_keepalive(23);
+        config.shared_client().unwrap();
+        let before = http_pool_stats();
+        config.clone().shared_client().unwrap();
+        let after = http_pool_stats();
+        assert!(after.shared_client_reuses > before.shared_client_reuses);
+        assert_eq!(after.shared_clients, before.shared_clients);
+
+        let legacy: HttpClientConfig = serde_json::from_str(r#"{"timeout_seconds": 5}"#).unwrap();
+        assert!(legacy.tcp_nodelay);
+    }
+
+    #[test]
+    fn test_missing_ca_bundle_is_config_error() {
+        let config = HttpClientConfig::new().with_ca_bundle("/nonexistent/ca.pem");
+        let err = config.build_client().unwrap_err();
+        assert!(matches!(err, crate::error::AdkError::ConfigError(_)));
+    }
+}
@@ -0,0 +1,468 @@
+//! Pre-established live connections
+//!
+//! Opening a live connection costs a WebSocket and auth handshake, which
+//! delays the first audio frame of a new session. A [`LiveConnectionPool`]
+//! keeps a few connections per model open ahead of time: live runs take a
+//! warm one if available and the pool opens a replacement in the background.
+//! Idle connections are discarded once older than the configured limit, as
+//! providers close long-idle sessions on their own.
+
+use crate::{
+    error::Result,
+    models::{BaseLlm, LlmConnection},
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// Size and lifetime of pooled live connections
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LivePoolConfig {
+    /// Idle connections kept open per model; 0 disables pooling
+    pub size: usize,
+
+    /// Idle connections older than this are closed instead of handed out
+    pub max_idle_seconds: u64,
+
+    /// How often stale connections are replaced
+    pub maintenance_interval_seconds: u64,
+
+    /// Models warmed when the server starts; others are pooled after their first live run
+    pub models: Vec<String>,
+}
+
+impl Default for LivePoolConfig {
+    fn default() -> Self {
+        Self {
+            size: 0,
+            max_idle_seconds: 240,
+            maintenance_interval_seconds: 30,
+            models: Vec::new(),
+        }
+    }
+}
+
+impl LivePoolConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_max_idle(mut self, seconds: u64) -> Self {
+        self.max_idle_seconds = seconds;
+        self
+    }
+
+    pub fn with_maintenance_interval(mut self, seconds: u64) -> Self {
+        self.maintenance_interval_seconds = seconds;
+        self
+    }
+
+    /// Warm connections to `model` at server start
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.models.push(model.into());
+        self
+    }
+
+    fn max_idle(&self) -> Duration {
+        Duration::from_secs(self.max_idle_seconds)
+    }
+}
+
+/// Snapshot of live connection pool usage
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LivePoolStats {
+    /// Warm connections currently waiting, by model
+    pub idle: HashMap<String, usize>,
+
+    /// Live runs that got a warm connection
+    pub warm_hits: u64,
+
+    /// Live runs that had to wait for a new connection
+    pub cold_starts: u64,
+
+    /// Connections opened, warm or not
+    pub connections_opened: u64,
+
+    /// Idle connections closed because they went stale or inactive
+    pub connections_discarded: u64,
+
+    /// Failed attempts to open a connection
+    pub connect_failures: u64,
+}
+
+struct WarmConnection {
+    connection: Box<dyn LlmConnection>,
+    opened: Instant,
+}
+
+#[derive(Default)]
+struct PoolMetrics {
+    warm_hits: AtomicU64,
+    cold_starts: AtomicU64,
+    connections_opened: AtomicU64,
+    connections_discarded: AtomicU64,
+    connect_failures: AtomicU64,
+}
+
+#[derive(Default)]
+struct PoolInner {
+    config: RwLock<LivePoolConfig>,
+    idle: Mutex<HashMap<String, VecDeque<WarmConnection>>>,
+    /// Models seen by the pool, used to open replacements
+    models: Mutex<HashMap<String, Arc<dyn BaseLlm>>>,
+    /// Models with a refill in progress
+    refilling: Mutex<HashSet<String>>,
+    metrics: PoolMetrics,
+}
+
+/// Shared pool of pre-established live connections, keyed by model name
+#[derive(Clone, Default)]
+pub struct LiveConnectionPool {
+    inner: Arc<PoolInner>,
+}
+
+impl std::fmt::Debug for LiveConnectionPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LiveConnectionPool").field("config", &self.config()).finish()
+    }
+}
+
+impl LiveConnectionPool {
+    pub fn new(config: LivePoolConfig) -> Self {
+        let pool = Self::default();
+        pool.configure(config);
+        pool
+    }
+
+    pub fn config(&self) -> LivePoolConfig {
+        self.inner.config.read().expect("live pool config poisoned").clone()
+    }
+
+    /// Replace the configuration; idle connections beyond the new size are dropped
+    pub fn configure(&self, config: LivePoolConfig) {
+        let size = config.size;
+        *self.inner.config.write().expect("live pool config poisoned") = config;
+        let mut idle = self.inner.idle.lock().expect("live pool poisoned");
+        for connections in idle.values_mut() {
+            connections.truncate(size);
+        }
+    }
+
+    /// A live connection to `model`, warm if one is waiting
+    pub async fn acquire(&self, model: Arc<dyn BaseLlm>) -> Result<Box<dyn LlmConnection>> {
+        let config = self.config();
+        if config.size == 0 {
+            return model.create_live_connection().await;
+        }
+        let name = model.model_name().to_string();
+        self.remember(&name, model.clone());
+
+        let warm = self.take_idle(&name, config.max_idle()).await;
+        let connection = match warm {
+            Some(connection) => {
+                self.inner.metrics.warm_hits.fetch_add(1, Ordering::Relaxed);
+                debug!("Using warm live connection to {}", name);
+                connection
+            }
+            None => {
+                self.inner.metrics.cold_starts.fetch_add(1, Ordering::Relaxed);
+                self.open(model.as_ref()).await?
+            }
+        };
+        self.spawn_refill(name);
+        Ok(connection)
+    }
+
+    /// Open connections to `model` until the pool is full; returns how many were opened
+    pub async fn warm(&self, model: Arc<dyn BaseLlm>) -> Result<usize> {
+        let name = model.model_name().to_string();
+        self.remember(&name, model);
+        self.refill(&name).await
+    }
+
+    /// Close stale idle connections and top up every model seen so far
+    pub async fn maintain(&self) {
+        let max_idle = self.config().max_idle();
+        let names: Vec<String> = self.inner.models.lock().expect("live pool poisoned").keys().cloned().collect();
+        for name in names {
+            let stale = {
+                let mut idle = self.inner.idle.lock().expect("live pool poisoned");
+                let connections = idle.entry(name.clone()).or_default();
+                let (fresh, stale): (VecDeque<_>, VecDeque<_>) = std::mem::take(connections)
+                    .into_iter()
+                    .partition(|warm| warm.connection.is_active() && warm.opened.elapsed() < max_idle);
+                *connections = fresh;
+                stale
+            };
+            self.discard(stale.into()).await;
+            if let Err(e) = self.refill(&name).await {
+                warn!("Failed to warm live connections to {}: {}", name, e);
+            }
+        }
+    }
+
+    /// Run [`maintain`](Self::maintain) periodically until `cancel` fires
+    pub fn spawn_maintenance(&self, cancel: CancellationToken) -> JoinHandle<()> {
+        let pool = self.clone();
+        let interval = Duration::from_secs(self.config().maintenance_interval_seconds.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticker.tick() => pool.maintain().await,
+                }
+            }
+            let idle: Vec<_> = std::mem::take(&mut *pool.inner.idle.lock().expect("live pool poisoned"))
+                .into_values()
+                .flatten()
+                .collect();
+            pool.discard(idle).await;
+        })
+    }
+
+    pub fn stats(&self) -> LivePoolStats {
+        let metrics = &self.inner.metrics;
+        LivePoolStats {
+            idle: self
+                .inner
+                .idle
+                .lock()
+                .expect("live pool poisoned")
+                .iter()
+                .map(|(name, connections)| (name.clone(), connections.len()))
+                .collect(),
+            warm_hits: metrics.warm_hits.load(Ordering::Relaxed),
+            cold_starts: metrics.cold_starts.load(Ordering::Relaxed),
+            connections_opened: metrics.connections_opened.load(Ordering::Relaxed),
+            connections_discarded: metrics.connections_discarded.load(Ordering::Relaxed),
+            connect_failures: metrics.connect_failures.load(Ordering::Relaxed),
+        }
+    }
+
+    fn remember(&self, name: &str, model: Arc<dyn BaseLlm>) {
+        self.inner.models.lock().expect("live pool poisoned").entry(name.to_string()).or_insert(model);
+    }
+
+    /// The oldest usable idle connection, closing stale ones on the way
+    async fn take_idle(&self, name: &str, max_idle: Duration) -> Option<Box<dyn LlmConnection>> {
+        let mut stale = Vec::new();
+        let warm = {
+            let mut idle = self.inner.idle.lock().expect("live pool poisoned");
+            let connections = idle.get_mut(name)?;
+            loop {
+                match connections.pop_front() {
+                    Some(warm) if warm.connection.is_active() && warm.opened.elapsed() < max_idle => break Some(warm),
+                    Some(warm) => stale.push(warm),
+                    None => break None,
+                }
+            }
+        };
+        self.discard(stale).await;
+        warm.map(|warm| warm.connection)
+    }
+
+    async fn open(&self, model: &dyn BaseLlm) -> Result<Box<dyn LlmConnection>> {
+        match model.create_live_connection().await {
+            Ok(connection) => {
+                self.inner.metrics.connections_opened.fetch_add(1, Ordering::Relaxed);
+                Ok(connection)
+            }
+            Err(e) => {
+                self.inner.metrics.connect_failures.fetch_add(1, Ordering::Relaxed);
+                Err(e)
+            }
+        }
+    }
+
+    async fn discard(&self, connections: Vec<WarmConnection>) {
+        for mut warm in connections {
+            self.inner.metrics.connections_discarded.fetch_add(1, Ordering::Relaxed);
+            if let Err(e) = warm.connection.close().await {
+                debug!("Failed to close stale live connection: {}", e);
+            }
+        }
+    }
+
+    fn idle_count(&self, name: &str) -> usize {
+        self.inner.idle.lock().expect("live pool poisoned").get(name).map_or(0, VecDeque::len)
+    }
+
+    /// Open connections to a known model until it has `size` idle; one refill per model at a time
+    async fn refill(&self, name: &str) -> Result<usize> {
+        let Some(model) = self.inner.models.lock().expect("live pool poisoned").get(name).cloned() else {
+            return Ok(0);
+        };
+        if !self.inner.refilling.lock().expect("live pool poisoned").insert(name.to_string()) {
+            return Ok(0);
+        }
+        let mut opened = 0;
+        let result = loop {
+            if self.idle_count(name) >= self.config().size {
+                break Ok(opened);
+            }
+            match self.open(model.as_ref()).await {
+                Ok(connection) => {
+                    self.inner
+                        .idle
+                        .lock()
+                        .expect("live pool poisoned")
+                        .entry(name.to_string())
+                        .or_default()
+                        .push_back(WarmConnection {
+                            connection,
+                            opened: Instant::now(),
+                        });
+                    opened += 1;
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        self.inner.refilling.lock().expect("live pool poisoned").remove(name);
+        result
+    }
+
+    fn spawn_refill(&self, name: String) {
+        let pool = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = pool.refill(&name).await {
+                warn!("Failed to warm live connections to {}: {}", name, e);
+            }
+        });
+    }
+}
+
+static GLOBAL_LIVE_POOL: Lazy<LiveConnectionPool> = Lazy::new(LiveConnectionPool::default);
+
+/// Get the global live connection pool, used by live runs of LLM agents
+pub fn global_live_pool() -> &'static LiveConnectionPool {
+    &GLOBAL_LIVE_POOL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{LlmRequest, LlmResponse},
+        types::{Blob, Content},
+    };
+    use async_trait::async_trait;
+    use futures::Stream;
+    use std::{pin::Pin, sync::atomic::AtomicBool};
+
+    struct TestConnection {
+        active: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl LlmConnection for TestConnection {
+        async fn send_message(&mut self, _content: Content) -> Result<()> {
+            Ok(())
+        }
+
+        async fn send_realtime(&mut self, _blob: Blob) -> Result<()> {
+            Ok(())
+        }
+
+        async fn receive(&mut self) -> Result<Option<LlmResponse>> {
+            Ok(None)
+        }
+
+        async fn close(&mut self) -> Result<()> {
+            self.active.store(false, Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn is_active(&self) -> bool {
+            self.active.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Live model whose connections can be dropped by the "provider"
+    #[derive(Default)]
+    struct TestLiveModel {
+        connections: Mutex<Vec<Arc<AtomicBool>>>,
+    }
+
+    #[async_trait]
+    impl BaseLlm for TestLiveModel {
+        fn model_name(&self) -> &str {
+            "test-live"
+        }
+
+        fn supported_models() -> Vec<String> {
+            vec!["test-live".to_string()]
+        }
+
+        async fn generate_content(&self, _request: LlmRequest) -> Result<LlmResponse> {
+            Err(crate::adk_error!(ModelError, "Live only"))
+        }
+
+        async fn generate_content_stream(
+            &self,
+            _request: LlmRequest,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>> {
+            Err(crate::adk_error!(ModelError, "Live only"))
+        }
+
+        fn supports_live(&self) -> bool {
+            true
+        }
+
+        async fn create_live_connection(&self) -> Result<Box<dyn LlmConnection>> {
+            let active = Arc::new(AtomicBool::new(true));
+            self.connections.lock().unwrap().push(active.clone());
+            Ok(Box::new(TestConnection { active }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_live_runs_take_warm_connections_and_pool_refills() {
+        let model = Arc::new(TestLiveModel::default());
+        let pool = LiveConnectionPool::new(LivePoolConfig::new().with_size(2));
+        assert_eq!(pool.warm(model.clone()).await.unwrap(), 2);
+        assert_eq!(pool.stats().idle["test-live"], 2);
+
+        let connection = pool.acquire(model.clone()).await.unwrap();
+        assert!(connection.is_active());
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        let refilling = || !pool.inner.refilling.lock().unwrap().is_empty();
+        while (pool.stats().idle["test-live"] < 2 || refilling()) && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let stats = pool.stats();
+        assert_eq!((stats.warm_hits, stats.cold_starts, stats.connections_opened), (1, 0, 3));
+        assert_eq!(stats.idle["test-live"], 2);
+
+        // Connections the provider dropped are replaced instead of handed out
+        for active in model.connections.lock().unwrap().iter().skip(1) {
+            active.store(false, Ordering::Relaxed);
+        }
+        pool.maintain().await;
+        let stats = pool.stats();
+        assert_eq!(stats.connections_discarded, 2);
+        assert_eq!(stats.connections_opened, 5);
+        assert!(pool.acquire(model.clone()).await.unwrap().is_active());
+
+        let disabled = LiveConnectionPool::default();
+        disabled.acquire(model.clone()).await.unwrap();
+        assert!(disabled.stats().idle.is_empty());
+    }
+}
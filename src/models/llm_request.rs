@@ -2,7 +2,7 @@
 
 use crate::{
     tools::BaseTool,
-    types::{Content, GenerateContentConfig, Tool},
+    types::{Content, FunctionCallingMode, GenerateContentConfig, SafetySetting, Tool, ToolConfig},
 };
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, sync::Arc};
@@ -13,6 +13,11 @@ pub struct LlmRequest {
     /// Model name to use
     pub model: String,
 
+    /// Instruction steering the whole conversation, sent apart from the contents
+    /// where the provider supports it; its role is always `system`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_instruction: Option<Content>,
+
     /// Content to send to the model
     pub contents: Vec<Content>,
 
@@ -28,6 +33,7 @@ impl std::fmt::Debug for LlmRequest {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("LlmRequest")
             .field("model", &self.model)
+            .field("system_instruction", &self.system_instruction)
             .field("contents", &self.contents)
             .field("config", &self.config)
             .field("tools_count", &self.tools_dict.len())
@@ -40,12 +46,20 @@ impl LlmRequest {
     pub fn new(model: impl Into<String>) -> Self {
         Self {
             model: model.into(),
+            system_instruction: None,
             contents: Vec::new(),
             config: GenerateContentConfig::default(),
             tools_dict: HashMap::new(),
         }
     }
 
+    /// Set the system instruction
+    pub fn with_system_instruction(mut self, mut instruction: Content) -> Self {
+        instruction.role = "system".to_string();
+        self.system_instruction = Some(instruction);
+        self
+    }
+
     /// Add content to the request
     pub fn add_content(mut self, content: Content) -> Self {
         self.contents.push(content);
@@ -100,6 +114,12 @@ impl LlmRequest {
         self
     }
 
+    /// Set the sampling seed
+    pub fn with_seed(mut self, seed: i32) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
     /// Add stop sequences
     pub fn with_stop_sequences(mut self, sequences: Vec<String>) -> Self {
         self.config.stop_sequences = sequences;
@@ -113,6 +133,25 @@ impl LlmRequest {
         self
     }
 
+    /// Set the function calling configuration
+    pub fn with_tool_config(mut self, tool_config: ToolConfig) -> Self {
+        self.config.tool_config = Some(tool_config);
+        self
+    }
+
+    /// Continue from a provider context cache holding the request's static prefix
+    pub fn with_cached_content(mut self, name: impl Into<String>) -> Self {
+        self.config.cached_content = Some(name.into());
+        self
+    }
+
+    /// Set the blocking threshold of a harm category, replacing any earlier one
+    pub fn with_safety_setting(mut self, setting: SafetySetting) -> Self {
+        self.config.safety_settings.retain(|existing| existing.category != setting.category);
+        self.config.safety_settings.push(setting);
+        self
+    }
+
     /// Add tools to the request
     pub fn add_tools(mut self, tools: Vec<Arc<dyn BaseTool>>) -> Self {
         if tools.is_empty() {
@@ -160,6 +199,12 @@ impl LlmRequest {
         !self.config.tools.is_empty()
     }
 
+    /// The system instruction, if any, followed by the contents; for providers
+    /// taking the instruction as a `system` turn
+    pub fn all_contents(&self) -> impl Iterator<Item = &Content> {
+        self.system_instruction.iter().chain(&self.contents)
+    }
+
     /// Get the last user message
     pub fn last_user_message(&self) -> Option<&Content> {
         self.contents
@@ -219,6 +264,18 @@ impl LlmRequest {
             }
         }
 
+        // Validate function calling configuration
+        if let Some(tool_config) = &self.config.tool_config {
+            if !tool_config.allowed_function_names.is_empty()
+                && tool_config.function_calling_mode != FunctionCallingMode::Any
+            {
+                return Err(crate::adk_error!(
+                    ValidationError,
+                    "allowed_function_names requires function calling mode ANY"
+                ));
+            }
+        }
+
         // Validate max_output_tokens
         if let Some(max_tokens) = self.config.max_output_tokens {
             if max_tokens <= 0 {
@@ -250,6 +307,11 @@ impl LlmRequestBuilder {
         self
     }
 
+    pub fn system_instruction(mut self, text: impl Into<String>) -> Self {
+        self.request = self.request.with_system_instruction(Content::system_text(text));
+        self
+    }
+
     pub fn user_message(mut self, text: impl Into<String>) -> Self {
         self.request = self.request.add_user_message(text);
         self
@@ -1,6 +1,9 @@
 //! LLM response types
 
-use crate::types::{Content, FunctionCall};
+use crate::{
+    events::{Citations, Transcription},
+    types::{Content, FunctionCall},
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -24,6 +27,22 @@ pub struct LlmResponse {
 
     /// Additional metadata
     pub metadata: HashMap<String, serde_json::Value>,
+
+    /// Grounding sources reported by the provider
+    #[serde(default)]
+    pub citations: Option<Citations>,
+
+    /// Transcriptions of live audio reported by the connection
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transcriptions: Vec<Transcription>,
+
+    /// Per-category safety ratings of the response, or of the prompt when it was blocked
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub safety_ratings: Vec<SafetyRating>,
+
+    /// Why the provider blocked the prompt (e.g. `SAFETY`), if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_reason: Option<String>,
 }
 
 /// Reason why the model finished generating
@@ -44,6 +63,20 @@ pub enum FinishReason {
     Other,
 }
 
+/// Provider rating of content for one harm category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SafetyRating {
+    /// Harm category, e.g. `HARM_CATEGORY_HARASSMENT`
+    pub category: String,
+
+    /// Probability bucket, e.g. `NEGLIGIBLE` or `HIGH`
+    pub probability: String,
+
+    /// Whether this rating caused the content to be blocked
+    #[serde(default)]
+    pub blocked: bool,
+}
+
 /// Token usage statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Usage {
@@ -67,6 +100,10 @@ impl LlmResponse {
             finish_reason: None,
             usage: None,
             metadata: HashMap::new(),
+            citations: None,
+            transcriptions: Vec::new(),
+            safety_ratings: Vec::new(),
+            block_reason: None,
         }
     }
 
@@ -79,6 +116,10 @@ impl LlmResponse {
             finish_reason: Some(FinishReason::Stop),
             usage: None,
             metadata: HashMap::new(),
+            citations: None,
+            transcriptions: Vec::new(),
+            safety_ratings: Vec::new(),
+            block_reason: None,
         }
     }
 
@@ -94,6 +135,10 @@ impl LlmResponse {
             finish_reason: Some(FinishReason::FunctionCall),
             usage: None,
             metadata: HashMap::new(),
+            citations: None,
+            transcriptions: Vec::new(),
+            safety_ratings: Vec::new(),
+            block_reason: None,
         }
     }
 
@@ -106,6 +151,10 @@ impl LlmResponse {
             finish_reason: None,
             usage: None,
             metadata: HashMap::new(),
+            citations: None,
+            transcriptions: Vec::new(),
+            safety_ratings: Vec::new(),
+            block_reason: None,
         }
     }
 
@@ -165,6 +214,11 @@ impl LlmResponse {
         matches!(self.finish_reason, Some(FinishReason::Safety))
     }
 
+    /// Check if the provider blocked the prompt
+    pub fn is_prompt_blocked(&self) -> bool {
+        self.block_reason.is_some()
+    }
+
     /// Check if response was stopped due to max tokens
     pub fn is_max_tokens(&self) -> bool {
         matches!(self.finish_reason, Some(FinishReason::MaxTokens))
@@ -197,6 +251,19 @@ impl LlmResponse {
             self.usage = other.usage;
         }
 
+        // Grounding is reported on the final chunk
+        if other.citations.is_some() {
+            self.citations = other.citations;
+        }
+
+        // Ratings are reported per chunk; the latest cover the text so far
+        if !other.safety_ratings.is_empty() {
+            self.safety_ratings = other.safety_ratings;
+        }
+        if other.block_reason.is_some() {
+            self.block_reason = other.block_reason;
+        }
+
         // Merge metadata
         self.metadata.extend(other.metadata);
 
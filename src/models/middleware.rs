@@ -0,0 +1,270 @@
+//! Request/response middleware for LLM backends
+
+use crate::{
+    error::Result,
+    models::{BaseLlm, LlmConnection, LlmRequest, LlmResponse},
+};
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use std::{pin::Pin, sync::Arc};
+use tracing::{debug, info};
+
+/// Hook that runs around every call to a wrapped model
+#[async_trait]
+pub trait LlmMiddleware: Send + Sync {
+    /// Get the middleware name
+    fn name(&self) -> &str;
+
+    /// Inspect or rewrite the request before it is sent to the model
+    async fn before_request(&self, request: LlmRequest) -> Result<LlmRequest> {
+        Ok(request)
+    }
+
+    /// Inspect or rewrite a response (or streaming chunk) returned by the model
+    async fn after_response(&self, _request: &LlmRequest, response: LlmResponse) -> Result<LlmResponse> {
+        Ok(response)
+    }
+}
+
+/// Model wrapper that applies a stack of middleware to an inner model.
+///
+/// `before_request` hooks run in the order the middleware were added;
+/// `after_response` hooks run in reverse order, so the first middleware
+/// added is the outermost layer.
+pub struct LayeredLlm {
+    inner: Arc<dyn BaseLlm>,
+    middlewares: Vec<Arc<dyn LlmMiddleware>>,
+}
+
+impl LayeredLlm {
+    /// Wrap a model with an empty middleware stack
+    pub fn new(inner: Arc<dyn BaseLlm>) -> Self {
+        Self {
+            inner,
+            middlewares: Vec::new(),
+        }
+    }
+
+    /// Add a middleware layer
+    pub fn layer(mut self, middleware: Arc<dyn LlmMiddleware>) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+
+    /// Get the wrapped model
+    pub fn inner(&self) -> &Arc<dyn BaseLlm> {
+        &self.inner
+    }
+
+    /// Get the middleware stack
+    pub fn middlewares(&self) -> &[Arc<dyn LlmMiddleware>] {
+        &self.middlewares
+    }
+
+    async fn apply_before(&self, mut request: LlmRequest) -> Result<LlmRequest> {
+        for middleware in &self.middlewares {
+            request = middleware.before_request(request).await?;
+        }
+        Ok(request)
+    }
+
+    async fn apply_after(
+        middlewares: &[Arc<dyn LlmMiddleware>],
+        request: &LlmRequest,
+        mut response: LlmResponse,
+    ) -> Result<LlmResponse> {
+        for middleware in middlewares.iter().rev() {
+            response = middleware.after_response(request, response).await?;
+        }
+        Ok(response)
+    }
+}
+
+impl std::fmt::Debug for LayeredLlm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LayeredLlm")
+            .field("model", &self.inner.model_name())
+            .field(
+                "middlewares",
+                &self.middlewares.iter().map(|m| m.name()).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[async_trait]
+impl BaseLlm for LayeredLlm {
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn supported_models() -> Vec<String> {
+        Vec::new()
+    }
+
+    async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let request = self.apply_before(request).await?;
+        let response = self.inner.generate_content(request.clone()).await?;
+        Self::apply_after(&self.middlewares, &request, response).await
+    }
+
+    async fn generate_content_stream(
+        &self,
+        request: LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>> {
+        let request = self.apply_before(request).await?;
+        let stream = self.inner.generate_content_stream(request.clone()).await?;
+
+        let middlewares = Arc::new(self.middlewares.clone());
+        let request = Arc::new(request);
+
+        Ok(Box::pin(stream.then(move |chunk| {
+            let middlewares = middlewares.clone();
+            let request = request.clone();
+            async move {
+                match chunk {
+                    Ok(response) => Self::apply_after(&middlewares, &request, response).await,
+                    Err(e) => Err(e),
+                }
+            }
+        })))
+    }
+
+    /// Counts the request as rewritten by the middleware
+    async fn count_tokens(&self, request: &LlmRequest) -> Result<u32> {
+        let request = self.apply_before(request.clone()).await?;
+        self.inner.count_tokens(&request).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn supports_function_calling(&self) -> bool {
+        self.inner.supports_function_calling()
+    }
+
+    fn supports_multimodal(&self) -> bool {
+        self.inner.supports_multimodal()
+    }
+
+    fn supports_live(&self) -> bool {
+        self.inner.supports_live()
+    }
+
+    async fn create_live_connection(&self) -> Result<Box<dyn LlmConnection>> {
+        self.inner.create_live_connection().await
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.inner.validate()
+    }
+}
+
+/// Middleware that logs requests and responses through `tracing`
+#[derive(Debug, Clone, Default)]
+pub struct LoggingMiddleware;
+
+impl LoggingMiddleware {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl LlmMiddleware for LoggingMiddleware {
+    fn name(&self) -> &str {
+        "logging"
+    }
+
+    async fn before_request(&self, request: LlmRequest) -> Result<LlmRequest> {
+        info!(
+            model = %request.model,
+            contents = request.contents.len(),
+            tools = request.tools_dict.len(),
+            "Sending LLM request"
+        );
+        Ok(request)
+    }
+
+    async fn after_response(&self, request: &LlmRequest, response: LlmResponse) -> Result<LlmResponse> {
+        debug!(
+            model = %request.model,
+            is_partial = response.is_partial,
+            function_calls = response.function_calls.len(),
+            finish_reason = ?response.finish_reason,
+            "Received LLM response"
+        );
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EchoLlm;
+
+    #[async_trait]
+    impl BaseLlm for EchoLlm {
+        fn model_name(&self) -> &str {
+            "echo"
+        }
+
+        fn supported_models() -> Vec<String> {
+            vec!["echo".to_string()]
+        }
+
+        async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+            let text = request.last_user_message().map(|c| c.get_text()).unwrap_or_default();
+            Ok(LlmResponse::text(text))
+        }
+
+        async fn generate_content_stream(
+            &self,
+            request: LlmRequest,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>> {
+            let response = self.generate_content(request).await?;
+            Ok(Box::pin(futures::stream::once(async move { Ok(response) })))
+        }
+    }
+
+    struct Tag(&'static str);
+
+    #[async_trait]
+    impl LlmMiddleware for Tag {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn before_request(&self, request: LlmRequest) -> Result<LlmRequest> {
+            let text = request.last_user_message().map(|c| c.get_text()).unwrap_or_default();
+            Ok(request.clear_contents().add_user_message(format!("{}{}", text, self.0)))
+        }
+
+        async fn after_response(&self, _request: &LlmRequest, response: LlmResponse) -> Result<LlmResponse> {
+            let text = response.get_text().unwrap_or_default();
+            Ok(LlmResponse::text(format!("{}{}", text, self.0)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_order() {
+        let llm = LayeredLlm::new(Arc::new(EchoLlm))
+            .layer(Arc::new(Tag("a")))
+            .layer(Arc::new(Tag("b")));
+
+        let response = llm
+            .generate_content(LlmRequest::new("echo").add_user_message(">"))
+            .await
+            .unwrap();
+        assert_eq!(response.get_text().unwrap(), ">abba");
+
+        let mut stream = llm
+            .generate_content_stream(LlmRequest::new("echo").add_user_message(">"))
+            .await
+            .unwrap();
+        let chunk = stream.next().await.unwrap().unwrap();
+        assert_eq!(chunk.get_text().unwrap(), ">abba");
+    }
+}
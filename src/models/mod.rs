@@ -1,19 +1,74 @@
 //! Model system for LLM integration
 
 pub mod base_llm;
+pub mod catalog;
+pub mod context_cache;
+pub mod cost;
+pub mod embedding;
+pub mod estimate;
+pub mod fallback;
+pub mod generic_rest_llm;
 pub mod google_llm;
+pub mod http_client;
+pub mod live_pool;
 pub mod llm_request;
 pub mod llm_response;
+pub mod middleware;
+pub mod moderation;
+pub mod profiles;
+pub mod rate_limit;
 pub mod registry;
+pub mod retry;
+pub mod sse;
 
 #[cfg(feature = "anthropic")]
 pub mod anthropic_llm;
 
+#[cfg(feature = "azure-openai")]
+pub mod azure_openai_llm;
+
+#[cfg(feature = "bedrock")]
+pub mod bedrock_llm;
+
+#[cfg(feature = "ollama")]
+pub mod ollama_llm;
+
+#[cfg(feature = "openai")]
+pub mod openai_llm;
+
 pub use base_llm::{BaseLlm, LlmConnection};
+pub use catalog::{global_catalog, Modality, ModelCatalog, ModelMetadata, ModelPricing};
+pub use context_cache::{CacheConfig, CachedContent, ContextCache};
+pub use cost::{CostSummary, CostTracker, PricingTable, COST_METADATA_KEY};
+pub use embedding::{Embedder, GoogleEmbedder};
+pub use estimate::{estimate_request_tokens, estimate_tokens, PromptEstimate, ToolDeclarationEstimate, TurnEstimate, CHARS_PER_TOKEN, MEDIA_PART_TOKENS};
+pub use fallback::{is_fallback_error, FallbackLlm, FALLBACK_METADATA_KEY};
+pub use generic_rest_llm::{GenericRestLlm, ResponseTemplate};
 pub use google_llm::GoogleLlm;
+pub use http_client::{http_pool_stats, HttpClientConfig, HttpPoolStats};
+pub use live_pool::{global_live_pool, LiveConnectionPool, LivePoolConfig, LivePoolStats};
 pub use llm_request::{LlmRequest, LlmRequestBuilder};
-pub use llm_response::{LlmResponse, FinishReason, Usage};
+pub use llm_response::{LlmResponse, FinishReason, SafetyRating, Usage};
+pub use middleware::{LayeredLlm, LlmMiddleware, LoggingMiddleware};
+pub use moderation::{
+    GoogleModerator, HttpModerator, ModerationPolicy, ModerationResult, ModerationViolation, Moderator, DEFAULT_REFUSAL,
+};
+pub use profiles::{global_profiles, ModelProfile, ModelProfiles};
+pub use rate_limit::{RateLimit, RateLimitPermit, RateLimitedLlm, RateLimiter};
 pub use registry::{LlmRegistry, global_registry, create_model, get_model_info, list_available_models, ModelInfo};
+pub use retry::{parse_retry_after, RetryPolicy};
 
 #[cfg(feature = "anthropic")]
 pub use anthropic_llm::AnthropicLlm;
+
+#[cfg(feature = "azure-openai")]
+pub use azure_openai_llm::{AzureAdTokenProvider, AzureOpenAiLlm};
+
+#[cfg(feature = "bedrock")]
+pub use bedrock_llm::{AwsCredentials, BedrockLlm};
+
+#[cfg(feature = "ollama")]
+pub use ollama_llm::OllamaLlm;
+
+#[cfg(feature = "openai")]
+pub use openai_llm::OpenAiLlm;
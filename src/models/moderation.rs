@@ -0,0 +1,365 @@
+//! Content moderation
+//!
+//! Moderators score text per harm category (0.0 to 1.0); a
+//! [`ModerationPolicy`] turns the scores into a verdict and a refusal.
+
+use crate::{
+    error::Result,
+    models::{http_client::InFlightRequest, HttpClientConfig},
+};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use tracing::{debug, error};
+
+/// Model used for Gemini safety scoring
+pub const DEFAULT_MODERATION_MODEL: &str = "gemini-2.0-flash";
+
+/// Refusal used when no category-specific template is configured
+pub const DEFAULT_REFUSAL: &str = "Sorry, I can't help with that request.";
+
+/// Scores per harm category, from 0.0 (safe) to 1.0
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModerationResult {
+    pub scores: BTreeMap<String, f32>,
+}
+
+/// Scores text for harmful content
+#[async_trait]
+pub trait Moderator: Send + Sync {
+    async fn moderate(&self, text: &str) -> Result<ModerationResult>;
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SafetyResponse {
+    prompt_feedback: Option<SafetyFeedback>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SafetyFeedback {
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SafetyRating {
+    category: String,
+    probability: String,
+    probability_score: Option<f32>,
+}
+
+impl SafetyRating {
+    /// `HARM_CATEGORY_HATE_SPEECH` -> `hate_speech`
+    fn category(&self) -> String {
+        self.category.trim_start_matches("HARM_CATEGORY_").to_lowercase()
+    }
+
+    /// The numeric score when the API reports one, otherwise the midpoint of the probability bucket
+    fn score(&self) -> f32 {
+        self.probability_score.unwrap_or(match self.probability.as_str() {
+            "LOW" => 0.375,
+            "MEDIUM" => 0.625,
+            "HIGH" => 0.875,
+            _ => 0.125,
+        })
+    }
+}
+
+/// Moderation through Gemini's safety ratings of the prompt
+#[derive(Debug, Clone)]
+pub struct GoogleModerator {
+    model: String,
+    api_key: Option<String>,
+    client: Client,
+    base_url: String,
+}
+
+impl GoogleModerator {
+    pub fn new(model: impl Into<String>) -> Self {
+        let client = HttpClientConfig::default()
+            .shared_client()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            model: model.into(),
+            api_key: None,
+            client,
+            base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    fn get_auth_header(&self) -> Result<String> {
+        match self.api_key.clone().or_else(|| std::env::var("GOOGLE_API_KEY").ok()) {
+            Some(key) => Ok(format!("Bearer {}", key)),
+            None => Err(crate::adk_error!(
+                AuthError,
+                "No API key provided. Set GOOGLE_API_KEY environment variable or use with_api_key()"
+            )),
+        }
+    }
+}
+
+impl Default for GoogleModerator {
+    fn default() -> Self {
+        Self::new(DEFAULT_MODERATION_MODEL)
+    }
+}
+
+#[async_trait]
+impl Moderator for GoogleModerator {
+    async fn moderate(&self, text: &str) -> Result<ModerationResult> {
+        debug!("Scoring {} bytes with {} safety ratings", text.len(), self.model);
+
+        // Ratings are computed for the prompt; blocking is disabled so every category is scored
+        let categories = ["HARASSMENT", "HATE_SPEECH", "SEXUALLY_EXPLICIT", "DANGEROUS_CONTENT"];
+        let request = json!({
+            "contents": [{"role": "user", "parts": [{"text": text}]}],
+            "safetySettings": categories
+                .iter()
+                .map(|category| json!({"category": format!("HARM_CATEGORY_{}", category), "threshold": "BLOCK_NONE"}))
+                .collect::<Vec<_>>(),
+            "generationConfig": {"maxOutputTokens": 1},
+        });
+
+        let url = format!("{}/models/{}:generateContent", self.base_url, self.model);
+        let _in_flight = InFlightRequest::start();
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", self.get_auth_header()?)
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!("Moderation API error: {} - {}", status, error_text);
+            return Err(crate::adk_error!(ModelError, "Moderation API error: {} - {}", status, error_text));
+        }
+
+        let response: SafetyResponse = response.json().await?;
+        let ratings = response.prompt_feedback.map(|f| f.safety_ratings).unwrap_or_default();
+        Ok(ModerationResult {
+            scores: ratings.iter().map(|rating| (rating.category(), rating.score())).collect(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExternalModerationResponse {
+    results: Vec<ExternalModerationScores>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExternalModerationScores {
+    category_scores: BTreeMap<String, f32>,
+}
+
+/// Moderation through an external API speaking the OpenAI moderation format
+/// (`{"input": ...}` in, `{"results": [{"category_scores": {...}}]}` out)
+#[derive(Debug, Clone)]
+pub struct HttpModerator {
+    url: String,
+    api_key: Option<String>,
+    model: Option<String>,
+    client: Client,
+}
+
+impl HttpModerator {
+    pub fn new(url: impl Into<String>) -> Self {
+        let client = HttpClientConfig::default()
+            .shared_client()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            url: url.into(),
+            api_key: None,
+            model: None,
+            client,
+        }
+    }
+
+    /// Sent as a bearer token
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+}
+
+#[async_trait]
+impl Moderator for HttpModerator {
+    async fn moderate(&self, text: &str) -> Result<ModerationResult> {
+        let mut request = json!({ "input": text });
+        if let Some(model) = &self.model {
+            request["model"] = json!(model);
+        }
+
+        let _in_flight = InFlightRequest::start();
+        let mut builder = self.client.post(&self.url).json(&request);
+        if let Some(key) = &self.api_key {
+            builder = builder.bearer_auth(key);
+        }
+        let response = builder.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!("Moderation API error: {} - {}", status, error_text);
+            return Err(crate::adk_error!(ModelError, "Moderation API error: {} - {}", status, error_text));
+        }
+
+        let response: ExternalModerationResponse = response.json().await?;
+        let mut scores = BTreeMap::new();
+        for result in response.results {
+            for (category, score) in result.category_scores {
+                let entry = scores.entry(category).or_insert(0.0f32);
+                *entry = entry.max(score);
+            }
+        }
+        Ok(ModerationResult { scores })
+    }
+}
+
+/// A category whose score reached its threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModerationViolation {
+    pub category: String,
+    pub score: f32,
+    pub threshold: f32,
+}
+
+/// Thresholds per category and the refusals sent when they are exceeded
+#[derive(Debug, Clone, Default)]
+pub struct ModerationPolicy {
+    thresholds: HashMap<String, f32>,
+    default_threshold: Option<f32>,
+    refusals: HashMap<String, String>,
+    default_refusal: Option<String>,
+}
+
+impl ModerationPolicy {
+    /// A policy flagging any category scoring at least `threshold`
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            default_threshold: Some(threshold),
+            ..Self::default()
+        }
+    }
+
+    /// A policy flagging only the categories given thresholds with [`Self::with_threshold`]
+    pub fn per_category() -> Self {
+        Self::default()
+    }
+
+    pub fn with_threshold(mut self, category: impl Into<String>, threshold: f32) -> Self {
+        self.thresholds.insert(category.into(), threshold);
+        self
+    }
+
+    /// Refusal for a category; `{category}` is replaced with the category name
+    pub fn with_refusal(mut self, category: impl Into<String>, template: impl Into<String>) -> Self {
+        self.refusals.insert(category.into(), template.into());
+        self
+    }
+
+    /// Refusal for categories without their own template
+    pub fn with_default_refusal(mut self, template: impl Into<String>) -> Self {
+        self.default_refusal = Some(template.into());
+        self
+    }
+
+    /// Categories at or above their threshold, highest score first
+    pub fn violations(&self, result: &ModerationResult) -> Vec<ModerationViolation> {
+        let mut violations: Vec<ModerationViolation> = result
+            .scores
+            .iter()
+            .filter_map(|(category, &score)| {
+                let threshold = self.thresholds.get(category).copied().or(self.default_threshold)?;
+                (score >= threshold).then(|| ModerationViolation {
+                    category: category.clone(),
+                    score,
+                    threshold,
+                })
+            })
+            .collect();
+        violations.sort_by(|a, b| b.score.total_cmp(&a.score));
+        violations
+    }
+
+    /// Refusal for the most severe violation
+    pub fn refusal(&self, violation: &ModerationViolation) -> String {
+        self.refusals
+            .get(&violation.category)
+            .or(self.default_refusal.as_ref())
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_REFUSAL)
+            .replace("{category}", &violation.category.replace('_', " "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_thresholds_and_refusals() {
+        let result = ModerationResult {
+            scores: BTreeMap::from([
+                ("harassment".to_string(), 0.4),
+                ("hate_speech".to_string(), 0.9),
+                ("dangerous_content".to_string(), 0.2),
+            ]),
+        };
+
+        let policy = ModerationPolicy::new(0.8)
+            .with_threshold("harassment", 0.3)
+            .with_refusal("harassment", "Let's keep this respectful.")
+            .with_default_refusal("I can't help with {category}.");
+        let violations = policy.violations(&result);
+        assert_eq!(
+            violations.iter().map(|v| v.category.as_str()).collect::<Vec<_>>(),
+            ["hate_speech", "harassment"]
+        );
+        assert_eq!(policy.refusal(&violations[0]), "I can't help with hate speech.");
+        assert_eq!(policy.refusal(&violations[1]), "Let's keep this respectful.");
+
+        // Only configured categories are checked without a default threshold
+        let policy = ModerationPolicy::per_category().with_threshold("dangerous_content", 0.5);
+        assert!(policy.violations(&result).is_empty());
+    }
+
+    #[test]
+    fn test_gemini_ratings_are_normalized() {
+        let response: SafetyResponse = serde_json::from_value(json!({
+            "promptFeedback": {"safetyRatings": [
+                {"category": "HARM_CATEGORY_HATE_SPEECH", "probability": "HIGH"},
+                {"category": "HARM_CATEGORY_HARASSMENT", "probability": "LOW", "probabilityScore": 0.31},
+            ]}
+        }))
+        .unwrap();
+        let ratings = response.prompt_feedback.unwrap().safety_ratings;
+        assert_eq!((ratings[0].category(), ratings[0].score()), ("hate_speech".to_string(), 0.875));
+        assert_eq!((ratings[1].category(), ratings[1].score()), ("harassment".to_string(), 0.31));
+    }
+}
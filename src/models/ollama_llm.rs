@@ -0,0 +1,577 @@
+//! Ollama LLM implementation
+//!
+//! [`OllamaLlm`] talks to the chat API of a local Ollama server, so agents
+//! can run fully offline. Registered as `ollama/<model>`, e.g.
+//! `ollama/llama3.2`; the server address is taken from `OLLAMA_HOST`.
+//! Streamed responses are JSON lines rather than server-sent events.
+
+use crate::{
+    error::Result,
+    models::{
+        base_llm::LlmConfig, http_client::InFlightRequest, BaseLlm, FinishReason, HttpClientConfig, LlmRequest,
+        LlmResponse, Usage,
+    },
+    types::{Content, ContentPart, FunctionCall},
+    utils::base64_bytes::Base64,
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::pin::Pin;
+use tracing::{debug, error, info};
+
+/// Address of a default local Ollama server
+pub const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
+/// Prefix of registered Ollama model names
+pub const OLLAMA_MODEL_PREFIX: &str = "ollama/";
+
+/// Ollama LLM implementation
+#[derive(Debug, Clone)]
+pub struct OllamaLlm {
+    model: String,
+    client: Client,
+    base_url: String,
+}
+
+/// Chat API request format
+#[derive(Debug, Serialize)]
+struct OllamaRequest {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OllamaTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Value>,
+    options: OllamaOptions,
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaMessage {
+    role: &'static str,
+    content: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+    /// Tool whose result the message carries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+}
+
+impl OllamaMessage {
+    fn new(role: &'static str) -> Self {
+        Self {
+            role,
+            content: String::new(),
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            tool_name: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaTool {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: OllamaFunctionDeclaration,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaFunctionDeclaration {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i32>,
+}
+
+/// Chat API response format; also each line of a streamed response
+#[derive(Debug, Deserialize)]
+struct OllamaResponse {
+    #[serde(default)]
+    message: Option<OllamaResponseMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaResponseMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<OllamaToolCall>,
+}
+
+impl OllamaResponse {
+    fn usage(&self) -> Option<Usage> {
+        if self.prompt_eval_count.is_none() && self.eval_count.is_none() {
+            return None;
+        }
+        Some(Usage {
+            prompt_tokens: self.prompt_eval_count,
+            completion_tokens: self.eval_count,
+            total_tokens: Some(self.prompt_eval_count.unwrap_or(0) + self.eval_count.unwrap_or(0)),
+        })
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        if !self.done {
+            return None;
+        }
+        Some(match self.done_reason.as_deref() {
+            Some("length") => FinishReason::MaxTokens,
+            _ if self.message.as_ref().is_some_and(|message| !message.tool_calls.is_empty()) => {
+                FinishReason::FunctionCall
+            }
+            Some("stop") | None => FinishReason::Stop,
+            Some(_) => FinishReason::Other,
+        })
+    }
+}
+
+impl OllamaLlm {
+    /// Create a new Ollama LLM instance; an `ollama/` prefix is removed from the model name
+    pub fn new(model: impl Into<String>) -> Self {
+        let client = HttpClientConfig::default()
+            .shared_client()
+            .expect("Failed to create HTTP client");
+        let model = model.into();
+
+        Self {
+            model: model.strip_prefix(OLLAMA_MODEL_PREFIX).unwrap_or(&model).to_string(),
+            client,
+            base_url: OLLAMA_BASE_URL.to_string(),
+        }
+    }
+
+    /// Create an Ollama LLM instance from an `LlmConfig`
+    pub fn from_config(config: LlmConfig) -> Result<Self> {
+        let mut http_client = config.http_client;
+        if http_client.timeout_seconds.is_none() {
+            http_client.timeout_seconds = config.timeout_seconds;
+        }
+
+        let mut llm = Self::new(config.model).with_http_client_config(&http_client)?;
+        if let Some(endpoint) = config.endpoint {
+            llm = llm.with_base_url(endpoint);
+        }
+        Ok(llm)
+    }
+
+    /// Use a caller-provided HTTP client
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Use the shared HTTP client for the given connection settings (proxy, CA bundle, timeouts, pooling)
+    pub fn with_http_client_config(mut self, config: &HttpClientConfig) -> Result<Self> {
+        self.client = config.shared_client()?;
+        Ok(self)
+    }
+
+    /// Send requests to another Ollama server; a bare `host:port` (as in `OLLAMA_HOST`) means HTTP
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into();
+        let base_url = base_url.trim_end_matches('/');
+        self.base_url = if base_url.contains("://") {
+            base_url.to_string()
+        } else {
+            format!("http://{}", base_url)
+        };
+        self
+    }
+
+    /// Convert ADK request to chat API format
+    fn convert_request(&self, request: &LlmRequest, stream: bool) -> Result<OllamaRequest> {
+        let mut messages = Vec::new();
+
+        for content in request.all_contents() {
+            let mut message = OllamaMessage::new(match content.role.as_str() {
+                "model" => "assistant",
+                "system" => "system",
+                _ => "user",
+            });
+            for part in &content.parts {
+                match part {
+                    ContentPart::Text { text } => message.content.push_str(text),
+                    ContentPart::FunctionCall { name, args } => message.tool_calls.push(OllamaToolCall {
+                        function: OllamaFunctionCall {
+                            name: name.clone(),
+                            arguments: args.clone(),
+                        },
+                    }),
+                    // Tool results are messages of their own
+                    ContentPart::FunctionResponse { name, response } => {
+                        let mut result = OllamaMessage::new("tool");
+                        result.content = response.to_string();
+                        result.tool_name = Some(name.clone());
+                        messages.push(result);
+                    }
+                    ContentPart::Image { data, .. } => message.images.push(Base64(data).to_string()),
+                    ContentPart::ExecutableCode { language, code } => {
+                        message.content.push_str(&format!("```{}\n{}\n```", language.to_lowercase(), code))
+                    }
+                    ContentPart::CodeExecutionResult { output, .. } => message.content.push_str(output),
+                    part => {
+                        return Err(crate::adk_error!(
+                            ModelError,
+                            "Ollama does not support this content part: {:?}",
+                            part
+                        ))
+                    }
+                }
+            }
+            if !message.content.is_empty() || !message.images.is_empty() || !message.tool_calls.is_empty() {
+                messages.push(message);
+            }
+        }
+
+        let tools = request
+            .config
+            .tools
+            .iter()
+            .flat_map(|tool| &tool.function_declarations)
+            .map(|decl| OllamaTool {
+                kind: "function",
+                function: OllamaFunctionDeclaration {
+                    name: decl.name.clone(),
+                    description: decl.description.clone(),
+                    parameters: decl.parameters.clone(),
+                },
+            })
+            .collect();
+
+        let format = match (&request.config.response_schema, request.config.response_mime_type.as_deref()) {
+            (Some(schema), _) => Some(schema.clone()),
+            (None, Some("application/json")) => Some(json!("json")),
+            _ => None,
+        };
+
+        Ok(OllamaRequest {
+            model: self.model.clone(),
+            messages,
+            tools,
+            format,
+            options: OllamaOptions {
+                temperature: request.config.temperature,
+                top_p: request.config.top_p,
+                top_k: request.config.top_k,
+                num_predict: request.config.max_output_tokens,
+                stop: request.config.stop_sequences.clone(),
+                seed: request.config.seed,
+            },
+            stream,
+        })
+    }
+
+    /// Convert chat API response to ADK format
+    fn convert_response(&self, response: OllamaResponse) -> Result<LlmResponse> {
+        if let Some(error) = &response.error {
+            return Err(crate::adk_error!(ModelError, "Ollama error: {}", error));
+        }
+        let mut llm_response = LlmResponse::new();
+        llm_response.finish_reason = response.finish_reason();
+        llm_response.usage = response.usage();
+
+        if let Some(message) = response.message {
+            if !message.content.is_empty() {
+                llm_response.content = Some(Content::model_text(message.content));
+            }
+            llm_response.function_calls = message.tool_calls.into_iter().map(FunctionCall::from).collect();
+        }
+        Ok(llm_response)
+    }
+
+    /// Send a request, turning error statuses into `ModelError`s
+    async fn post(&self, request: &OllamaRequest) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!("Ollama API error: {} - {}", status, error_text);
+            return Err(crate::adk_error!(
+                ModelError,
+                "Ollama API error: {} - {}",
+                status,
+                error_text
+            ));
+        }
+        Ok(response)
+    }
+}
+
+impl From<OllamaToolCall> for FunctionCall {
+    fn from(call: OllamaToolCall) -> Self {
+        Self {
+            name: call.function.name,
+            args: if call.function.arguments.is_null() {
+                json!({})
+            } else {
+                call.function.arguments
+            },
+        }
+    }
+}
+
+/// Parse a stream of JSON lines. A malformed line ends the stream with a
+/// `ModelError`; transport errors surface as `NetworkError`s.
+fn json_lines_stream<T, S, E>(bytes: S) -> Pin<Box<dyn Stream<Item = Result<T>> + Send>>
+where
+    T: DeserializeOwned + Send + 'static,
+    S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    Box::pin(stream! {
+        let mut buffer = Vec::new();
+        let mut bytes = Box::pin(bytes);
+
+        loop {
+            let finished = match bytes.next().await {
+                Some(Ok(chunk)) => {
+                    buffer.extend_from_slice(&chunk);
+                    false
+                }
+                Some(Err(e)) => {
+                    yield Err(crate::adk_error!(NetworkError, "Stream transport error: {}", e));
+                    return;
+                }
+                // A last line may lack its newline
+                None => {
+                    buffer.push(b'\n');
+                    true
+                }
+            };
+
+            while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                if line.trim_ascii().is_empty() {
+                    continue;
+                }
+                match serde_json::from_slice(&line) {
+                    Ok(value) => yield Ok(value),
+                    Err(e) => {
+                        yield Err(crate::adk_error!(ModelError, "Malformed stream line: {}", e));
+                        return;
+                    }
+                }
+            }
+
+            if finished {
+                return;
+            }
+        }
+    })
+}
+
+/// Turn a streamed chat into ADK responses: a partial response per line of
+/// text, then a final one with the tool calls, finish reason and usage
+fn response_stream<S, E>(bytes: S) -> Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    let mut lines = json_lines_stream::<OllamaResponse, _, _>(bytes);
+    Box::pin(stream! {
+        let mut function_calls = Vec::new();
+
+        while let Some(line) = lines.next().await {
+            let mut line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            if let Some(error) = line.error {
+                yield Err(crate::adk_error!(ModelError, "Ollama error: {}", error));
+                return;
+            }
+            if let Some(message) = line.message.as_mut() {
+                if !message.content.is_empty() {
+                    yield Ok(LlmResponse::partial_text(std::mem::take(&mut message.content)));
+                }
+                function_calls.extend(message.tool_calls.drain(..).map(FunctionCall::from));
+            }
+            if line.done {
+                let mut response = LlmResponse::new();
+                response.finish_reason = match (line.finish_reason(), function_calls.is_empty()) {
+                    (Some(FinishReason::Stop), false) => Some(FinishReason::FunctionCall),
+                    (reason, _) => reason,
+                };
+                response.usage = line.usage();
+                response.function_calls = function_calls;
+                yield Ok(response);
+                return;
+            }
+        }
+    })
+}
+
+#[async_trait]
+impl BaseLlm for OllamaLlm {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn supported_models() -> Vec<String> {
+        vec![r"ollama/.*".to_string()]
+    }
+
+    async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+        debug!("Generating content with Ollama for model: {}", self.model);
+
+        let ollama_request = self.convert_request(&request, false)?;
+
+        let _in_flight = InFlightRequest::start();
+        let response = self.post(&ollama_request).await?;
+
+        let ollama_response: OllamaResponse = response.json().await?;
+        let llm_response = self.convert_response(ollama_response)?;
+
+        info!("Successfully generated content with Ollama");
+        Ok(llm_response)
+    }
+
+    async fn generate_content_stream(
+        &self,
+        request: LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send + 'static>>> {
+        debug!("Streaming content with Ollama for model: {}", self.model);
+
+        let ollama_request = self.convert_request(&request, true)?;
+
+        // Counted as in flight until the stream is finished or dropped
+        let in_flight = InFlightRequest::start();
+        let response = self.post(&ollama_request).await?;
+        let mut responses = response_stream(response.bytes_stream());
+
+        Ok(Box::pin(stream! {
+            let _in_flight = in_flight;
+            while let Some(response) = responses.next().await {
+                yield response;
+            }
+        }))
+    }
+
+    fn supports_multimodal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FunctionDeclaration, Tool};
+
+    #[test]
+    fn test_request_maps_tools_and_tool_turns() {
+        let llm = OllamaLlm::new("ollama/llama3.2").with_base_url("127.0.0.1:11434");
+        assert_eq!(llm.model_name(), "llama3.2");
+        assert_eq!(llm.base_url, "http://127.0.0.1:11434");
+
+        let mut request = LlmRequest::new("ollama/llama3.2")
+            .add_user_message("Where is order 7?")
+            .add_content(Content::function_call(FunctionCall {
+                name: "lookup".to_string(),
+                args: json!({ "id": 7 }),
+            }))
+            .add_content(Content::function_response("lookup", json!({ "status": "shipped" })))
+            .with_max_tokens(64);
+        request.config.tools.push(Tool {
+            function_declarations: vec![FunctionDeclaration {
+                name: "lookup".to_string(),
+                description: "Look up an order".to_string(),
+                parameters: json!({ "type": "object" }),
+            }],
+        });
+
+        let body = serde_json::to_value(llm.convert_request(&request, false).unwrap()).unwrap();
+        assert_eq!(body["model"], "llama3.2");
+        assert_eq!(body["messages"][1]["tool_calls"][0]["function"]["arguments"], json!({ "id": 7 }));
+        assert_eq!(body["messages"][2], json!({
+            "role": "tool",
+            "content": "{\"status\":\"shipped\"}",
+            "tool_name": "lookup",
+        }));
+        assert_eq!(body["tools"][0]["type"], "function");
+        assert_eq!(body["tools"][0]["function"]["parameters"], json!({ "type": "object" }));
+        assert_eq!(body["options"]["num_predict"], 64);
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_text_then_the_final_response() {
+        // The last line arrives split across chunks and without a newline
+        let body = [
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"Let me \"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"check.\"},\"done\":false}\n{\"message\":{\"role\":\"assistant\",\"content\":\"\",",
+            "\"tool_calls\":[{\"function\":{\"name\":\"lookup\",\"arguments\":{\"id\":7}}}]},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,\"done_reason\":\"stop\",",
+            "\"prompt_eval_count\":20,\"eval_count\":9}",
+        ]
+        .iter()
+        .map(|chunk| Ok::<_, std::convert::Infallible>(Bytes::from(*chunk)))
+        .collect::<Vec<_>>();
+
+        let responses: Vec<LlmResponse> = response_stream(futures::stream::iter(body))
+            .map(|response| response.unwrap())
+            .collect()
+            .await;
+        let texts: Vec<Option<String>> = responses.iter().map(|response| response.get_text()).collect();
+        assert_eq!(texts, [Some("Let me ".to_string()), Some("check.".to_string()), None]);
+
+        let last = &responses[2];
+        assert!(!last.is_partial);
+        assert_eq!(last.function_calls[0].args, json!({ "id": 7 }));
+        assert!(matches!(last.finish_reason, Some(FinishReason::FunctionCall)));
+        assert_eq!(last.usage.as_ref().unwrap().total_tokens, Some(29));
+    }
+}
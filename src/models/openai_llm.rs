@@ -0,0 +1,721 @@
+//! OpenAI chat completions LLM implementation
+//!
+//! [`OpenAiLlm`] talks to the OpenAI chat completions API, or to any
+//! OpenAI-compatible endpoint (vLLM, LM Studio, proxies, ...) through
+//! [`OpenAiLlm::with_base_url`]. The chat API identifies tool calls by id
+//! while ADK content matches calls and responses by function name, so ids
+//! are assigned while the request is converted.
+
+use crate::{
+    error::Result,
+    models::{
+        base_llm::LlmConfig, http_client::InFlightRequest, sse::json_event_stream, BaseLlm, FinishReason,
+        HttpClientConfig, LlmRequest, LlmResponse, Usage,
+    },
+    types::{Content, ContentPart, FunctionCall, FunctionCallingMode},
+    utils::base64_bytes::Base64,
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::pin::Pin;
+use tracing::{debug, error, info};
+
+/// Default OpenAI API base URL
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// OpenAI chat completions LLM implementation
+#[derive(Debug, Clone)]
+pub struct OpenAiLlm {
+    model: String,
+    api_key: Option<String>,
+    client: Client,
+    base_url: String,
+}
+
+/// Chat completions request format
+#[derive(Debug, Serialize)]
+pub(super) struct OpenAiRequest {
+    model: String,
+    messages: Vec<OpenAiMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<OpenAiTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<Value>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAiMessage {
+    role: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<OpenAiMessageContent>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OpenAiToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+impl OpenAiMessage {
+    fn new(role: &'static str, content: Option<OpenAiMessageContent>) -> Self {
+        Self {
+            role,
+            content,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum OpenAiMessageContent {
+    Text(String),
+    Parts(Vec<OpenAiContentPart>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OpenAiContentPart {
+    Text { text: String },
+    ImageUrl { image_url: OpenAiImageUrl },
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAiImageUrl {
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAiToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: OpenAiFunctionCall,
+}
+
+/// A function call; the arguments are a JSON document in a string
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenAiFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: String,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAiTool {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: OpenAiFunctionDeclaration,
+}
+
+#[derive(Debug, Serialize)]
+struct OpenAiFunctionDeclaration {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+/// Chat completions response format
+#[derive(Debug, Deserialize)]
+pub(super) struct OpenAiResponse {
+    choices: Vec<OpenAiChoice>,
+    #[serde(default)]
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiChoice {
+    message: OpenAiResponseMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiResponseMessage {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenAiResponseToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiResponseToolCall {
+    function: OpenAiFunctionCall,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiUsage {
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+    total_tokens: Option<u32>,
+}
+
+impl From<OpenAiUsage> for Usage {
+    fn from(usage: OpenAiUsage) -> Self {
+        Self {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+        }
+    }
+}
+
+/// One server-sent chunk of a streamed response
+#[derive(Debug, Deserialize)]
+struct OpenAiStreamChunk {
+    #[serde(default)]
+    choices: Vec<OpenAiStreamChoice>,
+    #[serde(default)]
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiStreamChoice {
+    #[serde(default)]
+    index: usize,
+    #[serde(default)]
+    delta: OpenAiDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OpenAiDelta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenAiToolCallDelta>,
+}
+
+/// Fragment of a streamed tool call; fragments with the same index are concatenated
+#[derive(Debug, Deserialize)]
+struct OpenAiToolCallDelta {
+    #[serde(default)]
+    index: usize,
+    #[serde(default)]
+    function: OpenAiFunctionDelta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OpenAiFunctionDelta {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+impl OpenAiLlm {
+    /// Create a new OpenAI LLM instance
+    pub fn new(model: impl Into<String>) -> Self {
+        let client = HttpClientConfig::default()
+            .shared_client()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            model: model.into(),
+            api_key: None,
+            client,
+            base_url: OPENAI_BASE_URL.to_string(),
+        }
+    }
+
+    /// Create an OpenAI LLM instance from an `LlmConfig`
+    pub fn from_config(config: LlmConfig) -> Result<Self> {
+        let mut http_client = config.http_client;
+        if http_client.timeout_seconds.is_none() {
+            http_client.timeout_seconds = config.timeout_seconds;
+        }
+
+        let mut llm = Self::new(config.model).with_http_client_config(&http_client)?;
+        llm.api_key = config.api_key;
+        if let Some(endpoint) = config.endpoint {
+            llm.base_url = endpoint;
+        }
+        Ok(llm)
+    }
+
+    /// Use a caller-provided HTTP client
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Use the shared HTTP client for the given connection settings (proxy, CA bundle, timeouts, pooling)
+    pub fn with_http_client_config(mut self, config: &HttpClientConfig) -> Result<Self> {
+        self.client = config.shared_client()?;
+        Ok(self)
+    }
+
+    /// Send requests to an OpenAI-compatible endpoint, e.g. `http://localhost:8000/v1`
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set API key
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// The HTTP client requests are sent with
+    #[cfg(feature = "azure-openai")]
+    pub(super) fn http_client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Convert ADK request to chat completions format
+    pub(super) fn convert_request(&self, request: &LlmRequest, stream: bool) -> Result<OpenAiRequest> {
+        let mut messages = Vec::new();
+        // Ids of calls awaiting their response, in call order
+        let mut pending_calls: Vec<(String, String)> = Vec::new();
+        let mut next_call_id = 0;
+
+        for content in request.all_contents() {
+            if content.role == "model" {
+                let mut message = OpenAiMessage::new("assistant", None);
+                let mut text = String::new();
+                for part in &content.parts {
+                    match part {
+                        ContentPart::FunctionCall { name, args } => {
+                            let id = format!("call_{}", next_call_id);
+                            next_call_id += 1;
+                            pending_calls.push((name.clone(), id.clone()));
+                            message.tool_calls.push(OpenAiToolCall {
+                                id,
+                                kind: "function",
+                                function: OpenAiFunctionCall {
+                                    name: name.clone(),
+                                    arguments: args.to_string(),
+                                },
+                            });
+                        }
+                        part => {
+                            if let Some(part_text) = part_as_text(part) {
+                                text.push_str(&part_text);
+                            }
+                        }
+                    }
+                }
+                message.content = (!text.is_empty()).then_some(OpenAiMessageContent::Text(text));
+                messages.push(message);
+                continue;
+            }
+
+            let role = if content.role == "system" { "system" } else { "user" };
+            let mut parts = Vec::new();
+            for part in &content.parts {
+                match part {
+                    ContentPart::FunctionResponse { name, response } => {
+                        let id = match pending_calls.iter().position(|(call, _)| call == name) {
+                            Some(index) => pending_calls.remove(index).1,
+                            None => {
+                                next_call_id += 1;
+                                format!("call_{}", next_call_id - 1)
+                            }
+                        };
+                        let mut message =
+                            OpenAiMessage::new("tool", Some(OpenAiMessageContent::Text(response.to_string())));
+                        message.tool_call_id = Some(id);
+                        messages.push(message);
+                    }
+                    ContentPart::Image { data, mime_type } => parts.push(OpenAiContentPart::ImageUrl {
+                        image_url: OpenAiImageUrl {
+                            url: format!("data:{};base64,{}", mime_type, Base64(data)),
+                        },
+                    }),
+                    ContentPart::FileRef { uri, mime_type } if mime_type.starts_with("image/") => {
+                        parts.push(OpenAiContentPart::ImageUrl {
+                            image_url: OpenAiImageUrl { url: uri.clone() },
+                        })
+                    }
+                    part => match part_as_text(part) {
+                        Some(text) => parts.push(OpenAiContentPart::Text { text }),
+                        None => {
+                            return Err(crate::adk_error!(
+                                ModelError,
+                                "OpenAI chat completions do not support this content part: {:?}",
+                                part
+                            ))
+                        }
+                    },
+                }
+            }
+            let content = match parts.as_mut_slice() {
+                [] => continue,
+                [OpenAiContentPart::Text { text }] => OpenAiMessageContent::Text(std::mem::take(text)),
+                _ => OpenAiMessageContent::Parts(parts),
+            };
+            messages.push(OpenAiMessage::new(role, Some(content)));
+        }
+
+        let tools = request
+            .config
+            .tools
+            .iter()
+            .flat_map(|tool| &tool.function_declarations)
+            .map(|decl| OpenAiTool {
+                kind: "function",
+                function: OpenAiFunctionDeclaration {
+                    name: decl.name.clone(),
+                    description: decl.description.clone(),
+                    parameters: decl.parameters.clone(),
+                },
+            })
+            .collect();
+
+        let tool_choice = request.config.tool_config.as_ref().map(|config| {
+            match (config.function_calling_mode, config.allowed_function_names.as_slice()) {
+                (FunctionCallingMode::Any, [name]) => json!({ "type": "function", "function": { "name": name } }),
+                (FunctionCallingMode::Any, _) => json!("required"),
+                (FunctionCallingMode::None, _) => json!("none"),
+                (FunctionCallingMode::Auto, _) => json!("auto"),
+            }
+        });
+
+        let response_format = match (&request.config.response_schema, request.config.response_mime_type.as_deref()) {
+            (Some(schema), _) => Some(json!({
+                "type": "json_schema",
+                "json_schema": { "name": "response", "schema": schema },
+            })),
+            (None, Some("application/json")) => Some(json!({ "type": "json_object" })),
+            _ => None,
+        };
+
+        Ok(OpenAiRequest {
+            model: self.model.clone(),
+            messages,
+            tools,
+            tool_choice,
+            temperature: request.config.temperature,
+            top_p: request.config.top_p,
+            max_tokens: request.config.max_output_tokens,
+            stop: request.config.stop_sequences.clone(),
+            seed: request.config.seed,
+            response_format,
+            stream,
+            stream_options: stream.then(|| json!({ "include_usage": true })),
+        })
+    }
+
+    /// Convert chat completions response to ADK format
+    pub(super) fn convert_response(&self, response: OpenAiResponse) -> Result<LlmResponse> {
+        let mut llm_response = LlmResponse::new();
+        llm_response.usage = response.usage.map(Usage::from);
+
+        let Some(choice) = response.choices.into_iter().next() else {
+            return Ok(llm_response);
+        };
+        if let Some(text) = choice.message.content.filter(|text| !text.is_empty()) {
+            llm_response.content = Some(Content::model_text(text));
+        }
+        llm_response.function_calls = choice
+            .message
+            .tool_calls
+            .into_iter()
+            .map(|call| parse_function_call(call.function.name, &call.function.arguments))
+            .collect::<Result<_>>()?;
+        llm_response.finish_reason = choice.finish_reason.as_deref().map(convert_finish_reason);
+
+        Ok(llm_response)
+    }
+
+    /// Send a request, turning error statuses into `ModelError`s
+    async fn post(&self, request: &OpenAiRequest) -> Result<reqwest::Response> {
+        let mut builder = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .header("Content-Type", "application/json")
+            .json(request);
+        if let Some(api_key) = self.get_api_key()? {
+            builder = builder.bearer_auth(api_key);
+        }
+        let response = builder.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            error!("OpenAI API error: {} - {}", status, error_text);
+            return Err(crate::adk_error!(
+                ModelError,
+                "OpenAI API error: {} - {}",
+                status,
+                error_text
+            ));
+        }
+        Ok(response)
+    }
+
+    /// Get the API key; compatible endpoints other than OpenAI's may not need one
+    fn get_api_key(&self) -> Result<Option<String>> {
+        match self.api_key.clone().or_else(|| std::env::var("OPENAI_API_KEY").ok()) {
+            Some(api_key) => Ok(Some(api_key)),
+            None if self.base_url == OPENAI_BASE_URL => Err(crate::adk_error!(
+                AuthError,
+                "No API key provided. Set OPENAI_API_KEY environment variable or use with_api_key()"
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Text of parts the chat API has no counterpart for; `None` for media
+fn part_as_text(part: &ContentPart) -> Option<String> {
+    match part {
+        ContentPart::Text { text } => Some(text.clone()),
+        ContentPart::ExecutableCode { language, code } => {
+            Some(format!("```{}\n{}\n```", language.to_lowercase(), code))
+        }
+        ContentPart::CodeExecutionResult { output, .. } => Some(output.clone()),
+        _ => None,
+    }
+}
+
+fn parse_function_call(name: String, arguments: &str) -> Result<FunctionCall> {
+    let args = if arguments.trim().is_empty() {
+        json!({})
+    } else {
+        serde_json::from_str(arguments).map_err(|e| {
+            crate::adk_error!(ModelError, "OpenAI returned invalid arguments for '{}': {}", name, e)
+        })?
+    };
+    Ok(FunctionCall { name, args })
+}
+
+fn convert_finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "stop" => FinishReason::Stop,
+        "length" => FinishReason::MaxTokens,
+        "tool_calls" | "function_call" => FinishReason::FunctionCall,
+        "content_filter" => FinishReason::Safety,
+        _ => FinishReason::Other,
+    }
+}
+
+/// Turn a streamed chat completion into ADK responses: a partial response per
+/// text delta, then a final one with the tool calls, finish reason and usage
+pub(super) fn response_stream<S, E>(bytes: S) -> Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    let mut chunks = json_event_stream::<OpenAiStreamChunk, _, _>(bytes);
+    Box::pin(stream! {
+        // (name, arguments) of each tool call, by index
+        let mut tool_calls: Vec<(String, String)> = Vec::new();
+        let mut finish_reason = None;
+        let mut usage = None;
+
+        while let Some(chunk) = chunks.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            if let Some(chunk_usage) = chunk.usage {
+                usage = Some(Usage::from(chunk_usage));
+            }
+            for choice in chunk.choices.into_iter().filter(|choice| choice.index == 0) {
+                if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+                    yield Ok(LlmResponse::partial_text(text));
+                }
+                for delta in choice.delta.tool_calls {
+                    if tool_calls.len() <= delta.index {
+                        tool_calls.resize(delta.index + 1, (String::new(), String::new()));
+                    }
+                    let (name, arguments) = &mut tool_calls[delta.index];
+                    name.push_str(delta.function.name.as_deref().unwrap_or_default());
+                    arguments.push_str(delta.function.arguments.as_deref().unwrap_or_default());
+                }
+                if let Some(reason) = choice.finish_reason {
+                    finish_reason = Some(convert_finish_reason(&reason));
+                }
+            }
+        }
+
+        let function_calls = tool_calls
+            .into_iter()
+            .map(|(name, arguments)| parse_function_call(name, &arguments))
+            .collect::<Result<Vec<_>>>();
+        match function_calls {
+            Ok(function_calls) => {
+                let mut response = LlmResponse::new();
+                response.function_calls = function_calls;
+                response.finish_reason = finish_reason;
+                response.usage = usage;
+                yield Ok(response);
+            }
+            Err(e) => yield Err(e),
+        }
+    })
+}
+
+#[async_trait]
+impl BaseLlm for OpenAiLlm {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn supported_models() -> Vec<String> {
+        vec![r"gpt-.*".to_string(), r"o[134](-.*)?".to_string()]
+    }
+
+    async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+        debug!("Generating content with OpenAI for model: {}", self.model);
+
+        let openai_request = self.convert_request(&request, false)?;
+
+        let _in_flight = InFlightRequest::start();
+        let response = self.post(&openai_request).await?;
+
+        let openai_response: OpenAiResponse = response.json().await?;
+        let llm_response = self.convert_response(openai_response)?;
+
+        info!("Successfully generated content with OpenAI");
+        Ok(llm_response)
+    }
+
+    async fn generate_content_stream(
+        &self,
+        request: LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send + 'static>>> {
+        debug!("Streaming content with OpenAI for model: {}", self.model);
+
+        let openai_request = self.convert_request(&request, true)?;
+
+        // Counted as in flight until the stream is finished or dropped
+        let in_flight = InFlightRequest::start();
+        let response = self.post(&openai_request).await?;
+        let mut responses = response_stream(response.bytes_stream());
+
+        Ok(Box::pin(stream! {
+            let _in_flight = in_flight;
+            while let Some(response) = responses.next().await {
+                yield response;
+            }
+        }))
+    }
+
+    fn supports_multimodal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FunctionDeclaration, Tool, ToolConfig};
+
+    #[test]
+    fn test_tool_calls_and_responses_are_paired_by_id() {
+        let llm = OpenAiLlm::new("gpt-4o");
+        let mut request = LlmRequest::new("gpt-4o")
+            .add_user_message("Where is order 7?")
+            .add_content(Content::function_call(FunctionCall {
+                name: "lookup".to_string(),
+                args: json!({ "id": 7 }),
+            }))
+            .add_content(Content::function_response("lookup", json!({ "status": "shipped" })))
+            .with_tool_config(ToolConfig {
+                function_calling_mode: FunctionCallingMode::Auto,
+                allowed_function_names: Vec::new(),
+            });
+        request.config.tools.push(Tool {
+            function_declarations: vec![FunctionDeclaration {
+                name: "lookup".to_string(),
+                description: "Look up an order".to_string(),
+                parameters: json!({ "type": "object" }),
+            }],
+        });
+
+        let body = serde_json::to_value(llm.convert_request(&request, true).unwrap()).unwrap();
+        let messages = &body["messages"];
+        assert_eq!(messages[0], json!({ "role": "user", "content": "Where is order 7?" }));
+        assert_eq!(messages[1]["role"], "assistant");
+        assert_eq!(messages[1]["tool_calls"][0]["function"]["arguments"], "{\"id\":7}");
+        assert_eq!(messages[2]["role"], "tool");
+        assert_eq!(messages[2]["tool_call_id"], messages[1]["tool_calls"][0]["id"]);
+        assert_eq!(body["tools"][0]["function"]["name"], "lookup");
+        assert_eq!(body["tool_choice"], "auto");
+        assert_eq!(body["stream_options"]["include_usage"], true);
+    }
+
+    #[test]
+    fn test_response_with_tool_calls() {
+        let llm = OpenAiLlm::new("gpt-4o");
+        let response: OpenAiResponse = serde_json::from_value(json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_abc",
+                        "type": "function",
+                        "function": { "name": "lookup", "arguments": "{\"id\": 7}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": { "prompt_tokens": 12, "completion_tokens": 5, "total_tokens": 17 }
+        }))
+        .unwrap();
+
+        let response = llm.convert_response(response).unwrap();
+        assert!(response.content.is_none());
+        assert_eq!(response.function_calls[0].name, "lookup");
+        assert_eq!(response.function_calls[0].args, json!({ "id": 7 }));
+        assert!(matches!(response.finish_reason, Some(FinishReason::FunctionCall)));
+        assert_eq!(response.usage.unwrap().total_tokens, Some(17));
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_text_deltas_then_assembled_tool_calls() {
+        let body = [
+            r#"{"choices":[{"index":0,"delta":{"role":"assistant","content":"Let me "}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"content":"check."}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"lookup","arguments":"{\"id\""}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":": 7}"}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}"#,
+            r#"{"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":9,"total_tokens":21}}"#,
+            "[DONE]",
+        ]
+        .iter()
+        .map(|data| Ok::<_, std::convert::Infallible>(Bytes::from(format!("data: {}\n\n", data))))
+        .collect::<Vec<_>>();
+
+        let responses: Vec<LlmResponse> = response_stream(futures::stream::iter(body))
+            .map(|response| response.unwrap())
+            .collect()
+            .await;
+        let texts: Vec<Option<String>> = responses.iter().map(|response| response.get_text()).collect();
+        assert_eq!(texts, [Some("Let me ".to_string()), Some("check.".to_string()), None]);
+        assert!(responses[0].is_partial && !responses[2].is_partial);
+
+        let last = &responses[2];
+        assert_eq!(last.function_calls[0].args, json!({ "id": 7 }));
+        assert!(matches!(last.finish_reason, Some(FinishReason::FunctionCall)));
+        assert_eq!(last.usage.as_ref().unwrap().completion_tokens, Some(9));
+    }
+}
@@ -0,0 +1,244 @@
+//! Named model profiles loaded from `adk.toml`
+//!
+//! Profiles let agents refer to a model by role (`fast`, `smart`, `cheap`)
+//! instead of a concrete model name:
+//!
+//! ```toml
+//! [profiles.fast]
+//! model = "gemini-2.0-flash"
+//! temperature = 0.2
+//!
+//! [profiles.fast.safety_settings]
+//! HARM_CATEGORY_HARASSMENT = "BLOCK_ONLY_HIGH"
+//! ```
+
+use crate::{
+    error::Result,
+    models::{global_registry, BaseLlm},
+    types::{GenerateContentConfig, SafetySetting},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+use tracing::{debug, info};
+
+/// Default configuration file name
+pub const DEFAULT_CONFIG_FILE: &str = "adk.toml";
+
+/// Environment variable overriding the configuration file path
+pub const CONFIG_PATH_ENV_VAR: &str = "ADK_CONFIG";
+
+/// Model settings referenced by name from agents
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelProfile {
+    /// Concrete model name
+    pub model: String,
+
+    /// Registry pattern of the provider to use (e.g. `gemini`); inferred from the model name if unset
+    #[serde(default)]
+    pub provider: Option<String>,
+
+    /// Temperature for response generation
+    #[serde(default)]
+    pub temperature: Option<f32>,
+
+    /// Top-p for nucleus sampling
+    #[serde(default)]
+    pub top_p: Option<f32>,
+
+    /// Maximum output tokens
+    #[serde(default)]
+    pub max_output_tokens: Option<i32>,
+
+    /// Safety thresholds keyed by harm category
+    #[serde(default)]
+    pub safety_settings: HashMap<String, String>,
+}
+
+impl ModelProfile {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_safety_setting(mut self, category: impl Into<String>, threshold: impl Into<String>) -> Self {
+        self.safety_settings.insert(category.into(), threshold.into());
+        self
+    }
+
+    /// Apply the profile's generation settings to a request config
+    pub fn apply_to(&self, config: &mut GenerateContentConfig) {
+        if let Some(temperature) = self.temperature {
+            config.temperature = Some(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            config.top_p = Some(top_p);
+        }
+        if let Some(max_output_tokens) = self.max_output_tokens {
+            config.max_output_tokens = Some(max_output_tokens);
+        }
+        let mut categories: Vec<_> = self.safety_settings.iter().collect();
+        categories.sort();
+        for (category, threshold) in categories {
+            config.safety_settings.retain(|setting| &setting.category != category);
+            config.safety_settings.push(SafetySetting::new(category, threshold));
+        }
+    }
+
+    /// Create a model instance for this profile using the global registry
+    pub async fn create_model(&self) -> Result<Box<dyn BaseLlm>> {
+        match &self.provider {
+            Some(provider) => {
+                global_registry()
+                    .create_model_for_provider(provider, &self.model)
+                    .await
+            }
+            None => global_registry().create_model(&self.model).await,
+        }
+    }
+}
+
+/// On-disk layout of the profiles section of `adk.toml`
+#[derive(Debug, Default, Deserialize)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: HashMap<String, ModelProfile>,
+}
+
+/// Collection of named model profiles
+#[derive(Debug, Clone, Default)]
+pub struct ModelProfiles {
+    profiles: Arc<RwLock<HashMap<String, ModelProfile>>>,
+}
+
+impl ModelProfiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load profiles from a TOML file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file: ProfilesFile = config::Config::builder()
+            .add_source(config::File::from(path).format(config::FileFormat::Toml))
+            .build()?
+            .try_deserialize()?;
+
+        info!("Loaded {} model profiles from {}", file.profiles.len(), path.display());
+
+        Ok(Self {
+            profiles: Arc::new(RwLock::new(file.profiles)),
+        })
+    }
+
+    /// Load profiles from `$ADK_CONFIG` or `./adk.toml`, if present
+    pub fn load_default() -> Result<Self> {
+        let path = std::env::var(CONFIG_PATH_ENV_VAR).unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+        if Path::new(&path).exists() {
+            Self::from_file(path)
+        } else {
+            debug!("No {} found, starting with no model profiles", path);
+            Ok(Self::new())
+        }
+    }
+
+    /// Define or repoint a profile
+    pub fn set(&self, name: impl Into<String>, profile: ModelProfile) {
+        let mut profiles = self.profiles.write().expect("profiles lock poisoned");
+        profiles.insert(name.into(), profile);
+    }
+
+    /// Remove a profile
+    pub fn remove(&self, name: &str) -> Option<ModelProfile> {
+        let mut profiles = self.profiles.write().expect("profiles lock poisoned");
+        profiles.remove(name)
+    }
+
+    /// Get a profile by name
+    pub fn get(&self, name: &str) -> Option<ModelProfile> {
+        let profiles = self.profiles.read().expect("profiles lock poisoned");
+        profiles.get(name).cloned()
+    }
+
+    /// Get a profile by name, failing if it is not defined
+    pub fn resolve(&self, name: &str) -> Result<ModelProfile> {
+        self.get(name).ok_or_else(|| {
+            crate::adk_error!(ConfigError, "Model profile '{}' is not defined", name)
+        })
+    }
+
+    /// List profile names
+    pub fn names(&self) -> Vec<String> {
+        let profiles = self.profiles.read().expect("profiles lock poisoned");
+        profiles.keys().cloned().collect()
+    }
+}
+
+/// Global profiles instance, loaded from `adk.toml` on first use
+static GLOBAL_PROFILES: once_cell::sync::Lazy<ModelProfiles> = once_cell::sync::Lazy::new(|| {
+    ModelProfiles::load_default().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load model profiles: {}", e);
+        ModelProfiles::new()
+    })
+});
+
+/// Get the global model profiles
+pub fn global_profiles() -> &'static ModelProfiles {
+    &GLOBAL_PROFILES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_load_profiles_from_toml() {
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(
+            file,
+            r#"
+[profiles.fast]
+model = "gemini-2.0-flash"
+temperature = 0.2
+
+[profiles.fast.safety_settings]
+HARM_CATEGORY_HARASSMENT = "BLOCK_ONLY_HIGH"
+
+[profiles.smart]
+model = "gemini-1.5-pro"
+provider = "gemini"
+"#
+        )
+        .unwrap();
+
+        let profiles = ModelProfiles::from_file(file.path()).unwrap();
+        let fast = profiles.resolve("fast").unwrap();
+        assert_eq!(fast.model, "gemini-2.0-flash");
+        assert_eq!(fast.temperature, Some(0.2));
+        assert_eq!(fast.safety_settings["HARM_CATEGORY_HARASSMENT"], "BLOCK_ONLY_HIGH");
+        let mut config = GenerateContentConfig::default();
+        fast.apply_to(&mut config);
+        assert_eq!(config.safety_settings, vec![SafetySetting::new("HARM_CATEGORY_HARASSMENT", "BLOCK_ONLY_HIGH")]);
+        assert_eq!(profiles.resolve("smart").unwrap().provider.as_deref(), Some("gemini"));
+        assert!(profiles.resolve("cheap").is_err());
+
+        profiles.set("fast", ModelProfile::new("gemini-1.5-flash"));
+        assert_eq!(profiles.resolve("fast").unwrap().model, "gemini-1.5-flash");
+    }
+}
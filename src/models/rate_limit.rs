@@ -0,0 +1,268 @@
+//! Per-model request rate and concurrency limits
+//!
+//! A [`RateLimiter`] caps the requests started in any one-minute window and
+//! the requests in flight at once; callers over a limit wait for their turn.
+//! [`RateLimitedLlm`] applies one to a model. To limit a model across every
+//! agent that uses it, set the limit on the registry with
+//! [`LlmRegistry::set_rate_limit`](crate::models::LlmRegistry::set_rate_limit):
+//! all instances it creates for that model name then share one limiter.
+
+use crate::{
+    error::Result,
+    models::{BaseLlm, LlmConnection, LlmRequest, LlmResponse},
+};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits on the requests sent to a model
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    /// Requests started in any one-minute window; `None` is unlimited
+    pub requests_per_minute: Option<u32>,
+
+    /// Requests in flight at once, including open streams; `None` is unlimited
+    pub max_in_flight: Option<usize>,
+}
+
+impl RateLimit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_requests_per_minute(mut self, requests_per_minute: u32) -> Self {
+        self.requests_per_minute = Some(requests_per_minute);
+        self
+    }
+
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = Some(max_in_flight);
+        self
+    }
+}
+
+/// Shared state enforcing a [`RateLimit`]
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    /// Start times of the requests in the current window
+    started: Mutex<VecDeque<Instant>>,
+    in_flight: Option<Arc<Semaphore>>,
+}
+
+/// Held while a request is in flight
+#[derive(Debug)]
+pub struct RateLimitPermit {
+    _in_flight: Option<OwnedSemaphorePermit>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            in_flight: limit.max_in_flight.map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            started: Mutex::new(VecDeque::new()),
+            limit,
+        }
+    }
+
+    pub fn limit(&self) -> &RateLimit {
+        &self.limit
+    }
+
+    /// Wait until a request may start
+    pub async fn acquire(&self) -> RateLimitPermit {
+        let in_flight = match &self.in_flight {
+            Some(semaphore) => Some(semaphore.clone().acquire_owned().await.expect("rate limit semaphore is never closed")),
+            None => None,
+        };
+        while let Err(wait) = self.try_start(Instant::now()) {
+            debug!("Requests per minute limit reached, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+        RateLimitPermit { _in_flight: in_flight }
+    }
+
+    /// Record a request starting at `now`, or return how long until one may
+    fn try_start(&self, now: Instant) -> std::result::Result<(), Duration> {
+        let Some(requests_per_minute) = self.limit.requests_per_minute else {
+            return Ok(());
+        };
+        let mut started = self.started.lock().unwrap();
+        while started.front().is_some_and(|start| now.duration_since(*start) >= WINDOW) {
+            started.pop_front();
+        }
+        if started.len() < requests_per_minute.max(1) as usize {
+            started.push_back(now);
+            return Ok(());
+        }
+        Err(WINDOW - now.duration_since(started[0]))
+    }
+}
+
+/// Model wrapper that enforces a [`RateLimit`] on an inner model
+pub struct RateLimitedLlm {
+    inner: Arc<dyn BaseLlm>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitedLlm {
+    /// Wrap a model with limits of its own
+    pub fn new(inner: Arc<dyn BaseLlm>, limit: RateLimit) -> Self {
+        Self::with_limiter(inner, Arc::new(RateLimiter::new(limit)))
+    }
+
+    /// Wrap a model with a limiter shared with other models
+    pub fn with_limiter(inner: Arc<dyn BaseLlm>, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+
+    /// Get the wrapped model
+    pub fn inner(&self) -> &Arc<dyn BaseLlm> {
+        &self.inner
+    }
+
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+}
+
+impl std::fmt::Debug for RateLimitedLlm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimitedLlm")
+            .field("model", &self.inner.model_name())
+            .field("limit", self.limiter.limit())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl BaseLlm for RateLimitedLlm {
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn supported_models() -> Vec<String> {
+        Vec::new()
+    }
+
+    async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.generate_content(request).await
+    }
+
+    async fn generate_content_stream(
+        &self,
+        request: LlmRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>> {
+        // In flight until the stream is finished or dropped
+        let permit = self.limiter.acquire().await;
+        let mut responses = self.inner.generate_content_stream(request).await?;
+
+        Ok(Box::pin(stream! {
+            let _permit = permit;
+            while let Some(response) = responses.next().await {
+                yield response;
+            }
+        }))
+    }
+
+    async fn count_tokens(&self, request: &LlmRequest) -> Result<u32> {
+        self.inner.count_tokens(request).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn supports_function_calling(&self) -> bool {
+        self.inner.supports_function_calling()
+    }
+
+    fn supports_multimodal(&self) -> bool {
+        self.inner.supports_multimodal()
+    }
+
+    fn supports_live(&self) -> bool {
+        self.inner.supports_live()
+    }
+
+    async fn create_live_connection(&self) -> Result<Box<dyn LlmConnection>> {
+        self.inner.create_live_connection().await
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.inner.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_requests_per_minute_window() {
+        let limiter = RateLimiter::new(RateLimit::new().with_requests_per_minute(2));
+        let start = Instant::now();
+        assert!(limiter.try_start(start).is_ok());
+        assert!(limiter.try_start(start + Duration::from_secs(10)).is_ok());
+        assert_eq!(limiter.try_start(start + Duration::from_secs(20)), Err(Duration::from_secs(40)));
+        assert!(limiter.try_start(start + Duration::from_secs(60)).is_ok());
+        assert_eq!(limiter.try_start(start + Duration::from_secs(61)), Err(Duration::from_secs(9)));
+    }
+
+    struct SlowLlm {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl BaseLlm for SlowLlm {
+        fn model_name(&self) -> &str {
+            "slow"
+        }
+
+        fn supported_models() -> Vec<String> {
+            Vec::new()
+        }
+
+        async fn generate_content(&self, _request: LlmRequest) -> Result<LlmResponse> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(LlmResponse::text("done"))
+        }
+
+        async fn generate_content_stream(
+            &self,
+            request: LlmRequest,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>> {
+            let response = self.generate_content(request).await?;
+            Ok(Box::pin(futures::stream::once(async move { Ok(response) })))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight() {
+        let slow = Arc::new(SlowLlm { running: AtomicUsize::new(0), peak: AtomicUsize::new(0) });
+        let llm = RateLimitedLlm::new(slow.clone(), RateLimit::new().with_max_in_flight(2));
+
+        let calls = (0..5).map(|_| llm.generate_content(LlmRequest::new("slow")));
+        let responses = futures::future::join_all(calls).await;
+        assert!(responses.iter().all(|response| response.is_ok()));
+        assert_eq!(slow.peak.load(Ordering::SeqCst), 2);
+    }
+}
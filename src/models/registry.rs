@@ -2,51 +2,67 @@
 
 use crate::{
     error::Result,
-    models::{BaseLlm, GoogleLlm},
+    models::{
+        global_catalog, BaseLlm, GoogleLlm, HttpClientConfig, Modality, ModelPricing, RateLimit, RateLimitedLlm,
+        RateLimiter,
+    },
 };
+use serde::Serialize;
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
 use tracing::{debug, info};
 
+/// Factory that creates a model instance for a given model name
+pub type ModelFactory = Box<dyn Fn(&str) -> Result<Box<dyn BaseLlm>> + Send + Sync>;
+
 /// Registry for LLM models
 pub struct LlmRegistry {
-    models: Arc<RwLock<HashMap<String, Box<dyn Fn(&str) -> Result<Box<dyn BaseLlm>> + Send + Sync>>>>,
+    models: Arc<RwLock<HashMap<String, ModelFactory>>>,
+    /// Limiters shared by every instance created for a model name
+    rate_limits: Arc<RwLock<HashMap<String, Arc<RateLimiter>>>>,
 }
 
 impl LlmRegistry {
     /// Create a new registry
     pub fn new() -> Self {
-        let registry = Self {
-            models: Arc::new(RwLock::new(HashMap::new())),
-        };
-        
-        // Register default models
-        tokio::spawn({
-            let registry = registry.clone();
-            async move {
-                registry.register_default_models().await;
-            }
-        });
-        
-        registry
+        // Register default models up front so the registry is usable
+        // immediately, with or without a running tokio runtime
+        let mut models = HashMap::new();
+        Self::register_default_models(&mut models);
+
+        Self {
+            models: Arc::new(RwLock::new(models)),
+            rate_limits: Arc::new(RwLock::new(HashMap::new())),
+        }
     }
 
     /// Register default models
-    async fn register_default_models(&self) {
+    fn register_default_models(models: &mut HashMap<String, ModelFactory>) {
         info!("Registering default LLM models");
         
         // Register Google/Gemini models
-        self.register_google_models().await;
+        Self::register_google_models(models);
         
         #[cfg(feature = "anthropic")]
-        self.register_anthropic_models().await;
+        Self::register_anthropic_models(models);
+
+        #[cfg(feature = "openai")]
+        Self::register_openai_models(models);
+
+        #[cfg(feature = "ollama")]
+        Self::register_ollama_models(models);
+
+        #[cfg(feature = "azure-openai")]
+        Self::register_azure_openai_models(models);
+
+        #[cfg(feature = "bedrock")]
+        Self::register_bedrock_models(models);
         
         debug!("Default models registered successfully");
     }
 
     /// Register Google models
-    async fn register_google_models(&self) {
-        let mut models = self.models.write().await;
+    fn register_google_models(models: &mut HashMap<String, ModelFactory>) {
         
         // Register Gemini models with various patterns
         let gemini_patterns = vec![
@@ -62,7 +78,8 @@ impl LlmRegistry {
             models.insert(
                 pattern.to_string(),
                 Box::new(|model_name: &str| {
-                    let mut llm = GoogleLlm::new(model_name);
+                    let mut llm = GoogleLlm::new(model_name)
+                        .with_http_client_config(&HttpClientConfig::from_env())?;
                     
                     // Auto-configure from environment
                     if let Ok(api_key) = std::env::var("GOOGLE_API_KEY") {
@@ -93,24 +110,132 @@ impl LlmRegistry {
 
     /// Register Anthropic models
     #[cfg(feature = "anthropic")]
-    async fn register_anthropic_models(&self) {
-        let mut models = self.models.write().await;
-        
+    fn register_anthropic_models(models: &mut HashMap<String, ModelFactory>) {
+        use crate::models::AnthropicLlm;
+
         models.insert(
             "claude".to_string(),
             Box::new(|model_name: &str| {
-                // TODO: Implement AnthropicLlm
-                Err(crate::adk_error!(
-                    ModelError,
-                    "Anthropic models not yet implemented: {}",
-                    model_name
-                ))
+                let mut llm = AnthropicLlm::new(model_name)
+                    .with_http_client_config(&HttpClientConfig::from_env())?;
+
+                if let Ok(api_key) = std::env::var("ANTHROPIC_API_KEY") {
+                    llm = llm.with_api_key(api_key);
+                }
+
+                if let Ok(base_url) = std::env::var("ANTHROPIC_BASE_URL") {
+                    llm = llm.with_base_url(base_url);
+                }
+
+                Ok(Box::new(llm) as Box<dyn BaseLlm>)
             }),
         );
         
         debug!("Anthropic models registered");
     }
 
+    /// Register OpenAI models; the `openai` pattern also serves as the provider
+    /// of OpenAI-compatible endpoints, configured with `OPENAI_BASE_URL`
+    #[cfg(feature = "openai")]
+    fn register_openai_models(models: &mut HashMap<String, ModelFactory>) {
+        use crate::models::OpenAiLlm;
+
+        for pattern in ["openai", "gpt", "o1", "o3", "o4-mini"] {
+            models.insert(
+                pattern.to_string(),
+                Box::new(|model_name: &str| {
+                    let mut llm = OpenAiLlm::new(model_name)
+                        .with_http_client_config(&HttpClientConfig::from_env())?;
+
+                    if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
+                        llm = llm.with_api_key(api_key);
+                    }
+
+                    if let Ok(base_url) = std::env::var("OPENAI_BASE_URL") {
+                        llm = llm.with_base_url(base_url);
+                    }
+
+                    Ok(Box::new(llm) as Box<dyn BaseLlm>)
+                }),
+            );
+        }
+
+        debug!("OpenAI models registered");
+    }
+
+    /// Register local Ollama models as `ollama/<model>`; the server is taken from `OLLAMA_HOST`
+    #[cfg(feature = "ollama")]
+    fn register_ollama_models(models: &mut HashMap<String, ModelFactory>) {
+        use crate::models::{ollama_llm::OLLAMA_MODEL_PREFIX, OllamaLlm};
+
+        models.insert(
+            OLLAMA_MODEL_PREFIX.to_string(),
+            Box::new(|model_name: &str| {
+                let mut llm = OllamaLlm::new(model_name)
+                    .with_http_client_config(&HttpClientConfig::from_env())?;
+
+                if let Ok(host) = std::env::var("OLLAMA_HOST") {
+                    llm = llm.with_base_url(host);
+                }
+
+                Ok(Box::new(llm) as Box<dyn BaseLlm>)
+            }),
+        );
+
+        debug!("Ollama models registered");
+    }
+
+    /// Register Azure OpenAI deployments as `azure/<deployment>`; the resource
+    /// is taken from `AZURE_OPENAI_ENDPOINT`
+    #[cfg(feature = "azure-openai")]
+    fn register_azure_openai_models(models: &mut HashMap<String, ModelFactory>) {
+        use crate::models::{azure_openai_llm::AZURE_OPENAI_MODEL_PREFIX, AzureOpenAiLlm};
+
+        models.insert(
+            AZURE_OPENAI_MODEL_PREFIX.to_string(),
+            Box::new(|model_name: &str| {
+                let endpoint = std::env::var("AZURE_OPENAI_ENDPOINT").map_err(|_| {
+                    crate::adk_error!(ConfigError, "Set AZURE_OPENAI_ENDPOINT to use Azure OpenAI model: {}", model_name)
+                })?;
+                let deployment = model_name.strip_prefix(AZURE_OPENAI_MODEL_PREFIX).unwrap_or(model_name);
+                let mut llm = AzureOpenAiLlm::new(endpoint, deployment)
+                    .with_http_client_config(&HttpClientConfig::from_env())?;
+
+                if let Ok(api_version) = std::env::var("AZURE_OPENAI_API_VERSION") {
+                    llm = llm.with_api_version(api_version);
+                }
+
+                Ok(Box::new(llm) as Box<dyn BaseLlm>)
+            }),
+        );
+
+        debug!("Azure OpenAI models registered");
+    }
+
+    /// Register Bedrock models as `bedrock/<model id>`; the region is taken
+    /// from `AWS_REGION` or `AWS_DEFAULT_REGION`
+    #[cfg(feature = "bedrock")]
+    fn register_bedrock_models(models: &mut HashMap<String, ModelFactory>) {
+        use crate::models::{bedrock_llm::BEDROCK_MODEL_PREFIX, BedrockLlm};
+
+        models.insert(
+            BEDROCK_MODEL_PREFIX.to_string(),
+            Box::new(|model_name: &str| {
+                let region = std::env::var("AWS_REGION")
+                    .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+                    .map_err(|_| {
+                        crate::adk_error!(ConfigError, "Set AWS_REGION to use Bedrock model: {}", model_name)
+                    })?;
+                let llm = BedrockLlm::new(model_name, region)
+                    .with_http_client_config(&HttpClientConfig::from_env())?;
+
+                Ok(Box::new(llm) as Box<dyn BaseLlm>)
+            }),
+        );
+
+        debug!("Bedrock models registered");
+    }
+
     /// Register a model factory
     pub async fn register<F>(&self, pattern: String, factory: F)
     where
@@ -121,8 +246,36 @@ impl LlmRegistry {
         models.insert(pattern, Box::new(factory));
     }
 
+    /// Limit the requests sent to a model across all instances created for its name from now on
+    pub async fn set_rate_limit(&self, model_name: impl Into<String>, limit: RateLimit) {
+        let model_name = model_name.into();
+        debug!("Rate limit for model {}: {:?}", model_name, limit);
+        self.rate_limits
+            .write()
+            .await
+            .insert(model_name, Arc::new(RateLimiter::new(limit)));
+    }
+
+    /// Remove the rate limit of a model
+    pub async fn clear_rate_limit(&self, model_name: &str) {
+        self.rate_limits.write().await.remove(model_name);
+    }
+
+    /// Wrap a created model in its rate limiter, if it has one
+    async fn apply_rate_limit(&self, model_name: &str, model: Box<dyn BaseLlm>) -> Box<dyn BaseLlm> {
+        match self.rate_limits.read().await.get(model_name) {
+            Some(limiter) => Box::new(RateLimitedLlm::with_limiter(Arc::from(model), limiter.clone())),
+            None => model,
+        }
+    }
+
     /// Create a model instance
     pub async fn create_model(&self, model_name: &str) -> Result<Box<dyn BaseLlm>> {
+        let model = self.create_unlimited_model(model_name).await?;
+        Ok(self.apply_rate_limit(model_name, model).await)
+    }
+
+    async fn create_unlimited_model(&self, model_name: &str) -> Result<Box<dyn BaseLlm>> {
         let models = self.models.read().await;
         
         debug!("Creating model instance for: {}", model_name);
@@ -148,6 +301,26 @@ impl LlmRegistry {
         ))
     }
 
+    /// Create a model instance using the factory registered for a provider pattern
+    pub async fn create_model_for_provider(&self, provider: &str, model_name: &str) -> Result<Box<dyn BaseLlm>> {
+        let models = self.models.read().await;
+
+        debug!("Creating model instance for: {} (provider: {})", model_name, provider);
+
+        let model = match models.get(provider) {
+            Some(factory) => factory(model_name)?,
+            None => {
+                return Err(crate::adk_error!(
+                    ModelError,
+                    "No registered provider: {}. Available patterns: {:?}",
+                    provider,
+                    models.keys().collect::<Vec<_>>()
+                ))
+            }
+        };
+        Ok(self.apply_rate_limit(model_name, model).await)
+    }
+
     /// List available model patterns
     pub async fn list_patterns(&self) -> Vec<String> {
         let models = self.models.read().await;
@@ -185,6 +358,7 @@ impl LlmRegistry {
 
         // Create a temporary instance to get capabilities
         let model = self.create_model(model_name).await?;
+        let metadata = global_catalog().lookup(model_name).unwrap_or_default();
         
         Ok(ModelInfo {
             name: model_name.to_string(),
@@ -192,6 +366,11 @@ impl LlmRegistry {
             supports_function_calling: model.supports_function_calling(),
             supports_multimodal: model.supports_multimodal(),
             supports_live: model.supports_live(),
+            max_input_tokens: metadata.max_input_tokens,
+            max_output_tokens: metadata.max_output_tokens,
+            input_modalities: metadata.input_modalities,
+            output_modalities: metadata.output_modalities,
+            pricing: metadata.pricing,
         })
     }
 }
@@ -200,6 +379,7 @@ impl Clone for LlmRegistry {
     fn clone(&self) -> Self {
         Self {
             models: self.models.clone(),
+            rate_limits: self.rate_limits.clone(),
         }
     }
 }
@@ -211,18 +391,26 @@ impl Default for LlmRegistry {
 }
 
 /// Information about a model's capabilities
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ModelInfo {
     pub name: String,
     pub supports_streaming: bool,
     pub supports_function_calling: bool,
     pub supports_multimodal: bool,
     pub supports_live: bool,
+    /// Maximum prompt size in tokens, if known
+    pub max_input_tokens: Option<u32>,
+    /// Maximum generated tokens, if known
+    pub max_output_tokens: Option<u32>,
+    pub input_modalities: Vec<Modality>,
+    pub output_modalities: Vec<Modality>,
+    /// Token pricing, if known
+    pub pricing: Option<ModelPricing>,
 }
 
 /// Global registry instance
 static GLOBAL_REGISTRY: once_cell::sync::Lazy<LlmRegistry> = 
-    once_cell::sync::Lazy::new(|| LlmRegistry::new());
+    once_cell::sync::Lazy::new(LlmRegistry::new);
 
 /// Get the global registry
 pub fn global_registry() -> &'static LlmRegistry {
@@ -288,4 +476,11 @@ mod tests {
         let info = info.unwrap();
         assert!(info.supports_function_calling);
     }
+
+    #[test]
+    fn test_default_models_registered_without_runtime() {
+        let registry = LlmRegistry::new();
+        let models = registry.models.try_read().unwrap();
+        assert!(models.contains_key("gemini"));
+    }
 }
@@ -0,0 +1,155 @@
+//! Retry policy for model calls
+
+use reqwest::{header::HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// When and how often a failed model call is retried: on 429, 500, 502,
+/// 503 and 504 responses and on network errors, with exponential backoff
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Attempts including the first one; 1 disables retries
+    pub max_attempts: u32,
+
+    /// Delay before the first retry
+    pub initial_backoff_ms: u64,
+
+    /// Upper bound on the backoff delay
+    pub max_backoff_ms: u64,
+
+    /// Factor the delay grows by after every retry
+    pub multiplier: f64,
+
+    /// Wait a random delay between half and all of the backoff, so clients
+    /// throttled together do not retry together
+    pub jitter: bool,
+
+    /// Wait as long as the server asks with `Retry-After` instead of the backoff
+    pub respect_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 30_000,
+            multiplier: 2.0,
+            jitter: true,
+            respect_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail on the first error
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff_ms = initial.as_millis() as u64;
+        self.max_backoff_ms = max.as_millis() as u64;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_respect_retry_after(mut self, respect_retry_after: bool) -> Self {
+        self.respect_retry_after = respect_retry_after;
+        self
+    }
+
+    /// Whether a response status is worth retrying
+    pub fn is_retryable_status(status: StatusCode) -> bool {
+        matches!(status.as_u16(), 429 | 500 | 502 | 503 | 504)
+    }
+
+    /// Backoff before the given retry (1 for the first), without jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(retry.saturating_sub(1).min(32) as i32);
+        let millis = (self.initial_backoff_ms as f64 * factor).min(self.max_backoff_ms as f64);
+        Duration::from_millis(millis as u64)
+    }
+
+    /// Delay before the given retry, after a response with these headers if there was one
+    pub(crate) fn delay(&self, retry: u32, headers: Option<&HeaderMap>) -> Duration {
+        let retry_after = headers
+            .filter(|_| self.respect_retry_after)
+            .and_then(|headers| headers.get(reqwest::header::RETRY_AFTER))
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        if let Some(retry_after) = retry_after {
+            return retry_after;
+        }
+
+        let backoff = self.backoff(retry);
+        if self.jitter {
+            let fraction = (Uuid::new_v4().as_u128() % 1000) as f64 / 1000.0;
+            backoff.mul_f64(0.5 + fraction / 2.0)
+        } else {
+            backoff
+        }
+    }
+}
+
+/// Parse a `Retry-After` value: delay seconds or an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_up_to_the_limit() {
+        let policy = RetryPolicy::new()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(350))
+            .with_jitter(false);
+        let delays: Vec<_> = (1..=4).map(|retry| policy.delay(retry, None).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 350, 350]);
+
+        let jittered = policy.with_jitter(true).delay(2, None);
+        assert!(jittered >= Duration::from_millis(100) && jittered <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_retry_after_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(reqwest::header::RETRY_AFTER, "7".parse().unwrap());
+        let policy = RetryPolicy::new();
+        assert_eq!(policy.delay(1, Some(&headers)), Duration::from_secs(7));
+        assert!(policy.clone().with_respect_retry_after(false).delay(1, Some(&headers)) <= Duration::from_secs(1));
+
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
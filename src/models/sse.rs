@@ -0,0 +1,257 @@
+//! Server-Sent Events parsing for streaming model responses
+
+use crate::error::Result;
+use async_stream::stream;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use std::pin::Pin;
+use tracing::{debug, trace};
+
+/// Maximum number of payload characters included in parse error messages
+const ERROR_EXCERPT_CHARS: usize = 200;
+
+/// Payload some providers send to mark the end of a stream
+const DONE_SENTINEL: &str = "[DONE]";
+
+/// A single dispatched SSE event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event type, from the `event:` field
+    pub event: Option<String>,
+
+    /// Event payload, from one or more `data:` fields joined by newlines
+    pub data: String,
+
+    /// Event ID, from the `id:` field
+    pub id: Option<String>,
+}
+
+/// Incremental SSE decoder.
+///
+/// Bytes can be fed in arbitrary chunks; lines and UTF-8 sequences split
+/// across chunk boundaries are buffered until complete. Comment lines
+/// (keep-alives starting with `:`) are ignored.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of bytes and return any events completed by it
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|&b| b == b'\n') {
+            let mut line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+
+            let line = String::from_utf8_lossy(&line);
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Flush a trailing event that was not terminated by a blank line
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+            let line = line.trim_end_matches('\r');
+            if let Some(event) = self.process_line(line) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        if line.starts_with(':') {
+            trace!("Ignoring SSE comment line");
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "data" => self.data.push(value.to_string()),
+            "event" => self.event = Some(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            _ => trace!("Ignoring SSE field: {}", field),
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() {
+            self.event = None;
+            return None;
+        }
+
+        Some(SseEvent {
+            event: self.event.take(),
+            data: std::mem::take(&mut self.data).join("\n"),
+            id: self.id.clone(),
+        })
+    }
+}
+
+/// Truncate a payload for inclusion in an error message
+pub fn payload_excerpt(payload: &str) -> String {
+    let mut excerpt: String = payload.chars().take(ERROR_EXCERPT_CHARS).collect();
+    if payload.chars().count() > ERROR_EXCERPT_CHARS {
+        excerpt.push_str("...");
+    }
+    excerpt
+}
+
+/// Decode an SSE byte stream into JSON frames of type `T`.
+///
+/// Malformed frames surface as `ModelError`s carrying an excerpt of the
+/// offending payload, after which the stream ends. Transport errors surface
+/// as `NetworkError`s. The underlying byte stream is owned by the returned
+/// stream, so dropping it stops reading from the connection immediately.
+pub fn json_event_stream<T, S, E>(bytes: S) -> Pin<Box<dyn Stream<Item = Result<T>> + Send>>
+where
+    T: DeserializeOwned + Send + 'static,
+    S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    Box::pin(stream! {
+        let mut decoder = SseDecoder::new();
+        let mut bytes = Box::pin(bytes);
+
+        while let Some(chunk) = bytes.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    yield Err(crate::adk_error!(NetworkError, "Stream transport error: {}", e));
+                    return;
+                }
+            };
+
+            for event in decoder.feed(&chunk) {
+                if event.data == DONE_SENTINEL {
+                    debug!("Received end-of-stream sentinel");
+                    return;
+                }
+                match parse_frame(&event.data) {
+                    Ok(frame) => yield Ok(frame),
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+        }
+
+        if let Some(event) = decoder.finish() {
+            if event.data != DONE_SENTINEL {
+                yield parse_frame(&event.data);
+            }
+        }
+    })
+}
+
+fn parse_frame<T: DeserializeOwned>(data: &str) -> Result<T> {
+    serde_json::from_str(data).map_err(|e| {
+        crate::adk_error!(
+            ModelError,
+            "Malformed stream frame: {} (payload: {})",
+            e,
+            payload_excerpt(data)
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_decoder_handles_split_frames_and_comments() {
+        let mut decoder = SseDecoder::new();
+
+        assert!(decoder.feed(b": keep-alive\r\n\r\ndata: {\"a\":").is_empty());
+        let events = decoder.feed(b" 1}\r\n\r\ndata: {\"a\": 2}\n\ndata: {\"a\"");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].data, "{\"a\": 1}");
+        assert_eq!(events[1].data, "{\"a\": 2}");
+
+        assert!(decoder.feed(b": 3}").is_empty());
+        assert_eq!(decoder.finish().unwrap().data, "{\"a\": 3}");
+    }
+
+    fn decode_all(chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut decoder = SseDecoder::new();
+        let mut events: Vec<SseEvent> = chunks.iter().flat_map(|chunk| decoder.feed(chunk)).collect();
+        events.extend(decoder.finish());
+        events
+    }
+
+    proptest! {
+        #[test]
+        fn test_chunk_boundaries_do_not_change_events(
+            input in prop::collection::vec(prop_oneof![any::<u8>(), Just(b'\n'), Just(b'\r'), Just(b':')], 0..256),
+            splits in prop::collection::vec(any::<prop::sample::Index>(), 0..6),
+        ) {
+            let mut points: Vec<usize> = splits.iter().map(|split| split.index(input.len() + 1)).collect();
+            points.sort_unstable();
+            let mut chunks = Vec::new();
+            let mut start = 0;
+            for point in points {
+                chunks.push(&input[start..point]);
+                start = point;
+            }
+            chunks.push(&input[start..]);
+            prop_assert_eq!(decode_all(&chunks), decode_all(&[&input]));
+        }
+
+        #[test]
+        fn test_data_frames_round_trip(payloads in prop::collection::vec("[^\r\n]*", 1..8), crlf in any::<bool>()) {
+            let newline = if crlf { "\r\n" } else { "\n" };
+            let stream: String = payloads
+                .iter()
+                .map(|payload| format!("data: {}{}{}", payload, newline, newline))
+                .collect();
+            let data: Vec<String> = decode_all(&[stream.as_bytes()]).into_iter().map(|event| event.data).collect();
+            prop_assert_eq!(data, payloads);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_event_stream_reports_malformed_frame() {
+        let chunks: Vec<std::result::Result<Bytes, std::io::Error>> = vec![
+            Ok(Bytes::from_static(b"data: {\"a\": 1}\n\n")),
+            Ok(Bytes::from_static(b"data: {not json}\n\n")),
+            Ok(Bytes::from_static(b"data: {\"a\": 3}\n\n")),
+        ];
+        let frames: Vec<Result<serde_json::Value>> =
+            json_event_stream(futures::stream::iter(chunks)).collect().await;
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].as_ref().unwrap()["a"], 1);
+        let err = frames[1].as_ref().unwrap_err().to_string();
+        assert!(err.contains("{not json}"));
+    }
+}
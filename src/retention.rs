@@ -0,0 +1,428 @@
+//! Data retention and user data deletion
+//!
+//! [`RetentionPolicies`] set how long each app keeps conversations: a
+//! [`RetentionJob`] periodically deletes sessions idle for longer, together
+//! with their artifacts. [`DataEraser::delete_user_data`] purges everything
+//! stored about one user (GDPR erasure requests), and backs
+//! `DELETE /api/users/{user_id}/data`. Every deletion leaves a
+//! [`DeletionRecord`] in a [`DeletionAuditLog`]; records hold identifiers
+//! and counts, never the deleted content.
+//!
+//! The memory module has no storage service yet, so there are no memories to
+//! purge; sessions and artifacts are all the framework stores about a user.
+
+use crate::{
+    artifacts::BaseArtifactService,
+    error::Result,
+    sessions::{SessionFilter, SessionService},
+    types::{Timestamp, UserId},
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{io::AsyncWriteExt, sync::Mutex, task::JoinHandle};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{info, warn};
+
+/// How long an app keeps its conversations
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Sessions idle this many days are deleted with their artifacts; kept forever when unset
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn days(max_age_days: u64) -> Self {
+        Self {
+            max_age_days: Some(max_age_days),
+        }
+    }
+}
+
+/// Retention policies per app, with a default for apps not listed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicies {
+    #[serde(default)]
+    pub default: RetentionPolicy,
+
+    #[serde(default)]
+    pub apps: HashMap<String, RetentionPolicy>,
+}
+
+impl RetentionPolicies {
+    pub fn new(default: RetentionPolicy) -> Self {
+        Self {
+            default,
+            apps: HashMap::new(),
+        }
+    }
+
+    pub fn with_app(mut self, app_name: impl Into<String>, policy: RetentionPolicy) -> Self {
+        self.apps.insert(app_name.into(), policy);
+        self
+    }
+
+    pub fn for_app(&self, app_name: &str) -> &RetentionPolicy {
+        self.apps.get(app_name).unwrap_or(&self.default)
+    }
+}
+
+/// Schedule and policies of the retention job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// How often expired data is deleted; 0 disables the job
+    pub interval_seconds: u64,
+    pub policies: RetentionPolicies,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 3600,
+            policies: RetentionPolicies::default(),
+        }
+    }
+}
+
+/// Why data was deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionReason {
+    /// The user asked for their data to be erased
+    UserRequest,
+
+    /// The app's retention policy expired it
+    Retention,
+}
+
+/// Audit record of one deletion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeletionRecord {
+    pub id: String,
+    pub reason: DeletionReason,
+
+    /// User whose data was erased, for user requests
+    #[serde(default)]
+    pub user_id: Option<UserId>,
+
+    /// App whose expired sessions were deleted, for retention
+    #[serde(default)]
+    pub app_name: Option<String>,
+
+    /// Who asked for the deletion (e.g. an operator or ticket reference)
+    #[serde(default)]
+    pub requested_by: Option<String>,
+    pub deleted_at: Timestamp,
+    pub sessions: usize,
+    pub artifacts: usize,
+}
+
+impl DeletionRecord {
+    fn new(reason: DeletionReason) -> Self {
+        Self {
+            id: crate::utils::new_id(),
+            reason,
+            user_id: None,
+            app_name: None,
+            requested_by: None,
+            deleted_at: crate::types::now(),
+            sessions: 0,
+            artifacts: 0,
+        }
+    }
+}
+
+/// Append-only log of deletions
+#[async_trait]
+pub trait DeletionAuditLog: Send + Sync {
+    async fn record(&self, record: &DeletionRecord) -> Result<()>;
+
+    /// All records, oldest first
+    async fn list(&self) -> Result<Vec<DeletionRecord>>;
+}
+
+/// Keeps deletion records in memory, for tests and development
+#[derive(Debug, Default)]
+pub struct InMemoryDeletionAuditLog {
+    records: RwLock<Vec<DeletionRecord>>,
+}
+
+impl InMemoryDeletionAuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DeletionAuditLog for InMemoryDeletionAuditLog {
+    async fn record(&self, record: &DeletionRecord) -> Result<()> {
+        self.records.write().unwrap().push(record.clone());
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<DeletionRecord>> {
+        Ok(self.records.read().unwrap().clone())
+    }
+}
+
+/// Appends deletion records to a JSON Lines file
+#[derive(Debug)]
+pub struct FileDeletionAuditLog {
+    path: PathBuf,
+    // Serializes appends so records never interleave
+    lock: Mutex<()>,
+}
+
+impl FileDeletionAuditLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl DeletionAuditLog for FileDeletionAuditLog {
+    async fn record(&self, record: &DeletionRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let _appending = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(&self.path).await?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<DeletionRecord>> {
+        let contents = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(Into::into))
+            .collect()
+    }
+}
+
+/// Deletes user data across the session and artifact services, auditing every deletion
+#[derive(Clone)]
+pub struct DataEraser {
+    session_service: Arc<dyn SessionService>,
+    artifact_service: Option<Arc<dyn BaseArtifactService>>,
+    audit: Arc<dyn DeletionAuditLog>,
+}
+
+impl DataEraser {
+    pub fn new(session_service: Arc<dyn SessionService>, audit: Arc<dyn DeletionAuditLog>) -> Self {
+        Self {
+            session_service,
+            artifact_service: None,
+            audit,
+        }
+    }
+
+    pub fn with_artifact_service(mut self, service: Arc<dyn BaseArtifactService>) -> Self {
+        self.artifact_service = Some(service);
+        self
+    }
+
+    pub fn audit_log(&self) -> Arc<dyn DeletionAuditLog> {
+        self.audit.clone()
+    }
+
+    /// Erase every session and artifact of a user in every app
+    pub async fn delete_user_data(&self, user_id: &UserId, requested_by: Option<String>) -> Result<DeletionRecord> {
+        let mut record = DeletionRecord::new(DeletionReason::UserRequest);
+        record.user_id = Some(user_id.clone());
+        record.requested_by = requested_by;
+
+        let filter = SessionFilter::new().with_user_id(user_id.clone());
+        for session in self.session_service.list_sessions(&filter).await? {
+            if self.session_service.delete_session(&session.id).await? {
+                record.sessions += 1;
+            }
+        }
+        if let Some(artifacts) = &self.artifact_service {
+            record.artifacts = artifacts.delete_user_artifacts(user_id).await?;
+        }
+
+        record.deleted_at = crate::types::now();
+        self.audit.record(&record).await?;
+        info!(
+            "Deleted data of user {}: {} sessions, {} artifacts",
+            user_id, record.sessions, record.artifacts
+        );
+        Ok(record)
+    }
+
+    /// Delete sessions idle longer than their app's policy allows, with their artifacts
+    pub async fn apply_retention(&self, policies: &RetentionPolicies) -> Result<Vec<DeletionRecord>> {
+        let now = crate::types::now();
+        let mut per_app: BTreeMap<String, DeletionRecord> = BTreeMap::new();
+        for session in self.session_service.list_sessions(&SessionFilter::new()).await? {
+            let Some(days) = policies.for_app(&session.app_name).max_age_days else {
+                continue;
+            };
+            if now - session.updated_at < chrono::Duration::days(days as i64) {
+                continue;
+            }
+
+            let record = per_app.entry(session.app_name.clone()).or_insert_with(|| {
+                let mut record = DeletionRecord::new(DeletionReason::Retention);
+                record.app_name = Some(session.app_name.clone());
+                record
+            });
+            if let Some(artifacts) = &self.artifact_service {
+                record.artifacts += artifacts
+                    .delete_session_artifacts(&session.app_name, &session.user_id, &session.id)
+                    .await?;
+            }
+            if self.session_service.delete_session(&session.id).await? {
+                record.sessions += 1;
+            }
+        }
+
+        let mut records = Vec::new();
+        for (_, mut record) in per_app {
+            record.deleted_at = crate::types::now();
+            self.audit.record(&record).await?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+/// Periodically applies retention policies
+pub struct RetentionJob {
+    eraser: DataEraser,
+    config: RetentionConfig,
+}
+
+impl RetentionJob {
+    pub fn new(eraser: DataEraser, config: RetentionConfig) -> Self {
+        Self { eraser, config }
+    }
+
+    /// Run `apply_retention` on `tasks` every interval until `cancel` fires
+    pub fn spawn(self, tasks: &TaskTracker, cancel: CancellationToken) -> JoinHandle<()> {
+        let interval = Duration::from_secs(self.config.interval_seconds.max(1));
+        tasks.spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                match self.eraser.apply_retention(&self.config.policies).await {
+                    Ok(records) => {
+                        for record in records {
+                            info!(
+                                "Retention deleted {} sessions and {} artifacts of {}",
+                                record.sessions,
+                                record.artifacts,
+                                record.app_name.unwrap_or_default()
+                            );
+                        }
+                    }
+                    Err(e) => warn!("Retention cleanup failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        artifacts::{ArtifactKey, ArtifactMetadata, InMemoryArtifactService},
+        events::Event,
+        sessions::{InMemorySessionService, Session},
+    };
+    use bytes::Bytes;
+
+    async fn session_with_artifact(
+        sessions: &InMemorySessionService,
+        artifacts: &InMemoryArtifactService,
+        app: &str,
+        user: &str,
+        id: &str,
+        age_days: i64,
+    ) {
+        let mut session = Session::new(app.into(), user.into(), id.into());
+        session.add_event(Event::text_response("agent", "hello"));
+        session.updated_at = crate::types::now() - chrono::Duration::days(age_days);
+        sessions.create_session(session).await.unwrap();
+        let key = ArtifactKey::new(app, user, id, "notes.txt");
+        artifacts.save_artifact(&key, Bytes::from_static(b"notes"), ArtifactMetadata::new()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_user_deletion_and_retention_are_audited() {
+        let sessions = Arc::new(InMemorySessionService::new());
+        let artifacts = Arc::new(InMemoryArtifactService::new());
+        session_with_artifact(&sessions, &artifacts, "shop", "ada", "s1", 0).await;
+        session_with_artifact(&sessions, &artifacts, "support", "ada", "s2", 0).await;
+        session_with_artifact(&sessions, &artifacts, "shop", "bob", "s3", 40).await;
+        session_with_artifact(&sessions, &artifacts, "support", "bob", "s4", 40).await;
+        session_with_artifact(&sessions, &artifacts, "shop", "eve", "s5", 1).await;
+
+        let audit = Arc::new(InMemoryDeletionAuditLog::new());
+        let eraser = DataEraser::new(sessions.clone(), audit.clone()).with_artifact_service(artifacts.clone());
+
+        let record = eraser.delete_user_data(&"ada".to_string(), Some("ticket-7".into())).await.unwrap();
+        assert_eq!((record.sessions, record.artifacts), (2, 2));
+        let remaining = sessions.list_sessions(&SessionFilter::new()).await.unwrap();
+        assert!(remaining.iter().all(|session| session.user_id != "ada"));
+        assert!(artifacts.list_artifact_keys("shop", &"ada".to_string(), &"s1".to_string()).await.unwrap().is_empty());
+
+        // Only `shop` keeps sessions for 30 days; `support` keeps them forever
+        let policies = RetentionPolicies::default().with_app("shop", RetentionPolicy::days(30));
+        let records = eraser.apply_retention(&policies).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].app_name.as_deref(), Some("shop"));
+        assert_eq!((records[0].sessions, records[0].artifacts), (1, 1));
+        let mut remaining: Vec<String> = sessions
+            .list_sessions(&SessionFilter::new())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|session| session.id)
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, ["s4", "s5"]);
+
+        let log = audit.list().await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!((log[0].reason, log[0].requested_by.as_deref()), (DeletionReason::UserRequest, Some("ticket-7")));
+        assert_eq!(log[1].reason, DeletionReason::Retention);
+    }
+
+    #[tokio::test]
+    async fn test_file_audit_log_appends_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = FileDeletionAuditLog::new(dir.path().join("deletions.jsonl"));
+        assert!(log.list().await.unwrap().is_empty());
+
+        let mut record = DeletionRecord::new(DeletionReason::UserRequest);
+        record.user_id = Some("ada".into());
+        log.record(&record).await.unwrap();
+        log.record(&DeletionRecord::new(DeletionReason::Retention)).await.unwrap();
+
+        let records = log.list().await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], record);
+    }
+}
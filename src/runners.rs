@@ -1,25 +1,57 @@
 //! Agent runners for executing agents
 
 use crate::{
-    agents::{BaseAgent, InvocationContext},
+    agents::{
+        global_flags, instrument_events, stamp_agent_version, BaseAgent, InvocationContext, LiveRequestQueue,
+        ResponseValidation, RunConfig, DRY_RUN_REQUEST_METADATA_KEY,
+    },
     error::Result,
-    events::Event,
-    sessions::{Session, SessionService},
-    types::{Content, SessionId, UserId},
+    events::{
+        apply_output_processors, Event, EventBus, LifecycleEvent, LifecycleEventType, OutputProcessor, PublishedEvent,
+        RedactionVault, WebhookDispatcher,
+    },
+    experiments::{Experiment, ExperimentAssignment},
+    models::{CostTracker, LlmRequest, PricingTable, PromptEstimate},
+    sessions::{
+        begin_handoff, is_human_controlled, InMemorySessionService, Session, SessionService, StatelessResult,
+        StatelessSession,
+    },
+    types::{Content, InvocationId, SessionId, UserId},
+    utils::{global_trace_store, global_usage_tracker, Determinism, InvocationRecord, SpanKind, TraceCollector, TraceSpan},
 };
-use async_trait::async_trait;
-use futures::Stream;
-use std::{pin::Pin, sync::Arc};
-use tracing::{info, instrument};
+use async_stream::stream;
+use futures::{Future, Stream, StreamExt};
+use std::{
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{info, instrument, warn, Instrument};
+
+/// How long `Runner::close` waits for in-flight streams and tasks to finish
+pub const RUNNER_CLOSE_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Stream of events from runner execution
-pub type RunnerEventStream = Pin<Box<dyn Stream<Item = Result<Event>> + Send>>;
+pub type RunnerEventStream = Pin<Box<dyn Stream<Item = Result<Arc<Event>>> + Send>>;
 
 /// Runner for executing agents
 pub struct Runner {
     app_name: String,
     agent: Arc<dyn BaseAgent>,
     session_service: Arc<dyn SessionService>,
+    run_config: RunConfig,
+    cancel: CancellationToken,
+    tasks: TaskTracker,
+    event_bus: Option<EventBus>,
+    output_processors: Vec<Arc<dyn OutputProcessor>>,
+    webhooks: Option<WebhookDispatcher>,
+    determinism: Option<Determinism>,
+    experiment: Option<Arc<Experiment>>,
+    response_validation: Option<ResponseValidation>,
+    redaction_vault: Option<Arc<dyn RedactionVault>>,
+    pricing: Option<Arc<PricingTable>>,
 }
 
 impl Runner {
@@ -33,11 +65,123 @@ impl Runner {
             app_name: app_name.into(),
             agent,
             session_service,
+            run_config: RunConfig::default(),
+            cancel: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+            event_bus: None,
+            output_processors: Vec::new(),
+            webhooks: None,
+            determinism: None,
+            experiment: None,
+            response_validation: None,
+            redaction_vault: None,
+            pricing: None,
         }
     }
 
+    /// Rewrite the text of final agent events before they are persisted and streamed
+    pub fn with_output_processor(mut self, processor: Arc<dyn OutputProcessor>) -> Self {
+        self.output_processors.push(processor);
+        self
+    }
+
+    /// Stop this runner's streams and tasks when `token` is cancelled (e.g. a server shutdown token)
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Publish every streamed event, partial or complete, to `bus`
+    pub fn with_event_bus(mut self, bus: EventBus) -> Self {
+        self.event_bus = Some(bus);
+        self
+    }
+
+    /// Report created sessions and finished or failed invocations to the app's webhooks
+    pub fn with_webhooks(mut self, webhooks: WebhookDispatcher) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Take timestamps and IDs of runs from this clock and generator, e.g. for reproducible transcripts
+    pub fn with_determinism(mut self, determinism: Determinism) -> Self {
+        self.determinism = Some(determinism);
+        self
+    }
+
+    /// New session ID from the runner's ID generator
+    pub fn new_session_id(&self) -> SessionId {
+        match &self.determinism {
+            Some(determinism) => determinism.ids.new_uuid().to_string(),
+            None => crate::utils::new_id(),
+        }
+    }
+
+    /// Split sessions between the experiment's variants and tag their events with the assigned one
+    pub fn with_experiment(mut self, experiment: Experiment) -> Self {
+        self.experiment = Some(Arc::new(experiment));
+        self
+    }
+
+    /// Check final model answers, re-prompting the model when a check fails
+    pub fn with_response_validation(mut self, validation: ResponseValidation) -> Self {
+        self.response_validation = Some(validation);
+        self
+    }
+
+    /// Keep the originals of redacted events in `vault`; without one they are discarded
+    pub fn with_redaction_vault(mut self, vault: Arc<dyn RedactionVault>) -> Self {
+        self.redaction_vault = Some(vault);
+        self
+    }
+
+    /// Price model calls from `pricing` before the model catalog
+    pub fn with_pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = Some(Arc::new(pricing));
+        self
+    }
+
+    /// Token cancelled when the runner is closed
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Spawn a background task that is cancelled and awaited by `close`
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<Option<F::Output>>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let cancel = self.cancel.clone();
+        self.tasks.spawn(async move {
+            tokio::select! {
+                _ = cancel.cancelled() => None,
+                output = task => Some(output),
+            }
+        })
+    }
+
+    /// End the stream on cancellation and keep the runner's tracker open while it is alive
+    fn supervise(&self, events: RunnerEventStream) -> RunnerEventStream {
+        let guard = self.tasks.token();
+        Box::pin(
+            events
+                .take_until(self.cancel.clone().cancelled_owned())
+                .map(move |event| {
+                    let _alive = &guard;
+                    event
+                }),
+        )
+    }
+
+    /// Set the run configuration applied to every invocation
+    pub fn with_run_config(mut self, run_config: RunConfig) -> Self {
+        self.run_config = run_config;
+        self
+    }
+
     /// Run the agent with a new message
-    #[instrument(skip(self, new_message))]
+    #[instrument(skip(self, new_message), fields(app_name = %self.app_name))]
     pub async fn run_async(
         &self,
         user_id: UserId,
@@ -45,53 +189,309 @@ impl Runner {
         new_message: Content,
     ) -> Result<RunnerEventStream> {
         info!("Running agent for session: {}", session_id);
+        match &self.determinism {
+            Some(determinism) => {
+                let events = determinism.scope(self.start_run(user_id, session_id, new_message)).await?;
+                Ok(Box::pin(determinism.scope_stream(events)))
+            }
+            None => self.start_run(user_id, session_id, new_message).await,
+        }
+    }
+
+    async fn start_run(
+        &self,
+        user_id: UserId,
+        session_id: SessionId,
+        new_message: Content,
+    ) -> Result<RunnerEventStream> {
+        self.check_agent_enabled()?;
 
         // Get or create session
-        let session = self
-            .session_service
-            .get_session(&self.app_name, &user_id, &session_id)
-            .await?
-            .unwrap_or_else(|| {
-                // Create new session if not found
-                Session::new(self.app_name.clone(), user_id.clone(), session_id.clone())
-            });
+        let session = self.get_or_create_session(&user_id, &session_id).await?;
 
         // Create invocation context
-        let context = InvocationContext::new(
+        let mut context = InvocationContext::new(
             session.id.clone(),
             session.user_id.clone(),
             session.app_name.clone(),
             session.state.clone(),
             self.session_service.clone(),
         );
+        context.run_config = self.run_config.clone();
+        context.experiment = self.assign_variant(&session.id);
+        context.response_validation = self.response_validation.clone();
+        if let Some(pricing) = &self.pricing {
+            context.cost = CostTracker::with_pricing(pricing.clone());
+        }
 
         // Add the new message to session
-        let user_event = Event::user_input(new_message.get_text(), context.invocation_id);
+        let mut user_event = Event::user_input(new_message.get_text(), context.invocation_id);
+        stamp_agent_version(self.agent.as_ref(), &mut user_event);
+        if let Some(assignment) = &context.experiment {
+            assignment.tag(&mut user_event);
+        }
+        let user_event = Arc::new(user_event);
         self.session_service
-            .append_event(&session.id, user_event)
+            .append_event(&session.id, user_event.clone())
+            .await?;
+        if let Some(bus) = &self.event_bus {
+            bus.publish(PublishedEvent {
+                app_name: self.app_name.clone(),
+                user_id: session.user_id.clone(),
+                session_id: session.id.clone(),
+                event: user_event,
+            });
+        }
+
+        // An operator has the conversation; the user's message waits for them
+        if is_human_controlled(&session) {
+            info!("Session {} is human-controlled, not running the agent", session.id);
+            return Ok(Box::pin(futures::stream::empty()));
+        }
+
+        let invocation_id = context.invocation_id;
+        let trace = context.trace.clone();
+        let span = context.span.clone();
+        let experiment = context.experiment.clone();
+        global_trace_store().begin(invocation_id, session.id.clone(), &self.app_name, self.agent.name(), trace.clone());
+
+        // Run the agent, persisting completed events to the session
+        let events = match self.agent.run_async(context).instrument(span.clone()).await {
+            Ok(events) => events,
+            Err(e) => {
+                global_trace_store().finish(invocation_id, true);
+                self.notify(
+                    LifecycleEvent::new(LifecycleEventType::Error, &self.app_name, &session.user_id, &session.id)
+                        .with_invocation_id(invocation_id)
+                        .with_data(serde_json::json!({ "error": e.to_string() })),
+                );
+                return Err(e);
+            }
+        };
+        let events = self.supervise(instrument_events(events, span));
+        let events =
+            self.persist_events(session.id.clone(), session.user_id.clone(), invocation_id, trace, experiment, events);
+        Ok(self.track_invocation(session.user_id, session.id, invocation_id, events))
+    }
+
+    /// Refuse to run an agent an operator switched off
+    fn check_agent_enabled(&self) -> Result<()> {
+        if global_flags().is_agent_disabled(self.agent.name()) {
+            warn!("Agent {} is disabled by a feature flag", self.agent.name());
+            return Err(crate::adk_error!(AgentError, "Agent '{}' is disabled", self.agent.name()));
+        }
+        Ok(())
+    }
+
+    /// The experiment variant of a session, if the runner runs an experiment
+    fn assign_variant(&self, session_id: &SessionId) -> Option<ExperimentAssignment> {
+        let experiment = self.experiment.as_ref()?;
+        ExperimentAssignment::for_session(experiment, self.agent.name(), session_id)
+    }
+
+    /// Load the session, creating it (and notifying webhooks) if it does not exist
+    async fn get_or_create_session(&self, user_id: &UserId, session_id: &SessionId) -> Result<Session> {
+        if let Some(session) = self.session_service.get_session(&self.app_name, user_id, session_id).await? {
+            return Ok(session);
+        }
+        let session = self
+            .session_service
+            .get_or_create_session(&self.app_name, user_id, session_id)
             .await?;
+        self.notify(LifecycleEvent::new(
+            LifecycleEventType::SessionCreated,
+            &self.app_name,
+            &session.user_id,
+            &session.id,
+        ));
+        Ok(session)
+    }
+
+    fn notify(&self, event: LifecycleEvent) {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.dispatch(event);
+        }
+    }
+
+    /// Stamp each event with the invocation, agent version and experiment
+    /// variant, run the output processors, redact events marked as secret, append complete (non-partial)
+    /// events to the session as they are streamed, tracing state mutations, and publish them to the event bus
+    fn persist_events(
+        &self,
+        session_id: SessionId,
+        user_id: UserId,
+        invocation_id: InvocationId,
+        trace: TraceCollector,
+        experiment: Option<ExperimentAssignment>,
+        events: RunnerEventStream,
+    ) -> RunnerEventStream {
+        let session_service = self.session_service.clone();
+        let agent = self.agent.clone();
+        let app_name = self.app_name.clone();
+        let event_bus = self.event_bus.clone();
+        let output_processors = self.output_processors.clone();
+        let webhooks = self.webhooks.clone();
+        let redaction_vault = self.redaction_vault.clone();
+        Box::pin(events.then(move |result| {
+            let session_service = session_service.clone();
+            let session_id = session_id.clone();
+            let agent = agent.clone();
+            let app_name = app_name.clone();
+            let user_id = user_id.clone();
+            let publish = event_bus.clone();
+            let trace = trace.clone();
+            let output_processors = output_processors.clone();
+            let webhooks = webhooks.clone();
+            let experiment = experiment.clone();
+            let redaction_vault = redaction_vault.clone();
+            async move {
+                let mut event = result?;
+                // Events are normally unshared here, so stamping does not copy them
+                let stamped = Arc::make_mut(&mut event);
+                stamped.invocation_id = invocation_id;
+                stamp_agent_version(agent.as_ref(), stamped);
+                if let Some(assignment) = &experiment {
+                    assignment.tag(stamped);
+                }
+                apply_output_processors(stamped, &output_processors);
+                if event.redact {
+                    // Only the vault, if any, ever sees the original
+                    if let (Some(vault), false) = (&redaction_vault, event.is_partial) {
+                        if let Err(e) = vault.store(&session_id, &event).await {
+                            warn!("Failed to keep the original of redacted event {}: {}", event.id, e);
+                        }
+                    }
+                    event = Arc::new(event.redacted());
+                }
+                if !event.is_partial {
+                    let started = Instant::now();
+                    let persisted = session_service.append_event(&session_id, event.clone()).await;
+                    if !event.actions.state_delta.is_empty() {
+                        let mut keys: Vec<String> = event.actions.state_delta.keys().cloned().collect();
+                        keys.sort();
+                        let span = TraceSpan::finished(
+                            &event.author,
+                            SpanKind::StateMutation { keys, event_id: event.id.clone() },
+                            started,
+                        );
+                        trace.record(match &persisted {
+                            Ok(()) => span,
+                            Err(e) => span.with_error(e),
+                        });
+                    }
+                    if let Err(e) = persisted {
+                        warn!("Failed to persist event {}: {}", event.id, e);
+                        return Err(e);
+                    }
+                    if let Some(handoff) = &event.actions.handoff {
+                        if begin_handoff(session_service.as_ref(), &app_name, &user_id, &session_id).await? {
+                            info!("Session {} handed off to an operator: {}", session_id, handoff.reason);
+                            if let Some(webhooks) = &webhooks {
+                                webhooks.dispatch(
+                                    LifecycleEvent::new(
+                                        LifecycleEventType::HandoffRequested,
+                                        &app_name,
+                                        &user_id,
+                                        &session_id,
+                                    )
+                                    .with_invocation_id(invocation_id)
+                                    .with_data(serde_json::json!({ "reason": handoff.reason })),
+                                );
+                            }
+                        }
+                    }
+                }
+                if let Some(bus) = publish {
+                    bus.publish(PublishedEvent {
+                        app_name,
+                        user_id,
+                        session_id,
+                        event: event.clone(),
+                    });
+                }
+                Ok(event)
+            }
+        }))
+    }
+
+    /// Record the invocation's latency and outcome, finish its trace and
+    /// notify webhooks once its event stream ends
+    fn track_invocation(
+        &self,
+        user_id: UserId,
+        session_id: SessionId,
+        invocation_id: InvocationId,
+        events: RunnerEventStream,
+    ) -> RunnerEventStream {
+        let app_name = self.app_name.clone();
+        let webhooks = self.webhooks.clone();
+        let agent_name = self.agent.name().to_string();
+        let agent_version = self.agent.version().map(str::to_string);
+        let config_hash = self.agent.config_hash().map(str::to_string);
+        let started = Instant::now();
 
-        // Run the agent
-        self.agent.run_async(context).await
+        Box::pin(stream! {
+            let mut events = events;
+            let mut error = None;
+            let mut event_count = 0;
+            while let Some(result) = events.next().await {
+                match &result {
+                    Ok(_) => event_count += 1,
+                    Err(e) => error = Some(e.to_string()),
+                }
+                yield result;
+            }
+            let is_error = error.is_some();
+            let duration = started.elapsed();
+            if let Some(webhooks) = webhooks {
+                let event = match error {
+                    Some(error) => LifecycleEvent::new(LifecycleEventType::Error, &app_name, user_id, session_id)
+                        .with_data(serde_json::json!({ "error": error })),
+                    None => LifecycleEvent::new(LifecycleEventType::InvocationFinished, &app_name, user_id, session_id)
+                        .with_data(serde_json::json!({
+                            "agent": agent_name,
+                            "duration_ms": duration.as_millis() as u64,
+                            "event_count": event_count,
+                        })),
+                };
+                webhooks.dispatch(event.with_invocation_id(invocation_id));
+            }
+            global_usage_tracker().record_invocation(
+                InvocationRecord::new(app_name, agent_name, duration, is_error)
+                    .with_agent_version(agent_version, config_hash),
+            );
+            global_trace_store().finish(invocation_id, is_error);
+        })
     }
 
-    /// Run the agent in live mode
-    #[instrument(skip(self))]
+    /// Run the agent in live mode, reading user input from `queue` until it closes
+    #[instrument(skip(self, queue), fields(app_name = %self.app_name))]
     pub async fn run_live(
         &self,
         user_id: UserId,
         session_id: SessionId,
+        queue: LiveRequestQueue,
     ) -> Result<RunnerEventStream> {
         info!("Running agent in live mode for session: {}", session_id);
+        match &self.determinism {
+            Some(determinism) => {
+                let events = determinism.scope(self.start_live(user_id, session_id, queue)).await?;
+                Ok(Box::pin(determinism.scope_stream(events)))
+            }
+            None => self.start_live(user_id, session_id, queue).await,
+        }
+    }
+
+    async fn start_live(
+        &self,
+        user_id: UserId,
+        session_id: SessionId,
+        queue: LiveRequestQueue,
+    ) -> Result<RunnerEventStream> {
+        self.check_agent_enabled()?;
 
         // Get or create session
-        let session = self
-            .session_service
-            .get_session(&self.app_name, &user_id, &session_id)
-            .await?
-            .unwrap_or_else(|| {
-                Session::new(self.app_name.clone(), user_id.clone(), session_id.clone())
-            });
+        let session = self.get_or_create_session(&user_id, &session_id).await?;
 
         // Create invocation context for live mode
         let mut context = InvocationContext::new(
@@ -101,17 +501,119 @@ impl Runner {
             session.state.clone(),
             self.session_service.clone(),
         );
+        context.run_config = self.run_config.clone();
         context.is_live = true;
+        context.live_request_queue = Some(queue);
+        context.experiment = self.assign_variant(&session.id);
+        context.response_validation = self.response_validation.clone();
+        let invocation_id = context.invocation_id;
+        let trace = context.trace.clone();
+        let span = context.span.clone();
+        let experiment = context.experiment.clone();
 
         // Run the agent in live mode
-        self.agent.run_live(context).await
+        let events = self.agent.run_live(context).instrument(span.clone()).await?;
+        let events = self.supervise(instrument_events(events, span));
+        Ok(self.persist_events(session.id, session.user_id, invocation_id, trace, experiment, events))
     }
 
-    /// Close the runner and cleanup resources
+    /// Estimate the size of the model requests a message would cause, without
+    /// calling any model or changing the session
+    pub async fn estimate(
+        &self,
+        user_id: UserId,
+        session_id: SessionId,
+        new_message: Content,
+    ) -> Result<Vec<PromptEstimate>> {
+        let session = self
+            .session_service
+            .get_session(&self.app_name, &user_id, &session_id)
+            .await?
+            .unwrap_or_else(|| Session::new(self.app_name.clone(), user_id.clone(), session_id.clone()));
+        // The agents read history from a scratch copy holding the hypothetical message
+        let scratch = Arc::new(InMemorySessionService::new());
+        scratch.create_session(session.clone()).await?;
+
+        let mut context =
+            InvocationContext::new(session.id.clone(), session.user_id, session.app_name, session.state, scratch.clone());
+        context.run_config = RunConfig {
+            dry_run: true,
+            breakpoints: Default::default(),
+            ..self.run_config.clone()
+        };
+        context.experiment = self.assign_variant(&session.id);
+        let user_event = Event::user_input(new_message.get_text(), context.invocation_id);
+        scratch.append_event(&session.id, Arc::new(user_event)).await?;
+
+        let mut events = self.agent.run_async(context).await?;
+        let mut estimates = Vec::new();
+        while let Some(event) = events.next().await {
+            let event = event?;
+            if let Some(request) = event.metadata.get(DRY_RUN_REQUEST_METADATA_KEY) {
+                let request: LlmRequest = serde_json::from_value(request.clone())?;
+                estimates.push(PromptEstimate::of(&event.author, &request));
+            }
+        }
+        Ok(estimates)
+    }
+
+    /// Run the agent on a session supplied by the caller, without using the
+    /// runner's session service, and return what the run changed
+    pub async fn run_stateless(
+        &self,
+        user_id: UserId,
+        session_id: SessionId,
+        session: StatelessSession,
+        new_message: Content,
+    ) -> Result<StatelessResult> {
+        let initial_state = session.state.clone();
+        let initial_events = session.events.len();
+        let scratch = Arc::new(InMemorySessionService::new());
+        scratch
+            .create_session(session.into_session(&self.app_name, user_id.clone(), session_id.clone()))
+            .await?;
+
+        let runner = Runner {
+            app_name: self.app_name.clone(),
+            agent: self.agent.clone(),
+            session_service: scratch.clone(),
+            run_config: self.run_config.clone(),
+            cancel: self.cancel.clone(),
+            tasks: self.tasks.clone(),
+            event_bus: self.event_bus.clone(),
+            output_processors: self.output_processors.clone(),
+            webhooks: self.webhooks.clone(),
+            determinism: self.determinism.clone(),
+            experiment: self.experiment.clone(),
+            response_validation: self.response_validation.clone(),
+            redaction_vault: self.redaction_vault.clone(),
+            pricing: self.pricing.clone(),
+        };
+        let mut events = runner.run_async(user_id.clone(), session_id.clone(), new_message).await?;
+        while let Some(event) = events.next().await {
+            event?;
+        }
+
+        let session = scratch
+            .get_session(&self.app_name, &user_id, &session_id)
+            .await?
+            .ok_or_else(|| crate::adk_error!(SessionError, "Session not found: {}", session_id))?;
+        Ok(StatelessResult::from_session(&initial_state, initial_events, &session))
+    }
+
+    /// Cancel in-flight streams and background tasks and wait for them to finish
     pub async fn close(&self) -> Result<()> {
         info!("Closing runner for app: {}", self.app_name);
-        // TODO: Implement cleanup logic
-        Ok(())
+        self.cancel.cancel();
+        self.tasks.close();
+        if tokio::time::timeout(RUNNER_CLOSE_TIMEOUT, self.tasks.wait()).await.is_err() {
+            warn!(
+                "Runner for {} closed with {} streams or tasks still alive",
+                self.app_name,
+                self.tasks.len()
+            );
+        }
+        self.session_service.flush().await
     }
 }
 
@@ -120,6 +622,14 @@ pub struct RunnerBuilder {
     app_name: Option<String>,
     agent: Option<Arc<dyn BaseAgent>>,
     session_service: Option<Arc<dyn SessionService>>,
+    run_config: RunConfig,
+    event_bus: Option<EventBus>,
+    output_processors: Vec<Arc<dyn OutputProcessor>>,
+    webhooks: Option<WebhookDispatcher>,
+    determinism: Option<Determinism>,
+    experiment: Option<Experiment>,
+    response_validation: Option<ResponseValidation>,
+    redaction_vault: Option<Arc<dyn RedactionVault>>,
 }
 
 impl RunnerBuilder {
@@ -128,6 +638,14 @@ impl RunnerBuilder {
             app_name: None,
             agent: None,
             session_service: None,
+            run_config: RunConfig::default(),
+            event_bus: None,
+            output_processors: Vec::new(),
+            webhooks: None,
+            determinism: None,
+            experiment: None,
+            response_validation: None,
+            redaction_vault: None,
         }
     }
 
@@ -146,7 +664,45 @@ impl RunnerBuilder {
         self
     }
 
+    pub fn run_config(mut self, run_config: RunConfig) -> Self {
+        self.run_config = run_config;
+        self
+    }
 
+    pub fn event_bus(mut self, bus: EventBus) -> Self {
+        self.event_bus = Some(bus);
+        self
+    }
+
+    pub fn output_processor(mut self, processor: Arc<dyn OutputProcessor>) -> Self {
+        self.output_processors.push(processor);
+        self
+    }
+
+    pub fn webhooks(mut self, webhooks: WebhookDispatcher) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    pub fn determinism(mut self, determinism: Determinism) -> Self {
+        self.determinism = Some(determinism);
+        self
+    }
+
+    pub fn experiment(mut self, experiment: Experiment) -> Self {
+        self.experiment = Some(experiment);
+        self
+    }
+
+    pub fn response_validation(mut self, validation: ResponseValidation) -> Self {
+        self.response_validation = Some(validation);
+        self
+    }
+
+    pub fn redaction_vault(mut self, vault: Arc<dyn RedactionVault>) -> Self {
+        self.redaction_vault = Some(vault);
+        self
+    }
 
     pub fn build(self) -> Result<Runner> {
         let app_name = self.app_name.ok_or_else(|| {
@@ -159,7 +715,20 @@ impl RunnerBuilder {
             crate::adk_error!(ValidationError, "session_service is required")
         })?;
 
-        Ok(Runner::new(app_name, agent, session_service))
+        let mut runner = Runner::new(app_name, agent, session_service).with_run_config(self.run_config);
+        runner.output_processors = self.output_processors;
+        runner.webhooks = self.webhooks;
+        runner.determinism = self.determinism;
+        runner.response_validation = self.response_validation;
+        runner.redaction_vault = self.redaction_vault;
+        if let Some(experiment) = self.experiment {
+            experiment.validate()?;
+            runner.experiment = Some(Arc::new(experiment));
+        }
+        Ok(match self.event_bus {
+            Some(bus) => runner.with_event_bus(bus),
+            None => runner,
+        })
     }
 }
 
@@ -181,4 +750,184 @@ mod tests {
         // Should fail without required fields
         assert!(builder.build().is_err());
     }
+
+    #[tokio::test]
+    async fn test_run_creates_the_session_and_persists_complete_events() {
+        use crate::{
+            sessions::InMemorySessionService,
+            types::{AgentId, Metadata},
+        };
+        use async_trait::async_trait;
+
+        /// Streams a partial chunk followed by the complete answer
+        struct EchoAgent {
+            id: AgentId,
+            metadata: Metadata,
+        }
+
+        #[async_trait]
+        impl BaseAgent for EchoAgent {
+            fn id(&self) -> &AgentId {
+                &self.id
+            }
+            fn name(&self) -> &str {
+                "echo"
+            }
+            fn description(&self) -> &str {
+                ""
+            }
+            fn metadata(&self) -> &Metadata {
+                &self.metadata
+            }
+            fn parent(&self) -> Option<&dyn BaseAgent> {
+                None
+            }
+            fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+                &[]
+            }
+            async fn run_async(&self, _ctx: InvocationContext) -> Result<RunnerEventStream> {
+                let mut partial = Event::text_response("echo", "Hel");
+                partial.is_partial = true;
+                let complete = Event::text_response("echo", "Hello");
+                Ok(Box::pin(futures::stream::iter(vec![Ok(Arc::new(partial)), Ok(Arc::new(complete))])))
+            }
+            async fn run_live(&self, ctx: InvocationContext) -> Result<RunnerEventStream> {
+                self.run_async(ctx).await
+            }
+        }
+
+        let sessions = Arc::new(InMemorySessionService::new());
+        let agent = EchoAgent { id: "echo".to_string(), metadata: Metadata::new() };
+        let runner = Runner::new("app", Arc::new(agent), sessions.clone());
+        let events: Vec<_> = runner
+            .run_async("user".to_string(), "s1".to_string(), Content::user_text("Hi"))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(events.len(), 2);
+
+        let session = sessions
+            .get_session("app", &"user".to_string(), &"s1".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        let texts: Vec<_> = session.events.iter().map(|event| event.get_text()).collect();
+        assert_eq!(texts, vec![Some("Hi".to_string()), Some("Hello".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_close_cancels_streams_and_tasks() {
+        use crate::{
+            agents::{base_agent::AgentBuilder, LlmAgent},
+            sessions::InMemorySessionService,
+        };
+
+        let agent = LlmAgent::builder().name("helper").model("gemini-2.0-flash").build().unwrap();
+        let runner = Runner::new("app", Arc::new(agent), Arc::new(InMemorySessionService::new()));
+
+        let mut events = runner.supervise(Box::pin(futures::stream::pending()));
+        let task = runner.spawn(futures::future::pending::<()>());
+        let consumer = tokio::spawn(async move { events.next().await.is_none() });
+
+        tokio::time::timeout(Duration::from_secs(5), runner.close()).await.unwrap().unwrap();
+        assert!(consumer.await.unwrap());
+        assert_eq!(task.await.unwrap(), None);
+        assert!(runner.tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handoff_stops_running_the_agent() {
+        use crate::{
+            agents::base_agent::{events_to_stream, EventStream},
+            events::Handoff,
+            sessions::{InMemorySessionService, HUMAN_HANDOFF_TAG},
+            types::{AgentId, Metadata},
+        };
+        use async_trait::async_trait;
+
+        /// Hands every conversation over
+        struct HandoffAgent {
+            id: AgentId,
+            metadata: Metadata,
+        }
+
+        #[async_trait]
+        impl BaseAgent for HandoffAgent {
+            fn id(&self) -> &AgentId {
+                &self.id
+            }
+            fn name(&self) -> &str {
+                "support"
+            }
+            fn description(&self) -> &str {
+                ""
+            }
+            fn metadata(&self) -> &Metadata {
+                &self.metadata
+            }
+            fn parent(&self) -> Option<&dyn BaseAgent> {
+                None
+            }
+            fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+                &[]
+            }
+            async fn run_async(&self, _ctx: InvocationContext) -> Result<EventStream> {
+                let mut event = Event::text_response("support", "Connecting you with a colleague.");
+                event.actions.handoff = Some(Handoff::new("customer asked for a human"));
+                Ok(events_to_stream(vec![event]))
+            }
+            async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+                self.run_async(ctx).await
+            }
+        }
+
+        let sessions = Arc::new(InMemorySessionService::new());
+        let agent = HandoffAgent { id: "support".into(), metadata: Metadata::new() };
+        let runner = Runner::new("shop", Arc::new(agent), sessions.clone());
+        let run = |message: &'static str| {
+            let runner = &runner;
+            async move {
+                let events = runner.run_async("u1".into(), "s1".into(), Content::user_text(message)).await.unwrap();
+                events.collect::<Vec<_>>().await.len()
+            }
+        };
+
+        assert_eq!(run("I want a human").await, 1);
+        assert_eq!(run("hello?").await, 0);
+        let session = sessions.get_session("shop", &"u1".to_string(), &"s1".to_string()).await.unwrap().unwrap();
+        assert!(session.has_tag(HUMAN_HANDOFF_TAG));
+        assert_eq!(session.events.last().unwrap().get_text().unwrap(), "hello?");
+    }
+
+    #[tokio::test]
+    async fn test_determinism_reproduces_transcripts() {
+        use crate::{
+            agents::{base_agent::AgentBuilder, LlmAgent},
+            sessions::InMemorySessionService,
+            testing::MockGeminiServer,
+        };
+        use chrono::TimeZone;
+
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-determinism").await;
+        mock.set_fallback(crate::testing::MockReply::text("Same answer."));
+        let start = chrono::Utc.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap();
+
+        let transcript = || async {
+            let agent = LlmAgent::builder().name("helper").model("mock-gemini-determinism").build().unwrap();
+            let sessions = Arc::new(InMemorySessionService::new());
+            let runner = Runner::new("app", Arc::new(agent), sessions.clone()).with_determinism(Determinism::fixed(start));
+            let events = runner.run_async("u1".into(), "s1".into(), Content::user_text("Hi")).await.unwrap();
+            assert_eq!(events.collect::<Vec<_>>().await.len(), 1);
+            let session = sessions.get_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap().unwrap();
+            serde_json::to_value(&session.events).unwrap()
+        };
+
+        let first = transcript().await;
+        assert_eq!(first, transcript().await);
+        assert!(first[0]["timestamp"].as_str().unwrap().starts_with("2024-05-01T09:00:00"));
+        assert!(first[1]["id"].as_str().unwrap().starts_with("00000000-0000-0000-0000-"));
+        assert_eq!(first[0]["invocation_id"], first[1]["invocation_id"]);
+    }
 }
@@ -0,0 +1,427 @@
+//! Scripted tools, guardrails and routing conditions
+//!
+//! Scripts are small snippets evaluated by a [`ScriptEngine`] registered under
+//! a language name. [Rhai](https://rhai.rs) is built in as `rhai`, the
+//! default language, and runs with operation, depth and size limits; other
+//! languages can be registered. Scripts receive a JSON scope and return JSON,
+//! so they can be declared in agent YAML next to the rest of the configuration:
+//!
+//! ```yaml
+//! tools:
+//!   - name: discount
+//!     description: Compute a discount
+//!     parameters: { type: object, properties: { total: { type: number } } }
+//!     script: "if args.total > 100 { args.total * 0.1 } else { 0 }"
+//! guardrails:
+//!   - name: no_secrets
+//!     script: "!input.contains(\"password\")"
+//! conditions:
+//!   - name: is_billing
+//!     file: scripts/is_billing.rhai
+//! ```
+
+use crate::{error::Result, tools::BaseTool, types::FunctionDeclaration};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+/// Language used when a script does not name one
+pub const DEFAULT_SCRIPT_LANGUAGE: &str = "rhai";
+
+/// Interpreter for an embedded scripting language
+pub trait ScriptEngine: Send + Sync {
+    /// Evaluate `source` with the given variables in scope
+    fn eval(&self, source: &str, scope: &HashMap<String, Value>) -> Result<Value>;
+}
+
+/// Sandboxed [Rhai](https://rhai.rs) interpreter
+///
+/// Scope variables are converted to Rhai values (objects become maps) and
+/// the script's result back to JSON. Scripts cannot touch the file system
+/// and are stopped once they exceed the operation or size limits.
+pub struct RhaiEngine {
+    engine: rhai::Engine,
+}
+
+impl RhaiEngine {
+    /// Operations a single evaluation may run before it is aborted
+    pub const MAX_OPERATIONS: u64 = 1_000_000;
+
+    pub fn new() -> Self {
+        let mut engine = rhai::Engine::new();
+        engine
+            .set_max_operations(Self::MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(1024 * 1024)
+            .set_max_array_size(10_000)
+            .set_max_map_size(10_000);
+        Self { engine }
+    }
+}
+
+impl Default for RhaiEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptEngine for RhaiEngine {
+    fn eval(&self, source: &str, scope: &HashMap<String, Value>) -> Result<Value> {
+        let mut rhai_scope = rhai::Scope::new();
+        for (name, value) in scope {
+            let value = rhai::serde::to_dynamic(value)
+                .map_err(|e| crate::adk_error!(ValidationError, "Cannot pass `{}` to a script: {}", name, e))?;
+            rhai_scope.push_dynamic(name.as_str(), value);
+        }
+        let result: rhai::Dynamic = self
+            .engine
+            .eval_with_scope(&mut rhai_scope, source)
+            .map_err(|e| crate::adk_error!(ValidationError, "Script failed: {}", e))?;
+        rhai::serde::from_dynamic(&result)
+            .map_err(|e| crate::adk_error!(ValidationError, "Script returned a value without a JSON form: {}", e))
+    }
+}
+
+/// A script declared in configuration, inline or by file
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScriptSpec {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    /// JSON schema of tool arguments (tools only)
+    #[serde(default)]
+    pub parameters: Option<Value>,
+
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// Inline source
+    #[serde(default)]
+    pub script: Option<String>,
+
+    /// Source file, relative to the configuration file
+    #[serde(default)]
+    pub file: Option<String>,
+}
+
+impl ScriptSpec {
+    /// Resolve the script source, reading `file` relative to `base_dir`
+    pub fn source(&self, base_dir: &Path) -> Result<String> {
+        match (&self.script, &self.file) {
+            (Some(script), None) => Ok(script.clone()),
+            (None, Some(file)) => Ok(std::fs::read_to_string(base_dir.join(file))?),
+            _ => Err(crate::adk_error!(
+                ConfigError,
+                "Script '{}' must set exactly one of `script` or `file`",
+                self.name
+            )),
+        }
+    }
+}
+
+/// A script bound to the engine that evaluates it
+#[derive(Clone)]
+pub struct Script {
+    name: String,
+    source: String,
+    engine: Arc<dyn ScriptEngine>,
+}
+
+impl Script {
+    pub fn new(name: impl Into<String>, source: impl Into<String>, engine: Arc<dyn ScriptEngine>) -> Self {
+        Self {
+            name: name.into(),
+            source: source.into(),
+            engine,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn eval(&self, scope: &HashMap<String, Value>) -> Result<Value> {
+        self.engine.eval(&self.source, scope)
+    }
+
+    /// Evaluate a script that must return a boolean
+    fn eval_bool(&self, scope: &HashMap<String, Value>) -> Result<bool> {
+        match self.eval(scope)? {
+            Value::Bool(result) => Ok(result),
+            other => Err(crate::adk_error!(
+                ValidationError,
+                "Script '{}' returned {} instead of a boolean",
+                self.name,
+                other
+            )),
+        }
+    }
+}
+
+impl std::fmt::Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Script").field("name", &self.name).finish()
+    }
+}
+
+/// Tool whose body is a script; arguments are in scope as `args`
+#[derive(Debug, Clone)]
+pub struct ScriptedTool {
+    script: Script,
+    description: String,
+    parameters: Value,
+}
+
+#[async_trait]
+impl BaseTool for ScriptedTool {
+    fn name(&self) -> &str {
+        self.script.name()
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn get_declaration(&self) -> Option<FunctionDeclaration> {
+        Some(FunctionDeclaration {
+            name: self.script.name().to_string(),
+            description: self.description.clone(),
+            parameters: self.parameters.clone(),
+        })
+    }
+
+    async fn run_async(&self, args: HashMap<String, Value>) -> Result<Value> {
+        let scope = HashMap::from([("args".to_string(), serde_json::to_value(args)?)]);
+        self.script.eval(&scope)
+    }
+}
+
+/// Check on a text (user input or model output) in scope as `input`; the script returns `true` to allow it
+#[derive(Debug, Clone)]
+pub struct ScriptGuardrail {
+    script: Script,
+}
+
+impl ScriptGuardrail {
+    pub fn name(&self) -> &str {
+        self.script.name()
+    }
+
+    /// Fail with a validation error if the script rejects the input
+    pub fn check(&self, input: &str) -> Result<()> {
+        let scope = HashMap::from([("input".to_string(), Value::String(input.to_string()))]);
+        if self.script.eval_bool(&scope)? {
+            Ok(())
+        } else {
+            Err(crate::adk_error!(
+                ValidationError,
+                "Guardrail '{}' rejected the input",
+                self.script.name()
+            ))
+        }
+    }
+}
+
+/// Routing predicate over a message (`message`) and session state (`state`)
+#[derive(Debug, Clone)]
+pub struct ScriptCondition {
+    script: Script,
+}
+
+impl ScriptCondition {
+    pub fn name(&self) -> &str {
+        self.script.name()
+    }
+
+    pub fn matches(&self, message: &str, state: &HashMap<String, Value>) -> Result<bool> {
+        let scope = HashMap::from([
+            ("message".to_string(), Value::String(message.to_string())),
+            ("state".to_string(), serde_json::to_value(state)?),
+        ]);
+        self.script.eval_bool(&scope)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ScriptsConfig {
+    #[serde(default)]
+    tools: Vec<ScriptSpec>,
+    #[serde(default)]
+    guardrails: Vec<ScriptSpec>,
+    #[serde(default)]
+    conditions: Vec<ScriptSpec>,
+}
+
+/// Scripted tools, guardrails and conditions loaded from agent YAML
+#[derive(Default)]
+pub struct ScriptLibrary {
+    pub tools: Vec<Arc<dyn BaseTool>>,
+    pub guardrails: Vec<ScriptGuardrail>,
+    pub conditions: HashMap<String, ScriptCondition>,
+}
+
+/// Script engines keyed by language name
+#[derive(Clone)]
+pub struct ScriptEngines {
+    engines: HashMap<String, Arc<dyn ScriptEngine>>,
+}
+
+impl Default for ScriptEngines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptEngines {
+    /// Engines with the built-in [`RhaiEngine`] registered as `rhai`
+    pub fn new() -> Self {
+        Self::empty().register(DEFAULT_SCRIPT_LANGUAGE, Arc::new(RhaiEngine::new()))
+    }
+
+    /// No engines; register each language used
+    pub fn empty() -> Self {
+        Self { engines: HashMap::new() }
+    }
+
+    pub fn register(mut self, language: impl Into<String>, engine: Arc<dyn ScriptEngine>) -> Self {
+        self.engines.insert(language.into(), engine);
+        self
+    }
+
+    fn compile(&self, spec: &ScriptSpec, base_dir: &Path) -> Result<Script> {
+        let language = spec.language.as_deref().unwrap_or(DEFAULT_SCRIPT_LANGUAGE);
+        let engine = self.engines.get(language).cloned().ok_or_else(|| {
+            crate::adk_error!(
+                ConfigError,
+                "No script engine registered for language '{}' (script '{}')",
+                language,
+                spec.name
+            )
+        })?;
+        Ok(Script::new(spec.name.clone(), spec.source(base_dir)?, engine))
+    }
+
+    /// Load the `tools`, `guardrails` and `conditions` sections of an agent YAML document
+    pub fn load_yaml(&self, yaml: &str, base_dir: &Path) -> Result<ScriptLibrary> {
+        let config: ScriptsConfig = serde_yaml::from_str(yaml)
+            .map_err(|e| crate::adk_error!(ConfigError, "Invalid scripts config: {}", e))?;
+
+        let mut library = ScriptLibrary::default();
+        for spec in &config.tools {
+            library.tools.push(Arc::new(ScriptedTool {
+                script: self.compile(spec, base_dir)?,
+                description: spec.description.clone(),
+                parameters: spec
+                    .parameters
+                    .clone()
+                    .unwrap_or_else(|| serde_json::json!({"type": "object", "properties": {}})),
+            }));
+        }
+        for spec in &config.guardrails {
+            library.guardrails.push(ScriptGuardrail {
+                script: self.compile(spec, base_dir)?,
+            });
+        }
+        for spec in &config.conditions {
+            library.conditions.insert(
+                spec.name.clone(),
+                ScriptCondition {
+                    script: self.compile(spec, base_dir)?,
+                },
+            );
+        }
+        Ok(library)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Understands `contains:<var>:<text>` and `get:<var>.<field>`
+    struct TinyEngine;
+
+    impl ScriptEngine for TinyEngine {
+        fn eval(&self, source: &str, scope: &HashMap<String, Value>) -> Result<Value> {
+            let parts: Vec<&str> = source.splitn(3, ':').collect();
+            match parts.as_slice() {
+                ["contains", var, text] => Ok(Value::Bool(
+                    scope[*var].as_str().unwrap_or_default().contains(text),
+                )),
+                ["get", path] => {
+                    let (var, field) = path.split_once('.').unwrap();
+                    Ok(scope[var][field].clone())
+                }
+                _ => Err(crate::adk_error!(ValidationError, "bad script")),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_scripts_from_yaml() {
+        let yaml = r#"
+tools:
+  - name: echo_city
+    description: Echo the city
+    script: "get:args.city"
+guardrails:
+  - name: no_secrets
+    script: "contains:input:please"
+conditions:
+  - name: is_billing
+    language: tiny
+    script: "contains:message:invoice"
+"#;
+        let engine: Arc<dyn ScriptEngine> = Arc::new(TinyEngine);
+        let engines = ScriptEngines::new()
+            .register("rhai", engine.clone())
+            .register("tiny", engine);
+        let library = engines.load_yaml(yaml, Path::new(".")).unwrap();
+
+        let args = HashMap::from([("city".to_string(), Value::from("Oslo"))]);
+        assert_eq!(library.tools[0].run_async(args).await.unwrap(), "Oslo");
+        assert!(library.guardrails[0].check("please help").is_ok());
+        assert!(library.guardrails[0].check("help").is_err());
+        assert!(library.conditions["is_billing"]
+            .matches("where is my invoice", &HashMap::new())
+            .unwrap());
+
+        let missing = ScriptEngines::empty().load_yaml(yaml, Path::new("."));
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rhai_is_the_default_language() {
+        let yaml = r#"
+tools:
+  - name: discount
+    description: Compute a discount
+    script: "if args.total > 100 { args.total * 0.1 } else { 0 }"
+guardrails:
+  - name: no_secrets
+    script: "!input.contains(\"password\")"
+conditions:
+  - name: is_billing
+    script: "message.contains(\"invoice\") || state.plan == \"pro\""
+"#;
+        let library = ScriptEngines::new().load_yaml(yaml, Path::new(".")).unwrap();
+
+        let args = HashMap::from([("total".to_string(), Value::from(250))]);
+        assert_eq!(library.tools[0].run_async(args).await.unwrap(), 25.0);
+        let args = HashMap::from([("total".to_string(), Value::from(50))]);
+        assert_eq!(library.tools[0].run_async(args).await.unwrap(), 0);
+        assert!(library.guardrails[0].check("hello").is_ok());
+        assert!(library.guardrails[0].check("my password is hunter2").is_err());
+        let state = HashMap::from([("plan".to_string(), Value::from("pro"))]);
+        assert!(library.conditions["is_billing"].matches("hello", &state).unwrap());
+        assert!(!library.conditions["is_billing"].matches("hello", &HashMap::new()).unwrap());
+
+        // Runaway scripts are stopped
+        let looping = RhaiEngine::new().eval("loop {}", &HashMap::new()).unwrap_err();
+        assert!(looping.to_string().contains("Script failed"), "{}", looping);
+    }
+}
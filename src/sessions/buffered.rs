@@ -0,0 +1,464 @@
+//! Write-behind buffering and read-through caching for session services
+//!
+//! Wraps a (typically database-backed) session service so that event appends
+//! and state updates are collected per session and written in batches, either
+//! periodically, when a session's batch fills up, or on `flush`/`close`.
+//! Reads are served from a bounded cache that always reflects buffered writes.
+
+use crate::{
+    error::Result,
+    events::Event,
+    types::{Metadata, SessionId, SessionState, UserId},
+};
+use async_trait::async_trait;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::Mutex, task::JoinHandle};
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::{debug, warn};
+
+use super::{
+    session::Session,
+    session_service::{SessionFilter, SessionService},
+};
+
+/// Buffering and caching limits
+#[derive(Debug, Clone)]
+pub struct BufferedSessionConfig {
+    /// How often buffered writes are flushed in the background
+    pub flush_interval: Duration,
+
+    /// Buffered events per session that trigger an immediate flush of that session
+    pub max_batch_events: usize,
+
+    /// Maximum number of sessions kept in the read cache
+    pub cache_capacity: usize,
+}
+
+impl Default for BufferedSessionConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval: Duration::from_millis(500),
+            max_batch_events: 64,
+            cache_capacity: 1024,
+        }
+    }
+}
+
+/// Writes buffered for one session
+#[derive(Debug, Default)]
+struct PendingWrites {
+    events: Vec<Arc<Event>>,
+    state: Option<SessionState>,
+}
+
+impl PendingWrites {
+    /// Apply the buffered writes to a session loaded from the backend
+    fn apply_to(&self, session: &mut Session) {
+        self.events.iter().cloned().for_each(|event| session.add_event(event));
+        if let Some(state) = &self.state {
+            session.state = state.clone();
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CachedSession {
+    session: Session,
+    last_access: Instant,
+}
+
+struct Shared {
+    inner: Arc<dyn SessionService>,
+    config: BufferedSessionConfig,
+    pending: Mutex<HashMap<SessionId, PendingWrites>>,
+    cache: Mutex<HashMap<SessionId, CachedSession>>,
+    /// Serializes flushes so batches of one session reach the backend in order
+    flush_lock: Mutex<()>,
+}
+
+impl Shared {
+    async fn flush_session(&self, session_id: &SessionId) -> Result<()> {
+        let _flushing = self.flush_lock.lock().await;
+        let Some(writes) = self.pending.lock().await.remove(session_id) else {
+            return Ok(());
+        };
+        self.write(session_id, writes).await
+    }
+
+    async fn flush_all(&self) -> Result<()> {
+        let _flushing = self.flush_lock.lock().await;
+        let batches: Vec<_> = self.pending.lock().await.drain().collect();
+        let mut result = Ok(());
+        for (session_id, writes) in batches {
+            if let Err(e) = self.write(&session_id, writes).await {
+                warn!("Failed to flush session {}: {}", session_id, e);
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// Write one batch; on failure the unwritten part is put back in front of newer writes
+    async fn write(&self, session_id: &SessionId, mut writes: PendingWrites) -> Result<()> {
+        debug!("Flushing {} events for session {}", writes.events.len(), session_id);
+        if !writes.events.is_empty() {
+            let events = std::mem::take(&mut writes.events);
+            if let Err(e) = self.inner.append_events(session_id, events.clone()).await {
+                writes.events = events;
+                self.requeue(session_id, writes).await;
+                return Err(e);
+            }
+        }
+        if let Some(state) = writes.state.take() {
+            if let Err(e) = self.inner.update_session_state(session_id, &state).await {
+                writes.state = Some(state);
+                self.requeue(session_id, writes).await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn requeue(&self, session_id: &SessionId, mut writes: PendingWrites) {
+        let mut pending = self.pending.lock().await;
+        if let Some(newer) = pending.remove(session_id) {
+            writes.events.extend(newer.events);
+            writes.state = newer.state.or(writes.state);
+        }
+        pending.insert(session_id.clone(), writes);
+    }
+
+    async fn cache_insert(&self, session: Session) {
+        let mut cache = self.cache.lock().await;
+        if cache.len() >= self.config.cache_capacity && !cache.contains_key(&session.id) {
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_access)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                cache.remove(&oldest);
+            }
+        }
+        if self.config.cache_capacity > 0 {
+            cache.insert(
+                session.id.clone(),
+                CachedSession {
+                    session,
+                    last_access: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Whether the session is cached or has buffered writes, so it is known to exist
+    async fn is_tracked(&self, session_id: &SessionId) -> bool {
+        self.cache.lock().await.contains_key(session_id) || self.pending.lock().await.contains_key(session_id)
+    }
+
+    async fn cache_update(&self, session_id: &SessionId, update: impl FnOnce(&mut Session)) {
+        if let Some(cached) = self.cache.lock().await.get_mut(session_id) {
+            update(&mut cached.session);
+        }
+    }
+}
+
+/// Session service that batches writes to, and caches reads from, another service
+pub struct BufferedSessionService {
+    shared: Arc<Shared>,
+    flusher: Mutex<Option<JoinHandle<()>>>,
+    stop: CancellationToken,
+    _stop_on_drop: DropGuard,
+}
+
+impl BufferedSessionService {
+    /// Wrap `inner`, starting the periodic flush on the current Tokio runtime
+    pub fn new(inner: Arc<dyn SessionService>, config: BufferedSessionConfig) -> Self {
+        let shared = Arc::new(Shared {
+            inner,
+            config,
+            pending: Mutex::new(HashMap::new()),
+            cache: Mutex::new(HashMap::new()),
+            flush_lock: Mutex::new(()),
+        });
+        let stop = CancellationToken::new();
+
+        let flusher = tokio::spawn({
+            let shared = shared.clone();
+            let stop = stop.clone();
+            async move {
+                let mut ticks = tokio::time::interval(shared.config.flush_interval);
+                ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                loop {
+                    tokio::select! {
+                        _ = stop.cancelled() => {
+                            // Also reached when the service is dropped without `close`
+                            if let Err(e) = shared.flush_all().await {
+                                warn!("Dropped buffered session writes: {}", e);
+                            }
+                            break;
+                        }
+                        _ = ticks.tick() => {
+                            // Failed batches stay buffered and are retried on the next tick
+                            let _ = shared.flush_all().await;
+                        }
+                    }
+                }
+            }
+        });
+
+        Self {
+            shared,
+            flusher: Mutex::new(Some(flusher)),
+            _stop_on_drop: stop.clone().drop_guard(),
+            stop,
+        }
+    }
+
+    /// Number of events waiting to be written
+    pub async fn pending_events(&self) -> usize {
+        self.shared.pending.lock().await.values().map(|writes| writes.events.len()).sum()
+    }
+
+    /// Stop the periodic flush and write out everything still buffered
+    pub async fn close(&self) -> Result<()> {
+        self.stop.cancel();
+        if let Some(flusher) = self.flusher.lock().await.take() {
+            let _ = flusher.await;
+        }
+        self.shared.flush_all().await
+    }
+}
+
+impl std::fmt::Debug for BufferedSessionService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferedSessionService")
+            .field("config", &self.shared.config)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl SessionService for BufferedSessionService {
+    async fn create_session(&self, session: Session) -> Result<()> {
+        self.shared.inner.create_session(session.clone()).await?;
+        self.shared.cache_insert(session).await;
+        Ok(())
+    }
+
+    async fn get_session(
+        &self,
+        app_name: &str,
+        user_id: &UserId,
+        session_id: &SessionId,
+    ) -> Result<Option<Session>> {
+        if let Some(cached) = self.shared.cache.lock().await.get_mut(session_id) {
+            cached.last_access = Instant::now();
+            return Ok(Some(cached.session.clone()));
+        }
+
+        // Hold off flushes so a batch in flight is either stored or still pending
+        let _flushing = self.shared.flush_lock.lock().await;
+        let Some(mut session) = self.shared.inner.get_session(app_name, user_id, session_id).await? else {
+            return Ok(None);
+        };
+        if let Some(writes) = self.shared.pending.lock().await.get(session_id) {
+            writes.apply_to(&mut session);
+        }
+        self.shared.cache_insert(session.clone()).await;
+        Ok(Some(session))
+    }
+
+    async fn list_sessions(&self, filter: &SessionFilter) -> Result<Vec<Session>> {
+        self.shared.flush_all().await?;
+        self.shared.inner.list_sessions(filter).await
+    }
+
+    async fn update_session_state(&self, session_id: &SessionId, state: &SessionState) -> Result<()> {
+        // Writes to sessions this instance has not seen go straight through, so a missing session fails
+        if !self.shared.is_tracked(session_id).await {
+            return self.shared.inner.update_session_state(session_id, state).await;
+        }
+        self.shared.pending.lock().await.entry(session_id.clone()).or_default().state = Some(state.clone());
+        self.shared
+            .cache_update(session_id, |session| {
+                session.state = state.clone();
+                session.updated_at = crate::types::now();
+            })
+            .await;
+        Ok(())
+    }
+
+    async fn append_event(&self, session_id: &SessionId, event: Arc<Event>) -> Result<()> {
+        self.append_events(session_id, vec![event]).await
+    }
+
+    async fn append_events(&self, session_id: &SessionId, events: Vec<Arc<Event>>) -> Result<()> {
+        if !self.shared.is_tracked(session_id).await {
+            return self.shared.inner.append_events(session_id, events).await;
+        }
+        self.shared
+            .cache_update(session_id, |session| events.iter().cloned().for_each(|event| session.add_event(event)))
+            .await;
+        let batch_full = {
+            let mut pending = self.shared.pending.lock().await;
+            let writes = pending.entry(session_id.clone()).or_default();
+            writes.events.extend(events);
+            writes.events.len() >= self.shared.config.max_batch_events
+        };
+        if batch_full {
+            self.shared.flush_session(session_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn update_event_metadata(
+        &self,
+        session_id: &SessionId,
+        event_id: &str,
+        metadata: Metadata,
+    ) -> Result<()> {
+        self.shared.flush_session(session_id).await?;
+        self.shared
+            .inner
+            .update_event_metadata(session_id, event_id, metadata.clone())
+            .await?;
+        self.shared
+            .cache_update(session_id, |session| {
+                if let Some(event) = session.events.iter_mut().find(|event| event.id == event_id) {
+                    Arc::make_mut(event).metadata.extend(metadata);
+                }
+            })
+            .await;
+        Ok(())
+    }
+
+    async fn set_session_tags(&self, session_id: &SessionId, tags: BTreeSet<String>) -> Result<()> {
+        self.shared.flush_session(session_id).await?;
+        self.shared.inner.set_session_tags(session_id, tags.clone()).await?;
+        self.shared.cache_update(session_id, |session| session.tags = tags).await;
+        Ok(())
+    }
+
+    async fn delete_session(&self, session_id: &SessionId) -> Result<bool> {
+        // Drop buffered writes rather than flushing data that is being deleted
+        let _flushing = self.shared.flush_lock.lock().await;
+        self.shared.pending.lock().await.remove(session_id);
+        self.shared.cache.lock().await.remove(session_id);
+        self.shared.inner.delete_session(session_id).await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.shared.flush_all().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sessions::InMemorySessionService;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    crate::session_service_conformance!(BufferedSessionService::new(
+        Arc::new(InMemorySessionService::new()),
+        BufferedSessionConfig {
+            flush_interval: Duration::from_secs(3600),
+            max_batch_events: 7,
+            cache_capacity: 1,
+        },
+    ));
+
+    /// Counts batched writes reaching the backend
+    struct CountingService {
+        inner: InMemorySessionService,
+        batches: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SessionService for CountingService {
+        async fn create_session(&self, session: Session) -> Result<()> {
+            self.inner.create_session(session).await
+        }
+
+        async fn get_session(&self, app: &str, user: &UserId, id: &SessionId) -> Result<Option<Session>> {
+            self.inner.get_session(app, user, id).await
+        }
+
+        async fn list_sessions(&self, filter: &SessionFilter) -> Result<Vec<Session>> {
+            self.inner.list_sessions(filter).await
+        }
+
+        async fn update_session_state(&self, id: &SessionId, state: &SessionState) -> Result<()> {
+            self.inner.update_session_state(id, state).await
+        }
+
+        async fn append_event(&self, id: &SessionId, event: Arc<Event>) -> Result<()> {
+            self.inner.append_event(id, event).await
+        }
+
+        async fn append_events(&self, id: &SessionId, events: Vec<Arc<Event>>) -> Result<()> {
+            self.batches.fetch_add(1, Ordering::SeqCst);
+            self.inner.append_events(id, events).await
+        }
+
+        async fn update_event_metadata(&self, id: &SessionId, event_id: &str, metadata: Metadata) -> Result<()> {
+            self.inner.update_event_metadata(id, event_id, metadata).await
+        }
+
+        async fn set_session_tags(&self, id: &SessionId, tags: BTreeSet<String>) -> Result<()> {
+            self.inner.set_session_tags(id, tags).await
+        }
+
+        async fn delete_session(&self, id: &SessionId) -> Result<bool> {
+            self.inner.delete_session(id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batches_writes_and_reads_through_buffer() {
+        let backend = Arc::new(CountingService {
+            inner: InMemorySessionService::new(),
+            batches: AtomicUsize::new(0),
+        });
+        let config = BufferedSessionConfig {
+            flush_interval: Duration::from_secs(3600),
+            max_batch_events: 3,
+            cache_capacity: 1,
+        };
+        let service = BufferedSessionService::new(backend.clone(), config);
+        let (app, user) = ("app", "user".to_string());
+        let (s1, s2) = ("s1".to_string(), "s2".to_string());
+        service.get_or_create_session(app, &user, &s1).await.unwrap();
+
+        service.append_event(&s1, Event::text_response("agent", "one").into()).await.unwrap();
+        service.append_event(&s1, Event::text_response("agent", "two").into()).await.unwrap();
+        let mut state = SessionState::new();
+        state.insert("step".to_string(), serde_json::json!(2));
+        service.update_session_state(&s1, &state).await.unwrap();
+        assert_eq!(backend.batches.load(Ordering::SeqCst), 0);
+        let stored = backend.get_session(app, &user, &s1).await.unwrap().unwrap();
+        assert!(stored.events.is_empty());
+
+        // Evict s1 from the cache; reads still see the buffered writes
+        service.get_or_create_session(app, &user, &s2).await.unwrap();
+        let session = service.get_session(app, &user, &s1).await.unwrap().unwrap();
+        assert_eq!(session.events.len(), 2);
+        assert_eq!(session.state["step"], 2);
+
+        // A full batch is written at once
+        service.append_event(&s1, Event::text_response("agent", "three").into()).await.unwrap();
+        assert_eq!(backend.batches.load(Ordering::SeqCst), 1);
+        assert_eq!(service.pending_events().await, 0);
+
+        service.append_event(&s2, Event::text_response("agent", "four").into()).await.unwrap();
+        service.close().await.unwrap();
+        let stored = backend.get_session(app, &user, &s1).await.unwrap().unwrap();
+        assert_eq!(stored.events.len(), 3);
+        assert_eq!(stored.state["step"], 2);
+        let stored = backend.get_session(app, &user, &s2).await.unwrap().unwrap();
+        assert_eq!(stored.events.len(), 1);
+    }
+}
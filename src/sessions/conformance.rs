@@ -0,0 +1,154 @@
+//! Conformance tests for `SessionService` backends
+//!
+//! `session_service_conformance!(expr)` expands to a test module checking the
+//! guarantees documented on [`SessionService`](crate::sessions::SessionService)
+//! against the service `expr` builds. The expression is evaluated once per
+//! test inside a Tokio runtime and may use names imported by the calling
+//! module. The calling crate needs `tokio` and `uuid` as (dev-)dependencies.
+//!
+//! ```ignore
+//! #[cfg(test)]
+//! mod tests {
+//!     use my_backend::SqliteSessionService;
+//!
+//!     google_adk::session_service_conformance!(SqliteSessionService::in_memory().unwrap());
+//! }
+//! ```
+
+/// Generate the `SessionService` conformance tests for a backend
+#[macro_export]
+macro_rules! session_service_conformance {
+    ($service:expr) => {
+        mod session_service_conformance {
+            #[allow(unused_imports)]
+            use super::*;
+            use std::{collections::BTreeSet, sync::Arc};
+            use $crate::{
+                events::Event,
+                sessions::{SessionFilter, SessionService},
+                types::SessionState,
+            };
+
+            const APP: &str = "conformance";
+
+            fn service() -> Arc<dyn SessionService> {
+                Arc::new($service)
+            }
+
+            fn texts(session: &$crate::sessions::Session) -> Vec<String> {
+                session.events.iter().filter_map(|event| event.get_text()).collect()
+            }
+
+            #[tokio::test]
+            async fn writes_are_visible_to_the_next_read() {
+                let service = service();
+                let (user, id) = ("user".to_string(), uuid::Uuid::new_v4().to_string());
+                service.get_or_create_session(APP, &user, &id).await.unwrap();
+
+                let event = Arc::new(Event::text_response("agent", "hello"));
+                service.append_event(&id, event.clone()).await.unwrap();
+                let session = service.get_session(APP, &user, &id).await.unwrap().unwrap();
+                assert_eq!(texts(&session), ["hello"]);
+
+                let mut state = SessionState::new();
+                state.insert("step".to_string(), 2.into());
+                service.update_session_state(&id, &state).await.unwrap();
+                let session = service.get_session(APP, &user, &id).await.unwrap().unwrap();
+                assert_eq!(session.state["step"], 2);
+
+                let mut metadata = $crate::types::Metadata::new();
+                metadata.insert("rating".to_string(), "up".into());
+                service.update_event_metadata(&id, &event.id, metadata).await.unwrap();
+                let session = service.get_session(APP, &user, &id).await.unwrap().unwrap();
+                assert_eq!(session.events[0].metadata["rating"], "up");
+
+                let tags = BTreeSet::from(["conformance".to_string()]);
+                service.set_session_tags(&id, tags).await.unwrap();
+                let session = service.get_session(APP, &user, &id).await.unwrap().unwrap();
+                assert!(session.has_tag("conformance"));
+                let listed = service.list_sessions(&SessionFilter::new().with_tag("conformance")).await.unwrap();
+                assert!(listed.iter().any(|session| session.id == id && texts(session) == ["hello"]));
+            }
+
+            #[tokio::test]
+            async fn events_keep_append_order() {
+                let service = service();
+                let (user, id) = ("user".to_string(), uuid::Uuid::new_v4().to_string());
+                service.get_or_create_session(APP, &user, &id).await.unwrap();
+
+                for index in 0..5 {
+                    let event = Arc::new(Event::text_response("agent", format!("single {}", index)));
+                    service.append_event(&id, event).await.unwrap();
+                }
+                let batch = (0..5).map(|index| Arc::new(Event::text_response("agent", format!("batch {}", index))));
+                service.append_events(&id, batch.collect()).await.unwrap();
+
+                let session = service.get_session(APP, &user, &id).await.unwrap().unwrap();
+                let expected: Vec<String> = (0..5)
+                    .map(|index| format!("single {}", index))
+                    .chain((0..5).map(|index| format!("batch {}", index)))
+                    .collect();
+                assert_eq!(texts(&session), expected);
+            }
+
+            #[tokio::test]
+            async fn concurrent_appends_are_serialized() {
+                let service = service();
+                let (user, id) = ("user".to_string(), uuid::Uuid::new_v4().to_string());
+                service.get_or_create_session(APP, &user, &id).await.unwrap();
+
+                let writers: Vec<_> = (0..8)
+                    .map(|writer| {
+                        let service = service.clone();
+                        let id = id.clone();
+                        tokio::spawn(async move {
+                            for index in 0..10 {
+                                let event = Event::text_response("agent", format!("{}:{}", writer, index));
+                                service.append_event(&id, Arc::new(event)).await.unwrap();
+                            }
+                        })
+                    })
+                    .collect();
+                for writer in writers {
+                    writer.await.unwrap();
+                }
+
+                let session = service.get_session(APP, &user, &id).await.unwrap().unwrap();
+                let texts = texts(&session);
+                assert_eq!(texts.len(), 80);
+                for writer in 0..8 {
+                    let own: Vec<&String> = texts.iter().filter(|text| text.starts_with(&format!("{}:", writer))).collect();
+                    let expected: Vec<String> = (0..10).map(|index| format!("{}:{}", writer, index)).collect();
+                    assert_eq!(own, expected.iter().collect::<Vec<_>>());
+                }
+            }
+
+            #[tokio::test]
+            async fn writes_to_missing_sessions_fail() {
+                let service = service();
+                let (user, id) = ("user".to_string(), uuid::Uuid::new_v4().to_string());
+                assert!(service.get_session(APP, &user, &id).await.unwrap().is_none());
+
+                let event = Arc::new(Event::text_response("agent", "lost"));
+                assert!(service.append_event(&id, event).await.is_err());
+                assert!(service.update_session_state(&id, &SessionState::new()).await.is_err());
+                assert!(service.set_session_tags(&id, BTreeSet::new()).await.is_err());
+                assert!(service.get_session(APP, &user, &id).await.unwrap().is_none());
+            }
+
+            #[tokio::test]
+            async fn deleted_sessions_are_gone() {
+                let service = service();
+                let (user, id) = ("user".to_string(), uuid::Uuid::new_v4().to_string());
+                service.get_or_create_session(APP, &user, &id).await.unwrap();
+                service.append_event(&id, Arc::new(Event::text_response("agent", "secret"))).await.unwrap();
+
+                assert!(service.delete_session(&id).await.unwrap());
+                assert!(service.get_session(APP, &user, &id).await.unwrap().is_none());
+                let listed = service.list_sessions(&SessionFilter::new().with_user_id(user.clone())).await.unwrap();
+                assert!(listed.iter().all(|session| session.id != id));
+                assert!(!service.delete_session(&id).await.unwrap());
+            }
+        }
+    };
+}
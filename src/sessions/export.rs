@@ -0,0 +1,311 @@
+//! Export of stored sessions to fine-tuning datasets
+
+use crate::{
+    error::Result,
+    sessions::Session,
+    types::{Content, ContentPart, Timestamp},
+};
+use serde_json::{json, Value};
+use std::{fmt, io::Write, path::Path, str::FromStr};
+
+/// Fine-tuning dataset format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Gemini supervised tuning JSONL (`contents` with user/model turns)
+    GeminiTuning,
+    /// OpenAI chat fine-tuning JSONL (`messages` with tool calls)
+    OpenAiChat,
+}
+
+impl FromStr for ExportFormat {
+    type Err = crate::error::AdkError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "gemini-tuning" => Ok(Self::GeminiTuning),
+            "openai-chat" => Ok(Self::OpenAiChat),
+            other => Err(crate::adk_error!(
+                ValidationError,
+                "Unknown export format '{}' (expected gemini-tuning or openai-chat)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GeminiTuning => write!(f, "gemini-tuning"),
+            Self::OpenAiChat => write!(f, "openai-chat"),
+        }
+    }
+}
+
+/// Criteria selecting which sessions to export
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// Only sessions of this application
+    pub app_name: Option<String>,
+
+    /// Only sessions with events authored by this agent
+    pub agent: Option<String>,
+
+    /// Only sessions created at or after this time
+    pub since: Option<Timestamp>,
+
+    /// Only sessions created before this time
+    pub until: Option<Timestamp>,
+
+    /// Only sessions whose state has all of these keys set to `true`
+    pub quality_flags: Vec<String>,
+}
+
+impl ExportFilter {
+    /// Check whether a session matches this filter
+    pub fn matches(&self, session: &Session) -> bool {
+        if self.app_name.as_ref().is_some_and(|app| &session.app_name != app) {
+            return false;
+        }
+        if let Some(agent) = &self.agent {
+            if !session.events.iter().any(|event| &event.author == agent) {
+                return false;
+            }
+        }
+        if self.since.is_some_and(|since| session.created_at < since) {
+            return false;
+        }
+        if self.until.is_some_and(|until| session.created_at >= until) {
+            return false;
+        }
+        self.quality_flags
+            .iter()
+            .all(|flag| session.state.get(flag) == Some(&Value::Bool(true)))
+    }
+}
+
+/// Convert a session to a single training example, or `None` if it has no model turns
+pub fn export_session(session: &Session, format: ExportFormat) -> Option<Value> {
+    let contents: Vec<&Content> = session
+        .events
+        .iter()
+        .filter(|event| !event.is_partial)
+        .filter_map(|event| event.content.as_ref())
+        .collect();
+
+    match format {
+        ExportFormat::GeminiTuning => to_gemini_tuning(&contents),
+        ExportFormat::OpenAiChat => to_openai_chat(&contents),
+    }
+}
+
+/// Convert all sessions matching a filter to training examples
+pub fn export_sessions(sessions: &[Session], filter: &ExportFilter, format: ExportFormat) -> Vec<Value> {
+    sessions
+        .iter()
+        .filter(|session| filter.matches(session))
+        .filter_map(|session| export_session(session, format))
+        .collect()
+}
+
+/// Write examples as JSON lines, returning the number written
+pub fn write_jsonl(examples: &[Value], mut writer: impl Write) -> Result<usize> {
+    for example in examples {
+        serde_json::to_writer(&mut writer, example)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(examples.len())
+}
+
+/// Read sessions from a file containing either a JSON array or JSON lines
+pub fn read_sessions(path: impl AsRef<Path>) -> Result<Vec<Session>> {
+    let data = std::fs::read_to_string(path)?;
+    if data.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(&data)?);
+    }
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(Into::into))
+        .collect()
+}
+
+fn to_gemini_tuning(contents: &[&Content]) -> Option<Value> {
+    let mut turns: Vec<(String, Vec<Value>)> = Vec::new();
+
+    for content in contents {
+        let role = if content.role == "model" { "model" } else { "user" };
+        let parts: Vec<Value> = content
+            .parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text } if !text.is_empty() => Some(json!({ "text": text })),
+                ContentPart::FunctionCall { name, args } => {
+                    Some(json!({ "functionCall": { "name": name, "args": args } }))
+                }
+                ContentPart::FunctionResponse { name, response } => {
+                    let response = if response.is_object() {
+                        response.clone()
+                    } else {
+                        json!({ "result": response })
+                    };
+                    Some(json!({ "functionResponse": { "name": name, "response": response } }))
+                }
+                ContentPart::ExecutableCode { language, code } => {
+                    Some(json!({ "executableCode": { "language": language, "code": code } }))
+                }
+                ContentPart::CodeExecutionResult { outcome, output } => Some(json!({
+                    "codeExecutionResult": { "outcome": outcome, "output": output }
+                })),
+                _ => None,
+            })
+            .collect();
+
+        if parts.is_empty() {
+            continue;
+        }
+
+        // Gemini requires alternating roles, so merge consecutive turns
+        match turns.last_mut() {
+            Some((last_role, last_parts)) if last_role == role => last_parts.extend(parts),
+            _ => turns.push((role.to_string(), parts)),
+        }
+    }
+
+    // Examples must end with a model turn
+    while turns.last().is_some_and(|(role, _)| role != "model") {
+        turns.pop();
+    }
+    if turns.is_empty() {
+        return None;
+    }
+
+    let contents: Vec<Value> = turns
+        .into_iter()
+        .map(|(role, parts)| json!({ "role": role, "parts": parts }))
+        .collect();
+    Some(json!({ "contents": contents }))
+}
+
+fn to_openai_chat(contents: &[&Content]) -> Option<Value> {
+    let mut messages: Vec<Value> = Vec::new();
+    let mut pending_calls: Vec<(String, String)> = Vec::new();
+    let mut next_call_id = 0;
+
+    for content in contents {
+        let is_model = content.role == "model";
+        let text: String = content
+            .parts
+            .iter()
+            .filter_map(|part| part.as_text())
+            .collect();
+        let mut tool_calls = Vec::new();
+
+        for part in &content.parts {
+            match part {
+                ContentPart::FunctionCall { name, args } => {
+                    let id = format!("call_{}", next_call_id);
+                    next_call_id += 1;
+                    pending_calls.push((name.clone(), id.clone()));
+                    tool_calls.push(json!({
+                        "id": id,
+                        "type": "function",
+                        "function": { "name": name, "arguments": args.to_string() }
+                    }));
+                }
+                ContentPart::FunctionResponse { name, response } => {
+                    let id = match pending_calls.iter().position(|(call, _)| call == name) {
+                        Some(index) => pending_calls.remove(index).1,
+                        None => continue,
+                    };
+                    messages.push(json!({
+                        "role": "tool",
+                        "tool_call_id": id,
+                        "content": response.to_string()
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        if is_model && (!text.is_empty() || !tool_calls.is_empty()) {
+            let mut message = json!({ "role": "assistant" });
+            if !text.is_empty() {
+                message["content"] = json!(text);
+            }
+            if !tool_calls.is_empty() {
+                message["tool_calls"] = json!(tool_calls);
+            }
+            messages.push(message);
+        } else if !is_model && !text.is_empty() {
+            messages.push(json!({ "role": "user", "content": text }));
+        }
+    }
+
+    // Examples must end with an assistant message
+    while messages.last().is_some_and(|m| m["role"] != "assistant") {
+        messages.pop();
+    }
+    if messages.is_empty() {
+        return None;
+    }
+
+    Some(json!({ "messages": messages }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::Event, types::FunctionCall};
+
+    fn sample_session() -> Session {
+        let mut session = Session::new("app".to_string(), "user".to_string(), "s1".to_string());
+        session.add_event(Event::user_input("Weather in Paris?", uuid::Uuid::new_v4()));
+        session.add_event(Event::function_call(
+            "weather_agent",
+            FunctionCall {
+                name: "get_weather".to_string(),
+                args: json!({ "city": "Paris" }),
+            },
+        ));
+        session.add_event(Event::function_response("weather_agent", "get_weather", json!("sunny")));
+        session.add_event(Event::text_response("weather_agent", "It is sunny in Paris."));
+        session
+    }
+
+    #[test]
+    fn test_export_gemini_tuning_with_tool_turns() {
+        let example = export_session(&sample_session(), ExportFormat::GeminiTuning).unwrap();
+        let contents = example["contents"].as_array().unwrap();
+
+        let roles: Vec<&str> = contents.iter().map(|c| c["role"].as_str().unwrap()).collect();
+        assert_eq!(roles, vec!["user", "model", "user", "model"]);
+        assert_eq!(contents[1]["parts"][0]["functionCall"]["name"], "get_weather");
+        assert_eq!(contents[2]["parts"][0]["functionResponse"]["response"]["result"], "sunny");
+    }
+
+    #[test]
+    fn test_export_openai_chat_with_tool_turns() {
+        let example = export_session(&sample_session(), ExportFormat::OpenAiChat).unwrap();
+        let messages = example["messages"].as_array().unwrap();
+
+        let roles: Vec<&str> = messages.iter().map(|m| m["role"].as_str().unwrap()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "tool", "assistant"]);
+        assert_eq!(messages[1]["tool_calls"][0]["id"], messages[2]["tool_call_id"]);
+        assert_eq!(messages[1]["tool_calls"][0]["function"]["arguments"], "{\"city\":\"Paris\"}");
+    }
+
+    #[test]
+    fn test_filter_quality_flags_and_agent() {
+        let mut session = sample_session();
+        let filter = ExportFilter {
+            agent: Some("weather_agent".to_string()),
+            quality_flags: vec!["approved".to_string()],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&session));
+
+        session.state.insert("approved".to_string(), Value::Bool(true));
+        assert!(filter.matches(&session));
+        assert_eq!(export_sessions(&[session], &filter, ExportFormat::GeminiTuning).len(), 1);
+    }
+}
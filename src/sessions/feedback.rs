@@ -0,0 +1,202 @@
+//! User feedback on agent events
+
+use crate::{
+    error::Result,
+    events::Event,
+    sessions::{Session, SessionService},
+    types::{SessionId, Timestamp, UserId},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Event metadata key under which feedback is stored
+pub const FEEDBACK_METADATA_KEY: &str = "feedback";
+
+/// Thumbs up/down rating
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    ThumbsUp,
+    ThumbsDown,
+}
+
+/// Feedback left by a user on a single event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Feedback {
+    pub rating: Rating,
+
+    /// Free-text comment
+    #[serde(default)]
+    pub comment: Option<String>,
+
+    /// User who left the feedback
+    #[serde(default)]
+    pub user_id: Option<UserId>,
+
+    /// When the feedback was recorded
+    pub created_at: Timestamp,
+}
+
+impl Feedback {
+    pub fn new(rating: Rating) -> Self {
+        Self {
+            rating,
+            comment: None,
+            user_id: None,
+            created_at: crate::types::now(),
+        }
+    }
+
+    pub fn with_comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    pub fn with_user_id(mut self, user_id: impl Into<UserId>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    /// Read the feedback stored on an event, if any
+    pub fn from_event(event: &Event) -> Option<Self> {
+        event
+            .metadata
+            .get(FEEDBACK_METADATA_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+}
+
+/// Record feedback on an event, replacing any earlier feedback
+pub async fn record_feedback(
+    service: &dyn SessionService,
+    session_id: &SessionId,
+    event_id: &str,
+    feedback: &Feedback,
+) -> Result<()> {
+    let mut metadata = HashMap::new();
+    metadata.insert(FEEDBACK_METADATA_KEY.to_string(), serde_json::to_value(feedback)?);
+    service.update_event_metadata(session_id, event_id, metadata).await
+}
+
+/// Feedback counts for a group of events
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FeedbackCounts {
+    pub thumbs_up: usize,
+    pub thumbs_down: usize,
+}
+
+impl FeedbackCounts {
+    /// Total number of ratings
+    pub fn total(&self) -> usize {
+        self.thumbs_up + self.thumbs_down
+    }
+
+    /// Fraction of positive ratings, if any were given
+    pub fn approval_rate(&self) -> Option<f64> {
+        match self.total() {
+            0 => None,
+            total => Some(self.thumbs_up as f64 / total as f64),
+        }
+    }
+
+    fn add(&mut self, rating: Rating) {
+        match rating {
+            Rating::ThumbsUp => self.thumbs_up += 1,
+            Rating::ThumbsDown => self.thumbs_down += 1,
+        }
+    }
+}
+
+/// Aggregated feedback across sessions
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FeedbackSummary {
+    /// Counts across all events
+    pub overall: FeedbackCounts,
+
+    /// Counts keyed by the agent that authored the rated event
+    pub by_agent: HashMap<String, FeedbackCounts>,
+
+    /// Comments with their ratings, newest first
+    pub comments: Vec<FeedbackComment>,
+}
+
+/// A rated comment included in a summary
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedbackComment {
+    pub session_id: SessionId,
+    pub event_id: String,
+    pub agent: String,
+    pub rating: Rating,
+    pub comment: String,
+    pub created_at: Timestamp,
+}
+
+/// Aggregate the feedback recorded on the events of the given sessions
+pub fn summarize(sessions: &[Session]) -> FeedbackSummary {
+    let mut summary = FeedbackSummary::default();
+
+    for session in sessions {
+        for event in &session.events {
+            let Some(feedback) = Feedback::from_event(event) else {
+                continue;
+            };
+
+            summary.overall.add(feedback.rating);
+            summary
+                .by_agent
+                .entry(event.author.clone())
+                .or_default()
+                .add(feedback.rating);
+
+            if let Some(comment) = feedback.comment {
+                summary.comments.push(FeedbackComment {
+                    session_id: session.id.clone(),
+                    event_id: event.id.clone(),
+                    agent: event.author.clone(),
+                    rating: feedback.rating,
+                    comment,
+                    created_at: feedback.created_at,
+                });
+            }
+        }
+    }
+
+    summary.comments.sort_by_key(|c| std::cmp::Reverse(c.created_at));
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sessions::InMemorySessionService;
+
+    #[tokio::test]
+    async fn test_record_and_summarize_feedback() {
+        let service = InMemorySessionService::new();
+        let mut session = Session::new("app".to_string(), "user".to_string(), "s1".to_string());
+        let good = Event::text_response("helper", "Paris is the capital of France.");
+        let bad = Event::text_response("helper", "I don't know.");
+        let (good_id, bad_id) = (good.id.clone(), bad.id.clone());
+        session.add_event(good);
+        session.add_event(bad);
+        service.create_session(session).await.unwrap();
+
+        let session_id = "s1".to_string();
+        record_feedback(&service, &session_id, &good_id, &Feedback::new(Rating::ThumbsUp))
+            .await
+            .unwrap();
+        let negative = Feedback::new(Rating::ThumbsDown)
+            .with_comment("Unhelpful")
+            .with_user_id("user");
+        record_feedback(&service, &session_id, &bad_id, &negative).await.unwrap();
+        assert!(record_feedback(&service, &session_id, "missing", &negative).await.is_err());
+
+        let sessions = service.list_sessions(&Default::default()).await.unwrap();
+        let summary = summarize(&sessions);
+        assert_eq!(summary.overall.total(), 2);
+        assert_eq!(summary.overall.approval_rate(), Some(0.5));
+        assert_eq!(summary.by_agent["helper"].thumbs_down, 1);
+        assert_eq!(summary.comments.len(), 1);
+        assert_eq!(summary.comments[0].event_id, bad_id);
+    }
+}
@@ -0,0 +1,127 @@
+//! Handing conversations over to human operators
+//!
+//! An agent or guardrail requests a handoff by emitting an event with a
+//! `handoff` action. The runner then tags the session as human-controlled and
+//! stops running the agent on it: user messages are still recorded, and
+//! operators read the transcript and reply until they return the session to
+//! the agent.
+
+use crate::{
+    error::Result,
+    events::{Event, Handoff},
+    sessions::{Session, SessionFilter, SessionService},
+    types::{SessionId, UserId},
+};
+use std::sync::Arc;
+
+/// Tag of sessions controlled by a human operator
+pub const HUMAN_HANDOFF_TAG: &str = "human_handoff";
+
+/// Author of operator replies
+pub const OPERATOR_AUTHOR: &str = "operator";
+
+/// Metadata key naming the operator who wrote a reply
+pub const OPERATOR_METADATA_KEY: &str = "operator";
+
+/// Whether an operator controls the session
+pub fn is_human_controlled(session: &Session) -> bool {
+    session.has_tag(HUMAN_HANDOFF_TAG)
+}
+
+/// The latest handoff requested in the session
+pub fn latest_handoff(session: &Session) -> Option<(&Event, &Handoff)> {
+    session
+        .events
+        .iter()
+        .rev()
+        .find_map(|event| event.actions.handoff.as_ref().map(|handoff| (event.as_ref(), handoff)))
+}
+
+/// Flag a session as human-controlled; returns false if it already was
+pub async fn begin_handoff(
+    service: &dyn SessionService,
+    app_name: &str,
+    user_id: &UserId,
+    session_id: &SessionId,
+) -> Result<bool> {
+    let session = service
+        .get_session(app_name, user_id, session_id)
+        .await?
+        .ok_or_else(|| crate::adk_error!(ValidationError, "Session '{}' not found", session_id))?;
+    if is_human_controlled(&session) {
+        return Ok(false);
+    }
+    let mut tags = session.tags;
+    tags.insert(HUMAN_HANDOFF_TAG.to_string());
+    service.set_session_tags(session_id, tags).await?;
+    Ok(true)
+}
+
+/// Human-controlled sessions, most recently active first
+pub async fn human_controlled_sessions(service: &dyn SessionService, app_name: Option<String>) -> Result<Vec<Session>> {
+    let mut filter = SessionFilter::new().with_tag(HUMAN_HANDOFF_TAG);
+    filter.app_name = app_name;
+    let mut sessions = service.list_sessions(&filter).await?;
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.updated_at));
+    Ok(sessions)
+}
+
+/// A human-controlled session by id
+pub async fn find_human_controlled_session(service: &dyn SessionService, session_id: &str) -> Result<Option<Session>> {
+    Ok(human_controlled_sessions(service, None)
+        .await?
+        .into_iter()
+        .find(|session| session.id == session_id))
+}
+
+/// Append an operator's reply to the session
+pub async fn operator_reply(
+    service: &dyn SessionService,
+    session: &Session,
+    operator: Option<&str>,
+    text: impl Into<String>,
+) -> Result<Arc<Event>> {
+    let mut event = Event::text_response(OPERATOR_AUTHOR, text);
+    if let Some(operator) = operator {
+        event.metadata.insert(OPERATOR_METADATA_KEY.to_string(), operator.into());
+    }
+    let event = Arc::new(event);
+    service.append_event(&session.id, event.clone()).await?;
+    Ok(event)
+}
+
+/// Give the session back to the agent
+pub async fn return_to_agent(service: &dyn SessionService, session: &Session) -> Result<()> {
+    let mut tags = session.tags.clone();
+    tags.remove(HUMAN_HANDOFF_TAG);
+    service.set_session_tags(&session.id, tags).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sessions::InMemorySessionService;
+
+    #[tokio::test]
+    async fn test_handoff_lifecycle() {
+        let service = InMemorySessionService::new();
+        let (user, id) = ("u1".to_string(), "s1".to_string());
+        service.get_or_create_session("shop", &user, &id).await.unwrap();
+        let mut event = Event::text_response("support", "Let me get a colleague.");
+        event.actions.handoff = Some(Handoff::new("refund over limit"));
+        service.append_event(&id, Arc::new(event)).await.unwrap();
+
+        assert!(begin_handoff(&service, "shop", &user, &id).await.unwrap());
+        assert!(!begin_handoff(&service, "shop", &user, &id).await.unwrap());
+        let session = find_human_controlled_session(&service, "s1").await.unwrap().unwrap();
+        assert_eq!(latest_handoff(&session).unwrap().1.reason, "refund over limit");
+
+        operator_reply(&service, &session, Some("dana"), "Refund approved.").await.unwrap();
+        return_to_agent(&service, &session).await.unwrap();
+        assert!(human_controlled_sessions(&service, None).await.unwrap().is_empty());
+        let session = service.get_session("shop", &user, &id).await.unwrap().unwrap();
+        let reply = session.events.last().unwrap();
+        assert_eq!(reply.author, OPERATOR_AUTHOR);
+        assert_eq!(reply.metadata[OPERATOR_METADATA_KEY], "dana");
+    }
+}
@@ -0,0 +1,521 @@
+//! Import of conversation history from other frameworks
+//!
+//! Reads Python ADK session dumps and eval sets and Dialogflow CX
+//! conversation exports into [`Session`]s, which can then be stored in any
+//! session service or exported as fine-tuning data.
+
+use crate::{
+    error::Result,
+    events::{Event, EventAction},
+    sessions::Session,
+    types::{CodeExecutionOutcome, Content, ContentPart, InvocationId, Timestamp},
+};
+use base64::{engine::general_purpose, Engine};
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
+
+/// Metadata key holding the intent Dialogflow matched for a turn
+pub const IMPORTED_INTENT_METADATA_KEY: &str = "intent";
+
+/// Metadata key holding Dialogflow's confidence in the matched intent
+pub const IMPORTED_INTENT_CONFIDENCE_METADATA_KEY: &str = "intent_confidence";
+
+/// Source format of imported conversations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Python ADK `Session` dumps (`model_dump_json`, snake or camel case)
+    AdkSession,
+    /// Python ADK eval sets; each eval case becomes a session tagged `eval_set:<id>`
+    AdkEvalSet,
+    /// Dialogflow CX conversations from the conversation history API
+    DialogflowCx,
+}
+
+impl FromStr for ImportFormat {
+    type Err = crate::error::AdkError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "adk-session" => Ok(Self::AdkSession),
+            "adk-evalset" => Ok(Self::AdkEvalSet),
+            "dialogflow-cx" => Ok(Self::DialogflowCx),
+            other => Err(crate::adk_error!(
+                ValidationError,
+                "Unknown import format '{}' (expected adk-session, adk-evalset or dialogflow-cx)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AdkSession => write!(f, "adk-session"),
+            Self::AdkEvalSet => write!(f, "adk-evalset"),
+            Self::DialogflowCx => write!(f, "dialogflow-cx"),
+        }
+    }
+}
+
+/// Names given to imported sessions and events
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// App of the imported sessions; overrides the app recorded in ADK dumps
+    pub app_name: Option<String>,
+
+    /// User of the imported sessions when the source does not record one
+    pub user_id: String,
+
+    /// Author of agent turns when the source does not record one
+    pub agent_name: String,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            app_name: None,
+            user_id: "imported_user".to_string(),
+            agent_name: "imported_agent".to_string(),
+        }
+    }
+}
+
+/// Parse `data` (a JSON document or JSON lines) into sessions
+pub fn import_sessions(data: &str, format: ImportFormat, options: &ImportOptions) -> Result<Vec<Session>> {
+    let documents = parse_documents(data)?;
+    let mut sessions = Vec::new();
+    for document in documents {
+        match format {
+            ImportFormat::AdkSession => {
+                for session in unwrap_list(document, "sessions") {
+                    sessions.push(adk_session(&session, options)?);
+                }
+            }
+            ImportFormat::AdkEvalSet => sessions.extend(adk_eval_set(&document, options)?),
+            ImportFormat::DialogflowCx => {
+                for conversation in unwrap_list(document, "conversations") {
+                    sessions.push(dialogflow_conversation(&conversation, options)?);
+                }
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+/// A single JSON document, or one document per line
+fn parse_documents(data: &str) -> Result<Vec<Value>> {
+    if let Ok(document) = serde_json::from_str(data) {
+        return Ok(vec![document]);
+    }
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(Into::into))
+        .collect()
+}
+
+/// Items of a top-level array or of a list field such as `{"sessions": [...]}`
+fn unwrap_list(document: Value, key: &str) -> Vec<Value> {
+    match document {
+        Value::Array(items) => items,
+        Value::Object(mut object) if object.get(key).is_some_and(Value::is_array) => match object.remove(key) {
+            Some(Value::Array(items)) => items,
+            _ => Vec::new(),
+        },
+        document => vec![document],
+    }
+}
+
+/// First present field among snake and camel case spellings
+fn field<'a>(value: &'a Value, names: &[&str]) -> Option<&'a Value> {
+    names.iter().find_map(|name| value.get(name).filter(|v| !v.is_null()))
+}
+
+fn str_field<'a>(value: &'a Value, names: &[&str]) -> Option<&'a str> {
+    field(value, names).and_then(Value::as_str)
+}
+
+/// Seconds since the epoch (Python ADK) or an RFC 3339 string (Dialogflow)
+fn timestamp(value: Option<&Value>) -> Option<Timestamp> {
+    match value? {
+        Value::Number(seconds) => {
+            let seconds = seconds.as_f64()?;
+            Utc.timestamp_opt(seconds.trunc() as i64, (seconds.fract() * 1e9) as u32).single()
+        }
+        Value::String(text) => DateTime::parse_from_rfc3339(text).ok().map(|t| t.with_timezone(&Utc)),
+        _ => None,
+    }
+}
+
+fn object(value: Option<&Value>) -> HashMap<String, Value> {
+    match value {
+        Some(Value::Object(map)) => map.clone().into_iter().collect(),
+        _ => HashMap::new(),
+    }
+}
+
+/// Maps source invocation ids (arbitrary strings in Python ADK) to UUIDs
+#[derive(Default)]
+struct InvocationIds(HashMap<String, InvocationId>);
+
+impl InvocationIds {
+    fn get(&mut self, id: Option<&str>) -> InvocationId {
+        let Some(id) = id else {
+            return crate::utils::new_uuid();
+        };
+        *self
+            .0
+            .entry(id.to_string())
+            .or_insert_with(|| uuid::Uuid::parse_str(id).unwrap_or_else(|_| crate::utils::new_uuid()))
+    }
+}
+
+fn decode_base64(data: &str) -> Result<bytes::Bytes> {
+    general_purpose::STANDARD
+        .decode(data)
+        .or_else(|_| general_purpose::URL_SAFE.decode(data))
+        .map(Into::into)
+        .map_err(|e| crate::adk_error!(SerializationError, "Invalid base64 media data: {}", e))
+}
+
+/// A `google.genai` content part (snake or camel case)
+fn genai_part(part: &Value) -> Result<Option<ContentPart>> {
+    if let Some(text) = str_field(part, &["text"]) {
+        return Ok(Some(ContentPart::text(text)));
+    }
+    if let Some(call) = field(part, &["function_call", "functionCall"]) {
+        return Ok(Some(ContentPart::FunctionCall {
+            name: str_field(call, &["name"]).unwrap_or_default().to_string(),
+            args: field(call, &["args"]).cloned().unwrap_or_else(|| Value::Object(Map::new())),
+        }));
+    }
+    if let Some(response) = field(part, &["function_response", "functionResponse"]) {
+        return Ok(Some(ContentPart::FunctionResponse {
+            name: str_field(response, &["name"]).unwrap_or_default().to_string(),
+            response: field(response, &["response"]).cloned().unwrap_or(Value::Null),
+        }));
+    }
+    if let Some(code) = field(part, &["executable_code", "executableCode"]) {
+        return Ok(Some(ContentPart::ExecutableCode {
+            language: str_field(code, &["language"]).unwrap_or("PYTHON").to_string(),
+            code: str_field(code, &["code"]).unwrap_or_default().to_string(),
+        }));
+    }
+    if let Some(result) = field(part, &["code_execution_result", "codeExecutionResult"]) {
+        let outcome = field(result, &["outcome"])
+            .and_then(|outcome| serde_json::from_value::<CodeExecutionOutcome>(outcome.clone()).ok())
+            .unwrap_or_default();
+        return Ok(Some(ContentPart::CodeExecutionResult {
+            outcome,
+            output: str_field(result, &["output"]).unwrap_or_default().to_string(),
+        }));
+    }
+    if let Some(file) = field(part, &["file_data", "fileData"]) {
+        return Ok(Some(ContentPart::FileRef {
+            uri: str_field(file, &["file_uri", "fileUri"]).unwrap_or_default().to_string(),
+            mime_type: str_field(file, &["mime_type", "mimeType"]).unwrap_or_default().to_string(),
+        }));
+    }
+    if let Some(inline) = field(part, &["inline_data", "inlineData"]) {
+        let data = decode_base64(str_field(inline, &["data"]).unwrap_or_default())?;
+        let mime_type = str_field(inline, &["mime_type", "mimeType"]).unwrap_or_default().to_string();
+        return Ok(Some(if mime_type.starts_with("image/") {
+            ContentPart::Image { data, mime_type }
+        } else if mime_type.starts_with("audio/") {
+            ContentPart::Audio { data, mime_type }
+        } else if mime_type.starts_with("video/") {
+            ContentPart::Video { data, mime_type }
+        } else {
+            let filename = str_field(inline, &["display_name", "displayName"]).unwrap_or_default().to_string();
+            ContentPart::File { data, mime_type, filename }
+        }));
+    }
+    // Thoughts, signatures and other parts without a counterpart are dropped
+    Ok(None)
+}
+
+fn genai_content(content: &Value, default_role: &str) -> Result<Content> {
+    let mut parts = Vec::new();
+    for part in field(content, &["parts"]).and_then(Value::as_array).into_iter().flatten() {
+        parts.extend(genai_part(part)?);
+    }
+    Ok(Content {
+        role: str_field(content, &["role"]).unwrap_or(default_role).to_string(),
+        parts,
+    })
+}
+
+fn adk_session(value: &Value, options: &ImportOptions) -> Result<Session> {
+    let id = str_field(value, &["id"])
+        .ok_or_else(|| crate::adk_error!(ValidationError, "ADK session without an id"))?;
+    let app_name = options
+        .app_name
+        .clone()
+        .or_else(|| str_field(value, &["app_name", "appName"]).map(str::to_string))
+        .unwrap_or_default();
+    let user_id = str_field(value, &["user_id", "userId"]).unwrap_or(&options.user_id);
+
+    let mut session = Session::new(app_name, user_id.to_string(), id.to_string());
+    session.state = object(field(value, &["state"]));
+
+    let mut invocations = InvocationIds::default();
+    for event in field(value, &["events"]).and_then(Value::as_array).into_iter().flatten() {
+        let author = str_field(event, &["author"]).unwrap_or(&options.agent_name).to_string();
+        let default_role = if author == "user" { "user" } else { "model" };
+        let content = field(event, &["content"]).map(|c| genai_content(c, default_role)).transpose()?;
+        let actions = field(event, &["actions"]);
+        session.events.push(Arc::new(Event {
+            id: str_field(event, &["id"]).map(str::to_string).unwrap_or_else(crate::utils::new_id),
+            author,
+            content,
+            actions: EventAction {
+                state_delta: object(actions.and_then(|a| field(a, &["state_delta", "stateDelta"]))),
+                escalate: actions.and_then(|a| field(a, &["escalate"])).and_then(Value::as_bool).unwrap_or(false),
+                transfer_to: actions
+                    .and_then(|a| str_field(a, &["transfer_to_agent", "transferToAgent"]))
+                    .map(str::to_string),
+                ..Default::default()
+            },
+            timestamp: timestamp(field(event, &["timestamp"])).unwrap_or(session.created_at),
+            invocation_id: invocations.get(str_field(event, &["invocation_id", "invocationId"])),
+            is_partial: field(event, &["partial"]).and_then(Value::as_bool).unwrap_or(false),
+            metadata: HashMap::new(),
+            citations: None,
+            transcription: None,
+            redact: false,
+        }));
+    }
+
+    session.updated_at = timestamp(field(value, &["last_update_time", "lastUpdateTime"]))
+        .or_else(|| session.events.last().map(|event| event.timestamp))
+        .unwrap_or(session.updated_at);
+    session.created_at = session.events.first().map_or(session.updated_at, |event| event.timestamp);
+    Ok(session)
+}
+
+fn adk_eval_set(value: &Value, options: &ImportOptions) -> Result<Vec<Session>> {
+    // Legacy eval files are a bare list of `{query, expected_tool_use, reference}` turns
+    if let Some(turns) = value.as_array() {
+        let mut session = eval_session(&crate::utils::new_id(), options, None);
+        for turn in turns {
+            let invocation_id = crate::utils::new_uuid();
+            let query = str_field(turn, &["query"]).unwrap_or_default();
+            session.events.push(Arc::new(Event::user_input(query, invocation_id)));
+            for tool in field(turn, &["expected_tool_use"]).and_then(Value::as_array).into_iter().flatten() {
+                let call = ContentPart::FunctionCall {
+                    name: str_field(tool, &["tool_name"]).unwrap_or_default().to_string(),
+                    args: field(tool, &["tool_input"]).cloned().unwrap_or_else(|| Value::Object(Map::new())),
+                };
+                session.events.push(Arc::new(agent_event(options, invocation_id, vec![call])));
+            }
+            if let Some(reference) = str_field(turn, &["reference"]) {
+                let answer = ContentPart::text(reference);
+                session.events.push(Arc::new(agent_event(options, invocation_id, vec![answer])));
+            }
+        }
+        return Ok(vec![session]);
+    }
+
+    let eval_set_id = str_field(value, &["eval_set_id", "evalSetId"]);
+    let mut sessions = Vec::new();
+    for case in field(value, &["eval_cases", "evalCases"]).and_then(Value::as_array).into_iter().flatten() {
+        let eval_id = str_field(case, &["eval_id", "evalId"])
+            .map(str::to_string)
+            .unwrap_or_else(crate::utils::new_id);
+        let input = field(case, &["session_input", "sessionInput"]);
+        let mut case_options = options.clone();
+        if case_options.app_name.is_none() {
+            case_options.app_name = input.and_then(|i| str_field(i, &["app_name", "appName"])).map(str::to_string);
+        }
+        if let Some(user_id) = input.and_then(|i| str_field(i, &["user_id", "userId"])) {
+            case_options.user_id = user_id.to_string();
+        }
+        let mut session = eval_session(&eval_id, &case_options, eval_set_id);
+        session.state = object(input.and_then(|i| field(i, &["state"])));
+
+        let mut invocations = InvocationIds::default();
+        for turn in field(case, &["conversation"]).and_then(Value::as_array).into_iter().flatten() {
+            let invocation_id = invocations.get(str_field(turn, &["invocation_id", "invocationId"]));
+            if let Some(content) = field(turn, &["user_content", "userContent"]) {
+                let mut event = Event::user_input("", invocation_id);
+                event.content = Some(genai_content(content, "user")?);
+                session.events.push(Arc::new(event));
+            }
+            let intermediate = field(turn, &["intermediate_data", "intermediateData"]);
+            for tool in intermediate
+                .and_then(|i| field(i, &["tool_uses", "toolUses"]))
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let call = ContentPart::FunctionCall {
+                    name: str_field(tool, &["name"]).unwrap_or_default().to_string(),
+                    args: field(tool, &["args"]).cloned().unwrap_or_else(|| Value::Object(Map::new())),
+                };
+                session.events.push(Arc::new(agent_event(&case_options, invocation_id, vec![call])));
+            }
+            if let Some(content) = field(turn, &["final_response", "finalResponse"]) {
+                let parts = genai_content(content, "model")?.parts;
+                session.events.push(Arc::new(agent_event(&case_options, invocation_id, parts)));
+            }
+        }
+        sessions.push(session);
+    }
+    Ok(sessions)
+}
+
+fn eval_session(id: &str, options: &ImportOptions, eval_set_id: Option<&str>) -> Session {
+    let mut session = Session::new(
+        options.app_name.clone().unwrap_or_default(),
+        options.user_id.clone(),
+        id.to_string(),
+    );
+    session.tags.insert(format!("eval_set:{}", eval_set_id.unwrap_or("legacy")));
+    session
+}
+
+fn agent_event(options: &ImportOptions, invocation_id: InvocationId, parts: Vec<ContentPart>) -> Event {
+    let mut event = Event::content_response(&options.agent_name, Content { role: "model".to_string(), parts });
+    event.invocation_id = invocation_id;
+    event
+}
+
+fn dialogflow_conversation(value: &Value, options: &ImportOptions) -> Result<Session> {
+    let name = str_field(value, &["name"])
+        .ok_or_else(|| crate::adk_error!(ValidationError, "Dialogflow conversation without a name"))?;
+    let id = name.rsplit('/').next().unwrap_or(name);
+    let mut session = Session::new(options.app_name.clone().unwrap_or_default(), options.user_id.clone(), id.to_string());
+
+    // The API lists interactions newest first
+    let mut interactions: Vec<&Value> = field(value, &["interactions"]).and_then(Value::as_array).into_iter().flatten().collect();
+    interactions.sort_by_key(|interaction| timestamp(field(interaction, &["createTime", "create_time"])));
+
+    for interaction in interactions {
+        let at = timestamp(field(interaction, &["createTime", "create_time"])).unwrap_or(session.created_at);
+        let invocation_id = crate::utils::new_uuid();
+        let input = field(interaction, &["request"]).and_then(|r| field(r, &["queryInput", "query_input"]));
+        let query = input.and_then(|input| {
+            field(input, &["text"])
+                .and_then(|t| str_field(t, &["text"]))
+                .or_else(|| field(input, &["transcript"]).and_then(Value::as_str))
+                .or_else(|| field(input, &["dtmf"]).and_then(|d| str_field(d, &["digits"])))
+        });
+        if let Some(query) = query {
+            let mut event = Event::user_input(query, invocation_id);
+            event.timestamp = at;
+            session.events.push(Arc::new(event));
+        }
+
+        let Some(result) = field(interaction, &["response"]).and_then(|r| field(r, &["queryResult", "query_result"])) else {
+            continue;
+        };
+        let text: Vec<&str> = field(result, &["responseMessages", "response_messages"])
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|message| field(message, &["text"]).and_then(|t| field(t, &["text"])).and_then(Value::as_array))
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        let mut event = agent_event(options, invocation_id, vec![ContentPart::text(text.join("\n"))]);
+        event.timestamp = at;
+        let matched = field(result, &["match"]);
+        let intent = matched
+            .and_then(|m| field(m, &["intent"]))
+            .or_else(|| field(result, &["intent"]))
+            .and_then(|intent| str_field(intent, &["displayName", "display_name"]));
+        if let Some(intent) = intent {
+            event.metadata.insert(IMPORTED_INTENT_METADATA_KEY.to_string(), intent.into());
+        }
+        if let Some(confidence) = matched.and_then(|m| field(m, &["confidence"])) {
+            event.metadata.insert(IMPORTED_INTENT_CONFIDENCE_METADATA_KEY.to_string(), confidence.clone());
+        }
+        event.actions.state_delta = object(field(result, &["parameters"]));
+        session.state.extend(event.actions.state_delta.clone());
+        session.events.push(Arc::new(event));
+    }
+
+    session.created_at = timestamp(field(value, &["startTime", "start_time"]))
+        .or_else(|| session.events.first().map(|event| event.timestamp))
+        .unwrap_or(session.created_at);
+    session.updated_at = session.events.last().map_or(session.created_at, |event| event.timestamp);
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_import_adk_session_dump() {
+        let dump = json!({
+            "id": "s1", "appName": "weather", "userId": "u1", "state": {"city": "Paris"},
+            "lastUpdateTime": 1700000010.5,
+            "events": [
+                {"id": "e1", "invocationId": "e-1", "author": "user", "timestamp": 1700000000.0,
+                 "content": {"role": "user", "parts": [{"text": "Weather?"}]}},
+                {"id": "e2", "invocationId": "e-1", "author": "weather_agent", "timestamp": 1700000001.0,
+                 "content": {"role": "model", "parts": [{"functionCall": {"id": "c1", "name": "get_weather", "args": {"city": "Paris"}}}]},
+                 "actions": {"stateDelta": {"checked": true}}},
+                {"id": "e3", "invocationId": "e-1", "author": "weather_agent", "timestamp": 1700000002.0,
+                 "content": {"role": "model", "parts": [{"text": "Sunny."}, {"thought": true, "text": "hmm"}]}}
+            ]
+        });
+        let sessions = import_sessions(&dump.to_string(), ImportFormat::AdkSession, &ImportOptions::default()).unwrap();
+        let session = &sessions[0];
+        assert_eq!((session.app_name.as_str(), session.user_id.as_str()), ("weather", "u1"));
+        assert_eq!(session.state["city"], "Paris");
+        assert_eq!(session.events.len(), 3);
+        assert_eq!(session.events[0].invocation_id, session.events[2].invocation_id);
+        assert!(matches!(&session.events[1].content.as_ref().unwrap().parts[0], ContentPart::FunctionCall { name, .. } if name == "get_weather"));
+        assert_eq!(session.events[1].actions.state_delta["checked"], true);
+        assert_eq!(session.created_at.timestamp(), 1700000000);
+        assert_eq!(session.updated_at.timestamp_millis(), 1700000010500);
+    }
+
+    #[test]
+    fn test_import_eval_set_and_dialogflow_conversation() {
+        let eval_set = json!({
+            "eval_set_id": "smoke",
+            "eval_cases": [{
+                "eval_id": "case1",
+                "session_input": {"app_name": "weather", "user_id": "tester", "state": {}},
+                "conversation": [{
+                    "invocation_id": "i1",
+                    "user_content": {"role": "user", "parts": [{"text": "Weather in Oslo?"}]},
+                    "final_response": {"role": "model", "parts": [{"text": "Cold."}]},
+                    "intermediate_data": {"tool_uses": [{"name": "get_weather", "args": {"city": "Oslo"}}]}
+                }]
+            }]
+        });
+        let sessions = import_sessions(&eval_set.to_string(), ImportFormat::AdkEvalSet, &ImportOptions::default()).unwrap();
+        assert_eq!(sessions[0].id, "case1");
+        assert!(sessions[0].has_tag("eval_set:smoke"));
+        let texts: Vec<_> = sessions[0].events.iter().map(|e| e.get_text().unwrap()).collect();
+        assert_eq!(texts, ["Weather in Oslo?", "", "Cold."]);
+
+        let conversation = json!({
+            "name": "projects/p/locations/global/agents/a/conversations/conv-9",
+            "interactions": [
+                {"createTime": "2024-05-01T10:00:05Z",
+                 "request": {"queryInput": {"text": {"text": "Book it"}}},
+                 "response": {"queryResult": {"responseMessages": [{"text": {"text": ["Booked!"]}}],
+                              "match": {"intent": {"displayName": "confirm"}, "confidence": 0.93},
+                              "parameters": {"date": "tomorrow"}}}},
+                {"createTime": "2024-05-01T10:00:00Z",
+                 "request": {"queryInput": {"text": {"text": "Hi"}}},
+                 "response": {"queryResult": {"responseMessages": [{"text": {"text": ["Hello", "How can I help?"]}}]}}}
+            ]
+        });
+        let options = ImportOptions { app_name: Some("bookings".into()), ..Default::default() };
+        let sessions = import_sessions(&conversation.to_string(), ImportFormat::DialogflowCx, &options).unwrap();
+        let session = &sessions[0];
+        assert_eq!((session.id.as_str(), session.app_name.as_str()), ("conv-9", "bookings"));
+        let texts: Vec<_> = session.events.iter().map(|e| e.get_text().unwrap()).collect();
+        assert_eq!(texts, ["Hi", "Hello\nHow can I help?", "Book it", "Booked!"]);
+        assert_eq!(session.events[3].metadata[IMPORTED_INTENT_METADATA_KEY], "confirm");
+        assert_eq!(session.state["date"], "tomorrow");
+    }
+}
@@ -1,7 +1,23 @@
 //! Session management system
 
+pub mod buffered;
+pub mod conformance;
+pub mod export;
+pub mod feedback;
+pub mod handoff;
+pub mod import;
 pub mod session;
 pub mod session_service;
+pub mod stateless;
 
-pub use session::Session;
-pub use session_service::{SessionService, InMemorySessionService};
+pub use buffered::{BufferedSessionConfig, BufferedSessionService};
+pub use export::{ExportFilter, ExportFormat};
+pub use feedback::{Feedback, FeedbackSummary, Rating};
+pub use handoff::{
+    begin_handoff, find_human_controlled_session, human_controlled_sessions, is_human_controlled, latest_handoff,
+    operator_reply, return_to_agent, HUMAN_HANDOFF_TAG, OPERATOR_AUTHOR, OPERATOR_METADATA_KEY,
+};
+pub use import::{import_sessions, ImportFormat, ImportOptions, IMPORTED_INTENT_CONFIDENCE_METADATA_KEY, IMPORTED_INTENT_METADATA_KEY};
+pub use session::{Session, SESSION_TAGS_STATE_KEY};
+pub use session_service::{SessionFilter, SessionService, InMemorySessionService};
+pub use stateless::{StatelessResult, StatelessSession};
@@ -4,8 +4,11 @@ use crate::{
     events::Event,
     types::{SessionId, SessionState, Timestamp, UserId},
 };
-use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, sync::Arc};
+
+/// State delta key through which agents add tags to their session
+pub const SESSION_TAGS_STATE_KEY: &str = "session_tags";
 
 /// Session for managing conversation state
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,8 +25,12 @@ pub struct Session {
     /// Session state
     pub state: SessionState,
     
-    /// Events in this session
-    pub events: Vec<Event>,
+    /// Events in this session, shared with the streams that produced them
+    pub events: Vec<Arc<Event>>,
+
+    /// Labels for support and debugging workflows
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
     
     /// When the session was created
     pub created_at: Timestamp,
@@ -35,21 +42,49 @@ pub struct Session {
 impl Session {
     /// Create a new session
     pub fn new(app_name: String, user_id: UserId, session_id: SessionId) -> Self {
-        let now = Utc::now();
+        let now = crate::types::now();
         Self {
             id: session_id,
             user_id,
             app_name,
             state: SessionState::new(),
             events: Vec::new(),
+            tags: BTreeSet::new(),
             created_at: now,
             updated_at: now,
         }
     }
 
     /// Add an event to the session
-    pub fn add_event(&mut self, event: Event) {
+    pub fn add_event(&mut self, event: impl Into<Arc<Event>>) {
+        let event = event.into();
+        if let Some(tags) = event.actions.state_delta.get(SESSION_TAGS_STATE_KEY) {
+            let tags = tags.as_array().into_iter().flatten().filter_map(|tag| tag.as_str());
+            self.tags.extend(tags.map(str::to_string));
+        }
         self.events.push(event);
-        self.updated_at = Utc::now();
+        self.updated_at = crate::types::now();
+    }
+
+    /// Add a tag to the session
+    pub fn add_tag(&mut self, tag: impl Into<String>) {
+        self.tags.insert(tag.into());
+        self.updated_at = crate::types::now();
+    }
+
+    /// Check whether the session carries a tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// Case-insensitive full-text match over the text of the session's events
+    pub fn matches_text(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.events
+            .iter()
+            .filter_map(|event| event.content.as_ref())
+            .flat_map(|content| content.parts.iter())
+            .filter_map(|part| part.as_text())
+            .any(|text| text.to_lowercase().contains(&query))
     }
 }
@@ -3,17 +3,89 @@
 use crate::{
     error::Result,
     events::Event,
-    types::{SessionId, SessionState, UserId},
+    types::{Metadata, SessionId, SessionState, UserId},
 };
 use async_trait::async_trait;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
 use tokio::sync::RwLock;
 
 use super::session::Session;
 
+/// Criteria for listing sessions
+#[derive(Debug, Clone, Default)]
+pub struct SessionFilter {
+    /// Only sessions of this application
+    pub app_name: Option<String>,
+
+    /// Only sessions of this user
+    pub user_id: Option<UserId>,
+
+    /// Only sessions carrying all of these tags
+    pub tags: Vec<String>,
+
+    /// Only sessions whose event text contains this query (case-insensitive)
+    pub query: Option<String>,
+}
+
+impl SessionFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = Some(app_name.into());
+        self
+    }
+
+    pub fn with_user_id(mut self, user_id: impl Into<UserId>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn with_query(mut self, query: impl Into<String>) -> Self {
+        self.query = Some(query.into());
+        self
+    }
+
+    /// Check whether a session matches this filter
+    pub fn matches(&self, session: &Session) -> bool {
+        self.app_name.as_ref().is_none_or(|app| &session.app_name == app)
+            && self.user_id.as_ref().is_none_or(|user| &session.user_id == user)
+            && self.tags.iter().all(|tag| session.has_tag(tag))
+            && self.query.as_ref().is_none_or(|query| session.matches_text(query))
+    }
+}
+
 /// Service for managing sessions
+///
+/// Every backend guarantees, for a single service instance:
+///
+/// - **Read-after-write:** once a write (`create_session`, `append_event`,
+///   `append_events`, `update_session_state`, `update_event_metadata`,
+///   `set_session_tags`) returns `Ok`, every later `get_session` and
+///   `list_sessions` call reflects it, even if the write is still buffered.
+///   Agents rely on this to see the events the runner appended earlier in
+///   the same invocation.
+/// - **Ordering:** a session's events are returned in the order their appends
+///   completed; `append_events` keeps the order of its batch. Concurrent
+///   appends to one session are serialized, and each event appears once.
+/// - **No silent drops:** writes to a session that does not exist fail with a
+///   `SessionError` instead of being discarded.
+///
+/// Backends check these guarantees with [`session_service_conformance!`](crate::session_service_conformance).
 #[async_trait]
 pub trait SessionService: Send + Sync {
+    /// Store a new session
+    async fn create_session(&self, session: Session) -> Result<()>;
+
     /// Get a session by ID
     async fn get_session(
         &self,
@@ -22,6 +94,24 @@ pub trait SessionService: Send + Sync {
         session_id: &SessionId,
     ) -> Result<Option<Session>>;
 
+    /// List sessions matching a filter
+    async fn list_sessions(&self, filter: &SessionFilter) -> Result<Vec<Session>>;
+
+    /// Get a session, creating and storing an empty one if it does not exist
+    async fn get_or_create_session(
+        &self,
+        app_name: &str,
+        user_id: &UserId,
+        session_id: &SessionId,
+    ) -> Result<Session> {
+        if let Some(session) = self.get_session(app_name, user_id, session_id).await? {
+            return Ok(session);
+        }
+        let session = Session::new(app_name.to_string(), user_id.clone(), session_id.clone());
+        self.create_session(session.clone()).await?;
+        Ok(session)
+    }
+
     /// Update session state
     async fn update_session_state(
         &self,
@@ -30,7 +120,34 @@ pub trait SessionService: Send + Sync {
     ) -> Result<()>;
 
     /// Append an event to a session
-    async fn append_event(&self, session_id: &SessionId, event: Event) -> Result<()>;
+    async fn append_event(&self, session_id: &SessionId, event: Arc<Event>) -> Result<()>;
+
+    /// Append several events in order; backends override this to write them in one batch
+    async fn append_events(&self, session_id: &SessionId, events: Vec<Arc<Event>>) -> Result<()> {
+        for event in events {
+            self.append_event(session_id, event).await?;
+        }
+        Ok(())
+    }
+
+    /// Merge metadata into a stored event
+    async fn update_event_metadata(
+        &self,
+        session_id: &SessionId,
+        event_id: &str,
+        metadata: Metadata,
+    ) -> Result<()>;
+
+    /// Replace the tags of a session
+    async fn set_session_tags(&self, session_id: &SessionId, tags: BTreeSet<String>) -> Result<()>;
+
+    /// Delete a session with its events and state; returns whether it existed
+    async fn delete_session(&self, session_id: &SessionId) -> Result<bool>;
+
+    /// Write out any buffered changes
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// In-memory session service implementation
@@ -55,6 +172,12 @@ impl Default for InMemorySessionService {
 
 #[async_trait]
 impl SessionService for InMemorySessionService {
+    async fn create_session(&self, session: Session) -> Result<()> {
+        let mut sessions = self.sessions.write().await;
+        sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
     async fn get_session(
         &self,
         _app_name: &str,
@@ -65,24 +188,141 @@ impl SessionService for InMemorySessionService {
         Ok(sessions.get(session_id).cloned())
     }
 
+    async fn list_sessions(&self, filter: &SessionFilter) -> Result<Vec<Session>> {
+        let sessions = self.sessions.read().await;
+        Ok(sessions
+            .values()
+            .filter(|session| filter.matches(session))
+            .cloned()
+            .collect())
+    }
+
     async fn update_session_state(
         &self,
         session_id: &SessionId,
         state: &SessionState,
     ) -> Result<()> {
         let mut sessions = self.sessions.write().await;
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.state = state.clone();
-            session.updated_at = chrono::Utc::now();
-        }
+        let session = sessions.get_mut(session_id).ok_or_else(|| {
+            crate::adk_error!(SessionError, "Session not found: {}", session_id)
+        })?;
+        session.state = state.clone();
+        session.updated_at = crate::types::now();
         Ok(())
     }
 
-    async fn append_event(&self, session_id: &SessionId, event: Event) -> Result<()> {
+    async fn append_event(&self, session_id: &SessionId, event: Arc<Event>) -> Result<()> {
+        self.append_events(session_id, vec![event]).await
+    }
+
+    async fn append_events(&self, session_id: &SessionId, events: Vec<Arc<Event>>) -> Result<()> {
         let mut sessions = self.sessions.write().await;
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.add_event(event);
-        }
+        let session = sessions.get_mut(session_id).ok_or_else(|| {
+            crate::adk_error!(SessionError, "Session not found: {}", session_id)
+        })?;
+        events.into_iter().for_each(|event| session.add_event(event));
+        Ok(())
+    }
+
+    async fn update_event_metadata(
+        &self,
+        session_id: &SessionId,
+        event_id: &str,
+        metadata: Metadata,
+    ) -> Result<()> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(session_id).ok_or_else(|| {
+            crate::adk_error!(SessionError, "Session not found: {}", session_id)
+        })?;
+        let event = session
+            .events
+            .iter_mut()
+            .find(|event| event.id == event_id)
+            .ok_or_else(|| crate::adk_error!(SessionError, "Event not found: {}", event_id))?;
+        Arc::make_mut(event).metadata.extend(metadata);
+        session.updated_at = crate::types::now();
+        Ok(())
+    }
+
+    async fn set_session_tags(&self, session_id: &SessionId, tags: BTreeSet<String>) -> Result<()> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(session_id).ok_or_else(|| {
+            crate::adk_error!(SessionError, "Session not found: {}", session_id)
+        })?;
+        session.tags = tags;
+        session.updated_at = crate::types::now();
         Ok(())
     }
+
+    async fn delete_session(&self, session_id: &SessionId) -> Result<bool> {
+        Ok(self.sessions.write().await.remove(session_id).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sessions::SESSION_TAGS_STATE_KEY;
+
+    crate::session_service_conformance!(InMemorySessionService::new());
+
+    #[tokio::test]
+    async fn test_create_list_and_get_or_create_sessions() {
+        let service = InMemorySessionService::new();
+        service
+            .create_session(Session::new("app".to_string(), "alice".to_string(), "s1".to_string()))
+            .await
+            .unwrap();
+        service
+            .create_session(Session::new("other".to_string(), "bob".to_string(), "s2".to_string()))
+            .await
+            .unwrap();
+
+        let apps = service.list_sessions(&SessionFilter::new().with_app_name("app")).await.unwrap();
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].id, "s1");
+        let bobs = service.list_sessions(&SessionFilter::new().with_user_id("bob")).await.unwrap();
+        assert_eq!(bobs[0].id, "s2");
+        assert_eq!(service.list_sessions(&SessionFilter::new()).await.unwrap().len(), 2);
+
+        let existing = service
+            .get_or_create_session("app", &"alice".to_string(), &"s1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(existing.user_id, "alice");
+        let created = service
+            .get_or_create_session("app", &"carol".to_string(), &"s3".to_string())
+            .await
+            .unwrap();
+        assert_eq!(created.user_id, "carol");
+        assert!(service.get_session("app", &created.user_id, &created.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_filter_by_tags_and_text() {
+        let service = InMemorySessionService::new();
+        let mut session = Session::new("app".to_string(), "user".to_string(), "s1".to_string());
+        let mut event = Event::text_response("support_agent", "Your refund has been issued.");
+        event
+            .actions
+            .state_delta
+            .insert(SESSION_TAGS_STATE_KEY.to_string(), serde_json::json!(["refund"]));
+        session.add_event(event);
+        service.create_session(session).await.unwrap();
+        service
+            .create_session(Session::new("app".to_string(), "user".to_string(), "s2".to_string()))
+            .await
+            .unwrap();
+
+        let refunds = SessionFilter::new().with_tag("refund").with_query("REFUND HAS");
+        let found = service.list_sessions(&refunds).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "s1");
+
+        let escalated = SessionFilter::new().with_tag("escalated");
+        assert!(service.list_sessions(&escalated).await.unwrap().is_empty());
+        let tags = BTreeSet::from(["escalated".to_string()]);
+        service.set_session_tags(&"s2".to_string(), tags).await.unwrap();
+        assert_eq!(service.list_sessions(&escalated).await.unwrap()[0].id, "s2");
+    }
 }
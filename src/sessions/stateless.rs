@@ -0,0 +1,162 @@
+//! Stateless runs for serverless deployments
+//!
+//! The caller owns the session: it sends the state and recent history with
+//! each request and gets back what the run changed. Nothing outlives the
+//! request, so the agent server needs no session store and can scale to
+//! zero.
+
+use crate::{
+    events::Event,
+    sessions::Session,
+    types::{SessionId, SessionState, StateDelta, UserId},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Session state and history supplied with a stateless run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatelessSession {
+    #[serde(default)]
+    pub state: SessionState,
+
+    /// Recent events, oldest first; only these are visible to the agent
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+impl StatelessSession {
+    pub fn new(state: SessionState, events: Vec<Event>) -> Self {
+        Self { state, events }
+    }
+
+    /// Materialize as a session for a run
+    pub fn into_session(self, app_name: &str, user_id: UserId, session_id: SessionId) -> Session {
+        let mut session = Session::new(app_name.to_string(), user_id, session_id);
+        session.state = self.state;
+        for event in self.events {
+            session.add_event(event);
+        }
+        session
+    }
+
+    /// Apply the result of a run, as the owner of the session would
+    pub fn apply(&mut self, result: &StatelessResult) {
+        for (key, value) in &result.state_delta {
+            match value {
+                Value::Null => self.state.remove(key),
+                value => self.state.insert(key.clone(), value.clone()),
+            };
+        }
+        self.events.extend(result.events.iter().map(|event| event.as_ref().clone()));
+    }
+}
+
+/// What a stateless run changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatelessResult {
+    /// Changed and added keys; removed keys map to `null`
+    pub state_delta: StateDelta,
+
+    /// Events to append to the history, starting with the user's message
+    pub events: Vec<Arc<Event>>,
+}
+
+impl StatelessResult {
+    /// Compare the session after a run with the state and number of events it started from
+    pub fn from_session(initial_state: &SessionState, initial_events: usize, session: &Session) -> Self {
+        let mut state_delta: StateDelta = session
+            .state
+            .iter()
+            .filter(|(key, value)| initial_state.get(*key) != Some(value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        state_delta.extend(
+            initial_state
+                .keys()
+                .filter(|key| !session.state.contains_key(*key))
+                .map(|key| (key.clone(), Value::Null)),
+        );
+
+        let events = session.events.get(initial_events..).unwrap_or_default().to_vec();
+        // Deltas carried by events are part of the state the caller keeps
+        for event in &events {
+            state_delta.extend(event.actions.state_delta.clone());
+        }
+        Self { state_delta, events }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::AgentBuilder, LlmAgent},
+        runners::Runner,
+        sessions::{InMemorySessionService, SessionFilter, SessionService},
+        testing::MockGeminiServer,
+        types::Content,
+    };
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_caller_owns_state_and_history() {
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-stateless").await;
+        mock.push_text("Hi Ada!").push_text("You are Ada.");
+
+        let agent = LlmAgent::builder().name("greeter").model("mock-gemini-stateless").build().unwrap();
+        let store = Arc::new(InMemorySessionService::new());
+        let runner = Runner::new("app", Arc::new(agent), store.clone());
+
+        let mut owned = StatelessSession::new(SessionState::from([("plan".to_string(), json!("free"))]), Vec::new());
+        for message in ["I'm Ada", "Who am I?"] {
+            let result = runner
+                .run_stateless("u1".into(), "s1".into(), owned.clone(), Content::user_text(message))
+                .await
+                .unwrap();
+            assert_eq!(result.events.len(), 2);
+            assert_eq!(result.events[0].author, "user");
+            owned.apply(&result);
+        }
+
+        assert_eq!(owned.events.len(), 4);
+        assert_eq!(owned.events[3].get_text().as_deref(), Some("You are Ada."));
+        assert_eq!(mock.requests()[1].body["contents"].as_array().unwrap().len(), 3);
+        assert!(store.list_sessions(&SessionFilter::new()).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_result_reports_changed_removed_and_event_state() {
+        let initial = SessionState::from([
+            ("kept".to_string(), json!(1)),
+            ("changed".to_string(), json!(1)),
+            ("removed".to_string(), json!(1)),
+        ]);
+        let mut session = StatelessSession::new(initial.clone(), Vec::new()).into_session("app", "u1".into(), "s1".into());
+        session.state.remove("removed");
+        session.state.insert("changed".to_string(), json!(2));
+        let mut event = Event::text_response("agent", "done");
+        event.actions.state_delta.insert("step".to_string(), json!("confirm"));
+        session.add_event(event);
+
+        let result = StatelessResult::from_session(&initial, 0, &session);
+        assert_eq!(
+            result.state_delta,
+            StateDelta::from([
+                ("changed".to_string(), json!(2)),
+                ("removed".to_string(), Value::Null),
+                ("step".to_string(), json!("confirm")),
+            ])
+        );
+
+        let mut owned = StatelessSession::new(initial, Vec::new());
+        owned.apply(&result);
+        assert_eq!(owned.state, SessionState::from([
+            ("kept".to_string(), json!(1)),
+            ("changed".to_string(), json!(2)),
+            ("step".to_string(), json!("confirm")),
+        ]));
+        assert_eq!(owned.events.len(), 1);
+    }
+}
@@ -0,0 +1,193 @@
+//! Lightweight assertions on agent responses
+//!
+//! A sidecar YAML file lists checks every final response of a development
+//! run must pass, so prompt changes get instant feedback before a full
+//! evaluation (`adk run --assert`):
+//!
+//! ```yaml
+//! judge_model: gemini-2.0-flash
+//! assertions:
+//!   - contains: refund
+//!   - regex: "order #\\d+"
+//!   - judge: The answer apologizes and offers a refund.
+//! ```
+//!
+//! `contains` is case-insensitive. `judge` asks a model whether the response
+//! meets the criterion, using the assertion's `model` or the file's
+//! `judge_model`.
+
+use crate::{
+    error::Result,
+    models::{create_model, LlmRequest},
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Default sidecar file name, looked up next to the agent
+pub const DEFAULT_ASSERTIONS_FILE: &str = "assertions.yaml";
+
+/// Check of one response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResponseAssertion {
+    Contains { contains: String },
+    Regex { regex: String },
+    Judge {
+        judge: String,
+        #[serde(default)]
+        model: Option<String>,
+    },
+}
+
+impl std::fmt::Display for ResponseAssertion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Contains { contains } => write!(f, "contains {:?}", contains),
+            Self::Regex { regex } => write!(f, "matches /{}/", regex),
+            Self::Judge { judge, .. } => write!(f, "judge: {}", judge),
+        }
+    }
+}
+
+/// Outcome of one assertion on one response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AssertionResult {
+    /// The assertion, as shown to the developer
+    pub assertion: String,
+    pub passed: bool,
+
+    /// Why it failed, e.g. the judge's reason or a broken regex
+    pub detail: Option<String>,
+}
+
+impl std::fmt::Display for AssertionResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", if self.passed { "PASS" } else { "FAIL" }, self.assertion)?;
+        if let Some(detail) = &self.detail {
+            write!(f, " ({})", detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Assertions loaded from a sidecar file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssertionSet {
+    /// Model for `judge` assertions without their own
+    #[serde(default)]
+    pub judge_model: Option<String>,
+    pub assertions: Vec<ResponseAssertion>,
+}
+
+impl AssertionSet {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| crate::adk_error!(ConfigError, "Failed to read assertions '{}': {}", path.display(), e))?;
+        serde_yaml::from_str(&text)
+            .map_err(|e| crate::adk_error!(ConfigError, "Invalid assertions file '{}': {}", path.display(), e))
+    }
+
+    /// The default sidecar of an agent folder (or of the current directory)
+    pub fn sidecar_path(agent_dir: Option<&Path>) -> PathBuf {
+        agent_dir.unwrap_or(Path::new(".")).join(DEFAULT_ASSERTIONS_FILE)
+    }
+
+    /// Check a final response to `user_message` against every assertion
+    pub async fn check(&self, user_message: &str, response: &str) -> Vec<AssertionResult> {
+        let mut results = Vec::with_capacity(self.assertions.len());
+        for assertion in &self.assertions {
+            let outcome = match assertion {
+                ResponseAssertion::Contains { contains } => {
+                    Ok((response.to_lowercase().contains(&contains.to_lowercase()), None))
+                }
+                ResponseAssertion::Regex { regex } => Regex::new(regex)
+                    .map(|regex| (regex.is_match(response), None))
+                    .map_err(|e| e.to_string()),
+                ResponseAssertion::Judge { judge, model } => match model.as_ref().or(self.judge_model.as_ref()) {
+                    Some(model) => judge_response(model, judge, user_message, response)
+                        .await
+                        .map_err(|e| e.to_string()),
+                    None => Err("no judge model configured".to_string()),
+                },
+            };
+            let (passed, detail) = match outcome {
+                Ok((passed, reason)) => (passed, reason.filter(|_| !passed)),
+                Err(e) => (false, Some(e)),
+            };
+            results.push(AssertionResult {
+                assertion: assertion.to_string(),
+                passed,
+                detail,
+            });
+        }
+        results
+    }
+}
+
+/// Ask a judge model whether a response meets a criterion
+async fn judge_response(
+    model: &str,
+    criterion: &str,
+    user_message: &str,
+    response: &str,
+) -> Result<(bool, Option<String>)> {
+    #[derive(Deserialize)]
+    struct Verdict {
+        pass: bool,
+        #[serde(default)]
+        reason: Option<String>,
+    }
+
+    let prompt = format!(
+        "User message:\n{}\n\nAgent response:\n{}\n\nCriterion: {}\n\nDoes the response meet the criterion? \
+         Reply with only a JSON object: {{\"pass\": true or false, \"reason\": \"<one sentence>\"}}.",
+        user_message, response, criterion
+    );
+    let llm = create_model(model).await?;
+    let text = llm
+        .generate_content(LlmRequest::new(model).add_user_message(prompt))
+        .await?
+        .get_text()
+        .unwrap_or_default();
+    let json = text
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+    let verdict: Verdict = serde_json::from_str(json)
+        .map_err(|e| crate::adk_error!(ModelError, "Judge verdict is not the expected JSON object: {}", e))?;
+    Ok((verdict.pass, verdict.reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockGeminiServer;
+
+    #[tokio::test]
+    async fn test_assertions_from_sidecar_file() {
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-judge").await;
+        mock.push_text("{\"pass\": false, \"reason\": \"No refund is offered.\"}");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = AssertionSet::sidecar_path(Some(dir.path()));
+        std::fs::write(
+            &path,
+            "judge_model: mock-gemini-judge\nassertions:\n  - contains: SORRY\n  - regex: \"order #\\\\d+\"\n  - judge: Offers a refund.\n  - regex: \"(\"\n",
+        )
+        .unwrap();
+        let set = AssertionSet::from_file(&path).unwrap();
+        assert_eq!(set.assertions.len(), 4);
+
+        let results = set.check("My order is late", "Sorry about order #42.").await;
+        let passed: Vec<bool> = results.iter().map(|result| result.passed).collect();
+        assert_eq!(passed, [true, true, false, false]);
+        assert_eq!(results[2].to_string(), "FAIL judge: Offers a refund. (No refund is offered.)");
+        assert!(results[3].detail.is_some());
+        assert!(mock.requests()[0].body.to_string().contains("Sorry about order #42."));
+    }
+}
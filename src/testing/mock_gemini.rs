@@ -0,0 +1,417 @@
+//! Local stand-in for the Gemini API
+//!
+//! [`MockGeminiServer`] serves `generateContent`, `streamGenerateContent`
+//! and `countTokens` on a loopback port, answers model calls with scripted
+//! replies (text, function calls or API errors) and records them. Point a
+//! [`GoogleLlm`] at it, or register a model name that resolves to it, to test
+//! agent, runner and web server flows end to end without network access or
+//! API keys.
+
+use crate::{
+    error::Result,
+    models::{global_registry, BaseLlm, GoogleLlm, RetryPolicy},
+    types::FunctionCall,
+};
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio_util::sync::CancellationToken;
+
+/// API key models created by the mock server send
+pub const MOCK_API_KEY: &str = "mock-api-key";
+
+/// What the mock server answers to one model call
+#[derive(Debug, Clone)]
+pub enum MockReply {
+    /// A text answer; streamed word by word by `streamGenerateContent`
+    Text(String),
+
+    /// The model asks for tool calls
+    FunctionCalls(Vec<FunctionCall>),
+
+    /// An API error with the given HTTP status
+    Error { status: u16, message: String },
+
+    /// A verbatim `generateContent` response body
+    Raw(Value),
+}
+
+impl MockReply {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    pub fn function_call(name: impl Into<String>, args: Value) -> Self {
+        Self::FunctionCalls(vec![FunctionCall { name: name.into(), args }])
+    }
+
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::Error {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// A model call received by the mock server
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub model: String,
+
+    /// Whether it came through `streamGenerateContent`
+    pub streaming: bool,
+
+    pub authorization: Option<String>,
+    pub body: Value,
+}
+
+impl RecordedRequest {
+    /// Text of the system instruction
+    pub fn system_instruction(&self) -> Option<String> {
+        self.body["system_instruction"]["parts"][0]["text"].as_str().map(str::to_string)
+    }
+
+    /// Text of the last user turn
+    pub fn last_user_text(&self) -> Option<String> {
+        self.contents()
+            .iter()
+            .rev()
+            .find(|content| content["role"] == "user" && content["parts"][0]["text"].is_string())
+            .map(|content| content["parts"][0]["text"].as_str().unwrap_or_default().to_string())
+    }
+
+    /// Tool results sent back to the model, as `(name, response)`
+    pub fn function_responses(&self) -> Vec<(String, Value)> {
+        self.contents()
+            .iter()
+            .flat_map(|content| content["parts"].as_array().cloned().unwrap_or_default())
+            .filter_map(|part| {
+                let response = part.get("function_response")?;
+                Some((response["name"].as_str()?.to_string(), response["response"].clone()))
+            })
+            .collect()
+    }
+
+    /// Names of the functions declared to the model
+    pub fn declared_functions(&self) -> Vec<String> {
+        self.body["tools"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|tool| tool["function_declarations"].as_array().cloned().unwrap_or_default())
+            .filter_map(|declaration| declaration["name"].as_str().map(str::to_string))
+            .collect()
+    }
+
+    fn contents(&self) -> &[Value] {
+        self.body["contents"].as_array().map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    replies: VecDeque<MockReply>,
+    fallback: Option<MockReply>,
+    requests: Vec<RecordedRequest>,
+}
+
+type SharedState = Arc<Mutex<MockState>>;
+
+/// Gemini API emulator on a loopback port; shuts down when dropped
+#[derive(Debug)]
+pub struct MockGeminiServer {
+    addr: SocketAddr,
+    state: SharedState,
+    shutdown: CancellationToken,
+}
+
+impl MockGeminiServer {
+    /// Start serving on an ephemeral port
+    pub async fn start() -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let state = SharedState::default();
+        let shutdown = CancellationToken::new();
+
+        let router = Router::new()
+            .route("/v1beta/models/:call", post(handle_call))
+            .with_state(state.clone());
+        let stopped = shutdown.clone();
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, router)
+                .with_graceful_shutdown(async move { stopped.cancelled().await })
+                .await;
+        });
+
+        Ok(Self { addr, state, shutdown })
+    }
+
+    /// Base URL to configure Gemini clients with
+    pub fn base_url(&self) -> String {
+        format!("http://{}/v1beta", self.addr)
+    }
+
+    /// Queue the reply to the next model call
+    pub fn push_reply(&self, reply: MockReply) -> &Self {
+        self.state.lock().unwrap().replies.push_back(reply);
+        self
+    }
+
+    pub fn push_text(&self, text: impl Into<String>) -> &Self {
+        self.push_reply(MockReply::text(text))
+    }
+
+    pub fn push_function_call(&self, name: impl Into<String>, args: Value) -> &Self {
+        self.push_reply(MockReply::function_call(name, args))
+    }
+
+    pub fn push_error(&self, status: u16, message: impl Into<String>) -> &Self {
+        self.push_reply(MockReply::error(status, message))
+    }
+
+    /// Answer calls with `reply` once the queue is empty, instead of failing them
+    pub fn set_fallback(&self, reply: MockReply) {
+        self.state.lock().unwrap().fallback = Some(reply);
+    }
+
+    /// Model calls received so far
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// A Gemini model that talks to this server; it does not retry, so scripted errors reach the caller
+    pub fn llm(&self, model: impl Into<String>) -> GoogleLlm {
+        GoogleLlm::new(model)
+            .with_base_url(self.base_url())
+            .with_api_key(MOCK_API_KEY)
+            .with_retry_policy(RetryPolicy::none())
+    }
+
+    /// Make `model_name` resolve to this server in the global model registry,
+    /// so agents configured with that name call it. Use a name unique to the
+    /// test, as the registry is shared by the whole process.
+    pub async fn register_model(&self, model_name: impl Into<String>) {
+        let base_url = self.base_url();
+        global_registry()
+            .register(model_name.into(), move |name| {
+                Ok(Box::new(
+                    GoogleLlm::new(name)
+                        .with_base_url(base_url.clone())
+                        .with_api_key(MOCK_API_KEY)
+                        .with_retry_policy(RetryPolicy::none()),
+                ) as Box<dyn BaseLlm>)
+            })
+            .await;
+    }
+}
+
+impl Drop for MockGeminiServer {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+async fn handle_call(
+    State(state): State<SharedState>,
+    Path(call): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let Some((model, method)) = call.rsplit_once(':') else {
+        return api_error(StatusCode::NOT_FOUND, format!("Unknown method: {}", call));
+    };
+    let streaming = match method {
+        "generateContent" => false,
+        "streamGenerateContent" => true,
+        "countTokens" => {
+            let Some(request) = body.get("generate_content_request").or_else(|| body.get("generateContentRequest")) else {
+                return api_error(StatusCode::BAD_REQUEST, "countTokens expects a generateContentRequest".into());
+            };
+            return Json(json!({ "totalTokens": estimate_tokens(&request["contents"].to_string()) })).into_response();
+        }
+        _ => return api_error(StatusCode::NOT_FOUND, format!("Unknown method: {}", method)),
+    };
+
+    let prompt_tokens = estimate_tokens(&body.to_string());
+    let reply = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(RecordedRequest {
+            model: model.to_string(),
+            streaming,
+            authorization: headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            body,
+        });
+        state.replies.pop_front().or_else(|| state.fallback.clone())
+    };
+
+    let chunks = match reply {
+        None => return api_error(StatusCode::INTERNAL_SERVER_ERROR, "MockGeminiServer has no scripted reply".into()),
+        Some(MockReply::Error { status, message }) => {
+            return api_error(StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR), message)
+        }
+        Some(MockReply::Raw(response)) => vec![response],
+        Some(MockReply::FunctionCalls(calls)) => {
+            let parts = calls
+                .iter()
+                .map(|call| json!({ "functionCall": { "name": call.name, "args": call.args } }))
+                .collect();
+            vec![candidate(parts, Some("STOP"), Some((prompt_tokens, 1)))]
+        }
+        Some(MockReply::Text(text)) if streaming => {
+            let words: Vec<&str> = text.split_inclusive(' ').collect();
+            let completion_tokens = estimate_tokens(&text);
+            words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    let last = i + 1 == words.len();
+                    candidate(
+                        vec![json!({ "text": word })],
+                        last.then_some("STOP"),
+                        last.then_some((prompt_tokens, completion_tokens)),
+                    )
+                })
+                .collect()
+        }
+        Some(MockReply::Text(text)) => {
+            let completion_tokens = estimate_tokens(&text);
+            vec![candidate(vec![json!({ "text": text })], Some("STOP"), Some((prompt_tokens, completion_tokens)))]
+        }
+    };
+
+    if !streaming {
+        return Json(chunks.into_iter().next().unwrap_or_default()).into_response();
+    }
+    let body: String = chunks.iter().map(|chunk| format!("data: {}\r\n\r\n", chunk)).collect();
+    ([(header::CONTENT_TYPE, "text/event-stream")], body).into_response()
+}
+
+/// One `generateContent` response in the API's camelCase wire format;
+/// `usage` is `(prompt, completion)` tokens
+fn candidate(parts: Vec<Value>, finish_reason: Option<&str>, usage: Option<(u32, u32)>) -> Value {
+    let mut response = json!({
+        "candidates": [{
+            "content": { "role": "model", "parts": parts },
+            "index": 0,
+        }],
+    });
+    if let Some(finish_reason) = finish_reason {
+        response["candidates"][0]["finishReason"] = json!(finish_reason);
+    }
+    if let Some((prompt, completion)) = usage {
+        response["usageMetadata"] = json!({
+            "promptTokenCount": prompt,
+            "candidatesTokenCount": completion,
+            "totalTokenCount": prompt + completion,
+        });
+    }
+    response
+}
+
+fn estimate_tokens(text: &str) -> u32 {
+    (text.len() as u32).div_ceil(4).max(1)
+}
+
+/// Error body in the shape the Gemini API uses
+fn api_error(status: StatusCode, message: String) -> Response {
+    let body = json!({
+        "error": {
+            "code": status.as_u16(),
+            "message": message,
+            "status": status.canonical_reason().unwrap_or_default().to_uppercase().replace(' ', "_"),
+        }
+    });
+    (status, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::AgentBuilder, LlmAgent},
+        models::{sse::SseDecoder, LlmRequest},
+        runners::Runner,
+        sessions::InMemorySessionService,
+        tools::FunctionTool,
+        types::Content,
+    };
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_agent_runs_tool_round_trip_against_mock() {
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-tool-round-trip").await;
+        mock.push_function_call("get_weather", json!({ "city": "Oslo" }))
+            .push_text("It is 4 degrees in Oslo.");
+
+        let agent = LlmAgent::builder()
+            .name("forecaster")
+            .model("mock-gemini-tool-round-trip")
+            .tool(Arc::new(FunctionTool::new("get_weather", "Current weather", |args| async move {
+                Ok(json!({ "city": args["city"], "celsius": 4 }))
+            })))
+            .build()
+            .unwrap();
+        let runner = Runner::new("app", Arc::new(agent), Arc::new(InMemorySessionService::new()));
+        let events: Vec<_> = runner
+            .run_async("u1".into(), "s1".into(), Content::user_text("Weather in Oslo?"))
+            .await
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(events.last().unwrap().get_text().as_deref(), Some("It is 4 degrees in Oslo."));
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].model, "mock-gemini-tool-round-trip");
+        assert_eq!(requests[0].authorization.as_deref(), Some("Bearer mock-api-key"));
+        assert_eq!(requests[0].last_user_text().as_deref(), Some("Weather in Oslo?"));
+        assert_eq!(requests[0].declared_functions(), ["get_weather"]);
+        assert_eq!(requests[1].function_responses(), [("get_weather".to_string(), json!({ "city": "Oslo", "celsius": 4 }))]);
+
+        mock.push_error(429, "Resource exhausted");
+        let error = mock.llm("gemini-2.0-flash").generate_content(LlmRequest::new("gemini-2.0-flash")).await;
+        assert!(error.unwrap_err().to_string().contains("429"));
+    }
+
+    #[tokio::test]
+    async fn test_streams_text_as_sse_chunks() {
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.set_fallback(MockReply::text("one two three"));
+        let url = format!("{}/models/gemini-2.0-flash:streamGenerateContent?alt=sse", mock.base_url());
+        let body = reqwest::Client::new()
+            .post(url)
+            .json(&json!({ "contents": [] }))
+            .send()
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+
+        let events = SseDecoder::new().feed(&body);
+        let texts: Vec<Value> = events
+            .iter()
+            .map(|event| serde_json::from_str::<Value>(&event.data).unwrap()["candidates"][0]["content"]["parts"][0]["text"].clone())
+            .collect();
+        assert_eq!(texts, [json!("one "), json!("two "), json!("three")]);
+        let last: Value = serde_json::from_str(&events[2].data).unwrap();
+        assert_eq!(last["candidates"][0]["finishReason"], "STOP");
+        assert!(last["usageMetadata"]["totalTokenCount"].as_u64().unwrap() > 0);
+        assert!(mock.requests()[0].streaming);
+    }
+}
@@ -0,0 +1,17 @@
+//! Utilities for behavioral testing of agents
+
+pub mod assertions;
+#[cfg(any(test, feature = "server"))]
+pub mod mock_gemini;
+pub mod simulator;
+pub mod snapshot;
+#[cfg(test)]
+pub(crate) mod strategies;
+
+pub use assertions::{AssertionResult, AssertionSet, ResponseAssertion, DEFAULT_ASSERTIONS_FILE};
+#[cfg(any(test, feature = "server"))]
+pub use mock_gemini::{MockGeminiServer, MockReply, RecordedRequest, MOCK_API_KEY};
+pub use simulator::{
+    SimulatedTurn, Simulation, SimulationOutcome, UserSimulator, DEFAULT_MAX_TURNS, GOAL_COMPLETE_TOKEN,
+};
+pub use snapshot::{assert_event_snapshot, diff_lines, snapshot_path, EventSnapshot, UPDATE_SNAPSHOTS_ENV};
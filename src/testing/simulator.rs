@@ -0,0 +1,355 @@
+//! LLM-driven simulated users for end-to-end agent tests
+
+use crate::{
+    error::Result,
+    events::Event,
+    models::{BaseLlm, LlmRequest},
+    runners::Runner,
+    types::Content,
+};
+use futures::StreamExt;
+use std::sync::Arc;
+use tracing::debug;
+
+/// Token the simulated user replies with once its goal is achieved
+pub const GOAL_COMPLETE_TOKEN: &str = "GOAL_COMPLETE";
+
+/// Default maximum number of user turns in a simulation
+pub const DEFAULT_MAX_TURNS: usize = 10;
+
+/// Why a simulated conversation ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulationOutcome {
+    /// The simulated user declared its goal complete
+    GoalReached,
+    /// A custom stop condition matched a turn
+    ConditionMet,
+    /// The turn limit was reached first
+    MaxTurns,
+}
+
+/// One exchange between the simulated user and the agent
+#[derive(Debug, Clone)]
+pub struct SimulatedTurn {
+    pub user_message: String,
+    pub agent_reply: String,
+
+    /// Complete (non-partial) events produced by the agent
+    pub events: Vec<Arc<Event>>,
+}
+
+type StopCondition = Box<dyn Fn(&SimulatedTurn) -> bool + Send + Sync>;
+
+/// Persona with a goal script that converses with an agent through a runner
+pub struct UserSimulator {
+    model: Arc<dyn BaseLlm>,
+    persona: String,
+    goals: Vec<String>,
+    opening_message: Option<String>,
+    max_turns: usize,
+    stop_conditions: Vec<StopCondition>,
+}
+
+impl UserSimulator {
+    /// Create a simulator driven by `model` playing the described persona
+    pub fn new(model: Arc<dyn BaseLlm>, persona: impl Into<String>) -> Self {
+        Self {
+            model,
+            persona: persona.into(),
+            goals: Vec::new(),
+            opening_message: None,
+            max_turns: DEFAULT_MAX_TURNS,
+            stop_conditions: Vec::new(),
+        }
+    }
+
+    /// Append a step to the goal script, pursued in order
+    pub fn goal(mut self, goal: impl Into<String>) -> Self {
+        self.goals.push(goal.into());
+        self
+    }
+
+    /// Send a fixed first message instead of generating one
+    pub fn opening_message(mut self, message: impl Into<String>) -> Self {
+        self.opening_message = Some(message.into());
+        self
+    }
+
+    pub fn max_turns(mut self, max_turns: usize) -> Self {
+        self.max_turns = max_turns;
+        self
+    }
+
+    /// End the conversation as soon as a turn satisfies `condition`
+    pub fn stop_when(mut self, condition: impl Fn(&SimulatedTurn) -> bool + Send + Sync + 'static) -> Self {
+        self.stop_conditions.push(Box::new(condition));
+        self
+    }
+
+    fn system_prompt(&self) -> String {
+        let script = self
+            .goals
+            .iter()
+            .enumerate()
+            .map(|(i, goal)| format!("{}. {}", i + 1, goal))
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            "You are role-playing a user talking to an AI assistant. Persona: {}\n\
+             Pursue these goals in order:\n{}\n\
+             Write only the user's next message. When every goal is achieved, reply with exactly {}.",
+            self.persona, script, GOAL_COMPLETE_TOKEN
+        )
+    }
+
+    /// Generate the next user message from the transcript so far.
+    ///
+    /// Roles are swapped: the simulator is the model and the agent is its user.
+    async fn next_message(&self, turns: &[SimulatedTurn]) -> Result<String> {
+        let mut request = LlmRequest::new(self.model.model_name())
+            .add_content(Content::user_text(format!("System: {}", self.system_prompt())));
+        for turn in turns {
+            request = request
+                .add_content(Content::model_text(turn.user_message.clone()))
+                .add_content(Content::user_text(turn.agent_reply.clone()));
+        }
+        let response = self.model.generate_content(request).await?;
+        Ok(response.get_text().unwrap_or_default().trim().to_string())
+    }
+
+    /// Converse with the runner's agent in a fresh session until a termination condition
+    pub async fn run(&self, runner: &Runner) -> Result<Simulation> {
+        let session_id = runner.new_session_id();
+        let mut turns: Vec<SimulatedTurn> = Vec::new();
+
+        while turns.len() < self.max_turns {
+            let user_message = match (&self.opening_message, turns.is_empty()) {
+                (Some(opening), true) => opening.clone(),
+                _ => self.next_message(&turns).await?,
+            };
+            if user_message.contains(GOAL_COMPLETE_TOKEN) {
+                return Ok(Simulation::new(session_id, turns, SimulationOutcome::GoalReached));
+            }
+            debug!("Simulated user turn {}: {}", turns.len() + 1, user_message);
+
+            let mut stream = runner
+                .run_async(
+                    "simulated_user".to_string(),
+                    session_id.clone(),
+                    Content::user_text(user_message.clone()),
+                )
+                .await?;
+            let mut events = Vec::new();
+            while let Some(event) = stream.next().await {
+                let event = event?;
+                if !event.is_partial {
+                    events.push(event);
+                }
+            }
+
+            let agent_reply = events
+                .iter()
+                .filter_map(|event| event.get_text())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let turn = SimulatedTurn {
+                user_message,
+                agent_reply,
+                events,
+            };
+            let stop = self.stop_conditions.iter().any(|condition| condition(&turn));
+            turns.push(turn);
+            if stop {
+                return Ok(Simulation::new(session_id, turns, SimulationOutcome::ConditionMet));
+            }
+        }
+
+        Ok(Simulation::new(session_id, turns, SimulationOutcome::MaxTurns))
+    }
+}
+
+/// Transcript and outcome of a simulated conversation
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub session_id: String,
+    pub turns: Vec<SimulatedTurn>,
+    pub outcome: SimulationOutcome,
+}
+
+impl Simulation {
+    fn new(session_id: String, turns: Vec<SimulatedTurn>, outcome: SimulationOutcome) -> Self {
+        Self {
+            session_id,
+            turns,
+            outcome,
+        }
+    }
+
+    /// Render the conversation as `user:` / `agent:` lines
+    pub fn transcript(&self) -> String {
+        self.turns
+            .iter()
+            .map(|turn| format!("user: {}\nagent: {}", turn.user_message, turn.agent_reply))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Panic unless the simulated user reported its goal complete
+    pub fn assert_goal_reached(&self) {
+        assert_eq!(
+            self.outcome,
+            SimulationOutcome::GoalReached,
+            "simulated user did not reach its goal:\n{}",
+            self.transcript()
+        );
+    }
+
+    /// Panic unless the conversation ended within `max_turns` user turns
+    pub fn assert_finished_within(&self, max_turns: usize) {
+        assert!(
+            self.outcome != SimulationOutcome::MaxTurns && self.turns.len() <= max_turns,
+            "conversation did not finish within {} turns:\n{}",
+            max_turns,
+            self.transcript()
+        );
+    }
+
+    /// Panic unless some agent reply contains `text`
+    pub fn assert_agent_said(&self, text: &str) {
+        assert!(
+            self.turns.iter().any(|turn| turn.agent_reply.contains(text)),
+            "no agent reply contained {:?}:\n{}",
+            text,
+            self.transcript()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::EventStream, BaseAgent, InvocationContext},
+        models::LlmResponse,
+        sessions::InMemorySessionService,
+        types::{AgentId, Metadata},
+    };
+    use async_trait::async_trait;
+    use futures::Stream;
+    use std::{collections::HashMap, pin::Pin};
+
+    /// Plays a customer who asks for a refund, then confirms
+    struct CustomerLlm;
+
+    #[async_trait]
+    impl BaseLlm for CustomerLlm {
+        fn model_name(&self) -> &str {
+            "customer"
+        }
+
+        fn supported_models() -> Vec<String> {
+            vec!["customer".to_string()]
+        }
+
+        async fn generate_content(&self, request: LlmRequest) -> Result<LlmResponse> {
+            let last = request.contents.last().map(Content::get_text).unwrap_or_default();
+            let reply = if last.contains("refund issued") {
+                GOAL_COMPLETE_TOKEN
+            } else {
+                "My order 42 arrived broken, I want a refund"
+            };
+            Ok(LlmResponse::text(reply))
+        }
+
+        async fn generate_content_stream(
+            &self,
+            request: LlmRequest,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<LlmResponse>> + Send>>> {
+            let response = self.generate_content(request).await?;
+            Ok(Box::pin(futures::stream::once(async move { Ok(response) })))
+        }
+    }
+
+    /// Issues a refund once an order number is mentioned
+    struct SupportAgent {
+        id: AgentId,
+        metadata: Metadata,
+    }
+
+    #[async_trait]
+    impl BaseAgent for SupportAgent {
+        fn id(&self) -> &AgentId {
+            &self.id
+        }
+
+        fn name(&self) -> &str {
+            "support"
+        }
+
+        fn description(&self) -> &str {
+            "Customer support"
+        }
+
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+
+        fn parent(&self) -> Option<&dyn BaseAgent> {
+            None
+        }
+
+        fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+            &[]
+        }
+
+        async fn run_async(&self, ctx: InvocationContext) -> Result<EventStream> {
+            let session = ctx
+                .session_service
+                .get_session(&ctx.app_name, &ctx.user_id, &ctx.session_id)
+                .await?;
+            let message = session
+                .and_then(|s| s.events.last().and_then(|event| event.get_text()))
+                .unwrap_or_default();
+            let reply = if message.contains("42") {
+                "Sorry about that, refund issued for order 42."
+            } else {
+                "How can I help?"
+            };
+            let event = Event::text_response("support", reply);
+            Ok(Box::pin(futures::stream::once(async move { Ok(Arc::new(event)) })))
+        }
+
+        async fn run_live(&self, ctx: InvocationContext) -> Result<EventStream> {
+            self.run_async(ctx).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulated_user_reaches_goal() {
+        let agent = Arc::new(SupportAgent {
+            id: "support".to_string(),
+            metadata: HashMap::new(),
+        });
+        let runner = Runner::new("support_app", agent, Arc::new(InMemorySessionService::new()));
+
+        let simulation = UserSimulator::new(Arc::new(CustomerLlm), "An annoyed customer")
+            .goal("Get a refund for order 42")
+            .opening_message("Hi")
+            .max_turns(5)
+            .run(&runner)
+            .await
+            .unwrap();
+
+        simulation.assert_goal_reached();
+        simulation.assert_finished_within(2);
+        simulation.assert_agent_said("refund issued");
+        assert_eq!(simulation.turns.len(), 2);
+
+        let stopped = UserSimulator::new(Arc::new(CustomerLlm), "An annoyed customer")
+            .stop_when(|turn| turn.agent_reply.contains("refund"))
+            .run(&runner)
+            .await
+            .unwrap();
+        assert_eq!(stopped.outcome, SimulationOutcome::ConditionMet);
+    }
+}
@@ -0,0 +1,188 @@
+//! Snapshot assertions for agent event streams
+
+use crate::{error::Result, events::Event};
+use serde_json::Value;
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable that rewrites snapshots instead of comparing against them
+pub const UPDATE_SNAPSHOTS_ENV: &str = "UPDATE_SNAPSHOTS";
+
+/// Directory, relative to the crate root, holding snapshots for `assert_event_snapshot`
+pub const SNAPSHOT_DIR: &str = "tests/snapshots";
+
+/// Event stream normalized for stable comparison.
+///
+/// Event IDs and timestamps are dropped, and invocation IDs are replaced with
+/// `invocation-N` in order of first appearance so shared invocations stay visible.
+#[derive(Debug, Clone)]
+pub struct EventSnapshot {
+    events: Vec<Value>,
+}
+
+impl EventSnapshot {
+    pub fn new<E: Borrow<Event>>(events: &[E]) -> Self {
+        let mut invocations: HashMap<String, String> = HashMap::new();
+        let events = events
+            .iter()
+            .map(|event| {
+                let mut value = serde_json::to_value(event.borrow()).unwrap_or(Value::Null);
+                if let Value::Object(fields) = &mut value {
+                    fields.remove("id");
+                    fields.remove("timestamp");
+                    if let Some(invocation) = fields.get_mut("invocation_id") {
+                        let next = format!("invocation-{}", invocations.len() + 1);
+                        let placeholder = invocations
+                            .entry(invocation.to_string())
+                            .or_insert(next)
+                            .clone();
+                        *invocation = Value::String(placeholder);
+                    }
+                }
+                value
+            })
+            .collect();
+        Self { events }
+    }
+
+    /// Drop a volatile metadata key (e.g. a config hash) from every event
+    pub fn ignore_metadata(mut self, key: &str) -> Self {
+        for event in &mut self.events {
+            if let Some(Value::Object(metadata)) = event.get_mut("metadata") {
+                metadata.remove(key);
+            }
+        }
+        self
+    }
+
+    /// Compare events as a set, for agents such as `ParallelAgent` that interleave output
+    pub fn unordered(mut self) -> Self {
+        self.events.sort_by_cached_key(|event| event.to_string());
+        self
+    }
+
+    /// Deterministic pretty JSON (object keys are sorted)
+    pub fn render(&self) -> String {
+        let mut rendered = serde_json::to_string_pretty(&self.events).unwrap_or_default();
+        rendered.push('\n');
+        rendered
+    }
+
+    /// Compare against the snapshot at `path`, writing it when missing or when
+    /// `UPDATE_SNAPSHOTS` is set
+    pub fn check(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let actual = self.render();
+        let update = std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some();
+
+        if update || !path.exists() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, actual)?;
+            return Ok(());
+        }
+
+        let expected = std::fs::read_to_string(path)?;
+        if expected == actual {
+            return Ok(());
+        }
+        Err(crate::adk_error!(
+            EvaluationError,
+            "Snapshot {} does not match (rerun with {}=1 to accept):\n{}",
+            path.display(),
+            UPDATE_SNAPSHOTS_ENV,
+            diff_lines(&expected, &actual)
+        ))
+    }
+
+    /// Panic with a readable diff unless the snapshot at `path` matches
+    pub fn assert_matches(&self, path: impl AsRef<Path>) {
+        if let Err(e) = self.check(path) {
+            panic!("{}", e);
+        }
+    }
+}
+
+/// Path of a named snapshot under the crate's `tests/snapshots` directory
+pub fn snapshot_path(name: &str) -> PathBuf {
+    let root = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_default();
+    root.join(SNAPSHOT_DIR).join(format!("{}.snap.json", name))
+}
+
+/// Assert that events match the named snapshot, creating it on first run
+pub fn assert_event_snapshot<E: Borrow<Event>>(name: &str, events: &[E]) {
+    EventSnapshot::new(events).assert_matches(snapshot_path(name));
+}
+
+/// Line diff of two texts with `-` (expected only), `+` (actual only) and ` ` prefixes
+pub fn diff_lines(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // Longest common subsequence lengths of the suffixes
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            diff.push(format!("+ {}", new[j]));
+            j += 1;
+        } else {
+            diff.push(format!("- {}", old[i]));
+            i += 1;
+        }
+    }
+    diff.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_normalizes_and_diffs() {
+        let invocation = uuid::Uuid::new_v4();
+        let events = vec![
+            Event::user_input("hi", invocation),
+            Event::text_response("greeter", "Hello!"),
+        ];
+        let snapshot = EventSnapshot::new(&events);
+        let rendered = snapshot.render();
+        assert!(!rendered.contains(&events[0].id));
+        assert!(rendered.contains("\"invocation_id\": \"invocation-1\""));
+        assert_eq!(rendered, EventSnapshot::new(&events).render());
+
+        let path = std::env::temp_dir()
+            .join(format!("adk-snapshot-{}", uuid::Uuid::new_v4()))
+            .join("greeting.snap.json");
+        snapshot.check(&path).unwrap();
+        snapshot.check(&path).unwrap();
+
+        let changed = vec![
+            Event::user_input("hi", invocation),
+            Event::text_response("greeter", "Goodbye!"),
+        ];
+        let error = EventSnapshot::new(&changed).check(&path).unwrap_err().to_string();
+        assert!(error.contains("- ") && error.contains("Hello!"));
+        assert!(error.contains("+ ") && error.contains("Goodbye!"));
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,67 @@
+//! Proptest strategies for the crate's conversion tests
+
+use crate::types::{CodeExecutionOutcome, Content, ContentPart};
+use proptest::prelude::*;
+use serde_json::Value;
+
+/// JSON values, with keys that collide with the field names of provider payloads
+pub(crate) fn arb_json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<String>().prop_map(Value::from),
+    ];
+    leaf.prop_recursive(4, 32, 6, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6).prop_map(Value::from),
+            prop::collection::btree_map(
+                prop_oneof!["text|role|parts|name|args|function_call|content|candidates", "[a-z_]{1,8}"],
+                inner,
+                0..6
+            )
+            .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+fn arb_outcome() -> impl Strategy<Value = CodeExecutionOutcome> {
+    prop_oneof![
+        Just(CodeExecutionOutcome::Ok),
+        Just(CodeExecutionOutcome::Failed),
+        Just(CodeExecutionOutcome::DeadlineExceeded),
+        Just(CodeExecutionOutcome::Unspecified),
+    ]
+}
+
+/// Parts a model can produce: text, function calls and code execution
+pub(crate) fn arb_model_part() -> impl Strategy<Value = ContentPart> {
+    prop_oneof![
+        any::<String>().prop_map(ContentPart::text),
+        ("[a-zA-Z_][a-zA-Z0-9_]{0,15}", arb_json()).prop_map(|(name, args)| ContentPart::FunctionCall { name, args }),
+        ("[A-Z]{1,8}", any::<String>()).prop_map(|(language, code)| ContentPart::ExecutableCode { language, code }),
+        (arb_outcome(), any::<String>()).prop_map(|(outcome, output)| ContentPart::CodeExecutionResult { outcome, output }),
+    ]
+}
+
+/// Any part, including inline and referenced media
+pub(crate) fn arb_part() -> impl Strategy<Value = ContentPart> {
+    prop_oneof![
+        arb_model_part(),
+        ("[a-z_]{1,12}", arb_json()).prop_map(|(name, response)| ContentPart::FunctionResponse { name, response }),
+        (prop::collection::vec(any::<u8>(), 0..64), "image/(png|jpeg)")
+            .prop_map(|(data, mime_type)| ContentPart::image(data, mime_type)),
+        (prop::collection::vec(any::<u8>(), 0..64), "[a-z]{1,8}\\.pdf").prop_map(|(data, filename)| ContentPart::File {
+            data: data.into(),
+            mime_type: "application/pdf".to_string(),
+            filename,
+        }),
+        ("gs://[a-z]{1,8}/[a-z]{1,8}", "audio/(ogg|pcm)")
+            .prop_map(|(uri, mime_type)| ContentPart::FileRef { uri, mime_type }),
+    ]
+}
+
+pub(crate) fn arb_content() -> impl Strategy<Value = Content> {
+    (prop_oneof![Just("user"), Just("model")], prop::collection::vec(arb_part(), 0..6))
+        .prop_map(|(role, parts)| Content { role: role.to_string(), parts })
+}
@@ -2,6 +2,7 @@
 
 use crate::{
     error::Result,
+    tools::ToolContext,
     types::FunctionDeclaration,
 };
 use async_trait::async_trait;
@@ -27,4 +28,9 @@ pub trait BaseTool: Send + Sync {
         &self,
         args: HashMap<String, Value>,
     ) -> Result<Value>;
+
+    /// Run the tool within an agent; tools that use session state override this
+    async fn run_with_context(&self, args: HashMap<String, Value>, _ctx: &ToolContext) -> Result<Value> {
+        self.run_async(args).await
+    }
 }
@@ -1,8 +1,9 @@
 //! Function tool implementation
 
 use crate::{
+    agents::is_deterministic,
     error::Result,
-    tools::BaseTool,
+    tools::{BaseTool, StateAccessPolicy, ToolContext},
     types::FunctionDeclaration,
 };
 use async_trait::async_trait;
@@ -16,12 +17,27 @@ pub type AsyncToolFunction = Arc<
         + Sync,
 >;
 
+/// Type alias for async function that also receives the tool context
+pub type ContextualToolFunction = Arc<
+    dyn Fn(HashMap<String, Value>, ToolContext) -> Pin<Box<dyn Future<Output = Result<Value>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// The wrapped function, with or without access to the tool context
+#[derive(Clone)]
+enum ToolFunction {
+    Plain(AsyncToolFunction),
+    Contextual(ContextualToolFunction),
+}
+
 /// Tool that wraps a user-defined function
 pub struct FunctionTool {
     name: String,
     description: String,
-    function: AsyncToolFunction,
+    function: ToolFunction,
     declaration: Option<FunctionDeclaration>,
+    mock_response: Option<Value>,
 }
 
 impl FunctionTool {
@@ -35,9 +51,31 @@ impl FunctionTool {
         F: Fn(HashMap<String, Value>) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = Result<Value>> + Send + 'static,
     {
-        let name = name.into();
-        let description = description.into();
-        
+        Self::from_function(
+            name.into(),
+            description.into(),
+            ToolFunction::Plain(Arc::new(move |args| Box::pin(function(args)))),
+        )
+    }
+
+    /// Create a tool whose function reads and writes session state through its [`ToolContext`]
+    pub fn with_context<F, Fut>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        function: F,
+    ) -> Self
+    where
+        F: Fn(HashMap<String, Value>, ToolContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value>> + Send + 'static,
+    {
+        Self::from_function(
+            name.into(),
+            description.into(),
+            ToolFunction::Contextual(Arc::new(move |args, ctx| Box::pin(function(args, ctx)))),
+        )
+    }
+
+    fn from_function(name: String, description: String, function: ToolFunction) -> Self {
         // Create the declaration
         let declaration = FunctionDeclaration {
             name: name.clone(),
@@ -52,8 +90,9 @@ impl FunctionTool {
         Self {
             name,
             description,
-            function: Arc::new(move |args| Box::pin(function(args))),
+            function,
             declaration: Some(declaration),
+            mock_response: None,
         }
     }
 
@@ -62,6 +101,12 @@ impl FunctionTool {
         self.declaration = Some(declaration);
         self
     }
+
+    /// Fixed result returned instead of calling the function during deterministic runs
+    pub fn with_mock_response(mut self, response: Value) -> Self {
+        self.mock_response = Some(response);
+        self
+    }
 }
 
 impl std::fmt::Debug for FunctionTool {
@@ -91,6 +136,26 @@ impl BaseTool for FunctionTool {
         &self,
         args: HashMap<String, Value>,
     ) -> Result<Value> {
-        (self.function)(args).await
+        // Outside an agent there is no session state to share
+        let ctx = ToolContext::new(
+            &self.name,
+            "",
+            uuid::Uuid::nil(),
+            String::new(),
+            String::new(),
+            Default::default(),
+            StateAccessPolicy::none(),
+        );
+        self.run_with_context(args, &ctx).await
+    }
+
+    async fn run_with_context(&self, args: HashMap<String, Value>, ctx: &ToolContext) -> Result<Value> {
+        if let Some(mock) = self.mock_response.as_ref().filter(|_| is_deterministic()) {
+            return Ok(mock.clone());
+        }
+        match &self.function {
+            ToolFunction::Plain(function) => function(args).await,
+            ToolFunction::Contextual(function) => function(args, ctx.clone()).await,
+        }
     }
 }
@@ -1,12 +1,13 @@
 //! Google Search tool implementation
 
 use crate::{
+    agents::is_deterministic,
     error::Result,
     tools::{BaseTool, FunctionTool},
     types::FunctionDeclaration,
 };
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use serde_json::Value;
 use std::{collections::HashMap, sync::Arc, time::Duration};
 use tracing::{debug, error, warn};
@@ -126,10 +127,13 @@ pub fn google_search() -> Arc<dyn BaseTool> {
                 .and_then(|v| v.as_str())
                 .ok_or_else(|| crate::adk_error!(ToolError, "Missing 'query' parameter"))?;
 
-            // Check if we have API credentials
-            if std::env::var("GOOGLE_SEARCH_API_KEY").is_err() &&
-               std::env::var("GOOGLE_API_KEY").is_err() {
-                warn!("Google Search API key not configured, returning mock results");
+            // Use mock results without API credentials, or when replaying deterministically
+            let deterministic = is_deterministic();
+            if deterministic || (std::env::var("GOOGLE_SEARCH_API_KEY").is_err() &&
+               std::env::var("GOOGLE_API_KEY").is_err()) {
+                if !deterministic {
+                    warn!("Google Search API key not configured, returning mock results");
+                }
 
                 // Return mock response when API key is not available
                 return Ok(serde_json::json!({
@@ -3,7 +3,23 @@
 pub mod base_tool;
 pub mod function_tool;
 pub mod google_search_tool;
+pub mod moderation_tool;
+pub mod policy;
+#[cfg(feature = "python")]
+pub mod python_tool;
+pub mod submit_answer_tool;
+pub mod tool_context;
+#[cfg(feature = "wasm")]
+pub mod wasm_tool;
 
 pub use base_tool::BaseTool;
 pub use function_tool::FunctionTool;
 pub use google_search_tool::{google_search, google_search_with_config};
+pub use moderation_tool::{moderate_content, MODERATE_CONTENT_TOOL_NAME};
+pub use policy::{global_tool_defaults, EffectiveToolPolicy, ToolDefaults, ToolDefaultsConfig, ToolPolicy};
+#[cfg(feature = "python")]
+pub use python_tool::{PyFunctionTool, PyFunctionToolBuilder};
+pub use submit_answer_tool::{submit_answer, SUBMIT_ANSWER_TOOL_NAME};
+pub use tool_context::{StateAccess, StateAccessPolicy, ToolContext};
+#[cfg(feature = "wasm")]
+pub use wasm_tool::{WasmLimits, WasmTool, WasmToolManifest};
@@ -0,0 +1,54 @@
+//! Content moderation tool
+
+use crate::{
+    models::moderation::{ModerationPolicy, Moderator},
+    tools::{BaseTool, FunctionTool},
+    types::FunctionDeclaration,
+};
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::Arc};
+
+/// Name of the content moderation tool
+pub const MODERATE_CONTENT_TOOL_NAME: &str = "moderate_content";
+
+/// Create a `moderate_content` tool that scores text with `moderator` and
+/// reports the categories `policy` flags, with the refusal to use.
+pub fn moderate_content(moderator: Arc<dyn Moderator>, policy: ModerationPolicy) -> Arc<dyn BaseTool> {
+    let policy = Arc::new(policy);
+    let tool = FunctionTool::new(
+        MODERATE_CONTENT_TOOL_NAME,
+        "Check text for harmful content",
+        move |args: HashMap<String, Value>| {
+            let moderator = moderator.clone();
+            let policy = policy.clone();
+            async move {
+                let text = args
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| crate::adk_error!(ToolError, "moderate_content requires a 'text' argument"))?;
+                let result = moderator.moderate(text).await?;
+                let violations = policy.violations(&result);
+                Ok(json!({
+                    "flagged": !violations.is_empty(),
+                    "scores": result.scores,
+                    "refusal": violations.first().map(|violation| policy.refusal(violation)),
+                    "violations": violations,
+                }))
+            }
+        },
+    )
+    .with_declaration(FunctionDeclaration {
+        name: MODERATE_CONTENT_TOOL_NAME.to_string(),
+        description: "Check text for harmful content. If the result is flagged, reply with the returned refusal."
+            .to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "text": {"type": "string", "description": "The text to check"}
+            },
+            "required": ["text"]
+        }),
+    });
+
+    Arc::new(tool)
+}
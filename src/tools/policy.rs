@@ -0,0 +1,371 @@
+//! Layered tool execution policies
+//!
+//! Timeouts, retries, confirmation and output summarization of tool calls
+//! can be set for the whole app, for an agent, and for individual tools.
+//! Each level overrides the fields it sets, and LLM agents resolve the
+//! result into one [`EffectiveToolPolicy`] per tool when they are built.
+//! App-wide settings come from the `[tool_defaults]` section of `adk.toml`:
+//!
+//! ```toml
+//! [tool_defaults]
+//! timeout_seconds = 30
+//! retries = 1
+//!
+//! [tool_defaults.agents.support]
+//! timeout_seconds = 10
+//!
+//! [tool_defaults.tools.send_email]
+//! require_confirmation = true
+//! ```
+//!
+//! `adk doctor` prints the resolved policies.
+
+use crate::{
+    error::Result,
+    models::profiles::{CONFIG_PATH_ENV_VAR, DEFAULT_CONFIG_FILE},
+    tools::{BaseTool, ToolContext},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tracing::{debug, warn};
+
+/// Tool policy settings of one level; unset fields fall through to the level above
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolPolicy {
+    /// Limit on each attempt of a call
+    pub timeout_seconds: Option<u64>,
+
+    /// Pause each call until approved through the debugger API
+    pub require_confirmation: Option<bool>,
+
+    /// Outputs longer than this many characters are summarized by the agent's model
+    pub summarize_over_chars: Option<usize>,
+
+    /// Extra attempts after a failed call
+    pub retries: Option<u32>,
+}
+
+impl ToolPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, seconds: u64) -> Self {
+        self.timeout_seconds = Some(seconds);
+        self
+    }
+
+    pub fn with_confirmation(mut self, required: bool) -> Self {
+        self.require_confirmation = Some(required);
+        self
+    }
+
+    pub fn with_summarize_over(mut self, chars: usize) -> Self {
+        self.summarize_over_chars = Some(chars);
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// This level on top of `base`
+    pub fn over(&self, base: &ToolPolicy) -> ToolPolicy {
+        ToolPolicy {
+            timeout_seconds: self.timeout_seconds.or(base.timeout_seconds),
+            require_confirmation: self.require_confirmation.or(base.require_confirmation),
+            summarize_over_chars: self.summarize_over_chars.or(base.summarize_over_chars),
+            retries: self.retries.or(base.retries),
+        }
+    }
+
+    /// Fill in built-in defaults for fields no level set
+    pub fn resolve(&self) -> EffectiveToolPolicy {
+        EffectiveToolPolicy {
+            timeout_seconds: self.timeout_seconds,
+            require_confirmation: self.require_confirmation.unwrap_or(false),
+            summarize_over_chars: self.summarize_over_chars,
+            retries: self.retries.unwrap_or(0),
+        }
+    }
+}
+
+/// The policy a tool call runs under
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectiveToolPolicy {
+    /// No limit when unset
+    pub timeout_seconds: Option<u64>,
+    pub require_confirmation: bool,
+
+    /// Outputs are passed on verbatim when unset
+    pub summarize_over_chars: Option<usize>,
+    pub retries: u32,
+}
+
+impl EffectiveToolPolicy {
+    /// Run a tool, bounding each attempt by the timeout and retrying failures
+    pub async fn run(&self, tool: &dyn BaseTool, args: HashMap<String, Value>, ctx: &ToolContext) -> Result<Value> {
+        let mut attempt = 0;
+        loop {
+            let call = tool.run_with_context(args.clone(), ctx);
+            let result = match self.timeout_seconds {
+                Some(seconds) => tokio::time::timeout(Duration::from_secs(seconds), call)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(crate::adk_error!(
+                            TimeoutError,
+                            "Tool '{}' did not finish within {}s",
+                            tool.name(),
+                            seconds
+                        ))
+                    }),
+                None => call.await,
+            };
+            match result {
+                Err(e) if attempt < self.retries => {
+                    attempt += 1;
+                    warn!("Tool '{}' failed, retrying ({}/{}): {}", tool.name(), attempt, self.retries, e);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Whether an output is long enough to be summarized
+    pub fn should_summarize(&self, output: &Value) -> bool {
+        self.summarize_over_chars
+            .is_some_and(|limit| output.to_string().chars().count() > limit)
+    }
+}
+
+impl std::fmt::Display for EffectiveToolPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.timeout_seconds {
+            Some(seconds) => write!(f, "timeout {}s", seconds)?,
+            None => write!(f, "no timeout")?,
+        }
+        write!(f, ", retries {}", self.retries)?;
+        if self.require_confirmation {
+            write!(f, ", confirmation required")?;
+        }
+        if let Some(chars) = self.summarize_over_chars {
+            write!(f, ", summarized over {} chars", chars)?;
+        }
+        Ok(())
+    }
+}
+
+/// App-wide tool defaults with agent and per-tool overrides
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolDefaultsConfig {
+    #[serde(flatten)]
+    pub app: ToolPolicy,
+
+    /// Defaults of all tools of an agent, by agent name
+    pub agents: BTreeMap<String, ToolPolicy>,
+
+    /// Overrides of single tools, by tool name
+    pub tools: BTreeMap<String, ToolPolicy>,
+}
+
+impl ToolDefaultsConfig {
+    /// Resolve a tool's policy; agent and tool levels set in code override the same configured level
+    pub fn resolve(
+        &self,
+        agent: &str,
+        agent_policy: &ToolPolicy,
+        tool: &str,
+        tool_policy: &ToolPolicy,
+    ) -> EffectiveToolPolicy {
+        let empty = ToolPolicy::default();
+        let agent_level = agent_policy.over(self.agents.get(agent).unwrap_or(&empty));
+        let tool_level = tool_policy.over(self.tools.get(tool).unwrap_or(&empty));
+        tool_level.over(&agent_level.over(&self.app)).resolve()
+    }
+}
+
+/// On-disk layout of the tool defaults section of `adk.toml`
+#[derive(Debug, Default, Deserialize)]
+struct ToolDefaultsFile {
+    #[serde(default)]
+    tool_defaults: ToolDefaultsConfig,
+}
+
+/// Shared tool defaults
+#[derive(Debug, Clone, Default)]
+pub struct ToolDefaults {
+    config: Arc<RwLock<ToolDefaultsConfig>>,
+}
+
+impl ToolDefaults {
+    pub fn new(config: ToolDefaultsConfig) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+        }
+    }
+
+    /// Load the `[tool_defaults]` section of a config file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let file: ToolDefaultsFile = config::Config::builder()
+            .add_source(config::File::from(path.as_ref()).format(config::FileFormat::Toml))
+            .build()?
+            .try_deserialize()?;
+        Ok(Self::new(file.tool_defaults))
+    }
+
+    /// Load from `$ADK_CONFIG` or `./adk.toml`, if present
+    pub fn load_default() -> Result<Self> {
+        let path = std::env::var(CONFIG_PATH_ENV_VAR).unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+        if Path::new(&path).exists() {
+            Self::from_file(path)
+        } else {
+            debug!("No {} found, tools run without default policies", path);
+            Ok(Self::default())
+        }
+    }
+
+    pub fn snapshot(&self) -> ToolDefaultsConfig {
+        self.config.read().expect("tool defaults lock poisoned").clone()
+    }
+
+    /// Replace the defaults; agents built afterwards pick them up
+    pub fn set(&self, config: ToolDefaultsConfig) {
+        *self.config.write().expect("tool defaults lock poisoned") = config;
+    }
+
+    pub fn resolve(&self, agent: &str, agent_policy: &ToolPolicy, tool: &str, tool_policy: &ToolPolicy) -> EffectiveToolPolicy {
+        self.config
+            .read()
+            .expect("tool defaults lock poisoned")
+            .resolve(agent, agent_policy, tool, tool_policy)
+    }
+}
+
+/// Global tool defaults, loaded from `adk.toml` on first use
+static GLOBAL_TOOL_DEFAULTS: once_cell::sync::Lazy<ToolDefaults> = once_cell::sync::Lazy::new(|| {
+    ToolDefaults::load_default().unwrap_or_else(|e| {
+        warn!("Failed to load tool defaults: {}", e);
+        ToolDefaults::default()
+    })
+});
+
+/// Get the global tool defaults
+pub fn global_tool_defaults() -> &'static ToolDefaults {
+    &GLOBAL_TOOL_DEFAULTS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{FunctionTool, StateAccessPolicy};
+    use std::{
+        io::Write,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    #[test]
+    fn test_levels_override_in_order() {
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        writeln!(
+            file,
+            r#"
+[tool_defaults]
+timeout_seconds = 30
+retries = 1
+
+[tool_defaults.agents.support]
+timeout_seconds = 10
+summarize_over_chars = 2000
+
+[tool_defaults.tools.send_email]
+require_confirmation = true
+retries = 0
+"#
+        )
+        .unwrap();
+        let defaults = ToolDefaults::from_file(file.path()).unwrap();
+        let none = ToolPolicy::default();
+
+        let email = defaults.resolve("support", &none, "send_email", &none);
+        assert_eq!(
+            email,
+            EffectiveToolPolicy {
+                timeout_seconds: Some(10),
+                require_confirmation: true,
+                summarize_over_chars: Some(2000),
+                retries: 0,
+            }
+        );
+        let search = defaults.resolve("research", &none, "search", &none);
+        assert_eq!((search.timeout_seconds, search.retries, search.require_confirmation), (Some(30), 1, false));
+
+        // Levels set in code override the configured ones
+        let agent = ToolPolicy::new().with_timeout(5);
+        let tool = ToolPolicy::new().with_confirmation(false);
+        let email = defaults.resolve("support", &agent, "send_email", &tool);
+        assert_eq!((email.timeout_seconds, email.require_confirmation), (Some(5), false));
+    }
+
+    #[test]
+    fn test_agents_resolve_policies_at_build_time() {
+        use crate::agents::{base_agent::AgentBuilder, LlmAgent};
+
+        let lookup = FunctionTool::new("lookup", "Look up an order", |_| async { Ok(Value::Null) });
+        let agent = LlmAgent::builder()
+            .name("support")
+            .model("gemini-2.0-flash")
+            .tool(Arc::new(lookup))
+            .tool_defaults(ToolPolicy::new().with_timeout(20).with_retries(1))
+            .tool_policy("lookup", ToolPolicy::new().with_retries(3))
+            .build()
+            .unwrap();
+        let policy = &agent.tool_policies()["lookup"];
+        assert_eq!((policy.timeout_seconds, policy.retries), (Some(20), 3));
+        assert_eq!(policy.to_string(), "timeout 20s, retries 3");
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_and_times_out() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let flaky = FunctionTool::new("flaky", "Fails once", move |_| {
+            let counter = counter.clone();
+            async move {
+                match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(crate::adk_error!(ToolError, "temporarily unavailable")),
+                    _ => Ok(serde_json::json!("ok")),
+                }
+            }
+        });
+        let ctx = ToolContext::new(
+            "flaky",
+            "helper",
+            uuid::Uuid::nil(),
+            "s1".into(),
+            "u1".into(),
+            Default::default(),
+            StateAccessPolicy::none(),
+        );
+        let policy = ToolPolicy::new().with_retries(1).resolve();
+        assert_eq!(policy.run(&flaky, HashMap::new(), &ctx).await.unwrap(), "ok");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let slow = FunctionTool::new("slow", "Never finishes", |_| async {
+            std::future::pending::<()>().await;
+            Ok(Value::Null)
+        });
+        let policy = ToolPolicy::new().with_timeout(0).resolve();
+        let err = policy.run(&slow, HashMap::new(), &ctx).await.unwrap_err();
+        assert!(matches!(err, crate::error::AdkError::TimeoutError(_)));
+        assert!(ToolPolicy::new().with_summarize_over(3).resolve().should_summarize(&serde_json::json!("long")));
+    }
+}
@@ -0,0 +1,300 @@
+//! Tools implemented as Python functions
+//!
+//! Lets existing adk-python tool functions be reused from Rust. Functions run
+//! in an interpreter embedded with pyo3: a tool imports its function once and
+//! calls it with the JSON arguments converted to keyword arguments. Coroutine
+//! functions run on an event loop owned by the tool, so async tools keep their
+//! loop-bound state between calls.
+
+use crate::{error::Result, tools::BaseTool, types::FunctionDeclaration};
+use async_trait::async_trait;
+use pyo3::{
+    prelude::*,
+    types::{PyList, PyModule},
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::debug;
+
+/// Default limit for a single call
+pub const DEFAULT_PYTHON_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Helpers run inside the interpreter
+const BRIDGE: &str = r#"
+import asyncio, inspect, json
+
+TYPES = {str: "string", int: "integer", float: "number", bool: "boolean", list: "array", dict: "object"}
+
+def manifest(func):
+    properties, required = {}, []
+    for name, param in inspect.signature(func).parameters.items():
+        if name == "tool_context" or param.kind in (param.VAR_POSITIONAL, param.VAR_KEYWORD):
+            continue
+        properties[name] = {"type": TYPES.get(param.annotation, "string")}
+        if param.default is param.empty:
+            required.append(name)
+    return json.dumps({"name": func.__name__, "description": inspect.getdoc(func) or "",
+                       "parameters": {"type": "object", "properties": properties, "required": required}})
+
+def call(func, loop, args):
+    result = func(**json.loads(args))
+    if inspect.isawaitable(result):
+        result = loop.run_until_complete(result)
+    return json.dumps(result, default=str)
+"#;
+
+#[derive(Debug, Deserialize)]
+struct PyManifest {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+/// Imported function with the interpreter objects it is called through
+struct PyFunction {
+    bridge: Py<PyModule>,
+    function: Py<PyAny>,
+    event_loop: Py<PyAny>,
+}
+
+/// Tool that calls a Python function `module.function` with keyword arguments
+pub struct PyFunctionTool {
+    module: String,
+    function: String,
+    timeout: Duration,
+    declaration: FunctionDeclaration,
+    // Calls are serialized, as the event loop cannot run twice at once
+    imported: Arc<Mutex<PyFunction>>,
+}
+
+impl PyFunctionTool {
+    /// Import `module.function` and derive the declaration from its signature and docstring
+    pub async fn load(module: impl Into<String>, function: impl Into<String>) -> Result<Self> {
+        Self::builder(module, function).load().await
+    }
+
+    pub fn builder(module: impl Into<String>, function: impl Into<String>) -> PyFunctionToolBuilder {
+        PyFunctionToolBuilder {
+            module: module.into(),
+            function: function.into(),
+            python_path: Vec::new(),
+            timeout: DEFAULT_PYTHON_TIMEOUT,
+            description: None,
+        }
+    }
+}
+
+/// Import a function and create its event loop; returns them with the function's manifest
+fn import(module: &str, function: &str, python_path: &[PathBuf]) -> PyResult<(PyFunction, String)> {
+    Python::with_gil(|py| {
+        let sys_path = PyModule::import(py, "sys")?.getattr("path")?.downcast_into::<PyList>()?;
+        for dir in python_path.iter().rev() {
+            let dir = dir.display().to_string();
+            if !sys_path.contains(&dir)? {
+                sys_path.insert(0, dir)?;
+            }
+        }
+
+        let code = CString::new(BRIDGE).expect("bridge has no NUL bytes");
+        let bridge = PyModule::from_code(py, &code, c"adk_python_tool.py", c"adk_python_tool")?;
+        let function = PyModule::import(py, module)?.getattr(function)?;
+        let manifest = bridge.getattr("manifest")?.call1((&function,))?.extract::<String>()?;
+        let event_loop = PyModule::import(py, "asyncio")?.getattr("new_event_loop")?.call0()?;
+        Ok((
+            PyFunction {
+                bridge: bridge.unbind(),
+                function: function.unbind(),
+                event_loop: event_loop.unbind(),
+            },
+            manifest,
+        ))
+    })
+}
+
+/// Call the function with JSON arguments; the error is the exception it raised
+fn call(imported: &PyFunction, args: &str) -> PyResult<String> {
+    Python::with_gil(|py| {
+        imported
+            .bridge
+            .bind(py)
+            .getattr("call")?
+            .call1((imported.function.bind(py), imported.event_loop.bind(py), args))?
+            .extract()
+    })
+}
+
+/// Builder for PyFunctionTool
+pub struct PyFunctionToolBuilder {
+    module: String,
+    function: String,
+    python_path: Vec<PathBuf>,
+    timeout: Duration,
+    description: Option<String>,
+}
+
+impl PyFunctionToolBuilder {
+    /// Add a directory to the interpreter's `sys.path`
+    pub fn python_path(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.python_path.push(dir.into());
+        self
+    }
+
+    /// Limit for a single call. A call past it is reported as timed out, but the
+    /// interpreter cannot interrupt the function, so it finishes in the background
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Override the description taken from the docstring
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub async fn load(self) -> Result<PyFunctionTool> {
+        let (module, function, python_path) = (self.module.clone(), self.function.clone(), self.python_path);
+        let (imported, manifest) = tokio::task::spawn_blocking(move || import(&module, &function, &python_path))
+            .await
+            .map_err(|e| crate::adk_error!(ToolError, "Python import panicked: {}", e))?
+            .map_err(|e| crate::adk_error!(ToolError, "Failed to import {}.{}: {}", self.module, self.function, e))?;
+        let manifest: PyManifest = serde_json::from_str(&manifest)?;
+        debug!("Imported Python tool {}.{}", self.module, self.function);
+
+        Ok(PyFunctionTool {
+            module: self.module,
+            function: self.function,
+            timeout: self.timeout,
+            declaration: FunctionDeclaration {
+                name: manifest.name,
+                description: self.description.unwrap_or(manifest.description),
+                parameters: manifest.parameters,
+            },
+            imported: Arc::new(Mutex::new(imported)),
+        })
+    }
+}
+
+impl std::fmt::Debug for PyFunctionTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PyFunctionTool")
+            .field("module", &self.module)
+            .field("function", &self.function)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl BaseTool for PyFunctionTool {
+    fn name(&self) -> &str {
+        &self.declaration.name
+    }
+
+    fn description(&self) -> &str {
+        &self.declaration.description
+    }
+
+    fn get_declaration(&self) -> Option<FunctionDeclaration> {
+        Some(self.declaration.clone())
+    }
+
+    async fn run_async(&self, args: HashMap<String, Value>) -> Result<Value> {
+        let args = serde_json::to_string(&args)?;
+        let imported = self.imported.clone();
+        // Python code blocks its thread, and holds the GIL while it runs
+        let running = tokio::task::spawn_blocking(move || {
+            let imported = imported.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            call(&imported, &args)
+        });
+
+        let result = tokio::time::timeout(self.timeout, running)
+            .await
+            .map_err(|_| {
+                crate::adk_error!(
+                    TimeoutError,
+                    "Python tool {}.{} exceeded {:?}",
+                    self.module,
+                    self.function,
+                    self.timeout
+                )
+            })?
+            .map_err(|e| crate::adk_error!(ToolError, "Python tool {}.{} panicked: {}", self.module, self.function, e))?;
+        match result {
+            Ok(result) => Ok(serde_json::from_str(&result)?),
+            Err(error) => Err(crate::adk_error!(
+                ToolError,
+                "{}.{} raised {}",
+                self.module,
+                self.function,
+                error
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_calls_sync_and_async_python_functions() {
+        let dir = std::env::temp_dir().join(format!("adk-py-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("weather_tools.py"),
+            r#"
+import asyncio
+
+def get_weather(city: str, days: int = 1):
+    """Get the forecast for a city."""
+    print("noise on stdout")
+    return {"city": city, "days": days}
+
+async def slow_echo(text: str):
+    await asyncio.sleep(0)
+    if text == "boom":
+        raise ValueError("bad input")
+    return text
+"#,
+        )
+        .unwrap();
+
+        let weather = PyFunctionTool::builder("weather_tools", "get_weather")
+            .python_path(&dir)
+            .load()
+            .await
+            .unwrap();
+        let declaration = weather.get_declaration().unwrap();
+        assert_eq!(declaration.description, "Get the forecast for a city.");
+        assert_eq!(declaration.parameters["properties"]["days"]["type"], "integer");
+        assert_eq!(declaration.parameters["required"], serde_json::json!(["city"]));
+
+        let args = HashMap::from([("city".to_string(), Value::from("Oslo"))]);
+        let result = weather.run_async(args).await.unwrap();
+        assert_eq!(result, serde_json::json!({"city": "Oslo", "days": 1}));
+
+        let echo = PyFunctionTool::builder("weather_tools", "slow_echo")
+            .python_path(&dir)
+            .load()
+            .await
+            .unwrap();
+        let args = HashMap::from([("text".to_string(), Value::from("hi"))]);
+        assert_eq!(echo.run_async(args).await.unwrap(), "hi");
+        let args = HashMap::from([("text".to_string(), Value::from("boom"))]);
+        let error = echo.run_async(args).await.unwrap_err().to_string();
+        assert!(error.contains("ValueError: bad input"));
+        let args = HashMap::from([("text".to_string(), Value::from("again"))]);
+        assert_eq!(echo.run_async(args).await.unwrap(), "again");
+
+        assert!(PyFunctionTool::load("weather_tools", "missing").await.is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
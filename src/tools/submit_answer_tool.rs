@@ -0,0 +1,33 @@
+//! Structured final-answer tool
+
+use crate::{
+    tools::{BaseTool, FunctionTool},
+    types::FunctionDeclaration,
+};
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc};
+
+/// Default name of the final-answer tool
+pub const SUBMIT_ANSWER_TOOL_NAME: &str = "submit_answer";
+
+/// Create a `submit_answer` tool whose arguments follow the given JSON schema.
+///
+/// The tool returns its arguments unchanged, so pairing it with
+/// `LlmAgentBuilder::require_tool` or `stop_on_tool` yields the model's
+/// structured answer as the final event.
+pub fn submit_answer(parameters: Value) -> Arc<dyn BaseTool> {
+    let tool = FunctionTool::new(
+        SUBMIT_ANSWER_TOOL_NAME,
+        "Submit the final answer",
+        |args: HashMap<String, Value>| async move {
+            Ok(Value::Object(args.into_iter().collect()))
+        },
+    )
+    .with_declaration(FunctionDeclaration {
+        name: SUBMIT_ANSWER_TOOL_NAME.to_string(),
+        description: "Submit the final answer. Call this exactly once when you have the complete answer.".to_string(),
+        parameters,
+    });
+
+    Arc::new(tool)
+}
@@ -0,0 +1,211 @@
+//! Session state access for tools
+//!
+//! Tools reach session state only through a [`ToolContext`], which enforces
+//! the [`StateAccessPolicy`] the agent assigned to the tool. A third-party
+//! tool can be limited to, say, reading `order:` keys, so it never sees
+//! secrets or user data other tools stashed in state. Writes are collected
+//! as a state delta recorded on the tool's response event.
+
+use crate::{
+    error::Result,
+    types::{InvocationId, SessionId, SessionState, StateDelta, UserId},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+/// How much of session state a tool may touch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StateAccess {
+    None,
+    ReadOnly,
+    #[default]
+    ReadWrite,
+}
+
+/// State access granted to a tool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateAccessPolicy {
+    #[serde(default)]
+    pub access: StateAccess,
+
+    /// Key prefixes the access applies to; every key when empty
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+}
+
+impl StateAccessPolicy {
+    /// Read and write every key (the default)
+    pub fn full() -> Self {
+        Self::default()
+    }
+
+    /// No access to session state
+    pub fn none() -> Self {
+        Self {
+            access: StateAccess::None,
+            prefixes: Vec::new(),
+        }
+    }
+
+    /// Read every key, write none
+    pub fn read_only() -> Self {
+        Self {
+            access: StateAccess::ReadOnly,
+            prefixes: Vec::new(),
+        }
+    }
+
+    /// Limit access to keys starting with `prefix`; may be repeated
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    fn covers(&self, key: &str) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|prefix| key.starts_with(prefix.as_str()))
+    }
+
+    pub fn can_read(&self, key: &str) -> bool {
+        self.access != StateAccess::None && self.covers(key)
+    }
+
+    pub fn can_write(&self, key: &str) -> bool {
+        self.access == StateAccess::ReadWrite && self.covers(key)
+    }
+}
+
+/// A tool call's view of the invocation and its session state
+#[derive(Debug, Clone)]
+pub struct ToolContext {
+    pub tool_name: String,
+    pub agent_name: String,
+    pub invocation_id: InvocationId,
+    pub session_id: SessionId,
+    pub user_id: UserId,
+    policy: StateAccessPolicy,
+    state: Arc<Mutex<SessionState>>,
+    delta: Arc<Mutex<StateDelta>>,
+    redact: Arc<AtomicBool>,
+}
+
+impl ToolContext {
+    pub fn new(
+        tool_name: impl Into<String>,
+        agent_name: impl Into<String>,
+        invocation_id: InvocationId,
+        session_id: SessionId,
+        user_id: UserId,
+        state: SessionState,
+        policy: StateAccessPolicy,
+    ) -> Self {
+        Self {
+            tool_name: tool_name.into(),
+            agent_name: agent_name.into(),
+            invocation_id,
+            session_id,
+            user_id,
+            policy,
+            state: Arc::new(Mutex::new(state)),
+            delta: Arc::default(),
+            redact: Arc::default(),
+        }
+    }
+
+    pub fn policy(&self) -> &StateAccessPolicy {
+        &self.policy
+    }
+
+    /// Read a state value; fails if the policy does not allow reading the key
+    pub fn get_state(&self, key: &str) -> Result<Option<Value>> {
+        if !self.policy.can_read(key) {
+            return Err(crate::adk_error!(
+                AuthError,
+                "Tool '{}' may not read state key '{}'",
+                self.tool_name,
+                key
+            ));
+        }
+        Ok(self.state.lock().expect("tool state lock poisoned").get(key).cloned())
+    }
+
+    /// Write a state value; fails if the policy does not allow writing the key
+    pub fn set_state(&self, key: impl Into<String>, value: Value) -> Result<()> {
+        let key = key.into();
+        if !self.policy.can_write(&key) {
+            return Err(crate::adk_error!(
+                AuthError,
+                "Tool '{}' may not write state key '{}'",
+                self.tool_name,
+                key
+            ));
+        }
+        self.state.lock().expect("tool state lock poisoned").insert(key.clone(), value.clone());
+        self.delta.lock().expect("tool state lock poisoned").insert(key, value);
+        Ok(())
+    }
+
+    /// The state keys and values the tool may read
+    pub fn visible_state(&self) -> SessionState {
+        self.state
+            .lock()
+            .expect("tool state lock poisoned")
+            .iter()
+            .filter(|(key, _)| self.policy.can_read(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Values written by the tool
+    pub fn state_delta(&self) -> StateDelta {
+        self.delta.lock().expect("tool state lock poisoned").clone()
+    }
+
+    /// Mark the call's result as secret: the response event is stored and streamed redacted
+    pub fn redact_response(&self) {
+        self.redact.store(true, Ordering::Relaxed);
+    }
+
+    pub fn redacts_response(&self) -> bool {
+        self.redact.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_policy_limits_reads_and_writes() {
+        let state = SessionState::from([
+            ("order:id".to_string(), json!("A-1")),
+            ("secret:api_key".to_string(), json!("sk-123")),
+        ]);
+        let policy = StateAccessPolicy::read_only().with_prefix("order:");
+        let ctx = ToolContext::new("lookup", "helper", uuid::Uuid::nil(), "s1".into(), "u1".into(), state, policy);
+
+        assert_eq!(ctx.get_state("order:id").unwrap(), Some(json!("A-1")));
+        assert!(ctx.get_state("secret:api_key").is_err());
+        assert!(ctx.set_state("order:status", json!("shipped")).is_err());
+        assert_eq!(ctx.visible_state().len(), 1);
+
+        let writer = ToolContext::new(
+            "track",
+            "helper",
+            uuid::Uuid::nil(),
+            "s1".into(),
+            "u1".into(),
+            SessionState::new(),
+            StateAccessPolicy::full().with_prefix("order:"),
+        );
+        writer.set_state("order:status", json!("shipped")).unwrap();
+        assert_eq!(writer.get_state("order:status").unwrap(), Some(json!("shipped")));
+        assert!(writer.set_state("user:name", json!("Ada")).is_err());
+        assert_eq!(writer.state_delta().len(), 1);
+    }
+}
@@ -0,0 +1,308 @@
+//! Sandboxed tools compiled to WebAssembly
+//!
+//! A WASM tool is a WASI command module implementing a small interface:
+//!
+//! - invoked with the argument `describe`, it prints a JSON manifest
+//!   `{"name": ..., "description": ..., "parameters": <JSON schema>}` to stdout;
+//! - invoked with the argument `invoke`, it reads the call arguments as a JSON
+//!   object from stdin and prints the JSON result to stdout.
+//!
+//! Modules run in an embedded `wasmtime` runtime with fuel and memory
+//! limits, no preopened directories, no environment and no network access.
+//! Stdin and stdout are in-memory pipes, so output beyond the limit is never
+//! buffered.
+
+use crate::{error::Result, tools::BaseTool, types::FunctionDeclaration};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::debug;
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+use wasmtime_wasi::{
+    pipe::{MemoryInputPipe, MemoryOutputPipe},
+    preview1::{self, WasiP1Ctx},
+    I32Exit, WasiCtxBuilder,
+};
+
+/// Maximum size of the module's stderr kept for error messages
+const MAX_STDERR_BYTES: usize = 64 * 1024;
+
+/// Fuel consumed between yields to the async runtime, so timeouts can interrupt a module
+const FUEL_YIELD_INTERVAL: u64 = 100_000;
+
+/// Resource limits applied to every module execution
+#[derive(Debug, Clone, PartialEq)]
+pub struct WasmLimits {
+    /// Instruction fuel available to a single call
+    pub fuel: u64,
+
+    /// Maximum linear memory size in bytes
+    pub max_memory_bytes: u64,
+
+    /// Wall-clock limit for a single call
+    pub timeout: Duration,
+
+    /// Maximum size of the module's stdout
+    pub max_output_bytes: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 1_000_000_000,
+            max_memory_bytes: 64 * 1024 * 1024,
+            timeout: Duration::from_secs(10),
+            max_output_bytes: 1024 * 1024,
+        }
+    }
+}
+
+/// Manifest printed by a module's `describe` entry point
+#[derive(Debug, Clone, Deserialize)]
+pub struct WasmToolManifest {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default = "default_parameters")]
+    pub parameters: Value,
+}
+
+fn default_parameters() -> Value {
+    serde_json::json!({"type": "object", "properties": {}})
+}
+
+/// Engine shared by all WASM tools; compiled modules are tied to it
+fn engine() -> Result<&'static Engine> {
+    static ENGINE: once_cell::sync::OnceCell<Engine> = once_cell::sync::OnceCell::new();
+    ENGINE.get_or_try_init(|| {
+        let mut config = Config::new();
+        config.async_support(true).consume_fuel(true);
+        Engine::new(&config).map_err(|e| crate::adk_error!(ToolError, "Failed to create WASM engine: {}", e))
+    })
+}
+
+/// Store data of one module execution
+struct WasmHost {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+/// Tool backed by a sandboxed WASI module
+#[derive(Debug, Clone)]
+pub struct WasmTool {
+    path: PathBuf,
+    module: Module,
+    limits: WasmLimits,
+    manifest: WasmToolManifest,
+}
+
+impl WasmTool {
+    /// Load a module with default limits, reading its manifest
+    pub async fn load(module: impl AsRef<Path>) -> Result<Self> {
+        Self::load_with_limits(module, WasmLimits::default()).await
+    }
+
+    /// Load a module with the given limits, reading its manifest
+    pub async fn load_with_limits(module: impl AsRef<Path>, limits: WasmLimits) -> Result<Self> {
+        let path = module.as_ref().to_path_buf();
+        if !path.is_file() {
+            return Err(crate::adk_error!(ToolError, "WASM module not found: {}", path.display()));
+        }
+        let engine = engine()?;
+        // Compiling is CPU-bound and can take a while for large modules
+        let source = path.clone();
+        let compiled = tokio::task::spawn_blocking(move || Module::from_file(engine, source))
+            .await
+            .map_err(|e| crate::adk_error!(ToolError, "WASM compilation panicked: {}", e))?
+            .map_err(|e| crate::adk_error!(ToolError, "Invalid WASM module {}: {}", path.display(), e))?;
+
+        let mut tool = Self {
+            path,
+            module: compiled,
+            limits,
+            manifest: WasmToolManifest {
+                name: String::new(),
+                description: String::new(),
+                parameters: default_parameters(),
+            },
+        };
+        let output = tool.execute("describe", None).await?;
+        tool.manifest = serde_json::from_slice(&output).map_err(|e| {
+            crate::adk_error!(ToolError, "Invalid manifest from {}: {}", tool.path.display(), e)
+        })?;
+        debug!("Loaded WASM tool '{}' from {}", tool.manifest.name, tool.path.display());
+        Ok(tool)
+    }
+
+    pub fn limits(&self) -> &WasmLimits {
+        &self.limits
+    }
+
+    /// Run an entry point, feeding `input` on stdin, and return stdout
+    async fn execute(&self, entry_point: &str, input: Option<Vec<u8>>) -> Result<Vec<u8>> {
+        // One byte over the limit tells a full answer from an oversized one
+        let stdout = MemoryOutputPipe::new(self.limits.max_output_bytes + 1);
+        let stderr = MemoryOutputPipe::new(MAX_STDERR_BYTES);
+        let wasi = WasiCtxBuilder::new()
+            .args(&[self.path.display().to_string(), entry_point.to_string()])
+            .stdin(MemoryInputPipe::new(input.unwrap_or_default()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .build_p1();
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(usize::try_from(self.limits.max_memory_bytes).unwrap_or(usize::MAX))
+            .build();
+
+        let mut store = Store::new(self.module.engine(), WasmHost { wasi, limits });
+        store.limiter(|host| &mut host.limits);
+        store.set_fuel(self.limits.fuel)?;
+        store.fuel_async_yield_interval(Some(FUEL_YIELD_INTERVAL))?;
+        let mut linker = Linker::new(self.module.engine());
+        preview1::add_to_linker_async(&mut linker, |host: &mut WasmHost| &mut host.wasi)?;
+
+        let run = async {
+            let instance = linker.instantiate_async(&mut store, &self.module).await?;
+            let start = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+            start.call_async(&mut store, ()).await
+        };
+        let result = tokio::time::timeout(self.limits.timeout, run).await.map_err(|_| {
+            crate::adk_error!(TimeoutError, "WASM tool {} exceeded {:?}", self.path.display(), self.limits.timeout)
+        })?;
+
+        let output = stdout.contents();
+        if output.len() > self.limits.max_output_bytes {
+            return Err(crate::adk_error!(
+                ToolError,
+                "WASM tool {} output exceeds {} bytes",
+                self.path.display(),
+                self.limits.max_output_bytes
+            ));
+        }
+        if let Err(e) = result {
+            // `proc_exit(0)` ends a command successfully
+            if e.downcast_ref::<I32Exit>().map(|exit| exit.0) != Some(0) {
+                let reason = match e.downcast_ref::<Trap>() {
+                    Some(Trap::OutOfFuel) => "out of fuel".to_string(),
+                    _ => e.to_string(),
+                };
+                return Err(crate::adk_error!(
+                    ToolError,
+                    "WASM tool {} failed ({}): {}",
+                    self.path.display(),
+                    reason,
+                    String::from_utf8_lossy(&stderr.contents()).trim()
+                ));
+            }
+        }
+        Ok(output.to_vec())
+    }
+}
+
+#[async_trait]
+impl BaseTool for WasmTool {
+    fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn description(&self) -> &str {
+        &self.manifest.description
+    }
+
+    fn get_declaration(&self) -> Option<FunctionDeclaration> {
+        Some(FunctionDeclaration {
+            name: self.manifest.name.clone(),
+            description: self.manifest.description.clone(),
+            parameters: self.manifest.parameters.clone(),
+        })
+    }
+
+    async fn run_async(&self, args: HashMap<String, Value>) -> Result<Value> {
+        let input = serde_json::to_vec(&args)?;
+        let output = self.execute("invoke", Some(input)).await?;
+        serde_json::from_slice(&output).map_err(|e| {
+            crate::adk_error!(ToolError, "Invalid result from WASM tool '{}': {}", self.manifest.name, e)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// WASI command that prints its manifest on `describe`, echoes stdin on
+    /// `invoke` and spins forever on anything else
+    fn echo_module() -> Vec<u8> {
+        let manifest = r#"{"name":"echo","description":"Echo the arguments"}"#;
+        let wat = format!(
+            r#"(module
+                (import "wasi_snapshot_preview1" "args_sizes_get" (func $args_sizes_get (param i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "args_get" (func $args_get (param i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 2)
+                (data (i32.const 1024) "{escaped}")
+                (func (export "_start")
+                    ;; argc and the argument buffer size at 0 and 4, argv at 16, arguments at 256
+                    (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))
+                    (drop (call $args_get (i32.const 16) (i32.const 256)))
+                    ;; One iovec at 8
+                    (if (i32.eq (i32.load8_u (i32.load (i32.const 20))) (i32.const 100))
+                        (then
+                            (i32.store (i32.const 8) (i32.const 1024))
+                            (i32.store (i32.const 12) (i32.const {length}))
+                            (drop (call $fd_write (i32.const 1) (i32.const 8) (i32.const 1) (i32.const 0)))
+                            (return)))
+                    (if (i32.eq (i32.load8_u (i32.load (i32.const 20))) (i32.const 105))
+                        (then
+                            (i32.store (i32.const 8) (i32.const 4096))
+                            (i32.store (i32.const 12) (i32.const 60000))
+                            (drop (call $fd_read (i32.const 0) (i32.const 8) (i32.const 1) (i32.const 0)))
+                            (i32.store (i32.const 12) (i32.load (i32.const 0)))
+                            (drop (call $fd_write (i32.const 1) (i32.const 8) (i32.const 1) (i32.const 0)))
+                            (return)))
+                    (loop $spin (br $spin))))"#,
+            escaped = manifest.replace('"', "\\\""),
+            length = manifest.len(),
+        );
+        wat::parse_str(wat).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_runs_a_module_within_its_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("echo.wasm");
+        std::fs::write(&path, echo_module()).unwrap();
+
+        let tool = WasmTool::load(&path).await.unwrap();
+        assert_eq!(tool.name(), "echo");
+        assert_eq!(tool.get_declaration().unwrap().parameters["type"], "object");
+        let args = HashMap::from([("city".to_string(), serde_json::json!("Oslo"))]);
+        assert_eq!(tool.run_async(args.clone()).await.unwrap(), serde_json::json!({ "city": "Oslo" }));
+
+        // Output over the limit is an error rather than a truncated result
+        let mut limited = tool.clone();
+        limited.limits.max_output_bytes = 8;
+        let oversized = limited.run_async(args).await.unwrap_err();
+        assert!(oversized.to_string().contains("exceeds 8 bytes"), "{}", oversized);
+
+        // Runaway modules stop when their fuel or time runs out
+        let mut limited = tool.clone();
+        limited.limits.fuel = 1_000_000;
+        let spinning = limited.execute("spin", None).await.unwrap_err();
+        assert!(spinning.to_string().contains("out of fuel"), "{}", spinning);
+        let mut limited = tool.clone();
+        limited.limits.fuel = u64::MAX;
+        limited.limits.timeout = Duration::from_millis(100);
+        let spinning = limited.execute("spin", None).await.unwrap_err();
+        assert!(matches!(spinning, crate::error::AdkError::TimeoutError(_)), "{}", spinning);
+
+        assert!(WasmTool::load("does/not/exist.wasm").await.is_err());
+    }
+}
@@ -1,5 +1,6 @@
 //! Common types used throughout the ADK library
 
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -17,15 +18,61 @@ pub type UserId = String;
 /// Unique identifier for invocations
 pub type InvocationId = Uuid;
 
-/// Content part that can contain text, images, or other media
+/// Content part that can contain text, images, or other media.
+///
+/// Binary payloads are reference-counted `Bytes`, so cloning a part (into
+/// events, sessions or requests) never copies the data; they serialize as base64.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ContentPart {
     Text { text: String },
-    Image { data: Vec<u8>, mime_type: String },
-    Video { data: Vec<u8>, mime_type: String },
-    Audio { data: Vec<u8>, mime_type: String },
-    File { data: Vec<u8>, mime_type: String, filename: String },
+    Image {
+        #[serde(with = "crate::utils::base64_bytes")]
+        data: Bytes,
+        mime_type: String,
+    },
+    Video {
+        #[serde(with = "crate::utils::base64_bytes")]
+        data: Bytes,
+        mime_type: String,
+    },
+    Audio {
+        #[serde(with = "crate::utils::base64_bytes")]
+        data: Bytes,
+        mime_type: String,
+    },
+    File {
+        #[serde(with = "crate::utils::base64_bytes")]
+        data: Bytes,
+        mime_type: String,
+        filename: String,
+    },
+    /// Media passed by reference (an uploaded file or artifact URI) instead of inline
+    FileRef { uri: String, mime_type: String },
+    FunctionCall { name: String, args: serde_json::Value },
+    FunctionResponse { name: String, response: serde_json::Value },
+    /// Code generated by the model for built-in code execution
+    ExecutableCode { language: String, code: String },
+    /// Result of running the preceding executable code
+    CodeExecutionResult { outcome: CodeExecutionOutcome, output: String },
+}
+
+/// Outcome of a built-in code execution (serialized with Gemini's names)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CodeExecutionOutcome {
+    /// Execution completed successfully
+    #[serde(rename = "OUTCOME_OK")]
+    Ok,
+    /// Execution finished with an error; output typically holds the stderr
+    #[serde(rename = "OUTCOME_FAILED")]
+    Failed,
+    /// Execution ran too long and was cancelled
+    #[serde(rename = "OUTCOME_DEADLINE_EXCEEDED")]
+    DeadlineExceeded,
+    /// Unknown outcome
+    #[default]
+    #[serde(rename = "OUTCOME_UNSPECIFIED", other)]
+    Unspecified,
 }
 
 impl ContentPart {
@@ -35,13 +82,61 @@ impl ContentPart {
     }
 
     /// Create an image content part
-    pub fn image(data: Vec<u8>, mime_type: impl Into<String>) -> Self {
+    pub fn image(data: impl Into<Bytes>, mime_type: impl Into<String>) -> Self {
         Self::Image {
-            data,
+            data: data.into(),
+            mime_type: mime_type.into(),
+        }
+    }
+
+    /// Create an audio content part
+    pub fn audio(data: impl Into<Bytes>, mime_type: impl Into<String>) -> Self {
+        Self::Audio {
+            data: data.into(),
+            mime_type: mime_type.into(),
+        }
+    }
+
+    /// Create a content part referring to media by URI instead of embedding it
+    pub fn file_ref(uri: impl Into<String>, mime_type: impl Into<String>) -> Self {
+        Self::FileRef {
+            uri: uri.into(),
             mime_type: mime_type.into(),
         }
     }
 
+    /// Create a function call content part
+    pub fn function_call(name: impl Into<String>, args: serde_json::Value) -> Self {
+        Self::FunctionCall {
+            name: name.into(),
+            args,
+        }
+    }
+
+    /// Create a function response content part
+    pub fn function_response(name: impl Into<String>, response: serde_json::Value) -> Self {
+        Self::FunctionResponse {
+            name: name.into(),
+            response,
+        }
+    }
+
+    /// Create an executable code content part
+    pub fn executable_code(language: impl Into<String>, code: impl Into<String>) -> Self {
+        Self::ExecutableCode {
+            language: language.into(),
+            code: code.into(),
+        }
+    }
+
+    /// Create a code execution result content part
+    pub fn code_execution_result(outcome: CodeExecutionOutcome, output: impl Into<String>) -> Self {
+        Self::CodeExecutionResult {
+            outcome,
+            output: output.into(),
+        }
+    }
+
     /// Get text content if this is a text part
     pub fn as_text(&self) -> Option<&str> {
         match self {
@@ -49,6 +144,17 @@ impl ContentPart {
             _ => None,
         }
     }
+
+    /// Get the MIME type and inline data if this is a media part
+    pub fn as_inline_data(&self) -> Option<(&str, &Bytes)> {
+        match self {
+            Self::Image { data, mime_type }
+            | Self::Video { data, mime_type }
+            | Self::Audio { data, mime_type }
+            | Self::File { data, mime_type, .. } => Some((mime_type, data)),
+            _ => None,
+        }
+    }
 }
 
 /// Content with role and parts
@@ -75,6 +181,44 @@ impl Content {
         }
     }
 
+    /// Create system instruction content with text
+    pub fn system_text(text: impl Into<String>) -> Self {
+        Self {
+            role: "system".to_string(),
+            parts: vec![ContentPart::text(text)],
+        }
+    }
+
+    /// Create model content requesting a function call
+    pub fn function_call(call: FunctionCall) -> Self {
+        Self {
+            role: "model".to_string(),
+            parts: vec![ContentPart::function_call(call.name, call.args)],
+        }
+    }
+
+    /// Create content carrying a function result back to the model
+    pub fn function_response(name: impl Into<String>, response: serde_json::Value) -> Self {
+        Self {
+            role: "user".to_string(),
+            parts: vec![ContentPart::function_response(name, response)],
+        }
+    }
+
+    /// Get the function calls in this content
+    pub fn function_calls(&self) -> Vec<FunctionCall> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::FunctionCall { name, args } => Some(FunctionCall {
+                    name: name.clone(),
+                    args: args.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Get all text from content parts
     pub fn get_text(&self) -> String {
         self.parts
@@ -106,10 +250,62 @@ pub struct FunctionCall {
     pub args: serde_json::Value,
 }
 
+/// How the model may use the declared functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FunctionCallingMode {
+    /// The model decides whether to call a function
+    #[default]
+    Auto,
+    /// The model must call a function
+    Any,
+    /// The model must not call functions
+    None,
+}
+
+/// Function calling configuration for a request
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolConfig {
+    pub function_calling_mode: FunctionCallingMode,
+    /// Restricts which functions may be called; only valid with `Any`
+    #[serde(default)]
+    pub allowed_function_names: Vec<String>,
+}
+
+impl ToolConfig {
+    /// Let the model decide whether to call functions
+    pub fn auto() -> Self {
+        Self::default()
+    }
+
+    /// Force the model to call a function
+    pub fn any() -> Self {
+        Self {
+            function_calling_mode: FunctionCallingMode::Any,
+            allowed_function_names: Vec::new(),
+        }
+    }
+
+    /// Forbid function calls
+    pub fn none() -> Self {
+        Self {
+            function_calling_mode: FunctionCallingMode::None,
+            allowed_function_names: Vec::new(),
+        }
+    }
+
+    /// Restrict function calls to the given names
+    pub fn with_allowed_function_names(mut self, names: Vec<String>) -> Self {
+        self.allowed_function_names = names;
+        self
+    }
+}
+
 /// Configuration for content generation
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct GenerateContentConfig {
     pub tools: Vec<Tool>,
+    pub tool_config: Option<ToolConfig>,
     pub response_schema: Option<serde_json::Value>,
     pub response_mime_type: Option<String>,
     pub temperature: Option<f32>,
@@ -117,6 +313,36 @@ pub struct GenerateContentConfig {
     pub top_k: Option<i32>,
     pub max_output_tokens: Option<i32>,
     pub stop_sequences: Vec<String>,
+    /// Enable the provider's built-in code execution tool
+    #[serde(default)]
+    pub code_execution: bool,
+    /// Sampling seed for reproducible output, where the provider supports it
+    #[serde(default)]
+    pub seed: Option<i32>,
+    /// Name of a provider context cache the request continues from, e.g.
+    /// `cachedContents/abc123` for Gemini
+    #[serde(default)]
+    pub cached_content: Option<String>,
+    /// Blocking thresholds per harm category, for providers with safety filters
+    #[serde(default)]
+    pub safety_settings: Vec<SafetySetting>,
+}
+
+/// Blocking threshold for a harm category, in the provider's terms, e.g.
+/// `HARM_CATEGORY_HARASSMENT` and `BLOCK_ONLY_HIGH` for Gemini
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+impl SafetySetting {
+    pub fn new(category: impl Into<String>, threshold: impl Into<String>) -> Self {
+        Self {
+            category: category.into(),
+            threshold: threshold.into(),
+        }
+    }
 }
 
 /// State delta for session updates
@@ -136,21 +362,15 @@ pub enum StreamingMode {
 }
 
 /// Run configuration for agents
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RunConfig {
     pub streaming_mode: StreamingMode,
     pub max_iterations: Option<u32>,
     pub timeout_seconds: Option<u64>,
-}
-
-impl Default for RunConfig {
-    fn default() -> Self {
-        Self {
-            streaming_mode: StreamingMode::default(),
-            max_iterations: None,
-            timeout_seconds: None,
-        }
-    }
+    #[serde(default)]
+    pub seed: Option<i32>,
+    #[serde(default)]
+    pub deterministic: bool,
 }
 
 /// Metadata for various objects
@@ -159,23 +379,24 @@ pub type Metadata = HashMap<String, serde_json::Value>;
 /// Timestamp type
 pub type Timestamp = DateTime<Utc>;
 
-/// Helper function to get current timestamp
+/// Current timestamp from the injectable clock (see [`crate::utils::clock`])
 pub fn now() -> Timestamp {
-    Utc::now()
+    crate::utils::clock::now()
 }
 
 /// Blob data for streaming
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Blob {
     pub mime_type: String,
-    pub data: Vec<u8>,
+    #[serde(with = "crate::utils::base64_bytes")]
+    pub data: Bytes,
 }
 
 impl Blob {
-    pub fn new(mime_type: impl Into<String>, data: Vec<u8>) -> Self {
+    pub fn new(mime_type: impl Into<String>, data: impl Into<Bytes>) -> Self {
         Self {
             mime_type: mime_type.into(),
-            data,
+            data: data.into(),
         }
     }
 }
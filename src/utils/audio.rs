@@ -0,0 +1,323 @@
+//! Audio conversion for live runs
+//!
+//! Live connections take 16-bit little-endian mono PCM at 16 kHz in short
+//! frames and answer with 24 kHz PCM. Browsers capture at 44.1 or 48 kHz,
+//! often in stereo, and `MediaRecorder` produces Opus in WebM or Ogg. The
+//! helpers here resample and downmix PCM, cut it into live frames and, with
+//! the `audio` feature, decode compressed recordings via symphonia.
+
+use crate::{agents::vad::pcm_sample_rate, error::Result, types::Blob};
+use bytes::Bytes;
+
+/// Sample rate live connections expect for user audio
+pub const LIVE_INPUT_SAMPLE_RATE: u32 = 16_000;
+
+/// Sample rate of the audio live connections send back
+pub const LIVE_OUTPUT_SAMPLE_RATE: u32 = 24_000;
+
+/// Duration of the audio frames sent to live connections
+pub const LIVE_FRAME_MS: u32 = 20;
+
+/// MIME type of raw PCM audio at `sample_rate`
+pub fn pcm_mime_type(sample_rate: u32) -> String {
+    format!("audio/pcm;rate={}", sample_rate)
+}
+
+/// Samples of 16-bit little-endian PCM; a trailing odd byte is ignored
+pub fn pcm16_from_bytes(pcm: &[u8]) -> Vec<i16> {
+    pcm.chunks_exact(2).map(|sample| i16::from_le_bytes([sample[0], sample[1]])).collect()
+}
+
+pub fn pcm16_to_bytes(samples: &[i16]) -> Vec<u8> {
+    samples.iter().flat_map(|sample| sample.to_le_bytes()).collect()
+}
+
+/// Average interleaved channels into mono
+pub fn downmix(samples: &[i16], channels: usize) -> Vec<i16> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks_exact(channels)
+        .map(|frame| (frame.iter().map(|&sample| sample as i32).sum::<i32>() / channels as i32) as i16)
+        .collect()
+}
+
+/// Resample mono PCM. Downsampling averages the input each output sample
+/// spans, which keeps speech free of audible aliasing; upsampling interpolates.
+pub fn resample(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+    if from_rate == to_rate || from_rate == 0 || to_rate == 0 || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let step = from_rate as f64 / to_rate as f64;
+    let len = (samples.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let index = (pos as usize).min(samples.len() - 1);
+            if step > 1.0 {
+                let end = ((pos + step) as usize).clamp(index + 1, samples.len());
+                let sum: i64 = samples[index..end].iter().map(|&sample| sample as i64).sum();
+                (sum / (end - index) as i64) as i16
+            } else {
+                let next = samples.get(index + 1).copied().unwrap_or(samples[index]) as f64;
+                let current = samples[index] as f64;
+                (current + (next - current) * (pos - index as f64)).round() as i16
+            }
+        })
+        .collect()
+}
+
+/// Cuts a PCM byte stream into fixed-size frames, carrying the remainder between pushes
+#[derive(Debug, Clone)]
+pub struct PcmChunker {
+    frame_bytes: usize,
+    buffer: Vec<u8>,
+}
+
+impl PcmChunker {
+    /// Frames of `frame_ms` of 16-bit mono audio at `sample_rate`
+    pub fn new(sample_rate: u32, frame_ms: u32) -> Self {
+        Self {
+            frame_bytes: (sample_rate as usize * frame_ms as usize / 1000 * 2).max(2),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Frames of the size live connections expect
+    pub fn for_live_input() -> Self {
+        Self::new(LIVE_INPUT_SAMPLE_RATE, LIVE_FRAME_MS)
+    }
+
+    pub fn frame_bytes(&self) -> usize {
+        self.frame_bytes
+    }
+
+    /// Add audio and take the frames it completes
+    pub fn push(&mut self, pcm: &[u8]) -> Vec<Bytes> {
+        self.buffer.extend_from_slice(pcm);
+        let complete = self.buffer.len() - self.buffer.len() % self.frame_bytes;
+        let frames: Vec<Bytes> = self.buffer[..complete].chunks(self.frame_bytes).map(Bytes::copy_from_slice).collect();
+        self.buffer.drain(..complete);
+        frames
+    }
+
+    /// The buffered partial frame, padded with silence
+    pub fn flush(&mut self) -> Option<Bytes> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let mut frame = std::mem::take(&mut self.buffer);
+        frame.resize(self.frame_bytes, 0);
+        Some(frame.into())
+    }
+}
+
+/// Converts client audio into live input frames
+#[derive(Debug, Clone)]
+pub struct LiveAudioInput {
+    chunker: PcmChunker,
+}
+
+impl Default for LiveAudioInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveAudioInput {
+    pub fn new() -> Self {
+        Self {
+            chunker: PcmChunker::for_live_input(),
+        }
+    }
+
+    /// Convert a chunk of client audio into complete live frames.
+    ///
+    /// Raw PCM (`audio/pcm;rate=N`, mono) may arrive in pieces of any size.
+    /// Other formats must be whole recordings and need the `audio` feature.
+    pub fn push(&mut self, blob: &Blob) -> Result<Vec<Blob>> {
+        let samples = match pcm_sample_rate(&blob.mime_type) {
+            Some(sample_rate) => resample(&pcm16_from_bytes(&blob.data), sample_rate, LIVE_INPUT_SAMPLE_RATE),
+            None => decode_to_live_input(blob)?,
+        };
+        Ok(self
+            .chunker
+            .push(&pcm16_to_bytes(&samples))
+            .into_iter()
+            .map(|frame| Blob::new(pcm_mime_type(LIVE_INPUT_SAMPLE_RATE), frame))
+            .collect())
+    }
+
+    /// The final, silence-padded frame of the input
+    pub fn finish(&mut self) -> Option<Blob> {
+        self.chunker.flush().map(|frame| Blob::new(pcm_mime_type(LIVE_INPUT_SAMPLE_RATE), frame))
+    }
+}
+
+#[cfg(feature = "audio")]
+fn decode_to_live_input(blob: &Blob) -> Result<Vec<i16>> {
+    decode_audio(&blob.data, &blob.mime_type).map(|audio| audio.to_mono(LIVE_INPUT_SAMPLE_RATE))
+}
+
+#[cfg(not(feature = "audio"))]
+fn decode_to_live_input(blob: &Blob) -> Result<Vec<i16>> {
+    Err(crate::adk_error!(
+        ConfigError,
+        "Decoding '{}' audio requires the `audio` feature; send audio/pcm instead",
+        blob.mime_type
+    ))
+}
+
+/// Wrap 16-bit PCM in a WAV container, e.g. to play or store live output
+pub fn pcm_to_wav(pcm: &[u8], sample_rate: u32, channels: u16) -> Vec<u8> {
+    let block_align = channels as u32 * 2;
+    let mut wav = Vec::with_capacity(44 + pcm.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + pcm.len() as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * block_align).to_le_bytes());
+    wav.extend_from_slice(&(block_align as u16).to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(pcm.len() as u32).to_le_bytes());
+    wav.extend_from_slice(pcm);
+    wav
+}
+
+#[cfg(feature = "audio")]
+pub use decode::{decode_audio, decode_audio_with, DecodedAudio};
+
+#[cfg(feature = "audio")]
+mod decode {
+    use super::{downmix, resample};
+    use crate::error::Result;
+    use std::io::{Cursor, ErrorKind};
+    use symphonia::core::{
+        audio::SampleBuffer,
+        codecs::{CodecRegistry, DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS},
+        errors::Error,
+        formats::FormatOptions,
+        io::MediaSourceStream,
+        meta::MetadataOptions,
+        probe::Hint,
+    };
+
+    /// Interleaved 16-bit samples of a decoded recording
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct DecodedAudio {
+        pub sample_rate: u32,
+        pub channels: usize,
+        pub samples: Vec<i16>,
+    }
+
+    impl DecodedAudio {
+        /// Mono samples at `sample_rate`
+        pub fn to_mono(&self, sample_rate: u32) -> Vec<i16> {
+            resample(&downmix(&self.samples, self.channels), self.sample_rate, sample_rate)
+        }
+    }
+
+    /// Decode a WAV, Ogg, WebM/Matroska or FLAC recording with symphonia's bundled codecs
+    pub fn decode_audio(data: &[u8], mime_type: &str) -> Result<DecodedAudio> {
+        decode_audio_with(data, mime_type, symphonia::default::get_codecs())
+    }
+
+    /// Decode with a custom codec registry.
+    ///
+    /// Symphonia demuxes Opus from WebM and Ogg but ships no Opus decoder;
+    /// register one (e.g. a libopus adapter) to decode browser recordings.
+    pub fn decode_audio_with(data: &[u8], mime_type: &str, codecs: &CodecRegistry) -> Result<DecodedAudio> {
+        let source = MediaSourceStream::new(Box::new(Cursor::new(data.to_vec())), Default::default());
+        let mut hint = Hint::new();
+        hint.mime_type(mime_type.split(';').next().unwrap_or_default().trim());
+        let probed = symphonia::default::get_probe()
+            .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|e| crate::adk_error!(ValidationError, "Unrecognised '{}' audio: {}", mime_type, e))?;
+        let mut format = probed.format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| crate::adk_error!(ValidationError, "No audio track in '{}' data", mime_type))?;
+        let track_id = track.id;
+        let mut decoder = codecs.make(&track.codec_params, &DecoderOptions::default()).map_err(|e| {
+            if track.codec_params.codec == CODEC_TYPE_OPUS {
+                crate::adk_error!(ConfigError, "No Opus decoder registered; use decode_audio_with: {}", e)
+            } else {
+                crate::adk_error!(ValidationError, "Unsupported codec in '{}' audio: {}", mime_type, e)
+            }
+        })?;
+
+        let mut audio = DecodedAudio {
+            sample_rate: track.codec_params.sample_rate.unwrap_or_default(),
+            channels: track.codec_params.channels.map_or(1, |channels| channels.count()),
+            samples: Vec::new(),
+        };
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(Error::ResetRequired) => break,
+                Err(e) => return Err(crate::adk_error!(ValidationError, "Corrupt '{}' audio: {}", mime_type, e)),
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+            let decoded = match decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // A damaged packet only loses its own samples
+                Err(Error::DecodeError(_)) => continue,
+                Err(e) => return Err(crate::adk_error!(ValidationError, "Failed to decode '{}' audio: {}", mime_type, e)),
+            };
+            let spec = *decoded.spec();
+            audio.sample_rate = spec.rate;
+            audio.channels = spec.channels.count();
+            let mut buffer = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+            buffer.copy_interleaved_ref(decoded);
+            audio.samples.extend_from_slice(buffer.samples());
+        }
+        Ok(audio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_browser_pcm_becomes_live_frames() {
+        // 30 ms of 48 kHz stereo, left at 1000 and right at 3000
+        let stereo: Vec<i16> = (0..1440).flat_map(|_| [1000, 3000]).collect();
+        let mono = downmix(&stereo, 2);
+        assert_eq!(mono.len(), 1440);
+        assert!(mono.iter().all(|&sample| sample == 2000));
+
+        let mut input = LiveAudioInput::new();
+        let frames = input.push(&Blob::new("audio/pcm;rate=48000", pcm16_to_bytes(&mono))).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].mime_type, "audio/pcm;rate=16000");
+        assert_eq!(frames[0].data.len(), 640);
+        assert!(pcm16_from_bytes(&frames[0].data).iter().all(|&sample| sample == 2000));
+        let last = input.finish().unwrap();
+        assert_eq!(last.data.len(), 640);
+        assert_eq!(pcm16_from_bytes(&last.data)[159..161], [2000, 0]);
+        assert!(input.finish().is_none());
+
+        assert_eq!(resample(&[0, 100], 16_000, 32_000), [0, 50, 100, 100]);
+    }
+
+    #[cfg(feature = "audio")]
+    #[test]
+    fn test_decodes_wav_recordings() {
+        let pcm = pcm16_to_bytes(&[500; 4800]);
+        let audio = decode_audio(&pcm_to_wav(&pcm, 48_000, 2), "audio/wav").unwrap();
+        assert_eq!((audio.sample_rate, audio.channels, audio.samples.len()), (48_000, 2, 4800));
+        assert_eq!(audio.to_mono(LIVE_INPUT_SAMPLE_RATE).len(), 800);
+        assert!(decode_audio(b"not audio", "audio/webm").is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Base64 serialization of binary payloads without intermediate copies
+//!
+//! Use with `#[serde(with = "crate::utils::base64_bytes")]` on `Bytes` fields.
+//! Deserialization also accepts the legacy array-of-numbers form.
+
+use base64::{display::Base64Display, engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use serde::{de, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Lazily base64-encoded view of a byte slice; the encoding is streamed into
+/// the serializer or formatter instead of being built as a `String` first
+#[derive(Debug, Clone, Copy)]
+pub struct Base64<'a>(pub &'a [u8]);
+
+impl fmt::Display for Base64<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Base64Display::new(self.0, &STANDARD).fmt(f)
+    }
+}
+
+impl Serialize for Base64<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+pub fn serialize<S: Serializer>(data: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+    Base64(data).serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+    struct BytesVisitor;
+
+    impl<'de> de::Visitor<'de> for BytesVisitor {
+        type Value = Bytes;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("base64 string or byte array")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Bytes, E> {
+            STANDARD.decode(value).map(Bytes::from).map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Bytes, E> {
+            Ok(Bytes::copy_from_slice(value))
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Bytes, E> {
+            Ok(Bytes::from(value))
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Bytes, A::Error> {
+            let mut data = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+            while let Some(byte) = seq.next_element::<u8>()? {
+                data.push(byte);
+            }
+            Ok(Bytes::from(data))
+        }
+    }
+
+    deserializer.deserialize_any(BytesVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{Blob, ContentPart};
+
+    #[test]
+    fn test_base64_round_trip_and_legacy_arrays() {
+        let part = ContentPart::image(&b"\x89PNG"[..], "image/png");
+        let json = serde_json::to_value(&part).unwrap();
+        assert_eq!(json["data"], "iVBORw==");
+        let ContentPart::Image { data, .. } = serde_json::from_value(json).unwrap() else {
+            panic!("expected an image part");
+        };
+        assert_eq!(&data[..], b"\x89PNG");
+
+        let legacy: Blob = serde_json::from_str(r#"{"mime_type": "audio/pcm", "data": [1, 2, 3]}"#).unwrap();
+        assert_eq!(&legacy.data[..], &[1, 2, 3]);
+    }
+}
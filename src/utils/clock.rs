@@ -0,0 +1,222 @@
+//! Injectable clock and ID generation
+//!
+//! Events, sessions and invocation contexts take their timestamps from
+//! [`now`] and their IDs from [`new_uuid`] and [`new_id`]. These use the
+//! system clock and random v4 UUIDs unless a [`Determinism`] is in scope
+//! (see [`Runner::with_determinism`](crate::runners::Runner::with_determinism))
+//! or process-wide replacements were installed, so tests, replays and
+//! evaluations can produce identical transcripts run after run.
+//!
+//! Scopes follow the task that polls the run: work moved to other tasks with
+//! `tokio::spawn` falls back to the process-wide clock and IDs.
+
+use crate::types::Timestamp;
+use chrono::{Duration, Utc};
+use futures::{Future, Stream, StreamExt};
+use once_cell::sync::Lazy;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex, RwLock,
+};
+use uuid::Uuid;
+
+/// Source of timestamps
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Timestamp;
+}
+
+/// Source of unique IDs
+pub trait IdGenerator: Send + Sync {
+    fn new_uuid(&self) -> Uuid;
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Utc::now()
+    }
+}
+
+/// Random (v4) UUIDs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomIds;
+
+impl IdGenerator for RandomIds {
+    fn new_uuid(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// A clock that only moves when told to, optionally by a fixed tick per reading
+#[derive(Debug)]
+pub struct ManualClock {
+    current: Mutex<Timestamp>,
+    tick: Duration,
+}
+
+impl ManualClock {
+    pub fn new(start: Timestamp) -> Self {
+        Self {
+            current: Mutex::new(start),
+            tick: Duration::zero(),
+        }
+    }
+
+    /// Advance by `tick` after every reading, so successive timestamps differ
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.current.lock().expect("clock lock poisoned") += by;
+    }
+
+    pub fn set(&self, to: Timestamp) {
+        *self.current.lock().expect("clock lock poisoned") = to;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        let mut current = self.current.lock().expect("clock lock poisoned");
+        let now = *current;
+        *current += self.tick;
+        now
+    }
+}
+
+/// UUIDs counting up from 1 (`00000000-0000-0000-0000-000000000001`, ...)
+#[derive(Debug, Default)]
+pub struct SequentialIds {
+    next: AtomicU64,
+}
+
+impl SequentialIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl IdGenerator for SequentialIds {
+    fn new_uuid(&self) -> Uuid {
+        Uuid::from_u128(u128::from(self.next.fetch_add(1, Ordering::Relaxed)) + 1)
+    }
+}
+
+/// A clock and ID generator used together for a run
+#[derive(Clone)]
+pub struct Determinism {
+    pub clock: Arc<dyn Clock>,
+    pub ids: Arc<dyn IdGenerator>,
+}
+
+impl std::fmt::Debug for Determinism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Determinism").finish_non_exhaustive()
+    }
+}
+
+impl Determinism {
+    pub fn new(clock: Arc<dyn Clock>, ids: Arc<dyn IdGenerator>) -> Self {
+        Self { clock, ids }
+    }
+
+    /// A clock starting at `start` that ticks a millisecond per reading, and sequential IDs
+    pub fn fixed(start: Timestamp) -> Self {
+        Self::new(
+            Arc::new(ManualClock::new(start).with_tick(Duration::milliseconds(1))),
+            Arc::new(SequentialIds::new()),
+        )
+    }
+
+    /// Run `future` with this clock and these IDs
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        SCOPED.scope(self.clone(), future).await
+    }
+
+    /// Poll `stream` with this clock and these IDs
+    pub fn scope_stream<S>(&self, mut stream: S) -> impl Stream<Item = S::Item> + Send
+    where
+        S: Stream + Unpin + Send,
+    {
+        let determinism = self.clone();
+        futures::stream::poll_fn(move |cx| SCOPED.sync_scope(determinism.clone(), || stream.poll_next_unpin(cx)))
+    }
+}
+
+tokio::task_local! {
+    static SCOPED: Determinism;
+}
+
+static GLOBAL: Lazy<RwLock<Determinism>> =
+    Lazy::new(|| RwLock::new(Determinism::new(Arc::new(SystemClock), Arc::new(RandomIds))));
+// Skips the global lock until a replacement is installed
+static GLOBAL_REPLACED: AtomicBool = AtomicBool::new(false);
+
+/// Replace the process-wide clock, e.g. in a replay tool
+pub fn set_global_clock(clock: Arc<dyn Clock>) {
+    GLOBAL.write().expect("clock lock poisoned").clock = clock;
+    GLOBAL_REPLACED.store(true, Ordering::Release);
+}
+
+/// Replace the process-wide ID generator
+pub fn set_global_id_generator(ids: Arc<dyn IdGenerator>) {
+    GLOBAL.write().expect("clock lock poisoned").ids = ids;
+    GLOBAL_REPLACED.store(true, Ordering::Release);
+}
+
+/// Current time from the scoped, global or system clock
+pub fn now() -> Timestamp {
+    if let Ok(now) = SCOPED.try_with(|scoped| scoped.clock.now()) {
+        return now;
+    }
+    if GLOBAL_REPLACED.load(Ordering::Acquire) {
+        return GLOBAL.read().expect("clock lock poisoned").clock.now();
+    }
+    Utc::now()
+}
+
+/// New UUID from the scoped, global or random generator
+pub fn new_uuid() -> Uuid {
+    if let Ok(uuid) = SCOPED.try_with(|scoped| scoped.ids.new_uuid()) {
+        return uuid;
+    }
+    if GLOBAL_REPLACED.load(Ordering::Acquire) {
+        return GLOBAL.read().expect("clock lock poisoned").ids.new_uuid();
+    }
+    Uuid::new_v4()
+}
+
+/// [`new_uuid`] as a string
+pub fn new_id() -> String {
+    new_uuid().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn test_scoped_clock_and_ids_are_deterministic() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let readings = || async {
+            let determinism = Determinism::fixed(start);
+            determinism.scope(async { (now(), now(), new_id(), new_id()) }).await
+        };
+
+        let (first, second, id, next_id) = readings().await;
+        assert_eq!((first, second - first), (start, Duration::milliseconds(1)));
+        assert_eq!(id, "00000000-0000-0000-0000-000000000001");
+        assert_eq!(next_id, "00000000-0000-0000-0000-000000000002");
+        assert_eq!(readings().await, (first, second, id, next_id));
+
+        // Outside the scope the system clock and random IDs apply
+        assert!(now() > start);
+        assert_ne!(new_uuid().get_version_num(), 0);
+    }
+}
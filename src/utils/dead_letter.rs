@@ -0,0 +1,395 @@
+//! Persisted dead-letter queue for failed background work
+//!
+//! Background tasks (memory ingestion, telemetry export, webhook posts) push
+//! work they could not complete here instead of dropping it. A retry worker
+//! hands due items back to the handler registered for their kind with
+//! exponential backoff; items that exhaust their attempts stay parked until
+//! they are replayed or deleted through the admin API.
+//!
+//! Items are kept in memory and, when a path is given, persisted as a JSON
+//! file rewritten atomically on every change.
+
+use crate::{error::Result, types::Timestamp};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{sync::Mutex, task::JoinHandle};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::{info, warn};
+
+/// A unit of background work that failed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: String,
+
+    /// Kind of work, used to pick the retry handler (e.g. `webhook`)
+    pub kind: String,
+
+    /// Everything the handler needs to redo the work
+    pub payload: serde_json::Value,
+
+    /// Most recent failure
+    pub error: String,
+
+    pub attempts: u32,
+    pub first_failed_at: Timestamp,
+    pub last_failed_at: Timestamp,
+
+    /// When the retry worker picks the item up next; `None` once attempts are exhausted
+    pub next_retry_at: Option<Timestamp>,
+}
+
+/// Redoes failed work of one kind
+#[async_trait]
+pub trait DeadLetterHandler: Send + Sync {
+    async fn retry(&self, letter: &DeadLetter) -> Result<()>;
+}
+
+/// Retry policy of a [`DeadLetterQueue`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeadLetterConfig {
+    /// File the queue is persisted to; in memory only when unset
+    pub path: Option<PathBuf>,
+
+    /// Attempts (including the original failure) before an item is parked
+    pub max_attempts: u32,
+
+    /// Delay before the first retry; doubled after every failed attempt
+    pub initial_backoff_seconds: u64,
+
+    /// Upper bound on the retry delay
+    pub max_backoff_seconds: u64,
+
+    /// How often the retry worker looks for due items
+    pub poll_interval_seconds: u64,
+}
+
+impl Default for DeadLetterConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_attempts: 5,
+            initial_backoff_seconds: 30,
+            max_backoff_seconds: 3600,
+            poll_interval_seconds: 10,
+        }
+    }
+}
+
+impl DeadLetterConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn with_backoff(mut self, initial_seconds: u64, max_seconds: u64) -> Self {
+        self.initial_backoff_seconds = initial_seconds;
+        self.max_backoff_seconds = max_seconds;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, seconds: u64) -> Self {
+        self.poll_interval_seconds = seconds;
+        self
+    }
+
+    /// Delay after the given number of failed attempts
+    fn backoff(&self, attempts: u32) -> chrono::Duration {
+        let factor = 1u64 << attempts.saturating_sub(1).min(32);
+        let seconds = self.initial_backoff_seconds.saturating_mul(factor).min(self.max_backoff_seconds);
+        chrono::Duration::seconds(seconds as i64)
+    }
+}
+
+/// Outcome of one retry pass
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryReport {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub parked: usize,
+}
+
+/// Dead-letter queue with per-kind retry handlers
+pub struct DeadLetterQueue {
+    config: DeadLetterConfig,
+    letters: Mutex<BTreeMap<String, DeadLetter>>,
+    handlers: RwLock<HashMap<String, Arc<dyn DeadLetterHandler>>>,
+}
+
+impl DeadLetterQueue {
+    /// Open the queue, loading items persisted by a previous run
+    pub async fn open(config: DeadLetterConfig) -> Result<Self> {
+        let letters = match &config.path {
+            Some(path) if tokio::fs::try_exists(path).await? => {
+                let data = tokio::fs::read(path).await?;
+                let letters: Vec<DeadLetter> = serde_json::from_slice(&data)?;
+                letters.into_iter().map(|letter| (letter.id.clone(), letter)).collect()
+            }
+            _ => BTreeMap::new(),
+        };
+        Ok(Self {
+            config,
+            letters: Mutex::new(letters),
+            handlers: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Queue kept in memory only
+    pub fn in_memory(config: DeadLetterConfig) -> Self {
+        Self {
+            config: DeadLetterConfig { path: None, ..config },
+            letters: Mutex::new(BTreeMap::new()),
+            handlers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &DeadLetterConfig {
+        &self.config
+    }
+
+    /// Register the handler that retries items of `kind`
+    pub fn register_handler(&self, kind: impl Into<String>, handler: Arc<dyn DeadLetterHandler>) {
+        self.handlers.write().unwrap().insert(kind.into(), handler);
+    }
+
+    /// Record failed work; returns the new item's id
+    pub async fn push(
+        &self,
+        kind: impl Into<String>,
+        payload: serde_json::Value,
+        error: impl ToString,
+    ) -> Result<String> {
+        let now = crate::types::now();
+        let letter = DeadLetter {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind: kind.into(),
+            payload,
+            error: error.to_string(),
+            attempts: 1,
+            first_failed_at: now,
+            last_failed_at: now,
+            next_retry_at: (self.config.max_attempts > 1).then(|| now + self.config.backoff(1)),
+        };
+        warn!("Dead-lettered {} work {}: {}", letter.kind, letter.id, letter.error);
+        let id = letter.id.clone();
+        let mut letters = self.letters.lock().await;
+        letters.insert(id.clone(), letter);
+        self.persist(&letters).await?;
+        Ok(id)
+    }
+
+    /// All items, oldest failure first
+    pub async fn list(&self) -> Vec<DeadLetter> {
+        let mut letters: Vec<_> = self.letters.lock().await.values().cloned().collect();
+        letters.sort_by_key(|letter| letter.first_failed_at);
+        letters
+    }
+
+    pub async fn get(&self, id: &str) -> Option<DeadLetter> {
+        self.letters.lock().await.get(id).cloned()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.letters.lock().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Drop an item without retrying it; returns whether it existed
+    pub async fn delete(&self, id: &str) -> Result<bool> {
+        let mut letters = self.letters.lock().await;
+        let existed = letters.remove(id).is_some();
+        if existed {
+            self.persist(&letters).await?;
+        }
+        Ok(existed)
+    }
+
+    /// Retry one item now, regardless of its schedule or exhausted attempts
+    pub async fn replay(&self, id: &str) -> Result<bool> {
+        let letter = self
+            .get(id)
+            .await
+            .ok_or_else(|| crate::adk_error!(ValidationError, "Dead letter '{}' not found", id))?;
+        self.attempt(letter, true).await
+    }
+
+    /// Retry every item whose retry time has come
+    pub async fn retry_due(&self) -> Result<RetryReport> {
+        let now = crate::types::now();
+        let due: Vec<DeadLetter> = self
+            .letters
+            .lock()
+            .await
+            .values()
+            .filter(|letter| letter.next_retry_at.is_some_and(|at| at <= now))
+            .cloned()
+            .collect();
+
+        let mut report = RetryReport::default();
+        for letter in due {
+            if self.attempt(letter, false).await? {
+                report.succeeded += 1;
+            } else {
+                report.failed += 1;
+            }
+        }
+        report.parked = self
+            .letters
+            .lock()
+            .await
+            .values()
+            .filter(|letter| letter.next_retry_at.is_none())
+            .count();
+        Ok(report)
+    }
+
+    /// Run `retry_due` on `tasks` every poll interval until `cancel` fires
+    pub fn spawn_retry_worker(self: Arc<Self>, tasks: &TaskTracker, cancel: CancellationToken) -> JoinHandle<()> {
+        let interval = Duration::from_secs(self.config.poll_interval_seconds.max(1));
+        tasks.spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                match self.retry_due().await {
+                    Ok(report) if report.succeeded + report.failed > 0 => {
+                        info!(
+                            "Dead-letter retry: {} succeeded, {} failed, {} parked",
+                            report.succeeded, report.failed, report.parked
+                        );
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("Dead-letter retry pass failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Hand an item to its handler; removes it on success, reschedules or parks it on failure
+    async fn attempt(&self, letter: DeadLetter, forced: bool) -> Result<bool> {
+        let handler = self.handlers.read().unwrap().get(&letter.kind).cloned();
+        let outcome = match handler {
+            Some(handler) => handler.retry(&letter).await,
+            None => Err(crate::adk_error!(
+                ConfigError,
+                "No dead-letter handler registered for '{}'",
+                letter.kind
+            )),
+        };
+
+        let mut letters = self.letters.lock().await;
+        let Some(stored) = letters.get_mut(&letter.id) else {
+            // Deleted while the retry was running
+            return Ok(outcome.is_ok());
+        };
+        let succeeded = match outcome {
+            Ok(()) => {
+                letters.remove(&letter.id);
+                true
+            }
+            Err(e) => {
+                let now = crate::types::now();
+                stored.attempts += 1;
+                stored.error = e.to_string();
+                stored.last_failed_at = now;
+                // A manual replay gets one attempt without reviving the schedule
+                stored.next_retry_at = (!forced && stored.attempts < self.config.max_attempts)
+                    .then(|| now + self.config.backoff(stored.attempts));
+                false
+            }
+        };
+        self.persist(&letters).await?;
+        Ok(succeeded)
+    }
+
+    async fn persist(&self, letters: &BTreeMap<String, DeadLetter>) -> Result<()> {
+        let Some(path) = &self.config.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let data = serde_json::to_vec_pretty(&letters.values().collect::<Vec<_>>())?;
+        let temp = path.with_extension("tmp");
+        tokio::fs::write(&temp, data).await?;
+        tokio::fs::rename(&temp, path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FlakyHandler {
+        failures_left: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl DeadLetterHandler for FlakyHandler {
+        async fn retry(&self, _letter: &DeadLetter) -> Result<()> {
+            if self
+                .failures_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1))
+                .is_ok()
+            {
+                return Err(crate::adk_error!(NetworkError, "still down"));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_persists_and_parks_items() {
+        let path = std::env::temp_dir().join(format!("adk-dlq-{}.json", uuid::Uuid::new_v4()));
+        let config = DeadLetterConfig::new()
+            .with_path(&path)
+            .with_max_attempts(2)
+            .with_backoff(0, 0);
+
+        let queue = DeadLetterQueue::open(config.clone()).await.unwrap();
+        let id = queue
+            .push("webhook", serde_json::json!({"url": "http://example.invalid"}), "connection refused")
+            .await
+            .unwrap();
+
+        // Reopening loads the persisted item
+        let queue = DeadLetterQueue::open(config).await.unwrap();
+        assert_eq!(queue.get(&id).await.unwrap().attempts, 1);
+        queue.register_handler("webhook", Arc::new(FlakyHandler { failures_left: AtomicUsize::new(1) }));
+
+        let report = queue.retry_due().await.unwrap();
+        assert_eq!(report, RetryReport { succeeded: 0, failed: 1, parked: 1 });
+        let parked = queue.get(&id).await.unwrap();
+        assert_eq!((parked.attempts, parked.next_retry_at), (2, None));
+        assert_eq!(parked.error, "Network error: still down");
+
+        assert!(queue.replay(&id).await.unwrap());
+        assert!(queue.is_empty().await);
+        let persisted: Vec<DeadLetter> = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert!(persisted.is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+//! Repair of almost-valid JSON produced by models
+//!
+//! Structured output often arrives wrapped in a Markdown code fence, with a
+//! sentence before or after it, with trailing commas, or cut off mid-object
+//! when the model ran out of tokens. [`repair_json`] fixes these in order of
+//! increasing invasiveness, as far as the [`RepairStrictness`] allows, and
+//! reports which fixes were needed.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How much repair is attempted before output is rejected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairStrictness {
+    /// Only surrounding whitespace is tolerated
+    Strict,
+
+    /// Strip code fences and surrounding prose, drop trailing commas
+    #[default]
+    Lenient,
+
+    /// Also close unterminated strings, arrays and objects, e.g. of truncated output
+    BestEffort,
+}
+
+/// A fix applied to make output parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonRepair {
+    CodeFence,
+    SurroundingText,
+    TrailingCommas,
+    UnclosedBrackets,
+}
+
+/// Parsed output and the fixes it needed
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepairedJson {
+    pub value: Value,
+    pub repairs: Vec<JsonRepair>,
+}
+
+impl RepairedJson {
+    pub fn was_repaired(&self) -> bool {
+        !self.repairs.is_empty()
+    }
+}
+
+/// Parse `text` as JSON, repairing it as far as `strictness` allows
+pub fn repair_json(text: &str, strictness: RepairStrictness) -> Result<RepairedJson> {
+    let trimmed = text.trim();
+    let error = match serde_json::from_str(trimmed) {
+        Ok(value) => return Ok(RepairedJson { value, repairs: Vec::new() }),
+        Err(e) => e,
+    };
+    if strictness == RepairStrictness::Strict {
+        return Err(crate::adk_error!(ModelError, "Model output is not valid JSON: {}", error));
+    }
+
+    let mut repairs = Vec::new();
+    let mut json = trimmed.to_string();
+    if let Some(inner) = strip_code_fence(&json) {
+        json = inner.to_string();
+        repairs.push(JsonRepair::CodeFence);
+    }
+    if let Some(span) = json_span(&json).filter(|span| *span != json) {
+        json = span.to_string();
+        repairs.push(JsonRepair::SurroundingText);
+    }
+    let without_commas = remove_trailing_commas(&json);
+    if without_commas != json {
+        json = without_commas;
+        repairs.push(JsonRepair::TrailingCommas);
+    }
+
+    let error = match serde_json::from_str(&json) {
+        Ok(value) => return Ok(RepairedJson { value, repairs }),
+        Err(e) => e,
+    };
+    if strictness == RepairStrictness::BestEffort {
+        let closed = close_brackets(&json);
+        if closed != json {
+            if let Ok(value) = serde_json::from_str(&closed) {
+                repairs.push(JsonRepair::UnclosedBrackets);
+                return Ok(RepairedJson { value, repairs });
+            }
+        }
+    }
+    Err(crate::adk_error!(ModelError, "Model output is not valid JSON even after repair: {}", error))
+}
+
+/// Content of the first Markdown code fence; an unclosed fence runs to the end
+fn strip_code_fence(text: &str) -> Option<&str> {
+    let start = text.find("```")?;
+    let after = &text[start + 3..];
+    // Skip the language tag
+    let body = &after[after.find('\n').map_or(after.len(), |newline| newline + 1)..];
+    let end = body.find("```").unwrap_or(body.len());
+    Some(body[..end].trim())
+}
+
+/// String-aware scan of JSON text: calls `visit` with each character outside strings
+fn scan(text: &str, mut visit: impl FnMut(usize, char) -> bool) -> ScanState {
+    let mut state = ScanState::default();
+    for (index, c) in text.char_indices() {
+        if state.in_string {
+            if state.escaped {
+                state.escaped = false;
+            } else if c == '\\' {
+                state.escaped = true;
+            } else if c == '"' {
+                state.in_string = false;
+            }
+            continue;
+        }
+        if c == '"' {
+            state.in_string = true;
+            continue;
+        }
+        if !visit(index, c) {
+            break;
+        }
+    }
+    state
+}
+
+#[derive(Debug, Default)]
+struct ScanState {
+    in_string: bool,
+    escaped: bool,
+}
+
+/// The first object or array in `text`, up to its closing bracket or the end of the text
+fn json_span(text: &str) -> Option<&str> {
+    let start = text.find(['{', '['])?;
+    let rest = &text[start..];
+    let mut depth = 0usize;
+    let mut end = rest.len();
+    scan(rest, |index, c| {
+        match c {
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    end = index + 1;
+                    return false;
+                }
+            }
+            _ => {}
+        }
+        true
+    });
+    Some(rest[..end].trim())
+}
+
+fn remove_trailing_commas(text: &str) -> String {
+    let mut dropped = Vec::new();
+    scan(text, |index, c| {
+        if c == ',' && matches!(text[index + 1..].trim_start().chars().next(), Some('}' | ']')) {
+            dropped.push(index);
+        }
+        true
+    });
+    text.char_indices()
+        .filter(|(index, _)| !dropped.contains(index))
+        .map(|(_, c)| c)
+        .collect()
+}
+
+/// Terminate an open string and close open arrays and objects
+fn close_brackets(text: &str) -> String {
+    let mut open = Vec::new();
+    let state = scan(text, |_, c| {
+        match c {
+            '{' => open.push('}'),
+            '[' => open.push(']'),
+            '}' | ']' => {
+                open.pop();
+            }
+            _ => {}
+        }
+        true
+    });
+
+    let mut closed = text.trim_end().to_string();
+    if state.in_string {
+        if state.escaped {
+            closed.pop();
+        }
+        closed.push('"');
+    } else if closed.ends_with(',') {
+        closed.pop();
+    } else if closed.ends_with(':') {
+        closed.push_str("null");
+    }
+    closed.extend(open.iter().rev());
+    closed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_lenient_repairs_fences_prose_and_trailing_commas() {
+        let fenced = "Sure! Here it is:\n```json\n{\"city\": \"Oslo\", \"tags\": [\"a, ]\", \"b\",],}\n```\nAnything else?";
+        let repaired = repair_json(fenced, RepairStrictness::Lenient).unwrap();
+        assert_eq!(repaired.value, json!({ "city": "Oslo", "tags": ["a, ]", "b"] }));
+        assert_eq!(repaired.repairs, [JsonRepair::CodeFence, JsonRepair::TrailingCommas]);
+
+        let prose = repair_json("The answer is {\"ok\": true}. Hope that helps {sic}", RepairStrictness::Lenient).unwrap();
+        assert_eq!((prose.value, prose.repairs), (json!({ "ok": true }), vec![JsonRepair::SurroundingText]));
+
+        let clean = repair_json(" [1, 2] ", RepairStrictness::Strict).unwrap();
+        assert!(!clean.was_repaired());
+        assert!(repair_json("```json\n[1]\n```", RepairStrictness::Strict).is_err());
+    }
+
+    #[test]
+    fn test_best_effort_closes_truncated_output() {
+        let truncated = "{\"items\": [{\"name\": \"tea\", \"note\": \"hot \\";
+        assert!(repair_json(truncated, RepairStrictness::Lenient).is_err());
+        let repaired = repair_json(truncated, RepairStrictness::BestEffort).unwrap();
+        assert_eq!(repaired.value, json!({ "items": [{ "name": "tea", "note": "hot " }] }));
+        assert_eq!(repaired.repairs, [JsonRepair::UnclosedBrackets]);
+
+        let dangling = repair_json("{\"a\": 1, \"b\":", RepairStrictness::BestEffort).unwrap();
+        assert_eq!(dangling.value, json!({ "a": 1, "b": null }));
+        assert!(repair_json("no json here", RepairStrictness::BestEffort).is_err());
+    }
+}
@@ -1,3 +1,24 @@
 //! Utility functions and helpers
 
-// Stub implementation
+pub mod audio;
+pub mod base64_bytes;
+pub mod clock;
+pub mod dead_letter;
+pub mod json_repair;
+pub mod trace;
+pub mod usage;
+
+pub use audio::{
+    downmix, pcm16_from_bytes, pcm16_to_bytes, pcm_mime_type, pcm_to_wav, resample, LiveAudioInput, PcmChunker,
+    LIVE_FRAME_MS, LIVE_INPUT_SAMPLE_RATE, LIVE_OUTPUT_SAMPLE_RATE,
+};
+#[cfg(feature = "audio")]
+pub use audio::{decode_audio, decode_audio_with, DecodedAudio};
+pub use clock::{
+    new_id, new_uuid, set_global_clock, set_global_id_generator, Clock, Determinism, IdGenerator, ManualClock, RandomIds,
+    SequentialIds, SystemClock,
+};
+pub use dead_letter::{DeadLetter, DeadLetterConfig, DeadLetterHandler, DeadLetterQueue, RetryReport};
+pub use json_repair::{repair_json, JsonRepair, RepairStrictness, RepairedJson};
+pub use trace::{global_trace_store, InvocationTrace, SpanKind, TraceCollector, TraceSpan, TraceStore, DEFAULT_TRACE_RETENTION};
+pub use usage::{global_usage_tracker, InvocationRecord, ModelCallRecord, UsageTracker};
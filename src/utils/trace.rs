@@ -0,0 +1,278 @@
+//! Per-invocation traces of the agent decision loop
+//!
+//! Each invocation run through a runner collects ordered spans — model calls
+//! with request/response sizes, tool calls with their durations and session
+//! state mutations — which the dev UI retrieves to step through what the agent
+//! did.
+
+use crate::{
+    models::Usage,
+    types::{InvocationId, SessionId, Timestamp},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, RwLock},
+    time::Instant,
+};
+
+/// Default number of invocation traces kept in memory
+pub const DEFAULT_TRACE_RETENTION: usize = 1_000;
+
+/// What a span measured
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpanKind {
+    ModelCall {
+        model: String,
+        /// Size of the serialized request contents and configuration
+        request_bytes: usize,
+        /// Size of the serialized response content
+        response_bytes: usize,
+        prompt_tokens: Option<u32>,
+        completion_tokens: Option<u32>,
+    },
+    ToolCall {
+        tool: String,
+        args_bytes: usize,
+        result_bytes: usize,
+    },
+    StateMutation {
+        /// State keys written by the event
+        keys: Vec<String>,
+        event_id: String,
+    },
+}
+
+/// One step of an invocation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceSpan {
+    /// Agent that performed the step
+    pub agent: String,
+    pub started_at: Timestamp,
+    pub duration_ms: u64,
+    #[serde(flatten)]
+    pub kind: SpanKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TraceSpan {
+    /// Span for a step that started at `started` and just finished
+    pub fn finished(agent: impl Into<String>, kind: SpanKind, started: Instant) -> Self {
+        let elapsed = started.elapsed();
+        Self {
+            agent: agent.into(),
+            started_at: crate::types::now()
+                - chrono::Duration::from_std(elapsed).unwrap_or_else(|_| chrono::Duration::zero()),
+            duration_ms: elapsed.as_millis() as u64,
+            kind,
+            error: None,
+        }
+    }
+
+    pub fn with_error(mut self, error: impl ToString) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+/// Serialized size of `value` in bytes
+pub fn json_size<T: Serialize + ?Sized>(value: &T) -> usize {
+    serde_json::to_vec(value).map(|bytes| bytes.len()).unwrap_or(0)
+}
+
+/// Span sink shared by an invocation and its sub-agents; clones record into the same trace
+#[derive(Debug, Clone, Default)]
+pub struct TraceCollector {
+    spans: Arc<Mutex<Vec<TraceSpan>>>,
+}
+
+impl TraceCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, span: TraceSpan) {
+        self.spans.lock().expect("trace lock poisoned").push(span);
+    }
+
+    /// Spans recorded so far, in completion order
+    pub fn spans(&self) -> Vec<TraceSpan> {
+        self.spans.lock().expect("trace lock poisoned").clone()
+    }
+}
+
+/// Trace of one invocation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvocationTrace {
+    pub invocation_id: InvocationId,
+    pub session_id: SessionId,
+    pub app_name: String,
+    pub agent: String,
+    pub started_at: Timestamp,
+
+    /// Unset while the invocation is still running
+    pub finished_at: Option<Timestamp>,
+    pub is_error: bool,
+    pub spans: Vec<TraceSpan>,
+}
+
+impl InvocationTrace {
+    /// Tokens used by the invocation's model calls; unset if no call reported usage
+    pub fn usage(&self) -> Option<Usage> {
+        let calls: Vec<(u32, u32)> = self
+            .spans
+            .iter()
+            .filter_map(|span| match &span.kind {
+                SpanKind::ModelCall { prompt_tokens, completion_tokens, .. }
+                    if prompt_tokens.is_some() || completion_tokens.is_some() =>
+                {
+                    Some((prompt_tokens.unwrap_or(0), completion_tokens.unwrap_or(0)))
+                }
+                _ => None,
+            })
+            .collect();
+        if calls.is_empty() {
+            return None;
+        }
+        let prompt_tokens = calls.iter().map(|(prompt, _)| prompt).sum::<u32>();
+        let completion_tokens = calls.iter().map(|(_, completion)| completion).sum::<u32>();
+        Some(Usage {
+            prompt_tokens: Some(prompt_tokens),
+            completion_tokens: Some(completion_tokens),
+            total_tokens: Some(prompt_tokens + completion_tokens),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct TraceEntry {
+    trace: InvocationTrace,
+    collector: TraceCollector,
+}
+
+/// Bounded in-memory store of invocation traces
+#[derive(Debug, Clone)]
+pub struct TraceStore {
+    entries: Arc<RwLock<VecDeque<TraceEntry>>>,
+    retention: usize,
+}
+
+impl TraceStore {
+    pub fn new() -> Self {
+        Self::with_retention(DEFAULT_TRACE_RETENTION)
+    }
+
+    /// Create a store keeping at most `retention` traces
+    pub fn with_retention(retention: usize) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(VecDeque::new())),
+            retention,
+        }
+    }
+
+    /// Start tracing an invocation whose spans are recorded into `collector`
+    pub fn begin(
+        &self,
+        invocation_id: InvocationId,
+        session_id: impl Into<SessionId>,
+        app_name: impl Into<String>,
+        agent: impl Into<String>,
+        collector: TraceCollector,
+    ) {
+        let trace = InvocationTrace {
+            invocation_id,
+            session_id: session_id.into(),
+            app_name: app_name.into(),
+            agent: agent.into(),
+            started_at: crate::types::now(),
+            finished_at: None,
+            is_error: false,
+            spans: Vec::new(),
+        };
+        let mut entries = self.entries.write().expect("trace lock poisoned");
+        if entries.len() >= self.retention {
+            entries.pop_front();
+        }
+        entries.push_back(TraceEntry { trace, collector });
+    }
+
+    /// Mark an invocation as finished
+    pub fn finish(&self, invocation_id: InvocationId, is_error: bool) {
+        let mut entries = self.entries.write().expect("trace lock poisoned");
+        if let Some(entry) = entries.iter_mut().rev().find(|e| e.trace.invocation_id == invocation_id) {
+            entry.trace.finished_at = Some(crate::types::now());
+            entry.trace.is_error = is_error;
+        }
+    }
+
+    /// Trace of an invocation in `session_id`, including spans of a running invocation
+    pub fn get(&self, session_id: &str, invocation_id: InvocationId) -> Option<InvocationTrace> {
+        let entries = self.entries.read().expect("trace lock poisoned");
+        entries
+            .iter()
+            .rev()
+            .find(|e| e.trace.invocation_id == invocation_id && e.trace.session_id == session_id)
+            .map(|entry| InvocationTrace {
+                spans: entry.collector.spans(),
+                ..entry.trace.clone()
+            })
+    }
+}
+
+impl Default for TraceStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static GLOBAL_TRACES: once_cell::sync::Lazy<TraceStore> = once_cell::sync::Lazy::new(TraceStore::new);
+
+/// Get the global trace store
+pub fn global_trace_store() -> &'static TraceStore {
+    &GLOBAL_TRACES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_snapshots_running_and_finished_traces() {
+        let store = TraceStore::with_retention(1);
+        let collector = TraceCollector::new();
+        let invocation_id = uuid::Uuid::new_v4();
+        store.begin(invocation_id, "s1", "app", "agent", collector.clone());
+
+        collector.record(TraceSpan::finished(
+            "agent",
+            SpanKind::ToolCall { tool: "search".into(), args_bytes: 2, result_bytes: 10 },
+            Instant::now(),
+        ));
+        let running = store.get("s1", invocation_id).unwrap();
+        assert!(running.finished_at.is_none());
+        assert_eq!(running.spans.len(), 1);
+        assert!(store.get("other-session", invocation_id).is_none());
+
+        store.finish(invocation_id, false);
+        assert!(store.get("s1", invocation_id).unwrap().finished_at.is_some());
+
+        // Retention evicts the oldest trace
+        store.begin(uuid::Uuid::new_v4(), "s1", "app", "agent", TraceCollector::new());
+        assert!(store.get("s1", invocation_id).is_none());
+    }
+
+    #[test]
+    fn test_span_serializes_kind_inline() {
+        let span = TraceSpan::finished(
+            "agent",
+            SpanKind::StateMutation { keys: vec!["cart".into()], event_id: "e1".into() },
+            Instant::now(),
+        );
+        let json = serde_json::to_value(&span).unwrap();
+        assert_eq!(json["type"], "state_mutation");
+        assert_eq!(json["keys"][0], "cart");
+        assert!(json.get("error").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+//! In-process usage tracking for model calls and agent invocations
+
+use crate::{
+    experiments::ExperimentAssignment,
+    models::{global_catalog, Usage},
+    types::Timestamp,
+};
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// Default number of records of each kind kept in memory
+pub const DEFAULT_USAGE_RETENTION: usize = 100_000;
+
+/// A single model call
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelCallRecord {
+    pub timestamp: Timestamp,
+    pub app_name: String,
+    pub agent: String,
+    pub model: String,
+    pub latency_ms: u64,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+
+    /// Cost in USD, if pricing is known for the model
+    pub cost_usd: Option<f64>,
+    pub is_error: bool,
+
+    /// Experiment and variant of the session the call served
+    pub experiment: Option<String>,
+    pub variant: Option<String>,
+}
+
+impl ModelCallRecord {
+    /// Build a record from a completed call, pricing it from the model catalog
+    pub fn new(
+        app_name: impl Into<String>,
+        agent: impl Into<String>,
+        model: impl Into<String>,
+        latency: Duration,
+        usage: Option<&Usage>,
+        is_error: bool,
+    ) -> Self {
+        let model = model.into();
+        let prompt_tokens = usage.and_then(|u| u.prompt_tokens).unwrap_or(0);
+        let completion_tokens = usage.and_then(|u| u.completion_tokens).unwrap_or(0);
+        let cost_usd = global_catalog()
+            .lookup(&model)
+            .and_then(|metadata| metadata.pricing)
+            .map(|pricing| pricing.cost(prompt_tokens, completion_tokens));
+
+        Self {
+            timestamp: crate::types::now(),
+            app_name: app_name.into(),
+            agent: agent.into(),
+            model,
+            latency_ms: latency.as_millis() as u64,
+            prompt_tokens,
+            completion_tokens,
+            cost_usd,
+            is_error,
+            experiment: None,
+            variant: None,
+        }
+    }
+
+    pub fn with_experiment(mut self, assignment: Option<&ExperimentAssignment>) -> Self {
+        self.experiment = assignment.map(|a| a.experiment.clone());
+        self.variant = assignment.map(|a| a.variant.name.clone());
+        self
+    }
+}
+
+/// A single agent invocation run through a runner
+#[derive(Debug, Clone, Serialize)]
+pub struct InvocationRecord {
+    pub timestamp: Timestamp,
+    pub app_name: String,
+    pub agent: String,
+
+    /// Version of the agent that served the invocation
+    pub agent_version: Option<String>,
+
+    /// Configuration hash of the agent that served the invocation
+    pub config_hash: Option<String>,
+    pub latency_ms: u64,
+    pub is_error: bool,
+}
+
+impl InvocationRecord {
+    pub fn new(app_name: impl Into<String>, agent: impl Into<String>, latency: Duration, is_error: bool) -> Self {
+        Self {
+            timestamp: crate::types::now(),
+            app_name: app_name.into(),
+            agent: agent.into(),
+            agent_version: None,
+            config_hash: None,
+            latency_ms: latency.as_millis() as u64,
+            is_error,
+        }
+    }
+
+    pub fn with_agent_version(mut self, version: Option<String>, config_hash: Option<String>) -> Self {
+        self.agent_version = version;
+        self.config_hash = config_hash;
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct UsageLog {
+    model_calls: VecDeque<ModelCallRecord>,
+    invocations: VecDeque<InvocationRecord>,
+}
+
+/// Bounded in-memory log of model calls and invocations
+#[derive(Debug, Clone)]
+pub struct UsageTracker {
+    log: Arc<RwLock<UsageLog>>,
+    retention: usize,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::with_retention(DEFAULT_USAGE_RETENTION)
+    }
+
+    /// Create a tracker keeping at most `retention` records of each kind
+    pub fn with_retention(retention: usize) -> Self {
+        Self {
+            log: Arc::new(RwLock::new(UsageLog::default())),
+            retention,
+        }
+    }
+
+    pub fn record_model_call(&self, record: ModelCallRecord) {
+        let mut log = self.log.write().expect("usage lock poisoned");
+        if log.model_calls.len() >= self.retention {
+            log.model_calls.pop_front();
+        }
+        log.model_calls.push_back(record);
+    }
+
+    pub fn record_invocation(&self, record: InvocationRecord) {
+        let mut log = self.log.write().expect("usage lock poisoned");
+        if log.invocations.len() >= self.retention {
+            log.invocations.pop_front();
+        }
+        log.invocations.push_back(record);
+    }
+
+    /// Model calls recorded at or after `since`, oldest first
+    pub fn model_calls_since(&self, since: Timestamp) -> Vec<ModelCallRecord> {
+        let log = self.log.read().expect("usage lock poisoned");
+        log.model_calls.iter().filter(|r| r.timestamp >= since).cloned().collect()
+    }
+
+    /// Invocations recorded at or after `since`, oldest first
+    pub fn invocations_since(&self, since: Timestamp) -> Vec<InvocationRecord> {
+        let log = self.log.read().expect("usage lock poisoned");
+        log.invocations.iter().filter(|r| r.timestamp >= since).cloned().collect()
+    }
+}
+
+impl Default for UsageTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static GLOBAL_USAGE: once_cell::sync::Lazy<UsageTracker> = once_cell::sync::Lazy::new(UsageTracker::new);
+
+/// Get the global usage tracker
+pub fn global_usage_tracker() -> &'static UsageTracker {
+    &GLOBAL_USAGE
+}
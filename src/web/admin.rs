@@ -0,0 +1,206 @@
+//! Operational statistics for the admin dashboard
+
+use crate::{
+    error::Result,
+    events::SubscriberStats,
+    models::{global_live_pool, http_pool_stats, HttpPoolStats, LivePoolStats},
+    sessions::{SessionFilter, SessionService},
+    web::{stream_buffer_stats, SchedulerStats, StreamBufferStats},
+    types::Timestamp,
+    utils::UsageTracker,
+};
+use chrono::Duration;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Upper bound on the number of token spend buckets in one report
+pub const MAX_STATS_BUCKETS: i64 = 10_000;
+
+/// Invocation statistics for a single agent
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AgentStats {
+    pub invocations: usize,
+    pub errors: usize,
+    pub error_rate: f64,
+    pub average_latency_ms: f64,
+}
+
+/// Token usage and spend within one time bucket
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TokenSpend {
+    pub start: Option<Timestamp>,
+    pub model_calls: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Statistics overview returned by `/api/admin/stats`
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminStats {
+    pub generated_at: Timestamp,
+    pub window_start: Timestamp,
+
+    /// Sessions active within the window, keyed by application
+    pub sessions_per_app: BTreeMap<String, usize>,
+
+    /// Invocation statistics keyed by agent name
+    pub agents: BTreeMap<String, AgentStats>,
+
+    /// Model call error rate across the window
+    pub model_error_rate: f64,
+
+    /// Token spend across the whole window
+    pub token_totals: TokenSpend,
+
+    /// Token spend per bucket, oldest first
+    pub token_spend: Vec<TokenSpend>,
+
+    /// Current model HTTP client pool usage
+    pub http_pool: HttpPoolStats,
+
+    /// Pre-established live connections and how often runs found one
+    pub live_pool: LivePoolStats,
+
+    /// Buffering of streamed events for slow clients
+    pub stream_buffer: StreamBufferStats,
+
+    /// Current invocation admission queue; filled in by the server
+    pub scheduler: SchedulerStats,
+
+    /// Event bus subscriber delivery; filled in by the server
+    pub event_subscribers: Vec<SubscriberStats>,
+}
+
+/// Compute dashboard statistics over the last `window`, bucketing spend by `bucket`
+pub async fn collect_stats(
+    session_service: &dyn SessionService,
+    usage: &UsageTracker,
+    window: Duration,
+    bucket: Duration,
+) -> Result<AdminStats> {
+    if bucket <= Duration::zero() || window <= Duration::zero() {
+        return Err(crate::adk_error!(ValidationError, "Stats window and bucket must be positive"));
+    }
+    if window.num_milliseconds() / bucket.num_milliseconds() > MAX_STATS_BUCKETS {
+        return Err(crate::adk_error!(
+            ValidationError,
+            "Stats window spans more than {} buckets",
+            MAX_STATS_BUCKETS
+        ));
+    }
+
+    let now = crate::types::now();
+    let window_start = now - window;
+
+    let mut sessions_per_app = BTreeMap::new();
+    for session in session_service.list_sessions(&SessionFilter::new()).await? {
+        if session.updated_at >= window_start {
+            *sessions_per_app.entry(session.app_name).or_insert(0) += 1;
+        }
+    }
+
+    let mut agents: BTreeMap<String, AgentStats> = BTreeMap::new();
+    let mut total_latency: BTreeMap<String, u64> = BTreeMap::new();
+    for record in usage.invocations_since(window_start) {
+        let stats = agents.entry(record.agent.clone()).or_default();
+        stats.invocations += 1;
+        stats.errors += record.is_error as usize;
+        *total_latency.entry(record.agent).or_insert(0) += record.latency_ms;
+    }
+    for (agent, stats) in agents.iter_mut() {
+        stats.error_rate = stats.errors as f64 / stats.invocations as f64;
+        stats.average_latency_ms = total_latency[agent] as f64 / stats.invocations as f64;
+    }
+
+    let bucket_count = ((window.num_milliseconds() + bucket.num_milliseconds() - 1)
+        / bucket.num_milliseconds()) as usize;
+    let mut token_spend: Vec<TokenSpend> = (0..bucket_count)
+        .map(|i| TokenSpend {
+            start: Some(window_start + bucket * i as i32),
+            ..Default::default()
+        })
+        .collect();
+    let mut token_totals = TokenSpend::default();
+    let mut model_errors = 0;
+
+    for call in usage.model_calls_since(window_start) {
+        let index = ((call.timestamp - window_start).num_milliseconds() / bucket.num_milliseconds()) as usize;
+        let index = index.min(bucket_count - 1);
+        for spend in [&mut token_spend[index], &mut token_totals] {
+            spend.model_calls += 1;
+            spend.prompt_tokens += call.prompt_tokens as u64;
+            spend.completion_tokens += call.completion_tokens as u64;
+            spend.cost_usd += call.cost_usd.unwrap_or(0.0);
+        }
+        model_errors += call.is_error as usize;
+    }
+
+    let model_error_rate = match token_totals.model_calls {
+        0 => 0.0,
+        calls => model_errors as f64 / calls as f64,
+    };
+
+    Ok(AdminStats {
+        generated_at: now,
+        window_start,
+        sessions_per_app,
+        agents,
+        model_error_rate,
+        token_totals,
+        token_spend,
+        http_pool: http_pool_stats(),
+        live_pool: global_live_pool().stats(),
+        stream_buffer: stream_buffer_stats(),
+        scheduler: SchedulerStats::default(),
+        event_subscribers: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::Usage,
+        sessions::{InMemorySessionService, Session},
+        utils::{InvocationRecord, ModelCallRecord},
+    };
+    use std::time::Duration as StdDuration;
+
+    #[tokio::test]
+    async fn test_collect_stats() {
+        let sessions = InMemorySessionService::new();
+        for id in ["s1", "s2"] {
+            let session = Session::new("support".to_string(), "user".to_string(), id.to_string());
+            sessions.create_session(session).await.unwrap();
+        }
+
+        let usage = UsageTracker::new();
+        usage.record_invocation(InvocationRecord::new("support", "helper", StdDuration::from_millis(100), false));
+        usage.record_invocation(InvocationRecord::new("support", "helper", StdDuration::from_millis(300), true));
+        let tokens = Usage {
+            prompt_tokens: Some(1_000_000),
+            completion_tokens: Some(0),
+            total_tokens: Some(1_000_000),
+        };
+        usage.record_model_call(ModelCallRecord::new(
+            "support",
+            "helper",
+            "gemini-2.0-flash",
+            StdDuration::from_millis(80),
+            Some(&tokens),
+            false,
+        ));
+
+        let stats = collect_stats(&sessions, &usage, Duration::hours(24), Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(stats.sessions_per_app["support"], 2);
+        assert_eq!(stats.agents["helper"].invocations, 2);
+        assert_eq!(stats.agents["helper"].error_rate, 0.5);
+        assert_eq!(stats.agents["helper"].average_latency_ms, 200.0);
+        assert_eq!(stats.token_spend.len(), 24);
+        assert_eq!(stats.token_spend[23].prompt_tokens, 1_000_000);
+        assert_eq!(stats.token_totals.cost_usd, 0.10);
+    }
+}
@@ -0,0 +1,239 @@
+//! Artifact download and upload endpoints
+//!
+//! Downloads honour single `Range` requests (`bytes=a-b`, `bytes=a-`,
+//! `bytes=-n`) so clients can resume transfers and seek in media; bodies are
+//! streamed in both directions. Uploads without a specific `Content-Type`
+//! have theirs detected from the content.
+
+use crate::{
+    artifacts::{ArtifactKey, ArtifactMetadata, ArtifactVersion},
+    web::ServerState,
+};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use futures::StreamExt;
+use serde::Deserialize;
+use std::ops::Range;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use tracing::warn;
+
+/// Response header naming the artifact version served
+pub const ARTIFACT_VERSION_HEADER: &str = "x-artifact-version";
+
+/// What a `Range` header asks for, given the artifact size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// No usable range: serve the whole artifact
+    Full,
+
+    /// Serve these bytes with `206 Partial Content`
+    Partial(Range<u64>),
+
+    /// The range starts past the end of the artifact
+    Unsatisfiable,
+}
+
+impl RangeRequest {
+    /// Interpret a `Range` header; malformed and multi-range headers are ignored
+    pub fn parse(header: &str, size: u64) -> Self {
+        let Some(spec) = header.trim().strip_prefix("bytes=") else {
+            return Self::Full;
+        };
+        let Some((start, end)) = spec.trim().split_once('-').filter(|_| !spec.contains(',')) else {
+            return Self::Full;
+        };
+        let (start, end) = (start.trim(), end.trim());
+
+        if start.is_empty() {
+            return match end.parse::<u64>() {
+                Ok(0) => Self::Unsatisfiable,
+                Ok(_) if size == 0 => Self::Unsatisfiable,
+                Ok(suffix) => Self::Partial(size.saturating_sub(suffix)..size),
+                Err(_) => Self::Full,
+            };
+        }
+        let Ok(start) = start.parse::<u64>() else {
+            return Self::Full;
+        };
+        let end = match end {
+            "" => size,
+            end => match end.parse::<u64>() {
+                Ok(last) if last >= start => last.saturating_add(1).min(size),
+                _ => return Self::Full,
+            },
+        };
+        if start >= size {
+            Self::Unsatisfiable
+        } else {
+            Self::Partial(start..end)
+        }
+    }
+}
+
+/// Version selector of artifact downloads; the latest version when absent
+#[derive(Debug, Default, Deserialize)]
+pub struct ArtifactQuery {
+    #[serde(default)]
+    pub version: Option<u64>,
+}
+
+/// Metadata of an uploaded artifact version
+#[derive(Debug, Default, Deserialize)]
+pub struct ArtifactUploadQuery {
+    /// Comma-separated tags
+    #[serde(default)]
+    pub tags: Option<String>,
+
+    #[serde(default)]
+    pub created_by: Option<String>,
+
+    #[serde(default)]
+    pub invocation_id: Option<uuid::Uuid>,
+
+    /// Overrides the app's TTL policy
+    #[serde(default)]
+    pub ttl_seconds: Option<u64>,
+}
+
+impl ArtifactUploadQuery {
+    fn into_metadata(self, content_type: Option<&str>) -> ArtifactMetadata {
+        ArtifactMetadata {
+            mime_type: content_type
+                .filter(|content_type| *content_type != "application/octet-stream")
+                .map(str::to_string),
+            created_by: self.created_by,
+            invocation_id: self.invocation_id,
+            tags: self
+                .tags
+                .iter()
+                .flat_map(|tags| tags.split(','))
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+            ttl_seconds: self.ttl_seconds,
+        }
+    }
+}
+
+fn artifact_key(app_name: String, user_id: String, session_id: String, filename: String) -> Result<ArtifactKey, StatusCode> {
+    let key = ArtifactKey::new(app_name, user_id, session_id, filename);
+    key.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+    Ok(key)
+}
+
+/// Stream an artifact version, or the part of it a `Range` header selects
+pub async fn download_artifact(
+    Path((app_name, user_id, session_id, filename)): Path<(String, String, String, String)>,
+    Query(query): Query<ArtifactQuery>,
+    headers: HeaderMap,
+    State(state): State<ServerState>,
+) -> Result<Response, StatusCode> {
+    let key = artifact_key(app_name, user_id, session_id, filename)?;
+    let version = state
+        .artifact_service
+        .artifact_version(&key, query.version)
+        .await
+        .map_err(|e| {
+            warn!("Failed to look up artifact '{}': {}", key.filename, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let requested = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(RangeRequest::Full, |value| RangeRequest::parse(value, version.size));
+    let (status, range) = match requested {
+        RangeRequest::Full => (StatusCode::OK, 0..version.size),
+        RangeRequest::Partial(range) => (StatusCode::PARTIAL_CONTENT, range),
+        RangeRequest::Unsatisfiable => {
+            let content_range = format!("bytes */{}", version.size);
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, [(header::CONTENT_RANGE, content_range)]).into_response());
+        }
+    };
+
+    // Read the version the range was computed for, even if a newer one was committed meanwhile
+    let read = state
+        .artifact_service
+        .open_read_range(&key, Some(version.version), range)
+        .await
+        .map_err(|e| {
+            warn!("Failed to read artifact '{}': {}", key.filename, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut response = Response::new(Body::from_stream(ReaderStream::new(read.reader)));
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(read.range.end - read.range.start));
+    headers.insert(ARTIFACT_VERSION_HEADER, HeaderValue::from(read.version.version));
+    if let Ok(mime_type) = HeaderValue::from_str(&read.version.mime_type) {
+        headers.insert(header::CONTENT_TYPE, mime_type);
+    }
+    if status == StatusCode::PARTIAL_CONTENT {
+        let content_range = format!("bytes {}-{}/{}", read.range.start, read.range.end - 1, read.version.size);
+        headers.insert(header::CONTENT_RANGE, HeaderValue::from_str(&content_range).unwrap());
+    }
+    Ok(response)
+}
+
+/// Store the request body as the artifact's next version; `413` when it exceeds the app's quota
+pub async fn upload_artifact(
+    Path((app_name, user_id, session_id, filename)): Path<(String, String, String, String)>,
+    Query(query): Query<ArtifactUploadQuery>,
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<(StatusCode, Json<ArtifactVersion>), StatusCode> {
+    let key = artifact_key(app_name, user_id, session_id, filename)?;
+    let content_type = headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+    let metadata = query.into_metadata(content_type);
+    let mut writer = state.artifact_service.open_write(&key, metadata).await.map_err(|e| {
+        warn!("Failed to open artifact '{}' for writing: {}", key.filename, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        writer.write_all(&chunk).await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::FileTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => {
+                warn!("Failed to write artifact '{}': {}", key.filename, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+    }
+    let version = writer.commit().await.map_err(|e| {
+        warn!("Failed to commit artifact '{}': {}", key.filename, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok((StatusCode::CREATED, Json(version)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_header_forms() {
+        assert_eq!(RangeRequest::parse("bytes=0-99", 1000), RangeRequest::Partial(0..100));
+        assert_eq!(RangeRequest::parse("bytes=900-", 1000), RangeRequest::Partial(900..1000));
+        assert_eq!(RangeRequest::parse("bytes=-100", 1000), RangeRequest::Partial(900..1000));
+        assert_eq!(RangeRequest::parse("bytes=990-2000", 1000), RangeRequest::Partial(990..1000));
+        assert_eq!(RangeRequest::parse("bytes=-5000", 1000), RangeRequest::Partial(0..1000));
+        assert_eq!(RangeRequest::parse("bytes=1000-", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(RangeRequest::parse("bytes=-0", 1000), RangeRequest::Unsatisfiable);
+        assert_eq!(RangeRequest::parse("bytes=0-1,5-9", 1000), RangeRequest::Full);
+        assert_eq!(RangeRequest::parse("bytes=9-5", 1000), RangeRequest::Full);
+        assert_eq!(RangeRequest::parse("items=0-5", 1000), RangeRequest::Full);
+    }
+}
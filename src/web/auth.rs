@@ -0,0 +1,94 @@
+//! Admin authentication
+//!
+//! Endpoints that change how agents run or that read or erase other users'
+//! data require a bearer token from [`ServerConfig::admin_token`] or
+//! [`ServerConfig::admin_tokens`]. Without any configured token they are
+//! disabled and answer `404 Not Found`. The principal a token belongs to is
+//! the actor recorded by audited operations.
+
+use crate::web::{ServerConfig, ServerState};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Principal of the single [`ServerConfig::admin_token`]
+pub const DEFAULT_ADMIN_PRINCIPAL: &str = "admin";
+
+/// Authenticated admin, available to guarded handlers as a request extension
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminPrincipal(pub String);
+
+/// Resolve the principal of the request's bearer token
+pub fn authenticate(config: &ServerConfig, headers: &HeaderMap) -> Result<AdminPrincipal, StatusCode> {
+    let tokens = config
+        .admin_token
+        .iter()
+        .map(|token| (DEFAULT_ADMIN_PRINCIPAL, token.as_str()))
+        .chain(config.admin_tokens.iter().map(|(name, token)| (name.as_str(), token.as_str())));
+    let mut tokens = tokens.peekable();
+    if tokens.peek().is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    // Every token is compared so the time taken does not reveal which one matched
+    tokens
+        .fold(None, |found, (name, token)| match tokens_match(presented, token) {
+            true => found.or(Some(name)),
+            false => found,
+        })
+        .map(|name| AdminPrincipal(name.to_string()))
+        .ok_or(StatusCode::UNAUTHORIZED)
+}
+
+/// Route layer admitting only admins; use with `axum::middleware::from_fn_with_state`
+pub async fn require_admin(State(state): State<ServerState>, mut request: Request, next: Next) -> Response {
+    match authenticate(&state.config, request.headers()) {
+        Ok(principal) => {
+            request.extensions_mut().insert(principal);
+            next.run(request).await
+        }
+        Err(status) => status.into_response(),
+    }
+}
+
+/// Compare tokens in time independent of where they differ
+fn tokens_match(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens_resolve_to_their_principal() {
+        let headers = |token: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+            headers
+        };
+        let unconfigured = ServerConfig::default();
+        assert_eq!(authenticate(&unconfigured, &headers("x")), Err(StatusCode::NOT_FOUND));
+
+        let config = ServerConfig::default()
+            .with_admin_token("root-token")
+            .with_named_admin_token("dpo", "dpo-token");
+        assert_eq!(authenticate(&config, &headers("root-token")), Ok(AdminPrincipal("admin".to_string())));
+        assert_eq!(authenticate(&config, &headers("dpo-token")), Ok(AdminPrincipal("dpo".to_string())));
+        assert_eq!(authenticate(&config, &headers("guess")), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(authenticate(&config, &HeaderMap::new()), Err(StatusCode::UNAUTHORIZED));
+    }
+}
@@ -0,0 +1,271 @@
+//! Bounded buffering between agent event streams and slow clients
+//!
+//! The agent stream is drained into a fixed-size buffer by a background task.
+//! When a client reads slower than the model produces tokens and the buffer
+//! fills up, consecutive partial text deltas are coalesced into the newest
+//! buffered event; anything else pauses reading from the agent until the
+//! client catches up, so memory stays bounded either way.
+
+use crate::{error::Result, events::Event, types::ContentPart};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::sync::Notify;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+/// Default number of events buffered per streaming client
+pub const DEFAULT_STREAM_BUFFER_CAPACITY: usize = 64;
+
+static BUFFER_METRICS: BufferMetrics = BufferMetrics {
+    high_water_mark: AtomicU64::new(0),
+    coalesced_deltas: AtomicU64::new(0),
+    pauses: AtomicU64::new(0),
+};
+
+struct BufferMetrics {
+    high_water_mark: AtomicU64,
+    coalesced_deltas: AtomicU64,
+    pauses: AtomicU64,
+}
+
+/// Snapshot of streaming buffer usage across all clients
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamBufferStats {
+    /// Most events ever buffered for a single client
+    pub high_water_mark: u64,
+
+    /// Partial deltas merged into an already buffered event
+    pub coalesced_deltas: u64,
+
+    /// Times reading from an agent stream was paused for a full buffer
+    pub pauses: u64,
+}
+
+/// Current streaming buffer statistics
+pub fn stream_buffer_stats() -> StreamBufferStats {
+    StreamBufferStats {
+        high_water_mark: BUFFER_METRICS.high_water_mark.load(Ordering::Relaxed),
+        coalesced_deltas: BUFFER_METRICS.coalesced_deltas.load(Ordering::Relaxed),
+        pauses: BUFFER_METRICS.pauses.load(Ordering::Relaxed),
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    items: VecDeque<Result<Arc<Event>>>,
+    done: bool,
+}
+
+struct Buffer {
+    capacity: usize,
+    queue: Mutex<Queue>,
+    readable: Notify,
+    writable: Notify,
+}
+
+impl Buffer {
+    /// Buffer an item, coalescing or waiting for room when full; `false` if the reader went away
+    async fn push(&self, item: Result<Arc<Event>>, stop: &CancellationToken) -> bool {
+        let mut item = Some(item);
+        loop {
+            {
+                let mut queue = self.queue.lock().unwrap();
+                if queue.items.len() < self.capacity {
+                    queue.items.extend(item.take());
+                    BUFFER_METRICS
+                        .high_water_mark
+                        .fetch_max(queue.items.len() as u64, Ordering::Relaxed);
+                    self.readable.notify_one();
+                    return true;
+                }
+                if let (Some(last), Some(delta)) = (queue.items.back_mut(), item.as_ref()) {
+                    if coalesce(last, delta) {
+                        BUFFER_METRICS.coalesced_deltas.fetch_add(1, Ordering::Relaxed);
+                        return true;
+                    }
+                }
+            }
+            BUFFER_METRICS.pauses.fetch_add(1, Ordering::Relaxed);
+            tokio::select! {
+                _ = self.writable.notified() => {}
+                _ = stop.cancelled() => return false,
+            }
+        }
+    }
+
+    fn close(&self) {
+        self.queue.lock().unwrap().done = true;
+        self.readable.notify_one();
+    }
+}
+
+/// Merge a partial text delta into a buffered partial event from the same invocation
+fn coalesce(buffered: &mut Result<Arc<Event>>, delta: &Result<Arc<Event>>) -> bool {
+    let (Ok(buffered), Ok(delta)) = (buffered, delta) else {
+        return false;
+    };
+    let mergeable = buffered.is_partial
+        && delta.is_partial
+        && buffered.author == delta.author
+        && buffered.invocation_id == delta.invocation_id
+        && buffered.citations.is_none()
+        && delta.citations.is_none()
+        && is_text_only(buffered)
+        && is_text_only(delta);
+    if !mergeable {
+        return false;
+    }
+
+    let text = delta.get_text().unwrap_or_default();
+    let event = Arc::make_mut(buffered);
+    event.metadata.extend(delta.metadata.clone());
+    let parts = &mut event.content.as_mut().expect("text-only event has content").parts;
+    match parts.last_mut() {
+        Some(ContentPart::Text { text: last }) => last.push_str(&text),
+        _ => parts.push(ContentPart::Text { text }),
+    }
+    true
+}
+
+fn is_text_only(event: &Event) -> bool {
+    event
+        .content
+        .as_ref()
+        .is_some_and(|content| content.parts.iter().all(|part| part.as_text().is_some()))
+}
+
+/// Decouple an agent event stream from its consumer through a bounded buffer.
+///
+/// `events` is read by a task on `tasks`. Dropping the returned stream stops
+/// reading from `events`.
+pub fn buffer_events<S>(
+    events: S,
+    capacity: usize,
+    tasks: &TaskTracker,
+) -> impl Stream<Item = Result<Arc<Event>>> + Send + 'static
+where
+    S: Stream<Item = Result<Arc<Event>>> + Send + 'static,
+{
+    let buffer = Arc::new(Buffer {
+        capacity: capacity.max(1),
+        queue: Mutex::new(Queue::default()),
+        readable: Notify::new(),
+        writable: Notify::new(),
+    });
+    let stop = CancellationToken::new();
+
+    tasks.spawn({
+        let (buffer, stop) = (buffer.clone(), stop.clone());
+        async move {
+            let mut events = Box::pin(events);
+            loop {
+                let next = tokio::select! {
+                    next = events.next() => next,
+                    _ = stop.cancelled() => break,
+                };
+                let Some(item) = next else { break };
+                if !buffer.push(item, &stop).await {
+                    break;
+                }
+            }
+            buffer.close();
+        }
+    });
+
+    let guard = stop.drop_guard();
+    async_stream::stream! {
+        let _guard = guard;
+        loop {
+            let next = {
+                let mut queue = buffer.queue.lock().unwrap();
+                match queue.items.pop_front() {
+                    Some(item) => Some(item),
+                    None if queue.done => break,
+                    None => None,
+                }
+            };
+            match next {
+                Some(item) => {
+                    buffer.writable.notify_one();
+                    yield item;
+                }
+                None => buffer.readable.notified().await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Content;
+
+    fn delta(text: &str, invocation_id: uuid::Uuid) -> Result<Arc<Event>> {
+        let mut event = Event::text_response("agent", text);
+        event.is_partial = true;
+        event.invocation_id = invocation_id;
+        Ok(Arc::new(event))
+    }
+
+    #[tokio::test]
+    async fn test_slow_reader_gets_coalesced_deltas() {
+        let invocation_id = uuid::Uuid::new_v4();
+        let mut events: Vec<_> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|text| delta(text, invocation_id))
+            .collect();
+        events.push(Ok(Arc::new(Event::text_response("agent", "abcde"))));
+        let produced = Arc::new(AtomicU64::new(0));
+        let counter = produced.clone();
+        let source = futures::stream::iter(events).inspect(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut buffered = Box::pin(buffer_events(source, 2, &TaskTracker::new()));
+        // Let the producer run ahead of a reader that has not started yet
+        while produced.load(Ordering::SeqCst) < 6 {
+            tokio::task::yield_now().await;
+        }
+
+        let mut received = Vec::new();
+        while let Some(event) = buffered.next().await {
+            let event = event.unwrap();
+            received.push((event.get_text().unwrap(), event.is_partial));
+        }
+        assert_eq!(
+            received,
+            vec![
+                ("a".to_string(), true),
+                ("bcde".to_string(), true),
+                ("abcde".to_string(), false),
+            ]
+        );
+        let stats = stream_buffer_stats();
+        assert!(stats.high_water_mark >= 2);
+        assert!(stats.coalesced_deltas >= 3);
+        assert!(stats.pauses >= 1);
+    }
+
+    #[test]
+    fn test_non_text_deltas_are_not_coalesced() {
+        let invocation_id = uuid::Uuid::new_v4();
+        let mut buffered = delta("a", invocation_id);
+        let mut image = Event::text_response("agent", "");
+        image.content = Some(Content {
+            role: "model".to_string(),
+            parts: vec![ContentPart::image(&b"png"[..], "image/png")],
+        });
+        image.is_partial = true;
+        image.invocation_id = invocation_id;
+        assert!(!coalesce(&mut buffered, &Ok(Arc::new(image))));
+        assert!(!coalesce(&mut buffered, &delta("b", uuid::Uuid::new_v4())));
+        assert!(coalesce(&mut buffered, &delta("b", invocation_id)));
+        assert_eq!(buffered.unwrap().get_text().unwrap(), "ab");
+    }
+}
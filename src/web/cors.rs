@@ -0,0 +1,283 @@
+//! CORS policies for the web server
+//!
+//! A default policy applies to every route; the `/api` and `/ws` route groups
+//! may override it. Policies are validated when the server starts, so a
+//! malformed origin or an invalid combination such as credentials with a
+//! wildcard origin is reported instead of silently replaced.
+
+use crate::error::Result;
+use axum::http::{HeaderName, HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer, ExposeHeaders};
+
+/// Cross-origin policy for one group of routes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsPolicy {
+    /// Allowed origins such as `https://app.example.com`; `*` allows any origin
+    pub allowed_origins: Vec<String>,
+
+    /// Allowed methods; any method when empty
+    pub allowed_methods: Vec<String>,
+
+    /// Allowed request headers; any header when empty
+    pub allowed_headers: Vec<String>,
+
+    /// Response headers exposed to scripts
+    pub exposed_headers: Vec<String>,
+
+    /// Send `Access-Control-Allow-Credentials: true` (cookies, authorization headers)
+    pub allow_credentials: bool,
+
+    /// How long browsers may cache preflight responses
+    pub max_age_seconds: Option<u64>,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        Self::permissive()
+    }
+}
+
+impl CorsPolicy {
+    /// Any origin, method and header, without credentials
+    pub fn permissive() -> Self {
+        Self {
+            allowed_origins: vec!["*".to_string()],
+            allowed_methods: Vec::new(),
+            allowed_headers: Vec::new(),
+            exposed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age_seconds: None,
+        }
+    }
+
+    /// Only the given origins
+    pub fn with_origins(origins: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            allowed_origins: origins.into_iter().map(Into::into).collect(),
+            ..Self::permissive()
+        }
+    }
+
+    pub fn with_credentials(mut self) -> Self {
+        self.allow_credentials = true;
+        self
+    }
+
+    pub fn with_methods(mut self, methods: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.allowed_methods = methods.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_headers(mut self, headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.allowed_headers = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_exposed_headers(mut self, headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.exposed_headers = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_max_age(mut self, seconds: u64) -> Self {
+        self.max_age_seconds = Some(seconds);
+        self
+    }
+
+    fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    /// Check the policy and build the middleware enforcing it
+    pub fn layer(&self) -> Result<CorsLayer> {
+        if self.allowed_origins.is_empty() {
+            return Err(crate::adk_error!(ConfigError, "CORS policy allows no origins"));
+        }
+
+        let any_origin = self.allows_any_origin();
+        if any_origin && self.allowed_origins.len() > 1 {
+            return Err(crate::adk_error!(ConfigError, "CORS origin '*' cannot be combined with explicit origins"));
+        }
+        if any_origin && self.allow_credentials {
+            return Err(crate::adk_error!(
+                ConfigError,
+                "CORS credentials require explicit origins; browsers reject them with '*'"
+            ));
+        }
+
+        let origin = if any_origin {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(
+                self.allowed_origins
+                    .iter()
+                    .map(|origin| parse_origin(origin))
+                    .collect::<Result<Vec<_>>>()?,
+            )
+        };
+
+        // Wildcards are not allowed together with credentials, so mirror the request instead
+        let methods = if self.allowed_methods.is_empty() {
+            if self.allow_credentials {
+                AllowMethods::mirror_request()
+            } else {
+                AllowMethods::any()
+            }
+        } else {
+            AllowMethods::list(
+                self.allowed_methods
+                    .iter()
+                    .map(|method| {
+                        method.to_ascii_uppercase().parse::<Method>().map_err(|_| {
+                            crate::adk_error!(ConfigError, "Invalid CORS method '{}'", method)
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            )
+        };
+        let headers = if self.allowed_headers.is_empty() {
+            if self.allow_credentials {
+                AllowHeaders::mirror_request()
+            } else {
+                AllowHeaders::any()
+            }
+        } else {
+            AllowHeaders::list(parse_headers(&self.allowed_headers)?)
+        };
+
+        let mut layer = CorsLayer::new()
+            .allow_origin(origin)
+            .allow_methods(methods)
+            .allow_headers(headers)
+            .allow_credentials(self.allow_credentials);
+        if !self.exposed_headers.is_empty() {
+            layer = layer.expose_headers(ExposeHeaders::list(parse_headers(&self.exposed_headers)?));
+        }
+        if let Some(seconds) = self.max_age_seconds {
+            layer = layer.max_age(Duration::from_secs(seconds));
+        }
+        Ok(layer)
+    }
+}
+
+fn parse_origin(origin: &str) -> Result<HeaderValue> {
+    let invalid = || crate::adk_error!(ConfigError, "Invalid CORS origin '{}'", origin);
+    let url = url::Url::parse(origin).map_err(|_| invalid())?;
+    let is_bare_origin = matches!(url.scheme(), "http" | "https")
+        && url.host_str().is_some()
+        && url.path() == "/"
+        && url.query().is_none()
+        && !origin.ends_with('/');
+    if !is_bare_origin {
+        return Err(invalid());
+    }
+    HeaderValue::from_str(origin).map_err(|_| invalid())
+}
+
+fn parse_headers(headers: &[String]) -> Result<Vec<HeaderName>> {
+    headers
+        .iter()
+        .map(|header| {
+            header
+                .parse::<HeaderName>()
+                .map_err(|_| crate::adk_error!(ConfigError, "Invalid CORS header '{}'", header))
+        })
+        .collect()
+}
+
+/// CORS policies per route group
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Policy for routes without a group override (health, docs, static files)
+    pub default: CorsPolicy,
+
+    /// Override for `/api/...` routes
+    pub api: Option<CorsPolicy>,
+
+    /// Override for `/ws/...` routes
+    pub websocket: Option<CorsPolicy>,
+}
+
+impl CorsConfig {
+    pub fn new(default: CorsPolicy) -> Self {
+        Self {
+            default,
+            api: None,
+            websocket: None,
+        }
+    }
+
+    pub fn with_api(mut self, policy: CorsPolicy) -> Self {
+        self.api = Some(policy);
+        self
+    }
+
+    pub fn with_websocket(mut self, policy: CorsPolicy) -> Self {
+        self.websocket = Some(policy);
+        self
+    }
+
+    pub fn api_policy(&self) -> &CorsPolicy {
+        self.api.as_ref().unwrap_or(&self.default)
+    }
+
+    pub fn websocket_policy(&self) -> &CorsPolicy {
+        self.websocket.as_ref().unwrap_or(&self.default)
+    }
+
+    /// Validate every policy
+    pub fn validate(&self) -> Result<()> {
+        for policy in [&self.default, self.api_policy(), self.websocket_policy()] {
+            policy.layer().map(drop)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_invalid_policies_are_rejected() {
+        assert!(CorsPolicy::permissive().layer().is_ok());
+        assert!(CorsPolicy::with_origins(["https://app.example.com", "http://localhost:3000"]).layer().is_ok());
+        for bad in ["not a url", "https://app.example.com/", "https://app.example.com/path", "ftp://files"] {
+            assert!(CorsPolicy::with_origins([bad]).layer().is_err(), "{bad} accepted");
+        }
+        assert!(CorsPolicy::permissive().with_credentials().layer().is_err());
+        assert!(CorsPolicy::with_origins(["https://a.example"]).with_methods(["G ET"]).layer().is_err());
+
+        let config = CorsConfig::default().with_api(CorsPolicy::with_origins(["bad"]));
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_credentials_mirror_request_headers() {
+        let layer = CorsPolicy::with_origins(["https://app.example.com"])
+            .with_credentials()
+            .layer()
+            .unwrap();
+        let app = Router::new().route("/api/x", get(|| async { "ok" })).layer(layer);
+
+        let preflight = Request::builder()
+            .method("OPTIONS")
+            .uri("/api/x")
+            .header("origin", "https://app.example.com")
+            .header("access-control-request-method", "POST")
+            .header("access-control-request-headers", "authorization")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(preflight).await.unwrap();
+        let headers = response.headers();
+        assert_eq!(headers["access-control-allow-origin"], "https://app.example.com");
+        assert_eq!(headers["access-control-allow-credentials"], "true");
+        assert_eq!(headers["access-control-allow-headers"], "authorization");
+        assert_eq!(headers["access-control-allow-methods"], "POST");
+    }
+}
@@ -1,23 +1,63 @@
 //! HTTP API handlers
 
 use crate::{
-    agents::{BaseAgent, InvocationContextBuilder},
-    events::Event,
-    models::list_available_models,
-    types::{SessionState},
-    web::ServerState,
+    analytics::ConversationMetrics,
+    agents::{
+        AgentCapabilities, BaseAgent, Breakpoints, DebugCommand, PausedStep, RunConfig, AGENT_CONFIG_HASH_METADATA_KEY,
+        AGENT_VERSION_METADATA_KEY,
+    },
+    events::{Citations, Event, PublishedEvent, Transcription, WebhookConfig},
+    models::{self, list_available_models, PromptEstimate},
+    retention::DeletionRecord,
+    runners::Runner,
+    sessions::{
+        feedback, handoff, Feedback, FeedbackSummary, Rating, Session, SessionFilter, StatelessResult, StatelessSession,
+    },
+    types::{Content, ContentPart},
+    utils::{DeadLetter, InvocationTrace},
+    web::{
+        admin, buffer_events, AdminPrincipal,
+        idempotency::{in_progress_response, mismatch_response, IdempotencyCheck, MAX_IDEMPOTENCY_KEY_LEN},
+        Priority, ServerState, StructuredResponse, TrafficSplit, IDEMPOTENCY_KEY_HEADER,
+    },
 };
+use async_stream::stream;
 use axum::{
-    extract::{Path, Query, State, WebSocketUpgrade},
-    http::StatusCode,
-    response::{Json, Response, Html},
+    extract::{Extension, Path, Query, State, WebSocketUpgrade},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        Html, IntoResponse, Json, Response,
+    },
 };
+use bytes::Bytes;
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use tracing::{error, warn};
+use std::{
+    collections::{BTreeSet, HashMap},
+    convert::Infallible,
+    sync::Arc,
+};
+use tracing::warn;
 use uuid::Uuid;
 
+/// Runner executing an agent for an API request
+fn server_runner(state: &ServerState, agent_name: String, agent: Arc<dyn BaseAgent>, debug: Breakpoints) -> Runner {
+    let runner = Runner::new(agent_name, agent, state.session_service.clone())
+        .with_cancellation_token(state.shutdown.child_token())
+        .with_event_bus(state.event_bus.clone())
+        .with_webhooks(state.webhooks.clone())
+        .with_run_config(RunConfig::default().with_breakpoints(debug));
+    let runner = match &state.determinism {
+        Some(determinism) => runner.with_determinism(determinism.clone()),
+        None => runner,
+    };
+    match &state.redaction_vault {
+        Some(vault) => runner.with_redaction_vault(vault.clone()),
+        None => runner,
+    }
+}
+
 /// Health check response
 #[derive(Serialize)]
 pub struct HealthResponse {
@@ -32,10 +72,40 @@ pub struct AgentInfo {
     name: String,
     description: String,
     metadata: HashMap<String, serde_json::Value>,
+    /// Version served by this entry
+    version: String,
+    /// All versions registered under the agent name
+    versions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_hash: Option<String>,
+}
+
+impl AgentInfo {
+    fn from_registry(state: &ServerState, name: &str, version: Option<&str>) -> Option<Self> {
+        let agent = state.agents.get(name, version)?;
+        Some(Self {
+            name: name.to_string(),
+            description: agent.description().to_string(),
+            metadata: agent.metadata().clone(),
+            version: agent
+                .version()
+                .unwrap_or(crate::agents::DEFAULT_AGENT_VERSION)
+                .to_string(),
+            versions: state.agents.versions(name),
+            config_hash: agent.config_hash().map(str::to_string),
+        })
+    }
+}
+
+/// Selects an agent version; the registry default is used when absent
+#[derive(Debug, Default, Deserialize)]
+pub struct VersionQuery {
+    version: Option<String>,
 }
 
 /// Agent run request
 #[derive(Deserialize)]
+#[allow(dead_code)]
 pub struct AgentRunRequest {
     message: String,
     session_id: Option<String>,
@@ -43,15 +113,24 @@ pub struct AgentRunRequest {
     stream: Option<bool>,
     temperature: Option<f32>,
     max_tokens: Option<i32>,
+    /// Pause at these steps and wait for an admin's command on `/api/debug/paused/{pause_id}`
+    #[serde(default)]
+    debug: Breakpoints,
+    /// Run statelessly on this caller-owned state and history (`/run` only)
+    stateless: Option<StatelessSession>,
 }
 
 /// Agent run response
 #[derive(Serialize)]
 pub struct AgentRunResponse {
-    response: String,
+    /// Ordered answer parts, executed tool calls, usage and finish reason
+    response: StructuredResponse,
     session_id: String,
     events: Vec<EventResponse>,
     metadata: HashMap<String, serde_json::Value>,
+    /// State delta and full events for the caller to store, for stateless runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stateless: Option<StatelessResult>,
 }
 
 /// Event response
@@ -59,11 +138,41 @@ pub struct AgentRunResponse {
 pub struct EventResponse {
     id: String,
     author: String,
+    invocation_id: Uuid,
     content: Option<String>,
+    /// Structured parts (function calls, executable code, execution results)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    parts: Vec<ContentPart>,
+    /// Grounding sources; `content` carries matching `[n]` markers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    citations: Option<Citations>,
+    /// Transcription of live audio, for voice conversations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transcription: Option<Transcription>,
+    /// The content was replaced by placeholders because it holds secrets
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    redacted: bool,
     timestamp: chrono::DateTime<chrono::Utc>,
     metadata: HashMap<String, serde_json::Value>,
 }
 
+impl From<&Event> for EventResponse {
+    fn from(event: &Event) -> Self {
+        Self {
+            id: event.id.clone(),
+            author: event.author.clone(),
+            invocation_id: event.invocation_id,
+            content: event.get_cited_text(),
+            parts: event.content.as_ref().map(non_text_parts).unwrap_or_default(),
+            citations: event.citations.clone(),
+            transcription: event.transcription.clone(),
+            redacted: event.redact,
+            timestamp: event.timestamp,
+            metadata: event.metadata.clone(),
+        }
+    }
+}
+
 /// Session information
 #[derive(Serialize)]
 pub struct SessionInfo {
@@ -73,32 +182,51 @@ pub struct SessionInfo {
     created_at: chrono::DateTime<chrono::Utc>,
     updated_at: chrono::DateTime<chrono::Utc>,
     event_count: usize,
+    tags: BTreeSet<String>,
 }
 
-/// Model information response
-#[derive(Serialize)]
-pub struct ModelInfoResponse {
-    name: String,
-    supports_streaming: bool,
-    supports_function_calling: bool,
-    supports_multimodal: bool,
-    supports_live: bool,
+impl From<Session> for SessionInfo {
+    fn from(session: Session) -> Self {
+        Self {
+            event_count: session.events.len(),
+            id: session.id,
+            user_id: session.user_id,
+            app_name: session.app_name,
+            created_at: session.created_at,
+            updated_at: session.updated_at,
+            tags: session.tags,
+        }
+    }
+}
+
+/// Replacement tag set for a session
+#[derive(Debug, Deserialize)]
+pub struct SessionTagsRequest {
+    tags: BTreeSet<String>,
 }
 
+/// Model information response
+pub type ModelInfoResponse = models::ModelInfo;
+
 /// Query parameters for listing
 #[derive(Deserialize)]
 pub struct ListQuery {
     limit: Option<usize>,
     offset: Option<usize>,
     user_id: Option<String>,
+    app_name: Option<String>,
+    /// Comma-separated tags that must all be present
+    tag: Option<String>,
+    /// Full-text query over event content
+    q: Option<String>,
 }
 
 /// Health check endpoint
-pub async fn health_check() -> Json<HealthResponse> {
+pub async fn health_check(State(state): State<ServerState>) -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "ok".to_string(),
         version: crate::VERSION.to_string(),
-        timestamp: chrono::Utc::now(),
+        timestamp: state.now(),
     })
 }
 
@@ -123,8 +251,18 @@ pub async fn root() -> Html<&'static str> {
     <h2>Available Endpoints</h2>
     <div class="endpoint"><span class="method">GET</span> /health - Health check</div>
     <div class="endpoint"><span class="method">GET</span> /api/agents - List available agents</div>
-    <div class="endpoint"><span class="method">POST</span> /api/agents/{name}/run - Run an agent</div>
-    <div class="endpoint"><span class="method">GET</span> /api/sessions - List sessions</div>
+    <div class="endpoint"><span class="method">POST</span> /api/agents/{name}/run?version= - Run an agent (optionally a specific version)</div>
+    <div class="endpoint"><span class="method">PUT</span> /api/agents/{name}/traffic - Split traffic between agent versions</div>
+    <div class="endpoint"><span class="method">POST</span> /api/agents/{name}/webhooks - Register a lifecycle webhook</div>
+    <div class="endpoint"><span class="method">GET</span> /api/sessions?tag=...&amp;q=... - List and search sessions</div>
+    <div class="endpoint"><span class="method">PUT</span> /api/sessions/{id}/tags - Set session tags</div>
+    <div class="endpoint"><span class="method">POST</span> /api/sessions/{id}/events/{event_id}/feedback - Rate an event</div>
+    <div class="endpoint"><span class="method">GET</span> /api/handoffs - Conversations handed over to operators</div>
+    <div class="endpoint"><span class="method">POST</span> /api/handoffs/{session_id}/reply - Reply as an operator</div>
+    <div class="endpoint"><span class="method">POST</span> /api/handoffs/{session_id}/return - Return a conversation to the agent</div>
+    <div class="endpoint"><span class="method">GET</span> /api/artifacts/{app}/{user}/{session}/{filename} - Download an artifact (supports Range)</div>
+    <div class="endpoint"><span class="method">PUT</span> /api/artifacts/{app}/{user}/{session}/{filename} - Upload a new artifact version</div>
+    <div class="endpoint"><span class="method">GET</span> /api/feedback/summary - Aggregated feedback</div>
     <div class="endpoint"><span class="method">GET</span> /api/models - List available models</div>
     <div class="endpoint"><span class="method">GET</span> /docs - API documentation</div>
     <div class="endpoint"><span class="method">WS</span> /ws/{agent_name} - WebSocket connection</div>
@@ -137,80 +275,342 @@ pub async fn root() -> Html<&'static str> {
     "#)
 }
 
-/// List all available agents
+/// List all available agents with their default versions
 pub async fn list_agents(State(state): State<ServerState>) -> Json<Vec<AgentInfo>> {
     let agents: Vec<AgentInfo> = state
         .agents
+        .names()
         .iter()
-        .map(|(name, agent)| AgentInfo {
-            name: name.clone(),
-            description: agent.description().to_string(),
-            metadata: agent.metadata().clone(),
-        })
+        .filter_map(|name| AgentInfo::from_registry(&state, name, None))
         .collect();
 
     Json(agents)
 }
 
-/// Get information about a specific agent
+/// Get information about a specific agent, optionally at `?version=`
 pub async fn get_agent(
     Path(agent_name): Path<String>,
+    Query(query): Query<VersionQuery>,
     State(state): State<ServerState>,
 ) -> Result<Json<AgentInfo>, StatusCode> {
-    if let Some(agent) = state.agents.get(&agent_name) {
-        Ok(Json(AgentInfo {
-            name: agent_name,
-            description: agent.description().to_string(),
-            metadata: agent.metadata().clone(),
-        }))
-    } else {
-        Err(StatusCode::NOT_FOUND)
-    }
+    AgentInfo::from_registry(&state, &agent_name, query.version.as_deref())
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
 }
 
-/// Run an agent with a message
+/// Tools, schemas, modalities and sub-agent tree of an agent, optionally of a `?version=`
+pub async fn get_agent_capabilities(
+    Path(agent_name): Path<String>,
+    Query(query): Query<VersionQuery>,
+    State(state): State<ServerState>,
+) -> Result<Json<AgentCapabilities>, StatusCode> {
+    state
+        .agents
+        .get(&agent_name, query.version.as_deref())
+        .map(|agent| Json(agent.capabilities()))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Hypothetical message whose prompt size to estimate
+#[derive(Deserialize)]
+pub struct EstimateRequest {
+    message: String,
+    /// Session whose history the prompt would include; a new session when unset
+    session_id: Option<String>,
+    user_id: Option<String>,
+}
+
+/// Estimated sizes of the model requests a message would cause
+#[derive(Serialize)]
+pub struct EstimateResponse {
+    /// One estimate per model call, in the order the agents would make them
+    requests: Vec<PromptEstimate>,
+}
+
+/// Build the requests for a hypothetical message without calling the model and
+/// return their estimated prompt tokens, history turns and tool declaration sizes
+pub async fn estimate_prompt(
+    Path(agent_name): Path<String>,
+    Query(query): Query<VersionQuery>,
+    State(state): State<ServerState>,
+    Json(request): Json<EstimateRequest>,
+) -> Result<Json<EstimateResponse>, StatusCode> {
+    let agent = state
+        .agents
+        .get(&agent_name, query.version.as_deref())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let session_id = request.session_id.unwrap_or_else(|| state.new_id());
+    let user_id = request.user_id.unwrap_or_else(|| "api_user".to_string());
+    let runner = server_runner(&state, agent_name, agent, Breakpoints::default());
+    let requests = runner
+        .estimate(user_id, session_id, Content::user_text(request.message))
+        .await
+        .map_err(|e| {
+            warn!("Failed to estimate the prompt size: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(EstimateResponse { requests }))
+}
+
+/// Resolve the agent version serving a request, applying any traffic split.
+///
+/// The session ID is the routing key, so a session stays on one version.
+fn route_agent(
+    state: &ServerState,
+    agent_name: &str,
+    pinned: Option<&str>,
+    headers: &HeaderMap,
+    routing_key: &str,
+) -> Option<Arc<dyn BaseAgent>> {
+    let version = state
+        .routing
+        .select_version(agent_name, pinned, headers, routing_key);
+    state.agents.get(agent_name, version.as_deref())
+}
+
+/// Run an agent with a message, optionally pinned to `?version=`
 pub async fn run_agent(
     Path(agent_name): Path<String>,
+    Query(query): Query<VersionQuery>,
+    headers: HeaderMap,
     State(state): State<ServerState>,
-    Json(request): Json<AgentRunRequest>,
-) -> Result<Json<AgentRunResponse>, StatusCode> {
-    // Simple implementation for now
-    Ok(Json(AgentRunResponse {
-        response: format!("Agent {} received: {}", agent_name, request.message),
-        session_id: request.session_id.unwrap_or_else(|| Uuid::new_v4().to_string()),
-        events: vec![],
-        metadata: HashMap::new(),
-    }))
+    body: Bytes,
+) -> Result<Response, StatusCode> {
+    // Parsed by hand so retries can be compared with the body the key was first used with
+    let Json(request) = Json::<AgentRunRequest>::from_bytes(&body).map_err(|rejection| rejection.status())?;
+    let user_id = request.user_id.unwrap_or_else(|| "api_user".to_string());
+
+    // Retries with the same key, user and session get the stored result instead of a second run
+    let idempotency = match headers.get(IDEMPOTENCY_KEY_HEADER) {
+        Some(key) => {
+            let key = key
+                .to_str()
+                .ok()
+                .filter(|key| !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN)
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            match state.idempotency.begin(&agent_name, &user_id, request.session_id.as_deref(), key, &body) {
+                IdempotencyCheck::Started(guard) => Some(guard),
+                IdempotencyCheck::Replay(response) => return Ok(response),
+                IdempotencyCheck::InProgress => return Ok(in_progress_response()),
+                IdempotencyCheck::Mismatch => return Ok(mismatch_response()),
+            }
+        }
+        None => None,
+    };
+
+    let session_id = request.session_id.unwrap_or_else(|| state.new_id());
+    let agent = route_agent(&state, &agent_name, query.version.as_deref(), &headers, &session_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let _permit = match state.scheduler.acquire(&agent_name, Priority::from_headers(&headers)).await {
+        Ok(permit) => permit,
+        Err(overloaded) => return Ok(overloaded.into_response()),
+    };
+
+    let mut metadata = HashMap::new();
+    if let Some(version) = agent.version() {
+        metadata.insert(AGENT_VERSION_METADATA_KEY.to_string(), version.into());
+    }
+    if let Some(hash) = agent.config_hash() {
+        metadata.insert(AGENT_CONFIG_HASH_METADATA_KEY.to_string(), hash.into());
+    }
+
+    let runner = server_runner(&state, agent_name, agent, request.debug);
+    // Stateless servers keep no sessions: a run without supplied state starts a new conversation
+    let stateless = request
+        .stateless
+        .or_else(|| state.config.stateless.then(StatelessSession::default));
+    let (events, stateless) = match stateless {
+        Some(session) => {
+            let result = runner
+                .run_stateless(user_id, session_id.clone(), session, Content::user_text(request.message))
+                .await
+                .map_err(|e| {
+                    warn!("Stateless agent run failed: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            let events = result.events.iter().filter(|event| event.author != "user").cloned().collect();
+            (events, Some(result))
+        }
+        None => {
+            let mut stream = runner
+                .run_async(user_id, session_id.clone(), Content::user_text(request.message))
+                .await
+                .map_err(|e| {
+                    warn!("Failed to run agent: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+
+            let mut events = Vec::new();
+            while let Some(result) = stream.next().await {
+                let event = result.map_err(|e| {
+                    warn!("Agent run failed: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+                if !event.is_partial {
+                    events.push(event);
+                }
+            }
+            (events, None)
+        }
+    };
+
+    let trace = events
+        .first()
+        .and_then(|event| state.traces.get(&session_id, event.invocation_id));
+    let response = AgentRunResponse {
+        response: StructuredResponse::from_events(&events, trace.as_ref()),
+        session_id,
+        events: events.iter().map(|event| EventResponse::from(event.as_ref())).collect(),
+        metadata,
+        stateless,
+    };
+    if let Some(guard) = idempotency {
+        match serde_json::to_vec(&response) {
+            Ok(body) => guard.complete(body),
+            Err(e) => warn!("Failed to store idempotent response: {}", e),
+        }
+    }
+    Ok(Json(response).into_response())
 }
 
-/// Stream agent responses (Server-Sent Events)
+/// Stream agent responses (Server-Sent Events).
+///
+/// Heartbeat comments are sent while the agent is working, and the stream is
+/// closed with an `idle_timeout` event if no agent event arrives in time.
 pub async fn stream_agent(
     Path(agent_name): Path<String>,
-    State(_state): State<ServerState>,
+    Query(query): Query<VersionQuery>,
+    headers: HeaderMap,
+    State(state): State<ServerState>,
     Json(request): Json<AgentRunRequest>,
 ) -> Result<Response, StatusCode> {
-    // Simple SSE response for now
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header("Content-Type", "text/event-stream")
-        .header("Cache-Control", "no-cache")
-        .header("Connection", "keep-alive")
-        .body(axum::body::Body::from(format!(
-            "data: {{\"message\": \"Streaming from {}: {}\"}}\n\ndata: [DONE]\n\n",
-            agent_name, request.message
-        )))
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Stateless runs return their result in one response
+    if request.stateless.is_some() || state.config.stateless {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let session_id = request.session_id.unwrap_or_else(|| state.new_id());
+    let agent = route_agent(&state, &agent_name, query.version.as_deref(), &headers, &session_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let user_id = request.user_id.unwrap_or_else(|| "api_user".to_string());
+    // Held by the SSE stream so the slot is released when the client finishes or disconnects
+    let permit = match state.scheduler.acquire(&agent_name, Priority::from_headers(&headers)).await {
+        Ok(permit) => permit,
+        Err(overloaded) => return Ok(overloaded.into_response()),
+    };
+
+    let runner = server_runner(&state, agent_name, agent, request.debug);
+    let events = runner
+        .run_async(user_id, session_id, Content::user_text(request.message))
+        .await
+        .map_err(|e| {
+            warn!("Failed to start agent stream: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let mut events = Box::pin(buffer_events(events, state.config.stream_buffer_capacity, &state.tasks));
+
+    let idle_timeout = state.config.idle_timeout();
+    let frames = stream! {
+        let _permit = permit;
+        loop {
+            let next = match idle_timeout {
+                Some(limit) => match tokio::time::timeout(limit, events.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        warn!("Closing idle SSE stream after {:?}", limit);
+                        yield Ok::<_, Infallible>(SseEvent::default().event("idle_timeout").data("{}"));
+                        break;
+                    }
+                },
+                None => events.next().await,
+            };
+
+            match next {
+                Some(Ok(event)) => {
+                    let payload = EventResponse::from(event.as_ref());
+                    match SseEvent::default().json_data(payload) {
+                        Ok(frame) => yield Ok(frame),
+                        Err(e) => warn!("Failed to encode SSE event: {}", e),
+                    }
+                }
+                Some(Err(e)) => {
+                    yield Ok(SseEvent::default().event("error").data(e.to_string()));
+                    break;
+                }
+                None => break,
+            }
+        }
+        yield Ok(SseEvent::default().data("[DONE]"));
+    };
+
+    let sse = Sse::new(frames);
+    match state.config.heartbeat_interval() {
+        Some(interval) => Ok(sse.keep_alive(KeepAlive::new().interval(interval)).into_response()),
+        None => Ok(sse.into_response()),
+    }
+}
 
-    Ok(response)
+/// Parts of a content that clients render separately from its text
+pub(crate) fn non_text_parts(content: &Content) -> Vec<ContentPart> {
+    content
+        .parts
+        .iter()
+        .filter(|part| part.as_text().is_none())
+        .cloned()
+        .collect()
 }
 
-/// List sessions
+/// List sessions, optionally filtered by tag and full-text query
 pub async fn list_sessions(
-    Query(_query): Query<ListQuery>,
-    State(_state): State<ServerState>,
-) -> Json<Vec<SessionInfo>> {
-    warn!("Session listing not fully implemented");
-    Json(vec![])
+    Query(query): Query<ListQuery>,
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<SessionInfo>>, StatusCode> {
+    let filter = SessionFilter {
+        app_name: query.app_name,
+        user_id: query.user_id,
+        tags: query
+            .tag
+            .iter()
+            .flat_map(|tags| tags.split(','))
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect(),
+        query: query.q.filter(|q| !q.is_empty()),
+    };
+
+    let mut sessions = state
+        .session_service
+        .list_sessions(&filter)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.updated_at));
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .skip(query.offset.unwrap_or(0))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(SessionInfo::from)
+            .collect(),
+    ))
+}
+
+/// Replace the tags of a session
+pub async fn set_session_tags(
+    Path(session_id): Path<String>,
+    State(state): State<ServerState>,
+    Json(request): Json<SessionTagsRequest>,
+) -> Result<Json<BTreeSet<String>>, StatusCode> {
+    state
+        .session_service
+        .set_session_tags(&session_id, request.tags.clone())
+        .await
+        .map_err(|e| {
+            warn!("Failed to set session tags: {}", e);
+            StatusCode::NOT_FOUND
+        })?;
+
+    Ok(Json(request.tags))
 }
 
 /// Get session information
@@ -222,6 +622,18 @@ pub async fn get_session(
     Err(StatusCode::NOT_IMPLEMENTED)
 }
 
+/// Get the trace of an invocation: ordered model calls, tool calls and state mutations
+pub async fn get_invocation_trace(
+    Path((session_id, invocation_id)): Path<(String, Uuid)>,
+    State(state): State<ServerState>,
+) -> Result<Json<InvocationTrace>, StatusCode> {
+    state
+        .traces
+        .get(&session_id, invocation_id)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
 /// Update session
 pub async fn update_session(
     Path(_session_id): Path<String>,
@@ -241,6 +653,439 @@ pub async fn get_session_events(
     Ok(Json(vec![]))
 }
 
+/// Feedback submitted on an event
+#[derive(Debug, Deserialize)]
+pub struct FeedbackRequest {
+    rating: Rating,
+    comment: Option<String>,
+    user_id: Option<String>,
+}
+
+/// Query parameters for feedback aggregation
+#[derive(Debug, Deserialize)]
+pub struct FeedbackQuery {
+    app_name: Option<String>,
+    user_id: Option<String>,
+}
+
+/// Record thumbs up/down feedback on an event
+pub async fn record_event_feedback(
+    Path((session_id, event_id)): Path<(String, String)>,
+    State(state): State<ServerState>,
+    Json(request): Json<FeedbackRequest>,
+) -> Result<Json<Feedback>, StatusCode> {
+    let mut entry = Feedback::new(request.rating);
+    entry.comment = request.comment;
+    entry.user_id = request.user_id;
+
+    feedback::record_feedback(state.session_service.as_ref(), &session_id, &event_id, &entry)
+        .await
+        .map_err(|e| {
+            warn!("Failed to record feedback: {}", e);
+            StatusCode::NOT_FOUND
+        })?;
+
+    Ok(Json(entry))
+}
+
+/// Aggregate feedback across sessions
+pub async fn feedback_summary(
+    Query(query): Query<FeedbackQuery>,
+    State(state): State<ServerState>,
+) -> Result<Json<FeedbackSummary>, StatusCode> {
+    let filter = SessionFilter {
+        app_name: query.app_name,
+        user_id: query.user_id,
+        ..Default::default()
+    };
+    let sessions = state
+        .session_service
+        .list_sessions(&filter)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(feedback::summarize(&sessions)))
+}
+
+/// Query parameters for admin statistics
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    /// Size of the reporting window in hours (default 24)
+    window_hours: Option<i64>,
+    /// Size of each token spend bucket in minutes (default 60)
+    bucket_minutes: Option<i64>,
+}
+
+/// Summarize sessions, invocations, errors, latency, and token spend
+pub async fn admin_stats(
+    Query(query): Query<StatsQuery>,
+    State(state): State<ServerState>,
+) -> Result<Json<admin::AdminStats>, StatusCode> {
+    let window = chrono::Duration::hours(query.window_hours.unwrap_or(24));
+    let bucket = chrono::Duration::minutes(query.bucket_minutes.unwrap_or(60));
+
+    admin::collect_stats(state.session_service.as_ref(), &state.usage_tracker, window, bucket)
+        .await
+        .map(|stats| {
+            Json(admin::AdminStats {
+                scheduler: state.scheduler.stats(),
+                event_subscribers: state.event_bus.subscriber_stats(),
+                ..stats
+            })
+        })
+        .map_err(|e| match e {
+            crate::error::AdkError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })
+}
+
+/// Query parameters of `/api/admin/analytics`
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsQuery {
+    /// Only this application's metrics
+    app_name: Option<String>,
+}
+
+/// Latest conversation metrics of every app (or one app)
+pub async fn conversation_analytics(
+    Query(query): Query<AnalyticsQuery>,
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<ConversationMetrics>>, StatusCode> {
+    state.analytics.latest(query.app_name.as_deref()).await.map(Json).map_err(|e| {
+        warn!("Failed to load conversation analytics: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// List failed background work awaiting retry
+pub async fn list_dead_letters(State(state): State<ServerState>) -> Json<Vec<DeadLetter>> {
+    Json(state.dead_letters.list().await)
+}
+
+/// Result of replaying a dead letter
+#[derive(Debug, Serialize)]
+pub struct ReplayResponse {
+    id: String,
+    succeeded: bool,
+    /// The item as left in the queue after a failed replay
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dead_letter: Option<DeadLetter>,
+}
+
+/// Retry a dead letter immediately
+pub async fn replay_dead_letter(
+    Path(id): Path<String>,
+    State(state): State<ServerState>,
+) -> Result<Json<ReplayResponse>, StatusCode> {
+    if state.dead_letters.get(&id).await.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let succeeded = state.dead_letters.replay(&id).await.map_err(|e| {
+        warn!("Failed to replay dead letter {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let dead_letter = state.dead_letters.get(&id).await;
+    Ok(Json(ReplayResponse { id, succeeded, dead_letter }))
+}
+
+/// List agent steps paused at debug breakpoints
+pub async fn list_paused_steps(State(state): State<ServerState>) -> Json<Vec<PausedStep>> {
+    Json(state.debugger.paused())
+}
+
+/// Continue, modify or abort a paused step
+pub async fn resume_paused_step(
+    Path(pause_id): Path<String>,
+    State(state): State<ServerState>,
+    Json(command): Json<DebugCommand>,
+) -> StatusCode {
+    match state.debugger.resume(&pause_id, command) {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(e) => {
+            warn!("Failed to resume paused step: {}", e);
+            StatusCode::NOT_FOUND
+        }
+    }
+}
+
+/// Discard a dead letter without retrying it
+pub async fn delete_dead_letter(
+    Path(id): Path<String>,
+    State(state): State<ServerState>,
+) -> StatusCode {
+    match state.dead_letters.delete(&id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            warn!("Failed to delete dead letter {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Erase every session and artifact of a user and return the audit record,
+/// which names the admin who asked for it
+pub async fn delete_user_data(
+    Path(user_id): Path<String>,
+    Extension(admin): Extension<AdminPrincipal>,
+    State(state): State<ServerState>,
+) -> Result<Json<DeletionRecord>, StatusCode> {
+    state
+        .data_eraser()
+        .delete_user_data(&user_id, Some(admin.0))
+        .await
+        .map(Json)
+        .map_err(|e| {
+            warn!("Failed to delete data of user {}: {}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Audit records of user data erasures and retention deletions
+pub async fn list_deletions(State(state): State<ServerState>) -> Result<Json<Vec<DeletionRecord>>, StatusCode> {
+    state.deletion_audit.list().await.map(Json).map_err(|e| {
+        warn!("Failed to load deletion records: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Original of a redacted event; not found unless a redaction vault is configured
+pub async fn get_original_event(
+    Path((session_id, event_id)): Path<(String, String)>,
+    Extension(admin): Extension<AdminPrincipal>,
+    State(state): State<ServerState>,
+) -> Result<Json<Event>, StatusCode> {
+    let Some(vault) = &state.redaction_vault else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    match vault.original(&session_id, &event_id).await {
+        Ok(Some(event)) => {
+            tracing::info!(
+                "Admin {} viewed the original of redacted event {} in session {}",
+                admin.0,
+                event_id,
+                session_id
+            );
+            Ok(Json(event))
+        }
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            warn!("Failed to load the original of event {}: {}", event_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List the webhooks registered for an agent; secrets are not returned
+pub async fn list_webhooks(
+    Path(agent_name): Path<String>,
+    State(state): State<ServerState>,
+) -> Json<Vec<WebhookConfig>> {
+    Json(state.webhooks.webhooks(&agent_name))
+}
+
+/// Register a webhook for an agent's lifecycle events
+pub async fn register_webhook(
+    Path(agent_name): Path<String>,
+    State(state): State<ServerState>,
+    Json(webhook): Json<WebhookConfig>,
+) -> Result<(StatusCode, Json<WebhookConfig>), StatusCode> {
+    if url::Url::parse(&webhook.url).map_or(true, |url| !matches!(url.scheme(), "http" | "https")) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    state.webhooks.register(agent_name, webhook.clone());
+    Ok((StatusCode::CREATED, Json(webhook)))
+}
+
+/// Remove a webhook
+pub async fn delete_webhook(
+    Path((agent_name, webhook_id)): Path<(String, String)>,
+    State(state): State<ServerState>,
+) -> StatusCode {
+    if state.webhooks.remove(&agent_name, &webhook_id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+/// A conversation handed over to human operators
+#[derive(Serialize)]
+pub struct HandoffInfo {
+    session: SessionInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requested_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl From<&Session> for HandoffInfo {
+    fn from(session: &Session) -> Self {
+        let handoff = handoff::latest_handoff(session);
+        Self {
+            reason: handoff.map(|(_, handoff)| handoff.reason.clone()),
+            requested_at: handoff.map(|(event, _)| event.timestamp),
+            session: SessionInfo::from(session.clone()),
+        }
+    }
+}
+
+/// Transcript of a handed-over conversation
+#[derive(Serialize)]
+pub struct HandoffTranscript {
+    #[serde(flatten)]
+    handoff: HandoffInfo,
+    events: Vec<EventResponse>,
+}
+
+/// Query parameters for listing handoffs
+#[derive(Debug, Deserialize)]
+pub struct HandoffQuery {
+    app_name: Option<String>,
+}
+
+/// Operator reply to a handed-over conversation
+#[derive(Debug, Deserialize)]
+pub struct OperatorReplyRequest {
+    message: String,
+    operator: Option<String>,
+}
+
+/// Return a conversation to the agent, optionally with a closing operator message
+#[derive(Debug, Default, Deserialize)]
+pub struct ReturnToAgentRequest {
+    message: Option<String>,
+    operator: Option<String>,
+}
+
+/// Load a human-controlled session, 404 if the session is not handed over
+async fn handed_over_session(state: &ServerState, session_id: &str) -> Result<Session, StatusCode> {
+    handoff::find_human_controlled_session(state.session_service.as_ref(), session_id)
+        .await
+        .map_err(|e| {
+            warn!("Failed to load handed-over session: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Append an operator reply and publish it to streaming subscribers
+async fn post_operator_reply(
+    state: &ServerState,
+    session: &Session,
+    operator: Option<&str>,
+    message: String,
+) -> Result<Arc<Event>, StatusCode> {
+    let event = handoff::operator_reply(state.session_service.as_ref(), session, operator, message)
+        .await
+        .map_err(|e| {
+            warn!("Failed to record operator reply: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    state.event_bus.publish(PublishedEvent {
+        app_name: session.app_name.clone(),
+        user_id: session.user_id.clone(),
+        session_id: session.id.clone(),
+        event: event.clone(),
+    });
+    Ok(event)
+}
+
+/// List conversations waiting for or handled by operators, most recently active first
+pub async fn list_handoffs(
+    Query(query): Query<HandoffQuery>,
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<HandoffInfo>>, StatusCode> {
+    let sessions = handoff::human_controlled_sessions(state.session_service.as_ref(), query.app_name)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(sessions.iter().map(HandoffInfo::from).collect()))
+}
+
+/// Read the transcript of a handed-over conversation
+pub async fn get_handoff(
+    Path(session_id): Path<String>,
+    State(state): State<ServerState>,
+) -> Result<Json<HandoffTranscript>, StatusCode> {
+    let session = handed_over_session(&state, &session_id).await?;
+    Ok(Json(HandoffTranscript {
+        handoff: HandoffInfo::from(&session),
+        events: session.events.iter().map(|event| EventResponse::from(event.as_ref())).collect(),
+    }))
+}
+
+/// Reply to the user of a handed-over conversation
+pub async fn reply_to_handoff(
+    Path(session_id): Path<String>,
+    State(state): State<ServerState>,
+    Json(request): Json<OperatorReplyRequest>,
+) -> Result<Json<EventResponse>, StatusCode> {
+    if request.message.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let session = handed_over_session(&state, &session_id).await?;
+    let event = post_operator_reply(&state, &session, request.operator.as_deref(), request.message).await?;
+    Ok(Json(EventResponse::from(event.as_ref())))
+}
+
+/// Give a handed-over conversation back to the agent
+pub async fn return_handoff(
+    Path(session_id): Path<String>,
+    State(state): State<ServerState>,
+    request: Option<Json<ReturnToAgentRequest>>,
+) -> Result<Json<SessionInfo>, StatusCode> {
+    let Json(request) = request.unwrap_or_default();
+    let session = handed_over_session(&state, &session_id).await?;
+    if let Some(message) = request.message.filter(|message| !message.trim().is_empty()) {
+        post_operator_reply(&state, &session, request.operator.as_deref(), message).await?;
+    }
+    handoff::return_to_agent(state.session_service.as_ref(), &session)
+        .await
+        .map_err(|e| {
+            warn!("Failed to return session to the agent: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let mut session = session;
+    session.tags.remove(handoff::HUMAN_HANDOFF_TAG);
+    Ok(Json(SessionInfo::from(session)))
+}
+
+/// Get the traffic split for an agent
+pub async fn get_traffic_split(
+    Path(agent_name): Path<String>,
+    State(state): State<ServerState>,
+) -> Result<Json<TrafficSplit>, StatusCode> {
+    state.routing.split(&agent_name).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Split an agent's traffic between a stable and a canary version
+pub async fn set_traffic_split(
+    Path(agent_name): Path<String>,
+    State(state): State<ServerState>,
+    Json(split): Json<TrafficSplit>,
+) -> Result<Json<TrafficSplit>, StatusCode> {
+    state
+        .routing
+        .set_split(&state.agents, &agent_name, split)
+        .map(Json)
+        .map_err(|e| {
+            warn!("Rejected traffic split for {}: {}", agent_name, e);
+            StatusCode::BAD_REQUEST
+        })
+}
+
+/// Remove an agent's traffic split, returning all traffic to its default version
+pub async fn clear_traffic_split(
+    Path(agent_name): Path<String>,
+    State(state): State<ServerState>,
+) -> StatusCode {
+    match state.routing.clear_split(&agent_name) {
+        Some(_) => StatusCode::NO_CONTENT,
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
 /// List available models
 pub async fn list_models() -> Json<Vec<String>> {
     let models = list_available_models().await;
@@ -251,26 +1096,31 @@ pub async fn list_models() -> Json<Vec<String>> {
 pub async fn get_model_info(
     Path(model_name): Path<String>,
 ) -> Result<Json<ModelInfoResponse>, StatusCode> {
-    // Simple implementation for now
-    Ok(Json(ModelInfoResponse {
-        name: model_name,
-        supports_streaming: true,
-        supports_function_calling: true,
-        supports_multimodal: false,
-        supports_live: false,
-    }))
+    models::get_model_info(&model_name)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::NOT_FOUND)
 }
 
 /// WebSocket handler
 pub async fn websocket_handler(
     Path(agent_name): Path<String>,
+    Query(query): Query<VersionQuery>,
+    headers: HeaderMap,
     ws: WebSocketUpgrade,
     State(state): State<ServerState>,
 ) -> Response {
+    // Each connection is routed once, so its whole conversation uses one version
+    let routing_key = state.new_id();
+    let version = state
+        .routing
+        .select_version(&agent_name, query.version.as_deref(), &headers, &routing_key);
     let handler = state.websocket_handler.clone();
     let state_clone = state.clone();
     ws.on_upgrade(move |socket| async move {
-        handler.handle_connection(socket, agent_name, state_clone).await
+        handler
+            .handle_connection(socket, agent_name, version, state_clone)
+            .await
     })
 }
 
@@ -321,3 +1171,99 @@ pub async fn openapi_spec() -> Json<serde_json::Value> {
         }
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::{base_agent::EventStream, InvocationContext},
+        types::{AgentId, Metadata},
+        web::ServerConfig,
+    };
+    use async_trait::async_trait;
+    use axum::{body::Body, http::Request, routing::post, Router};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    /// Answers after `delay`, or never if there is none
+    struct SlowAgent {
+        id: AgentId,
+        metadata: Metadata,
+        delay: Option<Duration>,
+    }
+
+    #[async_trait]
+    impl BaseAgent for SlowAgent {
+        fn id(&self) -> &AgentId {
+            &self.id
+        }
+        fn name(&self) -> &str {
+            "slow"
+        }
+        fn description(&self) -> &str {
+            ""
+        }
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+        fn parent(&self) -> Option<&dyn BaseAgent> {
+            None
+        }
+        fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+            &[]
+        }
+        async fn run_async(&self, _ctx: InvocationContext) -> crate::error::Result<EventStream> {
+            let Some(delay) = self.delay else {
+                return Ok(Box::pin(futures::stream::pending()));
+            };
+            Ok(Box::pin(futures::stream::once(async move {
+                tokio::time::sleep(delay).await;
+                Ok(Arc::new(Event::text_response("slow", "Done")))
+            })))
+        }
+        async fn run_live(&self, ctx: InvocationContext) -> crate::error::Result<EventStream> {
+            self.run_async(ctx).await
+        }
+    }
+
+    /// Full SSE body of a stream request to the slow agent
+    async fn stream_body(config: ServerConfig, delay: Option<Duration>) -> String {
+        let state = ServerState::new(config);
+        state.agents.register("slow", Arc::new(SlowAgent { id: "slow".to_string(), metadata: Metadata::new(), delay }));
+        let router = Router::new()
+            .route("/api/agents/:agent_name/stream", post(stream_agent))
+            .with_state(state);
+        let request = Request::post("/api/agents/slow/stream")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"message": "Hi"}"#))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = tokio::time::timeout(Duration::from_secs(5), axum::body::to_bytes(response.into_body(), usize::MAX))
+            .await
+            .expect("the stream should end")
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stream_sends_heartbeats_while_the_agent_works() {
+        let config = ServerConfig::default().with_heartbeat_interval(1).with_idle_timeout(0);
+        let body = stream_body(config, Some(Duration::from_millis(1500))).await;
+
+        // A keep-alive comment precedes the answer that took longer than the interval
+        let heartbeat = body.find(":\n\n").expect("no heartbeat comment sent");
+        let answer = body.find("Done").expect("no answer sent");
+        assert!(heartbeat < answer);
+        assert!(body.ends_with("data: [DONE]\n\n"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_is_closed_with_an_idle_timeout_event() {
+        let config = ServerConfig::default().with_heartbeat_interval(0).with_idle_timeout(1);
+        let body = stream_body(config, None).await;
+
+        assert!(body.starts_with("event: idle_timeout\ndata: {}\n\n"));
+        assert!(body.ends_with("data: [DONE]\n\n"));
+    }
+}
@@ -0,0 +1,340 @@
+//! Idempotency keys for agent run requests
+//!
+//! A client retrying `POST /api/agents/{name}/run` with the same
+//! `Idempotency-Key` header (and user and session) gets the stored result of
+//! the first successful run instead of paying for another one. A retry that
+//! arrives while the first request is still running is answered with
+//! `409 Conflict`, and one reusing the key with a different request body with
+//! `422 Unprocessable Entity`. Failed runs release their key so they can be
+//! retried.
+//!
+//! Stored results expire after the TTL, checked when their key is looked up,
+//! and the oldest are evicted once the store holds its maximum number.
+
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Request header carrying the client's idempotency key
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set when a stored result is returned
+pub const IDEMPOTENT_REPLAY_HEADER: &str = "idempotent-replayed";
+
+/// Default time stored results stay replayable
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default maximum number of stored results
+pub const DEFAULT_IDEMPOTENCY_MAX_ENTRIES: usize = 10_000;
+
+/// Longest accepted idempotency key
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    agent: String,
+    user_id: String,
+    session_id: Option<String>,
+    key: String,
+}
+
+/// SHA-256 of the request body the key was first used with
+type Fingerprint = [u8; 32];
+
+struct Completed {
+    request: Fingerprint,
+    body: Bytes,
+    stored_at: Instant,
+}
+
+#[derive(Default)]
+struct Entries {
+    /// Keys of runs still in progress; bounded by the number of concurrent runs
+    in_progress: HashMap<Key, Fingerprint>,
+    completed: HashMap<Key, Completed>,
+    /// Completed keys with their store time, oldest first; a key whose entry
+    /// was since removed or replaced is skipped when it reaches the front
+    order: VecDeque<(Key, Instant)>,
+}
+
+impl Entries {
+    /// Drop the entry `order` points at, unless the key was stored again since
+    fn remove_stored(&mut self, key: &Key, stored_at: Instant) {
+        if self.completed.get(key).is_some_and(|entry| entry.stored_at == stored_at) {
+            self.completed.remove(key);
+        }
+    }
+}
+
+/// Outcome of starting a keyed request
+pub enum IdempotencyCheck {
+    /// First request with this key; complete the guard once the run succeeds
+    Started(IdempotencyGuard),
+
+    /// The key was already processed; this is the stored response
+    Replay(Response),
+
+    /// A request with this key is still running
+    InProgress,
+
+    /// The key was first used with a different request body
+    Mismatch,
+}
+
+/// Stored results of keyed run requests
+#[derive(Clone)]
+pub struct IdempotencyStore {
+    entries: Arc<Mutex<Entries>>,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl IdempotencyStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Entries::default())),
+            ttl,
+            max_entries: DEFAULT_IDEMPOTENCY_MAX_ENTRIES,
+        }
+    }
+
+    /// Keep at most `max_entries` stored results, evicting the oldest first
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Claim `key` for a run of `agent` by `user_id` in `session_id` with the
+    /// `request` body, or get what it already produced
+    pub fn begin(
+        &self,
+        agent: &str,
+        user_id: &str,
+        session_id: Option<&str>,
+        key: &str,
+        request: &[u8],
+    ) -> IdempotencyCheck {
+        let key = Key {
+            agent: agent.to_string(),
+            user_id: user_id.to_string(),
+            session_id: session_id.map(str::to_string),
+            key: key.to_string(),
+        };
+        let request: Fingerprint = Sha256::digest(request).into();
+        let mut entries = self.entries.lock().unwrap();
+
+        // An expired result is forgotten when its key comes back
+        if entries.completed.get(&key).is_some_and(|entry| entry.stored_at.elapsed() >= self.ttl) {
+            entries.completed.remove(&key);
+        }
+
+        if let Some(entry) = entries.completed.get(&key) {
+            return if entry.request != request {
+                IdempotencyCheck::Mismatch
+            } else {
+                IdempotencyCheck::Replay(replay_response(entry.body.clone()))
+            };
+        }
+        match entries.in_progress.get(&key) {
+            Some(started) if started != &request => IdempotencyCheck::Mismatch,
+            Some(_) => IdempotencyCheck::InProgress,
+            None => {
+                entries.in_progress.insert(key.clone(), request);
+                IdempotencyCheck::Started(IdempotencyGuard {
+                    store: self.clone(),
+                    key: Some((key, request)),
+                })
+            }
+        }
+    }
+
+    /// Store the result of a completed run, evicting expired and then the oldest results
+    fn store(&self, key: Key, request: Fingerprint, body: Bytes) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.in_progress.remove(&key);
+        let stored_at = Instant::now();
+        entries.completed.insert(key.clone(), Completed { request, body, stored_at });
+        entries.order.push_back((key, stored_at));
+
+        while let Some((key, stored_at)) = entries.order.front().cloned() {
+            if stored_at.elapsed() < self.ttl && entries.order.len() <= self.max_entries {
+                break;
+            }
+            entries.order.pop_front();
+            entries.remove_stored(&key, stored_at);
+        }
+    }
+
+    /// Number of keys currently remembered
+    pub fn len(&self) -> usize {
+        let entries = self.entries.lock().unwrap();
+        entries.in_progress.len() + entries.completed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for IdempotencyStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_TTL)
+    }
+}
+
+fn replay_response(body: Bytes) -> Response {
+    let mut response = (
+        [(header::CONTENT_TYPE, HeaderValue::from_static("application/json"))],
+        body,
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(IDEMPOTENT_REPLAY_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// Response for a retry that raced the original request
+pub fn in_progress_response() -> Response {
+    (
+        StatusCode::CONFLICT,
+        axum::Json(serde_json::json!({
+            "error": "a request with this idempotency key is still being processed"
+        })),
+    )
+        .into_response()
+}
+
+/// Response for a retry whose body differs from the request the key was first used with
+pub fn mismatch_response() -> Response {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        axum::Json(serde_json::json!({
+            "error": "this idempotency key was already used with a different request"
+        })),
+    )
+        .into_response()
+}
+
+/// Claim on an idempotency key; dropping it without [`complete`](Self::complete) releases the key
+pub struct IdempotencyGuard {
+    store: IdempotencyStore,
+    key: Option<(Key, Fingerprint)>,
+}
+
+impl IdempotencyGuard {
+    /// Store the serialized response for later retries
+    pub fn complete(mut self, body: impl Into<Bytes>) {
+        if let Some((key, request)) = self.key.take() {
+            self.store.store(key, request, body.into());
+        }
+    }
+}
+
+impl Drop for IdempotencyGuard {
+    fn drop(&mut self) {
+        if let Some((key, _)) = self.key.take() {
+            self.store.entries.lock().unwrap().in_progress.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = br#"{"message":"hi"}"#;
+
+    #[test]
+    fn test_replays_completed_and_releases_failed_keys() {
+        let store = IdempotencyStore::default();
+
+        let IdempotencyCheck::Started(guard) = store.begin("agent", "u1", Some("s1"), "k", BODY) else {
+            panic!("first request should start");
+        };
+        assert!(matches!(store.begin("agent", "u1", Some("s1"), "k", BODY), IdempotencyCheck::InProgress));
+        // Keys are scoped by session
+        assert!(matches!(store.begin("agent", "u1", Some("s2"), "k", BODY), IdempotencyCheck::Started(_)));
+        guard.complete(r#"{"response":"hi"}"#);
+
+        let IdempotencyCheck::Replay(response) = store.begin("agent", "u1", Some("s1"), "k", BODY) else {
+            panic!("completed request should replay");
+        };
+        assert_eq!(response.headers()[IDEMPOTENT_REPLAY_HEADER], "true");
+
+        // A failed run drops its guard and can be retried
+        let IdempotencyCheck::Started(failed) = store.begin("agent", "u1", None, "retry-me", BODY) else {
+            panic!("first request should start");
+        };
+        drop(failed);
+        assert!(matches!(store.begin("agent", "u1", None, "retry-me", BODY), IdempotencyCheck::Started(_)));
+    }
+
+    #[test]
+    fn test_keys_are_scoped_by_user_and_bound_to_the_body() {
+        let store = IdempotencyStore::default();
+        let IdempotencyCheck::Started(guard) = store.begin("agent", "alice", None, "k", BODY) else {
+            panic!("first request should start");
+        };
+        guard.complete(r#"{"response":"for alice"}"#);
+
+        // Another user reusing the key runs their own request
+        assert!(matches!(store.begin("agent", "mallory", None, "k", BODY), IdempotencyCheck::Started(_)));
+        // The same user reusing it for another request is rejected
+        let check = store.begin("agent", "alice", None, "k", br#"{"message":"bye"}"#);
+        assert!(matches!(check, IdempotencyCheck::Mismatch));
+        assert_eq!(mismatch_response().status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn test_expired_results_are_forgotten() {
+        let store = IdempotencyStore::new(Duration::ZERO);
+        let IdempotencyCheck::Started(guard) = store.begin("agent", "u1", None, "k", BODY) else {
+            panic!("first request should start");
+        };
+        guard.complete("{}");
+        // Expired results are dropped as soon as the next one is stored
+        assert!(store.is_empty());
+        assert!(matches!(store.begin("agent", "u1", None, "k", BODY), IdempotencyCheck::Started(_)));
+    }
+
+    #[test]
+    fn test_expired_results_are_forgotten_on_lookup() {
+        let store = IdempotencyStore::new(Duration::from_millis(20));
+        let IdempotencyCheck::Started(guard) = store.begin("agent", "u1", None, "k", BODY) else {
+            panic!("first request should start");
+        };
+        guard.complete("{}");
+        assert!(matches!(store.begin("agent", "u1", None, "k", BODY), IdempotencyCheck::Replay(_)));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(matches!(store.begin("agent", "u1", None, "k", BODY), IdempotencyCheck::Started(_)));
+    }
+
+    #[test]
+    fn test_oldest_results_are_evicted_beyond_max_entries() {
+        let store = IdempotencyStore::default().with_max_entries(2);
+        for key in ["a", "b", "c"] {
+            let IdempotencyCheck::Started(guard) = store.begin("agent", "u1", None, key, BODY) else {
+                panic!("first request should start");
+            };
+            guard.complete("{}");
+        }
+        assert_eq!(store.len(), 2);
+        let IdempotencyCheck::Started(_running) = store.begin("agent", "u1", None, "a", BODY) else {
+            panic!("the oldest result should have been evicted");
+        };
+        assert!(matches!(store.begin("agent", "u1", None, "b", BODY), IdempotencyCheck::Replay(_)));
+        assert!(matches!(store.begin("agent", "u1", None, "c", BODY), IdempotencyCheck::Replay(_)));
+        // Running requests do not count against the limit
+        assert_eq!(store.len(), 3);
+    }
+}
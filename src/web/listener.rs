@@ -0,0 +1,231 @@
+//! Where the web server accepts connections
+//!
+//! Besides the default TCP `host:port`, the server can listen on a Unix domain
+//! socket or on a socket inherited through systemd socket activation, for
+//! sidecar deployments behind a local reverse proxy.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::Mutex};
+
+/// Listening socket of the web server
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ListenMode {
+    /// TCP on the configured host and port
+    #[default]
+    Tcp,
+
+    /// Unix domain socket at `path`; a stale socket file is replaced
+    Unix { path: PathBuf },
+
+    /// First socket passed by systemd (`LISTEN_FDS`), TCP or Unix; requires
+    /// [`capture_systemd_activation`] at startup
+    Systemd,
+}
+
+/// A bound listener ready to accept connections
+pub(crate) enum BoundListener {
+    Tcp(tokio::net::TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener),
+}
+
+/// First file descriptor passed by systemd
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: std::os::fd::RawFd = 3;
+
+/// Whether systemd passed sockets, as read by [`capture_systemd_activation`];
+/// the first listener takes the socket and leaves an error behind
+static SYSTEMD_ACTIVATION: Mutex<Option<std::result::Result<(), &'static str>>> = Mutex::new(None);
+
+/// Read and clear systemd's socket activation variables.
+///
+/// Call at the start of `main`, before the async runtime or any other thread
+/// starts: the environment cannot be modified safely while other threads may
+/// read it. The variables are cleared so the sockets do not leak into child
+/// processes such as code executors. Only the first call has an effect.
+pub fn capture_systemd_activation() {
+    let mut activation = SYSTEMD_ACTIVATION.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if activation.is_none() {
+        *activation = Some(read_systemd_activation());
+    }
+}
+
+#[cfg(unix)]
+fn read_systemd_activation() -> std::result::Result<(), &'static str> {
+    let pid = std::env::var("LISTEN_PID").map_err(|_| "LISTEN_PID is not set")?;
+    if pid.trim() != std::process::id().to_string() {
+        return Err("LISTEN_PID belongs to another process");
+    }
+    let fds: u32 = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|fds| fds.trim().parse().ok())
+        .ok_or("LISTEN_FDS is not a number")?;
+    if fds == 0 {
+        return Err("no sockets were passed");
+    }
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn read_systemd_activation() -> std::result::Result<(), &'static str> {
+    Err("only supported on Unix")
+}
+
+/// Take the socket systemd passed to this process
+#[cfg(unix)]
+pub(crate) fn systemd_listener() -> Result<BoundListener> {
+    use std::os::fd::{FromRawFd, IntoRawFd};
+
+    let activation = SYSTEMD_ACTIVATION
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .replace(Err("the socket was already taken"));
+    activation
+        .unwrap_or(Err("capture_systemd_activation was not called at startup"))
+        .map_err(|reason| crate::adk_error!(ConfigError, "Systemd socket activation unavailable: {}", reason))?;
+
+    // SAFETY: systemd hands over ownership of descriptors starting at 3, and
+    // the activation was replaced above so the descriptor is only taken once
+    let tcp = unsafe { std::net::TcpListener::from_raw_fd(SD_LISTEN_FDS_START) };
+    if tcp.local_addr().is_ok() {
+        tcp.set_nonblocking(true)?;
+        return Ok(BoundListener::Tcp(tokio::net::TcpListener::from_std(tcp)?));
+    }
+    // SAFETY: same descriptor, released from the TCP wrapper above
+    let unix = unsafe { std::os::unix::net::UnixListener::from_raw_fd(tcp.into_raw_fd()) };
+    unix.set_nonblocking(true)?;
+    Ok(BoundListener::Unix(tokio::net::UnixListener::from_std(unix)?))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn systemd_listener() -> Result<BoundListener> {
+    Err(crate::adk_error!(ConfigError, "Systemd socket activation is only supported on Unix"))
+}
+
+/// Bind a Unix socket at `path`, replacing a stale socket file
+#[cfg(unix)]
+pub(crate) fn bind_unix(path: &std::path::Path) -> Result<BoundListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(crate::adk_error!(
+                ConfigError,
+                "Refusing to replace {}: not a socket",
+                path.display()
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    Ok(BoundListener::Unix(tokio::net::UnixListener::bind(path)?))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn bind_unix(_path: &std::path::Path) -> Result<BoundListener> {
+    Err(crate::adk_error!(ConfigError, "Unix domain sockets are only supported on Unix"))
+}
+
+/// Serve `router` on a Unix socket until `shutdown` is cancelled, then let
+/// in-flight connections finish for up to the shutdown grace period
+#[cfg(unix)]
+pub(crate) async fn serve_unix(
+    listener: tokio::net::UnixListener,
+    router: axum::Router,
+    shutdown: tokio_util::sync::CancellationToken,
+) -> Result<()> {
+    use hyper_util::{
+        rt::{TokioExecutor, TokioIo},
+        server::{conn::auto::Builder, graceful::GracefulShutdown},
+        service::TowerToHyperService,
+    };
+
+    let builder = Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("Failed to accept Unix socket connection: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    continue;
+                }
+            },
+            _ = shutdown.cancelled() => break,
+        };
+        let connection = builder
+            .serve_connection_with_upgrades(TokioIo::new(stream), TowerToHyperService::new(router.clone()))
+            .into_owned();
+        let connection = graceful.watch(connection);
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                tracing::debug!("Unix socket connection ended with error: {}", e);
+            }
+        });
+    }
+
+    if tokio::time::timeout(super::server::SHUTDOWN_GRACE_PERIOD, graceful.shutdown())
+        .await
+        .is_err()
+    {
+        tracing::warn!("Unix socket connections still open after shutdown grace period");
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_systemd_listener_reports_missing_activation() {
+        // The test runner is not socket activated
+        capture_systemd_activation();
+        let Err(error) = systemd_listener() else {
+            panic!("expected no systemd socket");
+        };
+        assert!(error.to_string().contains("LISTEN_PID is not set"));
+        let Err(error) = systemd_listener() else {
+            panic!("expected no systemd socket");
+        };
+        assert!(error.to_string().contains("already taken"));
+    }
+
+    #[tokio::test]
+    async fn test_serves_http_over_unix_socket() {
+        let path = std::env::temp_dir().join(format!("adk-{}.sock", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"not a socket").unwrap();
+        assert!(bind_unix(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        let BoundListener::Unix(listener) = bind_unix(&path).unwrap() else {
+            panic!("expected a Unix listener");
+        };
+        let router = axum::Router::new().route("/health", get(|| async { "ok" }));
+        let shutdown = tokio_util::sync::CancellationToken::new();
+        let server = tokio::spawn(serve_unix(listener, router, shutdown.clone()));
+
+        let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("ok"));
+
+        shutdown.cancel();
+        server.await.unwrap().unwrap();
+        // A second bind replaces the stale socket file
+        assert!(bind_unix(&path).is_ok());
+        std::fs::remove_file(path).unwrap();
+    }
+}
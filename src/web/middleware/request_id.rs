@@ -3,10 +3,10 @@
 use axum::{
     extract::Request,
     http::HeaderValue,
-    middleware::Next,
     response::Response,
 };
 use tower::{Layer, Service};
+use tracing::Instrument;
 use uuid::Uuid;
 use std::{
     future::Future,
@@ -72,8 +72,9 @@ where
                 HeaderValue::from_str(&request_id).unwrap(),
             );
 
-            // Call the inner service
-            let mut response = inner.call(request).await?;
+            // Call the inner service inside a span, so handler and agent logs carry the request id
+            let span = tracing::info_span!("request", request_id = %request_id);
+            let mut response = inner.call(request).instrument(span).await?;
 
             // Add request ID to response headers
             response.headers_mut().insert(
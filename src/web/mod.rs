@@ -1,10 +1,33 @@
 //! Web server and API system
 
+pub mod admin;
+pub mod artifacts;
+pub mod auth;
+pub mod backpressure;
+pub mod cors;
 pub mod server;
 pub mod handlers;
+pub mod idempotency;
+pub mod listener;
 pub mod websocket;
 pub mod middleware;
+pub mod routing;
+pub mod run_response;
+pub mod scheduling;
+pub mod tls;
 
+pub use artifacts::{RangeRequest, ARTIFACT_VERSION_HEADER};
+pub use auth::{AdminPrincipal, DEFAULT_ADMIN_PRINCIPAL};
+pub use backpressure::{buffer_events, stream_buffer_stats, StreamBufferStats, DEFAULT_STREAM_BUFFER_CAPACITY};
+pub use cors::{CorsConfig, CorsPolicy};
 pub use server::{WebServer, ServerConfig, WebServerBuilder, ServerState};
 pub use handlers::*;
+pub use idempotency::{
+    IdempotencyStore, DEFAULT_IDEMPOTENCY_MAX_ENTRIES, DEFAULT_IDEMPOTENCY_TTL, IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAY_HEADER,
+};
+pub use listener::{capture_systemd_activation, ListenMode};
+pub use routing::{RoutingPolicy, TrafficSplit, AGENT_VERSION_HEADER};
+pub use run_response::{ResponsePart, StructuredResponse, ToolCallRecord};
+pub use scheduling::{InvocationPermit, InvocationScheduler, Overloaded, Priority, SchedulerConfig, SchedulerStats, PRIORITY_HEADER};
+pub use tls::TlsConfig;
 pub use websocket::WebSocketHandler;
@@ -0,0 +1,176 @@
+//! Traffic-split routing between agent versions
+
+use crate::{agents::AgentRegistry, error::Result};
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// Request header pinning the agent version to serve
+pub const AGENT_VERSION_HEADER: &str = "x-agent-version";
+
+/// Split of an agent's traffic between a stable and a canary version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrafficSplit {
+    pub stable: String,
+    pub canary: String,
+
+    /// Percentage (0-100) of routing keys served by the canary
+    pub canary_percent: u8,
+
+    /// Requests carrying this header are always served by the canary
+    #[serde(default)]
+    pub canary_header: Option<String>,
+}
+
+impl TrafficSplit {
+    pub fn new(stable: impl Into<String>, canary: impl Into<String>, canary_percent: u8) -> Self {
+        Self {
+            stable: stable.into(),
+            canary: canary.into(),
+            canary_percent,
+            canary_header: None,
+        }
+    }
+
+    pub fn with_canary_header(mut self, header: impl Into<String>) -> Self {
+        self.canary_header = Some(header.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Pick the version for a request; the same routing key always gets the same version
+    pub fn select(&self, headers: &HeaderMap, routing_key: &str) -> &str {
+        let forced = self
+            .canary_header
+            .as_deref()
+            .is_some_and(|header| headers.contains_key(header));
+        if forced || bucket(routing_key) < u64::from(self.canary_percent) {
+            &self.canary
+        } else {
+            &self.stable
+        }
+    }
+}
+
+/// Stable bucket in 0..100 for a routing key
+fn bucket(routing_key: &str) -> u64 {
+    let digest = Sha256::digest(routing_key.as_bytes());
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(prefix) % 100
+}
+
+/// Per-agent traffic splits applied by the web server
+#[derive(Debug, Clone, Default)]
+pub struct RoutingPolicy {
+    splits: Arc<RwLock<HashMap<String, TrafficSplit>>>,
+}
+
+impl RoutingPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Install a split for an agent after checking both versions are registered
+    pub fn set_split(
+        &self,
+        registry: &AgentRegistry,
+        agent_name: &str,
+        mut split: TrafficSplit,
+    ) -> Result<TrafficSplit> {
+        if split.canary_percent > 100 {
+            return Err(crate::adk_error!(
+                ValidationError,
+                "canary_percent must be between 0 and 100, got {}",
+                split.canary_percent
+            ));
+        }
+        let versions = registry.versions(agent_name);
+        for version in [&split.stable, &split.canary] {
+            if !versions.contains(version) {
+                return Err(crate::adk_error!(
+                    ValidationError,
+                    "Agent '{}' has no version '{}'",
+                    agent_name,
+                    version
+                ));
+            }
+        }
+        split.canary_header = split.canary_header.map(|header| header.to_ascii_lowercase());
+        self.splits
+            .write()
+            .expect("routing lock poisoned")
+            .insert(agent_name.to_string(), split.clone());
+        Ok(split)
+    }
+
+    /// Remove an agent's split, returning traffic to its default version
+    pub fn clear_split(&self, agent_name: &str) -> Option<TrafficSplit> {
+        self.splits.write().expect("routing lock poisoned").remove(agent_name)
+    }
+
+    pub fn split(&self, agent_name: &str) -> Option<TrafficSplit> {
+        self.splits.read().expect("routing lock poisoned").get(agent_name).cloned()
+    }
+
+    /// Resolve the version serving a request.
+    ///
+    /// An explicit version (query parameter or `x-agent-version` header) wins,
+    /// then the agent's traffic split; `None` means the registry default.
+    pub fn select_version(
+        &self,
+        agent_name: &str,
+        pinned: Option<&str>,
+        headers: &HeaderMap,
+        routing_key: &str,
+    ) -> Option<String> {
+        if let Some(version) = pinned {
+            return Some(version.to_string());
+        }
+        if let Some(version) = headers.get(AGENT_VERSION_HEADER).and_then(|v| v.to_str().ok()) {
+            return Some(version.to_string());
+        }
+        let splits = self.splits.read().expect("routing lock poisoned");
+        splits
+            .get(agent_name)
+            .map(|split| split.select(headers, routing_key).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_is_sticky_and_respects_overrides() {
+        let split = TrafficSplit::new("v1", "v2", 20).with_canary_header("X-Canary");
+        let headers = HeaderMap::new();
+
+        let canary = (0..1000)
+            .filter(|i| split.select(&headers, &format!("session-{}", i)) == "v2")
+            .count();
+        assert!((150..250).contains(&canary), "canary share was {}", canary);
+        assert_eq!(split.select(&headers, "session-7"), split.select(&headers, "session-7"));
+
+        let mut forced = HeaderMap::new();
+        forced.insert("x-canary", "1".parse().unwrap());
+        assert!((0..50).all(|i| split.select(&forced, &i.to_string()) == "v2"));
+
+        let policy = RoutingPolicy::new();
+        policy
+            .splits
+            .write()
+            .unwrap()
+            .insert("helper".to_string(), TrafficSplit::new("v1", "v2", 0));
+        assert_eq!(policy.select_version("helper", None, &headers, "s").as_deref(), Some("v1"));
+        assert_eq!(policy.select_version("helper", Some("v3"), &headers, "s").as_deref(), Some("v3"));
+
+        let mut pinned = HeaderMap::new();
+        pinned.insert(AGENT_VERSION_HEADER, "v2".parse().unwrap());
+        assert_eq!(policy.select_version("helper", None, &pinned, "s").as_deref(), Some("v2"));
+        assert_eq!(policy.select_version("other", None, &headers, "s"), None);
+    }
+}
@@ -0,0 +1,196 @@
+//! Structured results of agent runs
+//!
+//! A [`StructuredResponse`] gathers what a run produced from its events —
+//! ordered answer parts, the tool calls made along the way, token usage and
+//! the model's finish reason — so API clients can render rich answers
+//! without re-parsing the event list.
+
+use crate::{
+    agents::FINISH_REASON_METADATA_KEY,
+    events::{Citations, Event},
+    models::{FinishReason, Usage},
+    types::{CodeExecutionOutcome, ContentPart},
+    utils::InvocationTrace,
+};
+use bytes::Bytes;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// One piece of an answer, in the order the agents produced them
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponsePart {
+    /// Answer text; carries `[n]` markers when followed by citations
+    Text { text: String },
+    /// Media or a file stored elsewhere, e.g. an artifact
+    Artifact { uri: String, mime_type: String },
+    /// Media or a file sent inline
+    Media {
+        mime_type: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+        #[serde(with = "crate::utils::base64_bytes")]
+        data: Bytes,
+    },
+    /// Code generated by the model for built-in code execution
+    Code { language: String, code: String },
+    /// Result of running the preceding code
+    CodeResult { outcome: CodeExecutionOutcome, output: String },
+    /// Sources grounding the preceding text
+    Citations { citations: Citations },
+}
+
+/// A tool call executed during the run
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCallRecord {
+    /// Agent whose model called the tool
+    pub agent: String,
+    pub name: String,
+    pub args: Value,
+
+    /// Unset if the call did not complete, e.g. it failed or was disabled
+    pub response: Option<Value>,
+}
+
+/// Structured result of a run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StructuredResponse {
+    /// Text of the final answer, with citation markers
+    pub text: String,
+    pub parts: Vec<ResponsePart>,
+    pub tool_calls: Vec<ToolCallRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<FinishReason>,
+}
+
+impl StructuredResponse {
+    /// Collect the complete agent events of a run; usage comes from the invocation's trace
+    pub fn from_events(events: &[Arc<Event>], trace: Option<&InvocationTrace>) -> Self {
+        let mut response = Self {
+            usage: trace.and_then(InvocationTrace::usage),
+            ..Self::default()
+        };
+        for event in events.iter().filter(|event| event.author != "user" && !event.is_partial) {
+            if let Some(reason) = event.metadata.get(FINISH_REASON_METADATA_KEY) {
+                response.finish_reason = serde_json::from_value(reason.clone()).ok();
+            }
+            response.add_event(event);
+        }
+        response.text = events
+            .iter()
+            .rev()
+            .find_map(|event| event.get_cited_text().filter(|text| !text.is_empty()))
+            .unwrap_or_default();
+        response
+    }
+
+    fn add_event(&mut self, event: &Event) {
+        let Some(content) = &event.content else {
+            return;
+        };
+        let text = event.get_cited_text().unwrap_or_default();
+        if !text.is_empty() {
+            self.parts.push(ResponsePart::Text { text });
+        }
+        for part in &content.parts {
+            match part {
+                ContentPart::Text { .. } => {}
+                ContentPart::FunctionCall { name, args } => self.tool_calls.push(ToolCallRecord {
+                    agent: event.author.clone(),
+                    name: name.clone(),
+                    args: args.clone(),
+                    response: None,
+                }),
+                ContentPart::FunctionResponse { name, response } => {
+                    if let Some(call) = self
+                        .tool_calls
+                        .iter_mut()
+                        .rev()
+                        .find(|call| &call.name == name && call.response.is_none())
+                    {
+                        call.response = Some(response.clone());
+                    }
+                }
+                ContentPart::FileRef { uri, mime_type } => self.parts.push(ResponsePart::Artifact {
+                    uri: uri.clone(),
+                    mime_type: mime_type.clone(),
+                }),
+                ContentPart::File { data, mime_type, filename } => self.parts.push(ResponsePart::Media {
+                    mime_type: mime_type.clone(),
+                    filename: Some(filename.clone()),
+                    data: data.clone(),
+                }),
+                ContentPart::Image { data, mime_type }
+                | ContentPart::Video { data, mime_type }
+                | ContentPart::Audio { data, mime_type } => self.parts.push(ResponsePart::Media {
+                    mime_type: mime_type.clone(),
+                    filename: None,
+                    data: data.clone(),
+                }),
+                ContentPart::ExecutableCode { language, code } => self.parts.push(ResponsePart::Code {
+                    language: language.clone(),
+                    code: code.clone(),
+                }),
+                ContentPart::CodeExecutionResult { outcome, output } => self.parts.push(ResponsePart::CodeResult {
+                    outcome: *outcome,
+                    output: output.clone(),
+                }),
+            }
+        }
+        if let Some(citations) = &event.citations {
+            self.parts.push(ResponsePart::Citations {
+                citations: citations.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Content, FunctionCall};
+    use serde_json::json;
+
+    #[test]
+    fn test_events_become_ordered_parts_and_tool_calls() {
+        let call = Event::function_call(
+            "helper",
+            FunctionCall {
+                name: "lookup".to_string(),
+                args: json!({ "id": 7 }),
+            },
+        );
+        let result = Event::function_response("helper", "lookup", json!({ "status": "shipped" }));
+        let mut answer = Content::model_text("Here is the chart.");
+        answer.parts.push(ContentPart::ExecutableCode {
+            language: "PYTHON".to_string(),
+            code: "plot()".to_string(),
+        });
+        answer.parts.push(ContentPart::FileRef {
+            uri: "artifact://chart.png".to_string(),
+            mime_type: "image/png".to_string(),
+        });
+        let mut answer = Event::content_response("helper", answer);
+        answer.metadata.insert(FINISH_REASON_METADATA_KEY.to_string(), json!("STOP"));
+        let events: Vec<_> = [Event::user_input("Chart my order", uuid::Uuid::nil()), call, result, answer]
+            .into_iter()
+            .map(Arc::new)
+            .collect();
+
+        let response = StructuredResponse::from_events(&events, None);
+        assert_eq!(response.text, "Here is the chart.");
+        let kinds: Vec<Value> = response
+            .parts
+            .iter()
+            .map(|part| serde_json::to_value(part).unwrap()["type"].clone())
+            .collect();
+        assert_eq!(kinds, [json!("text"), json!("code"), json!("artifact")]);
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].response, Some(json!({ "status": "shipped" })));
+        assert!(matches!(response.finish_reason, Some(FinishReason::Stop)));
+        assert!(response.usage.is_none());
+    }
+}
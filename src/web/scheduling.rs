@@ -0,0 +1,436 @@
+//! Admission control for agent invocations
+//!
+//! Caps concurrent invocations overall and per agent. Requests over the cap
+//! wait in a bounded queue, interactive before batch, and are rejected with
+//! `503 Service Unavailable` and `Retry-After` once the queue is full or the
+//! wait times out.
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::oneshot;
+
+/// Request header selecting the invocation priority class (`interactive` or `batch`)
+pub const PRIORITY_HEADER: &str = "x-invocation-priority";
+
+/// Priority class of an invocation; interactive requests are admitted first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    #[default]
+    Interactive,
+    Batch,
+}
+
+impl Priority {
+    /// Priority requested via [`PRIORITY_HEADER`]; anything but `batch` is interactive
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        match headers.get(PRIORITY_HEADER).and_then(|value| value.to_str().ok()) {
+            Some(value) if value.trim().eq_ignore_ascii_case("batch") => Self::Batch,
+            _ => Self::Interactive,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::Interactive => 0,
+            Self::Batch => 1,
+        }
+    }
+}
+
+/// Limits applied by the [`InvocationScheduler`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// Maximum invocations running at once across all agents; `None` is unlimited
+    pub max_concurrent: Option<usize>,
+
+    /// Maximum invocations running at once for a single agent; `None` is unlimited
+    pub max_concurrent_per_agent: Option<usize>,
+
+    /// Maximum invocations waiting for a slot; further requests are rejected
+    pub max_queued: usize,
+
+    /// How long a queued invocation waits before it is rejected
+    pub queue_timeout_seconds: u64,
+
+    /// Value of the `Retry-After` header sent with rejections
+    pub retry_after_seconds: u64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: None,
+            max_concurrent_per_agent: None,
+            max_queued: 128,
+            queue_timeout_seconds: 30,
+            retry_after_seconds: 1,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_concurrent(mut self, max: usize) -> Self {
+        self.max_concurrent = Some(max);
+        self
+    }
+
+    pub fn with_max_concurrent_per_agent(mut self, max: usize) -> Self {
+        self.max_concurrent_per_agent = Some(max);
+        self
+    }
+
+    pub fn with_max_queued(mut self, max: usize) -> Self {
+        self.max_queued = max;
+        self
+    }
+
+    pub fn with_queue_timeout(mut self, seconds: u64) -> Self {
+        self.queue_timeout_seconds = seconds;
+        self
+    }
+
+    pub fn with_retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after_seconds = seconds;
+        self
+    }
+}
+
+/// Invocation rejected because the server is at capacity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overloaded {
+    pub retry_after: Duration,
+}
+
+impl IntoResponse for Overloaded {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({
+            "error": "server overloaded",
+            "retry_after_seconds": self.retry_after.as_secs(),
+        }));
+        let mut response = (StatusCode::SERVICE_UNAVAILABLE, body).into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(self.retry_after.as_secs()));
+        response
+    }
+}
+
+/// Point-in-time view of the scheduler for the admin dashboard
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchedulerStats {
+    pub running: usize,
+    pub running_per_agent: HashMap<String, usize>,
+    pub queued_interactive: usize,
+    pub queued_batch: usize,
+    pub rejected: u64,
+}
+
+struct Waiter {
+    agent: String,
+    grant: oneshot::Sender<InvocationPermit>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    running: usize,
+    running_per_agent: HashMap<String, usize>,
+    /// Waiters per priority class, indexed by [`Priority::index`]
+    queues: [VecDeque<Waiter>; 2],
+    rejected: u64,
+}
+
+impl SchedulerState {
+    fn has_capacity(&self, config: &SchedulerConfig, agent: &str) -> bool {
+        let total_ok = config.max_concurrent.is_none_or(|max| self.running < max);
+        let agent_ok = config
+            .max_concurrent_per_agent
+            .is_none_or(|max| self.running_per_agent.get(agent).copied().unwrap_or(0) < max);
+        total_ok && agent_ok
+    }
+
+    fn start(&mut self, agent: &str) {
+        self.running += 1;
+        *self.running_per_agent.entry(agent.to_string()).or_default() += 1;
+    }
+
+    fn finish(&mut self, agent: &str) {
+        self.running = self.running.saturating_sub(1);
+        if let Some(count) = self.running_per_agent.get_mut(agent) {
+            *count -= 1;
+            if *count == 0 {
+                self.running_per_agent.remove(agent);
+            }
+        }
+    }
+
+    /// Drop waiters that gave up (timed out or disconnected)
+    fn prune(&mut self) {
+        for queue in &mut self.queues {
+            queue.retain(|waiter| !waiter.grant.is_closed());
+        }
+    }
+
+    fn queued(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+
+    /// Take slots for waiters, highest priority first, skipping agents at their
+    /// cap; the returned waiters are handed their permits by [`SchedulerInner::grant`]
+    fn dispatch(&mut self, config: &SchedulerConfig) -> Vec<Waiter> {
+        let mut granted = Vec::new();
+        for class in 0..self.queues.len() {
+            let mut index = 0;
+            while index < self.queues[class].len() {
+                if config.max_concurrent.is_some_and(|max| self.running >= max) {
+                    return granted;
+                }
+                if !self.has_capacity(config, &self.queues[class][index].agent) {
+                    index += 1;
+                    continue;
+                }
+                let waiter = self.queues[class].remove(index).expect("index in bounds");
+                // A waiter that already gave up simply does not take the slot
+                if !waiter.grant.is_closed() {
+                    self.start(&waiter.agent);
+                    granted.push(waiter);
+                }
+            }
+        }
+        granted
+    }
+}
+
+struct SchedulerInner {
+    config: SchedulerConfig,
+    state: Mutex<SchedulerState>,
+}
+
+impl SchedulerInner {
+    /// Send waiters the permits for the slots taken for them, outside the lock.
+    /// A permit its waiter can no longer receive, or that is left unreceived in
+    /// a dropped channel, releases the slot when it is dropped.
+    fn grant(self: &Arc<Self>, waiters: Vec<Waiter>) {
+        for waiter in waiters {
+            let permit = InvocationPermit {
+                inner: self.clone(),
+                agent: waiter.agent,
+            };
+            let _ = waiter.grant.send(permit);
+        }
+    }
+}
+
+/// Bounded, prioritized admission of agent invocations
+#[derive(Clone)]
+pub struct InvocationScheduler {
+    inner: Arc<SchedulerInner>,
+}
+
+impl InvocationScheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            inner: Arc::new(SchedulerInner {
+                config,
+                state: Mutex::new(SchedulerState::default()),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.inner.config
+    }
+
+    /// Wait for a slot to run `agent`; the slot is released when the permit is dropped
+    pub async fn acquire(&self, agent: &str, priority: Priority) -> Result<InvocationPermit, Overloaded> {
+        let config = &self.inner.config;
+        let mut receiver = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.prune();
+            if state.has_capacity(config, agent) {
+                state.start(agent);
+                return Ok(self.permit(agent));
+            }
+            if state.queued() >= config.max_queued {
+                state.rejected += 1;
+                return Err(self.overloaded());
+            }
+            let (grant, receiver) = oneshot::channel();
+            state.queues[priority.index()].push_back(Waiter {
+                agent: agent.to_string(),
+                grant,
+            });
+            receiver
+        };
+
+        let timeout = Duration::from_secs(config.queue_timeout_seconds);
+        if let Ok(Ok(permit)) = tokio::time::timeout(timeout, &mut receiver).await {
+            return Ok(permit);
+        }
+        // A permit sent before the channel is closed is ours; one sent after is
+        // returned to the sender and dropped, releasing its slot
+        receiver.close();
+        if let Ok(permit) = receiver.try_recv() {
+            return Ok(permit);
+        }
+        let mut state = self.inner.state.lock().unwrap();
+        state.rejected += 1;
+        Err(self.overloaded())
+    }
+
+    pub fn stats(&self) -> SchedulerStats {
+        let mut state = self.inner.state.lock().unwrap();
+        state.prune();
+        SchedulerStats {
+            running: state.running,
+            running_per_agent: state.running_per_agent.clone(),
+            queued_interactive: state.queues[Priority::Interactive.index()].len(),
+            queued_batch: state.queues[Priority::Batch.index()].len(),
+            rejected: state.rejected,
+        }
+    }
+
+    fn permit(&self, agent: &str) -> InvocationPermit {
+        InvocationPermit {
+            inner: self.inner.clone(),
+            agent: agent.to_string(),
+        }
+    }
+
+    fn overloaded(&self) -> Overloaded {
+        Overloaded {
+            retry_after: Duration::from_secs(self.inner.config.retry_after_seconds),
+        }
+    }
+}
+
+impl Default for InvocationScheduler {
+    fn default() -> Self {
+        Self::new(SchedulerConfig::default())
+    }
+}
+
+/// Slot held by a running invocation
+pub struct InvocationPermit {
+    inner: Arc<SchedulerInner>,
+    agent: String,
+}
+
+impl std::fmt::Debug for InvocationPermit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InvocationPermit").field("agent", &self.agent).finish()
+    }
+}
+
+impl Drop for InvocationPermit {
+    fn drop(&mut self) {
+        let granted = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.finish(&self.agent);
+            state.dispatch(&self.inner.config)
+        };
+        self.inner.grant(granted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_queue_prefers_interactive_and_rejects_when_full() {
+        let scheduler = InvocationScheduler::new(
+            SchedulerConfig::new()
+                .with_max_concurrent(1)
+                .with_max_queued(2)
+                .with_queue_timeout(5)
+                .with_retry_after(7),
+        );
+        let running = scheduler.acquire("agent", Priority::Interactive).await.unwrap();
+
+        let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut waiters = Vec::new();
+        for priority in [Priority::Batch, Priority::Interactive] {
+            let (scheduler, order_tx) = (scheduler.clone(), order_tx.clone());
+            waiters.push(tokio::spawn(async move {
+                let permit = scheduler.acquire("agent", priority).await.unwrap();
+                order_tx.send(priority).unwrap();
+                drop(permit);
+            }));
+            tokio::task::yield_now().await;
+        }
+
+        let stats = scheduler.stats();
+        assert_eq!((stats.queued_interactive, stats.queued_batch), (1, 1));
+        let rejected = scheduler.acquire("agent", Priority::Interactive).await.unwrap_err();
+        assert_eq!(rejected.retry_after, Duration::from_secs(7));
+        let response = rejected.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+
+        drop(running);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+        assert_eq!(order_rx.recv().await, Some(Priority::Interactive));
+        assert_eq!(order_rx.recv().await, Some(Priority::Batch));
+        assert_eq!(scheduler.stats().running, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_releases_its_granted_slot() {
+        let scheduler = InvocationScheduler::new(
+            SchedulerConfig::new()
+                .with_max_concurrent(1)
+                .with_queue_timeout(5),
+        );
+        let running = scheduler.acquire("agent", Priority::Interactive).await.unwrap();
+        let waiter = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.acquire("agent", Priority::Interactive).await })
+        };
+        tokio::task::yield_now().await;
+        assert_eq!(scheduler.stats().queued_interactive, 1);
+
+        // The slot is granted to the waiter, which is cancelled before it runs again
+        drop(running);
+        assert_eq!(scheduler.stats().running, 1);
+        waiter.abort();
+        assert!(waiter.await.unwrap_err().is_cancelled());
+
+        assert_eq!(scheduler.stats().running, 0);
+        let permit = tokio::time::timeout(Duration::from_millis(100), scheduler.acquire("agent", Priority::Batch)).await;
+        assert!(permit.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_per_agent_cap_does_not_block_other_agents() {
+        let scheduler = InvocationScheduler::new(
+            SchedulerConfig::new()
+                .with_max_concurrent_per_agent(1)
+                .with_max_queued(0),
+        );
+        let _busy = scheduler.acquire("busy", Priority::Interactive).await.unwrap();
+        assert!(scheduler.acquire("busy", Priority::Batch).await.is_err());
+        assert!(scheduler.acquire("idle", Priority::Batch).await.is_ok());
+        assert_eq!(scheduler.stats().rejected, 1);
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(Priority::from_headers(&headers), Priority::Interactive);
+        headers.insert(PRIORITY_HEADER, HeaderValue::from_static("Batch"));
+        assert_eq!(Priority::from_headers(&headers), Priority::Batch);
+    }
+}
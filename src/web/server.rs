@@ -1,17 +1,21 @@
 //! Web server implementation with HTTP API and WebSocket support
 
 use crate::{
-    agents::BaseAgent,
+    agents::{global_debugger, AgentRegistry, BaseAgent, Debugger},
+    artifacts::{ArtifactCleanupJob, BaseArtifactService, InMemoryArtifactService},
+    analytics::{AnalyticsConfig, AnalyticsJob, AnalyticsStore, InMemoryAnalyticsStore},
     error::Result,
+    events::{EventBus, RedactionVault, WebhookDispatcher},
+    models::{create_model, global_live_pool, LivePoolConfig},
+    retention::{DataEraser, DeletionAuditLog, InMemoryDeletionAuditLog, RetentionConfig, RetentionJob},
     runners::Runner,
     sessions::{SessionService, InMemorySessionService},
-    web::{handlers, middleware, WebSocketHandler},
+    types::Timestamp,
+    utils::{global_trace_store, global_usage_tracker, DeadLetterQueue, Determinism, TraceStore, UsageTracker},
+    web::{artifacts, auth, handlers, listener::{self, BoundListener}, middleware, CorsConfig, IdempotencyStore, InvocationScheduler, ListenMode, DEFAULT_IDEMPOTENCY_MAX_ENTRIES, DEFAULT_IDEMPOTENCY_TTL, DEFAULT_STREAM_BUFFER_CAPACITY, RoutingPolicy, SchedulerConfig, TlsConfig, TrafficSplit, WebSocketHandler},
 };
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::Json,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use serde::{Deserialize, Serialize};
@@ -19,10 +23,14 @@ use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 use tokio::net::TcpListener;
 use tower::ServiceBuilder;
 use tower_http::{
-    cors::{Any, CorsLayer},
+    compression::{
+        predicate::{NotForContentType, Predicate, SizeAbove},
+        CompressionLayer,
+    },
     trace::TraceLayer,
     timeout::TimeoutLayer,
 };
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use tracing::{info, warn};
 
 /// Web server configuration
@@ -34,8 +42,9 @@ pub struct ServerConfig {
     /// Port to bind to
     pub port: u16,
     
-    /// CORS allowed origins
-    pub cors_origins: Vec<String>,
+    /// CORS policies, validated when the server starts
+    #[serde(default)]
+    pub cors: CorsConfig,
     
     /// Request timeout in seconds
     pub timeout_seconds: u64,
@@ -51,6 +60,110 @@ pub struct ServerConfig {
     
     /// Static file serving directory
     pub static_dir: Option<String>,
+
+    /// Interval between server-initiated heartbeats (WS ping / SSE comment); 0 disables
+    #[serde(default = "default_heartbeat_interval_seconds")]
+    pub heartbeat_interval_seconds: u64,
+
+    /// Close WS/SSE connections with no traffic for this long; 0 disables
+    #[serde(default = "default_idle_timeout_seconds")]
+    pub idle_timeout_seconds: u64,
+
+    /// Events buffered per streaming client before deltas are coalesced or the agent is paused
+    #[serde(default = "default_stream_buffer_capacity")]
+    pub stream_buffer_capacity: usize,
+
+    /// Compress responses (gzip/brotli) when the client accepts it; SSE streams are never compressed
+    #[serde(default = "default_compression")]
+    pub compression: bool,
+
+    /// How long results of requests with an `Idempotency-Key` are kept for retries
+    #[serde(default = "default_idempotency_ttl_seconds")]
+    pub idempotency_ttl_seconds: u64,
+
+    /// Most results of requests with an `Idempotency-Key` kept; the oldest are evicted first
+    #[serde(default = "default_idempotency_max_entries")]
+    pub idempotency_max_entries: usize,
+
+    /// Responses smaller than this are sent uncompressed
+    #[serde(default = "default_compression_min_bytes")]
+    pub compression_min_bytes: u16,
+
+    /// Socket to accept connections on; `host`/`port` apply to TCP only
+    #[serde(default)]
+    pub listen: ListenMode,
+
+    /// Serve HTTPS/WSS directly with this certificate (requires the `tls` feature)
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+
+    /// Concurrency limits and queueing for agent invocations
+    #[serde(default)]
+    pub scheduling: SchedulerConfig,
+
+    /// Periodic aggregation of stored sessions into conversation metrics
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+
+    /// Interval between deletions of expired artifact versions; 0 disables them
+    #[serde(default = "default_artifact_cleanup_interval_seconds")]
+    pub artifact_cleanup_interval_seconds: u64,
+
+    /// How long each app keeps sessions and artifacts
+    #[serde(default)]
+    pub retention: RetentionConfig,
+
+    /// Live connections kept open ahead of new live sessions
+    #[serde(default)]
+    pub live_pool: LivePoolConfig,
+
+    /// Never use the session store for runs: callers supply state and history
+    /// with each `/run` request and keep the returned deltas
+    #[serde(default)]
+    pub stateless: bool,
+
+    /// Bearer token for admin endpoints, which change how agents run or read
+    /// or erase users' data; those endpoints are disabled without any token.
+    /// Its principal is `admin`.
+    #[serde(default)]
+    pub admin_token: Option<String>,
+
+    /// Further admin bearer tokens keyed by the principal they authenticate,
+    /// recorded as the actor of audited operations
+    #[serde(default)]
+    pub admin_tokens: HashMap<String, String>,
+}
+
+fn default_heartbeat_interval_seconds() -> u64 {
+    15
+}
+
+fn default_idle_timeout_seconds() -> u64 {
+    300
+}
+
+fn default_compression() -> bool {
+    true
+}
+
+fn default_compression_min_bytes() -> u16 {
+    1024
+}
+
+fn default_idempotency_ttl_seconds() -> u64 {
+    DEFAULT_IDEMPOTENCY_TTL.as_secs()
+}
+
+fn default_idempotency_max_entries() -> usize {
+    DEFAULT_IDEMPOTENCY_MAX_ENTRIES
+}
+
+fn default_artifact_cleanup_interval_seconds() -> u64 {
+    3600
+}
+
+fn default_stream_buffer_capacity() -> usize {
+    DEFAULT_STREAM_BUFFER_CAPACITY
 }
 
 impl Default for ServerConfig {
@@ -58,12 +171,29 @@ impl Default for ServerConfig {
         Self {
             host: "127.0.0.1".to_string(),
             port: 8000,
-            cors_origins: vec!["*".to_string()],
+            cors: CorsConfig::default(),
             timeout_seconds: 30,
             max_body_size: 16 * 1024 * 1024, // 16MB
             enable_websockets: true,
             enable_docs: true,
             static_dir: None,
+            heartbeat_interval_seconds: default_heartbeat_interval_seconds(),
+            idle_timeout_seconds: default_idle_timeout_seconds(),
+            stream_buffer_capacity: default_stream_buffer_capacity(),
+            compression: default_compression(),
+            idempotency_ttl_seconds: default_idempotency_ttl_seconds(),
+            idempotency_max_entries: default_idempotency_max_entries(),
+            compression_min_bytes: default_compression_min_bytes(),
+            listen: ListenMode::Tcp,
+            tls: None,
+            scheduling: SchedulerConfig::default(),
+            analytics: AnalyticsConfig::default(),
+            artifact_cleanup_interval_seconds: default_artifact_cleanup_interval_seconds(),
+            retention: RetentionConfig::default(),
+            live_pool: LivePoolConfig::default(),
+            stateless: false,
+            admin_token: None,
+            admin_tokens: HashMap::new(),
         }
     }
 }
@@ -83,8 +213,14 @@ impl ServerConfig {
         self
     }
 
+    /// Allow the given origins on every route; `*` allows any origin
     pub fn with_cors_origins(mut self, origins: Vec<String>) -> Self {
-        self.cors_origins = origins;
+        self.cors.default.allowed_origins = origins;
+        self
+    }
+
+    pub fn with_cors(mut self, cors: CorsConfig) -> Self {
+        self.cors = cors;
         self
     }
 
@@ -108,6 +244,102 @@ impl ServerConfig {
         self
     }
 
+    pub fn with_heartbeat_interval(mut self, seconds: u64) -> Self {
+        self.heartbeat_interval_seconds = seconds;
+        self
+    }
+
+    pub fn with_idle_timeout(mut self, seconds: u64) -> Self {
+        self.idle_timeout_seconds = seconds;
+        self
+    }
+
+    pub fn with_stream_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.stream_buffer_capacity = capacity;
+        self
+    }
+
+    pub fn with_idempotency_ttl(mut self, ttl: Duration) -> Self {
+        self.idempotency_ttl_seconds = ttl.as_secs();
+        self
+    }
+
+    pub fn with_idempotency_max_entries(mut self, max_entries: usize) -> Self {
+        self.idempotency_max_entries = max_entries;
+        self
+    }
+
+    pub fn with_compression_min_bytes(mut self, bytes: u16) -> Self {
+        self.compression_min_bytes = bytes;
+        self
+    }
+
+    pub fn disable_compression(mut self) -> Self {
+        self.compression = false;
+        self
+    }
+
+    /// Listen on a Unix domain socket instead of TCP
+    pub fn with_unix_socket(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.listen = ListenMode::Unix { path: path.into() };
+        self
+    }
+
+    /// Accept connections on the socket passed by systemd socket activation;
+    /// call [`capture_systemd_activation`](crate::web::capture_systemd_activation) at startup
+    pub fn with_systemd_socket(mut self) -> Self {
+        self.listen = ListenMode::Systemd;
+        self
+    }
+
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub fn with_scheduling(mut self, scheduling: SchedulerConfig) -> Self {
+        self.scheduling = scheduling;
+        self
+    }
+
+    pub fn with_retention(mut self, retention: RetentionConfig) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn with_live_pool(mut self, live_pool: LivePoolConfig) -> Self {
+        self.live_pool = live_pool;
+        self
+    }
+
+    /// Enable the admin endpoints for bearers of `token`, as principal `admin`
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    /// Enable the admin endpoints for bearers of `token`, as principal `principal`
+    pub fn with_named_admin_token(mut self, principal: impl Into<String>, token: impl Into<String>) -> Self {
+        self.admin_tokens.insert(principal.into(), token.into());
+        self
+    }
+
+    /// Run agents without server-side sessions, e.g. on scale-to-zero platforms
+    pub fn stateless(mut self) -> Self {
+        self.stateless = true;
+        self
+    }
+
+    /// Heartbeat interval, or `None` if heartbeats are disabled
+    pub fn heartbeat_interval(&self) -> Option<Duration> {
+        (self.heartbeat_interval_seconds > 0).then(|| Duration::from_secs(self.heartbeat_interval_seconds))
+    }
+
+    /// Idle timeout, or `None` if idle connections are never reaped
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        (self.idle_timeout_seconds > 0).then(|| Duration::from_secs(self.idle_timeout_seconds))
+    }
+
     pub fn socket_addr(&self) -> SocketAddr {
         format!("{}:{}", self.host, self.port)
             .parse()
@@ -115,14 +347,23 @@ impl ServerConfig {
     }
 }
 
+/// How long shutdown waits for background tasks such as WebSocket connections
+pub const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
 /// Server state shared across handlers
 #[derive(Clone)]
 pub struct ServerState {
-    /// Available agents
-    pub agents: Arc<HashMap<String, Arc<dyn BaseAgent>>>,
+    /// Available agents and their versions
+    pub agents: AgentRegistry,
+
+    /// Traffic splits between agent versions
+    pub routing: RoutingPolicy,
     
     /// Session service
     pub session_service: Arc<dyn SessionService>,
+
+    /// Artifact storage served by the artifact endpoints
+    pub artifact_service: Arc<dyn BaseArtifactService>,
     
     /// Active runners
     pub runners: Arc<tokio::sync::RwLock<HashMap<String, Arc<Runner>>>>,
@@ -132,24 +373,119 @@ pub struct ServerState {
     
     /// WebSocket handler
     pub websocket_handler: Arc<WebSocketHandler>,
+
+    /// Usage tracker backing the admin statistics
+    pub usage_tracker: UsageTracker,
+
+    /// Background tasks (WebSocket connections, webhook deliveries) awaited on shutdown
+    pub tasks: TaskTracker,
+
+    /// Cancelled when the server starts shutting down
+    pub shutdown: CancellationToken,
+
+    /// Admission control for agent invocations
+    pub scheduler: InvocationScheduler,
+
+    /// Every event streamed by the server's agents is published here
+    pub event_bus: EventBus,
+
+    /// Failed background work, retried while the server runs
+    pub dead_letters: Arc<DeadLetterQueue>,
+
+    /// Stored results of run requests carrying an `Idempotency-Key`
+    pub idempotency: IdempotencyStore,
+
+    /// Traces of recent invocations
+    pub traces: TraceStore,
+
+    /// Steps paused at debug breakpoints
+    pub debugger: Debugger,
+
+    /// Per-agent webhooks notified of session and invocation lifecycle events
+    pub webhooks: WebhookDispatcher,
+
+    /// Latest conversation metrics computed by the analytics job
+    pub analytics: Arc<dyn AnalyticsStore>,
+
+    /// Records of user data erasures and retention deletions
+    pub deletion_audit: Arc<dyn DeletionAuditLog>,
+
+    /// Originals of redacted events; discarded when unset
+    pub redaction_vault: Option<Arc<dyn RedactionVault>>,
+
+    /// Clock and ID source for session IDs, connections and runs; the process-wide ones when unset
+    pub determinism: Option<Determinism>,
 }
 
 impl ServerState {
     pub fn new(config: ServerConfig) -> Self {
         let session_service: Arc<dyn SessionService> = Arc::new(InMemorySessionService::new());
         let websocket_handler = Arc::new(WebSocketHandler::new());
+        let scheduler = InvocationScheduler::new(config.scheduling.clone());
+        let idempotency = IdempotencyStore::new(Duration::from_secs(config.idempotency_ttl_seconds))
+            .with_max_entries(config.idempotency_max_entries);
+        let dead_letters = Arc::new(DeadLetterQueue::in_memory(Default::default()));
+        let tasks = TaskTracker::new();
+        let webhooks = WebhookDispatcher::new()
+            .with_dead_letter_queue(dead_letters.clone())
+            .with_task_tracker(tasks.clone());
         
         Self {
-            agents: Arc::new(HashMap::new()),
+            agents: AgentRegistry::new(),
+            routing: RoutingPolicy::new(),
             session_service,
+            artifact_service: Arc::new(InMemoryArtifactService::new()),
             runners: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
             config,
             websocket_handler,
+            usage_tracker: global_usage_tracker().clone(),
+            tasks,
+            shutdown: CancellationToken::new(),
+            scheduler,
+            event_bus: EventBus::new(),
+            dead_letters,
+            idempotency,
+            traces: global_trace_store().clone(),
+            debugger: global_debugger().clone(),
+            webhooks,
+            analytics: Arc::new(InMemoryAnalyticsStore::new()),
+            deletion_audit: Arc::new(InMemoryDeletionAuditLog::new()),
+            redaction_vault: None,
+            determinism: None,
+        }
+    }
+
+    /// Current time from the server's clock
+    pub fn now(&self) -> Timestamp {
+        match &self.determinism {
+            Some(determinism) => determinism.clock.now(),
+            None => crate::types::now(),
+        }
+    }
+
+    /// New ID from the server's ID source
+    pub fn new_id(&self) -> String {
+        match &self.determinism {
+            Some(determinism) => determinism.ids.new_uuid().to_string(),
+            None => crate::utils::new_id(),
+        }
+    }
+
+    /// Deletes data from this server's session and artifact services
+    pub fn data_eraser(&self) -> DataEraser {
+        DataEraser::new(self.session_service.clone(), self.deletion_audit.clone())
+            .with_artifact_service(self.artifact_service.clone())
+    }
+
+    pub fn with_agents(self, agents: HashMap<String, Arc<dyn BaseAgent>>) -> Self {
+        for (name, agent) in agents {
+            self.agents.register(name, agent);
         }
+        self
     }
 
-    pub fn with_agents(mut self, agents: HashMap<String, Arc<dyn BaseAgent>>) -> Self {
-        self.agents = Arc::new(agents);
+    pub fn with_registry(mut self, registry: AgentRegistry) -> Self {
+        self.agents = registry;
         self
     }
 
@@ -157,6 +493,11 @@ impl ServerState {
         self.session_service = service;
         self
     }
+
+    pub fn with_usage_tracker(mut self, tracker: UsageTracker) -> Self {
+        self.usage_tracker = tracker;
+        self
+    }
 }
 
 /// Web server for the ADK
@@ -173,11 +514,9 @@ impl WebServer {
         Self { config, state }
     }
 
-    /// Add an agent to the server
-    pub fn add_agent(mut self, name: impl Into<String>, agent: Arc<dyn BaseAgent>) -> Self {
-        let mut agents = (*self.state.agents).clone();
-        agents.insert(name.into(), agent);
-        self.state.agents = Arc::new(agents);
+    /// Add an agent to the server; agents with distinct versions may share a name
+    pub fn add_agent(self, name: impl Into<String>, agent: Arc<dyn BaseAgent>) -> Self {
+        self.state.agents.register(name, agent);
         self
     }
 
@@ -187,35 +526,150 @@ impl WebServer {
         self
     }
 
-    /// Build the router with all routes
-    fn build_router(&self) -> Router {
+    /// Set artifact service; its policies bound upload sizes and lifetimes
+    pub fn with_artifact_service(mut self, service: Arc<dyn BaseArtifactService>) -> Self {
+        self.state.artifact_service = service;
+        self
+    }
+
+    /// Use a (typically persisted) dead-letter queue instead of the in-memory default
+    pub fn with_dead_letter_queue(mut self, queue: Arc<DeadLetterQueue>) -> Self {
+        self.state.webhooks = self.state.webhooks.clone().with_dead_letter_queue(queue.clone());
+        self.state.dead_letters = queue;
+        self
+    }
+
+    /// Persist conversation metrics to `store` (e.g. a SQLite table) instead of memory
+    pub fn with_analytics_store(mut self, store: Arc<dyn AnalyticsStore>) -> Self {
+        self.state.analytics = store;
+        self
+    }
+
+    /// Audit deletions to `log` (e.g. a file) instead of memory
+    pub fn with_deletion_audit_log(mut self, log: Arc<dyn DeletionAuditLog>) -> Self {
+        self.state.deletion_audit = log;
+        self
+    }
+
+    /// Keep the originals of redacted events in `vault`, readable by admins holding the `admin_token`
+    pub fn with_redaction_vault(mut self, vault: Arc<dyn RedactionVault>) -> Self {
+        self.state.redaction_vault = Some(vault);
+        self
+    }
+
+    /// Take session IDs, connection IDs and run timestamps from this clock and generator
+    pub fn with_determinism(mut self, determinism: Determinism) -> Self {
+        self.state.determinism = Some(determinism);
+        self
+    }
+
+    /// Dead-letter queue background tasks report failed work to
+    pub fn dead_letter_queue(&self) -> Arc<DeadLetterQueue> {
+        self.state.dead_letters.clone()
+    }
+
+    /// Token that stops the server when cancelled; also cancelled by the shutdown signal
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.state.shutdown.clone()
+    }
+
+    /// Bus the server publishes agent events to; subscribe before starting the server
+    pub fn event_bus(&self) -> EventBus {
+        self.state.event_bus.clone()
+    }
+
+    /// Webhooks notified of the lifecycle events of each agent's conversations
+    pub fn webhooks(&self) -> WebhookDispatcher {
+        self.state.webhooks.clone()
+    }
+
+    /// Split an agent's traffic between two registered versions
+    pub fn with_traffic_split(self, agent_name: &str, split: TrafficSplit) -> Result<Self> {
+        self.state.routing.set_split(&self.state.agents, agent_name, split)?;
+        Ok(self)
+    }
+
+    /// Build the router with all routes; fails if the CORS configuration is invalid
+    fn build_router(&self) -> Result<Router> {
         let mut router = Router::new()
             // Health check
             .route("/health", get(handlers::health_check))
-            .route("/", get(handlers::root))
-            
+            .route("/", get(handlers::root));
+
+        let api = Router::new()
             // Agent management
             .route("/api/agents", get(handlers::list_agents))
             .route("/api/agents/:agent_name", get(handlers::get_agent))
+            .route("/api/agents/:agent_name/capabilities", get(handlers::get_agent_capabilities))
+            .route("/api/agents/:agent_name/traffic", get(handlers::get_traffic_split))
             
             // Agent execution
             .route("/api/agents/:agent_name/run", post(handlers::run_agent))
             .route("/api/agents/:agent_name/stream", post(handlers::stream_agent))
+            .route("/api/agents/:agent_name/estimate", post(handlers::estimate_prompt))
             
             // Session management
             .route("/api/sessions", get(handlers::list_sessions))
             .route("/api/sessions/:session_id", get(handlers::get_session))
             .route("/api/sessions/:session_id", post(handlers::update_session))
+            .route("/api/sessions/:session_id/tags", put(handlers::set_session_tags))
             .route("/api/sessions/:session_id/events", get(handlers::get_session_events))
-            
+            .route(
+                "/api/sessions/:session_id/invocations/:invocation_id/trace",
+                get(handlers::get_invocation_trace),
+            )
+            .route(
+                "/api/sessions/:session_id/events/:event_id/feedback",
+                post(handlers::record_event_feedback),
+            )
+
+            // Artifacts
+            .route(
+                "/api/artifacts/:app_name/:user_id/:session_id/:filename",
+                get(artifacts::download_artifact).put(artifacts::upload_artifact),
+            )
+
+            // Feedback
+            .route("/api/feedback/summary", get(handlers::feedback_summary))
+
             // Model information
             .route("/api/models", get(handlers::list_models))
             .route("/api/models/:model_name", get(handlers::get_model_info));
 
-        // Add WebSocket support if enabled
-        if self.config.enable_websockets {
-            router = router.route("/ws/:agent_name", get(handlers::websocket_handler));
-        }
+        // Admin endpoints, behind the admin token
+        let admin = Router::new()
+            .route("/api/admin/stats", get(handlers::admin_stats))
+            .route("/api/admin/analytics", get(handlers::conversation_analytics))
+            .route("/api/admin/dead-letters", get(handlers::list_dead_letters))
+            .route("/api/admin/dead-letters/:id", delete(handlers::delete_dead_letter))
+            .route("/api/admin/dead-letters/:id/replay", post(handlers::replay_dead_letter))
+            .route("/api/admin/deletions", get(handlers::list_deletions))
+            .route(
+                "/api/admin/sessions/:session_id/events/:event_id/original",
+                get(handlers::get_original_event),
+            )
+            .route("/api/users/:user_id/data", delete(handlers::delete_user_data))
+            // Traffic splits decide which agent version serves users
+            .route(
+                "/api/agents/:agent_name/traffic",
+                put(handlers::set_traffic_split).delete(handlers::clear_traffic_split),
+            )
+            // Webhooks receive users' conversation events at any URL
+            .route(
+                "/api/agents/:agent_name/webhooks",
+                get(handlers::list_webhooks).post(handlers::register_webhook),
+            )
+            .route("/api/agents/:agent_name/webhooks/:webhook_id", delete(handlers::delete_webhook))
+            // Paused steps carry users' requests and resuming them changes the run
+            .route("/api/debug/paused", get(handlers::list_paused_steps))
+            .route("/api/debug/paused/:pause_id", post(handlers::resume_paused_step))
+            // Handed-over transcripts, and replying or returning them, are for operators
+            .route("/api/handoffs", get(handlers::list_handoffs))
+            .route("/api/handoffs/:session_id", get(handlers::get_handoff))
+            .route("/api/handoffs/:session_id/reply", post(handlers::reply_to_handoff))
+            .route("/api/handoffs/:session_id/return", post(handlers::return_handoff))
+            .route_layer(axum::middleware::from_fn_with_state(self.state.clone(), auth::require_admin));
+        let api = api.merge(admin);
 
         // Add API documentation if enabled
         if self.config.enable_docs {
@@ -230,34 +684,44 @@ impl WebServer {
                 tower_http::services::ServeDir::new(static_dir));
         }
 
-        // Add middleware
-        let cors = if self.config.cors_origins.contains(&"*".to_string()) {
-            CorsLayer::permissive()
-        } else {
-            CorsLayer::new()
-                .allow_origin(self.config.cors_origins.iter().map(|origin| {
-                    origin.parse().unwrap_or_else(|_| "http://localhost:3000".parse().unwrap())
-                }).collect::<Vec<_>>())
-                .allow_methods(Any)
-                .allow_headers(Any)
-        };
+        // Each route group gets its own CORS policy
+        let cors = &self.config.cors;
+        router = router
+            .layer(cors.default.layer()?)
+            .merge(api.layer(cors.api_policy().layer()?));
+
+        // Add WebSocket support if enabled
+        if self.config.enable_websockets {
+            let ws = Router::new()
+                .route("/ws/:agent_name", get(handlers::websocket_handler))
+                .layer(cors.websocket_policy().layer()?);
+            router = router.merge(ws);
+        }
+
+        if self.config.compression {
+            let predicate = SizeAbove::new(self.config.compression_min_bytes)
+                .and(NotForContentType::GRPC)
+                .and(NotForContentType::IMAGES)
+                .and(NotForContentType::SSE);
+            router = router.layer(CompressionLayer::new().gzip(true).br(true).compress_when(predicate));
+        }
 
-        router
+        // Add middleware
+        Ok(router
             .layer(
                 ServiceBuilder::new()
                     .layer(TraceLayer::new_for_http())
                     .layer(TimeoutLayer::new(Duration::from_secs(self.config.timeout_seconds)))
-                    .layer(cors)
                     .layer(middleware::request_id::RequestIdLayer::new())
                     .layer(middleware::logging::LoggingLayer::new())
             )
-            .with_state(self.state.clone())
+            .with_state(self.state.clone()))
     }
 
     /// Start the web server
     pub async fn start(self) -> Result<()> {
         let addr = self.config.socket_addr();
-        let router = self.build_router();
+        let router = self.build_router()?;
 
         info!("Starting web server on {}", addr);
         info!("WebSocket support: {}", self.config.enable_websockets);
@@ -267,16 +731,8 @@ impl WebServer {
             info!("Static files served from: {}", static_dir);
         }
 
-        let listener = TcpListener::bind(addr).await?;
-        
-        info!("🚀 ADK Web Server running on http://{}", addr);
-        info!("📚 API Documentation: http://{}/docs", addr);
-        info!("🔌 WebSocket endpoint: ws://{}/ws/{{agent_name}}", addr);
-
-        axum::serve(listener, router)
-            .await
-            .map_err(|e| crate::adk_error!(NetworkError, "Server error: {}", e))?;
-
+        self.serve(router).await?;
+        self.drain_tasks().await;
         Ok(())
     }
 
@@ -286,28 +742,137 @@ impl WebServer {
         shutdown_signal: impl std::future::Future<Output = ()> + Send + 'static,
     ) -> Result<()> {
         let addr = self.config.socket_addr();
-        let router = self.build_router();
+        let router = self.build_router()?;
 
         info!("Starting web server with graceful shutdown on {}", addr);
 
-        let listener = TcpListener::bind(addr).await?;
-        
-        info!("🚀 ADK Web Server running on http://{}", addr);
-
-        axum::serve(listener, router)
-            .with_graceful_shutdown(shutdown_signal)
-            .await
-            .map_err(|e| crate::adk_error!(NetworkError, "Server error: {}", e))?;
+        let shutdown = self.state.shutdown.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = shutdown_signal => shutdown.cancel(),
+                _ = shutdown.cancelled() => {}
+            }
+        });
 
+        self.serve(router).await?;
+        self.drain_tasks().await;
         info!("Server shut down gracefully");
         Ok(())
     }
+
+    /// Bind the configured listener and accept connections until shutdown
+    async fn serve(&self, router: Router) -> Result<()> {
+        let shutdown = self.state.shutdown.clone();
+
+        if let Some(tls) = &self.config.tls {
+            if self.config.listen != ListenMode::Tcp {
+                return Err(crate::adk_error!(ConfigError, "TLS is only supported on TCP listeners"));
+            }
+            let addr = self.config.socket_addr();
+            let rustls_config = tls.load().await?;
+            self.spawn_workers();
+            announce("https", "wss", &addr.to_string());
+            return super::tls::serve(rustls_config, addr, router, shutdown).await;
+        }
+
+        let listener = match &self.config.listen {
+            ListenMode::Tcp => BoundListener::Tcp(TcpListener::bind(self.config.socket_addr()).await?),
+            ListenMode::Unix { path } => listener::bind_unix(path)?,
+            ListenMode::Systemd => listener::systemd_listener()?,
+        };
+        self.spawn_workers();
+
+        match listener {
+            BoundListener::Tcp(listener) => {
+                announce("http", "ws", &listener.local_addr()?.to_string());
+                axum::serve(listener, router)
+                    .with_graceful_shutdown(shutdown.cancelled_owned())
+                    .await
+                    .map_err(|e| crate::adk_error!(NetworkError, "Server error: {}", e))
+            }
+            #[cfg(unix)]
+            BoundListener::Unix(listener) => {
+                let path = listener
+                    .local_addr()?
+                    .as_pathname()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|| "<unnamed socket>".to_string());
+                info!("🚀 ADK Web Server listening on unix:{}", path);
+                let result = listener::serve_unix(listener, router, shutdown).await;
+                if let ListenMode::Unix { path } = &self.config.listen {
+                    let _ = std::fs::remove_file(path);
+                }
+                result
+            }
+        }
+    }
+
+    /// Start background workers that run until shutdown
+    fn spawn_workers(&self) {
+        let tasks = &self.state.tasks;
+        self.state
+            .dead_letters
+            .clone()
+            .spawn_retry_worker(tasks, self.state.shutdown.child_token());
+        if self.config.analytics.interval_seconds > 0 {
+            let job = AnalyticsJob::new(
+                self.config.analytics.clone(),
+                self.state.session_service.clone(),
+                self.state.analytics.clone(),
+            );
+            Arc::new(job).spawn(tasks, self.state.shutdown.child_token());
+        }
+        if self.config.artifact_cleanup_interval_seconds > 0 {
+            let interval = Duration::from_secs(self.config.artifact_cleanup_interval_seconds);
+            let job = ArtifactCleanupJob::new(self.state.artifact_service.clone(), interval);
+            job.spawn(tasks, self.state.shutdown.child_token());
+        }
+        if self.config.retention.interval_seconds > 0 {
+            let job = RetentionJob::new(self.state.data_eraser(), self.config.retention.clone());
+            job.spawn(tasks, self.state.shutdown.child_token());
+        }
+        if self.config.live_pool.size > 0 {
+            let pool = global_live_pool();
+            pool.configure(self.config.live_pool.clone());
+            let models = self.config.live_pool.models.clone();
+            tasks.spawn(async move {
+                for name in models {
+                    let warmed = match create_model(&name).await {
+                        Ok(model) => pool.warm(Arc::from(model)).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = warmed {
+                        warn!("Failed to warm live connections to {}: {}", name, e);
+                    }
+                }
+            });
+            tasks.spawn(pool.spawn_maintenance(self.state.shutdown.child_token()));
+        }
+    }
+
+    /// Cancel background tasks, wait for them to finish and flush buffered session writes
+    async fn drain_tasks(&self) {
+        self.state.shutdown.cancel();
+        self.state.tasks.close();
+        if tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, self.state.tasks.wait()).await.is_err() {
+            warn!("{} background tasks still running after shutdown", self.state.tasks.len());
+        }
+        if let Err(e) = self.state.session_service.flush().await {
+            warn!("Failed to flush sessions on shutdown: {}", e);
+        }
+    }
+}
+
+fn announce(http: &str, ws: &str, addr: &str) {
+    info!("🚀 ADK Web Server running on {}://{}", http, addr);
+    info!("📚 API Documentation: {}://{}/docs", http, addr);
+    info!("🔌 WebSocket endpoint: {}://{}/ws/{{agent_name}}", ws, addr);
 }
 
 /// Builder for web server
 pub struct WebServerBuilder {
     config: ServerConfig,
-    agents: HashMap<String, Arc<dyn BaseAgent>>,
+    agents: Vec<(String, Arc<dyn BaseAgent>)>,
     session_service: Option<Arc<dyn SessionService>>,
 }
 
@@ -315,7 +880,7 @@ impl WebServerBuilder {
     pub fn new() -> Self {
         Self {
             config: ServerConfig::default(),
-            agents: HashMap::new(),
+            agents: Vec::new(),
             session_service: None,
         }
     }
@@ -336,7 +901,7 @@ impl WebServerBuilder {
     }
 
     pub fn add_agent(mut self, name: impl Into<String>, agent: Arc<dyn BaseAgent>) -> Self {
-        self.agents.insert(name.into(), agent);
+        self.agents.push((name.into(), agent));
         self
     }
 
@@ -348,7 +913,9 @@ impl WebServerBuilder {
     pub fn build(self) -> WebServer {
         let mut server = WebServer::new(self.config);
         
-        server.state.agents = Arc::new(self.agents);
+        for (name, agent) in self.agents {
+            server.state.agents.register(name, agent);
+        }
         
         if let Some(session_service) = self.session_service {
             server.state.session_service = session_service;
@@ -363,3 +930,308 @@ impl Default for WebServerBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_large_json_responses_are_compressed() {
+        let request = || {
+            Request::builder()
+                .uri("/openapi.json")
+                .header("accept-encoding", "br, gzip")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let config = ServerConfig::default();
+        let compressed = config.clone().with_compression_min_bytes(64);
+        let router = WebServer::new(compressed).build_router().unwrap();
+        let response = router.oneshot(request()).await.unwrap();
+        assert_eq!(response.headers()["content-encoding"], "br");
+
+        let router = WebServer::new(config.disable_compression()).build_router().unwrap();
+        let response = router.oneshot(request()).await.unwrap();
+        assert!(response.headers().get("content-encoding").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_artifact_upload_and_range_download() {
+        let router = WebServer::new(ServerConfig::default()).build_router().unwrap();
+        let uri = "/api/artifacts/app/u1/s1/clip.bin";
+        let upload = Request::put(uri)
+            .header("content-type", "application/octet-stream")
+            .body(Body::from("0123456789"))
+            .unwrap();
+        let response = router.clone().oneshot(upload).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+
+        let download = Request::get(uri).header("range", "bytes=-4").body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(download).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()["content-range"], "bytes 6-9/10");
+        assert_eq!(response.headers()["x-artifact-version"], "1");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"6789");
+
+        let download = Request::get(uri).header("range", "bytes=10-").body(Body::empty()).unwrap();
+        let response = router.oneshot(download).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()["content-range"], "bytes */10");
+    }
+
+    #[tokio::test]
+    async fn test_user_data_deletion_is_audited() {
+        let server = WebServer::new(ServerConfig::default().with_named_admin_token("dpo", "dpo-token"));
+        let sessions = server.state.session_service.clone();
+        sessions.get_or_create_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        let router = server.build_router().unwrap();
+        let uri = "/api/artifacts/app/u1/s1/notes.txt";
+        let upload = Request::put(uri).body(Body::from("notes")).unwrap();
+        router.clone().oneshot(upload).await.unwrap();
+
+        // Erasure is an admin operation, audited under the token's principal
+        let erase = |token: Option<&str>| {
+            let mut request = Request::delete("/api/users/u1/data?requested_by=someone-else");
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let response = router.clone().oneshot(erase(None)).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+        let response = router.clone().oneshot(erase(Some("dpo-token"))).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let record: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!((record["sessions"].as_u64(), record["artifacts"].as_u64()), (Some(1), Some(1)));
+
+        let download = Request::get(uri).body(Body::empty()).unwrap();
+        assert_eq!(router.clone().oneshot(download).await.unwrap().status(), axum::http::StatusCode::NOT_FOUND);
+        assert!(sessions.get_session("app", &"u1".to_string(), &"s1".to_string()).await.unwrap().is_none());
+
+        let audit = Request::get("/api/admin/deletions")
+            .header("authorization", "Bearer dpo-token")
+            .body(Body::empty())
+            .unwrap();
+        let body = axum::body::to_bytes(router.oneshot(audit).await.unwrap().into_body(), usize::MAX).await.unwrap();
+        let records: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["requested_by"], "dpo");
+    }
+
+    #[tokio::test]
+    async fn test_agent_capabilities_endpoint() {
+        use crate::agents::{base_agent::AgentBuilder, LlmAgent};
+
+        let agent = LlmAgent::builder().name("helper").model("gemini-2.0-flash").build().unwrap();
+        let router = WebServer::new(ServerConfig::default())
+            .add_agent("helper", Arc::new(agent))
+            .build_router()
+            .unwrap();
+        let request = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        let response = router.clone().oneshot(request("/api/agents/helper/capabilities")).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let capabilities: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(capabilities["name"], "helper");
+        assert_eq!(capabilities["streaming"], true);
+        assert!(capabilities["input_modalities"].as_array().unwrap().contains(&"audio".into()));
+
+        let missing = router.oneshot(request("/api/agents/nobody/capabilities")).await.unwrap();
+        assert_eq!(missing.status(), axum::http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_estimate_does_not_run_the_model() {
+        use crate::agents::{base_agent::AgentBuilder, LlmAgent};
+
+        let agent = LlmAgent::builder()
+            .name("helper")
+            .model("gemini-2.0-flash")
+            .instruction("Be brief.")
+            .build()
+            .unwrap();
+        let server = WebServer::new(ServerConfig::default()).add_agent("helper", Arc::new(agent));
+        let sessions = server.state.session_service.clone();
+        let session = sessions.get_or_create_session("helper", &"u1".to_string(), &"s1".to_string()).await.unwrap();
+        sessions
+            .append_event(&session.id, Arc::new(crate::events::Event::user_input("Earlier question", uuid::Uuid::nil())))
+            .await
+            .unwrap();
+
+        let request = Request::post("/api/agents/helper/estimate")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"message": "How long is this prompt?", "session_id": "s1", "user_id": "u1"}"#))
+            .unwrap();
+        let response = server.build_router().unwrap().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let estimate: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let estimate = &estimate["requests"][0];
+        assert_eq!(estimate["agent"], "helper");
+        // Instruction, the earlier question and the new message
+        assert_eq!(estimate["turns"].as_array().unwrap().len(), 3);
+        assert!(estimate["prompt_tokens"].as_u64().unwrap() > 0);
+
+        let session = sessions.get_session("helper", &"u1".to_string(), &"s1".to_string()).await.unwrap().unwrap();
+        assert_eq!(session.events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_returns_a_structured_response() {
+        use crate::{
+            agents::{base_agent::AgentBuilder, LlmAgent},
+            testing::MockGeminiServer,
+            tools::FunctionTool,
+        };
+
+        let mock = MockGeminiServer::start().await.unwrap();
+        mock.register_model("mock-gemini-structured").await;
+        mock.push_function_call("track", serde_json::json!({ "order": "A-1" }))
+            .push_text("Order A-1 has shipped.");
+        let tool = FunctionTool::new("track", "Track an order", |_| async { Ok(serde_json::json!("shipped")) });
+        let agent = LlmAgent::builder()
+            .name("tracker")
+            .model("mock-gemini-structured")
+            .tool(Arc::new(tool))
+            .build()
+            .unwrap();
+        let router = WebServer::new(ServerConfig::default())
+            .add_agent("tracker", Arc::new(agent))
+            .build_router()
+            .unwrap();
+
+        let request = Request::post("/api/agents/tracker/run")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"message": "Where is order A-1?"}"#))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let response = &body["response"];
+        assert_eq!(response["text"], "Order A-1 has shipped.");
+        assert_eq!(response["parts"][0]["type"], "text");
+        assert_eq!(response["tool_calls"][0]["name"], "track");
+        assert_eq!(response["tool_calls"][0]["args"]["order"], "A-1");
+        assert_eq!(response["tool_calls"][0]["response"], "shipped");
+        assert!(response["usage"]["prompt_tokens"].as_u64().unwrap() > 0, "{}", response);
+    }
+
+    #[tokio::test]
+    async fn test_redacted_originals_require_the_admin_token() {
+        use crate::events::{Event, InMemoryRedactionVault, RedactionVault};
+
+        let vault = Arc::new(InMemoryRedactionVault::new());
+        let event = Event::text_response("agent", "sk-live-123").with_redaction();
+        vault.store(&"s1".to_string(), &event).await.unwrap();
+        let uri = format!("/api/admin/sessions/s1/events/{}/original", event.id);
+        let request = |token: Option<&str>| {
+            let mut request = Request::get(uri.as_str());
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let unconfigured = WebServer::new(ServerConfig::default()).with_redaction_vault(vault.clone());
+        let response = unconfigured.build_router().unwrap().oneshot(request(Some("admin"))).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+
+        let config = ServerConfig::default().with_admin_token("admin");
+        let router = WebServer::new(config).with_redaction_vault(vault).build_router().unwrap();
+        for token in [None, Some("guess")] {
+            let response = router.clone().oneshot(request(token)).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+        }
+        let response = router.oneshot(request(Some("admin"))).await.unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let original: Event = serde_json::from_slice(&body).unwrap();
+        assert_eq!(original.get_text().as_deref(), Some("sk-live-123"));
+    }
+
+    #[tokio::test]
+    async fn test_operator_routes_require_the_admin_token() {
+        use axum::http::StatusCode;
+
+        let requests = [
+            ("GET", "/api/debug/paused", "", StatusCode::OK),
+            ("POST", "/api/debug/paused/p1", r#"{"action": "continue"}"#, StatusCode::NOT_FOUND),
+            (
+                "PUT",
+                "/api/agents/shop/traffic",
+                r#"{"stable": "shop-v1", "canary": "shop-v2", "canary_percent": 10}"#,
+                StatusCode::BAD_REQUEST,
+            ),
+            ("DELETE", "/api/agents/shop/traffic", "", StatusCode::NOT_FOUND),
+            ("GET", "/api/agents/shop/webhooks", "", StatusCode::OK),
+            ("POST", "/api/agents/shop/webhooks", r#"{"url": "https://example.com/hook"}"#, StatusCode::CREATED),
+            ("DELETE", "/api/agents/shop/webhooks/w1", "", StatusCode::NOT_FOUND),
+            ("GET", "/api/handoffs", "", StatusCode::OK),
+            ("GET", "/api/handoffs/s1", "", StatusCode::NOT_FOUND),
+            ("POST", "/api/handoffs/s1/reply", r#"{"message": "Hello"}"#, StatusCode::NOT_FOUND),
+            ("POST", "/api/handoffs/s1/return", "", StatusCode::NOT_FOUND),
+        ];
+        let request = |(method, uri, body, _): (&str, &str, &str, StatusCode), token: Option<&str>| {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json");
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            request.body(Body::from(body.to_string())).unwrap()
+        };
+
+        let unconfigured = WebServer::new(ServerConfig::default()).build_router().unwrap();
+        let router = WebServer::new(ServerConfig::default().with_admin_token("admin"))
+            .build_router()
+            .unwrap();
+        for route in requests {
+            let response = unconfigured.clone().oneshot(request(route, Some("admin"))).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{:?}", route);
+            for token in [None, Some("guess")] {
+                let response = router.clone().oneshot(request(route, token)).await.unwrap();
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{:?}", route);
+            }
+            let response = router.clone().oneshot(request(route, Some("admin"))).await.unwrap();
+            assert_eq!(response.status(), route.3, "{:?}", route);
+        }
+
+        // Reading a traffic split stays public
+        let response = router.oneshot(request(("GET", "/api/agents/shop/traffic", "", StatusCode::OK), None)).await.unwrap();
+        assert_ne!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_background_workers_run_on_the_server_task_tracker() {
+        let server = WebServer::new(ServerConfig::default());
+        server.spawn_workers();
+        // Dead-letter retries, analytics, artifact cleanup and retention
+        assert_eq!(server.state.tasks.len(), 4);
+
+        tokio::time::timeout(Duration::from_secs(5), server.drain_tasks()).await.unwrap();
+        assert!(server.state.tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_determinism_applies_to_server_ids_and_timestamps() {
+        use chrono::TimeZone;
+
+        let start = chrono::Utc.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap();
+        let server = WebServer::new(ServerConfig::default()).with_determinism(Determinism::fixed(start));
+        assert_eq!(server.state.new_id(), "00000000-0000-0000-0000-000000000001");
+
+        let router = server.build_router().unwrap();
+        let response = router.oneshot(Request::get("/health").body(Body::empty()).unwrap()).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let health: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(health["timestamp"], "2024-05-01T09:00:00Z");
+    }
+}
@@ -0,0 +1,95 @@
+//! Native TLS termination for the web server
+//!
+//! Serving HTTPS/WSS directly requires the `tls` feature (rustls); deployments
+//! behind a reverse proxy should terminate TLS there instead.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// PEM certificate chain and private key used to serve HTTPS
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+impl TlsConfig {
+    pub fn new(cert_path: impl Into<PathBuf>, key_path: impl Into<PathBuf>) -> Self {
+        Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+        }
+    }
+
+    /// Load and check the certificate and key
+    #[cfg(feature = "tls")]
+    pub(crate) async fn load(&self) -> Result<axum_server::tls_rustls::RustlsConfig> {
+        // Several providers may be linked in; ring is the one this crate enables
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        axum_server::tls_rustls::RustlsConfig::from_pem_file(&self.cert_path, &self.key_path)
+            .await
+            .map_err(|e| {
+                crate::adk_error!(
+                    ConfigError,
+                    "Failed to load TLS certificate {} / key {}: {}",
+                    self.cert_path.display(),
+                    self.key_path.display(),
+                    e
+                )
+            })
+    }
+
+    #[cfg(not(feature = "tls"))]
+    pub(crate) async fn load(&self) -> Result<std::convert::Infallible> {
+        Err(crate::adk_error!(
+            ConfigError,
+            "Serving TLS requires building with the `tls` feature"
+        ))
+    }
+}
+
+/// Serve `router` over TLS until `shutdown` is cancelled
+#[cfg(feature = "tls")]
+pub(crate) async fn serve(
+    rustls_config: axum_server::tls_rustls::RustlsConfig,
+    addr: std::net::SocketAddr,
+    router: axum::Router,
+    shutdown: tokio_util::sync::CancellationToken,
+) -> Result<()> {
+    let handle = axum_server::Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown.cancelled().await;
+            handle.graceful_shutdown(Some(super::server::SHUTDOWN_GRACE_PERIOD));
+        }
+    });
+    axum_server::bind_rustls(addr, rustls_config)
+        .handle(handle)
+        .serve(router.into_make_service())
+        .await
+        .map_err(|e| crate::adk_error!(NetworkError, "Server error: {}", e))
+}
+
+#[cfg(not(feature = "tls"))]
+pub(crate) async fn serve(
+    rustls_config: std::convert::Infallible,
+    _addr: std::net::SocketAddr,
+    _router: axum::Router,
+    _shutdown: tokio_util::sync::CancellationToken,
+) -> Result<()> {
+    match rustls_config {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_missing_certificate_is_a_config_error() {
+        let config = TlsConfig::new("/nonexistent/cert.pem", "/nonexistent/key.pem");
+        let error = config.load().await.unwrap_err();
+        assert!(matches!(error, crate::error::AdkError::ConfigError(_)));
+    }
+}
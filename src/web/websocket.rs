@@ -1,18 +1,23 @@
 //! WebSocket handler for real-time agent communication
 
 use crate::{
-    agents::{BaseAgent, InvocationContextBuilder},
-    events::Event,
-    types::{SessionState},
-    web::ServerState,
+    agents::{stamp_agent_version, BaseAgent, InvocationContextBuilder},
+    events::{Citations, Event, PublishedEvent},
+    types::{ContentPart, SessionState},
+    web::{buffer_events, handlers::non_text_parts, Priority, ServerState},
 };
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
 use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, sync::Arc};
-use tokio::sync::broadcast;
+use tokio::{sync::broadcast, time::Instant};
 use tracing::{debug, error, info, warn};
-use uuid::Uuid;
+
+/// Close code sent when a connection is reaped for inactivity (private-use range, mirrors HTTP 408)
+pub const IDLE_TIMEOUT_CLOSE_CODE: u16 = 4408;
+
+/// Close code sent when the server shuts down (RFC 6455 "going away")
+pub const GOING_AWAY_CLOSE_CODE: u16 = 1001;
 
 /// WebSocket message types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +39,12 @@ pub enum WebSocketMessage {
         timestamp: chrono::DateTime<chrono::Utc>,
         is_partial: bool,
         metadata: HashMap<String, serde_json::Value>,
+        /// Structured parts such as executable code and its results
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        parts: Vec<ContentPart>,
+        /// Grounding sources matching the `[n]` markers in `message`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        citations: Option<Citations>,
     },
     
     /// System message
@@ -94,10 +105,11 @@ impl WebSocketHandler {
         &self,
         socket: WebSocket,
         agent_name: String,
+        version: Option<String>,
         state: ServerState,
     ) {
-        let connection_id = Uuid::new_v4().to_string();
-        let session_id = Uuid::new_v4().to_string();
+        let connection_id = state.new_id();
+        let session_id = state.new_id();
         let user_id = "websocket_user".to_string();
 
         info!("New WebSocket connection: {} for agent: {}", connection_id, agent_name);
@@ -109,7 +121,7 @@ impl WebSocketHandler {
                 session_id: session_id.clone(),
                 user_id: user_id.clone(),
                 agent_name: agent_name.clone(),
-                connected_at: chrono::Utc::now(),
+                connected_at: state.now(),
             });
         }
 
@@ -129,11 +141,15 @@ impl WebSocketHandler {
         }
 
         // Get agent
-        let agent = match state.agents.get(&agent_name) {
-            Some(agent) => agent.clone(),
+        let agent = match state.agents.get(&agent_name, version.as_deref()) {
+            Some(agent) => agent,
             None => {
+                let error = match &version {
+                    Some(version) => format!("Agent '{}' version '{}' not found", agent_name, version),
+                    None => format!("Agent '{}' not found", agent_name),
+                };
                 let error_msg = WebSocketMessage::Error {
-                    error: format!("Agent '{}' not found", agent_name),
+                    error,
                     code: Some("AGENT_NOT_FOUND".to_string()),
                 };
                 let _ = sender.send(Message::Text(serde_json::to_string(&error_msg).unwrap())).await;
@@ -152,11 +168,58 @@ impl WebSocketHandler {
         let user_id_clone = user_id.clone();
         let agent_name_clone = agent_name.clone();
 
-        tokio::spawn(async move {
+        // Heartbeats keep intermediaries from dropping the connection; idle
+        // connections (no client messages) are closed with a clear close code
+        let heartbeat = state.config.heartbeat_interval();
+        let idle_timeout = state.config.idle_timeout();
+        let shutdown = state.shutdown.clone();
+
+        state.tasks.spawn(async move {
+            let heartbeat_period = heartbeat.unwrap_or(std::time::Duration::MAX);
+            let mut heartbeat_timer = tokio::time::interval_at(
+                Instant::now().checked_add(heartbeat_period).unwrap_or_else(Instant::now),
+                heartbeat_period,
+            );
+            let mut last_activity = Instant::now();
+
             loop {
+                let idle_deadline = idle_timeout.and_then(|limit| last_activity.checked_add(limit));
+
                 tokio::select! {
+                    // Close the connection when the server shuts down
+                    _ = shutdown.cancelled() => {
+                        info!("Closing WebSocket connection {} for shutdown", connection_id);
+                        let _ = sender.send(Message::Close(Some(CloseFrame {
+                            code: GOING_AWAY_CLOSE_CODE,
+                            reason: "server shutting down".into(),
+                        }))).await;
+                        break;
+                    }
+
+                    // Send a heartbeat ping
+                    _ = heartbeat_timer.tick(), if heartbeat.is_some() => {
+                        if let Err(e) = sender.send(Message::Ping(Vec::new())).await {
+                            debug!("Failed to send heartbeat, closing connection: {}", e);
+                            break;
+                        }
+                    }
+
+                    // Reap the connection after the idle timeout
+                    _ = tokio::time::sleep_until(idle_deadline.unwrap_or_else(Instant::now)), if idle_deadline.is_some() => {
+                        info!("Closing idle WebSocket connection {}", connection_id);
+                        let _ = sender.send(Message::Close(Some(CloseFrame {
+                            code: IDLE_TIMEOUT_CLOSE_CODE,
+                            reason: "idle timeout".into(),
+                        }))).await;
+                        break;
+                    }
+
                     // Handle incoming WebSocket messages
                     msg = receiver.next() => {
+                        if matches!(msg, Some(Ok(Message::Text(_) | Message::Binary(_)))) {
+                            last_activity = Instant::now();
+                        }
+
                         match msg {
                             Some(Ok(Message::Text(text))) => {
                                 debug!("Received WebSocket message: {}", text);
@@ -179,6 +242,8 @@ impl WebSocketHandler {
                                             };
                                             let _ = sender.send(Message::Text(serde_json::to_string(&error_msg).unwrap())).await;
                                         }
+                                        // The agent's reply counts as activity too
+                                        last_activity = Instant::now();
                                     }
                                     Err(e) => {
                                         warn!("Failed to parse WebSocket message: {}", e);
@@ -257,10 +322,23 @@ impl WebSocketHandler {
         agent_name: &str,
     ) -> crate::error::Result<()> {
         match message {
-            WebSocketMessage::UserMessage { message, session_id: msg_session_id, user_id: msg_user_id, metadata } => {
+            WebSocketMessage::UserMessage { message, session_id: msg_session_id, user_id: msg_user_id, metadata: _ } => {
                 let effective_session_id = msg_session_id.unwrap_or_else(|| session_id.to_string());
                 let effective_user_id = msg_user_id.unwrap_or_else(|| user_id.to_string());
 
+                let _permit = match state.scheduler.acquire(agent_name, Priority::Interactive).await {
+                    Ok(permit) => permit,
+                    Err(overloaded) => {
+                        let error_msg = WebSocketMessage::Error {
+                            error: format!("Server overloaded, retry in {}s", overloaded.retry_after.as_secs()),
+                            code: Some("OVERLOADED".to_string()),
+                        };
+                        sender.send(Message::Text(serde_json::to_string(&error_msg)?)).await
+                            .map_err(|e| crate::adk_error!(NetworkError, "Failed to send error: {}", e))?;
+                        return Ok(());
+                    }
+                };
+
                 // Create invocation context
                 let context = InvocationContextBuilder::new()
                     .session_id(effective_session_id.clone())
@@ -272,23 +350,55 @@ impl WebSocketHandler {
                     .build()?;
 
                 // Add user message to session
-                let user_event = Event::user_input(&message, context.invocation_id);
-                state.session_service.append_event(&effective_session_id, user_event).await?;
+                state.session_service
+                    .get_or_create_session(agent_name, &effective_user_id, &effective_session_id)
+                    .await?;
+                let mut user_event = Event::user_input(&message, context.invocation_id);
+                stamp_agent_version(agent.as_ref(), &mut user_event);
+                let user_event = Arc::new(user_event);
+                state.session_service.append_event(&effective_session_id, user_event.clone()).await?;
+                state.event_bus.publish(PublishedEvent {
+                    app_name: agent_name.to_string(),
+                    user_id: effective_user_id.clone(),
+                    session_id: effective_session_id.clone(),
+                    event: user_event,
+                });
 
-                // Run agent and stream responses
-                let mut event_stream = agent.run_async(context).await?;
+                // Run agent and stream responses, stopping when the server shuts down
+                let mut event_stream = Box::pin(buffer_events(
+                    agent
+                        .run_async(context)
+                        .await?
+                        .take_until(state.shutdown.clone().cancelled_owned()),
+                    state.config.stream_buffer_capacity,
+                    &state.tasks,
+                ));
                 
                 while let Some(event_result) = event_stream.next().await {
                     match event_result {
-                        Ok(event) => {
-                            if let Some(text) = event.get_text() {
+                        Ok(mut event) => {
+                            stamp_agent_version(agent.as_ref(), Arc::make_mut(&mut event));
+                            if !event.is_partial {
+                                state.session_service.append_event(&effective_session_id, event.clone()).await?;
+                            }
+                            state.event_bus.publish(PublishedEvent {
+                                app_name: agent_name.to_string(),
+                                user_id: effective_user_id.clone(),
+                                session_id: effective_session_id.clone(),
+                                event: event.clone(),
+                            });
+                            let text = event.get_cited_text().unwrap_or_default();
+                            let parts = event.content.as_ref().map(non_text_parts).unwrap_or_default();
+                            if !text.is_empty() || !parts.is_empty() {
                                 let response_msg = WebSocketMessage::AgentResponse {
                                     message: text,
+                                    parts,
+                                    citations: event.citations.clone(),
                                     session_id: effective_session_id.clone(),
-                                    author: event.author,
+                                    author: event.author.clone(),
                                     timestamp: event.timestamp,
                                     is_partial: event.is_partial,
-                                    metadata: event.metadata,
+                                    metadata: event.metadata.clone(),
                                 };
 
                                 sender.send(Message::Text(serde_json::to_string(&response_msg)?)).await
@@ -310,7 +420,7 @@ impl WebSocketHandler {
             
             WebSocketMessage::Ping { timestamp: _ } => {
                 let pong_msg = WebSocketMessage::Pong {
-                    timestamp: chrono::Utc::now(),
+                    timestamp: state.now(),
                 };
                 sender.send(Message::Text(serde_json::to_string(&pong_msg)?)).await
                     .map_err(|e| crate::adk_error!(NetworkError, "Failed to send pong: {}", e))?;
@@ -348,3 +458,137 @@ impl Default for WebSocketHandler {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agents::InvocationContext,
+        agents::base_agent::EventStream,
+        types::{AgentId, Metadata},
+        web::{handlers, ServerConfig},
+    };
+    use async_trait::async_trait;
+    use axum::{routing::get, Router};
+    use std::time::Duration;
+    use tokio_tungstenite::tungstenite;
+
+    /// Streams a partial chunk followed by the complete answer
+    struct EchoAgent {
+        id: AgentId,
+        metadata: Metadata,
+    }
+
+    #[async_trait]
+    impl BaseAgent for EchoAgent {
+        fn id(&self) -> &AgentId {
+            &self.id
+        }
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn description(&self) -> &str {
+            ""
+        }
+        fn metadata(&self) -> &Metadata {
+            &self.metadata
+        }
+        fn parent(&self) -> Option<&dyn BaseAgent> {
+            None
+        }
+        fn sub_agents(&self) -> &[Box<dyn BaseAgent>] {
+            &[]
+        }
+        async fn run_async(&self, _ctx: InvocationContext) -> crate::error::Result<EventStream> {
+            let mut partial = Event::text_response("echo", "Hel");
+            partial.is_partial = true;
+            let complete = Event::text_response("echo", "Hello");
+            Ok(Box::pin(futures::stream::iter(vec![Ok(Arc::new(partial)), Ok(Arc::new(complete))])))
+        }
+        async fn run_live(&self, ctx: InvocationContext) -> crate::error::Result<EventStream> {
+            self.run_async(ctx).await
+        }
+    }
+
+    /// Serve the WebSocket endpoint with an `echo` agent, returning its URL
+    async fn serve(state: ServerState) -> String {
+        state.agents.register("echo", Arc::new(EchoAgent { id: "echo".to_string(), metadata: Metadata::new() }));
+        let router = Router::new()
+            .route("/ws/:agent_name", get(handlers::websocket_handler))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let url = format!("ws://{}/ws/echo", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_user_message_creates_the_session_and_persists_complete_events() {
+        let state = ServerState::new(ServerConfig::default());
+        let sessions = state.session_service.clone();
+        let url = serve(state).await;
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        let message = WebSocketMessage::UserMessage {
+            message: "Hi".to_string(),
+            session_id: Some("s1".to_string()),
+            user_id: Some("alice".to_string()),
+            metadata: None,
+        };
+        socket
+            .send(tungstenite::Message::Text(serde_json::to_string(&message).unwrap()))
+            .await
+            .unwrap();
+        // Partial chunks are streamed but only complete events are stored
+        let mut replies = Vec::new();
+        while replies.len() < 2 {
+            if let tungstenite::Message::Text(text) = socket.next().await.unwrap().unwrap() {
+                if let WebSocketMessage::AgentResponse { message, .. } = serde_json::from_str(&text).unwrap() {
+                    replies.push(message);
+                }
+            }
+        }
+        assert_eq!(replies, vec!["Hel", "Hello"]);
+
+        let session = sessions
+            .get_session("echo", &"alice".to_string(), &"s1".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        let texts: Vec<_> = session.events.iter().map(|event| event.get_text()).collect();
+        assert_eq!(texts, vec![Some("Hi".to_string()), Some("Hello".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_ping_is_sent_within_the_interval() {
+        let url = serve(ServerState::new(ServerConfig::default().with_heartbeat_interval(1).with_idle_timeout(0))).await;
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        let ping = tokio::time::timeout(Duration::from_secs(3), async {
+            loop {
+                match socket.next().await.unwrap().unwrap() {
+                    tungstenite::Message::Ping(_) => break,
+                    _ => continue,
+                }
+            }
+        });
+        ping.await.expect("no heartbeat ping within the interval");
+    }
+
+    #[tokio::test]
+    async fn test_idle_connection_is_closed_with_the_idle_timeout_code() {
+        let url = serve(ServerState::new(ServerConfig::default().with_heartbeat_interval(0).with_idle_timeout(1))).await;
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        let close = tokio::time::timeout(Duration::from_secs(3), async {
+            loop {
+                if let tungstenite::Message::Close(frame) = socket.next().await.unwrap().unwrap() {
+                    break frame;
+                }
+            }
+        });
+        let frame = close.await.expect("idle connection was not closed").unwrap();
+        assert_eq!(u16::from(frame.code), IDLE_TIMEOUT_CLOSE_CODE);
+        assert_eq!(frame.reason, "idle timeout");
+    }
+}